@@ -1,15 +1,19 @@
 use crate::auth;
 use crate::db;
 use crate::encryption;
+use crate::mdns;
 use crate::models::*;
 use crate::models::SurveyQuestion;
 use crate::server;
+use crate::tls;
 use once_cell::sync::OnceCell;
 use std::sync::atomic::{AtomicBool, Ordering};
 
 // HTTP 서버 상태 관리
 static SERVER_RUNNING: AtomicBool = AtomicBool::new(false);
 static SERVER_PORT: OnceCell<u16> = OnceCell::new();
+/// mDNS로 광고 중인 호스트 이름 (광고하지 않으면 None)
+static MDNS_HOSTNAME: std::sync::Mutex<Option<String>> = std::sync::Mutex::new(None);
 
 // ============ 인증 명령어 ============
 
@@ -55,6 +59,7 @@ pub struct ClinicSettingsInput {
     pub clinic_phone: Option<String>,
     pub doctor_name: Option<String>,
     pub license_number: Option<String>,
+    pub clinic_logo: Option<String>,
     pub created_at: Option<String>,
     #[allow(dead_code)]
     pub updated_at: Option<String>,
@@ -77,6 +82,7 @@ pub fn save_clinic_settings(settings: ClinicSettingsInput) -> Result<(), String>
         clinic_phone: settings.clinic_phone,
         doctor_name: settings.doctor_name,
         license_number: settings.license_number,
+        clinic_logo: settings.clinic_logo,
         created_at,
         updated_at: now,
     };
@@ -93,8 +99,8 @@ pub fn get_clinic_settings() -> Result<Option<ClinicSettings>, String> {
 // ============ 환자 관리 명령어 ============
 
 #[tauri::command]
-pub fn create_patient(patient: Patient) -> Result<(), String> {
-    db::create_patient(&patient).map_err(|e| e.to_string())
+pub fn create_patient(patient: Patient, allow_duplicate: Option<bool>) -> Result<(), String> {
+    db::create_patient(&patient, allow_duplicate.unwrap_or(false)).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -102,9 +108,64 @@ pub fn get_patient(id: String) -> Result<Option<Patient>, String> {
     db::get_patient(&id).map_err(|e| e.to_string())
 }
 
+/// 환자 중요 정보 목록 (심각도 → 종류 → 등록순)
 #[tauri::command]
-pub fn list_patients(search: Option<String>) -> Result<Vec<Patient>, String> {
-    db::list_patients(search.as_deref()).map_err(|e| e.to_string())
+pub fn list_patient_alerts(patient_id: String) -> Result<Vec<crate::models::PatientAlert>, String> {
+    db::list_patient_alerts(&patient_id).map_err(|e| e.to_string())
+}
+
+/// 환자 중요 정보 저장 (같은 ID면 수정)
+#[tauri::command]
+pub fn save_patient_alert(alert: crate::models::PatientAlert) -> Result<crate::models::PatientAlert, String> {
+    db::save_patient_alert(&alert)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "환자를 찾을 수 없습니다".to_string())
+}
+
+#[tauri::command]
+pub fn delete_patient_alert(id: String) -> Result<bool, String> {
+    db::delete_patient_alert(&id).map_err(|e| e.to_string())
+}
+
+/// 환자 요약 PDF를 다운로드 폴더에 저장 (반환: 저장한 파일 경로)
+#[tauri::command]
+pub fn save_patient_pdf(patient_id: String) -> Result<String, String> {
+    let pdf = db::generate_patient_pdf(&patient_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "환자를 찾을 수 없습니다".to_string())?;
+    let dir = dirs::download_dir().ok_or_else(|| "다운로드 폴더를 찾을 수 없습니다".to_string())?;
+    let name = db::get_patient(&patient_id)
+        .ok()
+        .flatten()
+        .map(|p| p.name.chars().filter(|c| !matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|')).collect())
+        .unwrap_or_else(|| patient_id.clone());
+    let path = dir.join(format!("환자요약_{}_{}.pdf", name, chrono::Local::now().format("%Y%m%d%H%M%S")));
+    std::fs::write(&path, pdf).map_err(|e| e.to_string())?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub fn list_patients(
+    search: Option<String>,
+    tags: Option<Vec<String>>,
+    match_any: Option<bool>,
+    include_stats: Option<bool>,
+) -> Result<Vec<crate::models::PatientListItem>, String> {
+    let match_all = !match_any.unwrap_or(false);
+    db::list_patient_items(search.as_deref(), &tags.unwrap_or_default(), match_all, include_stats.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+/// 태그가 달린 환자 목록
+#[tauri::command]
+pub fn search_patients_by_tag(tag: String) -> Result<Vec<Patient>, String> {
+    db::search_patients_by_tag(&tag).map_err(|e| e.to_string())
+}
+
+/// 환자 태그 목록
+#[tauri::command]
+pub fn list_patient_tags() -> Result<Vec<String>, String> {
+    db::list_patient_tags().map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -138,6 +199,12 @@ pub fn list_all_prescriptions() -> Result<Vec<Prescription>, String> {
     db::list_all_prescriptions().map_err(|e| e.to_string())
 }
 
+/// 특정 배치 번호의 약재를 쓴 처방 목록 (약재 회수 추적)
+#[tauri::command]
+pub fn list_prescriptions_by_herb_batch(batch_number: String) -> Result<Vec<Prescription>, String> {
+    db::list_prescriptions_by_herb_batch(&batch_number).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub fn update_prescription(prescription: Prescription) -> Result<(), String> {
     db::update_prescription(&prescription).map_err(|e| e.to_string())
@@ -148,6 +215,11 @@ pub fn soft_delete_prescription(id: String) -> Result<(), String> {
     db::soft_delete_prescription(&id).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub fn delete_prescription(id: String, cascade: Option<bool>) -> Result<(), String> {
+    db::delete_prescription(&id, cascade.unwrap_or(false)).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub fn clear_all_prescriptions() -> Result<(), String> {
     db::clear_all_prescriptions().map_err(|e| e.to_string())
@@ -155,9 +227,10 @@ pub fn clear_all_prescriptions() -> Result<(), String> {
 
 // ============ 차팅 관리 명령어 ============
 
+/// 차팅 기록 생성 (`expand_macros`가 false가 아니면 전역 약어 확장)
 #[tauri::command]
-pub fn create_chart_record(record: ChartRecord) -> Result<(), String> {
-    db::create_chart_record(&record).map_err(|e| e.to_string())
+pub fn create_chart_record(record: ChartRecord, expand_macros: Option<bool>) -> Result<(), String> {
+    db::create_chart_record(&record, chart_macro_expansion(expand_macros)).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -165,6 +238,114 @@ pub fn get_chart_records_by_patient(patient_id: String) -> Result<Vec<ChartRecor
     db::get_chart_records_by_patient(&patient_id).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub fn get_chart_record(id: String) -> Result<Option<ChartRecord>, String> {
+    db::get_chart_record(&id).map_err(|e| e.to_string())
+}
+
+/// 차팅 기록 수정 (반환: 약어가 확장된 기록)
+#[tauri::command]
+pub fn update_chart_record(record: ChartRecord, expand_macros: Option<bool>) -> Result<ChartRecord, String> {
+    db::update_chart_record(&record, None, chart_macro_expansion(expand_macros)).map_err(|e| e.to_string())
+}
+
+/// 데스크톱 앱은 직원 계정이 없으므로 전역 약어만 사용
+fn chart_macro_expansion(expand_macros: Option<bool>) -> db::MacroExpansion<'static> {
+    if expand_macros.unwrap_or(true) {
+        db::MacroExpansion::Global
+    } else {
+        db::MacroExpansion::Off
+    }
+}
+
+#[tauri::command]
+pub fn delete_chart_record(id: String) -> Result<(), String> {
+    db::delete_chart_record(&id, None).map_err(|e| e.to_string())
+}
+
+// ============ 약어(텍스트 매크로) 명령어 ============
+
+/// 약어 목록 (전역, `owner_id`를 주면 해당 직원 약어 포함)
+#[tauri::command]
+pub fn list_text_macros(owner_id: Option<String>) -> Result<Vec<TextMacro>, String> {
+    db::list_text_macros(owner_id.as_deref()).map_err(|e| e.to_string())
+}
+
+/// 약어 저장 (`id`가 없으면 새로 추가)
+#[tauri::command]
+pub fn save_text_macro(
+    id: Option<String>,
+    abbreviation: String,
+    expansion: String,
+    owner_id: Option<String>,
+) -> Result<TextMacro, String> {
+    let text_macro = match id {
+        Some(id) => {
+            let existing = db::get_text_macro(&id)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| "약어를 찾을 수 없습니다".to_string())?;
+            TextMacro { abbreviation, expansion, ..existing }
+        }
+        None => TextMacro::new(abbreviation, expansion, owner_id),
+    };
+    db::save_text_macro(&text_macro).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_text_macro(id: String) -> Result<(), String> {
+    db::delete_text_macro(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn sign_document(
+    document_type: String,
+    document_id: String,
+    signed_by: Option<String>,
+    chain: Option<bool>,
+) -> Result<crate::models::DocumentSignature, String> {
+    db::sign_document(&document_type, &document_id, signed_by.as_deref(), chain.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn verify_document_integrity(id: String) -> Result<crate::models::DocumentIntegrity, String> {
+    db::verify_document_integrity(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_prescription_history(patient_id: String) -> Result<Vec<db::PrescriptionHistoryRow>, String> {
+    db::get_prescription_history(&patient_id).map_err(|e| e.to_string())
+}
+
+/// 환자 이벤트 타임라인 (방문/처방/설문/연락/문서)
+#[tauri::command]
+pub fn get_patient_events(
+    patient_id: String,
+    event_types: Option<Vec<String>>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<Vec<PatientEvent>, String> {
+    db::get_patient_events(
+        &patient_id,
+        &event_types.unwrap_or_default(),
+        limit.unwrap_or(50).clamp(1, 500),
+        offset.unwrap_or(0).max(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// 환자의 설문 반복 응답 추이 (척도 문항, 제출 시각 순)
+#[tauri::command]
+pub fn get_patient_survey_series(patient_id: String, template_id: String) -> Result<Option<PatientSurveySeries>, String> {
+    db::get_patient_survey_series(&patient_id, &template_id).map_err(|e| e.to_string())
+}
+
+/// 처방 전후 설문 점수 변화와 복약 순응도 상관
+#[tauri::command]
+pub fn get_treatment_outcome_correlation(patient_id: String) -> Result<TreatmentOutcomeCorrelation, String> {
+    db::get_treatment_outcome_correlation(&patient_id).map_err(|e| e.to_string())
+}
+
 // ============ 초진차트 관리 명령어 ============
 
 use crate::models::{InitialChart, ProgressNote};
@@ -238,6 +419,78 @@ pub fn export_all_data() -> Result<String, String> {
     db::export_all_data().map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub fn export_all_data_encrypted(passphrase: String) -> Result<Vec<u8>, String> {
+    db::export_all_data_encrypted(&passphrase).map_err(|e| e.to_string())
+}
+
+/// 전체 데이터 ZIP 내보내기 (테이블별 CSV)
+#[tauri::command]
+pub fn export_all_data_zip() -> Result<Vec<u8>, String> {
+    db::export_all_data_zip().map_err(|e| e.to_string())
+}
+
+/// 설문 응답 일괄 내보내기 작업 등록 (진행률은 `get_export_job`으로 확인)
+#[tauri::command]
+pub fn start_survey_export(request: crate::models::SurveyExportRequest) -> Result<crate::jobs::JobInfo, String> {
+    crate::jobs::start_survey_export(request).map_err(|e| e.to_string())
+}
+
+/// 내보내기 작업 목록
+#[tauri::command]
+pub fn list_export_jobs() -> Vec<crate::jobs::JobInfo> {
+    crate::jobs::list_jobs()
+}
+
+/// 내보내기 작업 상태/진행률
+#[tauri::command]
+pub fn get_export_job(id: String) -> Option<crate::jobs::JobInfo> {
+    crate::jobs::get_job(&id)
+}
+
+/// 내보내기 작업 취소
+#[tauri::command]
+pub fn cancel_export_job(id: String) -> Result<crate::jobs::JobInfo, String> {
+    crate::jobs::cancel_job(&id).ok_or_else(|| "작업을 찾을 수 없습니다".to_string())
+}
+
+/// 완료된 내보내기 파일 내용
+#[tauri::command]
+pub fn get_export_job_file(id: String) -> Result<Vec<u8>, String> {
+    crate::jobs::read_job_file(&id)
+        .map_err(|e| e.to_string())?
+        .map(|file| file.bytes)
+        .ok_or_else(|| "내려받을 파일이 없습니다".to_string())
+}
+
+/// 전체 백업(JSON) 복원 (`conflict_strategy`: skip, overwrite, error, replace)
+#[tauri::command]
+pub fn import_json_export(json: String, conflict_strategy: ConflictStrategy) -> Result<ImportSummary, String> {
+    db::import_from_json_export(&json, conflict_strategy).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn import_encrypted(data: Vec<u8>, passphrase: String) -> Result<usize, String> {
+    db::import_encrypted(&data, &passphrase).map_err(|e| e.to_string())
+}
+
+// ============ 내원 경로 명령어 ============
+
+#[tauri::command]
+pub fn get_referral_breakdown(from: Option<String>, to: Option<String>) -> Result<Vec<crate::models::ReferralCount>, String> {
+    db::get_referral_breakdown(from.as_deref(), to.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_referral_sources() -> Result<Vec<String>, String> {
+    db::get_referral_sources().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_referral_sources(sources: Vec<String>) -> Result<Vec<String>, String> {
+    db::set_referral_sources(&sources).map_err(|e| e.to_string())
+}
+
 // ============ 초기화 명령어 ============
 
 #[tauri::command]
@@ -340,6 +593,13 @@ pub fn initialize_offline(user_id: String) -> Result<(), String> {
     Ok(())
 }
 
+/// 응답 없이 끝난 오래된 설문 세션 삭제 (기본 90일, 반환: 삭제 건수)
+#[tauri::command]
+pub fn cleanup_orphaned_survey_sessions(older_than_days: Option<i64>) -> Result<usize, String> {
+    db::cleanup_orphaned_survey_sessions(older_than_days.unwrap_or(db::ORPHANED_SESSION_RETENTION_DAYS))
+        .map_err(|e| e.to_string())
+}
+
 // ============ 직원 비밀번호 관리 명령어 ============
 
 #[tauri::command]
@@ -359,6 +619,10 @@ pub async fn start_http_server(
     port: Option<u16>,
     plan_type: Option<String>,
     survey_external: Option<bool>,
+    debug_endpoints: Option<bool>,
+    session_abuse_threshold: Option<i32>,
+    metrics_endpoint: Option<bool>,
+    access_log_level: Option<String>,
 ) -> Result<String, String> {
     if SERVER_RUNNING.load(Ordering::SeqCst) {
         return Err("서버가 이미 실행 중입니다".to_string());
@@ -373,11 +637,34 @@ pub async fn start_http_server(
     // 플랜 정보
     let plan = plan_type.unwrap_or_else(|| "free".to_string());
     let external_enabled = survey_external.unwrap_or(false);
+    let debug_enabled = debug_endpoints.unwrap_or(false);
+    let metrics_enabled = metrics_endpoint.unwrap_or(true);
+    let mdns_enabled = db::get_server_mdns_advertise().unwrap_or(true);
+    // "off", "error", "warn", "info", "debug", "trace" (기본: info)
+    let access_log_level = match access_log_level {
+        Some(level) => level
+            .parse::<log::LevelFilter>()
+            .map_err(|_| format!("알 수 없는 로그 레벨입니다: {}", level))?,
+        None => log::LevelFilter::Info,
+    };
+    let mut abuse_threshold = server::SessionAbuseThreshold::default();
+    if let Some(max_sessions) = session_abuse_threshold {
+        abuse_threshold.max_sessions = max_sessions;
+    }
     log::info!("HTTP 서버 플랜: {}, 온라인 설문: {}", plan, external_enabled);
+    let cors_origins = db::get_cors_allowed_origins().unwrap_or_default();
+    let tls = db::get_server_tls_settings().unwrap_or_default();
+    let tls_cert_path = tls.cert_path.map(std::path::PathBuf::from);
+    let tls_key_path = tls.key_path.map(std::path::PathBuf::from);
+    // 인증서 오류는 서버 시작 전에 바로 알림
+    let tls_config = tls::load_config(tls.enabled, tls_cert_path.as_deref(), tls_key_path.as_deref())
+        .await
+        .map_err(|e| e.to_string())?;
+    let scheme = if tls_config.is_some() { "https" } else { "http" };
 
     // 로컬 IP 주소 가져오기
-    let local_ip = get_local_ip().unwrap_or_else(|| "localhost".to_string());
-    let url = format!("http://{}:{}", local_ip, port);
+    let local_ip = server::detect_local_ip().unwrap_or_else(|| "localhost".to_string());
+    let url = format!("{}://{}:{}", scheme, local_ip, port);
 
     // 먼저 바인딩 테스트
     let addr = std::net::SocketAddr::from(([0, 0, 0, 0], port));
@@ -392,16 +679,16 @@ pub async fn start_http_server(
         log::info!("HTTP 서버 태스크 시작됨");
 
         let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-            let state = server::AppState::with_plan(plan.clone(), external_enabled);
+            let state = server::AppState::with_plan(plan.clone(), external_enabled)
+                .with_debug_endpoints(debug_enabled)
+                .with_session_abuse_threshold(abuse_threshold)
+                .with_metrics_enabled(metrics_enabled)
+                .with_access_log_level(access_log_level)
+                .with_cors_origins(cors_origins)
+                .with_tls(tls.enabled, tls_cert_path, tls_key_path);
             log::info!("AppState 생성 완료 (plan: {}, survey_external: {})", plan, external_enabled);
 
-            let cors = tower_http::cors::CorsLayer::new()
-                .allow_origin(tower_http::cors::Any)
-                .allow_methods(tower_http::cors::Any)
-                .allow_headers(tower_http::cors::Any);
-            log::info!("CORS 설정 완료");
-
-            let app = server::create_router(state).layer(cors);
+            let app = server::create_router(state);
             log::info!("Router 생성 완료");
             app
         }));
@@ -409,9 +696,33 @@ pub async fn start_http_server(
         match result {
             Ok(app) => {
                 log::info!("서버 시작 중... axum::serve 호출");
-                if let Err(e) = axum::serve(listener, app).await {
+                let sweep = server::spawn_session_expiry_sweep();
+                let advertisement = if mdns_enabled {
+                    match mdns::advertise(port) {
+                        Ok(advertisement) => {
+                            if let Ok(mut hostname) = MDNS_HOSTNAME.lock() {
+                                *hostname = Some(advertisement.hostname().to_string());
+                            }
+                            Some(advertisement)
+                        }
+                        Err(e) => {
+                            log::warn!("{}", e);
+                            None
+                        }
+                    }
+                } else {
+                    None
+                };
+                if let Err(e) = server::serve(listener, app, tls_config).await {
                     log::error!("HTTP 서버 오류: {}", e);
                 }
+                if let Some(advertisement) = advertisement {
+                    advertisement.shutdown();
+                }
+                if let Ok(mut hostname) = MDNS_HOSTNAME.lock() {
+                    *hostname = None;
+                }
+                server::stop_session_expiry_sweep(sweep);
             }
             Err(e) => {
                 log::error!("HTTP 서버 초기화 중 패닉 발생: {:?}", e);
@@ -438,19 +749,30 @@ pub fn stop_http_server() -> Result<(), String> {
 pub fn get_server_status() -> Result<ServerStatus, String> {
     let running = SERVER_RUNNING.load(Ordering::SeqCst);
     let port = SERVER_PORT.get().copied();
-    let local_ip = get_local_ip();
+    let local_ip = server::detect_local_ip();
 
     let url = if running {
-        port.map(|p| format!("http://{}:{}", local_ip.as_deref().unwrap_or("localhost"), p))
+        port.map(|p| format!("{}://{}:{}", server::url_scheme(), local_ip.as_deref().unwrap_or("localhost"), p))
+    } else {
+        None
+    };
+    let mdns_hostname = if running {
+        MDNS_HOSTNAME.lock().ok().and_then(|h| h.clone())
     } else {
         None
     };
+    let mdns_url = mdns_hostname
+        .as_deref()
+        .zip(port)
+        .map(|(host, p)| format!("{}://{}:{}", server::url_scheme(), host, p));
 
     Ok(ServerStatus {
         running,
         port,
         local_ip,
         url,
+        mdns_hostname,
+        mdns_url,
     })
 }
 
@@ -460,14 +782,10 @@ pub struct ServerStatus {
     pub port: Option<u16>,
     pub local_ip: Option<String>,
     pub url: Option<String>,
-}
-
-/// 로컬 IP 주소 가져오기
-fn get_local_ip() -> Option<String> {
-    use std::net::UdpSocket;
-    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
-    socket.connect("8.8.8.8:80").ok()?;
-    socket.local_addr().ok().map(|addr| addr.ip().to_string())
+    /// mDNS 광고 호스트 이름 (예: gosibang.local)
+    pub mdns_hostname: Option<String>,
+    /// 태블릿 안내용 주소 (예: http://gosibang.local:8787)
+    pub mdns_url: Option<String>,
 }
 
 /// HTTP 서버 자동 시작 설정 조회
@@ -476,12 +794,72 @@ pub fn get_server_autostart() -> Result<bool, String> {
     db::get_server_autostart().map_err(|e| e.to_string())
 }
 
+/// mDNS 광고 설정 조회
+#[tauri::command]
+pub fn get_server_mdns_advertise() -> Result<bool, String> {
+    db::get_server_mdns_advertise().map_err(|e| e.to_string())
+}
+
+/// mDNS 광고 설정 저장 (다음 서버 시작부터 적용)
+#[tauri::command]
+pub fn set_server_mdns_advertise(enabled: bool) -> Result<(), String> {
+    db::set_server_mdns_advertise(enabled).map_err(|e| e.to_string())
+}
+
 /// HTTP 서버 자동 시작 설정 저장
 #[tauri::command]
 pub fn set_server_autostart(enabled: bool) -> Result<(), String> {
     db::set_server_autostart(enabled).map_err(|e| e.to_string())
 }
 
+/// 설문 링크 유효 시간(시간) 조회
+#[tauri::command]
+pub fn get_survey_ttl_hours() -> Result<i64, String> {
+    db::get_survey_ttl_hours().map_err(|e| e.to_string())
+}
+
+/// 설문 링크 유효 시간(시간) 저장
+#[tauri::command]
+pub fn set_survey_ttl_hours(hours: i64) -> Result<(), String> {
+    db::set_survey_ttl_hours(hours).map_err(|e| e.to_string())
+}
+
+/// 키오스크 무입력 대기 시간(분) 조회
+#[tauri::command]
+pub fn get_kiosk_idle_minutes() -> Result<i64, String> {
+    db::get_kiosk_idle_minutes().map_err(|e| e.to_string())
+}
+
+/// 키오스크 무입력 대기 시간(분) 저장
+#[tauri::command]
+pub fn set_kiosk_idle_minutes(minutes: i64) -> Result<(), String> {
+    db::set_kiosk_idle_minutes(minutes).map_err(|e| e.to_string())
+}
+
+/// CORS 추가 허용 출처 조회
+#[tauri::command]
+pub fn get_cors_allowed_origins() -> Result<Vec<String>, String> {
+    db::get_cors_allowed_origins().map_err(|e| e.to_string())
+}
+
+/// CORS 추가 허용 출처 저장 (서버 재시작 후 적용)
+#[tauri::command]
+pub fn set_cors_allowed_origins(origins: Vec<String>) -> Result<(), String> {
+    db::set_cors_allowed_origins(&origins).map_err(|e| e.to_string())
+}
+
+/// 원내 서버 HTTPS 설정 조회
+#[tauri::command]
+pub fn get_server_tls_settings() -> Result<ServerTlsSettings, String> {
+    db::get_server_tls_settings().map_err(|e| e.to_string())
+}
+
+/// 원내 서버 HTTPS 설정 저장 (서버 재시작 후 적용)
+#[tauri::command]
+pub fn set_server_tls_settings(settings: ServerTlsSettings) -> Result<(), String> {
+    db::set_server_tls_settings(&settings).map_err(|e| e.to_string())
+}
+
 // ============ 설문 템플릿 관리 명령어 ============
 
 /// 설문 템플릿 입력 구조체
@@ -493,6 +871,13 @@ pub struct SurveyTemplateInput {
     pub questions: Vec<SurveyQuestion>,
     pub display_mode: Option<String>,
     pub is_active: Option<bool>,
+    pub require_contact: Option<bool>,
+    pub anonymous: Option<bool>,
+    pub redirect_url: Option<String>,
+    pub category: Option<String>,
+    pub randomize_questions: Option<bool>,
+    pub randomize_options: Option<bool>,
+    pub pin_required_questions: Option<bool>,
 }
 
 /// 설문 템플릿 목록 조회
@@ -501,6 +886,12 @@ pub fn list_survey_templates() -> Result<Vec<db::SurveyTemplateDb>, String> {
     db::list_survey_templates().map_err(|e| e.to_string())
 }
 
+/// 설문 템플릿 분류 목록
+#[tauri::command]
+pub fn list_survey_template_categories() -> Result<Vec<String>, String> {
+    db::list_survey_template_categories().map_err(|e| e.to_string())
+}
+
 /// 설문 템플릿 단일 조회
 #[tauri::command]
 pub fn get_survey_template(id: String) -> Result<Option<db::SurveyTemplateDb>, String> {
@@ -519,6 +910,13 @@ pub fn save_survey_template(template: SurveyTemplateInput) -> Result<String, Str
         questions: template.questions,
         display_mode: template.display_mode,
         is_active: template.is_active.unwrap_or(true),
+        require_contact: template.require_contact.unwrap_or(false),
+        anonymous: template.anonymous.unwrap_or(false),
+        redirect_url: template.redirect_url,
+        category: template.category,
+        randomize_questions: template.randomize_questions.unwrap_or(false),
+        randomize_options: template.randomize_options.unwrap_or(false),
+        pin_required_questions: template.pin_required_questions.unwrap_or(false),
     };
 
     db::save_survey_template(&template_db).map_err(|e| e.to_string())?;
@@ -526,10 +924,67 @@ pub fn save_survey_template(template: SurveyTemplateInput) -> Result<String, Str
     Ok(id)
 }
 
-/// 설문 템플릿 삭제
+/// 설문 문항 순서 변경
+#[tauri::command]
+pub fn reorder_survey_template(id: String, question_ids: Vec<String>) -> Result<Option<db::SurveyTemplateDb>, String> {
+    db::reorder_survey_template(&id, &question_ids).map_err(|e| e.to_string())
+}
+
+/// 설문 템플릿 삭제 (기본 제공 템플릿은 `force` 필요)
+#[tauri::command]
+pub fn delete_survey_template(id: String, force: Option<bool>) -> Result<(), String> {
+    db::delete_survey_template(&id, force.unwrap_or(false)).map_err(|e| e.to_string())
+}
+
+/// 설문 템플릿 복제
+#[tauri::command]
+pub fn duplicate_survey_template(id: String) -> Result<db::SurveyTemplateDb, String> {
+    db::duplicate_survey_template(&id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "템플릿을 찾을 수 없습니다".to_string())
+}
+
+/// 설문 템플릿 내보내기 (JSON 문자열)
+#[tauri::command]
+pub fn export_survey_template(id: String) -> Result<String, String> {
+    let export = db::export_survey_template(&id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "템플릿을 찾을 수 없습니다".to_string())?;
+    serde_json::to_string_pretty(&export).map_err(|e| e.to_string())
+}
+
+/// 설문 템플릿 가져오기 (JSON 문자열)
+#[tauri::command]
+pub fn import_survey_template(json: String) -> Result<db::SurveyTemplateDb, String> {
+    let value: serde_json::Value = serde_json::from_str(&json)
+        .map_err(|e| format!("템플릿 파일을 읽을 수 없습니다: {}", e))?;
+    let data = db::parse_survey_template_import(value).map_err(|e| e.to_string())?;
+    db::import_survey_template(data).map_err(|e| e.to_string())
+}
+
+/// 후속 설문 규칙 목록
 #[tauri::command]
-pub fn delete_survey_template(id: String) -> Result<(), String> {
-    db::delete_survey_template(&id).map_err(|e| e.to_string())
+pub fn list_follow_up_rules() -> Result<Vec<crate::models::SurveyFollowUpRule>, String> {
+    db::list_follow_up_rules().map_err(|e| e.to_string())
+}
+
+/// 후속 설문 규칙 저장 (id 미지정 시 생성)
+#[tauri::command]
+pub fn save_follow_up_rule(mut rule: crate::models::SurveyFollowUpRule) -> Result<String, String> {
+    if rule.id.is_empty() {
+        rule.id = uuid::Uuid::new_v4().to_string();
+    }
+    if rule.created_at.is_empty() {
+        rule.created_at = chrono::Utc::now().to_rfc3339();
+    }
+    db::save_follow_up_rule(&rule).map_err(|e| e.to_string())?;
+    Ok(rule.id)
+}
+
+/// 후속 설문 규칙 삭제
+#[tauri::command]
+pub fn delete_follow_up_rule(id: String) -> Result<(), String> {
+    db::delete_follow_up_rule(&id).map_err(|e| e.to_string())
 }
 
 /// 기본 설문 템플릿 복원
@@ -543,13 +998,14 @@ pub fn restore_default_survey_templates() -> Result<(), String> {
 /// 설문 세션 목록 조회
 #[tauri::command]
 pub fn list_survey_sessions(patient_id: Option<String>, status: Option<String>) -> Result<Vec<db::SurveySessionWithPatient>, String> {
-    db::list_survey_sessions(patient_id.as_deref(), status.as_deref()).map_err(|e| e.to_string())
+    db::list_survey_sessions(patient_id.as_deref(), status.as_deref(), None).map_err(|e| e.to_string())
 }
 
 /// 설문 세션 생성
 #[tauri::command]
-pub fn create_survey_session(patient_id: Option<String>, template_id: String, respondent_name: Option<String>, created_by: Option<String>, token: Option<String>, patient_name: Option<String>, chart_number: Option<String>, patient_age: Option<String>, patient_gender: Option<String>) -> Result<db::SurveySessionDb, String> {
-    db::create_survey_session(patient_id.as_deref(), &template_id, respondent_name.as_deref(), created_by.as_deref(), token.as_deref(), patient_name.as_deref(), chart_number.as_deref(), patient_age.as_deref(), patient_gender.as_deref()).map_err(|e| e.to_string())
+pub fn create_survey_session(patient_id: Option<String>, template_id: String, respondent_name: Option<String>, created_by: Option<String>, token: Option<String>, patient_name: Option<String>, chart_number: Option<String>, patient_age: Option<String>, patient_gender: Option<String>, verify_birth_date: Option<bool>, expires_in_hours: Option<u32>) -> Result<db::SurveySessionDb, String> {
+    let expires_at = expires_in_hours.map(|h| chrono::Utc::now() + chrono::Duration::hours(i64::from(h)));
+    db::create_survey_session(patient_id.as_deref(), &template_id, respondent_name.as_deref(), created_by.as_deref(), token.as_deref(), patient_name.as_deref(), chart_number.as_deref(), patient_age.as_deref(), patient_gender.as_deref(), verify_birth_date.unwrap_or(false), expires_at).map_err(|e| e.to_string())
 }
 
 /// 설문 세션 토큰으로 조회
@@ -586,25 +1042,9 @@ pub fn delete_survey_session(id: String) -> Result<(), String> {
 
 #[tauri::command]
 pub fn generate_survey_qr(url: String) -> Result<String, String> {
-    use qrcode::QrCode;
-    use image::Luma;
-    use image::ImageEncoder;
     use base64::Engine;
 
-    let code = QrCode::new(url.as_bytes()).map_err(|e| e.to_string())?;
-    let qr_image = code.render::<Luma<u8>>().build();
-
-    // PNG로 인코딩
-    let mut png_data = Vec::new();
-    let encoder = image::codecs::png::PngEncoder::new(&mut png_data);
-    encoder
-        .write_image(
-            qr_image.as_raw(),
-            qr_image.width(),
-            qr_image.height(),
-            image::ExtendedColorType::L8,
-        )
-        .map_err(|e| e.to_string())?;
+    let png_data = server::render_qr_png(&url).map_err(|e| e.to_string())?;
 
     // Base64 인코딩
     let base64_str = base64::engine::general_purpose::STANDARD.encode(&png_data);
@@ -612,6 +1052,124 @@ pub fn generate_survey_qr(url: String) -> Result<String, String> {
     Ok(format!("data:image/png;base64,{}", base64_str))
 }
 
+/// 이메일 발송 설정 조회 (비밀번호는 비워서 반환)
+#[tauri::command]
+pub fn get_smtp_settings() -> Result<Option<SmtpSettings>, String> {
+    db::get_smtp_settings()
+        .map(|settings| settings.map(|s| SmtpSettings { password: String::new(), ..s }))
+        .map_err(|e| e.to_string())
+}
+
+/// 이메일 발송 설정 저장 (None이면 이메일 발송 끔, 비밀번호를 비워 두면 기존 값 유지)
+#[tauri::command]
+pub fn save_smtp_settings(settings: Option<SmtpSettings>) -> Result<(), String> {
+    db::save_smtp_settings(settings.as_ref()).map_err(|e| e.to_string())
+}
+
+/// 문자 발송 설정 조회 (API 키는 비워서 반환)
+#[tauri::command]
+pub fn get_sms_settings() -> Result<Option<SmsSettings>, String> {
+    db::get_sms_settings()
+        .map(|settings| settings.map(|s| SmsSettings { api_key: String::new(), ..s }))
+        .map_err(|e| e.to_string())
+}
+
+/// 문자 발송 설정 저장 (None이면 문자 발송 끔, API 키를 비워 두면 기존 값 유지)
+#[tauri::command]
+pub fn save_sms_settings(settings: Option<SmsSettings>) -> Result<(), String> {
+    db::save_sms_settings(settings.as_ref()).map_err(|e| e.to_string())
+}
+
+/// 시험 문자 발송
+#[tauri::command]
+pub async fn send_test_sms(to_phone: String) -> Result<(), String> {
+    let clinic_name = db::get_clinic_settings()
+        .ok()
+        .flatten()
+        .map(|s| s.clinic_name)
+        .unwrap_or_default();
+    crate::sms::send(&to_phone, &format!("[{}] 문자 발송 시험입니다.", clinic_name))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 복약 알림 발송 채널 조회
+#[tauri::command]
+pub fn get_notification_channel() -> Result<NotificationChannel, String> {
+    db::get_notification_channel().map_err(|e| e.to_string())
+}
+
+/// 복약 알림 발송 채널 변경
+#[tauri::command]
+pub fn set_notification_channel(channel: NotificationChannel) -> Result<(), String> {
+    db::set_notification_channel(channel).map_err(|e| e.to_string())
+}
+
+/// 미완료 설문 리마인더 시점 조회 (만료 몇 시간 전, None이면 사용 안 함)
+#[tauri::command]
+pub fn get_survey_reminder_hours() -> Result<Option<i64>, String> {
+    db::get_survey_reminder_hours().map_err(|e| e.to_string())
+}
+
+/// 미완료 설문 리마인더 시점 변경 (None이면 끔)
+#[tauri::command]
+pub fn set_survey_reminder_hours(hours: Option<i64>) -> Result<(), String> {
+    db::set_survey_reminder_hours(hours).map_err(|e| e.to_string())
+}
+
+/// 오래된 기록 자동 정리 보관 기간 조회
+#[tauri::command]
+pub fn get_retention_settings() -> Result<crate::models::RetentionSettings, String> {
+    db::get_retention_settings().map_err(|e| e.to_string())
+}
+
+/// 오래된 기록 자동 정리 보관 기간 저장 (항목별 30일 이상, 비우면 정리하지 않음)
+#[tauri::command]
+pub fn save_retention_settings(settings: crate::models::RetentionSettings) -> Result<(), String> {
+    db::save_retention_settings(&settings).map_err(|e| e.to_string())
+}
+
+/// 저장된 보관 기간으로 지금 정리 (`dry_run`이면 지울 대상 건수만)
+#[tauri::command]
+pub fn purge_old_data(dry_run: Option<bool>) -> Result<crate::models::PurgeSummary, String> {
+    let retention = db::get_retention_settings().map_err(|e| e.to_string())?;
+    db::purge_old_data(&retention, dry_run.unwrap_or(true)).map_err(|e| e.to_string())
+}
+
+/// DB 최적화 (WAL 체크포인트 + VACUUM, 내보내기 작업 중에는 거부)
+#[tauri::command]
+pub fn run_db_maintenance() -> Result<crate::models::DbMaintenanceReport, String> {
+    db::run_maintenance().map_err(|e| e.to_string())
+}
+
+/// DB 최적화 주간 자동 실행 설정 조회
+#[tauri::command]
+pub fn get_db_maintenance_settings() -> Result<crate::models::DbMaintenanceSettings, String> {
+    db::get_db_maintenance_settings().map_err(|e| e.to_string())
+}
+
+/// DB 최적화 주간 자동 실행 설정 저장
+#[tauri::command]
+pub fn save_db_maintenance_settings(settings: crate::models::DbMaintenanceSettings) -> Result<(), String> {
+    db::save_db_maintenance_settings(&settings).map_err(|e| e.to_string())
+}
+
+/// 키오스크 안내문 내보내기 (`format`: "pdf" 안내문 전체, "png" QR 코드만) - HTTP 서버 실행 중에만 가능
+#[tauri::command]
+pub fn export_kiosk_poster(format: String) -> Result<Vec<u8>, String> {
+    let port = SERVER_PORT
+        .get()
+        .copied()
+        .filter(|_| SERVER_RUNNING.load(Ordering::SeqCst))
+        .ok_or("HTTP 서버가 실행 중이 아닙니다")?;
+    match format.as_str() {
+        "pdf" => server::render_kiosk_poster_pdf(port),
+        "png" => server::render_qr_png(&server::kiosk_url(port)),
+        other => return Err(format!("지원하지 않는 형식입니다: {}", other)),
+    }
+    .map_err(|e| e.to_string())
+}
+
 // ============ 내부 직원 계정 관리 명령어 ============
 
 /// 직원 계정 생성 요청
@@ -623,30 +1181,14 @@ pub struct CreateStaffAccountInput {
     pub role: String,
 }
 
-/// 직원 계정 생성
+/// 직원 계정 생성 (반환: 계정 ID)
 #[tauri::command]
 pub fn create_staff_account(input: CreateStaffAccountInput) -> Result<String, String> {
-    use crate::models::{StaffAccount, StaffRole};
-
-    // 비밀번호 해시
-    let password_hash = db::hash_staff_password(&input.password)
-        .map_err(|e| e.to_string())?;
-
-    // 역할 파싱
-    let role = StaffRole::from_str(&input.role);
-
-    // 계정 생성
-    let account = StaffAccount::new(
-        input.username,
-        input.display_name,
-        password_hash,
-        role,
-    );
-
-    let id = account.id.clone();
-    db::create_staff_account(&account).map_err(|e| e.to_string())?;
+    use crate::models::StaffRole;
 
-    Ok(id)
+    db::create_staff_account(&input.username, &input.display_name, &input.password, StaffRole::from_str(&input.role))
+        .map(|account| account.id)
+        .map_err(|e| e.to_string())
 }
 
 /// 직원 계정 목록 조회
@@ -704,11 +1246,21 @@ pub fn update_staff_account(input: UpdateStaffAccountInput) -> Result<(), String
         last_login_at: existing.last_login_at,
         created_at: existing.created_at,
         updated_at: chrono::Utc::now(),
+        must_change_password: existing.must_change_password,
     };
 
     db::update_staff_account(&account).map_err(|e| e.to_string())
 }
 
+/// 직원 계정 활성/비활성 (마지막 관리자는 비활성화 불가)
+#[tauri::command]
+pub fn set_staff_account_active(id: String, is_active: bool) -> Result<crate::models::StaffAccountInfo, String> {
+    db::set_staff_account_active(&id, is_active)
+        .map_err(|e| e.to_string())?
+        .map(Into::into)
+        .ok_or_else(|| "계정을 찾을 수 없습니다".to_string())
+}
+
 /// 직원 계정 삭제
 #[tauri::command]
 pub fn delete_staff_account(id: String) -> Result<(), String> {
@@ -735,6 +1287,42 @@ pub fn link_survey_response_to_patient(response_id: String, patient_id: String)
     db::link_survey_response_to_patient(&response_id, &patient_id).map_err(|e| e.to_string())
 }
 
+/// 설문 응답에 연결할 환자 후보 (이름/생년월일 일치도 순)
+#[tauri::command]
+pub fn suggest_patient_for_response(response_id: String) -> Result<Vec<Patient>, String> {
+    db::suggest_patient_for_response(&response_id).map_err(|e| e.to_string())
+}
+
+/// 알림 다시 알림 (반환: 다시 표시될 시각, 알림이 없으면 None)
+#[tauri::command]
+pub fn snooze_notification(id: String, minutes: i64) -> Result<Option<String>, String> {
+    db::snooze_notification(&id, minutes).map_err(|e| e.to_string())
+}
+
+/// 정리/알림 작업 즉시 실행 (다른 인스턴스가 실행 중이면 None)
+#[tauri::command]
+pub fn run_notification_checks() -> Result<Option<server::SweepSummary>, String> {
+    server::run_checks_once().map_err(|e| e.to_string())
+}
+
+/// 정리/알림 작업 실행 주기(초)
+#[tauri::command]
+pub fn get_scheduler_interval() -> Result<u64, String> {
+    db::get_scheduler_interval_secs().map_err(|e| e.to_string())
+}
+
+/// 정리/알림 작업 실행 주기 변경 (다음 주기부터 적용)
+#[tauri::command]
+pub fn set_scheduler_interval(secs: u64) -> Result<(), String> {
+    db::set_scheduler_interval_secs(secs).map_err(|e| e.to_string())
+}
+
+/// 환자와 연결되지 않은 응답 수 (`older_than_hours` 미지정 시 24시간 경과 기준)
+#[tauri::command]
+pub fn count_unlinked_responses(older_than_hours: Option<i64>) -> Result<i64, String> {
+    db::count_unlinked_responses(older_than_hours.unwrap_or(db::UNLINKED_RESPONSE_HOURS)).map_err(|e| e.to_string())
+}
+
 /// 설문 응답 제출
 #[tauri::command]
 pub fn submit_survey_response(
@@ -775,6 +1363,23 @@ pub fn save_survey_response_sync(
     .map_err(|e| e.to_string())
 }
 
+/// 동기화 충돌 목록 (기본은 미해결만)
+#[tauri::command]
+pub fn list_sync_conflicts(include_resolved: Option<bool>) -> Result<Vec<crate::models::SyncConflict>, String> {
+    db::list_sync_conflicts(include_resolved.unwrap_or(false)).map_err(|e| e.to_string())
+}
+
+/// 동기화 충돌 해결 (`choice`: local이면 로컬 유지, remote면 원격 버전 적용)
+#[tauri::command]
+pub fn resolve_sync_conflict(
+    id: String,
+    choice: crate::models::SyncConflictChoice,
+) -> Result<crate::models::SyncConflict, String> {
+    db::resolve_sync_conflict(&id, choice)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "충돌 기록을 찾을 수 없습니다".to_string())
+}
+
 // ============ 처방 카테고리 명령어 ============
 
 #[tauri::command]
@@ -924,6 +1529,11 @@ pub fn get_medication_schedule(id: String) -> Result<Option<crate::models::Medic
     db::get_medication_schedule_cmd(&id).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub fn get_completed_schedules(patient_id: String) -> Result<Vec<crate::models::MedicationSchedule>, String> {
+    db::get_completed_schedules(&patient_id).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub fn create_medication_schedule(schedule: crate::models::MedicationSchedule) -> Result<(), String> {
     db::create_medication_schedule_cmd(&schedule).map_err(|e| e.to_string())
@@ -1010,6 +1620,45 @@ pub fn get_trash_count() -> Result<crate::models::TrashCount, String> {
     db::get_trash_count().map_err(|e| e.to_string())
 }
 
+// ============ 태그 명령어 ============
+
+#[tauri::command]
+pub fn get_entity_tags(entity_type: String, entity_id: String) -> Result<Option<Vec<String>>, String> {
+    db::get_entity_tags(&entity_type, &entity_id).map_err(|e| e.to_string())
+}
+
+/// 태그 추가 (대상이 없으면 None)
+#[tauri::command]
+pub fn add_entity_tags(entity_type: String, entity_id: String, tags: Vec<String>) -> Result<Option<Vec<String>>, String> {
+    db::add_entity_tags(&entity_type, &entity_id, &tags).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn remove_entity_tag(entity_type: String, entity_id: String, tag: String) -> Result<bool, String> {
+    db::remove_entity_tag(&entity_type, &entity_id, &tag).map_err(|e| e.to_string())
+}
+
+/// 태그 교차 검색 (`match_all`: 모든 태그 일치)
+#[tauri::command]
+pub fn search_tagged_entities(
+    tags: Vec<String>,
+    entity_types: Option<Vec<String>>,
+    match_all: Option<bool>,
+) -> Result<Vec<TaggedEntity>, String> {
+    db::search_tagged_entities(&tags, &entity_types.unwrap_or_default(), match_all.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn suggest_tags(prefix: String, limit: Option<i64>) -> Result<Vec<String>, String> {
+    db::suggest_tags(&prefix, limit.unwrap_or(10)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_tag_stats() -> Result<Vec<TagStat>, String> {
+    db::get_tag_stats().map_err(|e| e.to_string())
+}
+
 // ============ 사용량 통계 명령어 ============
 
 #[tauri::command]
@@ -1017,6 +1666,24 @@ pub fn get_usage_stats() -> Result<crate::models::UsageStats, String> {
     db::get_usage_stats().map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub fn get_dashboard_stats(from: Option<String>, to: Option<String>) -> Result<crate::models::DashboardStats, String> {
+    db::get_dashboard_stats(from.as_deref(), to.as_deref()).map_err(|e| e.to_string())
+}
+
+/// 설문 응답 집계 조회 (캐시 우선)
+#[tauri::command]
+pub fn get_survey_response_stats() -> Result<crate::models::SurveyResponseStats, String> {
+    db::get_survey_response_stats().map_err(|e| e.to_string())
+}
+
+// ============ 매출 리포트 명령어 ============
+
+#[tauri::command]
+pub fn get_revenue_report(from: String, to: String) -> Result<crate::models::RevenueReport, String> {
+    db::get_revenue_report(&from, &to).map_err(|e| e.to_string())
+}
+
 // ============ 초기화 명령어 ============
 
 #[tauri::command]
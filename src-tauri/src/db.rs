@@ -8,6 +8,9 @@ use std::sync::Mutex;
 
 static DB_CONNECTION: OnceCell<Mutex<Connection>> = OnceCell::new();
 static CURRENT_USER_ID: OnceCell<Mutex<Option<String>>> = OnceCell::new();
+/// 설문 응답 집계 캐시 (None이면 다음 조회 시 재구축)
+/// 잠금 순서: 항상 DB 연결 → 캐시 순으로 잠가 갱신이 DB 변경과 직렬화되도록 함
+static SURVEY_STATS_CACHE: OnceCell<Mutex<Option<SurveyResponseStats>>> = OnceCell::new();
 
 /// 데이터베이스 경로 가져오기
 fn get_db_path() -> AppResult<PathBuf> {
@@ -121,10 +124,49 @@ pub fn init_database_encrypted(user_id: &str, encryption_key: &str) -> AppResult
     // 기본 설문 템플릿 삽입
     ensure_default_templates()?;
 
+    // 이전 실행에서 전송 중 종료된 동기화 항목 복구
+    match reset_inflight_sync_items() {
+        Ok(0) => {}
+        Ok(count) => log::info!("전송 중이던 동기화 항목 {}건 복구", count),
+        Err(e) => log::warn!("동기화 대기열 복구 실패: {}", e),
+    }
+    match reset_inflight_emails() {
+        Ok(0) => {}
+        Ok(count) => log::info!("발송 중이던 이메일 {}건 복구", count),
+        Err(e) => log::warn!("이메일 대기열 복구 실패: {}", e),
+    }
+    match cleanup_orphaned_survey_sessions(ORPHANED_SESSION_RETENTION_DAYS) {
+        Ok(0) => {}
+        Ok(count) => log::info!("응답 없이 끝난 오래된 설문 세션 {}건 삭제", count),
+        Err(e) => log::warn!("오래된 설문 세션 정리 실패: {}", e),
+    }
+
     log::info!("Encrypted database initialized at {:?}", db_path);
     Ok(())
 }
 
+/// 테스트용 데이터베이스 초기화 (임시 폴더의 암호화 DB, 프로세스당 한 번 - 테스트끼리 공유하므로 ID는 매번 새로 만들 것)
+#[cfg(test)]
+pub(crate) fn init_test_database() {
+    static INIT: std::sync::Once = std::sync::Once::new();
+    INIT.call_once(|| {
+        let db_path = std::env::temp_dir().join(format!("gosibang-test-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&db_path);
+        let conn = Connection::open(&db_path).expect("테스트 DB 열기 실패");
+        conn.execute_batch(&format!(
+            "PRAGMA key = 'x\"{}\"';
+             PRAGMA cipher_compatibility = 4;",
+            "0".repeat(64)
+        ))
+        .expect("테스트 DB 키 설정 실패");
+        create_tables(&conn).expect("테스트 DB 테이블 생성 실패");
+        run_migrations(&conn).expect("테스트 DB 마이그레이션 실패");
+        let _ = DB_CONNECTION.set(Mutex::new(conn));
+        let _ = CURRENT_USER_ID.set(Mutex::new(Some("test".to_string())));
+        ensure_default_templates().expect("기본 템플릿 삽입 실패");
+    });
+}
+
 /// 현재 로그인한 사용자 ID 조회
 #[allow(dead_code)]
 pub fn get_current_user_id() -> Option<String> {
@@ -144,6 +186,8 @@ pub fn is_database_initialized() -> bool {
 fn ensure_default_templates() -> AppResult<()> {
     let conn = get_conn()?;
     let now = chrono::Utc::now().to_rfc3339();
+    // 처음 실행(템플릿 없음)일 때만 기본 제공 문진 템플릿 추가
+    let is_first_run = conn.query_row("SELECT COUNT(*) FROM survey_templates", [], |row| row.get::<_, i64>(0))? == 0;
 
     // 여성 건강 설문지
     let female_questions = get_female_health_survey_questions();
@@ -155,7 +199,7 @@ fn ensure_default_templates() -> AppResult<()> {
 
     if count > 0 {
         conn.execute(
-            "UPDATE survey_templates SET name = ?1, description = ?2, questions = ?3, updated_at = ?4 WHERE id = ?5",
+            "UPDATE survey_templates SET name = ?1, description = ?2, questions = ?3, updated_at = ?4, is_builtin = 1 WHERE id = ?5",
             rusqlite::params![
                 "기본설문지-여성",
                 "여성 환자용 기본 건강 설문지입니다.",
@@ -167,7 +211,7 @@ fn ensure_default_templates() -> AppResult<()> {
         log::info!("기본 설문 템플릿 '기본설문지-여성' 업데이트됨");
     } else {
         conn.execute(
-            "INSERT INTO survey_templates (id, name, description, questions, display_mode, is_active, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            "INSERT INTO survey_templates (id, name, description, questions, display_mode, is_active, created_at, updated_at, is_builtin) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 1)",
             rusqlite::params![
                 "default_female_health",
                 "기본설문지-여성",
@@ -192,7 +236,7 @@ fn ensure_default_templates() -> AppResult<()> {
 
     if child_count > 0 {
         conn.execute(
-            "UPDATE survey_templates SET name = ?1, description = ?2, questions = ?3, updated_at = ?4 WHERE id = ?5",
+            "UPDATE survey_templates SET name = ?1, description = ?2, questions = ?3, updated_at = ?4, is_builtin = 1 WHERE id = ?5",
             rusqlite::params![
                 "기본설문지-소아",
                 "소아 환자용 기본 건강 설문지입니다.",
@@ -204,7 +248,7 @@ fn ensure_default_templates() -> AppResult<()> {
         log::info!("기본 설문 템플릿 '기본설문지-소아' 업데이트됨");
     } else {
         conn.execute(
-            "INSERT INTO survey_templates (id, name, description, questions, display_mode, is_active, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            "INSERT INTO survey_templates (id, name, description, questions, display_mode, is_active, created_at, updated_at, is_builtin) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 1)",
             rusqlite::params![
                 "default_child_health",
                 "기본설문지-소아",
@@ -219,9 +263,42 @@ fn ensure_default_templates() -> AppResult<()> {
         log::info!("기본 설문 템플릿 '기본설문지-소아' 삽입됨");
     }
 
+    if is_first_run {
+        seed_builtin_templates(&conn, &now)?;
+    }
+
     Ok(())
 }
 
+/// 기본 제공 문진 템플릿 (초진 설문, 통증 평가 VAS, 수면/소화 문진)
+const BUILTIN_TEMPLATES_JSON: &str = include_str!("default_survey_templates.json");
+
+#[derive(serde::Deserialize)]
+struct BuiltinTemplate {
+    id: String,
+    name: String,
+    description: Option<String>,
+    display_mode: String,
+    questions: serde_json::Value,
+}
+
+/// 기본 제공 문진 템플릿 중 없는 것만 추가 (사용자가 수정한 템플릿은 덮어쓰지 않음)
+fn seed_builtin_templates(conn: &Connection, now: &str) -> AppResult<usize> {
+    let templates: Vec<BuiltinTemplate> = serde_json::from_str(BUILTIN_TEMPLATES_JSON)?;
+    let mut inserted = 0;
+    for t in templates {
+        inserted += conn.execute(
+            "INSERT OR IGNORE INTO survey_templates (id, name, description, questions, display_mode, is_active, created_at, updated_at, is_builtin)
+             VALUES (?1, ?2, ?3, ?4, ?5, 1, ?6, ?6, 1)",
+            params![t.id, t.name, t.description, t.questions.to_string(), t.display_mode, now],
+        )?;
+    }
+    if inserted > 0 {
+        log::info!("기본 제공 문진 템플릿 {}개 추가됨", inserted);
+    }
+    Ok(inserted)
+}
+
 /// 여성 건강 설문지 질문 데이터 (JSON) - 원본 form1.html 기반 (내용 변경 없이 그대로)
 fn get_female_health_survey_questions() -> String {
     r#"[
@@ -384,6 +461,8 @@ fn create_tables(conn: &Connection) -> AppResult<()> {
             total_dosage REAL NOT NULL DEFAULT 0,
             final_total_amount REAL NOT NULL DEFAULT 0,
             notes TEXT,
+            price INTEGER,
+            insurance_type TEXT,
             status TEXT NOT NULL DEFAULT 'draft',
             issued_at TEXT,
             created_by TEXT,
@@ -404,6 +483,7 @@ fn create_tables(conn: &Connection) -> AppResult<()> {
             treatment TEXT,
             prescription_id TEXT,
             notes TEXT,
+            is_deleted INTEGER NOT NULL DEFAULT 0,
             created_at TEXT NOT NULL,
             updated_at TEXT NOT NULL,
             FOREIGN KEY (patient_id) REFERENCES patients(id),
@@ -461,7 +541,8 @@ fn create_tables(conn: &Connection) -> AppResult<()> {
             display_mode TEXT DEFAULT 'one_by_one',
             is_active INTEGER DEFAULT 1,
             created_at TEXT NOT NULL,
-            updated_at TEXT NOT NULL
+            updated_at TEXT NOT NULL,
+            is_builtin INTEGER NOT NULL DEFAULT 0
         );
 
         -- 설문 세션 (온라인 설문용)
@@ -692,6 +773,183 @@ fn create_tables(conn: &Connection) -> AppResult<()> {
         CREATE INDEX IF NOT EXISTS idx_herb_stock_log_type ON herb_stock_log(log_type);
         CREATE INDEX IF NOT EXISTS idx_herb_stock_log_prescription ON herb_stock_log(prescription_id);
 
+        -- 변경 이력 (차팅 기록 등 법적 기록의 수정/삭제 추적)
+        CREATE TABLE IF NOT EXISTS audit_logs (
+            id TEXT PRIMARY KEY,
+            entity_type TEXT NOT NULL,
+            entity_id TEXT NOT NULL,
+            action TEXT NOT NULL,
+            previous_data TEXT,
+            new_data TEXT,
+            changed_by TEXT,
+            created_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_audit_logs_entity ON audit_logs(entity_type, entity_id);
+
+        -- 문서 서명 (처방/차트 무결성)
+        CREATE TABLE IF NOT EXISTS document_signatures (
+            id TEXT PRIMARY KEY,
+            document_type TEXT NOT NULL,
+            document_id TEXT NOT NULL,
+            content_hash TEXT NOT NULL,
+            previous_hash TEXT,
+            signed_by TEXT,
+            signed_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_document_signatures_document ON document_signatures(document_id);
+
+        -- 설문 세션 생성 기록 (남용 탐지)
+        CREATE TABLE IF NOT EXISTS survey_session_creation_logs (
+            id TEXT PRIMARY KEY,
+            session_id TEXT NOT NULL,
+            staff_id TEXT,
+            ip TEXT,
+            created_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_survey_session_creation_logs_created_at ON survey_session_creation_logs(created_at);
+
+        -- 후속 설문 규칙
+        CREATE TABLE IF NOT EXISTS survey_follow_up_rules (
+            id TEXT PRIMARY KEY,
+            source_template_id TEXT NOT NULL,
+            follow_up_template_id TEXT NOT NULL,
+            trigger_type TEXT NOT NULL DEFAULT 'completed',
+            question_id TEXT,
+            min_score REAL,
+            max_score REAL,
+            delay_hours INTEGER NOT NULL DEFAULT 0,
+            is_active INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL
+        );
+
+        -- 후속 설문 예약 (due_at 이후 세션 생성, chain_depth로 연쇄 횟수 제한)
+        CREATE TABLE IF NOT EXISTS survey_follow_ups (
+            id TEXT PRIMARY KEY,
+            rule_id TEXT NOT NULL,
+            source_session_id TEXT NOT NULL,
+            follow_up_template_id TEXT NOT NULL,
+            chain_depth INTEGER NOT NULL,
+            due_at TEXT NOT NULL,
+            session_id TEXT,
+            created_at TEXT NOT NULL,
+            UNIQUE (rule_id, source_session_id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_survey_follow_ups_due ON survey_follow_ups(due_at);
+
+        -- 동기화 대기열 (Supabase 전송 실패/보류 항목)
+        CREATE TABLE IF NOT EXISTS sync_queue (
+            id TEXT PRIMARY KEY,
+            item_type TEXT NOT NULL,
+            data TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            retry_count INTEGER NOT NULL DEFAULT 0,
+            in_flight INTEGER NOT NULL DEFAULT 0,
+            next_retry_at TEXT
+        );
+
+        -- 공통 태그 (환자/처방/차트 등 엔티티 종류 무관)
+        CREATE TABLE IF NOT EXISTS tags (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            entity_type TEXT NOT NULL,
+            entity_id TEXT NOT NULL,
+            tag TEXT NOT NULL COLLATE NOCASE,
+            created_at TEXT NOT NULL,
+            UNIQUE (entity_type, entity_id, tag)
+        );
+        CREATE INDEX IF NOT EXISTS idx_tags_tag ON tags(tag);
+
+        -- 설문 첨부 파일 (환자가 올린 검사지 사진 등, DB 암호화 범위 안에 보관)
+        CREATE TABLE IF NOT EXISTS survey_attachments (
+            id TEXT PRIMARY KEY,
+            session_id TEXT NOT NULL,
+            question_id TEXT NOT NULL,
+            response_id TEXT,
+            file_name TEXT NOT NULL,
+            mime_type TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            data BLOB NOT NULL,
+            created_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_survey_attachments_session ON survey_attachments(session_id);
+        CREATE INDEX IF NOT EXISTS idx_survey_attachments_response ON survey_attachments(response_id);
+
+        -- 이메일 발송 대기열 (SMTP 발송 실패 항목, 정리 작업 주기마다 재시도)
+        CREATE TABLE IF NOT EXISTS email_queue (
+            id TEXT PRIMARY KEY,
+            recipient TEXT NOT NULL,
+            subject TEXT NOT NULL,
+            body TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            retry_count INTEGER NOT NULL DEFAULT 0,
+            in_flight INTEGER NOT NULL DEFAULT 0,
+            next_retry_at TEXT NOT NULL,
+            last_error TEXT
+        );
+
+        -- 복약 알림 발송 기록 (복약 시각/채널별 중복 발송 방지)
+        CREATE TABLE IF NOT EXISTS reminder_deliveries (
+            schedule_id TEXT NOT NULL,
+            slot TEXT NOT NULL,
+            channel TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            PRIMARY KEY (schedule_id, slot, channel)
+        );
+
+        -- 차팅 입력용 약어 (owner_id가 NULL이면 전역, 아니면 직원 계정별)
+        CREATE TABLE IF NOT EXISTS text_macros (
+            id TEXT PRIMARY KEY,
+            abbreviation TEXT NOT NULL,
+            expansion TEXT NOT NULL,
+            owner_id TEXT,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_text_macros_abbreviation ON text_macros(abbreviation, COALESCE(owner_id, ''));
+
+        -- 직원 웹 세션 (토큰 원문 대신 SHA-256 해시 저장, 앱을 다시 켜도 로그인 유지)
+        CREATE TABLE IF NOT EXISTS web_sessions (
+            token_hash TEXT PRIMARY KEY,
+            clinic_name TEXT NOT NULL,
+            account_id TEXT,
+            must_change_password INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            last_activity TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_web_sessions_account ON web_sessions(account_id);
+
+        -- 환자 중요 정보 (알레르기/주의 약물/만성질환 등)
+        CREATE TABLE IF NOT EXISTS patient_alerts (
+            id TEXT PRIMARY KEY,
+            patient_id TEXT NOT NULL,
+            alert_type TEXT NOT NULL,
+            severity TEXT NOT NULL DEFAULT 'medium',
+            content TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (patient_id) REFERENCES patients(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_patient_alerts_patient ON patient_alerts(patient_id);
+
+        -- 동기화 충돌 (로컬/원격 양쪽 버전, 직원이 골라 해결)
+        CREATE TABLE IF NOT EXISTS sync_conflicts (
+            id TEXT PRIMARY KEY,
+            item_type TEXT NOT NULL,
+            item_id TEXT NOT NULL,
+            local_data TEXT NOT NULL,
+            remote_data TEXT NOT NULL,
+            detected_at TEXT NOT NULL,
+            resolved_at TEXT,
+            resolution TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_sync_conflicts_item ON sync_conflicts(item_type, item_id);
+
+        -- 백그라운드 작업 단일 실행 락 (같은 DB를 여는 여러 프로세스 간)
+        CREATE TABLE IF NOT EXISTS scheduler_locks (
+            name TEXT PRIMARY KEY,
+            owner TEXT NOT NULL,
+            heartbeat_at TEXT NOT NULL
+        );
+
         -- 인덱스 생성
         CREATE INDEX IF NOT EXISTS idx_patients_name ON patients(name);
         CREATE INDEX IF NOT EXISTS idx_prescriptions_patient ON prescriptions(patient_id);
@@ -773,6 +1031,60 @@ fn run_migrations(conn: &Connection) -> AppResult<()> {
     // patients 테이블에 deleted_at 컬럼 추가 (휴지통 기능)
     let _ = conn.execute("ALTER TABLE patients ADD COLUMN deleted_at TEXT", []);
 
+    // 환자 중복 등록 방지 (이름 + 생년월일 + 연락처, 의도적 동명이인은 allow_duplicate로 제외)
+    // 인덱스 생성 전에 이미 있는 중복은 먼저 등록된 환자만 남기고 허용 처리
+    let _ = conn.execute("ALTER TABLE patients ADD COLUMN allow_duplicate INTEGER NOT NULL DEFAULT 0", []);
+    conn.execute_batch(
+        r#"
+        UPDATE patients SET allow_duplicate = 1
+        WHERE deleted_at IS NULL AND allow_duplicate = 0
+          AND (COALESCE(birth_date, '') <> '' OR COALESCE(phone, '') <> '')
+          AND rowid NOT IN (
+              SELECT MIN(rowid) FROM patients
+              WHERE deleted_at IS NULL AND allow_duplicate = 0
+              GROUP BY name, COALESCE(birth_date, ''), COALESCE(phone, '')
+          );
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_patients_identity
+            ON patients(name, COALESCE(birth_date, ''), COALESCE(phone, ''))
+            WHERE deleted_at IS NULL AND allow_duplicate = 0
+              AND (COALESCE(birth_date, '') <> '' OR COALESCE(phone, '') <> '');
+        "#,
+    )?;
+
+    // chart_records 테이블에 is_deleted 컬럼 추가 (소프트 삭제)
+    let _ = conn.execute("ALTER TABLE chart_records ADD COLUMN is_deleted INTEGER NOT NULL DEFAULT 0", []);
+
+    // sync_queue 테이블에 다음 재시도 시각 컬럼 추가 (지수 백오프)
+    let _ = conn.execute("ALTER TABLE sync_queue ADD COLUMN next_retry_at TEXT", []);
+
+    // clinic_settings 테이블에 설문 링크 유효 시간 컬럼 추가
+    let _ = conn.execute("ALTER TABLE clinic_settings ADD COLUMN survey_ttl_hours INTEGER", []);
+
+    // clinic_settings 테이블에 키오스크 무입력 대기 시간 컬럼 추가
+    let _ = conn.execute("ALTER TABLE clinic_settings ADD COLUMN kiosk_idle_minutes INTEGER", []);
+
+    // clinic_settings 테이블에 CORS 허용 출처 컬럼 추가
+    let _ = conn.execute("ALTER TABLE clinic_settings ADD COLUMN cors_allowed_origins TEXT", []);
+
+    // clinic_settings 테이블에 로고 컬럼 추가 (base64 PNG)
+    let _ = conn.execute("ALTER TABLE clinic_settings ADD COLUMN clinic_logo TEXT", []);
+
+    // 이메일 발송 설정 (JSON) 및 일일 요약 마지막 발송일
+    let _ = conn.execute("ALTER TABLE clinic_settings ADD COLUMN smtp_settings TEXT", []);
+
+    // 원내 서버 HTTPS 설정 (JSON)
+    let _ = conn.execute("ALTER TABLE clinic_settings ADD COLUMN server_tls_settings TEXT", []);
+    let _ = conn.execute("ALTER TABLE notification_settings ADD COLUMN daily_summary_last_sent_on TEXT", []);
+
+    // 내원 경로 (환자 / 키오스크 세션) 및 경로 목록 설정 컬럼 추가
+    let _ = conn.execute("ALTER TABLE patients ADD COLUMN referral_source TEXT", []);
+    let _ = conn.execute("ALTER TABLE survey_sessions ADD COLUMN referral_source TEXT", []);
+    let _ = conn.execute("ALTER TABLE clinic_settings ADD COLUMN referral_sources TEXT", []);
+
+    // prescriptions 테이블에 금액/보험 구분 컬럼 추가 (매출 리포트)
+    let _ = conn.execute("ALTER TABLE prescriptions ADD COLUMN price INTEGER", []);
+    let _ = conn.execute("ALTER TABLE prescriptions ADD COLUMN insurance_type TEXT", []);
+
     // 처방 정의 기본 데이터 삽입 (비어있을 때만)
     let count: i32 = conn.query_row(
         "SELECT COUNT(*) FROM prescription_definitions",
@@ -792,6 +1104,70 @@ fn run_migrations(conn: &Connection) -> AppResult<()> {
     let _ = conn.execute("ALTER TABLE survey_sessions ADD COLUMN patient_age TEXT", []);
     let _ = conn.execute("ALTER TABLE survey_sessions ADD COLUMN patient_gender TEXT", []);
 
+    // survey_responses 테이블에 멱등 키 컬럼 추가 (중복 제출 재시도 식별)
+    let _ = conn.execute("ALTER TABLE survey_responses ADD COLUMN idempotency_key TEXT", []);
+
+    // survey_sessions 테이블에 생년월일 확인 컬럼 추가 (외부 링크 본인 확인)
+    let _ = conn.execute("ALTER TABLE survey_sessions ADD COLUMN verify_birth_date INTEGER NOT NULL DEFAULT 0", []);
+    let _ = conn.execute("ALTER TABLE survey_sessions ADD COLUMN verify_failed_count INTEGER NOT NULL DEFAULT 0", []);
+
+    // 표시 방식/활성 여부 컬럼이 생기기 전에 만든 DB 보완 (이미 있으면 무시됨)
+    let _ = conn.execute("ALTER TABLE survey_templates ADD COLUMN display_mode TEXT DEFAULT 'one_by_one'", []);
+    let _ = conn.execute("ALTER TABLE survey_templates ADD COLUMN is_active INTEGER DEFAULT 1", []);
+
+    // 기본 제공 템플릿 표시 (실수로 삭제하지 않도록 확인 필요)
+    let _ = conn.execute("ALTER TABLE survey_templates ADD COLUMN is_builtin INTEGER NOT NULL DEFAULT 0", []);
+
+    // 제출 시 연락처 필수 여부 + 응답자가 입력한 연락처
+    let _ = conn.execute("ALTER TABLE survey_templates ADD COLUMN require_contact INTEGER NOT NULL DEFAULT 0", []);
+    let _ = conn.execute("ALTER TABLE survey_responses ADD COLUMN respondent_phone TEXT", []);
+    let _ = conn.execute("ALTER TABLE survey_responses ADD COLUMN respondent_birth_date TEXT", []);
+
+    // 익명 설문 (템플릿 설정 + 응답 저장 당시 익명 여부)
+    let _ = conn.execute("ALTER TABLE survey_templates ADD COLUMN anonymous INTEGER NOT NULL DEFAULT 0", []);
+    let _ = conn.execute("ALTER TABLE survey_responses ADD COLUMN anonymous INTEGER NOT NULL DEFAULT 0", []);
+
+    // 제출 완료 후 이동할 주소 (한의원 홈페이지/예약 페이지)
+    let _ = conn.execute("ALTER TABLE survey_templates ADD COLUMN redirect_url TEXT", []);
+
+    // 설문 템플릿 분류
+    let _ = conn.execute("ALTER TABLE survey_templates ADD COLUMN category TEXT", []);
+
+    // 문항/선택지 순서 무작위화 (템플릿 설정 + 세션별로 정해진 표시 순서 JSON)
+    let _ = conn.execute("ALTER TABLE survey_templates ADD COLUMN randomize_questions INTEGER NOT NULL DEFAULT 0", []);
+    let _ = conn.execute("ALTER TABLE survey_templates ADD COLUMN randomize_options INTEGER NOT NULL DEFAULT 0", []);
+    let _ = conn.execute("ALTER TABLE survey_templates ADD COLUMN pin_required_questions INTEGER NOT NULL DEFAULT 0", []);
+    let _ = conn.execute("ALTER TABLE survey_sessions ADD COLUMN display_order TEXT", []);
+
+    // 알림 다시 알림(스누즈) 시각 + 허용 스누즈 시간(분, JSON 배열)
+    let _ = conn.execute("ALTER TABLE notifications ADD COLUMN snoozed_until TEXT", []);
+    let _ = conn.execute("ALTER TABLE notification_settings ADD COLUMN snooze_minutes_options TEXT", []);
+    let _ = conn.execute("ALTER TABLE notification_settings ADD COLUMN scheduler_interval_secs INTEGER", []);
+    // 복약 알림 발송 채널 및 문자 발송 설정 (JSON)
+    let _ = conn.execute("ALTER TABLE notification_settings ADD COLUMN channel TEXT", []);
+    let _ = conn.execute("ALTER TABLE notification_settings ADD COLUMN sms_settings TEXT", []);
+
+    // 미완료 설문 리마인더 (만료 몇 시간 전에 보낼지, 세션별 발송 시각)
+    let _ = conn.execute("ALTER TABLE notification_settings ADD COLUMN survey_reminder_hours INTEGER", []);
+    let _ = conn.execute("ALTER TABLE survey_sessions ADD COLUMN reminder_sent_at TEXT", []);
+
+    // 오래된 기록 자동 정리 (보관 기간 JSON, 마지막 정리일)
+    let _ = conn.execute("ALTER TABLE notification_settings ADD COLUMN retention_settings TEXT", []);
+    let _ = conn.execute("ALTER TABLE notification_settings ADD COLUMN retention_last_run_on TEXT", []);
+
+    // DB 최적화 주간 자동 실행 (설정 JSON, 마지막 실행일)
+    let _ = conn.execute("ALTER TABLE notification_settings ADD COLUMN db_maintenance_settings TEXT", []);
+    let _ = conn.execute("ALTER TABLE notification_settings ADD COLUMN db_maintenance_last_run_on TEXT", []);
+
+    // 임시 비밀번호로 재설정된 직원 계정 (다음 로그인 시 변경 필요)
+    let _ = conn.execute("ALTER TABLE staff_accounts ADD COLUMN must_change_password INTEGER NOT NULL DEFAULT 0", []);
+
+    // 문서 서명 해시 형식 버전 (기존 서명은 모델 전체 JSON 해시 = 0)
+    let _ = conn.execute("ALTER TABLE document_signatures ADD COLUMN hash_version INTEGER NOT NULL DEFAULT 0", []);
+
+    // 권한 항목이 빠졌거나 읽을 수 없는 계정은 역할 기본 권한으로 (API 권한 검사 도입 전 계정)
+    migrate_staff_permissions(conn)?;
+
     // 약재 기본 데이터 삽입 (비어있을 때만)
     let herb_count: i32 = conn.query_row(
         "SELECT COUNT(*) FROM herbs",
@@ -809,17 +1185,70 @@ fn run_migrations(conn: &Connection) -> AppResult<()> {
 }
 
 /// DB 연결 가져오기
-fn get_conn() -> AppResult<std::sync::MutexGuard<'static, Connection>> {
-    DB_CONNECTION
+fn get_conn() -> AppResult<ConnGuard> {
+    let guard = DB_CONNECTION
         .get()
         .ok_or_else(|| AppError::Custom("Database not initialized".to_string()))?
         .lock()
-        .map_err(|_| AppError::Custom("Database lock error".to_string()))
+        .map_err(|_| AppError::Custom("Database lock error".to_string()))?;
+    Ok(ConnGuard { guard, acquired: std::time::Instant::now() })
+}
+
+/// DB 연결 잠금 (해제 시 점유 시간을 쿼리 지연 지표로 기록)
+struct ConnGuard {
+    guard: std::sync::MutexGuard<'static, Connection>,
+    acquired: std::time::Instant,
+}
+
+impl std::ops::Deref for ConnGuard {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        &self.guard
+    }
+}
+
+impl std::ops::DerefMut for ConnGuard {
+    fn deref_mut(&mut self) -> &mut Connection {
+        &mut self.guard
+    }
+}
+
+impl Drop for ConnGuard {
+    fn drop(&mut self) {
+        crate::metrics::observe_db_query(self.acquired.elapsed());
+    }
 }
 
 // ============ 한의원 설정 ============
 
+/// 한의원 로고 최대 크기 (base64 문자열 길이)
+pub const CLINIC_LOGO_MAX_BASE64_LEN: usize = 200 * 1024;
+
+/// 로고 검증 (base64로 인코딩된 PNG만 허용, 빈 문자열은 로고 없음)
+fn validate_clinic_logo(logo: Option<&str>) -> AppResult<()> {
+    use base64::Engine;
+
+    let Some(logo) = logo.filter(|l| !l.is_empty()) else {
+        return Ok(());
+    };
+    if logo.len() > CLINIC_LOGO_MAX_BASE64_LEN {
+        return Err(AppError::Validation(format!(
+            "로고 이미지가 너무 큽니다 (base64 기준 최대 {}KB)",
+            CLINIC_LOGO_MAX_BASE64_LEN / 1024
+        )));
+    }
+    let data = base64::engine::general_purpose::STANDARD
+        .decode(logo)
+        .map_err(|_| AppError::Validation("로고가 올바른 base64 형식이 아닙니다".to_string()))?;
+    if !data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Err(AppError::Validation("로고는 PNG 이미지만 사용할 수 있습니다".to_string()));
+    }
+    Ok(())
+}
+
 pub fn save_clinic_settings(settings: &ClinicSettings) -> AppResult<()> {
+    validate_clinic_logo(settings.clinic_logo.as_deref())?;
     ensure_db_initialized()?;
     let conn = get_conn()?;
 
@@ -831,6 +1260,21 @@ pub fn save_clinic_settings(settings: &ClinicSettings) -> AppResult<()> {
             |row| row.get(0),
         )
         .ok();
+    // 이메일 발송 설정도 별도 화면에서 저장하므로 보존
+    let existing_smtp_settings: Option<String> = conn
+        .query_row(
+            "SELECT smtp_settings FROM clinic_settings WHERE smtp_settings IS NOT NULL LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .ok();
+    let existing_tls_settings: Option<String> = conn
+        .query_row(
+            "SELECT server_tls_settings FROM clinic_settings WHERE server_tls_settings IS NOT NULL LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .ok();
 
     log::info!("save_clinic_settings: preserving password_hash = {:?}", existing_password_hash.is_some());
 
@@ -841,8 +1285,8 @@ pub fn save_clinic_settings(settings: &ClinicSettings) -> AppResult<()> {
     // 새 row 생성 (비밀번호 해시 보존)
     conn.execute(
         r#"INSERT INTO clinic_settings
-           (id, clinic_name, clinic_address, clinic_phone, doctor_name, license_number, staff_password_hash, created_at, updated_at)
-           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)"#,
+           (id, clinic_name, clinic_address, clinic_phone, doctor_name, license_number, clinic_logo, staff_password_hash, smtp_settings, server_tls_settings, created_at, updated_at)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)"#,
         params![
             settings.id,
             settings.clinic_name,
@@ -850,7 +1294,10 @@ pub fn save_clinic_settings(settings: &ClinicSettings) -> AppResult<()> {
             settings.clinic_phone,
             settings.doctor_name,
             settings.license_number,
+            settings.clinic_logo.as_deref().filter(|l| !l.is_empty()),
             existing_password_hash,
+            existing_smtp_settings,
+            existing_tls_settings,
             settings.created_at.to_rfc3339(),
             Utc::now().to_rfc3339(),
         ],
@@ -871,7 +1318,7 @@ pub fn get_clinic_settings() -> AppResult<Option<ClinicSettings>> {
     log::info!("get_clinic_settings: reading clinic_name = {:?}", debug_name);
 
     let mut stmt = conn.prepare(
-        "SELECT id, clinic_name, clinic_address, clinic_phone, doctor_name, license_number, created_at, updated_at
+        "SELECT id, clinic_name, clinic_address, clinic_phone, doctor_name, license_number, clinic_logo, created_at, updated_at
          FROM clinic_settings LIMIT 1",
     )?;
 
@@ -883,10 +1330,11 @@ pub fn get_clinic_settings() -> AppResult<Option<ClinicSettings>> {
             clinic_phone: row.get(3)?,
             doctor_name: row.get(4)?,
             license_number: row.get(5)?,
-            created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(6)?)
+            clinic_logo: row.get(6)?,
+            created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(7)?)
                 .unwrap()
                 .with_timezone(&Utc),
-            updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(7)?)
+            updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?)
                 .unwrap()
                 .with_timezone(&Utc),
         })
@@ -922,12 +1370,30 @@ pub fn debug_get_all_clinic_rows() -> AppResult<Vec<String>> {
 
 // ============ 환자 관리 ============
 
-pub fn create_patient(patient: &Patient) -> AppResult<()> {
+/// 환자 등록 (`allow_duplicate`가 false이면 이름/생년월일/연락처가 모두 같은 환자가 있을 때 `AppError::Conflict`)
+pub fn create_patient(patient: &Patient, allow_duplicate: bool) -> AppResult<()> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
+    insert_patient(&conn, patient, allow_duplicate)
+}
+
+/// 환자 중복 제약(idx_patients_identity) 위반을 Conflict로 변환
+fn map_patient_conflict(e: rusqlite::Error) -> AppError {
+    match &e {
+        rusqlite::Error::SqliteFailure(err, Some(msg))
+            if err.code == rusqlite::ErrorCode::ConstraintViolation && msg.contains("idx_patients_identity") =>
+        {
+            AppError::Conflict("이미 등록된 환자입니다 (이름, 생년월일, 연락처가 같은 환자가 있습니다)".to_string())
+        }
+        _ => e.into(),
+    }
+}
+
+/// 환자 추가 (연결/트랜잭션을 잡은 상태에서 호출)
+fn insert_patient(conn: &Connection, patient: &Patient, allow_duplicate: bool) -> AppResult<()> {
     conn.execute(
-        r#"INSERT INTO patients (id, name, chart_number, birth_date, gender, phone, address, notes, created_at, updated_at)
-           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"#,
+        r#"INSERT INTO patients (id, name, chart_number, birth_date, gender, phone, address, notes, created_at, updated_at, referral_source, allow_duplicate)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)"#,
         params![
             patient.id,
             patient.name,
@@ -939,8 +1405,14 @@ pub fn create_patient(patient: &Patient) -> AppResult<()> {
             patient.notes,
             patient.created_at.to_rfc3339(),
             patient.updated_at.to_rfc3339(),
+            normalize_referral_source(patient.referral_source.as_deref()),
+            allow_duplicate,
         ],
-    )?;
+    )
+    .map_err(map_patient_conflict)?;
+    if let Some(tags) = &patient.tags {
+        replace_entity_tags(conn, "patient", &patient.id, tags)?;
+    }
     Ok(())
 }
 
@@ -948,28 +1420,12 @@ pub fn get_patient(id: &str) -> AppResult<Option<Patient>> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
     let mut stmt = conn.prepare(
-        "SELECT id, name, chart_number, birth_date, gender, phone, address, notes, created_at, updated_at
+        "SELECT id, name, chart_number, birth_date, gender, phone, address, notes, created_at, updated_at, referral_source,
+                (SELECT group_concat(tag, ',') FROM tags WHERE entity_type = 'patient' AND entity_id = patients.id)
          FROM patients WHERE id = ?1",
     )?;
 
-    let result = stmt.query_row([id], |row| {
-        Ok(Patient {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            chart_number: row.get(2)?,
-            birth_date: row.get(3)?,
-            gender: row.get(4)?,
-            phone: row.get(5)?,
-            address: row.get(6)?,
-            notes: row.get(7)?,
-            created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?)
-                .unwrap()
-                .with_timezone(&Utc),
-            updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(9)?)
-                .unwrap()
-                .with_timezone(&Utc),
-        })
-    });
+    let result = stmt.query_row([id], map_patient_row);
 
     match result {
         Ok(patient) => Ok(Some(patient)),
@@ -978,38 +1434,160 @@ pub fn get_patient(id: &str) -> AppResult<Option<Patient>> {
     }
 }
 
-pub fn list_patients(search: Option<&str>) -> AppResult<Vec<Patient>> {
-    log::info!("[DB] list_patients 호출, search: {:?}", search);
+/// 환자 상세 (중요 정보 포함)
+pub fn get_patient_detail(id: &str) -> AppResult<Option<PatientDetail>> {
+    let Some(patient) = get_patient(id)? else {
+        return Ok(None);
+    };
+    let alerts = list_patient_alerts(id)?;
+    Ok(Some(PatientDetail { patient, alerts }))
+}
+
+pub fn list_patients(search: Option<&str>, tags: &[String]) -> AppResult<Vec<Patient>> {
+    Ok(list_patient_items(search, tags, true, false)?.into_iter().map(|item| item.patient).collect())
+}
+
+/// 환자 목록 (`match_all`이면 모든 태그가 달린 환자만, 아니면 하나라도 달린 환자,
+/// `include_stats`면 진료기록 기준 내원 횟수와 마지막 내원일 포함)
+pub fn list_patient_items(
+    search: Option<&str>,
+    tags: &[String],
+    match_all: bool,
+    include_stats: bool,
+) -> AppResult<Vec<PatientListItem>> {
+    log::info!("[DB] list_patients 호출, search: {:?}, tags: {:?}", search, tags);
     ensure_db_initialized()?;
+    let tags = dedup_tags(tags)?;
     let conn = get_conn()?;
     log::info!("[DB] list_patients: DB 연결 획득 성공");
 
-    let query = match search {
-        Some(_) => {
-            "SELECT id, name, chart_number, birth_date, gender, phone, address, notes, created_at, updated_at
-             FROM patients WHERE name LIKE ?1 AND deleted_at IS NULL ORDER BY name"
-        }
-        None => {
-            "SELECT id, name, chart_number, birth_date, gender, phone, address, notes, created_at, updated_at
-             FROM patients WHERE deleted_at IS NULL ORDER BY name"
-        }
-    };
-
-    let mut stmt = conn.prepare(query)?;
-    let rows = if let Some(s) = search {
-        stmt.query_map([format!("%{}%", s)], map_patient_row)?
-    } else {
-        stmt.query_map([], map_patient_row)?
-    };
+    let pattern = format!("%{}%", search.unwrap_or(""));
+    let mut values: Vec<&dyn rusqlite::ToSql> = vec![&pattern];
+    let mut sql = patient_list_select(include_stats);
+    sql.push_str(" WHERE name LIKE ?1 AND deleted_at IS NULL");
+    sql.push_str(&patient_tag_condition(&tags, match_all, &mut values));
+    sql.push_str(" ORDER BY name");
 
-    let mut patients = Vec::new();
-    for row in rows {
-        patients.push(row?);
-    }
+    let mut stmt = conn.prepare(&sql)?;
+    let patients = stmt
+        .query_map(values.as_slice(), |row| map_patient_list_row(row, include_stats))?
+        .collect::<Result<Vec<_>, _>>()?;
     log::info!("[DB] list_patients: 결과 {}명", patients.len());
     Ok(patients)
 }
 
+/// 환자 목록 페이지 조회 (이름 검색, 태그 필터, 이름순, `include_stats`면 내원 통계 포함)
+pub fn list_patients_page(
+    search: Option<&str>,
+    tags: &[String],
+    match_all: bool,
+    include_stats: bool,
+    limit: i64,
+    offset: i64,
+) -> AppResult<PaginatedResponse<PatientListItem>> {
+    ensure_db_initialized()?;
+    let tags = dedup_tags(tags)?;
+    let conn = get_conn()?;
+    let pattern = format!("%{}%", search.unwrap_or(""));
+
+    let mut values: Vec<&dyn rusqlite::ToSql> = vec![&pattern];
+    let condition = patient_tag_condition(&tags, match_all, &mut values);
+
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM patients WHERE name LIKE ?1 AND deleted_at IS NULL{}", condition),
+        values.as_slice(),
+        |row| row.get(0),
+    )?;
+
+    values.push(&limit);
+    let limit_idx = values.len();
+    values.push(&offset);
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE name LIKE ?1 AND deleted_at IS NULL{} ORDER BY name LIMIT ?{} OFFSET ?{}",
+        patient_list_select(include_stats),
+        condition,
+        limit_idx,
+        limit_idx + 1
+    ))?;
+    let patients = stmt
+        .query_map(values.as_slice(), |row| map_patient_list_row(row, include_stats))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(PaginatedResponse::new(patients, total, limit, offset))
+}
+
+/// 태그가 달린 환자 목록 (휴지통 제외, 이름순)
+pub fn search_patients_by_tag(tag: &str) -> AppResult<Vec<Patient>> {
+    list_patients(None, &[tag.to_string()])
+}
+
+/// 환자에게 달린 태그 목록 (중복 제거, 가나다순)
+pub fn list_patient_tags() -> AppResult<Vec<String>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT tag FROM tags
+         WHERE entity_type = 'patient' AND entity_id IN (SELECT id FROM patients WHERE deleted_at IS NULL)
+         GROUP BY tag ORDER BY tag",
+    )?;
+    let tags = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+    Ok(tags)
+}
+
+/// 태그 필터 조건 (`match_all`이면 모든 태그, 아니면 하나라도 달린 환자, `values`에 태그 바인딩 추가)
+fn patient_tag_condition<'a>(tags: &'a [String], match_all: bool, values: &mut Vec<&'a dyn rusqlite::ToSql>) -> String {
+    if tags.is_empty() {
+        return String::new();
+    }
+    let placeholders = tags
+        .iter()
+        .map(|t| {
+            values.push(t);
+            format!("?{}", values.len())
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    if !match_all {
+        return format!(" AND id IN (SELECT entity_id FROM tags WHERE entity_type = 'patient' AND tag IN ({}))", placeholders);
+    }
+    format!(
+        " AND id IN (SELECT entity_id FROM tags WHERE entity_type = 'patient' AND tag IN ({})
+           GROUP BY entity_id HAVING COUNT(DISTINCT tag) = {})",
+        placeholders,
+        tags.len()
+    )
+}
+
+/// 환자 목록 SELECT ~ FROM 부분 (내원 통계는 진료기록을 환자별로 미리 집계해 LEFT JOIN)
+fn patient_list_select(include_stats: bool) -> String {
+    let mut sql = "SELECT id, name, chart_number, birth_date, gender, phone, address, notes, created_at, updated_at, referral_source,
+                (SELECT group_concat(tag, ',') FROM tags WHERE entity_type = 'patient' AND entity_id = patients.id)"
+        .to_string();
+    if include_stats {
+        sql.push_str(
+            ", COALESCE(visits.visit_count, 0), visits.last_visit_date
+         FROM patients
+         LEFT JOIN (SELECT patient_id, COUNT(*) AS visit_count, MAX(visit_date) AS last_visit_date
+                    FROM chart_records WHERE is_deleted = 0 GROUP BY patient_id) visits
+           ON visits.patient_id = patients.id",
+        );
+    } else {
+        sql.push_str(" FROM patients");
+    }
+    sql
+}
+
+fn map_patient_list_row(row: &rusqlite::Row, include_stats: bool) -> rusqlite::Result<PatientListItem> {
+    let stats = if include_stats {
+        Some(PatientVisitStats { visit_count: row.get(12)?, last_visit_date: row.get(13)? })
+    } else {
+        None
+    };
+    Ok(PatientListItem { patient: map_patient_row(row)?, stats })
+}
+
 fn map_patient_row(row: &rusqlite::Row) -> rusqlite::Result<Patient> {
     Ok(Patient {
         id: row.get(0)?,
@@ -1026,15 +1604,22 @@ fn map_patient_row(row: &rusqlite::Row) -> rusqlite::Result<Patient> {
         updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(9)?)
             .unwrap()
             .with_timezone(&Utc),
+        referral_source: row.get(10)?,
+        tags: Some(split_tag_list(row.get::<_, Option<String>>(11)?)),
     })
 }
 
 pub fn update_patient(patient: &Patient) -> AppResult<()> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
+    update_patient_row(&conn, patient)
+}
+
+/// 환자 정보 덮어쓰기 (연결/트랜잭션을 잡은 상태에서 호출)
+fn update_patient_row(conn: &Connection, patient: &Patient) -> AppResult<()> {
     conn.execute(
         r#"UPDATE patients SET name = ?2, chart_number = ?3, birth_date = ?4, gender = ?5, phone = ?6,
-           address = ?7, notes = ?8, updated_at = ?9 WHERE id = ?1"#,
+           address = ?7, notes = ?8, updated_at = ?9, referral_source = ?10 WHERE id = ?1"#,
         params![
             patient.id,
             patient.name,
@@ -1045,15 +1630,53 @@ pub fn update_patient(patient: &Patient) -> AppResult<()> {
             patient.address,
             patient.notes,
             Utc::now().to_rfc3339(),
+            normalize_referral_source(patient.referral_source.as_deref()),
         ],
-    )?;
+    )
+    .map_err(map_patient_conflict)?;
+    // tags가 None이면 기존 태그 유지
+    if let Some(tags) = &patient.tags {
+        replace_entity_tags(conn, "patient", &patient.id, tags)?;
+    }
     Ok(())
 }
 
+/// 환자 정보 일부 수정 (지정한 필드만 변경, 없는 환자면 None)
+pub fn patch_patient(id: &str, patch: &PatientPatch) -> AppResult<Option<Patient>> {
+    if patch.name.as_deref().is_some_and(|name| name.trim().is_empty()) {
+        return Err(AppError::Validation("환자 이름은 비워 둘 수 없습니다".to_string()));
+    }
+
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let tx = conn.unchecked_transaction()?;
+
+    let current = tx.query_row(
+        "SELECT id, name, chart_number, birth_date, gender, phone, address, notes, created_at, updated_at, referral_source,
+                (SELECT group_concat(tag, ',') FROM tags WHERE entity_type = 'patient' AND entity_id = patients.id)
+         FROM patients WHERE id = ?1",
+        [id],
+        map_patient_row,
+    );
+    let current = match current {
+        Ok(patient) => patient,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let existing_tags = current.tags.clone();
+
+    let patched = patch.apply(current);
+    update_patient_row(&tx, &patched)?;
+    tx.commit()?;
+
+    Ok(Some(Patient { tags: patched.tags.clone().or(existing_tags), ..patched }))
+}
+
 pub fn delete_patient(id: &str) -> AppResult<()> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
     conn.execute("DELETE FROM patients WHERE id = ?1", [id])?;
+    delete_entity_tags(&conn, "patient", id)?;
     Ok(())
 }
 
@@ -1062,6 +1685,11 @@ pub fn delete_patient(id: &str) -> AppResult<()> {
 pub fn create_prescription(prescription: &Prescription) -> AppResult<()> {
     log::info!("[DB] create_prescription 호출: id={}, formula={}", prescription.id, prescription.formula);
     let conn = get_conn()?;
+    insert_prescription(&conn, prescription)
+}
+
+/// 처방 추가 (연결/트랜잭션을 잡은 상태에서 호출)
+fn insert_prescription(conn: &Connection, prescription: &Prescription) -> AppResult<()> {
     conn.execute(
         r#"INSERT INTO prescriptions (
             id, patient_id, patient_name, prescription_name, chart_number,
@@ -1069,8 +1697,8 @@ pub fn create_prescription(prescription: &Prescription) -> AppResult<()> {
             formula, merged_herbs, final_herbs, total_doses, days, doses_per_day,
             total_packs, pack_volume, water_amount, herb_adjustment, total_dosage,
             final_total_amount, notes, status, issued_at, created_by, deleted_at,
-            created_at, updated_at
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28)"#,
+            created_at, updated_at, price, insurance_type
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30)"#,
         params![
             prescription.id,
             prescription.patient_id,
@@ -1100,6 +1728,8 @@ pub fn create_prescription(prescription: &Prescription) -> AppResult<()> {
             prescription.deleted_at,
             prescription.created_at,
             prescription.updated_at,
+            prescription.price,
+            prescription.insurance_type,
         ],
     )?;
     Ok(())
@@ -1129,6 +1759,8 @@ fn row_to_prescription(row: &rusqlite::Row) -> rusqlite::Result<Prescription> {
         total_dosage: row.get("total_dosage")?,
         final_total_amount: row.get("final_total_amount")?,
         notes: row.get("notes")?,
+        price: row.get("price")?,
+        insurance_type: row.get("insurance_type")?,
         status: row.get("status")?,
         issued_at: row.get("issued_at")?,
         created_by: row.get("created_by")?,
@@ -1170,14 +1802,71 @@ pub fn list_all_prescriptions() -> AppResult<Vec<Prescription>> {
     Ok(prescriptions)
 }
 
+/// 처방 목록 페이지 조회 (환자 지정 시 해당 환자만, 최신순)
+/// 특정 배치 번호의 약재를 쓴 처방 목록 (약재 회수 추적용, 최신순)
+pub fn list_prescriptions_by_herb_batch(batch_number: &str) -> AppResult<Vec<Prescription>> {
+    let batch_number = batch_number.trim();
+    if batch_number.is_empty() {
+        return Err(AppError::Validation("배치 번호를 입력해주세요".to_string()));
+    }
+
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    // JSON 문자열에 배치 번호가 들어 있는 처방만 먼저 추린 뒤 약재 항목 단위로 확인
+    let pattern = format!("%{}%", batch_number.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+    let mut stmt = conn.prepare(
+        r"SELECT * FROM prescriptions
+          WHERE deleted_at IS NULL AND (final_herbs LIKE ?1 ESCAPE '\' OR merged_herbs LIKE ?1 ESCAPE '\')
+          ORDER BY created_at DESC",
+    )?;
+    let candidates = stmt.query_map([pattern], row_to_prescription)?.collect::<Result<Vec<_>, _>>()?;
+
+    let uses_batch = |herbs_json: &str| {
+        serde_json::from_str::<Vec<HerbItem>>(herbs_json)
+            .map(|herbs| herbs.iter().any(|h| h.batch_number.as_deref().map(str::trim) == Some(batch_number)))
+            .unwrap_or(false)
+    };
+    Ok(candidates
+        .into_iter()
+        .filter(|p| uses_batch(&p.final_herbs) || uses_batch(&p.merged_herbs))
+        .collect())
+}
+
+pub fn list_prescriptions_page(patient_id: Option<&str>, limit: i64, offset: i64) -> AppResult<PaginatedResponse<Prescription>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let total: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM prescriptions WHERE deleted_at IS NULL AND (?1 IS NULL OR patient_id = ?1)",
+        [patient_id],
+        |row| row.get(0),
+    )?;
+
+    let mut stmt = conn.prepare(
+        "SELECT * FROM prescriptions WHERE deleted_at IS NULL AND (?1 IS NULL OR patient_id = ?1)
+         ORDER BY created_at DESC LIMIT ?2 OFFSET ?3",
+    )?;
+    let prescriptions = stmt
+        .query_map(params![patient_id, limit, offset], row_to_prescription)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(PaginatedResponse::new(prescriptions, total, limit, offset))
+}
+
 pub fn clear_all_prescriptions() -> AppResult<()> {
     let conn = get_conn()?;
     conn.execute("DELETE FROM prescriptions", [])?;
+    conn.execute("DELETE FROM tags WHERE entity_type = 'prescription'", [])?;
     Ok(())
 }
 
 pub fn update_prescription(prescription: &Prescription) -> AppResult<()> {
     let conn = get_conn()?;
+    update_prescription_row(&conn, prescription)
+}
+
+/// 처방 덮어쓰기 (연결/트랜잭션을 잡은 상태에서 호출)
+fn update_prescription_row(conn: &Connection, prescription: &Prescription) -> AppResult<()> {
     conn.execute(
         r#"UPDATE prescriptions SET
             patient_id = ?1, patient_name = ?2, prescription_name = ?3, chart_number = ?4,
@@ -1186,8 +1875,8 @@ pub fn update_prescription(prescription: &Prescription) -> AppResult<()> {
             days = ?13, doses_per_day = ?14, total_packs = ?15, pack_volume = ?16,
             water_amount = ?17, herb_adjustment = ?18, total_dosage = ?19,
             final_total_amount = ?20, notes = ?21, status = ?22, issued_at = ?23,
-            created_by = ?24, updated_at = ?25
-        WHERE id = ?26"#,
+            created_by = ?24, updated_at = ?25, price = ?26, insurance_type = ?27
+        WHERE id = ?28"#,
         params![
             prescription.patient_id,
             prescription.patient_name,
@@ -1214,6 +1903,8 @@ pub fn update_prescription(prescription: &Prescription) -> AppResult<()> {
             prescription.issued_at,
             prescription.created_by,
             prescription.updated_at,
+            prescription.price,
+            prescription.insurance_type,
             prescription.id,
         ],
     )?;
@@ -1230,11 +1921,96 @@ pub fn soft_delete_prescription(id: &str) -> AppResult<()> {
     Ok(())
 }
 
-// ============ 차팅 관리 ============
-
-pub fn create_chart_record(record: &ChartRecord) -> AppResult<()> {
+/// 처방 단건 조회 (삭제되지 않은 것만)
+pub fn get_prescription(id: &str) -> AppResult<Option<Prescription>> {
     let conn = get_conn()?;
-    conn.execute(
+    query_prescription(&conn, id)
+}
+
+/// 처방 단건 조회 (연결/트랜잭션을 잡은 상태에서 호출)
+fn query_prescription(conn: &Connection, id: &str) -> AppResult<Option<Prescription>> {
+    let result = conn.query_row(
+        "SELECT * FROM prescriptions WHERE id = ?1 AND deleted_at IS NULL",
+        [id],
+        |row| row_to_prescription(row),
+    );
+
+    match result {
+        Ok(prescription) => Ok(Some(prescription)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 처방 영구 삭제
+/// 복약 일정/복약 관리가 참조 중이면 거부하고, cascade가 true이면 함께 삭제
+pub fn delete_prescription(id: &str, cascade: bool) -> AppResult<()> {
+    let conn = get_conn()?;
+    // 복약 기록/일정 정리와 처방 삭제를 한 번에 반영 (중간에 실패하면 모두 취소)
+    let tx = conn.unchecked_transaction()?;
+
+    let schedule_count: i32 = tx.query_row(
+        "SELECT COUNT(*) FROM medication_schedules WHERE prescription_id = ?1",
+        [id],
+        |row| row.get(0),
+    )?;
+    let management_count: i32 = tx.query_row(
+        "SELECT COUNT(*) FROM medication_management WHERE prescription_id = ?1",
+        [id],
+        |row| row.get(0),
+    )?;
+
+    if !cascade && (schedule_count > 0 || management_count > 0) {
+        return Err(AppError::Custom(format!(
+            "복약 일정 {}건, 복약 관리 {}건이 이 처방을 참조하고 있어 삭제할 수 없습니다",
+            schedule_count, management_count
+        )));
+    }
+
+    if cascade {
+        tx.execute(
+            "DELETE FROM medication_logs WHERE schedule_id IN (SELECT id FROM medication_schedules WHERE prescription_id = ?1)",
+            [id],
+        )?;
+        tx.execute("DELETE FROM medication_schedules WHERE prescription_id = ?1", [id])?;
+        tx.execute("DELETE FROM medication_management WHERE prescription_id = ?1", [id])?;
+    }
+
+    // 차팅 기록은 남기고 처방 연결만 해제
+    tx.execute(
+        "UPDATE chart_records SET prescription_id = NULL WHERE prescription_id = ?1",
+        [id],
+    )?;
+    tx.execute("DELETE FROM prescriptions WHERE id = ?1", [id])?;
+    delete_entity_tags(&tx, "prescription", id)?;
+    tx.commit()?;
+
+    log::info!("처방 삭제됨: {} (cascade: {})", id, cascade);
+    Ok(())
+}
+
+// ============ 차팅 관리 ============
+
+/// 차팅 저장 시 약어 확장 방식
+#[derive(Debug, Clone, Copy)]
+pub enum MacroExpansion<'a> {
+    /// 입력 그대로 저장
+    Off,
+    /// 전역 약어만 (데스크톱 앱)
+    Global,
+    /// 전역 + 해당 직원 계정의 약어 (같은 약어는 직원 것이 우선)
+    Staff(&'a str),
+}
+
+pub fn create_chart_record(record: &ChartRecord, macros: MacroExpansion) -> AppResult<()> {
+    let conn = get_conn()?;
+    let record = expand_chart_record_macros(&conn, record, macros)?;
+    insert_chart_record(&conn, &record)
+}
+
+/// 차팅 기록 추가 (연결/트랜잭션을 잡은 상태에서 호출)
+fn insert_chart_record(conn: &Connection, record: &ChartRecord) -> AppResult<()> {
+    conn.execute(
         r#"INSERT INTO chart_records (id, patient_id, visit_date, chief_complaint, symptoms, diagnosis, treatment, prescription_id, notes, created_at, updated_at)
            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)"#,
         params![
@@ -1254,34 +2030,36 @@ pub fn create_chart_record(record: &ChartRecord) -> AppResult<()> {
     Ok(())
 }
 
+fn row_to_chart_record(row: &rusqlite::Row) -> rusqlite::Result<ChartRecord> {
+    Ok(ChartRecord {
+        id: row.get(0)?,
+        patient_id: row.get(1)?,
+        visit_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(2)?)
+            .unwrap()
+            .with_timezone(&Utc),
+        chief_complaint: row.get(3)?,
+        symptoms: row.get(4)?,
+        diagnosis: row.get(5)?,
+        treatment: row.get(6)?,
+        prescription_id: row.get(7)?,
+        notes: row.get(8)?,
+        created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(9)?)
+            .unwrap()
+            .with_timezone(&Utc),
+        updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(10)?)
+            .unwrap()
+            .with_timezone(&Utc),
+    })
+}
+
 pub fn get_chart_records_by_patient(patient_id: &str) -> AppResult<Vec<ChartRecord>> {
     let conn = get_conn()?;
     let mut stmt = conn.prepare(
         "SELECT id, patient_id, visit_date, chief_complaint, symptoms, diagnosis, treatment, prescription_id, notes, created_at, updated_at
-         FROM chart_records WHERE patient_id = ?1 ORDER BY visit_date DESC",
+         FROM chart_records WHERE patient_id = ?1 AND is_deleted = 0 ORDER BY visit_date DESC",
     )?;
 
-    let rows = stmt.query_map([patient_id], |row| {
-        Ok(ChartRecord {
-            id: row.get(0)?,
-            patient_id: row.get(1)?,
-            visit_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(2)?)
-                .unwrap()
-                .with_timezone(&Utc),
-            chief_complaint: row.get(3)?,
-            symptoms: row.get(4)?,
-            diagnosis: row.get(5)?,
-            treatment: row.get(6)?,
-            prescription_id: row.get(7)?,
-            notes: row.get(8)?,
-            created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(9)?)
-                .unwrap()
-                .with_timezone(&Utc),
-            updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(10)?)
-                .unwrap()
-                .with_timezone(&Utc),
-        })
-    })?;
+    let rows = stmt.query_map([patient_id], |row| row_to_chart_record(row))?;
 
     let mut records = Vec::new();
     for row in rows {
@@ -1290,3368 +2068,11099 @@ pub fn get_chart_records_by_patient(patient_id: &str) -> AppResult<Vec<ChartReco
     Ok(records)
 }
 
-// ============ 데이터 내보내기 ============
+/// 차팅 기록 단건 조회 (삭제된 기록 제외)
+pub fn get_chart_record(id: &str) -> AppResult<Option<ChartRecord>> {
+    let conn = get_conn()?;
+    query_chart_record(&conn, id)
+}
 
-pub fn export_patient_data(patient_id: &str) -> AppResult<String> {
-    let patient = get_patient(patient_id)?
-        .ok_or_else(|| AppError::Custom("Patient not found".to_string()))?;
-    let prescriptions = get_prescriptions_by_patient(patient_id)?;
-    let chart_records = get_chart_records_by_patient(patient_id)?;
+/// 차팅 기록 단건 조회 (연결/트랜잭션을 잡은 상태에서 호출)
+fn query_chart_record(conn: &Connection, id: &str) -> AppResult<Option<ChartRecord>> {
+    let result = conn.query_row(
+        "SELECT id, patient_id, visit_date, chief_complaint, symptoms, diagnosis, treatment, prescription_id, notes, created_at, updated_at
+         FROM chart_records WHERE id = ?1 AND is_deleted = 0",
+        [id],
+        |row| row_to_chart_record(row),
+    );
 
-    let export_data = serde_json::json!({
-        "patient": patient,
-        "prescriptions": prescriptions,
-        "chart_records": chart_records,
-        "exported_at": Utc::now().to_rfc3339(),
-    });
+    match result {
+        Ok(record) => Ok(Some(record)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
 
-    Ok(serde_json::to_string_pretty(&export_data)?)
+/// 차팅 기록 수정 (이전 내용은 변경 이력에 남김)
+pub fn update_chart_record(record: &ChartRecord, changed_by: Option<&str>, macros: MacroExpansion) -> AppResult<ChartRecord> {
+    let conn = get_conn()?;
+    // 기록 수정과 변경 이력을 한 번에 반영 (이력 없이 수정만 남지 않도록)
+    let tx = conn.unchecked_transaction()?;
+    let previous = query_chart_record(&tx, &record.id)?
+        .ok_or_else(|| AppError::Custom("차팅 기록을 찾을 수 없습니다".to_string()))?;
+
+    let record = expand_chart_record_macros(&tx, record, macros)?;
+    update_chart_record_row(&tx, &record)?;
+
+    insert_audit_log(
+        &tx,
+        "chart_record",
+        &record.id,
+        "update",
+        Some(&serde_json::to_string(&previous)?),
+        Some(&serde_json::to_string(&record)?),
+        changed_by,
+    )?;
+    tx.commit()?;
+    Ok(record)
 }
 
-pub fn export_all_data() -> AppResult<String> {
-    let patients = list_patients(None)?;
-    let settings = get_clinic_settings()?;
+/// 차팅 기록 내용 덮어쓰기 (연결/트랜잭션을 잡은 상태에서 호출, 변경 이력은 호출하는 쪽에서 기록)
+fn update_chart_record_row(conn: &Connection, record: &ChartRecord) -> AppResult<()> {
+    conn.execute(
+        r#"UPDATE chart_records SET
+            visit_date = ?1, chief_complaint = ?2, symptoms = ?3, diagnosis = ?4,
+            treatment = ?5, prescription_id = ?6, notes = ?7, updated_at = ?8
+        WHERE id = ?9 AND is_deleted = 0"#,
+        params![
+            record.visit_date.to_rfc3339(),
+            record.chief_complaint,
+            record.symptoms,
+            record.diagnosis,
+            record.treatment,
+            record.prescription_id,
+            record.notes,
+            record.updated_at.to_rfc3339(),
+            record.id,
+        ],
+    )?;
+    Ok(())
+}
 
-    let mut all_data = Vec::new();
-    for patient in &patients {
-        let prescriptions = get_prescriptions_by_patient(&patient.id)?;
-        let chart_records = get_chart_records_by_patient(&patient.id)?;
-        all_data.push(serde_json::json!({
-            "patient": patient,
-            "prescriptions": prescriptions,
-            "chart_records": chart_records,
-        }));
-    }
+/// 차팅 기록 삭제 (법적 기록이므로 소프트 삭제만 지원)
+pub fn delete_chart_record(id: &str, changed_by: Option<&str>) -> AppResult<()> {
+    let conn = get_conn()?;
+    // 삭제 표시와 변경 이력을 한 번에 반영 (이력 없이 삭제만 남지 않도록)
+    let tx = conn.unchecked_transaction()?;
+    let previous = query_chart_record(&tx, id)?
+        .ok_or_else(|| AppError::Custom("차팅 기록을 찾을 수 없습니다".to_string()))?;
 
-    let export_data = serde_json::json!({
-        "clinic_settings": settings,
-        "patients_data": all_data,
-        "exported_at": Utc::now().to_rfc3339(),
-    });
+    tx.execute(
+        "UPDATE chart_records SET is_deleted = 1, updated_at = ?1 WHERE id = ?2",
+        params![Utc::now().to_rfc3339(), id],
+    )?;
 
-    Ok(serde_json::to_string_pretty(&export_data)?)
+    insert_audit_log(
+        &tx,
+        "chart_record",
+        id,
+        "delete",
+        Some(&serde_json::to_string(&previous)?),
+        None,
+        changed_by,
+    )?;
+    tx.commit()?;
+    Ok(())
 }
 
-// ============ 설문 세션 관리 (HTTP 서버용) ============
+// ============ 환자 중요 정보 ============
 
-/// 설문 세션 정보 (환자명 포함, 프론트엔드용)
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct SurveySessionWithPatient {
-    pub id: String,
-    pub token: String,
-    pub patient_id: Option<String>,
-    pub template_id: String,
-    pub respondent_name: Option<String>,
-    pub status: String,
-    pub expires_at: String,
-    pub created_by: Option<String>,
-    pub created_at: String,
-    pub completed_at: Option<String>,
-    pub patient_name: Option<String>,
-    pub chart_number: Option<String>,
-    pub patient_age: Option<String>,
-    pub patient_gender: Option<String>,
-}
+const PATIENT_ALERT_COLUMNS: &str = "id, patient_id, alert_type, severity, content, created_at, updated_at";
 
-/// 설문 세션 정보 (DB용)
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct SurveySessionDb {
-    pub id: String,
-    pub token: String,
-    pub patient_id: Option<String>,
-    pub template_id: String,
-    pub respondent_name: Option<String>,
-    pub patient_name: Option<String>,
-    pub chart_number: Option<String>,
-    pub patient_age: Option<String>,
-    pub patient_gender: Option<String>,
-    pub status: SessionStatus,
-    pub expires_at: String,
-    pub created_at: String,
+fn map_patient_alert_row(row: &rusqlite::Row) -> rusqlite::Result<PatientAlert> {
+    Ok(PatientAlert {
+        id: row.get(0)?,
+        patient_id: row.get(1)?,
+        alert_type: PatientAlertType::from_str(&row.get::<_, String>(2)?),
+        severity: PatientAlertSeverity::from_str(&row.get::<_, String>(3)?),
+        content: row.get(4)?,
+        created_at: row.get::<_, String>(5)?.parse().unwrap_or_else(|_| Utc::now()),
+        updated_at: row.get::<_, String>(6)?.parse().unwrap_or_else(|_| Utc::now()),
+    })
 }
 
-/// 설문 템플릿 정보 (DB용)
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct SurveyTemplateDb {
-    pub id: String,
-    pub name: String,
-    pub description: Option<String>,
-    pub questions: Vec<SurveyQuestion>,
-    pub display_mode: Option<String>,
-    pub is_active: bool,
+/// 환자의 중요 정보 목록 (심각도 → 종류 → 등록순)
+pub fn list_patient_alerts(patient_id: &str) -> AppResult<Vec<PatientAlert>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM patient_alerts WHERE patient_id = ?1
+         ORDER BY CASE severity WHEN 'high' THEN 0 WHEN 'medium' THEN 1 ELSE 2 END,
+                  CASE alert_type WHEN 'allergy' THEN 0 WHEN 'caution_medication' THEN 1 WHEN 'chronic_disease' THEN 2 ELSE 3 END,
+                  created_at",
+        PATIENT_ALERT_COLUMNS
+    ))?;
+    let alerts = stmt.query_map([patient_id], map_patient_alert_row)?;
+    Ok(alerts.collect::<Result<Vec<_>, _>>()?)
 }
 
-/// 설문 응답 정보 (DB용)
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct SurveyResponseDb {
-    pub id: String,
-    pub session_id: Option<String>,
-    pub template_id: String,
-    pub patient_id: Option<String>,
-    pub respondent_name: Option<String>,
-    pub answers: String,
-    pub submitted_at: String,
+/// 중요 정보 조회
+pub fn get_patient_alert(id: &str) -> AppResult<Option<PatientAlert>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let found = conn
+        .query_row(
+            &format!("SELECT {} FROM patient_alerts WHERE id = ?1", PATIENT_ALERT_COLUMNS),
+            [id],
+            map_patient_alert_row,
+        )
+        .ok();
+    Ok(found)
 }
 
-use crate::models::{SessionStatus, SurveyAnswer, SurveyQuestion};
+/// 중요 정보 저장 (같은 ID면 수정, 환자가 없으면 None)
+pub fn save_patient_alert(alert: &PatientAlert) -> AppResult<Option<PatientAlert>> {
+    let content = alert.content.trim();
+    if content.is_empty() {
+        return Err(AppError::Validation("중요 정보 내용을 입력해주세요".to_string()));
+    }
+    let saved = PatientAlert { content: content.to_string(), updated_at: Utc::now(), ..alert.clone() };
 
-/// 토큰으로 설문 세션 조회
-pub fn get_survey_session_by_token(token: &str) -> AppResult<Option<SurveySessionDb>> {
+    ensure_db_initialized()?;
     let conn = get_conn()?;
-    let mut stmt = conn.prepare(
-        "SELECT id, token, patient_id, template_id, respondent_name, status, expires_at, created_at, patient_name, chart_number, patient_age, patient_gender
-         FROM survey_sessions WHERE token = ?1",
+    let patient_exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM patients WHERE id = ?1 AND deleted_at IS NULL)",
+        [&saved.patient_id],
+        |row| row.get(0),
     )?;
-
-    let result = stmt.query_row([token], |row| {
-        let status_str: String = row.get(5)?;
-        let status = match status_str.as_str() {
-            "completed" => SessionStatus::Completed,
-            "expired" => SessionStatus::Expired,
-            _ => SessionStatus::Pending,
-        };
-        Ok(SurveySessionDb {
-            id: row.get(0)?,
-            token: row.get(1)?,
-            patient_id: row.get(2)?,
-            template_id: row.get(3)?,
-            respondent_name: row.get(4)?,
-            patient_name: row.get(8)?,
-            chart_number: row.get(9)?,
-            patient_age: row.get(10)?,
-            patient_gender: row.get(11)?,
-            status,
-            expires_at: row.get(6)?,
-            created_at: row.get(7)?,
-        })
-    });
-
-    match result {
-        Ok(session) => {
-            // 만료 확인
-            if session.status == SessionStatus::Pending {
-                if let Ok(expires) = chrono::DateTime::parse_from_rfc3339(&session.expires_at) {
-                    if expires < Utc::now() {
-                        // 만료 처리
-                        conn.execute(
-                            "UPDATE survey_sessions SET status = 'expired' WHERE id = ?1",
-                            [&session.id],
-                        )?;
-                        return Ok(Some(SurveySessionDb {
-                            status: SessionStatus::Expired,
-                            ..session
-                        }));
-                    }
-                }
-            }
-            Ok(Some(session))
-        }
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(e.into()),
+    if !patient_exists {
+        return Ok(None);
     }
+    conn.execute(
+        r#"INSERT INTO patient_alerts (id, patient_id, alert_type, severity, content, created_at, updated_at)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+           ON CONFLICT(id) DO UPDATE SET alert_type = excluded.alert_type, severity = excluded.severity,
+               content = excluded.content, updated_at = excluded.updated_at"#,
+        params![
+            saved.id,
+            saved.patient_id,
+            saved.alert_type.as_str(),
+            saved.severity.as_str(),
+            saved.content,
+            saved.created_at.to_rfc3339(),
+            saved.updated_at.to_rfc3339(),
+        ],
+    )?;
+    Ok(Some(saved))
 }
 
-/// 설문 템플릿 저장
-pub fn save_survey_template(template: &SurveyTemplateDb) -> AppResult<()> {
+/// 중요 정보 삭제 (반환: 삭제 여부)
+pub fn delete_patient_alert(id: &str) -> AppResult<bool> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    let questions_json = serde_json::to_string(&template.questions)?;
-    let now = Utc::now().to_rfc3339();
+    Ok(conn.execute("DELETE FROM patient_alerts WHERE id = ?1", [id])? > 0)
+}
 
-    conn.execute(
-        r#"INSERT OR REPLACE INTO survey_templates (id, name, description, questions, display_mode, is_active, created_at, updated_at)
-           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"#,
-        params![
-            template.id,
-            template.name,
-            template.description,
-            questions_json,
-            template.display_mode,
-            if template.is_active { 1 } else { 0 },
-            now,
-            now,
-        ],
-    )?;
-    Ok(())
-}
+// ============ 약어(텍스트 매크로) ============
 
-/// 설문 템플릿 조회
-pub fn get_survey_template(id: &str) -> AppResult<Option<SurveyTemplateDb>> {
+/// 약어 목록 (전역 + `owner_id` 직원의 약어, 약어 순)
+pub fn list_text_macros(owner_id: Option<&str>) -> AppResult<Vec<TextMacro>> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
+    query_text_macros(&conn, owner_id)
+}
+
+fn query_text_macros(conn: &Connection, owner_id: Option<&str>) -> AppResult<Vec<TextMacro>> {
     let mut stmt = conn.prepare(
-        "SELECT id, name, description, questions, display_mode, is_active
-         FROM survey_templates WHERE id = ?1",
+        "SELECT id, abbreviation, expansion, owner_id, created_at, updated_at
+         FROM text_macros WHERE owner_id IS NULL OR owner_id = ?1
+         ORDER BY abbreviation, owner_id IS NULL",
     )?;
-
-    let result = stmt.query_row([id], |row| {
-        let questions_json: String = row.get(3)?;
-        let questions: Vec<SurveyQuestion> = serde_json::from_str(&questions_json).unwrap_or_default();
-        let is_active: i32 = row.get(5)?;
-        Ok(SurveyTemplateDb {
+    let rows = stmt.query_map([owner_id], |row| {
+        Ok(TextMacro {
             id: row.get(0)?,
-            name: row.get(1)?,
-            description: row.get(2)?,
-            questions,
-            display_mode: row.get(4)?,
-            is_active: is_active != 0,
+            abbreviation: row.get(1)?,
+            expansion: row.get(2)?,
+            owner_id: row.get(3)?,
+            created_at: row.get::<_, String>(4)?.parse().unwrap_or_else(|_| Utc::now()),
+            updated_at: row.get::<_, String>(5)?.parse().unwrap_or_else(|_| Utc::now()),
         })
-    });
+    })?;
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
 
-    match result {
-        Ok(template) => Ok(Some(template)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(e.into()),
+/// 약어 조회
+pub fn get_text_macro(id: &str) -> AppResult<Option<TextMacro>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let found = conn
+        .query_row(
+            "SELECT id, abbreviation, expansion, owner_id, created_at, updated_at FROM text_macros WHERE id = ?1",
+            [id],
+            |row| {
+                Ok(TextMacro {
+                    id: row.get(0)?,
+                    abbreviation: row.get(1)?,
+                    expansion: row.get(2)?,
+                    owner_id: row.get(3)?,
+                    created_at: row.get::<_, String>(4)?.parse().unwrap_or_else(|_| Utc::now()),
+                    updated_at: row.get::<_, String>(5)?.parse().unwrap_or_else(|_| Utc::now()),
+                })
+            },
+        )
+        .ok();
+    Ok(found)
+}
+
+/// 약어 저장 (같은 ID면 수정, 같은 범위에 같은 약어가 있으면 `AppError::Conflict`)
+pub fn save_text_macro(text_macro: &TextMacro) -> AppResult<TextMacro> {
+    let abbreviation = text_macro.abbreviation.trim();
+    if abbreviation.is_empty() || text_macro.expansion.is_empty() {
+        return Err(AppError::Validation("약어와 바꿀 내용을 입력해주세요".to_string()));
+    }
+    if abbreviation.chars().any(char::is_whitespace) {
+        return Err(AppError::Validation("약어에는 공백을 넣을 수 없습니다".to_string()));
     }
+    let saved = TextMacro {
+        abbreviation: abbreviation.to_string(),
+        updated_at: Utc::now(),
+        ..text_macro.clone()
+    };
+
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute(
+        r#"INSERT INTO text_macros (id, abbreviation, expansion, owner_id, created_at, updated_at)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+           ON CONFLICT(id) DO UPDATE SET abbreviation = excluded.abbreviation, expansion = excluded.expansion, updated_at = excluded.updated_at"#,
+        params![
+            saved.id,
+            saved.abbreviation,
+            saved.expansion,
+            saved.owner_id,
+            saved.created_at.to_rfc3339(),
+            saved.updated_at.to_rfc3339(),
+        ],
+    )
+    .map_err(|e| match &e {
+        rusqlite::Error::SqliteFailure(err, _) if err.code == rusqlite::ErrorCode::ConstraintViolation => {
+            AppError::Conflict(format!("이미 등록된 약어입니다: {}", saved.abbreviation))
+        }
+        _ => e.into(),
+    })?;
+    Ok(saved)
 }
 
-/// 설문 응답 저장 (동기화용 데이터 반환)
-pub fn save_survey_response(
-    session_id: &str,
-    template_id: &str,
-    patient_id: Option<&str>,
-    respondent_name: Option<&str>,
-    answers: &[SurveyAnswer],
-) -> AppResult<SurveyResponseDb> {
+/// 약어 삭제
+pub fn delete_text_macro(id: &str) -> AppResult<()> {
+    ensure_db_initialized()?;
     let conn = get_conn()?;
-    let id = uuid::Uuid::new_v4().to_string();
-    let answers_json = serde_json::to_string(answers)?;
-    let now = Utc::now().to_rfc3339();
+    conn.execute("DELETE FROM text_macros WHERE id = ?1", [id])?;
+    Ok(())
+}
+
+/// 약어 확장 (앞뒤가 글자/숫자가 아닌 곳의 약어만, 여러 약어가 겹치면 긴 것 우선)
+///
+/// 같은 약어가 전역과 직원 계정에 모두 있으면 직원 것을 씁니다.
+pub fn expand_macros(text: &str, macros: &[TextMacro]) -> String {
+    let mut candidates: Vec<&TextMacro> = Vec::new();
+    for m in macros.iter().filter(|m| !m.abbreviation.is_empty()) {
+        match candidates.iter_mut().find(|c| c.abbreviation == m.abbreviation) {
+            Some(existing) if existing.owner_id.is_none() => *existing = m,
+            Some(_) => {}
+            None => candidates.push(m),
+        }
+    }
+    candidates.sort_by_key(|m| std::cmp::Reverse(m.abbreviation.chars().count()));
+
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut at_boundary = true;
+    while let Some(c) = rest.chars().next() {
+        let matched = at_boundary
+            .then(|| {
+                candidates.iter().find(|m| {
+                    rest.strip_prefix(m.abbreviation.as_str())
+                        .is_some_and(|after| !after.chars().next().is_some_and(char::is_alphanumeric))
+                })
+            })
+            .flatten();
+        match matched {
+            Some(m) => {
+                expanded.push_str(&m.expansion);
+                rest = &rest[m.abbreviation.len()..];
+                at_boundary = false;
+            }
+            None => {
+                expanded.push(c);
+                rest = &rest[c.len_utf8()..];
+                at_boundary = !c.is_alphanumeric();
+            }
+        }
+    }
+    expanded
+}
+
+/// 차팅 기록의 글 항목에 약어 확장 적용
+fn expand_chart_record_macros(conn: &Connection, record: &ChartRecord, macros: MacroExpansion) -> AppResult<ChartRecord> {
+    let owner_id = match macros {
+        MacroExpansion::Off => return Ok(record.clone()),
+        MacroExpansion::Global => None,
+        MacroExpansion::Staff(id) => Some(id),
+    };
+    let text_macros = query_text_macros(conn, owner_id)?;
+    if text_macros.is_empty() {
+        return Ok(record.clone());
+    }
 
+    let expand = |field: &Option<String>| field.as_deref().map(|text| expand_macros(text, &text_macros));
+    Ok(ChartRecord {
+        chief_complaint: expand(&record.chief_complaint),
+        symptoms: expand(&record.symptoms),
+        diagnosis: expand(&record.diagnosis),
+        treatment: expand(&record.treatment),
+        notes: expand(&record.notes),
+        ..record.clone()
+    })
+}
+
+/// 변경 이력 추가
+fn insert_audit_log(
+    conn: &Connection,
+    entity_type: &str,
+    entity_id: &str,
+    action: &str,
+    previous_data: Option<&str>,
+    new_data: Option<&str>,
+    changed_by: Option<&str>,
+) -> AppResult<()> {
     conn.execute(
-        r#"INSERT INTO survey_responses (id, session_id, template_id, patient_id, respondent_name, answers, submitted_at)
-           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"#,
-        params![id, session_id, template_id, patient_id, respondent_name, answers_json, now.clone()],
+        r#"INSERT INTO audit_logs (id, entity_type, entity_id, action, previous_data, new_data, changed_by, created_at)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"#,
+        params![
+            uuid::Uuid::new_v4().to_string(),
+            entity_type,
+            entity_id,
+            action,
+            previous_data,
+            new_data,
+            changed_by,
+            Utc::now().to_rfc3339(),
+        ],
     )?;
+    Ok(())
+}
 
-    let response = SurveyResponseDb {
-        id,
-        session_id: Some(session_id.to_string()),
-        template_id: template_id.to_string(),
-        patient_id: patient_id.map(|s| s.to_string()),
-        respondent_name: respondent_name.map(|s| s.to_string()),
-        answers: answers_json,
-        submitted_at: now,
+/// 변경 이력의 (작업, 변경자) 목록 (기록 순)
+#[cfg(test)]
+pub(crate) fn audit_log_actors(entity_type: &str, entity_id: &str) -> Vec<(String, Option<String>)> {
+    let conn = get_conn().unwrap();
+    let mut stmt = conn
+        .prepare("SELECT action, changed_by FROM audit_logs WHERE entity_type = ?1 AND entity_id = ?2 ORDER BY created_at, rowid")
+        .unwrap();
+    stmt.query_map([entity_type, entity_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+// ============ 문서 서명 (무결성) ============
+
+/// 새 서명의 해시 형식 버전
+/// 0: 모델 전체 JSON (필드가 추가되면 기존 서명이 모두 깨지므로 이전 서명 검증에만 사용)
+/// 1: 버전별로 고정한 내용 필드 목록
+const DOCUMENT_HASH_VERSION: i32 = 1;
+
+/// 약재 목록의 서명 대상 내용 (약재명/용량/단위만, 로트·공급처 등 추적용 필드는 제외)
+fn herbs_hash_content(herbs_json: &str) -> serde_json::Value {
+    match serde_json::from_str::<Vec<HerbItem>>(herbs_json) {
+        Ok(herbs) => herbs.iter().map(|h| serde_json::json!([h.herb_name, h.amount, h.unit])).collect(),
+        Err(_) => serde_json::Value::String(herbs_json.to_string()),
+    }
+}
+
+/// 서명 대상 문서의 해시 입력 (문서가 없으면 None)
+fn document_hash_content(conn: &Connection, document_type: &str, document_id: &str, version: i32) -> AppResult<Option<String>> {
+    let content = match (document_type, version) {
+        ("prescription", 0) => query_prescription(conn, document_id)?.map(|p| serde_json::to_string(&p)).transpose()?,
+        ("chart_record", 0) => query_chart_record(conn, document_id)?.map(|r| serde_json::to_string(&r)).transpose()?,
+        ("prescription", 1) => query_prescription(conn, document_id)?.map(|p| {
+            serde_json::json!([
+                "prescription", 1, p.id, p.patient_id, p.prescription_name, p.formula,
+                herbs_hash_content(&p.merged_herbs), herbs_hash_content(&p.final_herbs),
+                p.total_doses, p.days, p.doses_per_day, p.total_packs, p.pack_volume, p.water_amount,
+                p.herb_adjustment, p.total_dosage, p.final_total_amount, p.notes,
+            ])
+            .to_string()
+        }),
+        ("chart_record", 1) => query_chart_record(conn, document_id)?.map(|r| {
+            serde_json::json!([
+                "chart_record", 1, r.id, r.patient_id, r.visit_date.to_rfc3339(),
+                r.chief_complaint, r.symptoms, r.diagnosis, r.treatment, r.prescription_id, r.notes,
+            ])
+            .to_string()
+        }),
+        ("prescription" | "chart_record", _) => {
+            return Err(AppError::Custom(format!("지원하지 않는 서명 형식입니다: {}", version)));
+        }
+        _ => return Err(AppError::Validation(format!("서명할 수 없는 문서 유형입니다: {}", document_type))),
     };
+    Ok(content)
+}
+
+/// 서명 대상 문서의 현재 내용 해시 (문서가 없으면 None)
+fn document_content_hash(
+    conn: &Connection,
+    document_type: &str,
+    document_id: &str,
+    previous_hash: Option<&str>,
+    version: i32,
+) -> AppResult<Option<String>> {
+    use sha2::{Digest, Sha256};
+
+    Ok(document_hash_content(conn, document_type, document_id, version)?.map(|c| {
+        let mut hasher = Sha256::new();
+        if let Some(prev) = previous_hash {
+            hasher.update(prev.as_bytes());
+        }
+        hasher.update(c.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }))
+}
 
-    Ok(response)
+fn row_to_document_signature(row: &rusqlite::Row) -> rusqlite::Result<DocumentSignature> {
+    Ok(DocumentSignature {
+        id: row.get("id")?,
+        document_type: row.get("document_type")?,
+        document_id: row.get("document_id")?,
+        content_hash: row.get("content_hash")?,
+        previous_hash: row.get("previous_hash")?,
+        hash_version: row.get("hash_version")?,
+        signed_by: row.get("signed_by")?,
+        signed_at: row.get("signed_at")?,
+    })
 }
 
-/// 설문 세션 완료 처리
-pub fn complete_survey_session(session_id: &str) -> AppResult<()> {
+/// 처방/차트 확정 서명 (chain = true이면 직전 서명 해시를 포함해 체인 구성)
+pub fn sign_document(
+    document_type: &str,
+    document_id: &str,
+    signed_by: Option<&str>,
+    chain: bool,
+) -> AppResult<DocumentSignature> {
+    ensure_db_initialized()?;
     let conn = get_conn()?;
-    let now = Utc::now().to_rfc3339();
+    // 직전 해시 조회와 저장 사이에 다른 서명이 끼어들어 체인이 갈라지지 않도록 한 트랜잭션으로 처리
+    let tx = rusqlite::Transaction::new_unchecked(&conn, rusqlite::TransactionBehavior::Immediate)?;
 
-    conn.execute(
-        "UPDATE survey_sessions SET status = 'completed', completed_at = ?1 WHERE id = ?2",
-        params![now, session_id],
+    let previous_hash: Option<String> = if chain {
+        tx.query_row(
+            "SELECT content_hash FROM document_signatures ORDER BY signed_at DESC, rowid DESC LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .ok()
+    } else {
+        None
+    };
+
+    let content_hash = document_content_hash(&tx, document_type, document_id, previous_hash.as_deref(), DOCUMENT_HASH_VERSION)?
+        .ok_or_else(|| AppError::NotFound("문서를 찾을 수 없습니다".to_string()))?;
+
+    let signature = DocumentSignature {
+        id: uuid::Uuid::new_v4().to_string(),
+        document_type: document_type.to_string(),
+        document_id: document_id.to_string(),
+        content_hash,
+        previous_hash,
+        hash_version: DOCUMENT_HASH_VERSION,
+        signed_by: signed_by.map(|s| s.to_string()),
+        signed_at: Utc::now().to_rfc3339(),
+    };
+
+    tx.execute(
+        r#"INSERT INTO document_signatures (id, document_type, document_id, content_hash, previous_hash, hash_version, signed_by, signed_at)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"#,
+        params![
+            signature.id,
+            signature.document_type,
+            signature.document_id,
+            signature.content_hash,
+            signature.previous_hash,
+            signature.hash_version,
+            signature.signed_by,
+            signature.signed_at,
+        ],
     )?;
+    tx.commit()?;
 
-    Ok(())
+    log::info!("문서 서명: {} {} ({})", document_type, document_id, signature.content_hash);
+    Ok(signature)
 }
 
-/// 설문 세션 생성
-pub fn create_survey_session(
-    patient_id: Option<&str>,
-    template_id: &str,
-    respondent_name: Option<&str>,
-    created_by: Option<&str>,
-    token_override: Option<&str>,
-    patient_name: Option<&str>,
-    chart_number: Option<&str>,
-    patient_age: Option<&str>,
-    patient_gender: Option<&str>,
-) -> AppResult<SurveySessionDb> {
+/// 문서 무결성 검증 (최근 서명 해시와 현재 내용 해시를 서명 당시 형식으로 대조)
+pub fn verify_document_integrity(document_id: &str) -> AppResult<DocumentIntegrity> {
+    ensure_db_initialized()?;
     let conn = get_conn()?;
-    let id = uuid::Uuid::new_v4().to_string();
-    let token = token_override.map(|t| t.to_string()).unwrap_or_else(|| generate_survey_token());
-    let now = Utc::now();
-    let expires_at = (now + chrono::Duration::hours(24)).to_rfc3339();
-    let created_at = now.to_rfc3339();
 
-    conn.execute(
-        r#"INSERT INTO survey_sessions (id, token, patient_id, template_id, respondent_name, status, expires_at, created_by, created_at, patient_name, chart_number, patient_age, patient_gender)
-           VALUES (?1, ?2, ?3, ?4, ?5, 'pending', ?6, ?7, ?8, ?9, ?10, ?11, ?12)"#,
-        params![id, token, patient_id, template_id, respondent_name, expires_at, created_by, created_at, patient_name, chart_number, patient_age, patient_gender],
+    let result = conn.query_row(
+        "SELECT * FROM document_signatures WHERE document_id = ?1 ORDER BY signed_at DESC, rowid DESC LIMIT 1",
+        [document_id],
+        row_to_document_signature,
+    );
+    let signature = match result {
+        Ok(sig) => sig,
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            return Ok(DocumentIntegrity {
+                document_id: document_id.to_string(),
+                valid: false,
+                signature: None,
+                current_hash: None,
+            });
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let current_hash = document_content_hash(
+        &conn,
+        &signature.document_type,
+        document_id,
+        signature.previous_hash.as_deref(),
+        signature.hash_version,
     )?;
+    let valid = current_hash.as_deref() == Some(signature.content_hash.as_str());
+    if !valid {
+        log::warn!("문서 변조 감지: {} {}", signature.document_type, document_id);
+    }
 
-    Ok(SurveySessionDb {
-        id,
-        token,
-        patient_id: patient_id.map(|s| s.to_string()),
-        template_id: template_id.to_string(),
-        respondent_name: respondent_name.map(|s| s.to_string()),
-        patient_name: patient_name.map(|s| s.to_string()),
-        chart_number: chart_number.map(|s| s.to_string()),
-        patient_age: patient_age.map(|s| s.to_string()),
-        patient_gender: patient_gender.map(|s| s.to_string()),
-        status: SessionStatus::Pending,
-        expires_at,
-        created_at,
+    Ok(DocumentIntegrity {
+        document_id: document_id.to_string(),
+        valid,
+        signature: Some(signature),
+        current_hash,
     })
 }
 
-/// 설문 세션 목록 조회 (환자명 포함)
-pub fn list_survey_sessions(patient_id: Option<&str>, status: Option<&str>) -> AppResult<Vec<SurveySessionWithPatient>> {
-    let conn = get_conn()?;
-    let mut sql = String::from(
-        "SELECT s.id, s.token, s.patient_id, s.template_id, s.respondent_name, s.status, s.expires_at, s.created_by, s.created_at, s.completed_at, COALESCE(p.name, s.patient_name) as patient_name, s.chart_number, s.patient_age, s.patient_gender
-         FROM survey_sessions s
-         LEFT JOIN patients p ON s.patient_id = p.id
-         WHERE 1=1"
-    );
-    let mut params_vec: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+/// 처방 이력 (처방 + 해당 처방을 참조하는 차팅 기록)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PrescriptionHistoryRow {
+    pub prescription_id: String,
+    pub prescription_name: Option<String>,
+    pub created_at: String,
+    pub visit_date: Option<String>,      // 연결된 차팅 기록이 없으면 None
+    pub chief_complaint: Option<String>,
+    pub diagnosis: Option<String>,
+}
 
-    if let Some(pid) = patient_id {
-        sql.push_str(&format!(" AND s.patient_id = ?{}", params_vec.len() + 1));
-        params_vec.push(Box::new(pid.to_string()));
-    }
-    if let Some(st) = status {
-        sql.push_str(&format!(" AND s.status = ?{}", params_vec.len() + 1));
-        params_vec.push(Box::new(st.to_string()));
-    }
-    sql.push_str(" ORDER BY s.created_at DESC");
+/// 환자별 처방 이력 조회 (어느 내원에서 어떤 처방이 나갔는지)
+pub fn get_prescription_history(patient_id: &str) -> AppResult<Vec<PrescriptionHistoryRow>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare(
+        r#"SELECT p.id, p.prescription_name, p.created_at, cr.visit_date, cr.chief_complaint, cr.diagnosis
+           FROM prescriptions p
+           LEFT JOIN chart_records cr ON cr.prescription_id = p.id AND cr.is_deleted = 0
+           WHERE p.patient_id = ?1 AND p.deleted_at IS NULL
+           ORDER BY COALESCE(cr.visit_date, p.created_at) DESC"#,
+    )?;
 
-    let params_refs: Vec<&dyn rusqlite::types::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
-    let mut stmt = conn.prepare(&sql)?;
-    let rows = stmt.query_map(params_refs.as_slice(), |row| {
-        let status_str: String = row.get(5)?;
-        let status = match status_str.as_str() {
-            "completed" => "completed".to_string(),
-            "expired" => "expired".to_string(),
-            _ => "pending".to_string(),
-        };
-        Ok(SurveySessionWithPatient {
-            id: row.get(0)?,
-            token: row.get(1)?,
-            patient_id: row.get(2)?,
-            template_id: row.get(3)?,
-            respondent_name: row.get(4)?,
-            status,
-            expires_at: row.get(6)?,
-            created_by: row.get(7)?,
-            created_at: row.get(8)?,
-            completed_at: row.get(9)?,
-            patient_name: row.get(10)?,
-            chart_number: row.get(11)?,
-            patient_age: row.get(12)?,
-            patient_gender: row.get(13)?,
+    let rows = stmt.query_map([patient_id], |row| {
+        Ok(PrescriptionHistoryRow {
+            prescription_id: row.get(0)?,
+            prescription_name: row.get(1)?,
+            created_at: row.get(2)?,
+            visit_date: row.get(3)?,
+            chief_complaint: row.get(4)?,
+            diagnosis: row.get(5)?,
         })
     })?;
 
-    let mut sessions = Vec::new();
+    let mut history = Vec::new();
     for row in rows {
-        let mut session = row?;
-        // 만료 확인
-        if session.status == "pending" {
-            if let Ok(expires) = chrono::DateTime::parse_from_rfc3339(&session.expires_at) {
-                if expires < Utc::now() {
-                    conn.execute(
-                        "UPDATE survey_sessions SET status = 'expired' WHERE id = ?1",
-                        [&session.id],
-                    )?;
-                    session.status = "expired".to_string();
-                }
-            }
-        }
-        sessions.push(session);
+        history.push(row?);
     }
+    Ok(history)
+}
 
-    Ok(sessions)
+// ============ 데이터 내보내기 ============
+
+pub fn export_patient_data(patient_id: &str) -> AppResult<String> {
+    let patient = get_patient(patient_id)?
+        .ok_or_else(|| AppError::Custom("Patient not found".to_string()))?;
+    let prescriptions = get_prescriptions_by_patient(patient_id)?;
+    let chart_records = get_chart_records_by_patient(patient_id)?;
+
+    let export_data = serde_json::json!({
+        "patient": patient,
+        "prescriptions": prescriptions,
+        "chart_records": chart_records,
+        "exported_at": Utc::now().to_rfc3339(),
+    });
+
+    Ok(serde_json::to_string_pretty(&export_data)?)
 }
 
-/// 설문 세션 ID로 조회
-pub fn get_survey_session(id: &str) -> AppResult<Option<SurveySessionDb>> {
-    let conn = get_conn()?;
-    let mut stmt = conn.prepare(
-        "SELECT id, token, patient_id, template_id, respondent_name, status, expires_at, created_at, patient_name, chart_number, patient_age, patient_gender
-         FROM survey_sessions WHERE id = ?1",
-    )?;
+pub fn export_all_data() -> AppResult<String> {
+    let patients = list_patients(None, &[])?;
+    let settings = get_clinic_settings()?;
 
-    let result = stmt.query_row([id], |row| {
-        let status_str: String = row.get(5)?;
-        let status = match status_str.as_str() {
-            "completed" => SessionStatus::Completed,
-            "expired" => SessionStatus::Expired,
-            _ => SessionStatus::Pending,
-        };
-        Ok(SurveySessionDb {
-            id: row.get(0)?,
-            token: row.get(1)?,
-            patient_id: row.get(2)?,
-            template_id: row.get(3)?,
-            respondent_name: row.get(4)?,
-            patient_name: row.get(8)?,
-            chart_number: row.get(9)?,
-            patient_age: row.get(10)?,
-            patient_gender: row.get(11)?,
-            status,
-            expires_at: row.get(6)?,
-            created_at: row.get(7)?,
-        })
+    let mut all_data = Vec::new();
+    for patient in &patients {
+        let prescriptions = get_prescriptions_by_patient(&patient.id)?;
+        let chart_records = get_chart_records_by_patient(&patient.id)?;
+        all_data.push(serde_json::json!({
+            "patient": patient,
+            "prescriptions": prescriptions,
+            "chart_records": chart_records,
+        }));
+    }
+
+    let export_data = serde_json::json!({
+        "clinic_settings": settings,
+        "patients_data": all_data,
+        "exported_at": Utc::now().to_rfc3339(),
     });
 
-    match result {
-        Ok(session) => Ok(Some(session)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(e.into()),
-    }
+    Ok(serde_json::to_string_pretty(&export_data)?)
 }
 
-/// 설문 세션 만료 처리
-pub fn expire_survey_session(id: &str) -> AppResult<()> {
+/// 스트리밍 내보내기 시 한 번에 읽는 환자 수
+const EXPORT_BATCH_SIZE: i64 = 200;
+
+/// 환자 ID 순으로 `after` 다음 환자들 조회 (스트리밍 내보내기용 커서)
+fn list_patients_after(after: Option<&str>, limit: i64) -> AppResult<Vec<Patient>> {
+    ensure_db_initialized()?;
     let conn = get_conn()?;
-    conn.execute(
-        "UPDATE survey_sessions SET status = 'expired' WHERE id = ?1",
-        [id],
+    let mut stmt = conn.prepare(
+        "SELECT id, name, chart_number, birth_date, gender, phone, address, notes, created_at, updated_at, referral_source,
+                (SELECT group_concat(tag, ',') FROM tags WHERE entity_type = 'patient' AND entity_id = patients.id)
+         FROM patients WHERE deleted_at IS NULL AND id > ?1 ORDER BY id LIMIT ?2",
     )?;
-    Ok(())
+    let patients = stmt
+        .query_map(params![after.unwrap_or(""), limit], map_patient_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(patients)
 }
 
-/// 설문 세션 삭제
-pub fn delete_survey_session(id: &str) -> AppResult<()> {
-    let conn = get_conn()?;
-    conn.execute("DELETE FROM survey_sessions WHERE id = ?1", [id])?;
+/// 전체 데이터를 줄 단위 JSON(NDJSON)으로 스트리밍 내보내기
+/// 첫 줄은 병원 설정과 내보낸 시각, 이후 환자 1명당 한 줄(처방/차팅 기록 포함)
+/// 마지막 줄은 `{"export_complete": true, "patient_count": N}` (이 줄이 없으면 중간에 끊긴 파일)
+/// 환자는 ID 순으로 나눠 읽고 한 줄씩 기록하므로 환자 수와 무관하게 메모리 사용량이 일정함
+pub async fn stream_all_data(writer: impl tokio::io::AsyncWrite + Unpin) -> AppResult<()> {
+    use tokio::io::AsyncWriteExt;
+
+    let mut writer = writer;
+    let mut line = Vec::new();
+
+    serde_json::to_writer(
+        &mut line,
+        &serde_json::json!({
+            "clinic_settings": get_clinic_settings()?,
+            "exported_at": Utc::now().to_rfc3339(),
+        }),
+    )?;
+    line.push(b'\n');
+    writer.write_all(&line).await?;
+
+    let mut after: Option<String> = None;
+    let mut patient_count = 0usize;
+    loop {
+        let patients = list_patients_after(after.as_deref(), EXPORT_BATCH_SIZE)?;
+        patient_count += patients.len();
+        for patient in &patients {
+            line.clear();
+            serde_json::to_writer(
+                &mut line,
+                &serde_json::json!({
+                    "patient": patient,
+                    "prescriptions": get_prescriptions_by_patient(&patient.id)?,
+                    "chart_records": get_chart_records_by_patient(&patient.id)?,
+                }),
+            )?;
+            line.push(b'\n');
+            writer.write_all(&line).await?;
+        }
+
+        match patients.last() {
+            Some(last) if patients.len() as i64 == EXPORT_BATCH_SIZE => after = Some(last.id.clone()),
+            _ => break,
+        }
+    }
+
+    line.clear();
+    serde_json::to_writer(&mut line, &serde_json::json!({"export_complete": true, "patient_count": patient_count}))?;
+    line.push(b'\n');
+    writer.write_all(&line).await?;
+
+    writer.shutdown().await?;
     Ok(())
 }
 
-/// 8자리 토큰 생성
-fn generate_survey_token() -> String {
-    use rand::Rng;
-    let mut rng = rand::thread_rng();
-    (0..8)
-        .map(|_| {
-            let idx = rng.gen_range(0..36);
-            if idx < 10 {
-                (b'0' + idx) as char
-            } else {
-                (b'a' + idx - 10) as char
-            }
-        })
-        .collect()
+/// 전체 데이터 암호화 내보내기 (AES-256-GCM)
+pub fn export_all_data_encrypted(passphrase: &str) -> AppResult<Vec<u8>> {
+    let json = export_all_data()?;
+    crate::encryption::encrypt_backup(json.as_bytes(), passphrase)
 }
 
-// ============ 직원 비밀번호 관리 ============
+/// ZIP 내보내기에 포함할 테이블 (파일명은 `<테이블>.csv`)
+const ZIP_EXPORT_TABLES: &[&str] = &[
+    "patients",
+    "prescriptions",
+    "chart_records",
+    "medication_schedules",
+    "survey_responses",
+];
+
+/// 전체 데이터를 테이블별 CSV 파일로 묶은 ZIP 내보내기 (백업/이전용)
+pub fn export_all_data_zip() -> AppResult<Vec<u8>> {
+    use std::io::Write;
 
-/// 직원 비밀번호 설정
-pub fn set_staff_password(password: &str) -> AppResult<()> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    let hash = bcrypt::hash(password, bcrypt::DEFAULT_COST)
-        .map_err(|e| AppError::Custom(format!("Password hash error: {}", e)))?;
 
-    // 기존 설정이 있는지 확인
-    let existing: Option<String> = conn
-        .query_row("SELECT id FROM clinic_settings LIMIT 1", [], |row| row.get(0))
-        .ok();
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let zip_err = |e: zip::result::ZipError| AppError::Custom(format!("ZIP 생성 실패: {}", e));
 
-    if let Some(id) = existing {
-        conn.execute(
-            "UPDATE clinic_settings SET staff_password_hash = ?, updated_at = ? WHERE id = ?",
-            params![hash, Utc::now().to_rfc3339(), id],
-        )?;
-    } else {
-        // 설정이 없으면 새로 생성
-        let id = uuid::Uuid::new_v4().to_string();
-        let now = Utc::now().to_rfc3339();
-        conn.execute(
-            "INSERT INTO clinic_settings (id, clinic_name, staff_password_hash, created_at, updated_at) VALUES (?, '', ?, ?, ?)",
-            params![id, hash, now, now],
-        )?;
+    for table in ZIP_EXPORT_TABLES {
+        let csv = table_to_csv(&conn, table)?;
+        zip.start_file(format!("{}.csv", table), options).map_err(zip_err)?;
+        zip.write_all(&csv)?;
     }
 
-    log::info!("Staff password updated");
-    Ok(())
-}
-
-/// 직원 비밀번호 검증
-pub fn verify_staff_password(password: &str) -> AppResult<bool> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
-    let hash: Option<String> = conn
-        .query_row(
-            "SELECT staff_password_hash FROM clinic_settings LIMIT 1",
-            [],
-            |row| row.get(0),
-        )
-        .ok()
-        .flatten();
-
-    match hash {
-        Some(h) => {
-            let valid = bcrypt::verify(password, &h)
-                .map_err(|e| AppError::Custom(format!("Password verify error: {}", e)))?;
-            Ok(valid)
+    let cursor = zip.finish().map_err(zip_err)?;
+    Ok(cursor.into_inner())
+}
+
+/// 테이블 전체를 RFC 4180 CSV로 변환 (헤더 포함, CRLF 줄바꿈, 엑셀 한글 인식을 위해 UTF-8 BOM)
+fn table_to_csv(conn: &Connection, table: &str) -> AppResult<Vec<u8>> {
+    let mut stmt = conn.prepare(&format!("SELECT * FROM {} ORDER BY rowid", table))?;
+    let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+
+    let mut out = "\u{feff}".as_bytes().to_vec();
+    write_csv_record(&mut out, columns.iter().map(String::as_str));
+
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let mut fields = Vec::with_capacity(columns.len());
+        for i in 0..columns.len() {
+            fields.push(match row.get_ref(i)? {
+                rusqlite::types::ValueRef::Null => String::new(),
+                rusqlite::types::ValueRef::Integer(v) => v.to_string(),
+                rusqlite::types::ValueRef::Real(v) => v.to_string(),
+                rusqlite::types::ValueRef::Text(v) => String::from_utf8_lossy(v).into_owned(),
+                rusqlite::types::ValueRef::Blob(v) => {
+                    use base64::Engine;
+                    base64::engine::general_purpose::STANDARD.encode(v)
+                }
+            });
         }
-        None => Ok(false), // 비밀번호 미설정
+        write_csv_record(&mut out, fields.iter().map(String::as_str));
     }
+    Ok(out)
 }
 
-/// 직원 비밀번호 설정 여부 확인
-pub fn has_staff_password() -> AppResult<bool> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
-    let hash: Option<String> = conn
-        .query_row(
-            "SELECT staff_password_hash FROM clinic_settings LIMIT 1",
-            [],
-            |row| row.get(0),
-        )
-        .ok()
-        .flatten();
-
-    Ok(hash.is_some())
+/// CSV 한 줄 기록 (쉼표/따옴표/줄바꿈이 있는 필드는 따옴표로 감싸고 따옴표는 두 번)
+fn write_csv_record<'a>(out: &mut Vec<u8>, fields: impl Iterator<Item = &'a str>) {
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            out.push(b',');
+        }
+        if field.contains([',', '"', '\r', '\n']) {
+            out.push(b'"');
+            out.extend_from_slice(field.replace('"', "\"\"").as_bytes());
+            out.push(b'"');
+        } else {
+            out.extend_from_slice(field.as_bytes());
+        }
+    }
+    out.extend_from_slice(b"\r\n");
 }
 
-/// HTTP 서버 포트 가져오기
-pub fn get_http_server_port() -> AppResult<u16> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
-    let port: Option<i32> = conn
-        .query_row(
-            "SELECT http_server_port FROM clinic_settings LIMIT 1",
-            [],
-            |row| row.get(0),
-        )
-        .ok()
-        .flatten();
+/// 암호화 백업 복원 (기존에 없는 환자/처방/차팅 기록만 추가, 복원 건수 반환)
+pub fn import_encrypted(data: &[u8], passphrase: &str) -> AppResult<usize> {
+    let plaintext = crate::encryption::decrypt_backup(data, passphrase)?;
+    let json = String::from_utf8(plaintext)
+        .map_err(|_| AppError::Custom("백업 데이터 형식이 올바르지 않습니다".to_string()))?;
+    let summary = import_from_json_export(&json, ConflictStrategy::Skip)?;
 
-    Ok(port.unwrap_or(3030) as u16)
+    let restored = summary.patients_imported + summary.prescriptions_imported + summary.charts_imported;
+    log::info!("암호화 백업 복원 완료: {}건", restored);
+    Ok(restored)
 }
 
-/// HTTP 서버 포트 설정
-#[allow(dead_code)]
-pub fn set_http_server_port(port: u16) -> AppResult<()> {
-    let conn = get_conn()?;
-    conn.execute(
-        "UPDATE clinic_settings SET http_server_port = ?, updated_at = ?",
-        params![port as i32, Utc::now().to_rfc3339()],
-    )?;
-    Ok(())
+/// 백업에서 읽은 환자 1명분 (형식이 잘못된 처방/차팅 기록은 사유와 함께 제외)
+struct ImportEntry {
+    patient: Patient,
+    prescriptions: Vec<Prescription>,
+    chart_records: Vec<ChartRecord>,
 }
 
-/// HTTP 서버 자동 시작 설정 조회
-pub fn get_server_autostart() -> AppResult<bool> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
+/// `export_all_data()` 형식 검증 (쓰기 전에 전체를 먼저 읽어 중간 실패로 일부만 들어가는 일이 없도록)
+fn parse_json_export(json: &str, errors: &mut Vec<String>) -> AppResult<Vec<ImportEntry>> {
+    let invalid = |msg: String| AppError::Validation(format!("백업 데이터 형식이 올바르지 않습니다: {}", msg));
 
-    // 컬럼이 없으면 추가
-    let _ = conn.execute(
-        "ALTER TABLE clinic_settings ADD COLUMN http_server_autostart INTEGER DEFAULT 0",
-        [],
-    );
+    let export: serde_json::Value = serde_json::from_str(json).map_err(|e| invalid(e.to_string()))?;
+    let items = export
+        .get("patients_data")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| invalid("patients_data가 없습니다".to_string()))?;
 
-    let autostart: Option<i32> = conn
-        .query_row(
-            "SELECT http_server_autostart FROM clinic_settings LIMIT 1",
-            [],
-            |row| row.get(0),
-        )
-        .ok()
-        .flatten();
+    let mut entries = Vec::with_capacity(items.len());
+    for (i, item) in items.iter().enumerate() {
+        let patient: Patient = serde_json::from_value(item.get("patient").cloned().unwrap_or_default())
+            .map_err(|e| invalid(format!("{}번째 환자: {}", i + 1, e)))?;
 
-    Ok(autostart.unwrap_or(0) == 1)
-}
+        let mut prescriptions = Vec::new();
+        for value in item.get("prescriptions").and_then(|v| v.as_array()).into_iter().flatten() {
+            match serde_json::from_value::<Prescription>(value.clone()) {
+                Ok(p) if p.patient_id.as_deref().is_some_and(|id| id != patient.id) => {
+                    errors.push(format!("처방 {}: 다른 환자의 처방입니다", p.id));
+                }
+                Ok(p) => prescriptions.push(p),
+                Err(e) => errors.push(format!("{} 환자의 처방 형식 오류: {}", patient.name, e)),
+            }
+        }
 
-/// HTTP 서버 자동 시작 설정 저장
-pub fn set_server_autostart(enabled: bool) -> AppResult<()> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
+        let mut chart_records = Vec::new();
+        for value in item.get("chart_records").and_then(|v| v.as_array()).into_iter().flatten() {
+            match serde_json::from_value::<ChartRecord>(value.clone()) {
+                Ok(r) if r.patient_id != patient.id => {
+                    errors.push(format!("차팅 기록 {}: 다른 환자의 기록입니다", r.id));
+                }
+                Ok(r) => chart_records.push(r),
+                Err(e) => errors.push(format!("{} 환자의 차팅 기록 형식 오류: {}", patient.name, e)),
+            }
+        }
 
-    // 컬럼이 없으면 추가
-    let _ = conn.execute(
-        "ALTER TABLE clinic_settings ADD COLUMN http_server_autostart INTEGER DEFAULT 0",
-        [],
-    );
+        entries.push(ImportEntry { patient, prescriptions, chart_records });
+    }
+    Ok(entries)
+}
 
-    conn.execute(
-        "UPDATE clinic_settings SET http_server_autostart = ?, updated_at = ?",
-        params![if enabled { 1 } else { 0 }, Utc::now().to_rfc3339()],
+/// 같은 ID의 기록이 있는지 (`table`은 내부 상수만 전달)
+fn record_exists(conn: &Connection, table: &str, id: &str) -> AppResult<bool> {
+    let exists: bool = conn.query_row(
+        &format!("SELECT EXISTS(SELECT 1 FROM {} WHERE id = ?1)", table),
+        [id],
+        |row| row.get(0),
     )?;
-
-    log::info!("HTTP 서버 자동 시작 설정: {}", enabled);
-    Ok(())
+    Ok(exists)
 }
 
-// ============ 설문 응답 목록 조회 (직원용) ============
+/// 전체 백업(`export_all_data()` JSON) 복원 - 하나의 트랜잭션으로 처리해 실패 시 아무것도 반영하지 않음
+///
+/// `Replace`는 형식 검증을 통과한 뒤 기존 환자 데이터(설문/복약 기록 포함)를 모두 지우고 복원합니다.
+pub fn import_from_json_export(json: &str, conflict_strategy: ConflictStrategy) -> AppResult<ImportSummary> {
+    let mut summary = ImportSummary::default();
+    let entries = parse_json_export(json, &mut summary.errors)?;
 
-/// 설문 응답 목록 조회
-pub fn list_survey_responses(limit: Option<i32>) -> AppResult<Vec<SurveyResponseWithTemplate>> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    let limit_val = limit.unwrap_or(100);
+    let tx = conn.unchecked_transaction()?;
+    if conflict_strategy == ConflictStrategy::Replace {
+        delete_all_user_data(&tx)?;
+    }
 
-    let mut stmt = conn.prepare(
-        r#"SELECT r.id, r.session_id, r.patient_id, r.template_id, r.respondent_name,
-                  r.answers, r.submitted_at, t.name as template_name, p.name as patient_name,
-                  p.chart_number
-           FROM survey_responses r
-           LEFT JOIN survey_templates t ON r.template_id = t.id
-           LEFT JOIN patients p ON r.patient_id = p.id
-           ORDER BY r.submitted_at DESC
-           LIMIT ?"#,
-    )?;
+    // 이미 있는 기록 처리 (Some(false): 새로 넣음, Some(true): 덮어씀, None: 건너뜀)
+    let mut conflicts = Vec::new();
+    let mut resolve = |table: &str, id: &str, label: &str| -> AppResult<Option<bool>> {
+        if !record_exists(&tx, table, id)? {
+            return Ok(Some(false));
+        }
+        match conflict_strategy {
+            ConflictStrategy::Skip => {
+                conflicts.push(format!("{} {}: 기존 기록 유지", label, id));
+                Ok(None)
+            }
+            ConflictStrategy::Overwrite => {
+                conflicts.push(format!("{} {}: 백업 내용으로 덮어씀", label, id));
+                Ok(Some(true))
+            }
+            ConflictStrategy::Error => Err(AppError::Validation(format!("이미 있는 {}입니다: {}", label, id))),
+            // 백업 안에서 같은 ID가 두 번 나온 경우
+            ConflictStrategy::Replace => Err(AppError::Validation(format!("백업에 중복된 {}가 있습니다: {}", label, id))),
+        }
+    };
 
-    let rows = stmt.query_map([limit_val], |row| {
-        let answers_json: String = row.get(5)?;
-        let answers: Vec<SurveyAnswer> = serde_json::from_str(&answers_json).unwrap_or_default();
-        Ok(SurveyResponseWithTemplate {
-            id: row.get(0)?,
-            session_id: row.get(1)?,
-            patient_id: row.get(2)?,
-            template_id: row.get(3)?,
-            respondent_name: row.get(4)?,
-            answers,
-            submitted_at: row.get(6)?,
-            template_name: row.get(7)?,
-            patient_name: row.get(8)?,
-            chart_number: row.get(9)?,
-        })
-    })?;
+    for entry in &entries {
+        // 건너뛴 환자도 처방/차팅 기록은 각각 확인해 새 기록만 추가
+        match resolve("patients", &entry.patient.id, "환자")? {
+            // 백업 원본에 있던 동명이인이 복원을 막지 않도록 중복 허용
+            Some(false) => {
+                insert_patient(&tx, &entry.patient, true)?;
+                summary.patients_imported += 1;
+            }
+            Some(true) => {
+                update_patient_row(&tx, &entry.patient)?;
+                summary.patients_imported += 1;
+            }
+            None => {}
+        }
 
-    let mut responses = Vec::new();
-    for row in rows {
-        responses.push(row?);
+        for prescription in &entry.prescriptions {
+            match resolve("prescriptions", &prescription.id, "처방")? {
+                Some(false) => insert_prescription(&tx, prescription)?,
+                Some(true) => update_prescription_row(&tx, prescription)?,
+                None => continue,
+            }
+            summary.prescriptions_imported += 1;
+        }
+
+        for record in &entry.chart_records {
+            match resolve("chart_records", &record.id, "차팅 기록")? {
+                Some(false) => insert_chart_record(&tx, record)?,
+                Some(true) => {
+                    // 차팅 기록은 법적 기록이므로 덮어쓴 내용을 변경 이력에 남김
+                    let previous = tx
+                        .query_row(
+                            "SELECT id, patient_id, visit_date, chief_complaint, symptoms, diagnosis, treatment, prescription_id, notes, created_at, updated_at
+                             FROM chart_records WHERE id = ?1",
+                            [&record.id],
+                            row_to_chart_record,
+                        )
+                        .ok();
+                    update_chart_record_row(&tx, record)?;
+                    insert_audit_log(
+                        &tx,
+                        "chart_record",
+                        &record.id,
+                        "import",
+                        previous.map(|p| serde_json::to_string(&p)).transpose()?.as_deref(),
+                        Some(&serde_json::to_string(record)?),
+                        None,
+                    )?;
+                }
+                None => continue,
+            }
+            summary.charts_imported += 1;
+        }
     }
-    Ok(responses)
+
+    tx.commit()?;
+    summary.conflicts = conflicts;
+    if conflict_strategy == ConflictStrategy::Replace {
+        invalidate_survey_stats();
+    }
+    log::info!(
+        "백업 복원 완료 ({:?}): 환자 {}명, 처방 {}건, 차팅 {}건 (제외 {}건, 충돌 {}건)",
+        conflict_strategy,
+        summary.patients_imported,
+        summary.prescriptions_imported,
+        summary.charts_imported,
+        summary.errors.len(),
+        summary.conflicts.len()
+    );
+    Ok(summary)
 }
 
-/// 설문 응답 (템플릿 이름 포함)
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct SurveyResponseWithTemplate {
+// ============ 설문 세션 관리 (HTTP 서버용) ============
+
+/// 설문 세션 정보 (환자명 포함, 프론트엔드용)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SurveySessionWithPatient {
     pub id: String,
-    pub session_id: Option<String>,
+    pub token: String,
     pub patient_id: Option<String>,
     pub template_id: String,
     pub respondent_name: Option<String>,
-    pub answers: Vec<SurveyAnswer>,
-    pub submitted_at: String,
+    pub status: String,
+    pub expires_at: String,
+    pub created_by: Option<String>,
+    pub created_at: String,
+    pub completed_at: Option<String>,
+    pub patient_name: Option<String>,
+    pub chart_number: Option<String>,
+    pub patient_age: Option<String>,
+    pub patient_gender: Option<String>,
     pub template_name: Option<String>,
+}
+
+/// 설문 세션 정보 (DB용)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SurveySessionDb {
+    pub id: String,
+    pub token: String,
+    pub patient_id: Option<String>,
+    pub template_id: String,
+    pub respondent_name: Option<String>,
     pub patient_name: Option<String>,
     pub chart_number: Option<String>,
+    pub patient_age: Option<String>,
+    pub patient_gender: Option<String>,
+    pub status: SessionStatus,
+    pub expires_at: String,
+    pub created_at: String,
+    pub verify_birth_date: bool,    // 설문 시작 전 생년월일 확인
+    /// 무작위 순서 템플릿이면 생성 시 정해진 문항/선택지 표시 순서
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_order: Option<SurveyDisplayOrder>,
 }
 
-/// 모든 설문 템플릿 목록 조회
-pub fn list_survey_templates() -> AppResult<Vec<SurveyTemplateDb>> {
-    ensure_db_initialized()?;
+/// 설문 템플릿 정보 (DB용)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SurveyTemplateDb {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub questions: Vec<SurveyQuestion>,
+    pub display_mode: Option<String>,
+    pub is_active: bool,
+    /// 제출 시 응답자 이름과 연락처(휴대폰 또는 생년월일) 필수
+    #[serde(default)]
+    pub require_contact: bool,
+    /// 익명 설문 - 응답자 이름/환자 연결을 저장하지 않음
+    #[serde(default)]
+    pub anonymous: bool,
+    /// 제출 완료 후 이동할 주소 (없으면 완료 화면 유지)
+    #[serde(default)]
+    pub redirect_url: Option<String>,
+    /// 분류 (초진, 통증 평가, 치료 후 경과 등 - 목록에서 묶어 보여줌)
+    #[serde(default)]
+    pub category: Option<String>,
+    /// 세션마다 문항 순서를 섞음 (응답 편향 감소)
+    #[serde(default)]
+    pub randomize_questions: bool,
+    /// 세션마다 선택지 순서를 섞음
+    #[serde(default)]
+    pub randomize_options: bool,
+    /// 문항 순서를 섞을 때 필수 문항은 제자리 유지
+    #[serde(default)]
+    pub pin_required_questions: bool,
+}
+
+/// 설문 응답 정보 (DB용)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SurveyResponseDb {
+    pub id: String,
+    pub session_id: Option<String>,
+    pub template_id: String,
+    pub patient_id: Option<String>,
+    pub respondent_name: Option<String>,
+    pub answers: String,
+    pub submitted_at: String,
+}
+
+use crate::models::{SessionStatus, SurveyAnswer, SurveyDisplayOrder, SurveyQuestion};
+
+/// 토큰으로 설문 세션 조회
+pub fn get_survey_session_by_token(token: &str) -> AppResult<Option<SurveySessionDb>> {
     let conn = get_conn()?;
     let mut stmt = conn.prepare(
-        "SELECT id, name, description, questions, display_mode, is_active FROM survey_templates WHERE is_active = 1 ORDER BY name",
+        "SELECT id, token, patient_id, template_id, respondent_name, status, expires_at, created_at, patient_name, chart_number, patient_age, patient_gender, verify_birth_date, display_order
+         FROM survey_sessions WHERE token = ?1",
     )?;
 
-    let rows = stmt.query_map([], |row| {
-        let questions_json: String = row.get(3)?;
-        let questions: Vec<SurveyQuestion> = serde_json::from_str(&questions_json).unwrap_or_default();
-        let is_active: i32 = row.get(5)?;
-        Ok(SurveyTemplateDb {
+    let result = stmt.query_row([token], |row| {
+        let status_str: String = row.get(5)?;
+        let status = match status_str.as_str() {
+            "completed" => SessionStatus::Completed,
+            "expired" => SessionStatus::Expired,
+            "locked" => SessionStatus::Locked,
+            _ => SessionStatus::Pending,
+        };
+        Ok(SurveySessionDb {
             id: row.get(0)?,
-            name: row.get(1)?,
-            description: row.get(2)?,
-            questions,
-            display_mode: row.get(4)?,
-            is_active: is_active != 0,
+            token: row.get(1)?,
+            patient_id: row.get(2)?,
+            template_id: row.get(3)?,
+            respondent_name: row.get(4)?,
+            patient_name: row.get(8)?,
+            chart_number: row.get(9)?,
+            patient_age: row.get(10)?,
+            patient_gender: row.get(11)?,
+            status,
+            expires_at: row.get(6)?,
+            created_at: row.get(7)?,
+            verify_birth_date: row.get::<_, i32>(12)? != 0,
+            display_order: parse_display_order(row.get(13)?),
         })
-    })?;
+    });
 
-    let mut templates = Vec::new();
-    for row in rows {
-        templates.push(row?);
+    match result {
+        Ok(session) => {
+            // 만료 확인
+            if session.status == SessionStatus::Pending {
+                if let Ok(expires) = chrono::DateTime::parse_from_rfc3339(&session.expires_at) {
+                    if expires < Utc::now() {
+                        // 만료 처리
+                        conn.execute(
+                            "UPDATE survey_sessions SET status = 'expired' WHERE id = ?1",
+                            [&session.id],
+                        )?;
+                        return Ok(Some(SurveySessionDb {
+                            status: SessionStatus::Expired,
+                            ..session
+                        }));
+                    }
+                }
+            }
+            Ok(Some(session))
+        }
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
     }
-    Ok(templates)
 }
 
-/// 설문 템플릿 삭제
-pub fn delete_survey_template(id: &str) -> AppResult<()> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
-    conn.execute("DELETE FROM survey_templates WHERE id = ?1", [id])?;
-    log::info!("설문 템플릿 삭제됨: {}", id);
+/// 저장된 문항 JSON 파싱 (`order` 순 정렬, 순서 없는 문항은 뒤로)
+fn parse_display_order(json: Option<String>) -> Option<SurveyDisplayOrder> {
+    json.and_then(|s| serde_json::from_str(&s).ok())
+}
+
+fn parse_template_questions(questions_json: &str) -> Vec<SurveyQuestion> {
+    let mut questions: Vec<SurveyQuestion> = serde_json::from_str(questions_json).unwrap_or_default();
+    questions.sort_by_key(|q| q.order.unwrap_or(i32::MAX));
+    questions
+}
+
+/// 문항 순서 검증 - 모든 문항에 서로 다른 order가 있어야 함
+fn validate_question_order(questions: &[SurveyQuestion]) -> AppResult<()> {
+    let mut seen = std::collections::HashSet::new();
+    for (i, q) in questions.iter().enumerate() {
+        let Some(order) = q.order else {
+            return Err(AppError::Custom(format!("{}번 문항의 순서(order)가 없습니다", i + 1)));
+        };
+        if !seen.insert(order) {
+            return Err(AppError::Custom(format!("문항 순서가 중복됩니다: {}", order)));
+        }
+    }
     Ok(())
 }
 
-/// 설문 응답 삭제
-pub fn delete_survey_response(id: &str) -> AppResult<()> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
-    conn.execute("DELETE FROM survey_responses WHERE id = ?1", [id])?;
-    log::info!("설문 응답 삭제됨: {}", id);
+/// 척도형 문항 설정 검증 (최소값 < 최대값, 간격 > 0)
+fn validate_scale_configs(questions: &[SurveyQuestion]) -> AppResult<()> {
+    for (i, q) in questions.iter().enumerate() {
+        if !matches!(q.question_type, QuestionType::Scale) {
+            continue;
+        }
+        let Some(config) = q.scale_config.as_ref() else {
+            return Err(AppError::Validation(format!("{}번 문항에 척도 설정이 없습니다", i + 1)));
+        };
+        if config.min >= config.max {
+            return Err(AppError::Validation(format!(
+                "{}번 문항의 척도 최소값({})은 최대값({})보다 작아야 합니다",
+                i + 1, config.min, config.max
+            )));
+        }
+        if config.step() <= 0 {
+            return Err(AppError::Validation(format!("{}번 문항의 척도 간격은 1 이상이어야 합니다", i + 1)));
+        }
+    }
     Ok(())
 }
 
-/// 설문 응답에 환자 연결
-pub fn link_survey_response_to_patient(response_id: &str, patient_id: &str) -> AppResult<()> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
+/// 척도형 답변 검증 (범위와 간격, 빈 답변은 건너뜀)
+pub fn validate_scale_answers(questions: &[SurveyQuestion], answers: &[SurveyAnswer]) -> AppResult<()> {
+    for answer in answers {
+        let Some((q, config)) = questions
+            .iter()
+            .find(|q| q.id == answer.question_id && matches!(q.question_type, QuestionType::Scale))
+            .and_then(|q| q.scale_config.as_ref().map(|c| (q, c)))
+        else {
+            continue;
+        };
+        let value = match &answer.answer {
+            serde_json::Value::Null => continue,
+            serde_json::Value::String(s) if s.trim().is_empty() => continue,
+            serde_json::Value::Number(n) => n.as_i64(),
+            serde_json::Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        };
+        if !value.is_some_and(|v| config.accepts(v)) {
+            return Err(AppError::Validation(format!(
+                "'{}' 문항의 답변은 {}~{} 사이 {} 단위 값이어야 합니다",
+                q.question_text, config.min, config.max, config.step()
+            )));
+        }
+    }
+    Ok(())
+}
 
-    // 환자 이름 조회
-    let patient_name: Option<String> = conn.query_row(
-        "SELECT name FROM patients WHERE id = ?1",
-        [patient_id],
-        |row| row.get(0),
-    ).ok();
+/// 설문 완료 후 이동 주소 최대 길이
+const REDIRECT_URL_MAX_LEN: usize = 2048;
 
-    conn.execute(
-        "UPDATE survey_responses SET patient_id = ?1 WHERE id = ?2",
-        params![patient_id, response_id],
-    )?;
+/// 설문 완료 후 이동 주소 검증 (http/https만, 계정 정보·공백·역슬래시 불가, 빈 값이면 None)
+///
+/// 주소는 직원이 템플릿에 저장한 값만 쓰고 요청 파라미터로는 받지 않으므로 임의 주소로의 이동(오픈 리다이렉트)은 생기지 않습니다.
+pub fn validate_redirect_url(url: Option<&str>) -> AppResult<Option<String>> {
+    let Some(url) = url.map(str::trim).filter(|u| !u.is_empty()) else {
+        return Ok(None);
+    };
+    let invalid = || AppError::Validation(format!("이동할 주소가 올바르지 않습니다: {}", url));
 
-    log::info!("설문 응답 환자 연결: {} -> {} ({})", response_id, patient_id, patient_name.unwrap_or_default());
-    Ok(())
+    if url.len() > REDIRECT_URL_MAX_LEN || url.chars().any(|c| c.is_whitespace() || c.is_control() || c == '\\') {
+        return Err(invalid());
+    }
+    let lower = url.to_ascii_lowercase();
+    let rest = ["https://", "http://"]
+        .iter()
+        .find_map(|scheme| lower.strip_prefix(scheme))
+        .ok_or_else(invalid)?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit_once(':').map_or(authority, |(host, _)| host);
+    if host.is_empty() || authority.contains('@') {
+        return Err(invalid());
+    }
+    Ok(Some(url.to_string()))
 }
 
-/// 기본 설문 템플릿 복원
-pub fn restore_default_templates() -> AppResult<()> {
+/// 설문 템플릿 저장
+pub fn save_survey_template(template: &SurveyTemplateDb) -> AppResult<()> {
     ensure_db_initialized()?;
-    ensure_default_templates()?;
-    log::info!("기본 설문 템플릿이 복원되었습니다.");
+    validate_question_order(&template.questions)?;
+    validate_scale_configs(&template.questions)?;
+    if template.anonymous && template.require_contact {
+        return Err(AppError::Custom("익명 설문에는 연락처 필수를 함께 설정할 수 없습니다".to_string()));
+    }
+    let redirect_url = validate_redirect_url(template.redirect_url.as_deref())?;
+    let category = normalize_template_category(template.category.as_deref());
+    let conn = get_conn()?;
+    let questions_json = serde_json::to_string(&template.questions)?;
+    let now = Utc::now().to_rfc3339();
+
+    conn.execute(
+        r#"INSERT OR REPLACE INTO survey_templates (id, name, description, questions, display_mode, is_active, created_at, updated_at, require_contact, anonymous, redirect_url, category, randomize_questions, randomize_options, pin_required_questions)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)"#,
+        params![
+            template.id,
+            template.name,
+            template.description,
+            questions_json,
+            template.display_mode,
+            if template.is_active { 1 } else { 0 },
+            now,
+            now,
+            template.require_contact,
+            template.anonymous,
+            redirect_url,
+            category,
+            template.randomize_questions,
+            template.randomize_options,
+            template.pin_required_questions,
+        ],
+    )?;
     Ok(())
 }
 
-// ============ 설문 응답 관리 (Tauri 명령어용) ============
+/// 템플릿 분류 정리 (앞뒤 공백 제거, 비어 있으면 분류 없음)
+fn normalize_template_category(category: Option<&str>) -> Option<String> {
+    category.map(str::trim).filter(|c| !c.is_empty()).map(str::to_string)
+}
 
-/// 설문 응답 제출 (프론트엔드에서 직접 호출)
-/// 세션은 sql.js에만 존재하므로 FK 체크를 일시 비활성화
-pub fn submit_survey_response(
-    session_id: Option<&str>,
-    template_id: &str,
-    patient_id: Option<&str>,
-    respondent_name: Option<&str>,
-    answers: &[SurveyAnswer],
-) -> AppResult<()> {
+/// 설문 템플릿 조회
+pub fn get_survey_template(id: &str) -> AppResult<Option<SurveyTemplateDb>> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    let id = uuid::Uuid::new_v4().to_string();
-    let answers_json = serde_json::to_string(answers)?;
-    let now = Utc::now().to_rfc3339();
+    let mut stmt = conn.prepare(
+        "SELECT id, name, description, questions, display_mode, is_active, require_contact, anonymous, redirect_url, category, randomize_questions, randomize_options, pin_required_questions
+         FROM survey_templates WHERE id = ?1",
+    )?;
 
-    // 세션은 sql.js에만 있고 clinic.db에는 없으므로 FK 체크 일시 비활성화
-    conn.execute_batch("PRAGMA foreign_keys = OFF")?;
+    let result = stmt.query_row([id], |row| {
+        let questions_json: String = row.get(3)?;
+        let questions = parse_template_questions(&questions_json);
+        let is_active: i32 = row.get(5)?;
+        Ok(SurveyTemplateDb {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            description: row.get(2)?,
+            questions,
+            display_mode: row.get(4)?,
+            is_active: is_active != 0,
+            require_contact: row.get::<_, Option<bool>>(6)?.unwrap_or(false),
+            anonymous: row.get::<_, Option<bool>>(7)?.unwrap_or(false),
+            redirect_url: row.get(8)?,
+            category: row.get(9)?,
+            randomize_questions: row.get::<_, Option<bool>>(10)?.unwrap_or(false),
+            randomize_options: row.get::<_, Option<bool>>(11)?.unwrap_or(false),
+            pin_required_questions: row.get::<_, Option<bool>>(12)?.unwrap_or(false),
+        })
+    });
 
-    let result = conn.execute(
-        r#"INSERT INTO survey_responses (id, session_id, template_id, patient_id, respondent_name, answers, submitted_at)
-           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"#,
-        params![id, session_id, template_id, patient_id, respondent_name, answers_json, now],
-    );
+    match result {
+        Ok(template) => Ok(Some(template)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
 
-    conn.execute_batch("PRAGMA foreign_keys = ON")?;
-    result?;
+/// 설문 템플릿 내보내기 형식 (다른 한의원/기기로 옮길 수 있는 JSON)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SurveyTemplateExport {
+    pub format: String,
+    pub schema_version: u32,
+    pub name: String,
+    pub description: Option<String>,
+    pub display_mode: Option<String>,
+    pub questions: Vec<SurveyQuestion>,
+    #[serde(default)]
+    pub require_contact: bool,
+    #[serde(default)]
+    pub anonymous: bool,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub randomize_questions: bool,
+    #[serde(default)]
+    pub randomize_options: bool,
+    #[serde(default)]
+    pub pin_required_questions: bool,
+    pub exported_at: Option<String>,
+}
+
+pub const SURVEY_TEMPLATE_EXPORT_FORMAT: &str = "gosibang.survey_template";
+/// 내보내기 스키마 버전 (1: `version` 필드, 문항 필드 일부 생략 가능 / 2: `schema_version`, 모든 필드 명시)
+pub const SURVEY_TEMPLATE_EXPORT_VERSION: u32 = 2;
+
+/// 지원하는 문항 유형 (가져오기 시 미지원 유형 거부)
+const SUPPORTED_QUESTION_TYPES: [&str; 6] = ["text", "single_choice", "multiple_choice", "scale", "yes_no", "file_upload"];
+
+/// 새 템플릿/문항 ID를 부여해 저장 (`source.id`는 무시)
+fn insert_template_copy(source: SurveyTemplateDb) -> AppResult<SurveyTemplateDb> {
+    let mut questions = source.questions;
+    // 순서가 없던 이전 형식이면 현재 배열 순서대로 번호 부여
+    if questions.iter().any(|q| q.order.is_none()) {
+        for (i, q) in questions.iter_mut().enumerate() {
+            q.order = Some(i as i32 + 1);
+        }
+    }
+    let template = SurveyTemplateDb {
+        id: uuid::Uuid::new_v4().to_string(),
+        questions: questions
+            .into_iter()
+            .map(|q| SurveyQuestion { id: uuid::Uuid::new_v4().to_string(), ..q })
+            .collect(),
+        ..source
+    };
+    save_survey_template(&template)?;
+    Ok(template)
+}
 
-    log::info!("설문 응답 제출됨: {} (template: {})", id, template_id);
-    Ok(())
+/// 설문 템플릿 복제 (이름에 " (복사본)" 추가, 문항 ID 재생성)
+///
+/// 복사본은 비활성으로 만들어 편집을 마치고 켜기 전까지 키오스크/설문 링크에 나오지 않습니다.
+pub fn duplicate_survey_template(id: &str) -> AppResult<Option<SurveyTemplateDb>> {
+    let Some(source) = get_survey_template(id)? else {
+        return Ok(None);
+    };
+
+    let copy = insert_template_copy(SurveyTemplateDb {
+        name: format!("{} (복사본)", source.name),
+        is_active: false,
+        ..source
+    })?;
+    log::info!("설문 템플릿 복제됨: {} -> {}", id, copy.id);
+    Ok(Some(copy))
 }
 
-/// 동기화된 설문 응답 저장 (Supabase에서 수신, session_id로 중복 체크)
-/// 이미 존재하면 false 반환, 새로 저장하면 true 반환
-/// 세션은 sql.js에만 존재하므로 FK 체크를 일시 비활성화
-pub fn save_survey_response_from_sync(
-    session_id: &str,
-    template_id: &str,
-    patient_id: Option<&str>,
-    respondent_name: Option<&str>,
-    answers: &[SurveyAnswer],
-    submitted_at: &str,
-) -> AppResult<bool> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
+/// 문항 순서 변경 (`question_ids`는 템플릿의 모든 문항 ID를 원하는 순서로)
+pub fn reorder_survey_template(id: &str, question_ids: &[String]) -> AppResult<Option<SurveyTemplateDb>> {
+    let Some(mut template) = get_survey_template(id)? else {
+        return Ok(None);
+    };
 
-    // session_id로 중복 체크
-    let existing: Option<String> = conn
-        .query_row(
-            "SELECT id FROM survey_responses WHERE session_id = ?1",
-            [session_id],
-            |row| row.get(0),
-        )
-        .ok();
+    let unique: std::collections::HashSet<&str> = question_ids.iter().map(|s| s.as_str()).collect();
+    if unique.len() != question_ids.len() {
+        return Err(AppError::Custom("문항 ID가 중복되었습니다".to_string()));
+    }
+    if question_ids.len() != template.questions.len()
+        || template.questions.iter().any(|q| !unique.contains(q.id.as_str()))
+    {
+        return Err(AppError::Custom("템플릿의 모든 문항 ID를 한 번씩 전달해야 합니다".to_string()));
+    }
 
-    if existing.is_some() {
-        log::info!("설문 응답 이미 존재 (session: {})", session_id);
-        return Ok(false);
+    for q in template.questions.iter_mut() {
+        let position = question_ids.iter().position(|qid| *qid == q.id).unwrap_or_default();
+        q.order = Some(position as i32 + 1);
+    }
+    template.questions.sort_by_key(|q| q.order);
+    save_survey_template(&template)?;
+    Ok(Some(template))
+}
+
+/// 설문 템플릿 내보내기
+pub fn export_survey_template(id: &str) -> AppResult<Option<SurveyTemplateExport>> {
+    Ok(get_survey_template(id)?.map(|t| SurveyTemplateExport {
+        format: SURVEY_TEMPLATE_EXPORT_FORMAT.to_string(),
+        schema_version: SURVEY_TEMPLATE_EXPORT_VERSION,
+        name: t.name,
+        description: t.description,
+        display_mode: t.display_mode,
+        questions: t.questions,
+        require_contact: t.require_contact,
+        anonymous: t.anonymous,
+        category: t.category,
+        randomize_questions: t.randomize_questions,
+        randomize_options: t.randomize_options,
+        pin_required_questions: t.pin_required_questions,
+        exported_at: Some(Utc::now().to_rfc3339()),
+    }))
+}
+
+/// 가져올 템플릿 JSON을 현재 스키마 버전으로 변환 후 파싱 (더 새로운 버전/미지원 문항 유형은 거부)
+pub fn parse_survey_template_import(mut value: serde_json::Value) -> AppResult<SurveyTemplateExport> {
+    let invalid = |msg: String| Err(AppError::Custom(msg));
+
+    let Some(obj) = value.as_object_mut() else {
+        return invalid("설문 템플릿 파일 형식이 아닙니다".to_string());
+    };
+    if obj.get("format").and_then(|v| v.as_str()) != Some(SURVEY_TEMPLATE_EXPORT_FORMAT) {
+        return invalid("설문 템플릿 파일 형식이 아닙니다".to_string());
     }
 
-    let id = uuid::Uuid::new_v4().to_string();
-    let answers_json = serde_json::to_string(answers)?;
+    // 버전 필드가 없으면 최초 형식(1)으로 간주
+    let version = match obj.get("schema_version").or_else(|| obj.get("version")) {
+        Some(v) => v
+            .as_u64()
+            .ok_or_else(|| AppError::Custom("템플릿 버전이 올바르지 않습니다".to_string()))?,
+        None => 1,
+    };
+    if version == 0 || version > SURVEY_TEMPLATE_EXPORT_VERSION as u64 {
+        return invalid(format!(
+            "지원하지 않는 템플릿 버전입니다: {} (지원 버전: 1~{})",
+            version, SURVEY_TEMPLATE_EXPORT_VERSION
+        ));
+    }
+    if version < 2 {
+        upgrade_template_export_v1(obj);
+    }
 
-    // 세션은 sql.js에만 있고 clinic.db에는 없으므로 FK 체크 일시 비활성화
-    conn.execute_batch("PRAGMA foreign_keys = OFF")?;
+    if let Some(questions) = obj.get("questions").and_then(|v| v.as_array()) {
+        for (i, q) in questions.iter().enumerate() {
+            let question_type = q.get("question_type").and_then(|v| v.as_str()).unwrap_or_default();
+            if !SUPPORTED_QUESTION_TYPES.contains(&question_type) {
+                return invalid(format!("{}번 문항의 유형을 지원하지 않습니다: {}", i + 1, question_type));
+            }
+        }
+    }
 
-    let result = conn.execute(
-        r#"INSERT INTO survey_responses (id, session_id, template_id, patient_id, respondent_name, answers, submitted_at)
-           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"#,
-        params![id, session_id, template_id, patient_id, respondent_name, answers_json, submitted_at],
-    );
+    serde_json::from_value(value).map_err(|e| AppError::Custom(format!("템플릿 파일을 읽을 수 없습니다: {}", e)))
+}
 
-    conn.execute_batch("PRAGMA foreign_keys = ON")?;
-    result?;
+/// 버전 1 → 2: `version` → `schema_version`, 생략된 필드 기본값 채움
+fn upgrade_template_export_v1(obj: &mut serde_json::Map<String, serde_json::Value>) {
+    use serde_json::Value;
 
-    log::info!("동기화 설문 응답 저장됨: {} (session: {})", id, session_id);
-    Ok(true)
-}
+    obj.remove("version");
+    obj.insert("schema_version".to_string(), Value::from(2));
+    for key in ["require_contact", "anonymous"] {
+        obj.entry(key).or_insert(Value::Bool(false));
+    }
+    for key in ["description", "display_mode", "exported_at"] {
+        obj.entry(key).or_insert(Value::Null);
+    }
 
-// ============ 내부 직원 계정 관리 ============
+    let Some(questions) = obj.get_mut("questions").and_then(|v| v.as_array_mut()) else {
+        return;
+    };
+    for q in questions.iter_mut().filter_map(|q| q.as_object_mut()) {
+        // 문항 ID는 가져올 때 새로 부여하므로 빈 값으로 채움
+        q.entry("id").or_insert(Value::from(""));
+        q.entry("required").or_insert(Value::Bool(false));
+        for key in ["options", "scale_config"] {
+            q.entry(key).or_insert(Value::Null);
+        }
+    }
+}
 
-/// 직원 계정 생성
-pub fn create_staff_account(account: &StaffAccount) -> AppResult<()> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
+/// 설문 템플릿 가져오기 (검증 후 새 ID로 저장)
+pub fn import_survey_template(data: SurveyTemplateExport) -> AppResult<SurveyTemplateDb> {
+    validate_template_export(&data)?;
+
+    let template = insert_template_copy(SurveyTemplateDb {
+        id: String::new(),
+        name: data.name,
+        description: data.description,
+        questions: data.questions,
+        display_mode: data.display_mode,
+        is_active: true,
+        require_contact: data.require_contact,
+        anonymous: data.anonymous,
+        randomize_questions: data.randomize_questions,
+        randomize_options: data.randomize_options,
+        pin_required_questions: data.pin_required_questions,
+        // 이동 주소는 한의원마다 다르므로 내보내기/가져오기에 포함하지 않음
+        redirect_url: None,
+        category: data.category,
+    })?;
+    log::info!("설문 템플릿 가져옴: {} ({}문항)", template.id, template.questions.len());
+    Ok(template)
+}
 
-    let permissions_json = serde_json::to_string(&account.permissions)?;
+fn validate_template_export(data: &SurveyTemplateExport) -> AppResult<()> {
+    let invalid = |msg: String| Err(AppError::Custom(msg));
 
-    conn.execute(
-        r#"INSERT INTO staff_accounts (id, username, display_name, password_hash, role, permissions, is_active, last_login_at, created_at, updated_at)
-           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"#,
-        params![
-            account.id,
-            account.username,
-            account.display_name,
-            account.password_hash,
-            account.role.as_str(),
-            permissions_json,
-            account.is_active,
-            account.last_login_at.map(|d| d.to_rfc3339()),
-            account.created_at.to_rfc3339(),
-            account.updated_at.to_rfc3339(),
-        ],
-    )?;
+    if data.format != SURVEY_TEMPLATE_EXPORT_FORMAT {
+        return invalid("설문 템플릿 파일 형식이 아닙니다".to_string());
+    }
+    if data.schema_version != SURVEY_TEMPLATE_EXPORT_VERSION {
+        return invalid(format!("지원하지 않는 템플릿 버전입니다: {}", data.schema_version));
+    }
+    if data.name.trim().is_empty() {
+        return invalid("템플릿 이름이 비어 있습니다".to_string());
+    }
+    if data.questions.is_empty() {
+        return invalid("문항이 없습니다".to_string());
+    }
 
-    log::info!("직원 계정 생성됨: {} ({})", account.username, account.role.as_str());
+    for (i, q) in data.questions.iter().enumerate() {
+        let n = i + 1;
+        if q.question_text.trim().is_empty() {
+            return invalid(format!("{}번 문항의 내용이 비어 있습니다", n));
+        }
+        match q.question_type {
+            QuestionType::SingleChoice | QuestionType::MultipleChoice => {
+                if q.options.as_ref().map_or(true, |o| o.is_empty()) {
+                    return invalid(format!("{}번 문항에 선택지가 없습니다", n));
+                }
+            }
+            QuestionType::Scale => {
+                if !q.scale_config.as_ref().is_some_and(|c| c.is_valid()) {
+                    return invalid(format!("{}번 문항의 척도 범위가 올바르지 않습니다", n));
+                }
+            }
+            QuestionType::Text | QuestionType::YesNo | QuestionType::FileUpload => {}
+        }
+    }
     Ok(())
 }
 
-/// 직원 계정 조회 (ID로)
-pub fn get_staff_account(id: &str) -> AppResult<Option<StaffAccount>> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
+/// 설문 제출 결과
+pub enum SurveySubmission {
+    /// 새로 저장됨 (동기화용 데이터)
+    Saved(SurveyResponseDb),
+    /// 같은 멱등 키로 이미 저장된 요청의 재시도
+    Duplicate,
+    /// 다른 요청으로 이미 제출된 세션
+    AlreadySubmitted,
+}
+
+/// 템플릿의 익명 설정 (템플릿이 없으면 false)
+fn query_template_anonymous(conn: &Connection, template_id: &str) -> AppResult<bool> {
+    match conn.query_row(
+        "SELECT anonymous FROM survey_templates WHERE id = ?1",
+        [template_id],
+        |row| row.get::<_, bool>(0),
+    ) {
+        Ok(anonymous) => Ok(anonymous),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
 
-    let mut stmt = conn.prepare(
-        r#"SELECT id, username, display_name, password_hash, role, permissions, is_active, last_login_at, created_at, updated_at
-           FROM staff_accounts WHERE id = ?1"#,
-    )?;
+/// 익명 응답은 환자와 연결할 수 없음
+fn ensure_response_linkable(conn: &Connection, response_id: &str) -> AppResult<()> {
+    let anonymous = conn
+        .query_row(
+            "SELECT anonymous FROM survey_responses WHERE id = ?1",
+            [response_id],
+            |row| row.get::<_, bool>(0),
+        )
+        .unwrap_or(false);
+    if anonymous {
+        return Err(AppError::Custom("익명 설문 응답은 환자와 연결할 수 없습니다".to_string()));
+    }
+    Ok(())
+}
 
-    let result = stmt.query_row([id], |row| {
-        let permissions_str: String = row.get(5)?;
-        let permissions: StaffPermissions = serde_json::from_str(&permissions_str).unwrap_or_default();
-        let role_str: String = row.get(4)?;
+/// 설문 제출 시 응답자가 입력한 연락처
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct RespondentContact {
+    pub respondent_name: Option<String>,
+    pub phone: Option<String>,
+    pub birth_date: Option<String>,
+}
+
+impl RespondentContact {
+    /// 공백 제거 후 빈 값은 None으로
+    pub fn normalized(&self) -> Self {
+        let clean = |v: &Option<String>| v.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string);
+        Self {
+            respondent_name: clean(&self.respondent_name),
+            phone: clean(&self.phone),
+            birth_date: clean(&self.birth_date),
+        }
+    }
+}
 
-        Ok(StaffAccount {
-            id: row.get(0)?,
-            username: row.get(1)?,
-            display_name: row.get(2)?,
-            password_hash: row.get(3)?,
-            role: StaffRole::from_str(&role_str),
-            permissions,
-            is_active: row.get(6)?,
-            last_login_at: row.get::<_, Option<String>>(7)?
-                .and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
-                .map(|d| d.with_timezone(&Utc)),
-            created_at: row.get::<_, String>(8)?
-                .parse::<chrono::DateTime<Utc>>()
-                .unwrap_or_else(|_| Utc::now()),
-            updated_at: row.get::<_, String>(9)?
-                .parse::<chrono::DateTime<Utc>>()
-                .unwrap_or_else(|_| Utc::now()),
-        })
-    });
+/// 연락처 필수 템플릿 검증 - 이름(입력값 또는 세션 이름)과 휴대폰/생년월일 중 하나
+pub fn validate_respondent_contact(contact: &RespondentContact, session_name: Option<&str>) -> AppResult<()> {
+    let has_name = contact.respondent_name.is_some() || session_name.is_some_and(|s| !s.trim().is_empty());
+    if !has_name {
+        return Err(AppError::Custom("응답자 이름을 입력해주세요".to_string()));
+    }
 
-    match result {
-        Ok(account) => Ok(Some(account)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(e.into()),
+    let digits = |s: &str| s.chars().filter(|c| c.is_ascii_digit()).count();
+    if let Some(phone) = &contact.phone {
+        if !(9..=11).contains(&digits(phone)) {
+            return Err(AppError::Custom("휴대폰 번호 형식이 올바르지 않습니다".to_string()));
+        }
+    }
+    if let Some(birth_date) = &contact.birth_date {
+        if chrono::NaiveDate::parse_from_str(birth_date, "%Y-%m-%d").is_err() {
+            return Err(AppError::Custom("생년월일 형식이 올바르지 않습니다 (YYYY-MM-DD)".to_string()));
+        }
     }
+    if contact.phone.is_none() && contact.birth_date.is_none() {
+        return Err(AppError::Custom("휴대폰 번호 또는 생년월일을 입력해주세요".to_string()));
+    }
+    Ok(())
 }
 
-/// 직원 계정 조회 (username으로)
-pub fn get_staff_account_by_username(username: &str) -> AppResult<Option<StaffAccount>> {
-    ensure_db_initialized()?;
+/// 설문 응답 저장 및 세션 완료 (세션당 한 번만 저장)
+pub fn save_survey_response(
+    session_id: &str,
+    template_id: &str,
+    patient_id: Option<&str>,
+    respondent_name: Option<&str>,
+    contact: &RespondentContact,
+    answers: &[SurveyAnswer],
+    idempotency_key: Option<&str>,
+) -> AppResult<SurveySubmission> {
     let conn = get_conn()?;
+    let id = uuid::Uuid::new_v4().to_string();
+    let answers_json = serde_json::to_string(answers)?;
+    let now = Utc::now().to_rfc3339();
 
-    let mut stmt = conn.prepare(
-        r#"SELECT id, username, display_name, password_hash, role, permissions, is_active, last_login_at, created_at, updated_at
-           FROM staff_accounts WHERE username = ?1"#,
+    // 세션 완료 전환(pending → completed)과 응답 저장을 한 트랜잭션으로 처리 (중복 제출 방지)
+    let tx = conn.unchecked_transaction()?;
+    let updated = tx.execute(
+        "UPDATE survey_sessions SET status = 'completed', completed_at = ?1 WHERE id = ?2 AND status = 'pending'",
+        params![now, session_id],
     )?;
+    if updated == 0 {
+        let duplicate = match idempotency_key {
+            Some(key) => tx
+                .query_row(
+                    "SELECT COUNT(*) FROM survey_responses WHERE session_id = ?1 AND idempotency_key = ?2",
+                    params![session_id, key],
+                    |row| row.get::<_, i32>(0),
+                )
+                .map(|count| count > 0)?,
+            None => false,
+        };
+        return Ok(if duplicate {
+            SurveySubmission::Duplicate
+        } else {
+            SurveySubmission::AlreadySubmitted
+        });
+    }
 
-    let result = stmt.query_row([username], |row| {
-        let permissions_str: String = row.get(5)?;
-        let permissions: StaffPermissions = serde_json::from_str(&permissions_str).unwrap_or_default();
-        let role_str: String = row.get(4)?;
+    // 익명 설문은 요청에 이름/환자가 섞여 들어와도 저장하지 않음
+    let anonymous = query_template_anonymous(&tx, template_id)?;
+    let (patient_id, respondent_name, contact) = if anonymous {
+        (None, None, &RespondentContact::default())
+    } else {
+        (patient_id, respondent_name, contact)
+    };
 
-        Ok(StaffAccount {
-            id: row.get(0)?,
-            username: row.get(1)?,
-            display_name: row.get(2)?,
-            password_hash: row.get(3)?,
-            role: StaffRole::from_str(&role_str),
-            permissions,
-            is_active: row.get(6)?,
-            last_login_at: row.get::<_, Option<String>>(7)?
-                .and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
-                .map(|d| d.with_timezone(&Utc)),
-            created_at: row.get::<_, String>(8)?
-                .parse::<chrono::DateTime<Utc>>()
-                .unwrap_or_else(|_| Utc::now()),
-            updated_at: row.get::<_, String>(9)?
-                .parse::<chrono::DateTime<Utc>>()
-                .unwrap_or_else(|_| Utc::now()),
-        })
-    });
+    tx.execute(
+        r#"INSERT INTO survey_responses (id, session_id, template_id, patient_id, respondent_name, answers, submitted_at, idempotency_key, respondent_phone, respondent_birth_date, anonymous)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)"#,
+        params![
+            id,
+            session_id,
+            template_id,
+            patient_id,
+            respondent_name,
+            answers_json,
+            now.clone(),
+            idempotency_key,
+            contact.phone,
+            contact.birth_date,
+            anonymous,
+        ],
+    )?;
+    link_survey_attachments(&tx, &id, session_id, answers)?;
+    tx.commit()?;
+    apply_survey_stats_delta(template_id, patient_id.is_some(), &now, 1);
 
-    match result {
-        Ok(account) => Ok(Some(account)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(e.into()),
-    }
+    let response = SurveyResponseDb {
+        id,
+        session_id: Some(session_id.to_string()),
+        template_id: template_id.to_string(),
+        patient_id: patient_id.map(|s| s.to_string()),
+        respondent_name: respondent_name.map(|s| s.to_string()),
+        answers: answers_json,
+        submitted_at: now,
+    };
+
+    Ok(SurveySubmission::Saved(response))
 }
 
-/// 직원 계정 목록 조회
-pub fn list_staff_accounts() -> AppResult<Vec<StaffAccountInfo>> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
+// ============ 설문 첨부 파일 ============
 
-    let mut stmt = conn.prepare(
-        r#"SELECT id, username, display_name, password_hash, role, permissions, is_active, last_login_at, created_at, updated_at
-           FROM staff_accounts ORDER BY created_at DESC"#,
-    )?;
+/// 첨부 파일 최대 크기 (10MB)
+pub const SURVEY_ATTACHMENT_MAX_BYTES: usize = 10 * 1024 * 1024;
 
-    let rows = stmt.query_map([], |row| {
-        let permissions_str: String = row.get(5)?;
-        let permissions: StaffPermissions = serde_json::from_str(&permissions_str).unwrap_or_default();
-        let role_str: String = row.get(4)?;
+/// 세션당 최대 첨부 파일 수 (교체 업로드 포함)
+const SURVEY_ATTACHMENT_MAX_PER_SESSION: i64 = 20;
 
-        Ok(StaffAccountInfo {
-            id: row.get(0)?,
-            username: row.get(1)?,
-            display_name: row.get(2)?,
-            role: StaffRole::from_str(&role_str),
-            permissions,
-            is_active: row.get(6)?,
-            last_login_at: row.get::<_, Option<String>>(7)?
-                .and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
-                .map(|d| d.with_timezone(&Utc)),
-            created_at: row.get::<_, String>(8)?
-                .parse::<chrono::DateTime<Utc>>()
-                .unwrap_or_else(|_| Utc::now()),
-            updated_at: row.get::<_, String>(9)?
-                .parse::<chrono::DateTime<Utc>>()
-                .unwrap_or_else(|_| Utc::now()),
-        })
-    })?;
+/// 허용 MIME 타입 (검사지 사진/스캔본)
+const SURVEY_ATTACHMENT_MIME_TYPES: [&str; 5] = ["image/jpeg", "image/png", "image/webp", "image/heic", "application/pdf"];
 
-    let mut accounts = Vec::new();
-    for row in rows {
-        accounts.push(row?);
+/// 파일 앞부분이 선언한 MIME 타입과 맞는지 (확장자만 바꾼 파일 거부)
+fn matches_mime_signature(mime_type: &str, data: &[u8]) -> bool {
+    match mime_type {
+        "image/jpeg" => data.starts_with(&[0xFF, 0xD8, 0xFF]),
+        "image/png" => data.starts_with(b"\x89PNG\r\n\x1a\n"),
+        "image/webp" => data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP",
+        "image/heic" => data.len() >= 12 && &data[4..8] == b"ftyp",
+        "application/pdf" => data.starts_with(b"%PDF-"),
+        _ => false,
     }
-    Ok(accounts)
 }
 
-/// 직원 계정 수정
-pub fn update_staff_account(account: &StaffAccount) -> AppResult<()> {
+/// 설문 첨부 파일 저장 (대기 중인 세션의 파일 첨부 문항만, 제출 전에 업로드)
+pub fn save_survey_attachment(
+    session_id: &str,
+    question_id: &str,
+    file_name: &str,
+    mime_type: &str,
+    data: &[u8],
+) -> AppResult<SurveyAttachment> {
+    let mime_type = mime_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    if !SURVEY_ATTACHMENT_MIME_TYPES.contains(&mime_type.as_str()) {
+        return Err(AppError::Validation(format!("지원하지 않는 파일 형식입니다: {}", mime_type)));
+    }
+    if data.is_empty() {
+        return Err(AppError::Validation("빈 파일입니다".to_string()));
+    }
+    if data.len() > SURVEY_ATTACHMENT_MAX_BYTES {
+        return Err(AppError::Validation(format!(
+            "파일이 너무 큽니다 (최대 {}MB)",
+            SURVEY_ATTACHMENT_MAX_BYTES / 1024 / 1024
+        )));
+    }
+    if !matches_mime_signature(&mime_type, data) {
+        return Err(AppError::Validation("파일 내용이 형식과 맞지 않습니다".to_string()));
+    }
+
+    // 경로는 버리고 파일 이름만 보관
+    let file_name = file_name.rsplit(['/', '\\']).next().unwrap_or_default().trim();
+    let file_name = if file_name.is_empty() {
+        "첨부파일".to_string()
+    } else {
+        file_name.chars().take(200).collect()
+    };
+
     ensure_db_initialized()?;
     let conn = get_conn()?;
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM survey_attachments WHERE session_id = ?1",
+        [session_id],
+        |row| row.get(0),
+    )?;
+    if count >= SURVEY_ATTACHMENT_MAX_PER_SESSION {
+        return Err(AppError::Validation("첨부 파일이 너무 많습니다".to_string()));
+    }
 
-    let permissions_json = serde_json::to_string(&account.permissions)?;
-
+    let attachment = SurveyAttachment {
+        id: uuid::Uuid::new_v4().to_string(),
+        session_id: session_id.to_string(),
+        question_id: question_id.to_string(),
+        response_id: None,
+        file_name,
+        mime_type,
+        size: data.len() as i64,
+        created_at: Utc::now().to_rfc3339(),
+    };
     conn.execute(
-        r#"UPDATE staff_accounts
-           SET username = ?2, display_name = ?3, password_hash = ?4, role = ?5,
-               permissions = ?6, is_active = ?7, updated_at = ?8
-           WHERE id = ?1"#,
+        r#"INSERT INTO survey_attachments (id, session_id, question_id, file_name, mime_type, size, data, created_at)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"#,
         params![
-            account.id,
-            account.username,
-            account.display_name,
-            account.password_hash,
-            account.role.as_str(),
-            permissions_json,
-            account.is_active,
-            Utc::now().to_rfc3339(),
+            attachment.id,
+            attachment.session_id,
+            attachment.question_id,
+            attachment.file_name,
+            attachment.mime_type,
+            attachment.size,
+            data,
+            attachment.created_at,
         ],
     )?;
-
-    log::info!("직원 계정 수정됨: {}", account.username);
-    Ok(())
+    Ok(attachment)
 }
 
-/// 직원 계정 삭제
-pub fn delete_staff_account(id: &str) -> AppResult<()> {
+/// 첨부 파일 조회 (정보, 파일 내용)
+pub fn get_survey_attachment(id: &str) -> AppResult<Option<(SurveyAttachment, Vec<u8>)>> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    conn.execute("DELETE FROM staff_accounts WHERE id = ?1", [id])?;
-    log::info!("직원 계정 삭제됨: {}", id);
+    let result = conn.query_row(
+        "SELECT id, session_id, question_id, response_id, file_name, mime_type, size, created_at, data FROM survey_attachments WHERE id = ?1",
+        [id],
+        |row| {
+            Ok((
+                SurveyAttachment {
+                    id: row.get(0)?,
+                    session_id: row.get(1)?,
+                    question_id: row.get(2)?,
+                    response_id: row.get(3)?,
+                    file_name: row.get(4)?,
+                    mime_type: row.get(5)?,
+                    size: row.get(6)?,
+                    created_at: row.get(7)?,
+                },
+                row.get::<_, Vec<u8>>(8)?,
+            ))
+        },
+    );
+    match result {
+        Ok(attachment) => Ok(Some(attachment)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 답변에 담긴 첨부 파일(`{"attachment_id", "file_name"}`)을 응답에 연결 (같은 세션에서 올린 파일만)
+fn link_survey_attachments(conn: &Connection, response_id: &str, session_id: &str, answers: &[SurveyAnswer]) -> AppResult<()> {
+    for answer in answers {
+        if let Some(attachment_id) = answer.answer.get("attachment_id").and_then(|v| v.as_str()) {
+            conn.execute(
+                "UPDATE survey_attachments SET response_id = ?1 WHERE id = ?2 AND session_id = ?3 AND question_id = ?4",
+                params![response_id, attachment_id, session_id, answer.question_id],
+            )?;
+        }
+    }
     Ok(())
 }
 
-/// 직원 로그인 시간 업데이트
-pub fn update_staff_last_login(id: &str) -> AppResult<()> {
+/// 응답에 연결되지 않은 첨부 파일 정리 (세션이 만료/완료/삭제된 경우 - 교체되었거나 제출되지 않은 파일)
+pub fn purge_orphan_survey_attachments() -> AppResult<usize> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    conn.execute(
-        "UPDATE staff_accounts SET last_login_at = ?2, updated_at = ?2 WHERE id = ?1",
-        params![id, Utc::now().to_rfc3339()],
+    let count = conn.execute(
+        r#"DELETE FROM survey_attachments
+           WHERE response_id IS NULL
+             AND NOT EXISTS (
+                 SELECT 1 FROM survey_sessions s
+                 WHERE s.id = survey_attachments.session_id AND s.status = 'pending' AND s.expires_at > ?1
+             )"#,
+        [Utc::now().to_rfc3339()],
     )?;
-    Ok(())
+    Ok(count)
 }
 
-/// 직원 비밀번호 검증
-pub fn verify_staff_account_password(username: &str, password: &str) -> AppResult<Option<StaffAccount>> {
-    let account = get_staff_account_by_username(username)?;
+/// 첨부 파일 답변 표시값 (파일 이름)
+pub fn attachment_answer_name(value: &serde_json::Value) -> Option<&str> {
+    value.get("attachment_id")?;
+    Some(value.get("file_name").and_then(|v| v.as_str()).unwrap_or("첨부파일"))
+}
 
-    match account {
-        Some(acc) if acc.is_active => {
-            // bcrypt 비밀번호 검증
-            match bcrypt::verify(password, &acc.password_hash) {
-                Ok(true) => {
-                    // 로그인 시간 업데이트
-                    let _ = update_staff_last_login(&acc.id);
-                    Ok(Some(acc))
-                }
-                _ => Ok(None),
-            }
+// ============ 설문 응답 집계 캐시 ============
+
+fn survey_stats_cache() -> &'static Mutex<Option<SurveyResponseStats>> {
+    SURVEY_STATS_CACHE.get_or_init(|| Mutex::new(None))
+}
+
+/// 설문 응답 집계 캐시 무효화 (대량 변경 후 호출)
+pub fn invalidate_survey_stats() {
+    if let Ok(mut cache) = survey_stats_cache().lock() {
+        *cache = None;
+    }
+}
+
+/// 응답 1건 추가(+1)/삭제(-1)를 캐시에 반영 (DB 연결을 잡은 상태에서 호출)
+fn apply_survey_stats_delta(template_id: &str, linked: bool, submitted_at: &str, delta: i64) {
+    let Ok(mut cache) = survey_stats_cache().lock() else {
+        return;
+    };
+    // 아직 구축되지 않았으면 다음 조회 시 전체 집계
+    let Some(stats) = cache.as_mut() else {
+        return;
+    };
+
+    fn bump(map: &mut std::collections::BTreeMap<String, i64>, key: &str, delta: i64) {
+        let count = map.entry(key.to_string()).or_insert(0);
+        *count += delta;
+        if *count <= 0 {
+            map.remove(key);
         }
-        _ => Ok(None),
     }
+
+    stats.total_responses += delta;
+    if !linked {
+        stats.unlinked_responses += delta;
+    }
+    bump(&mut stats.by_template, template_id, delta);
+    bump(&mut stats.by_date, submitted_at.get(..10).unwrap_or(submitted_at), delta);
 }
 
-/// 비밀번호 해시 생성
-pub fn hash_staff_password(password: &str) -> AppResult<String> {
-    bcrypt::hash(password, bcrypt::DEFAULT_COST)
-        .map_err(|e| AppError::Custom(format!("Password hashing failed: {}", e)))
+/// 설문 응답 전체 집계 (캐시 재구축용)
+fn build_survey_response_stats(conn: &Connection) -> AppResult<SurveyResponseStats> {
+    let (total_responses, unlinked_responses) = conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(CASE WHEN patient_id IS NULL THEN 1 ELSE 0 END), 0) FROM survey_responses",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+
+    let group_counts = |sql: &str| -> AppResult<std::collections::BTreeMap<String, i64>> {
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    };
+
+    Ok(SurveyResponseStats {
+        total_responses,
+        unlinked_responses,
+        by_template: group_counts("SELECT template_id, COUNT(*) FROM survey_responses GROUP BY template_id")?,
+        by_date: group_counts("SELECT substr(submitted_at, 1, 10), COUNT(*) FROM survey_responses GROUP BY 1")?,
+    })
 }
 
-// ============ 초진차트 관리 ============
+/// 캐시 우선 집계 조회 (호출자가 DB 연결을 잡고 있어야 함)
+fn cached_survey_response_stats(conn: &Connection) -> AppResult<SurveyResponseStats> {
+    let mut cache = survey_stats_cache()
+        .lock()
+        .map_err(|_| AppError::Custom("Stats cache lock error".to_string()))?;
+    if let Some(stats) = cache.as_ref() {
+        return Ok(stats.clone());
+    }
 
-use crate::models::{InitialChart, ProgressNote};
+    let stats = build_survey_response_stats(conn)?;
+    *cache = Some(stats.clone());
+    Ok(stats)
+}
 
-/// 초진차트 생성
-pub fn create_initial_chart(chart: &InitialChart) -> AppResult<()> {
+/// 설문 응답 집계 조회 (캐시 우선, 앱 시작 후 첫 조회 시 재구축)
+pub fn get_survey_response_stats() -> AppResult<SurveyResponseStats> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
+    cached_survey_response_stats(&conn)
+}
+
+/// 설문 세션 완료 처리
+pub fn complete_survey_session(session_id: &str) -> AppResult<()> {
+    let conn = get_conn()?;
+    let now = Utc::now().to_rfc3339();
 
     conn.execute(
-        r#"INSERT INTO initial_charts (id, patient_id, doctor_name, chart_date, chief_complaint, present_illness, past_medical_history, notes, prescription_issued, prescription_issued_at, deleted_at, created_at, updated_at)
-           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)"#,
-        params![
-            chart.id,
-            chart.patient_id,
-            chart.doctor_name,
-            chart.chart_date,
-            chart.chief_complaint,
-            chart.present_illness,
-            chart.past_medical_history,
-            chart.notes,
-            if chart.prescription_issued { 1 } else { 0 },
-            chart.prescription_issued_at,
-            chart.deleted_at,
-            chart.created_at.to_rfc3339(),
-            chart.updated_at.to_rfc3339(),
-        ],
+        "UPDATE survey_sessions SET status = 'completed', completed_at = ?1 WHERE id = ?2",
+        params![now, session_id],
     )?;
+
     Ok(())
 }
 
-/// 초진차트 조회
-pub fn get_initial_chart(id: &str) -> AppResult<Option<InitialChart>> {
-    ensure_db_initialized()?;
+/// 설문 세션 생성
+pub fn create_survey_session(
+    patient_id: Option<&str>,
+    template_id: &str,
+    respondent_name: Option<&str>,
+    created_by: Option<&str>,
+    token_override: Option<&str>,
+    patient_name: Option<&str>,
+    chart_number: Option<&str>,
+    patient_age: Option<&str>,
+    patient_gender: Option<&str>,
+    verify_birth_date: bool,
+    expires_at: Option<chrono::DateTime<Utc>>,
+) -> AppResult<SurveySessionDb> {
     let conn = get_conn()?;
 
-    let mut stmt = conn.prepare(
-        r#"SELECT id, patient_id, doctor_name, chart_date, chief_complaint, present_illness, past_medical_history, notes, prescription_issued, prescription_issued_at, deleted_at, created_at, updated_at
-           FROM initial_charts WHERE id = ?1 AND deleted_at IS NULL"#,
+    // 익명 설문은 응답자/환자 정보를 세션에 남기지 않음
+    let anonymous = query_template_anonymous(&conn, template_id)?;
+    if anonymous && verify_birth_date {
+        return Err(AppError::Custom("익명 설문에는 생년월일 확인을 사용할 수 없습니다".to_string()));
+    }
+    let (patient_id, respondent_name, patient_name, chart_number) = if anonymous {
+        (None, None, None, None)
+    } else {
+        (patient_id, respondent_name, patient_name, chart_number)
+    };
+
+    // 생년월일 확인은 생년월일이 등록된 환자에게만 사용 가능
+    if verify_birth_date {
+        let birth_date: Option<String> = match patient_id {
+            Some(pid) => conn
+                .query_row("SELECT birth_date FROM patients WHERE id = ?1", [pid], |row| row.get(0))
+                .unwrap_or(None),
+            None => None,
+        };
+        if birth_date.map(|d| d.trim().is_empty()).unwrap_or(true) {
+            return Err(AppError::Custom("생년월일 확인을 사용하려면 생년월일이 등록된 환자를 선택해야 합니다".to_string()));
+        }
+    }
+
+    let display_order = query_template_display_order(&conn, template_id)?;
+    let display_order_json = display_order.as_ref().map(serde_json::to_string).transpose()?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let token = token_override.map(|t| t.to_string()).unwrap_or_else(|| generate_survey_token());
+    let now = Utc::now();
+    // 만료 시각 미지정 시 설정된 유효 시간 (기본 48시간)
+    let expires_at = expires_at
+        .unwrap_or_else(|| now + chrono::Duration::hours(query_survey_ttl_hours(&conn)))
+        .to_rfc3339();
+    let created_at = now.to_rfc3339();
+
+    conn.execute(
+        r#"INSERT INTO survey_sessions (id, token, patient_id, template_id, respondent_name, status, expires_at, created_by, created_at, patient_name, chart_number, patient_age, patient_gender, verify_birth_date, display_order)
+           VALUES (?1, ?2, ?3, ?4, ?5, 'pending', ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)"#,
+        params![id, token, patient_id, template_id, respondent_name, expires_at, created_by, created_at, patient_name, chart_number, patient_age, patient_gender, verify_birth_date as i32, display_order_json],
     )?;
 
-    let result = stmt.query_row([id], |row| {
-        Ok(InitialChart {
-            id: row.get(0)?,
-            patient_id: row.get(1)?,
-            doctor_name: row.get(2)?,
-            chart_date: row.get(3)?,
-            chief_complaint: row.get(4)?,
-            present_illness: row.get(5)?,
-            past_medical_history: row.get(6)?,
-            notes: row.get(7)?,
-            prescription_issued: row.get::<_, i32>(8)? != 0,
-            prescription_issued_at: row.get(9)?,
-            deleted_at: row.get(10)?,
-            created_at: row.get::<_, String>(11)?
-                .parse::<chrono::DateTime<Utc>>()
-                .unwrap_or_else(|_| Utc::now()),
-            updated_at: row.get::<_, String>(12)?
-                .parse::<chrono::DateTime<Utc>>()
-                .unwrap_or_else(|_| Utc::now()),
-        })
-    });
+    Ok(SurveySessionDb {
+        id,
+        token,
+        patient_id: patient_id.map(|s| s.to_string()),
+        template_id: template_id.to_string(),
+        respondent_name: respondent_name.map(|s| s.to_string()),
+        patient_name: patient_name.map(|s| s.to_string()),
+        chart_number: chart_number.map(|s| s.to_string()),
+        patient_age: patient_age.map(|s| s.to_string()),
+        patient_gender: patient_gender.map(|s| s.to_string()),
+        status: SessionStatus::Pending,
+        expires_at,
+        created_at,
+        verify_birth_date,
+        display_order,
+    })
+}
 
-    match result {
-        Ok(chart) => Ok(Some(chart)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(e.into()),
+/// 무작위 순서 템플릿이면 새 시드로 세션 표시 순서 생성 (섞을 것이 없으면 None)
+fn query_template_display_order(conn: &Connection, template_id: &str) -> AppResult<Option<SurveyDisplayOrder>> {
+    let row = conn.query_row(
+        "SELECT questions, randomize_questions, randomize_options, pin_required_questions FROM survey_templates WHERE id = ?1",
+        [template_id],
+        |row| Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?, row.get::<_, bool>(2)?, row.get::<_, bool>(3)?)),
+    );
+    let (questions_json, randomize_questions, randomize_options, pin_required) = match row {
+        Ok(row) => row,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    let questions = parse_template_questions(&questions_json);
+    if !randomize_questions && !randomize_options && !questions.iter().any(|q| q.shuffle_options) {
+        return Ok(None);
     }
+    Ok(Some(SurveyDisplayOrder::generate(
+        rand::random(),
+        &questions,
+        randomize_questions,
+        randomize_options,
+        pin_required,
+    )))
 }
 
-/// 환자별 초진차트 목록 조회
-pub fn get_initial_charts_by_patient(patient_id: &str) -> AppResult<Vec<InitialChart>> {
-    ensure_db_initialized()?;
+/// 생년월일 확인 최대 시도 횟수 (초과 시 세션 잠금)
+const SURVEY_BIRTH_DATE_MAX_ATTEMPTS: i32 = 3;
+
+/// 생년월일 확인 결과
+#[derive(Debug, Clone, PartialEq)]
+pub enum BirthDateCheck {
+    Verified,
+    Mismatch { remaining: i32 },
+    Locked,
+}
+
+/// 설문 세션의 연결 환자 생년월일 확인 (실패 누적 시 세션 잠금 + 직원 알림)
+pub fn verify_survey_birth_date(session_id: &str, dob: &str) -> AppResult<BirthDateCheck> {
     let conn = get_conn()?;
 
-    let mut stmt = conn.prepare(
-        r#"SELECT id, patient_id, doctor_name, chart_date, chief_complaint, present_illness, past_medical_history, notes, prescription_issued, prescription_issued_at, deleted_at, created_at, updated_at
-           FROM initial_charts WHERE patient_id = ?1 AND deleted_at IS NULL ORDER BY chart_date DESC"#,
+    let (patient_id, status, failed_count): (Option<String>, String, i32) = conn.query_row(
+        "SELECT patient_id, status, verify_failed_count FROM survey_sessions WHERE id = ?1",
+        [session_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
     )?;
 
-    let rows = stmt.query_map([patient_id], |row| {
-        Ok(InitialChart {
-            id: row.get(0)?,
-            patient_id: row.get(1)?,
-            doctor_name: row.get(2)?,
-            chart_date: row.get(3)?,
-            chief_complaint: row.get(4)?,
-            present_illness: row.get(5)?,
-            past_medical_history: row.get(6)?,
-            notes: row.get(7)?,
-            prescription_issued: row.get::<_, i32>(8)? != 0,
-            prescription_issued_at: row.get(9)?,
-            deleted_at: row.get(10)?,
-            created_at: row.get::<_, String>(11)?
-                .parse::<chrono::DateTime<Utc>>()
-                .unwrap_or_else(|_| Utc::now()),
-            updated_at: row.get::<_, String>(12)?
-                .parse::<chrono::DateTime<Utc>>()
-                .unwrap_or_else(|_| Utc::now()),
-        })
-    })?;
+    if status == "locked" {
+        return Ok(BirthDateCheck::Locked);
+    }
 
-    let mut charts = Vec::new();
-    for row in rows {
-        charts.push(row?);
+    let (patient_name, birth_date): (String, Option<String>) = match &patient_id {
+        Some(pid) => conn
+            .query_row("SELECT name, birth_date FROM patients WHERE id = ?1", [pid], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap_or_default(),
+        None => Default::default(),
+    };
+
+    // 구분자(-, ., /) 차이는 무시하고 숫자만 비교
+    let digits = |s: &str| s.chars().filter(|c| c.is_ascii_digit()).collect::<String>();
+    let matched = birth_date
+        .map(|b| !digits(&b).is_empty() && digits(&b) == digits(dob))
+        .unwrap_or(false);
+
+    if matched {
+        return Ok(BirthDateCheck::Verified);
     }
-    Ok(charts)
+
+    let failed_count = failed_count + 1;
+    conn.execute(
+        "UPDATE survey_sessions SET verify_failed_count = ?1 WHERE id = ?2",
+        params![failed_count, session_id],
+    )?;
+
+    if failed_count < SURVEY_BIRTH_DATE_MAX_ATTEMPTS {
+        return Ok(BirthDateCheck::Mismatch { remaining: SURVEY_BIRTH_DATE_MAX_ATTEMPTS - failed_count });
+    }
+
+    conn.execute("UPDATE survey_sessions SET status = 'locked' WHERE id = ?1", [session_id])?;
+    insert_notification(
+        &conn,
+        "survey_locked",
+        "설문 링크 잠금",
+        &format!("{} 환자의 설문 링크에서 생년월일 확인이 {}회 실패하여 잠겼습니다.", patient_name, failed_count),
+        "high",
+        patient_id.as_deref(),
+    )?;
+    log::warn!("설문 세션 잠금 (생년월일 확인 실패): {}", session_id);
+
+    Ok(BirthDateCheck::Locked)
 }
 
-/// 모든 초진차트 목록 조회 (환자 이름 포함)
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct InitialChartWithPatient {
-    #[serde(flatten)]
-    pub chart: InitialChart,
-    pub patient_name: String,
+/// 직원 알림 추가
+fn insert_notification(
+    conn: &Connection,
+    notification_type: &str,
+    title: &str,
+    body: &str,
+    priority: &str,
+    patient_id: Option<&str>,
+) -> AppResult<()> {
+    conn.execute(
+        r#"INSERT INTO notifications (id, notification_type, title, body, priority, patient_id, created_at)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"#,
+        params![
+            uuid::Uuid::new_v4().to_string(),
+            notification_type,
+            title,
+            body,
+            priority,
+            patient_id,
+            Utc::now().to_rfc3339(),
+        ],
+    )?;
+    crate::metrics::record_notification_sent();
+    Ok(())
 }
 
-pub fn list_initial_charts() -> AppResult<Vec<InitialChartWithPatient>> {
+/// 같은 종류의 알림이 최근 `within_hours` 시간 안에 생성되었는지 (중복 알림 방지)
+fn has_recent_notification(conn: &Connection, notification_type: &str, within_hours: i64) -> AppResult<bool> {
+    let since = (Utc::now() - chrono::Duration::hours(within_hours)).to_rfc3339();
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM notifications WHERE notification_type = ?1 AND created_at >= ?2)",
+        params![notification_type, since],
+        |row| row.get(0),
+    )?;
+    Ok(exists)
+}
+
+/// 알림 목록 페이지 조회 (닫은 알림 제외, 최신순, 안 읽은 알림은 스누즈 중인 알림 제외)
+pub fn list_notifications_page(unread_only: bool, limit: i64, offset: i64) -> AppResult<PaginatedResponse<Notification>> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
+    let now = Utc::now().to_rfc3339();
+    let (filter, mut query_params): (&str, Vec<&dyn rusqlite::ToSql>) = if unread_only {
+        (
+            "is_dismissed = 0 AND is_read = 0 AND (snoozed_until IS NULL OR snoozed_until <= ?)",
+            vec![&now],
+        )
+    } else {
+        ("is_dismissed = 0", Vec::new())
+    };
 
-    let mut stmt = conn.prepare(
-        r#"SELECT ic.id, ic.patient_id, ic.doctor_name, ic.chart_date, ic.chief_complaint, ic.present_illness, ic.past_medical_history, ic.notes, ic.prescription_issued, ic.prescription_issued_at, ic.deleted_at, ic.created_at, ic.updated_at, p.name as patient_name
-           FROM initial_charts ic
-           LEFT JOIN patients p ON ic.patient_id = p.id
-           WHERE ic.deleted_at IS NULL
-           ORDER BY ic.chart_date DESC"#,
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM notifications WHERE {}", filter),
+        query_params.as_slice(),
+        |row| row.get(0),
     )?;
 
-    let rows = stmt.query_map([], |row| {
-        Ok(InitialChartWithPatient {
-            chart: InitialChart {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, notification_type, title, body, priority, schedule_id, patient_id, is_read, is_dismissed, action_url, created_at, read_at, snoozed_until
+         FROM notifications WHERE {} ORDER BY created_at DESC LIMIT ? OFFSET ?",
+        filter
+    ))?;
+    query_params.push(&limit);
+    query_params.push(&offset);
+    let notifications = stmt
+        .query_map(query_params.as_slice(), |row| {
+            Ok(Notification {
                 id: row.get(0)?,
-                patient_id: row.get(1)?,
-                doctor_name: row.get(2)?,
-                chart_date: row.get(3)?,
-                chief_complaint: row.get(4)?,
-                present_illness: row.get(5)?,
-                past_medical_history: row.get(6)?,
-                notes: row.get(7)?,
-                prescription_issued: row.get::<_, i32>(8)? != 0,
-                prescription_issued_at: row.get(9)?,
-                deleted_at: row.get(10)?,
-                created_at: row.get::<_, String>(11)?
-                    .parse::<chrono::DateTime<Utc>>()
-                    .unwrap_or_else(|_| Utc::now()),
-                updated_at: row.get::<_, String>(12)?
-                    .parse::<chrono::DateTime<Utc>>()
-                    .unwrap_or_else(|_| Utc::now()),
-            },
-            patient_name: row.get(13)?,
-        })
-    })?;
+                notification_type: row.get(1)?,
+                title: row.get(2)?,
+                body: row.get(3)?,
+                priority: row.get(4)?,
+                schedule_id: row.get(5)?,
+                patient_id: row.get(6)?,
+                is_read: row.get::<_, i32>(7)? != 0,
+                is_dismissed: row.get::<_, i32>(8)? != 0,
+                action_url: row.get(9)?,
+                created_at: row.get(10)?,
+                read_at: row.get(11)?,
+                snoozed_until: row.get(12)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
 
-    let mut charts = Vec::new();
-    for row in rows {
-        charts.push(row?);
-    }
-    Ok(charts)
+    Ok(PaginatedResponse::new(notifications, total, limit, offset))
 }
 
-/// 초진차트 수정
-pub fn update_initial_chart(chart: &InitialChart) -> AppResult<()> {
+/// 알림 설정이 없을 때 허용하는 스누즈 시간(분)
+pub const DEFAULT_SNOOZE_MINUTES: &[i64] = &[5, 10, 30, 60];
+
+/// 허용 스누즈 시간 (전역 알림 설정, 없으면 기본값)
+fn allowed_snooze_minutes(conn: &Connection) -> Vec<i64> {
+    conn.query_row(
+        "SELECT snooze_minutes_options FROM notification_settings WHERE schedule_id IS NULL ORDER BY updated_at DESC LIMIT 1",
+        [],
+        |row| row.get::<_, Option<String>>(0),
+    )
+    .ok()
+    .flatten()
+    .and_then(|json| serde_json::from_str::<Vec<i64>>(&json).ok())
+    .filter(|options| !options.is_empty())
+    .unwrap_or_else(|| DEFAULT_SNOOZE_MINUTES.to_vec())
+}
+
+/// 알림 설정이 없을 때 정리/알림 작업 실행 주기(초)
+pub const DEFAULT_SCHEDULER_INTERVAL_SECS: u64 = 10 * 60;
+
+/// 정리/알림 작업 실행 주기 허용 범위(초)
+const SCHEDULER_INTERVAL_RANGE: std::ops::RangeInclusive<u64> = 10..=24 * 60 * 60;
+
+/// 정리/알림 작업 실행 주기 (전역 알림 설정, 없으면 기본값)
+pub fn get_scheduler_interval_secs() -> AppResult<u64> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
 
-    conn.execute(
-        r#"UPDATE initial_charts SET
-           doctor_name = ?2, chart_date = ?3, chief_complaint = ?4, present_illness = ?5,
-           past_medical_history = ?6, notes = ?7, prescription_issued = ?8, prescription_issued_at = ?9,
-           updated_at = ?10
-           WHERE id = ?1"#,
-        params![
-            chart.id,
-            chart.doctor_name,
-            chart.chart_date,
-            chart.chief_complaint,
-            chart.present_illness,
-            chart.past_medical_history,
-            chart.notes,
-            if chart.prescription_issued { 1 } else { 0 },
-            chart.prescription_issued_at,
-            Utc::now().to_rfc3339(),
-        ],
+    let secs = conn
+        .query_row(
+            "SELECT scheduler_interval_secs FROM notification_settings WHERE schedule_id IS NULL ORDER BY updated_at DESC LIMIT 1",
+            [],
+            |row| row.get::<_, Option<i64>>(0),
+        )
+        .ok()
+        .flatten()
+        .and_then(|secs| u64::try_from(secs).ok())
+        .filter(|secs| SCHEDULER_INTERVAL_RANGE.contains(secs))
+        .unwrap_or(DEFAULT_SCHEDULER_INTERVAL_SECS);
+    Ok(secs)
+}
+
+/// 정리/알림 작업 실행 주기 변경 (다음 주기부터 적용)
+pub fn set_scheduler_interval_secs(secs: u64) -> AppResult<()> {
+    if !SCHEDULER_INTERVAL_RANGE.contains(&secs) {
+        return Err(AppError::Validation(format!(
+            "실행 주기는 {}~{}초 사이여야 합니다",
+            SCHEDULER_INTERVAL_RANGE.start(),
+            SCHEDULER_INTERVAL_RANGE.end()
+        )));
+    }
+
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let now = Utc::now().to_rfc3339();
+
+    let updated = conn.execute(
+        "UPDATE notification_settings SET scheduler_interval_secs = ?1, updated_at = ?2 WHERE schedule_id IS NULL",
+        params![secs as i64, now],
     )?;
+    if updated == 0 {
+        conn.execute(
+            "INSERT INTO notification_settings (id, schedule_id, scheduler_interval_secs, created_at, updated_at)
+             VALUES (?1, NULL, ?2, ?3, ?3)",
+            params![uuid::Uuid::new_v4().to_string(), secs as i64, now],
+        )?;
+    }
     Ok(())
 }
 
-/// 초진차트 소프트 삭제
-pub fn delete_initial_chart(id: &str) -> AppResult<()> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
-    conn.execute(
-        "UPDATE initial_charts SET deleted_at = ?2 WHERE id = ?1",
-        params![id, Utc::now().to_rfc3339()],
+/// 전역 알림 설정 한 컬럼 저장 (전역 행이 없으면 생성)
+fn set_global_notification_setting(conn: &Connection, column: &str, value: Option<String>) -> AppResult<()> {
+    let now = Utc::now().to_rfc3339();
+    let updated = conn.execute(
+        &format!("UPDATE notification_settings SET {} = ?1, updated_at = ?2 WHERE schedule_id IS NULL", column),
+        params![value, now],
     )?;
+    if updated == 0 {
+        conn.execute(
+            &format!(
+                "INSERT INTO notification_settings (id, schedule_id, {}, created_at, updated_at) VALUES (?1, NULL, ?2, ?3, ?3)",
+                column
+            ),
+            params![uuid::Uuid::new_v4().to_string(), value, now],
+        )?;
+    }
     Ok(())
 }
 
-// ============ 경과기록 관리 ============
+/// 전역 알림 설정 한 컬럼 조회 (INTEGER 컬럼도 문자열로)
+fn get_global_notification_setting(conn: &Connection, column: &str) -> Option<String> {
+    conn.query_row(
+        &format!(
+            "SELECT CAST({} AS TEXT) FROM notification_settings WHERE schedule_id IS NULL ORDER BY updated_at DESC LIMIT 1",
+            column
+        ),
+        [],
+        |row| row.get(0),
+    )
+    .ok()
+    .flatten()
+}
 
-/// 경과기록 생성
-pub fn create_progress_note(note: &ProgressNote) -> AppResult<()> {
+/// 복약 알림 발송 채널 (없으면 데스크톱)
+pub fn get_notification_channel() -> AppResult<NotificationChannel> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
+    Ok(get_global_notification_setting(&conn, "channel")
+        .and_then(|c| serde_json::from_value(serde_json::Value::String(c)).ok())
+        .unwrap_or_default())
+}
 
-    conn.execute(
-        r#"INSERT INTO progress_notes (id, patient_id, doctor_name, note_date, subjective, objective, assessment, plan, follow_up_plan, notes, prescription_issued, prescription_issued_at, deleted_at, created_at, updated_at)
-           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)"#,
-        params![
-            note.id,
-            note.patient_id,
-            note.doctor_name,
-            note.note_date,
-            note.subjective,
-            note.objective,
-            note.assessment,
-            note.plan,
-            note.follow_up_plan,
-            note.notes,
-            if note.prescription_issued { 1 } else { 0 },
-            note.prescription_issued_at,
-            note.deleted_at,
-            note.created_at.to_rfc3339(),
-            note.updated_at.to_rfc3339(),
-        ],
-    )?;
+/// 복약 알림 발송 채널 변경 (문자 채널은 문자 발송 설정이 있어야 함)
+pub fn set_notification_channel(channel: NotificationChannel) -> AppResult<()> {
+    if channel.includes_sms() && get_sms_settings()?.is_none() {
+        return Err(AppError::Validation("문자 발송 설정을 먼저 저장해주세요".to_string()));
+    }
+
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let value = serde_json::to_value(channel)?.as_str().map(String::from);
+    set_global_notification_setting(&conn, "channel", value)?;
+    log::info!("복약 알림 채널: {:?}", channel);
     Ok(())
 }
 
-/// 경과기록 조회
-pub fn get_progress_note(id: &str) -> AppResult<Option<ProgressNote>> {
+/// 휴대폰 번호 정규화 (숫자만, 010-1234-5678 / +82 10-1234-5678 → 01012345678), 휴대폰 번호가 아니면 None
+pub fn normalize_mobile_phone(phone: &str) -> Option<String> {
+    let digits: String = phone.chars().filter(char::is_ascii_digit).collect();
+    let digits = match digits.strip_prefix("82") {
+        Some(rest) if phone.trim_start().starts_with('+') => format!("0{}", rest),
+        _ => digits,
+    };
+    (digits.starts_with("01") && (10..=11).contains(&digits.len())).then_some(digits)
+}
+
+/// 문자 발송 설정 조회 (API 키 포함, 화면에 돌려줄 때는 호출하는 쪽에서 비움)
+pub fn get_sms_settings() -> AppResult<Option<SmsSettings>> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
+    Ok(get_global_notification_setting(&conn, "sms_settings").and_then(|j| serde_json::from_str(&j).ok()))
+}
+
+/// 문자 발송 설정 저장 (None이면 문자 발송 끔, API 키를 비워 두면 기존 값 유지)
+pub fn save_sms_settings(settings: Option<&SmsSettings>) -> AppResult<()> {
+    let mut settings = settings.cloned();
+    if let Some(s) = settings.as_mut() {
+        s.gateway_url = s.gateway_url.trim().to_string();
+        s.user_id = s.user_id.trim().to_string();
+        s.sender = normalize_phone_digits(&s.sender)
+            .ok_or_else(|| AppError::Validation(format!("발신 번호가 올바르지 않습니다: {}", s.sender)))?;
+        if s.provider == SmsProvider::HttpGateway
+            && !(s.gateway_url.starts_with("https://") || s.gateway_url.starts_with("http://"))
+        {
+            return Err(AppError::Validation("게이트웨이 주소는 http:// 또는 https://로 시작해야 합니다".to_string()));
+        }
+        if s.provider == SmsProvider::Aligo && s.user_id.is_empty() {
+            return Err(AppError::Validation("알리고 아이디를 입력해주세요".to_string()));
+        }
+        if s.api_key.is_empty() {
+            s.api_key = get_sms_settings()?.map(|existing| existing.api_key).unwrap_or_default();
+        }
+        if s.api_key.is_empty() {
+            return Err(AppError::Validation("API 키를 입력해주세요".to_string()));
+        }
+    }
 
-    let mut stmt = conn.prepare(
-        r#"SELECT id, patient_id, doctor_name, note_date, subjective, objective, assessment, plan, follow_up_plan, notes, prescription_issued, prescription_issued_at, deleted_at, created_at, updated_at
-           FROM progress_notes WHERE id = ?1 AND deleted_at IS NULL"#,
-    )?;
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let json = settings.as_ref().map(serde_json::to_string).transpose()?;
+    set_global_notification_setting(&conn, "sms_settings", json)?;
+    if settings.is_none() {
+        // 문자 발송을 끄면 채널도 데스크톱으로
+        set_global_notification_setting(&conn, "channel", Some("desktop".to_string()))?;
+    }
 
-    let result = stmt.query_row([id], |row| {
-        Ok(ProgressNote {
-            id: row.get(0)?,
-            patient_id: row.get(1)?,
-            doctor_name: row.get(2)?,
-            note_date: row.get(3)?,
-            subjective: row.get(4)?,
-            objective: row.get(5)?,
-            assessment: row.get(6)?,
-            plan: row.get(7)?,
-            follow_up_plan: row.get(8)?,
-            notes: row.get(9)?,
-            prescription_issued: row.get::<_, i32>(10)? != 0,
-            prescription_issued_at: row.get(11)?,
-            deleted_at: row.get(12)?,
-            created_at: row.get::<_, String>(13)?
-                .parse::<chrono::DateTime<Utc>>()
-                .unwrap_or_else(|_| Utc::now()),
-            updated_at: row.get::<_, String>(14)?
-                .parse::<chrono::DateTime<Utc>>()
-                .unwrap_or_else(|_| Utc::now()),
-        })
-    });
+    log::info!("문자 발송 설정 저장: {}", settings.as_ref().map_or("사용 안 함".to_string(), |s| format!("{:?}", s.provider)));
+    Ok(())
+}
 
-    match result {
-        Ok(note) => Ok(Some(note)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(e.into()),
-    }
+/// 발신 번호 정규화 (유선 번호도 허용, 숫자 8~12자리)
+fn normalize_phone_digits(phone: &str) -> Option<String> {
+    let digits: String = phone.chars().filter(char::is_ascii_digit).collect();
+    (8..=12).contains(&digits.len()).then_some(digits)
 }
 
-/// 환자별 경과기록 목록 조회
-pub fn get_progress_notes_by_patient(patient_id: &str) -> AppResult<Vec<ProgressNote>> {
+/// 지금이 방해 금지 시간인지 (전역 알림 설정, 자정을 넘는 구간 지원)
+pub fn is_do_not_disturb_now() -> AppResult<bool> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
 
-    let mut stmt = conn.prepare(
-        r#"SELECT id, patient_id, doctor_name, note_date, subjective, objective, assessment, plan, follow_up_plan, notes, prescription_issued, prescription_issued_at, deleted_at, created_at, updated_at
-           FROM progress_notes WHERE patient_id = ?1 AND deleted_at IS NULL ORDER BY note_date DESC"#,
-    )?;
+    let parse = |column: &str| {
+        get_global_notification_setting(&conn, column)
+            .and_then(|t| chrono::NaiveTime::parse_from_str(t.trim(), "%H:%M").ok())
+    };
+    let (Some(start), Some(end)) = (parse("do_not_disturb_start"), parse("do_not_disturb_end")) else {
+        return Ok(false);
+    };
 
-    let rows = stmt.query_map([patient_id], |row| {
-        Ok(ProgressNote {
-            id: row.get(0)?,
-            patient_id: row.get(1)?,
-            doctor_name: row.get(2)?,
-            note_date: row.get(3)?,
-            subjective: row.get(4)?,
-            objective: row.get(5)?,
-            assessment: row.get(6)?,
-            plan: row.get(7)?,
-            follow_up_plan: row.get(8)?,
-            notes: row.get(9)?,
-            prescription_issued: row.get::<_, i32>(10)? != 0,
-            prescription_issued_at: row.get(11)?,
-            deleted_at: row.get(12)?,
-            created_at: row.get::<_, String>(13)?
-                .parse::<chrono::DateTime<Utc>>()
-                .unwrap_or_else(|_| Utc::now()),
-            updated_at: row.get::<_, String>(14)?
-                .parse::<chrono::DateTime<Utc>>()
-                .unwrap_or_else(|_| Utc::now()),
-        })
-    })?;
+    let now = chrono::Local::now().time();
+    Ok(if start <= end { start <= now && now < end } else { now >= start || now < end })
+}
 
-    let mut notes = Vec::new();
-    for row in rows {
-        notes.push(row?);
+/// 설문 리마인더 발송 시점 허용 범위 (만료 몇 시간 전)
+const SURVEY_REMINDER_HOURS_RANGE: std::ops::RangeInclusive<i64> = 1..=168;
+
+/// 미완료 설문 리마인더 시점 (만료 몇 시간 전, None이면 보내지 않음)
+pub fn get_survey_reminder_hours() -> AppResult<Option<i64>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    Ok(get_global_notification_setting(&conn, "survey_reminder_hours")
+        .and_then(|h| h.parse().ok())
+        .filter(|h| SURVEY_REMINDER_HOURS_RANGE.contains(h)))
+}
+
+/// 미완료 설문 리마인더 시점 변경 (문자 발송 설정이 있어야 함)
+pub fn set_survey_reminder_hours(hours: Option<i64>) -> AppResult<()> {
+    if let Some(h) = hours {
+        if !SURVEY_REMINDER_HOURS_RANGE.contains(&h) {
+            return Err(AppError::Validation(format!(
+                "리마인더는 만료 {}~{}시간 전 사이로 설정해주세요",
+                SURVEY_REMINDER_HOURS_RANGE.start(),
+                SURVEY_REMINDER_HOURS_RANGE.end()
+            )));
+        }
+        if get_sms_settings()?.is_none() {
+            return Err(AppError::Validation("문자 발송 설정을 먼저 저장해주세요".to_string()));
+        }
     }
-    Ok(notes)
+
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    set_global_notification_setting(&conn, "survey_reminder_hours", hours.map(|h| h.to_string()))?;
+    Ok(())
 }
 
-/// 경과기록 수정
-pub fn update_progress_note(note: &ProgressNote) -> AppResult<()> {
+/// 곧 만료되는 미완료 설문 세션
+#[derive(Debug, Clone)]
+pub struct ExpiringSurveySession {
+    pub session_id: String,
+    pub template_name: String,
+    /// 환자 이름 (없으면 세션의 응답자 이름)
+    pub name: Option<String>,
+    /// 연결된 환자의 연락처 (환자 미연결이거나 삭제된 환자면 None)
+    pub phone: Option<String>,
+    pub expires_at: chrono::DateTime<Utc>,
+}
+
+/// `hours`시간 안에 만료되는 미완료 설문 세션 (리마인더를 아직 보내지 않았고 응답이 없는 세션만)
+pub fn get_pending_sessions_expiring(hours: i64) -> AppResult<Vec<ExpiringSurveySession>> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
+    let now = Utc::now();
 
-    conn.execute(
-        r#"UPDATE progress_notes SET
-           doctor_name = ?2, note_date = ?3, subjective = ?4, objective = ?5,
-           assessment = ?6, plan = ?7, follow_up_plan = ?8, notes = ?9,
-           prescription_issued = ?10, prescription_issued_at = ?11, updated_at = ?12
-           WHERE id = ?1"#,
-        params![
-            note.id,
-            note.doctor_name,
-            note.note_date,
-            note.subjective,
-            note.objective,
-            note.assessment,
-            note.plan,
-            note.follow_up_plan,
-            note.notes,
-            if note.prescription_issued { 1 } else { 0 },
-            note.prescription_issued_at,
-            Utc::now().to_rfc3339(),
-        ],
+    let mut stmt = conn.prepare(
+        r#"SELECT s.id, t.name, COALESCE(p.name, s.respondent_name), p.phone, s.expires_at
+           FROM survey_sessions s
+           JOIN survey_templates t ON t.id = s.template_id
+           LEFT JOIN patients p ON p.id = s.patient_id AND p.deleted_at IS NULL
+           WHERE s.status = 'pending'
+             AND s.reminder_sent_at IS NULL
+             AND s.expires_at > ?1 AND s.expires_at <= ?2
+             AND NOT EXISTS (SELECT 1 FROM survey_responses r WHERE r.session_id = s.id)
+           ORDER BY s.expires_at"#,
     )?;
-    Ok(())
+    let sessions = stmt
+        .query_map(params![now.to_rfc3339(), (now + chrono::Duration::hours(hours)).to_rfc3339()], |row| {
+            Ok(ExpiringSurveySession {
+                session_id: row.get(0)?,
+                template_name: row.get(1)?,
+                name: row.get(2)?,
+                phone: row.get(3)?,
+                expires_at: row.get::<_, String>(4)?.parse().unwrap_or(now),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(sessions)
 }
 
-/// 경과기록 소프트 삭제
-pub fn delete_progress_note(id: &str) -> AppResult<()> {
+/// 설문 리마인더 발송 차례 확보 (이미 처리한 세션이면 false, 연락처가 없어 건너뛴 경우도 처리한 것으로 봄)
+pub fn claim_survey_reminder(session_id: &str) -> AppResult<bool> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    conn.execute(
-        "UPDATE progress_notes SET deleted_at = ?2 WHERE id = ?1",
-        params![id, Utc::now().to_rfc3339()],
+    let updated = conn.execute(
+        "UPDATE survey_sessions SET reminder_sent_at = ?2 WHERE id = ?1 AND reminder_sent_at IS NULL",
+        params![session_id, Utc::now().to_rfc3339()],
     )?;
+    Ok(updated == 1)
+}
+
+/// 설문 리마인더 발송 실패 시 기록 취소 (다음 주기에 다시 시도)
+pub fn release_survey_reminder(session_id: &str) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute("UPDATE survey_sessions SET reminder_sent_at = NULL WHERE id = ?1", [session_id])?;
     Ok(())
 }
 
-// ============ 복약 일정 관리 ============
+/// 알림을 보낼 복약 시간
+#[derive(Debug, Clone)]
+pub struct DueMedicationReminder {
+    pub schedule_id: String,
+    pub patient_id: String,
+    pub patient_name: String,
+    pub phone: Option<String>,
+    /// 복약 시각 (현지 시간 "YYYY-MM-DD HH:MM", 중복 발송 확인 키)
+    pub slot: String,
+    /// 복약 시간 ("HH:MM")
+    pub time: String,
+}
 
-use crate::models::{MedicationSchedule, MedicationLog, MedicationStatus, MedicationStats};
+/// 복약 알림 발송 기록 보관 일수
+const REMINDER_DELIVERY_RETENTION_DAYS: i64 = 7;
 
-/// 복약 일정 목록 조회
-pub fn list_medication_schedules() -> AppResult<Vec<MedicationSchedule>> {
+/// 지금 알림을 보낼 복약 시간 목록
+///
+/// 복약 시각에서 미리 알림 시간(일정별 설정, 없으면 전역 설정)을 뺀 시각부터 `window_secs` 동안 대상입니다.
+/// 실행 주기를 창 크기로 넘기면 주기 사이에 놓치는 알림이 없고, 중복은 `claim_reminder_delivery`로 막습니다.
+pub fn list_due_medication_reminders(window_secs: u64) -> AppResult<Vec<DueMedicationReminder>> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
+    let now_utc = Utc::now();
+
+    conn.execute(
+        "DELETE FROM reminder_deliveries WHERE created_at < ?1",
+        [(now_utc - chrono::Duration::days(REMINDER_DELIVERY_RETENTION_DAYS)).to_rfc3339()],
+    )?;
+
+    let global: (bool, i64) = conn
+        .query_row(
+            "SELECT enabled, pre_reminder_minutes FROM notification_settings
+             WHERE schedule_id IS NULL ORDER BY updated_at DESC LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap_or((true, 5));
 
     let mut stmt = conn.prepare(
-        r#"SELECT id, patient_id, prescription_id, start_date, end_date, times_per_day, medication_times, notes, created_at
-           FROM medication_schedules ORDER BY created_at DESC"#,
+        r#"SELECT ms.id, ms.patient_id, p.name, p.phone, ms.medication_times, ns.enabled, ns.pre_reminder_minutes
+           FROM medication_schedules ms
+           JOIN patients p ON p.id = ms.patient_id AND p.deleted_at IS NULL
+           LEFT JOIN notification_settings ns ON ns.schedule_id = ms.id
+           WHERE ms.start_date <= ?1 AND ms.end_date > ?1"#,
     )?;
+    let rows = stmt
+        .query_map([now_utc.to_rfc3339()], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, Option<bool>>(5)?,
+                row.get::<_, Option<i64>>(6)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let now = chrono::Local::now().naive_local();
+    let window = chrono::Duration::seconds(window_secs as i64);
+    let mut due = Vec::new();
+    for (schedule_id, patient_id, patient_name, phone, times_json, enabled, pre_minutes) in rows {
+        if !enabled.unwrap_or(global.0) {
+            continue;
+        }
+        let pre = chrono::Duration::minutes(pre_minutes.unwrap_or(global.1).max(0));
+        let times: Vec<chrono::NaiveTime> = serde_json::from_str::<Vec<String>>(&times_json)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|t| chrono::NaiveTime::parse_from_str(t.trim(), "%H:%M").ok())
+            .collect();
+
+        // 자정 직후 복약 시간은 미리 알림이 전날에 걸리므로 내일 날짜도 확인
+        for date in [now.date(), now.date() + chrono::Duration::days(1)] {
+            for &time in &times {
+                let slot = date.and_time(time);
+                let remind_at = slot - pre;
+                if remind_at <= now && now < remind_at + window {
+                    due.push(DueMedicationReminder {
+                        schedule_id: schedule_id.clone(),
+                        patient_id: patient_id.clone(),
+                        patient_name: patient_name.clone(),
+                        phone: phone.clone(),
+                        slot: slot.format("%Y-%m-%d %H:%M").to_string(),
+                        time: time.format("%H:%M").to_string(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(due)
+}
 
-    let rows = stmt.query_map([], |row| {
-        let medication_times_json: String = row.get(6)?;
-        let medication_times: Vec<String> = serde_json::from_str(&medication_times_json).unwrap_or_default();
-        Ok(MedicationSchedule {
-            id: row.get(0)?,
-            patient_id: row.get(1)?,
-            prescription_id: row.get(2)?,
-            start_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(3)?)
-                .unwrap()
-                .with_timezone(&Utc),
-            end_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?)
-                .unwrap()
-                .with_timezone(&Utc),
-            times_per_day: row.get(5)?,
-            medication_times,
-            notes: row.get(7)?,
-            created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?)
-                .unwrap()
-                .with_timezone(&Utc),
-        })
-    })?;
-
-    let mut schedules = Vec::new();
-    for row in rows {
-        schedules.push(row?);
-    }
-    Ok(schedules)
+/// 복약 알림 발송 차례 확보 (같은 복약 시각/채널로 이미 보냈으면 false)
+pub fn claim_reminder_delivery(schedule_id: &str, slot: &str, channel: &str) -> AppResult<bool> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO reminder_deliveries (schedule_id, slot, channel, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![schedule_id, slot, channel, Utc::now().to_rfc3339()],
+    )?;
+    Ok(inserted == 1)
 }
 
-/// 복약 일정 조회 (ID로)
-pub fn get_medication_schedule(id: &str) -> AppResult<Option<MedicationSchedule>> {
+/// 발송 실패 시 기록 취소 (다음 주기에 다시 시도)
+pub fn release_reminder_delivery(schedule_id: &str, slot: &str, channel: &str) -> AppResult<()> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-
-    let mut stmt = conn.prepare(
-        r#"SELECT id, patient_id, prescription_id, start_date, end_date, times_per_day, medication_times, notes, created_at
-           FROM medication_schedules WHERE id = ?1"#,
+    conn.execute(
+        "DELETE FROM reminder_deliveries WHERE schedule_id = ?1 AND slot = ?2 AND channel = ?3",
+        params![schedule_id, slot, channel],
     )?;
+    Ok(())
+}
 
-    let result = stmt.query_row([id], |row| {
-        let medication_times_json: String = row.get(6)?;
-        let medication_times: Vec<String> = serde_json::from_str(&medication_times_json).unwrap_or_default();
-        Ok(MedicationSchedule {
-            id: row.get(0)?,
-            patient_id: row.get(1)?,
-            prescription_id: row.get(2)?,
-            start_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(3)?)
-                .unwrap()
-                .with_timezone(&Utc),
-            end_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?)
-                .unwrap()
-                .with_timezone(&Utc),
-            times_per_day: row.get(5)?,
-            medication_times,
-            notes: row.get(7)?,
-            created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?)
-                .unwrap()
-                .with_timezone(&Utc),
-        })
-    });
+/// 복약 알림 데스크톱 알림 생성 (같은 복약 시각에 한 번만, 반환: 생성 여부)
+pub fn notify_medication_reminder(reminder: &DueMedicationReminder) -> AppResult<bool> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let tx = conn.unchecked_transaction()?;
 
-    match result {
-        Ok(schedule) => Ok(Some(schedule)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(e.into()),
+    let inserted = tx.execute(
+        "INSERT OR IGNORE INTO reminder_deliveries (schedule_id, slot, channel, created_at) VALUES (?1, ?2, 'desktop', ?3)",
+        params![reminder.schedule_id, reminder.slot, Utc::now().to_rfc3339()],
+    )?;
+    if inserted == 0 {
+        return Ok(false);
     }
+    insert_notification(
+        &tx,
+        "medication_reminder",
+        "복약 알림",
+        &format!("{} 환자 {} 복약 시간입니다", reminder.patient_name, reminder.time),
+        "normal",
+        Some(&reminder.patient_id),
+    )?;
+    tx.commit()?;
+    Ok(true)
 }
 
-/// 환자별 복약 일정 조회
-pub fn get_medication_schedules_by_patient(patient_id: &str) -> AppResult<Vec<MedicationSchedule>> {
+/// 알림 다시 알림 (`minutes` 뒤까지 안 읽은 알림 목록에서 숨김, 반환: 다시 표시될 시각)
+pub fn snooze_notification(id: &str, minutes: i64) -> AppResult<Option<String>> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
 
-    let mut stmt = conn.prepare(
-        r#"SELECT id, patient_id, prescription_id, start_date, end_date, times_per_day, medication_times, notes, created_at
-           FROM medication_schedules WHERE patient_id = ?1 ORDER BY created_at DESC"#,
-    )?;
-
-    let rows = stmt.query_map([patient_id], |row| {
-        let medication_times_json: String = row.get(6)?;
-        let medication_times: Vec<String> = serde_json::from_str(&medication_times_json).unwrap_or_default();
-        Ok(MedicationSchedule {
-            id: row.get(0)?,
-            patient_id: row.get(1)?,
-            prescription_id: row.get(2)?,
-            start_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(3)?)
-                .unwrap()
-                .with_timezone(&Utc),
-            end_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?)
-                .unwrap()
-                .with_timezone(&Utc),
-            times_per_day: row.get(5)?,
-            medication_times,
-            notes: row.get(7)?,
-            created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?)
-                .unwrap()
-                .with_timezone(&Utc),
-        })
-    })?;
+    let allowed = allowed_snooze_minutes(&conn);
+    if !allowed.contains(&minutes) {
+        return Err(AppError::Validation(format!(
+            "허용되지 않은 스누즈 시간입니다: {}분 (허용: {:?})",
+            minutes, allowed
+        )));
+    }
 
-    let mut schedules = Vec::new();
-    for row in rows {
-        schedules.push(row?);
+    let snoozed_until = (Utc::now() + chrono::Duration::minutes(minutes)).to_rfc3339();
+    let updated = conn.execute(
+        "UPDATE notifications SET snoozed_until = ?1, is_read = 0, read_at = NULL WHERE id = ?2 AND is_dismissed = 0",
+        params![snoozed_until, id],
+    )?;
+    if updated == 0 {
+        return Ok(None);
     }
-    Ok(schedules)
+    Ok(Some(snoozed_until))
 }
 
-/// 복약 일정 생성
-pub fn create_medication_schedule(schedule: &MedicationSchedule) -> AppResult<()> {
+/// 스누즈 시간이 지난 알림을 다시 안 읽음으로 표시 (반환: 다시 표시된 알림 수)
+pub fn resurface_snoozed_notifications() -> AppResult<usize> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
+    let count = conn.execute(
+        "UPDATE notifications SET snoozed_until = NULL, is_read = 0, read_at = NULL WHERE snoozed_until IS NOT NULL AND snoozed_until <= ?1",
+        [Utc::now().to_rfc3339()],
+    )?;
+    Ok(count)
+}
 
-    let medication_times_json = serde_json::to_string(&schedule.medication_times)?;
-
+/// 설문 세션 생성 기록 후 기간 내 직원/IP별 생성 수 반환 (직원 수, IP 수)
+pub fn log_survey_session_creation(
+    session_id: &str,
+    staff_id: Option<&str>,
+    ip: Option<&str>,
+    window_minutes: i64,
+) -> AppResult<(i32, i32)> {
+    let conn = get_conn()?;
+    let now = Utc::now();
     conn.execute(
-        r#"INSERT INTO medication_schedules (id, patient_id, prescription_id, start_date, end_date, times_per_day, medication_times, notes, created_at)
-           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)"#,
-        params![
-            schedule.id,
-            schedule.patient_id,
-            schedule.prescription_id,
-            schedule.start_date.to_rfc3339(),
-            schedule.end_date.to_rfc3339(),
-            schedule.times_per_day,
-            medication_times_json,
-            schedule.notes,
-            schedule.created_at.to_rfc3339(),
-        ],
+        "INSERT INTO survey_session_creation_logs (id, session_id, staff_id, ip, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![uuid::Uuid::new_v4().to_string(), session_id, staff_id, ip, now.to_rfc3339()],
     )?;
 
-    log::info!("복약 일정 생성됨: {}", schedule.id);
+    let since = (now - chrono::Duration::minutes(window_minutes)).to_rfc3339();
+    let by_staff: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM survey_session_creation_logs WHERE staff_id = ?1 AND created_at >= ?2",
+        params![staff_id, since],
+        |row| row.get(0),
+    )?;
+    let by_ip: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM survey_session_creation_logs WHERE ip = ?1 AND created_at >= ?2",
+        params![ip, since],
+        |row| row.get(0),
+    )?;
+    Ok((by_staff, by_ip))
+}
+
+/// 설문 세션 과다 생성 경고 알림
+pub fn notify_survey_session_abuse(subject: &str, count: i32, window_minutes: i64) -> AppResult<()> {
+    let conn = get_conn()?;
+    insert_notification(
+        &conn,
+        "survey_session_abuse",
+        "설문 세션 과다 생성 감지",
+        &format!("{}에서 최근 {}분 동안 설문 세션이 {}건 생성되었습니다.", subject, window_minutes, count),
+        "high",
+        None,
+    )?;
+    log::warn!("설문 세션 과다 생성 감지: {} ({}건/{}분)", subject, count, window_minutes);
     Ok(())
 }
 
-/// 복약 일정 수정
-pub fn update_medication_schedule(id: &str, schedule: &MedicationSchedule) -> AppResult<()> {
+/// 환자 미연결 응답 알림 기준 (제출 후 경과 시간)
+pub const UNLINKED_RESPONSE_HOURS: i64 = 24;
+
+/// 제출 후 `older_than_hours` 시간이 지나도록 환자와 연결되지 않은 응답 수 (익명 설문 제외)
+pub fn count_unlinked_responses(older_than_hours: i64) -> AppResult<i64> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
+    query_unlinked_response_count(&conn, older_than_hours)
+}
 
-    let medication_times_json = serde_json::to_string(&schedule.medication_times)?;
-
-    conn.execute(
-        r#"UPDATE medication_schedules SET
-           patient_id = ?2, prescription_id = ?3, start_date = ?4, end_date = ?5,
-           times_per_day = ?6, medication_times = ?7, notes = ?8
-           WHERE id = ?1"#,
-        params![
-            id,
-            schedule.patient_id,
-            schedule.prescription_id,
-            schedule.start_date.to_rfc3339(),
-            schedule.end_date.to_rfc3339(),
-            schedule.times_per_day,
-            medication_times_json,
-            schedule.notes,
-        ],
+fn query_unlinked_response_count(conn: &Connection, older_than_hours: i64) -> AppResult<i64> {
+    let cutoff = (Utc::now() - chrono::Duration::hours(older_than_hours)).to_rfc3339();
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM survey_responses WHERE patient_id IS NULL AND anonymous = 0 AND submitted_at < ?1",
+        [cutoff],
+        |row| row.get(0),
     )?;
-
-    log::info!("복약 일정 수정됨: {}", id);
-    Ok(())
+    Ok(count)
 }
 
-/// 복약 일정 삭제
-pub fn delete_medication_schedule(id: &str) -> AppResult<()> {
+/// 미연결 응답이 있으면 직원 알림 생성 (하루 한 번, 생성한 경우 건수 반환)
+pub fn notify_unlinked_responses() -> AppResult<Option<i64>> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
+    if has_recent_notification(&conn, "unlinked_responses", 24)? {
+        return Ok(None);
+    }
+    let count = query_unlinked_response_count(&conn, UNLINKED_RESPONSE_HOURS)?;
+    if count == 0 {
+        return Ok(None);
+    }
+    insert_notification(
+        &conn,
+        "unlinked_responses",
+        "환자 미연결 설문 응답",
+        &format!(
+            "제출 후 {}시간이 지나도록 환자와 연결되지 않은 설문 응답이 {}건 있습니다.",
+            UNLINKED_RESPONSE_HOURS, count
+        ),
+        "normal",
+        None,
+    )?;
+    Ok(Some(count))
+}
 
-    // 연관된 복약 기록도 삭제
-    conn.execute("DELETE FROM medication_logs WHERE schedule_id = ?1", [id])?;
-    conn.execute("DELETE FROM medication_schedules WHERE id = ?1", [id])?;
+// ============ 후속 설문 ============
 
-    log::info!("복약 일정 삭제됨: {}", id);
-    Ok(())
-}
+/// 후속 설문 연쇄 최대 단계 (후속의 후속... 무한 반복 방지)
+pub const MAX_FOLLOW_UP_DEPTH: i32 = 3;
 
-// ============ 복약 기록 관리 ============
+/// 후속 설문 규칙 저장 (생성 또는 수정)
+pub fn save_follow_up_rule(rule: &SurveyFollowUpRule) -> AppResult<()> {
+    if rule.source_template_id == rule.follow_up_template_id {
+        return Err(AppError::Custom("같은 설문을 후속 설문으로 지정할 수 없습니다".to_string()));
+    }
+    match rule.trigger_type.as_str() {
+        "completed" => {}
+        "score" => {
+            if rule.question_id.as_deref().map_or(true, |q| q.is_empty()) {
+                return Err(AppError::Custom("점수 조건에는 기준 문항이 필요합니다".to_string()));
+            }
+            if rule.min_score.is_none() && rule.max_score.is_none() {
+                return Err(AppError::Custom("점수 조건에는 최소 또는 최대 점수가 필요합니다".to_string()));
+            }
+        }
+        other => return Err(AppError::Custom(format!("알 수 없는 트리거 조건: {}", other))),
+    }
+    if rule.delay_hours < 0 {
+        return Err(AppError::Custom("지연 시간은 0 이상이어야 합니다".to_string()));
+    }
 
-/// 복약 기록 목록 조회
-pub fn list_medication_logs() -> AppResult<Vec<MedicationLog>> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-
-    let mut stmt = conn.prepare(
-        r#"SELECT id, schedule_id, taken_at, status, notes
-           FROM medication_logs ORDER BY taken_at DESC"#,
+    conn.execute(
+        r#"INSERT OR REPLACE INTO survey_follow_up_rules
+           (id, source_template_id, follow_up_template_id, trigger_type, question_id, min_score, max_score, delay_hours, is_active, created_at)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"#,
+        params![
+            rule.id,
+            rule.source_template_id,
+            rule.follow_up_template_id,
+            rule.trigger_type,
+            rule.question_id,
+            rule.min_score,
+            rule.max_score,
+            rule.delay_hours,
+            rule.is_active as i32,
+            rule.created_at,
+        ],
     )?;
+    Ok(())
+}
 
-    let rows = stmt.query_map([], |row| {
-        let status_str: String = row.get(3)?;
-        let status = match status_str.as_str() {
-            "taken" => MedicationStatus::Taken,
-            "missed" => MedicationStatus::Missed,
-            "skipped" => MedicationStatus::Skipped,
-            _ => MedicationStatus::Taken,
-        };
-        Ok(MedicationLog {
-            id: row.get(0)?,
-            schedule_id: row.get(1)?,
-            taken_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(2)?)
-                .unwrap()
-                .with_timezone(&Utc),
-            status,
-            notes: row.get(4)?,
-        })
-    })?;
-
-    let mut logs = Vec::new();
-    for row in rows {
-        logs.push(row?);
-    }
-    Ok(logs)
+/// 후속 설문 규칙 목록
+pub fn list_follow_up_rules() -> AppResult<Vec<SurveyFollowUpRule>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    query_follow_up_rules(&conn, None)
 }
 
-/// 복약 기록 조회 (ID로)
-pub fn get_medication_log(id: &str) -> AppResult<Option<MedicationLog>> {
+/// 후속 설문 규칙 삭제
+pub fn delete_follow_up_rule(id: &str) -> AppResult<()> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
+    conn.execute("DELETE FROM survey_follow_up_rules WHERE id = ?1", [id])?;
+    Ok(())
+}
 
+fn query_follow_up_rules(conn: &Connection, active_source_template: Option<&str>) -> AppResult<Vec<SurveyFollowUpRule>> {
     let mut stmt = conn.prepare(
-        r#"SELECT id, schedule_id, taken_at, status, notes
-           FROM medication_logs WHERE id = ?1"#,
+        r#"SELECT id, source_template_id, follow_up_template_id, trigger_type, question_id, min_score, max_score, delay_hours, is_active, created_at
+           FROM survey_follow_up_rules
+           WHERE ?1 IS NULL OR (source_template_id = ?1 AND is_active = 1)
+           ORDER BY created_at"#,
     )?;
+    let rules = stmt
+        .query_map([active_source_template], |row| {
+            Ok(SurveyFollowUpRule {
+                id: row.get(0)?,
+                source_template_id: row.get(1)?,
+                follow_up_template_id: row.get(2)?,
+                trigger_type: row.get(3)?,
+                question_id: row.get(4)?,
+                min_score: row.get(5)?,
+                max_score: row.get(6)?,
+                delay_hours: row.get(7)?,
+                is_active: row.get::<_, i32>(8)? != 0,
+                created_at: row.get(9)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rules)
+}
 
-    let result = stmt.query_row([id], |row| {
-        let status_str: String = row.get(3)?;
-        let status = match status_str.as_str() {
-            "taken" => MedicationStatus::Taken,
-            "missed" => MedicationStatus::Missed,
-            "skipped" => MedicationStatus::Skipped,
-            _ => MedicationStatus::Taken,
-        };
-        Ok(MedicationLog {
-            id: row.get(0)?,
-            schedule_id: row.get(1)?,
-            taken_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(2)?)
-                .unwrap()
-                .with_timezone(&Utc),
-            status,
-            notes: row.get(4)?,
-        })
-    });
+/// 규칙의 트리거 조건 충족 여부
+fn follow_up_rule_matches(rule: &SurveyFollowUpRule, answers: &[SurveyAnswer]) -> bool {
+    if rule.trigger_type != "score" {
+        return true;
+    }
 
-    match result {
-        Ok(log) => Ok(Some(log)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(e.into()),
+    let score = rule
+        .question_id
+        .as_deref()
+        .and_then(|qid| answers.iter().find(|a| a.question_id == qid))
+        .and_then(|a| match &a.answer {
+            serde_json::Value::Number(n) => n.as_f64(),
+            serde_json::Value::String(s) => s.trim().parse::<f64>().ok(),
+            _ => None,
+        });
+
+    match score {
+        Some(score) => {
+            rule.min_score.map_or(true, |min| score >= min) && rule.max_score.map_or(true, |max| score <= max)
+        }
+        None => false,
     }
 }
 
-/// 일정별 복약 기록 조회
-pub fn get_medication_logs_by_schedule(schedule_id: &str) -> AppResult<Vec<MedicationLog>> {
+/// 설문 완료 시 조건에 맞는 후속 설문 예약 (예약 건수 반환)
+pub fn schedule_survey_follow_ups(session_id: &str, template_id: &str, answers: &[SurveyAnswer]) -> AppResult<usize> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
 
-    let mut stmt = conn.prepare(
-        r#"SELECT id, schedule_id, taken_at, status, notes
-           FROM medication_logs WHERE schedule_id = ?1 ORDER BY taken_at DESC"#,
-    )?;
-
-    let rows = stmt.query_map([schedule_id], |row| {
-        let status_str: String = row.get(3)?;
+    // 이 세션 자체가 후속 설문이면 그 단계를 이어받음
+    let depth: i32 = conn
+        .query_row(
+            "SELECT chain_depth FROM survey_follow_ups WHERE session_id = ?1",
+            [session_id],
+            |row| row.get(0),
+        )
+        .unwrap_or(0);
+    if depth >= MAX_FOLLOW_UP_DEPTH {
+        log::info!("후속 설문 연쇄 한도 도달 (session: {}, depth: {})", session_id, depth);
+        return Ok(0);
+    }
+
+    let now = Utc::now();
+    let mut scheduled = 0;
+    for rule in query_follow_up_rules(&conn, Some(template_id))? {
+        if !follow_up_rule_matches(&rule, answers) {
+            continue;
+        }
+        scheduled += conn.execute(
+            r#"INSERT OR IGNORE INTO survey_follow_ups (id, rule_id, source_session_id, follow_up_template_id, chain_depth, due_at, created_at)
+               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"#,
+            params![
+                uuid::Uuid::new_v4().to_string(),
+                rule.id,
+                session_id,
+                rule.follow_up_template_id,
+                depth + 1,
+                (now + chrono::Duration::hours(rule.delay_hours)).to_rfc3339(),
+                now.to_rfc3339(),
+            ],
+        )?;
+    }
+    Ok(scheduled)
+}
+
+/// 예약 시각이 된 후속 설문 세션 생성 후 직원 알림 (생성 건수 반환)
+pub fn process_due_survey_follow_ups() -> AppResult<usize> {
+    ensure_db_initialized()?;
+    let due: Vec<(String, String, String)> = {
+        let conn = get_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, source_session_id, follow_up_template_id FROM survey_follow_ups
+             WHERE session_id IS NULL AND due_at <= ?1 ORDER BY due_at",
+        )?;
+        let rows = stmt.query_map([Utc::now().to_rfc3339()], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect::<Result<_, _>>()?
+    };
+
+    let mut created = 0;
+    for (follow_up_id, source_session_id, template_id) in due {
+        // 동시에 실행된 다른 처리와 중복 생성하지 않도록 먼저 선점
+        let claimed = get_conn()?.execute(
+            "UPDATE survey_follow_ups SET session_id = '' WHERE id = ?1 AND session_id IS NULL",
+            [&follow_up_id],
+        )?;
+        if claimed == 0 {
+            continue;
+        }
+
+        match create_follow_up_session(&follow_up_id, &source_session_id, &template_id) {
+            Ok(true) => created += 1,
+            // 원본 세션이 삭제되어 만들 수 없는 후속 설문은 예약 취소
+            Ok(false) => {
+                get_conn()?.execute("DELETE FROM survey_follow_ups WHERE id = ?1", [&follow_up_id])?;
+            }
+            // 선점을 풀어 다음 실행 때 다시 시도
+            Err(e) => {
+                log::warn!("후속 설문 세션 생성 실패 ({}): {}", follow_up_id, e);
+                get_conn()?.execute(
+                    "UPDATE survey_follow_ups SET session_id = NULL WHERE id = ?1 AND session_id = ''",
+                    [&follow_up_id],
+                )?;
+            }
+        }
+    }
+    Ok(created)
+}
+
+/// 선점한 후속 설문의 세션 생성 + 직원 알림 (원본 세션이 없으면 false)
+fn create_follow_up_session(follow_up_id: &str, source_session_id: &str, template_id: &str) -> AppResult<bool> {
+    let Some(source) = get_survey_session(source_session_id)? else {
+        return Ok(false);
+    };
+    let session = create_survey_session(
+        source.patient_id.as_deref(),
+        template_id,
+        source.respondent_name.as_deref(),
+        Some("follow_up"),
+        None,
+        source.patient_name.as_deref(),
+        source.chart_number.as_deref(),
+        source.patient_age.as_deref(),
+        source.patient_gender.as_deref(),
+        false,
+        None,
+    )?;
+
+    let conn = get_conn()?;
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "UPDATE survey_follow_ups SET session_id = ?1 WHERE id = ?2",
+        params![session.id, follow_up_id],
+    )?;
+    let name = source
+        .patient_name
+        .as_deref()
+        .or(source.respondent_name.as_deref())
+        .unwrap_or("응답자");
+    insert_notification(
+        &tx,
+        "survey_follow_up",
+        "후속 설문 링크 생성",
+        &format!("{}님의 후속 설문 링크가 생성되었습니다: /s/{}", name, session.token),
+        "normal",
+        source.patient_id.as_deref(),
+    )?;
+    tx.commit()?;
+    Ok(true)
+}
+
+/// 설문 세션 목록 조회 (환자명/템플릿명 포함)
+pub fn list_survey_sessions(
+    patient_id: Option<&str>,
+    status: Option<&str>,
+    limit: Option<i32>,
+) -> AppResult<Vec<SurveySessionWithPatient>> {
+    let conn = get_conn()?;
+    let mut sql = String::from(
+        "SELECT s.id, s.token, s.patient_id, s.template_id, s.respondent_name, s.status, s.expires_at, s.created_by, s.created_at, s.completed_at, COALESCE(p.name, s.patient_name) as patient_name, s.chart_number, s.patient_age, s.patient_gender, t.name
+         FROM survey_sessions s
+         LEFT JOIN patients p ON s.patient_id = p.id
+         LEFT JOIN survey_templates t ON s.template_id = t.id
+         WHERE 1=1"
+    );
+    let mut params_vec: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+
+    if let Some(pid) = patient_id {
+        sql.push_str(&format!(" AND s.patient_id = ?{}", params_vec.len() + 1));
+        params_vec.push(Box::new(pid.to_string()));
+    }
+    if let Some(st) = status {
+        sql.push_str(&format!(" AND s.status = ?{}", params_vec.len() + 1));
+        params_vec.push(Box::new(st.to_string()));
+    }
+    sql.push_str(" ORDER BY s.created_at DESC");
+    if let Some(limit) = limit {
+        sql.push_str(&format!(" LIMIT ?{}", params_vec.len() + 1));
+        params_vec.push(Box::new(limit));
+    }
+
+    let params_refs: Vec<&dyn rusqlite::types::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params_refs.as_slice(), |row| {
+        let status_str: String = row.get(5)?;
         let status = match status_str.as_str() {
-            "taken" => MedicationStatus::Taken,
-            "missed" => MedicationStatus::Missed,
-            "skipped" => MedicationStatus::Skipped,
-            _ => MedicationStatus::Taken,
+            "completed" => "completed".to_string(),
+            "expired" => "expired".to_string(),
+            "locked" => "locked".to_string(),
+            _ => "pending".to_string(),
         };
-        Ok(MedicationLog {
+        Ok(SurveySessionWithPatient {
             id: row.get(0)?,
-            schedule_id: row.get(1)?,
-            taken_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(2)?)
-                .unwrap()
-                .with_timezone(&Utc),
+            token: row.get(1)?,
+            patient_id: row.get(2)?,
+            template_id: row.get(3)?,
+            respondent_name: row.get(4)?,
             status,
-            notes: row.get(4)?,
+            expires_at: row.get(6)?,
+            created_by: row.get(7)?,
+            created_at: row.get(8)?,
+            completed_at: row.get(9)?,
+            patient_name: row.get(10)?,
+            chart_number: row.get(11)?,
+            patient_age: row.get(12)?,
+            patient_gender: row.get(13)?,
+            template_name: row.get(14)?,
         })
     })?;
 
-    let mut logs = Vec::new();
+    let mut sessions = Vec::new();
     for row in rows {
-        logs.push(row?);
+        let mut session = row?;
+        // 만료 확인
+        if session.status == "pending" {
+            if let Ok(expires) = chrono::DateTime::parse_from_rfc3339(&session.expires_at) {
+                if expires < Utc::now() {
+                    conn.execute(
+                        "UPDATE survey_sessions SET status = 'expired' WHERE id = ?1",
+                        [&session.id],
+                    )?;
+                    session.status = "expired".to_string();
+                }
+            }
+        }
+        // 방금 만료 처리된 세션은 상태 필터에서 제외
+        if status.is_some_and(|st| st != session.status) {
+            continue;
+        }
+        sessions.push(session);
     }
-    Ok(logs)
+
+    Ok(sessions)
 }
 
-/// 복약 기록 생성
-pub fn create_medication_log(log: &MedicationLog) -> AppResult<()> {
-    ensure_db_initialized()?;
+/// 설문 세션 ID로 조회
+pub fn get_survey_session(id: &str) -> AppResult<Option<SurveySessionDb>> {
     let conn = get_conn()?;
-
-    let status_str = match log.status {
-        MedicationStatus::Taken => "taken",
-        MedicationStatus::Missed => "missed",
-        MedicationStatus::Skipped => "skipped",
-    };
-
-    conn.execute(
-        r#"INSERT INTO medication_logs (id, schedule_id, taken_at, status, notes)
-           VALUES (?1, ?2, ?3, ?4, ?5)"#,
-        params![
-            log.id,
-            log.schedule_id,
-            log.taken_at.to_rfc3339(),
-            status_str,
-            log.notes,
-        ],
+    let mut stmt = conn.prepare(
+        "SELECT id, token, patient_id, template_id, respondent_name, status, expires_at, created_at, patient_name, chart_number, patient_age, patient_gender, verify_birth_date, display_order
+         FROM survey_sessions WHERE id = ?1",
     )?;
 
-    log::info!("복약 기록 생성됨: {}", log.id);
-    Ok(())
+    let result = stmt.query_row([id], |row| {
+        let status_str: String = row.get(5)?;
+        let status = match status_str.as_str() {
+            "completed" => SessionStatus::Completed,
+            "expired" => SessionStatus::Expired,
+            "locked" => SessionStatus::Locked,
+            _ => SessionStatus::Pending,
+        };
+        Ok(SurveySessionDb {
+            id: row.get(0)?,
+            token: row.get(1)?,
+            patient_id: row.get(2)?,
+            template_id: row.get(3)?,
+            respondent_name: row.get(4)?,
+            patient_name: row.get(8)?,
+            chart_number: row.get(9)?,
+            patient_age: row.get(10)?,
+            patient_gender: row.get(11)?,
+            status,
+            expires_at: row.get(6)?,
+            created_at: row.get(7)?,
+            verify_birth_date: row.get::<_, i32>(12)? != 0,
+            display_order: parse_display_order(row.get(13)?),
+        })
+    });
+
+    match result {
+        Ok(session) => Ok(Some(session)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
 }
 
-/// 복약 기록 수정
-pub fn update_medication_log(id: &str, log: &MedicationLog) -> AppResult<()> {
+/// 만료 시각이 지난 대기 세션을 일괄 만료 처리 (처리 건수 반환)
+pub fn expire_overdue_survey_sessions() -> AppResult<usize> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
+    let count = conn.execute(
+        "UPDATE survey_sessions SET status = 'expired' WHERE status = 'pending' AND expires_at < ?1",
+        [Utc::now().to_rfc3339()],
+    )?;
+    Ok(count)
+}
 
-    let status_str = match log.status {
-        MedicationStatus::Taken => "taken",
-        MedicationStatus::Missed => "missed",
-        MedicationStatus::Skipped => "skipped",
-    };
+// ============ 오래된 기록 정리 ============
 
-    conn.execute(
-        r#"UPDATE medication_logs SET
-           schedule_id = ?2, taken_at = ?3, status = ?4, notes = ?5
-           WHERE id = ?1"#,
-        params![
-            id,
-            log.schedule_id,
-            log.taken_at.to_rfc3339(),
-            status_str,
-            log.notes,
-        ],
-    )?;
+/// 자동 정리 보관 기간 최소값 (일, 실수로 최근 기록을 지우지 않도록)
+pub const MIN_RETENTION_DAYS: i64 = 30;
 
-    log::info!("복약 기록 수정됨: {}", id);
+/// 자동 정리 보관 기간 조회 (설정이 없으면 모두 유지)
+pub fn get_retention_settings() -> AppResult<RetentionSettings> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    Ok(get_global_notification_setting(&conn, "retention_settings")
+        .and_then(|j| serde_json::from_str(&j).ok())
+        .unwrap_or_default())
+}
+
+/// 자동 정리 보관 기간 저장 (항목별 30일 이상, None이면 해당 항목은 정리하지 않음)
+pub fn save_retention_settings(settings: &RetentionSettings) -> AppResult<()> {
+    for days in [settings.survey_responses_days, settings.notifications_days, settings.medication_logs_days]
+        .into_iter()
+        .flatten()
+    {
+        if days < MIN_RETENTION_DAYS {
+            return Err(AppError::Validation(format!("보관 기간은 {}일 이상이어야 합니다", MIN_RETENTION_DAYS)));
+        }
+    }
+
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let json = (!settings.is_empty()).then(|| serde_json::to_string(settings)).transpose()?;
+    set_global_notification_setting(&conn, "retention_settings", json)?;
+    log::info!("자동 정리 보관 기간 저장: {:?}", settings);
     Ok(())
 }
 
-/// 환자별 복약 통계 조회
-pub fn get_medication_stats_by_patient(patient_id: &str) -> AppResult<MedicationStats> {
+/// 오늘 자동 정리를 실행할 차례인지 확인하고 실행일 기록 (하루 한 번, 보관 기간이 없으면 None)
+pub fn claim_daily_retention_purge() -> AppResult<Option<RetentionSettings>> {
+    let settings = get_retention_settings()?;
+    if settings.is_empty() {
+        return Ok(None);
+    }
+
+    let conn = get_conn()?;
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    if get_global_notification_setting(&conn, "retention_last_run_on").as_deref() == Some(today.as_str()) {
+        return Ok(None);
+    }
+    set_global_notification_setting(&conn, "retention_last_run_on", Some(today))?;
+    Ok(Some(settings))
+}
+
+/// 보관 기간이 지난 설문 응답(첨부 포함), 읽었거나 닫은 알림, 복약 기록 삭제
+///
+/// 환자/차트/처방 기록은 건드리지 않습니다. `dry_run`이면 지우지 않고 대상 건수만 셉니다.
+pub fn purge_old_data(retention: &RetentionSettings, dry_run: bool) -> AppResult<PurgeSummary> {
+    let cutoff = |days: Option<i64>| days.map(|d| (Utc::now() - chrono::Duration::days(d)).to_rfc3339());
+    let mut summary = PurgeSummary { dry_run, ..Default::default() };
+
     ensure_db_initialized()?;
     let conn = get_conn()?;
+    let tx = conn.unchecked_transaction()?;
+    if let Some(cutoff) = cutoff(retention.survey_responses_days) {
+        if !dry_run {
+            tx.execute(
+                "DELETE FROM survey_attachments WHERE response_id IN (SELECT id FROM survey_responses WHERE submitted_at < ?1)",
+                [&cutoff],
+            )?;
+        }
+        summary.survey_responses = purge_rows(&tx, "survey_responses WHERE submitted_at < ?1", &cutoff, dry_run)?;
+    }
+    if let Some(cutoff) = cutoff(retention.notifications_days) {
+        let target = "notifications WHERE created_at < ?1 AND (is_read = 1 OR is_dismissed = 1)";
+        summary.notifications = purge_rows(&tx, target, &cutoff, dry_run)?;
+    }
+    if let Some(cutoff) = cutoff(retention.medication_logs_days) {
+        summary.medication_logs = purge_rows(&tx, "medication_logs WHERE taken_at < ?1", &cutoff, dry_run)?;
+    }
+    tx.commit()?;
 
-    // 전체 일정 수
-    let total_schedules: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM medication_schedules WHERE patient_id = ?1",
-        [patient_id],
-        |row| row.get(0),
-    )?;
+    if !dry_run && summary.survey_responses > 0 {
+        invalidate_survey_stats();
+    }
+    Ok(summary)
+}
 
-    // 활성 일정 수 (종료일이 현재 시간 이후)
-    let now = Utc::now().to_rfc3339();
-    let active_schedules: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM medication_schedules WHERE patient_id = ?1 AND end_date > ?2",
-        params![patient_id, now],
-        |row| row.get(0),
-    )?;
+/// `target`("테이블 WHERE 조건")에 해당하는 행 삭제 (`dry_run`이면 건수만)
+fn purge_rows(conn: &Connection, target: &str, cutoff: &str, dry_run: bool) -> AppResult<usize> {
+    if dry_run {
+        let count: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM {}", target), [cutoff], |row| row.get(0))?;
+        return Ok(count as usize);
+    }
+    Ok(conn.execute(&format!("DELETE FROM {}", target), [cutoff])?)
+}
 
-    // 복약 기록 통계
-    let total_logs: i32 = conn.query_row(
-        r#"SELECT COUNT(*) FROM medication_logs ml
-           JOIN medication_schedules ms ON ml.schedule_id = ms.id
-           WHERE ms.patient_id = ?1"#,
-        [patient_id],
-        |row| row.get(0),
-    )?;
+// ============ DB 최적화 ============
 
-    let taken_count: i32 = conn.query_row(
-        r#"SELECT COUNT(*) FROM medication_logs ml
-           JOIN medication_schedules ms ON ml.schedule_id = ms.id
-           WHERE ms.patient_id = ?1 AND ml.status = 'taken'"#,
-        [patient_id],
-        |row| row.get(0),
-    )?;
+/// DB 최적화 주간 자동 실행 설정 조회 (설정이 없으면 끔)
+pub fn get_db_maintenance_settings() -> AppResult<DbMaintenanceSettings> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    Ok(get_global_notification_setting(&conn, "db_maintenance_settings")
+        .and_then(|j| serde_json::from_str(&j).ok())
+        .unwrap_or_default())
+}
 
-    let missed_count: i32 = conn.query_row(
-        r#"SELECT COUNT(*) FROM medication_logs ml
-           JOIN medication_schedules ms ON ml.schedule_id = ms.id
-           WHERE ms.patient_id = ?1 AND ml.status = 'missed'"#,
-        [patient_id],
-        |row| row.get(0),
-    )?;
+/// DB 최적화 주간 자동 실행 설정 저장 (요일 0~6, 시각 0~23)
+pub fn save_db_maintenance_settings(settings: &DbMaintenanceSettings) -> AppResult<()> {
+    if settings.weekday > 6 {
+        return Err(AppError::Validation("요일은 0(월)~6(일) 사이여야 합니다".to_string()));
+    }
+    if settings.hour > 23 {
+        return Err(AppError::Validation("실행 시각은 0~23시 사이여야 합니다".to_string()));
+    }
 
-    let skipped_count: i32 = conn.query_row(
-        r#"SELECT COUNT(*) FROM medication_logs ml
-           JOIN medication_schedules ms ON ml.schedule_id = ms.id
-           WHERE ms.patient_id = ?1 AND ml.status = 'skipped'"#,
-        [patient_id],
-        |row| row.get(0),
-    )?;
-
-    // 복약 순응률 계산 (복용한 횟수 / 전체 기록 수 * 100)
-    let compliance_rate = if total_logs > 0 {
-        (taken_count as f64 / total_logs as f64) * 100.0
-    } else {
-        0.0
-    };
-
-    Ok(MedicationStats {
-        patient_id: patient_id.to_string(),
-        total_schedules,
-        active_schedules,
-        total_logs,
-        taken_count,
-        missed_count,
-        skipped_count,
-        compliance_rate,
-    })
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    set_global_notification_setting(&conn, "db_maintenance_settings", Some(serde_json::to_string(settings)?))?;
+    log::info!("DB 최적화 자동 실행 설정 저장: {:?}", settings);
+    Ok(())
 }
 
-// ============ 처방 카테고리 ============
-// (알림 관련 함수 제거됨)
+/// 이번 주 자동 최적화를 실행할 차례인지 확인하고 실행일 기록
+///
+/// 설정한 요일의 설정 시각 이후 첫 점검 때 한 번 실행. 내보내기 작업 중이면 기록하지 않아 다음 점검 때 다시 시도합니다.
+pub fn claim_weekly_db_maintenance() -> AppResult<bool> {
+    use chrono::{Datelike, Timelike};
+
+    let settings = get_db_maintenance_settings()?;
+    let now = chrono::Local::now();
+    if !settings.weekly_enabled
+        || now.weekday().num_days_from_monday() != settings.weekday
+        || now.hour() < settings.hour
+        || crate::jobs::has_active_jobs()
+    {
+        return Ok(false);
+    }
 
-// ============ 처방 카테고리 ============
+    let conn = get_conn()?;
+    let today = now.format("%Y-%m-%d").to_string();
+    if get_global_notification_setting(&conn, "db_maintenance_last_run_on").as_deref() == Some(today.as_str()) {
+        return Ok(false);
+    }
+    set_global_notification_setting(&conn, "db_maintenance_last_run_on", Some(today))?;
+    Ok(true)
+}
 
-pub fn list_prescription_categories() -> AppResult<Vec<PrescriptionCategory>> {
+/// WAL 체크포인트(TRUNCATE) 후 VACUUM으로 DB 파일 정리
+///
+/// 내보내기 작업이 진행 중이거나 열린 트랜잭션이 있으면 실행하지 않습니다.
+/// 실행 동안 DB 연결을 잡고 있으므로 다른 요청은 끝날 때까지 대기합니다.
+pub fn run_maintenance() -> AppResult<DbMaintenanceReport> {
+    if crate::jobs::has_active_jobs() {
+        return Err(AppError::Conflict("내보내기 작업이 진행 중입니다. 작업이 끝난 뒤 다시 시도해주세요".to_string()));
+    }
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    let mut stmt = conn.prepare(
-        "SELECT id, name, color, sort_order, created_at FROM prescription_categories ORDER BY sort_order, name"
-    )?;
-    let rows = stmt.query_map([], |row| {
-        Ok(PrescriptionCategory {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            color: row.get(2)?,
-            sort_order: row.get(3)?,
-            created_at: row.get(4)?,
-        })
-    })?;
-    let mut result = Vec::new();
-    for row in rows {
-        result.push(row?);
+    if !conn.is_autocommit() {
+        return Err(AppError::Conflict("진행 중인 트랜잭션이 있어 DB 최적화를 할 수 없습니다".to_string()));
     }
-    Ok(result)
+
+    let started = std::time::Instant::now();
+    let (db_size_before, wal_size_before) = db_file_sizes(&conn);
+    // WAL 모드가 아니면 (0, -1, -1)을 돌려주고 아무것도 하지 않음
+    let busy: i64 = conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))?;
+    if busy != 0 {
+        log::warn!("WAL 체크포인트가 완료되지 않았습니다 (다른 연결이 읽는 중)");
+    }
+    conn.execute_batch("VACUUM")?;
+    let (db_size_after, wal_size_after) = db_file_sizes(&conn);
+
+    let report = DbMaintenanceReport {
+        db_size_before,
+        db_size_after,
+        wal_size_before,
+        wal_size_after,
+        duration_ms: started.elapsed().as_millis() as u64,
+    };
+    log::info!("DB 최적화 완료: {:?}", report);
+    Ok(report)
 }
 
-pub fn create_prescription_category(cat: &PrescriptionCategory) -> AppResult<i64> {
+/// DB 파일과 WAL 파일 크기 (바이트, 파일이 없으면 0)
+fn db_file_sizes(conn: &Connection) -> (u64, u64) {
+    let Some(path) = conn.path().filter(|p| !p.is_empty()) else {
+        return (0, 0);
+    };
+    let size = |p: &str| std::fs::metadata(p).map(|m| m.len()).unwrap_or(0);
+    (size(path), size(&format!("{}-wal", path)))
+}
+
+/// 앱 시작 시 정리하는 응답 없는 세션의 보관 기간 (일)
+pub const ORPHANED_SESSION_RETENTION_DAYS: i64 = 90;
+
+/// 응답 없이 만료/완료된 오래된 설문 세션 삭제 (삭제 건수 반환)
+///
+/// 대기 중인 세션, 응답이 있는 세션, 후속 설문 예약에 쓰인 세션은 남깁니다.
+pub fn cleanup_orphaned_survey_sessions(older_than_days: i64) -> AppResult<usize> {
+    if older_than_days < 1 {
+        return Err(AppError::Validation("보관 기간은 1일 이상이어야 합니다".to_string()));
+    }
+    let cutoff = Utc::now() - chrono::Duration::days(older_than_days);
+
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    conn.execute(
-        "INSERT INTO prescription_categories (name, color, sort_order, created_at) VALUES (?1, ?2, ?3, ?4)",
-        params![cat.name, cat.color, cat.sort_order, cat.created_at],
+    let count = conn.execute(
+        r#"DELETE FROM survey_sessions
+           WHERE status <> 'pending'
+             AND created_at < ?1
+             AND NOT EXISTS (SELECT 1 FROM survey_responses r WHERE r.session_id = survey_sessions.id)
+             AND NOT EXISTS (
+                 SELECT 1 FROM survey_follow_ups f
+                 WHERE f.source_session_id = survey_sessions.id OR f.session_id = survey_sessions.id
+             )"#,
+        [cutoff.to_rfc3339()],
     )?;
-    Ok(conn.last_insert_rowid())
+    Ok(count)
 }
 
-pub fn update_prescription_category(cat: &PrescriptionCategory) -> AppResult<()> {
-    ensure_db_initialized()?;
+/// 설문 세션 만료 처리
+pub fn expire_survey_session(id: &str) -> AppResult<()> {
     let conn = get_conn()?;
     conn.execute(
-        "UPDATE prescription_categories SET name = ?1, color = ?2, sort_order = ?3 WHERE id = ?4",
-        params![cat.name, cat.color, cat.sort_order, cat.id],
+        "UPDATE survey_sessions SET status = 'expired' WHERE id = ?1",
+        [id],
     )?;
     Ok(())
 }
 
-pub fn delete_prescription_category(id: i64) -> AppResult<()> {
-    ensure_db_initialized()?;
+/// 설문 세션 취소 (대기 중인 세션을 만료 처리)
+pub fn cancel_survey_session(id: &str) -> AppResult<()> {
     let conn = get_conn()?;
-    conn.execute("DELETE FROM prescription_categories WHERE id = ?1", params![id])?;
+    let updated = conn.execute(
+        "UPDATE survey_sessions SET status = 'expired' WHERE id = ?1 AND status = 'pending'",
+        [id],
+    )?;
+    if updated == 0 {
+        return Err(AppError::Custom("대기 중인 설문 세션만 취소할 수 있습니다".to_string()));
+    }
     Ok(())
 }
 
-// ============ 약재 ============
-
-pub fn list_herbs() -> AppResult<Vec<Herb>> {
+/// 키오스크에서 환자가 자리를 떠난 설문 세션 중단 (대기 중인 세션만 만료 처리)
+/// 세션이 없으면 None, 이미 완료/만료된 세션이면 변경 없이 Some(false)
+pub fn abandon_survey_session(token: &str) -> AppResult<Option<bool>> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    let mut stmt = conn.prepare(
-        "SELECT id, name, default_dosage, unit, description, created_at FROM herbs ORDER BY name"
+    let updated = conn.execute(
+        "UPDATE survey_sessions SET status = 'expired' WHERE token = ?1 AND status = 'pending'",
+        [token],
     )?;
-    let rows = stmt.query_map([], |row| {
-        Ok(Herb {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            default_dosage: row.get(2)?,
-            unit: row.get(3)?,
-            description: row.get(4)?,
-            created_at: row.get(5)?,
-        })
-    })?;
-    let mut result = Vec::new();
-    for row in rows {
-        result.push(row?);
+    if updated > 0 {
+        return Ok(Some(true));
     }
-    Ok(result)
-}
 
-pub fn create_herb(herb: &Herb) -> AppResult<i64> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
-    conn.execute(
-        "INSERT INTO herbs (name, default_dosage, unit, description, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
-        params![herb.name, herb.default_dosage, herb.unit, herb.description, herb.created_at],
-    )?;
-    Ok(conn.last_insert_rowid())
+    let exists = conn
+        .query_row("SELECT 1 FROM survey_sessions WHERE token = ?1", [token], |_| Ok(()))
+        .is_ok();
+    Ok(exists.then_some(false))
 }
 
-pub fn update_herb(herb: &Herb) -> AppResult<()> {
-    ensure_db_initialized()?;
+/// 설문 세션 만료 연장 (현재 만료 시각 기준, 이미 지났으면 지금부터)
+pub fn extend_survey_session(id: &str, hours: i64) -> AppResult<String> {
     let conn = get_conn()?;
+    let current: Option<String> = conn
+        .query_row(
+            "SELECT expires_at FROM survey_sessions WHERE id = ?1 AND status = 'pending'",
+            [id],
+            |row| row.get(0),
+        )
+        .ok();
+    let current = current
+        .ok_or_else(|| AppError::Custom("대기 중인 설문 세션만 연장할 수 있습니다".to_string()))?;
+
+    let now = Utc::now();
+    let base = chrono::DateTime::parse_from_rfc3339(&current)
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or(now)
+        .max(now);
+    let expires_at = (base + chrono::Duration::hours(hours)).to_rfc3339();
+
     conn.execute(
-        "UPDATE herbs SET name = ?1, default_dosage = ?2, unit = ?3, description = ?4 WHERE id = ?5",
-        params![herb.name, herb.default_dosage, herb.unit, herb.description, herb.id],
+        "UPDATE survey_sessions SET expires_at = ?1 WHERE id = ?2",
+        params![expires_at, id],
     )?;
-    Ok(())
+    Ok(expires_at)
 }
 
-pub fn delete_herb(id: i64) -> AppResult<()> {
-    ensure_db_initialized()?;
+/// 설문 세션 삭제 (대기 중인 세션만 가능)
+pub fn delete_survey_session(id: &str) -> AppResult<()> {
     let conn = get_conn()?;
-    conn.execute("DELETE FROM herbs WHERE id = ?1", params![id])?;
+    let deleted = conn.execute(
+        "DELETE FROM survey_sessions WHERE id = ?1 AND status = 'pending' AND expires_at > ?2",
+        params![id, Utc::now().to_rfc3339()],
+    )?;
+    if deleted == 0 {
+        return Err(AppError::Custom("대기 중인 설문 세션만 삭제할 수 있습니다".to_string()));
+    }
     Ok(())
 }
 
-// ============ 처방 정의 ============
-
-pub fn list_prescription_definitions() -> AppResult<Vec<PrescriptionDefinition>> {
-    ensure_db_initialized()?;
+/// 진행 중(대기, 미만료) 설문 세션 목록
+pub fn list_active_survey_sessions() -> AppResult<Vec<SurveySession>> {
     let conn = get_conn()?;
     let mut stmt = conn.prepare(
-        "SELECT id, name, alias, category, source, composition, description, created_at, updated_at FROM prescription_definitions ORDER BY name"
+        "SELECT id, token, template_id, patient_id, respondent_name, expires_at, created_at
+         FROM survey_sessions
+         WHERE status = 'pending' AND expires_at > ?1
+         ORDER BY created_at DESC",
     )?;
-    let rows = stmt.query_map([], |row| {
-        Ok(PrescriptionDefinition {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            alias: row.get(2)?,
-            category: row.get(3)?,
-            source: row.get(4)?,
-            composition: row.get(5)?,
-            description: row.get(6)?,
-            created_at: row.get(7)?,
-            updated_at: row.get(8)?,
-        })
-    })?;
-    let mut result = Vec::new();
-    for row in rows {
-        result.push(row?);
-    }
-    Ok(result)
-}
 
-pub fn get_prescription_definition(id: i64) -> AppResult<Option<PrescriptionDefinition>> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
-    let result = conn.query_row(
-        "SELECT id, name, alias, category, source, composition, description, created_at, updated_at FROM prescription_definitions WHERE id = ?1",
-        params![id],
-        |row| {
-            Ok(PrescriptionDefinition {
+    let sessions = stmt
+        .query_map([Utc::now().to_rfc3339()], |row| {
+            Ok(SurveySession {
                 id: row.get(0)?,
-                name: row.get(1)?,
-                alias: row.get(2)?,
-                category: row.get(3)?,
-                source: row.get(4)?,
-                composition: row.get(5)?,
-                description: row.get(6)?,
-                created_at: row.get(7)?,
-                updated_at: row.get(8)?,
+                token: row.get(1)?,
+                template_id: row.get(2)?,
+                patient_id: row.get(3)?,
+                respondent_name: row.get(4)?,
+                status: SessionStatus::Pending,
+                expires_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(5)?)
+                    .unwrap()
+                    .with_timezone(&Utc),
+                created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(6)?)
+                    .unwrap()
+                    .with_timezone(&Utc),
             })
-        },
-    );
-    match result {
-        Ok(def) => Ok(Some(def)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(e.into()),
-    }
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(sessions)
 }
 
-pub fn create_prescription_definition(def: &PrescriptionDefinition) -> AppResult<i64> {
+/// 대기 중인 세션의 링크 토큰 재발급 (환자/템플릿 연결 유지, 기존 토큰은 무효, 만료 시각은 설정된 유효 시간으로 재설정)
+pub fn regenerate_survey_session_token(session_id: &str) -> AppResult<SurveySessionDb> {
     ensure_db_initialized()?;
-    let conn = get_conn()?;
-    let now = Utc::now().to_rfc3339();
-    conn.execute(
-        "INSERT INTO prescription_definitions (name, alias, category, source, composition, description, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-        params![def.name, def.alias, def.category, def.source, def.composition, def.description, now, now],
-    )?;
-    Ok(conn.last_insert_rowid())
+    {
+        let conn = get_conn()?;
+        let status: Option<String> = conn
+            .query_row("SELECT status FROM survey_sessions WHERE id = ?1", [session_id], |row| row.get(0))
+            .ok();
+        match status.as_deref() {
+            Some("pending") => {}
+            Some(_) => return Err(AppError::Custom("대기 중인 설문 세션만 링크를 다시 만들 수 있습니다".to_string())),
+            None => return Err(AppError::Custom("설문 세션을 찾을 수 없습니다".to_string())),
+        }
+
+        let expires_at = (Utc::now() + chrono::Duration::hours(query_survey_ttl_hours(&conn))).to_rfc3339();
+        conn.execute(
+            "UPDATE survey_sessions SET token = ?1, expires_at = ?2 WHERE id = ?3",
+            params![generate_survey_token(), expires_at, session_id],
+        )?;
+    }
+
+    get_survey_session(session_id)?.ok_or_else(|| AppError::Custom("설문 세션을 찾을 수 없습니다".to_string()))
 }
 
-pub fn update_prescription_definition(def: &PrescriptionDefinition) -> AppResult<()> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
-    let now = Utc::now().to_rfc3339();
-    conn.execute(
-        "UPDATE prescription_definitions SET name = ?1, alias = ?2, category = ?3, source = ?4, composition = ?5, description = ?6, updated_at = ?7 WHERE id = ?8",
-        params![def.name, def.alias, def.category, def.source, def.composition, def.description, now, def.id],
-    )?;
-    Ok(())
+/// 8자리 토큰 생성
+fn generate_survey_token() -> String {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    (0..8)
+        .map(|_| {
+            let idx = rng.gen_range(0..36);
+            if idx < 10 {
+                (b'0' + idx) as char
+            } else {
+                (b'a' + idx - 10) as char
+            }
+        })
+        .collect()
 }
 
-pub fn delete_prescription_definition(id: i64) -> AppResult<()> {
+// ============ 직원 비밀번호 관리 ============
+
+/// 직원 비밀번호 설정
+pub fn set_staff_password(password: &str) -> AppResult<()> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    conn.execute("DELETE FROM prescription_definitions WHERE id = ?1", params![id])?;
+    let hash = bcrypt::hash(password, bcrypt::DEFAULT_COST)
+        .map_err(|e| AppError::Custom(format!("Password hash error: {}", e)))?;
+
+    // 기존 설정이 있는지 확인
+    let existing: Option<String> = conn
+        .query_row("SELECT id FROM clinic_settings LIMIT 1", [], |row| row.get(0))
+        .ok();
+
+    if let Some(id) = existing {
+        conn.execute(
+            "UPDATE clinic_settings SET staff_password_hash = ?, updated_at = ? WHERE id = ?",
+            params![hash, Utc::now().to_rfc3339(), id],
+        )?;
+    } else {
+        // 설정이 없으면 새로 생성
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO clinic_settings (id, clinic_name, staff_password_hash, created_at, updated_at) VALUES (?, '', ?, ?, ?)",
+            params![id, hash, now, now],
+        )?;
+    }
+
+    log::info!("Staff password updated");
     Ok(())
 }
 
-// ============ 처방 노트 ============
-
-pub fn list_prescription_notes(prescription_definition_id: i64) -> AppResult<Vec<PrescriptionNote>> {
+/// 직원 비밀번호 검증
+pub fn verify_staff_password(password: &str) -> AppResult<bool> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    let mut stmt = conn.prepare(
-        "SELECT id, prescription_definition_id, content, created_at, updated_at FROM prescription_notes WHERE prescription_definition_id = ?1 ORDER BY created_at DESC"
-    )?;
-    let rows = stmt.query_map(params![prescription_definition_id], |row| {
-        Ok(PrescriptionNote {
-            id: row.get(0)?,
-            prescription_definition_id: row.get(1)?,
-            content: row.get(2)?,
-            created_at: row.get(3)?,
-            updated_at: row.get(4)?,
-        })
-    })?;
-    let mut result = Vec::new();
-    for row in rows {
-        result.push(row?);
+    let hash: Option<String> = conn
+        .query_row(
+            "SELECT staff_password_hash FROM clinic_settings LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .ok()
+        .flatten();
+
+    match hash {
+        Some(h) => {
+            let valid = bcrypt::verify(password, &h)
+                .map_err(|e| AppError::Custom(format!("Password verify error: {}", e)))?;
+            Ok(valid)
+        }
+        None => Ok(false), // 비밀번호 미설정
     }
-    Ok(result)
 }
 
-pub fn create_prescription_note(note: &PrescriptionNote) -> AppResult<i64> {
+/// 직원 비밀번호 설정 여부 확인
+pub fn has_staff_password() -> AppResult<bool> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    let now = Utc::now().to_rfc3339();
-    conn.execute(
-        "INSERT INTO prescription_notes (prescription_definition_id, content, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
-        params![note.prescription_definition_id, note.content, now, now],
-    )?;
-    Ok(conn.last_insert_rowid())
+    let hash: Option<String> = conn
+        .query_row(
+            "SELECT staff_password_hash FROM clinic_settings LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .ok()
+        .flatten();
+
+    Ok(hash.is_some())
 }
 
-pub fn update_prescription_note(note: &PrescriptionNote) -> AppResult<()> {
+/// HTTP 서버 포트 가져오기
+pub fn get_http_server_port() -> AppResult<u16> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    let now = Utc::now().to_rfc3339();
+    let port: Option<i32> = conn
+        .query_row(
+            "SELECT http_server_port FROM clinic_settings LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .ok()
+        .flatten();
+
+    Ok(port.unwrap_or(3030) as u16)
+}
+
+/// HTTP 서버 포트 설정
+#[allow(dead_code)]
+pub fn set_http_server_port(port: u16) -> AppResult<()> {
+    let conn = get_conn()?;
     conn.execute(
-        "UPDATE prescription_notes SET content = ?1, updated_at = ?2 WHERE id = ?3",
-        params![note.content, now, note.id],
+        "UPDATE clinic_settings SET http_server_port = ?, updated_at = ?",
+        params![port as i32, Utc::now().to_rfc3339()],
     )?;
     Ok(())
 }
 
-pub fn delete_prescription_note(id: i64) -> AppResult<()> {
+/// 설문 링크 기본 유효 시간 (시간)
+pub const DEFAULT_SURVEY_TTL_HOURS: i64 = 48;
+
+fn query_survey_ttl_hours(conn: &Connection) -> i64 {
+    conn.query_row(
+        "SELECT survey_ttl_hours FROM clinic_settings LIMIT 1",
+        [],
+        |row| row.get::<_, Option<i64>>(0),
+    )
+    .ok()
+    .flatten()
+    .filter(|hours| *hours > 0)
+    .unwrap_or(DEFAULT_SURVEY_TTL_HOURS)
+}
+
+/// 설문 링크 유효 시간 조회
+pub fn get_survey_ttl_hours() -> AppResult<i64> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    conn.execute("DELETE FROM prescription_notes WHERE id = ?1", params![id])?;
-    Ok(())
+    Ok(query_survey_ttl_hours(&conn))
 }
 
-// ============ 처방 치험례 ============
-
-pub fn list_prescription_case_studies(prescription_definition_id: i64) -> AppResult<Vec<PrescriptionCaseStudy>> {
+/// 설문 링크 유효 시간 설정 (이후 생성되는 세션부터 적용)
+pub fn set_survey_ttl_hours(hours: i64) -> AppResult<()> {
+    if hours <= 0 {
+        return Err(AppError::Custom("유효 시간은 1시간 이상이어야 합니다".to_string()));
+    }
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    let mut stmt = conn.prepare(
-        "SELECT id, prescription_definition_id, title, content, created_at, updated_at FROM prescription_case_studies WHERE prescription_definition_id = ?1 ORDER BY created_at DESC"
+    conn.execute(
+        "UPDATE clinic_settings SET survey_ttl_hours = ?, updated_at = ?",
+        params![hours, Utc::now().to_rfc3339()],
     )?;
-    let rows = stmt.query_map(params![prescription_definition_id], |row| {
-        Ok(PrescriptionCaseStudy {
-            id: row.get(0)?,
-            prescription_definition_id: row.get(1)?,
-            title: row.get(2)?,
-            content: row.get(3)?,
-            created_at: row.get(4)?,
-            updated_at: row.get(5)?,
-        })
-    })?;
-    let mut result = Vec::new();
-    for row in rows {
-        result.push(row?);
-    }
-    Ok(result)
+    Ok(())
 }
 
-pub fn create_prescription_case_study(cs: &PrescriptionCaseStudy) -> AppResult<i64> {
+/// 키오스크 무입력 대기 시간 기본값 (분)
+pub const DEFAULT_KIOSK_IDLE_MINUTES: i64 = 5;
+
+/// 키오스크 무입력 대기 시간 조회 (이 시간 동안 입력이 없으면 계속 여부 확인)
+pub fn get_kiosk_idle_minutes() -> AppResult<i64> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    let now = Utc::now().to_rfc3339();
-    conn.execute(
-        "INSERT INTO prescription_case_studies (prescription_definition_id, title, content, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5)",
-        params![cs.prescription_definition_id, cs.title, cs.content, now, now],
-    )?;
-    Ok(conn.last_insert_rowid())
+    Ok(conn
+        .query_row(
+            "SELECT kiosk_idle_minutes FROM clinic_settings LIMIT 1",
+            [],
+            |row| row.get::<_, Option<i64>>(0),
+        )
+        .ok()
+        .flatten()
+        .filter(|minutes| *minutes > 0)
+        .unwrap_or(DEFAULT_KIOSK_IDLE_MINUTES))
 }
 
-pub fn update_prescription_case_study(cs: &PrescriptionCaseStudy) -> AppResult<()> {
+/// 키오스크 무입력 대기 시간 설정 (1~60분)
+pub fn set_kiosk_idle_minutes(minutes: i64) -> AppResult<()> {
+    if !(1..=60).contains(&minutes) {
+        return Err(AppError::Custom("대기 시간은 1~60분이어야 합니다".to_string()));
+    }
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    let now = Utc::now().to_rfc3339();
     conn.execute(
-        "UPDATE prescription_case_studies SET title = ?1, content = ?2, updated_at = ?3 WHERE id = ?4",
-        params![cs.title, cs.content, now, cs.id],
+        "UPDATE clinic_settings SET kiosk_idle_minutes = ?, updated_at = ?",
+        params![minutes, Utc::now().to_rfc3339()],
     )?;
     Ok(())
 }
 
-pub fn delete_prescription_case_study(id: i64) -> AppResult<()> {
+/// CORS 추가 허용 출처 조회 (JSON 배열)
+pub fn get_cors_allowed_origins() -> AppResult<Vec<String>> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    conn.execute("DELETE FROM prescription_case_studies WHERE id = ?1", params![id])?;
-    Ok(())
-}
+    let origins: Option<String> = conn
+        .query_row(
+            "SELECT cors_allowed_origins FROM clinic_settings LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .ok()
+        .flatten();
 
-// ============ 복약 관리 (해피콜) ============
+    Ok(origins
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default())
+}
 
-pub fn list_medication_management() -> AppResult<Vec<MedicationManagement>> {
+/// CORS 추가 허용 출처 설정 (서버 재시작 후 적용)
+pub fn set_cors_allowed_origins(origins: &[String]) -> AppResult<()> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    let mut stmt = conn.prepare(
-        "SELECT id, prescription_id, patient_id, patient_name, prescription_name, prescription_date, days, delivery_days, start_date, end_date, happy_call_date, status, notes, postpone_count, postponed_to, contacted_at, created_at, updated_at FROM medication_management ORDER BY happy_call_date ASC"
+    conn.execute(
+        "UPDATE clinic_settings SET cors_allowed_origins = ?, updated_at = ?",
+        params![serde_json::to_string(origins)?, Utc::now().to_rfc3339()],
     )?;
-    let rows = stmt.query_map([], |row| {
-        Ok(MedicationManagement {
-            id: row.get(0)?,
-            prescription_id: row.get(1)?,
-            patient_id: row.get(2)?,
-            patient_name: row.get(3)?,
-            prescription_name: row.get(4)?,
-            prescription_date: row.get(5)?,
-            days: row.get(6)?,
-            delivery_days: row.get(7)?,
-            start_date: row.get(8)?,
-            end_date: row.get(9)?,
-            happy_call_date: row.get(10)?,
-            status: row.get(11)?,
-            notes: row.get(12)?,
-            postpone_count: row.get(13)?,
-            postponed_to: row.get(14)?,
-            contacted_at: row.get(15)?,
-            created_at: row.get(16)?,
-            updated_at: row.get(17)?,
-        })
-    })?;
-    let mut result = Vec::new();
-    for row in rows {
-        result.push(row?);
-    }
-    Ok(result)
+    Ok(())
 }
 
-pub fn create_medication_management(mm: &MedicationManagement) -> AppResult<()> {
+/// HTTP 서버 자동 시작 설정 조회
+pub fn get_server_autostart() -> AppResult<bool> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    let now = Utc::now().to_rfc3339();
-    // FK 체크 일시 비활성화 (마이그레이션 잔류 이슈 회피)
-    conn.execute_batch("PRAGMA foreign_keys = OFF")?;
-    let result = conn.execute(
-        "INSERT INTO medication_management (id, prescription_id, patient_id, patient_name, prescription_name, prescription_date, days, delivery_days, start_date, end_date, happy_call_date, status, notes, postpone_count, postponed_to, contacted_at, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
-        params![
-            mm.id, mm.prescription_id, mm.patient_id, mm.patient_name, mm.prescription_name,
-            mm.prescription_date, mm.days, mm.delivery_days, mm.start_date, mm.end_date,
-            mm.happy_call_date, mm.status, mm.notes, mm.postpone_count, mm.postponed_to,
-            mm.contacted_at, now, now
-        ],
+
+    // 컬럼이 없으면 추가
+    let _ = conn.execute(
+        "ALTER TABLE clinic_settings ADD COLUMN http_server_autostart INTEGER DEFAULT 0",
+        [],
     );
-    conn.execute_batch("PRAGMA foreign_keys = ON")?;
-    result?;
-    Ok(())
+
+    let autostart: Option<i32> = conn
+        .query_row(
+            "SELECT http_server_autostart FROM clinic_settings LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .ok()
+        .flatten();
+
+    Ok(autostart.unwrap_or(0) == 1)
 }
 
-pub fn update_medication_management(mm: &MedicationManagement) -> AppResult<()> {
+/// HTTP 서버 자동 시작 설정 저장
+pub fn set_server_autostart(enabled: bool) -> AppResult<()> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    let now = Utc::now().to_rfc3339();
+
+    // 컬럼이 없으면 추가
+    let _ = conn.execute(
+        "ALTER TABLE clinic_settings ADD COLUMN http_server_autostart INTEGER DEFAULT 0",
+        [],
+    );
+
     conn.execute(
-        "UPDATE medication_management SET status = ?1, notes = ?2, postpone_count = ?3, postponed_to = ?4, happy_call_date = ?5, contacted_at = ?6, updated_at = ?7 WHERE id = ?8",
-        params![mm.status, mm.notes, mm.postpone_count, mm.postponed_to, mm.happy_call_date, mm.contacted_at, now, mm.id],
+        "UPDATE clinic_settings SET http_server_autostart = ?, updated_at = ?",
+        params![if enabled { 1 } else { 0 }, Utc::now().to_rfc3339()],
     )?;
+
+    log::info!("HTTP 서버 자동 시작 설정: {}", enabled);
     Ok(())
 }
 
-pub fn delete_medication_management(id: &str) -> AppResult<()> {
+/// HTTP 서버 mDNS 광고 설정 조회 (기본: 사용)
+pub fn get_server_mdns_advertise() -> AppResult<bool> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    conn.execute("DELETE FROM medication_management WHERE id = ?1", params![id])?;
-    Ok(())
+
+    // 컬럼이 없으면 추가
+    let _ = conn.execute(
+        "ALTER TABLE clinic_settings ADD COLUMN http_server_mdns INTEGER DEFAULT 1",
+        [],
+    );
+
+    let enabled: Option<i32> = conn
+        .query_row(
+            "SELECT http_server_mdns FROM clinic_settings LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .ok()
+        .flatten();
+
+    Ok(enabled.unwrap_or(1) == 1)
 }
 
-// ============ 복약 스케줄 (커맨드용) ============
+/// HTTP 서버 mDNS 광고 설정 저장
+pub fn set_server_mdns_advertise(enabled: bool) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
 
-pub fn list_medication_schedules_cmd(patient_id: Option<&str>) -> AppResult<Vec<MedicationSchedule>> {
+    // 컬럼이 없으면 추가
+    let _ = conn.execute(
+        "ALTER TABLE clinic_settings ADD COLUMN http_server_mdns INTEGER DEFAULT 1",
+        [],
+    );
+
+    conn.execute(
+        "UPDATE clinic_settings SET http_server_mdns = ?, updated_at = ?",
+        params![if enabled { 1 } else { 0 }, Utc::now().to_rfc3339()],
+    )?;
+
+    log::info!("HTTP 서버 mDNS 광고 설정: {}", enabled);
+    Ok(())
+}
+
+/// 원내 서버 HTTPS 설정 조회
+pub fn get_server_tls_settings() -> AppResult<ServerTlsSettings> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    let mut result = Vec::new();
+    let json: Option<String> = conn
+        .query_row(
+            "SELECT server_tls_settings FROM clinic_settings LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .ok()
+        .flatten();
 
-    if let Some(pid) = patient_id {
-        let mut stmt = conn.prepare(
-            "SELECT id, patient_id, prescription_id, start_date, end_date, times_per_day, medication_times, notes, created_at FROM medication_schedules WHERE patient_id = ?1 ORDER BY created_at DESC"
-        )?;
-        let rows = stmt.query_map(params![pid], |row| {
-            let times_json: String = row.get(6)?;
-            let medication_times: Vec<String> = serde_json::from_str(&times_json).unwrap_or_default();
-            Ok(MedicationSchedule {
-                id: row.get(0)?,
-                patient_id: row.get(1)?,
-                prescription_id: row.get(2)?,
-                start_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(3)?).unwrap().with_timezone(&Utc),
-                end_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?).unwrap().with_timezone(&Utc),
-                times_per_day: row.get(5)?,
-                medication_times,
-                notes: row.get(7)?,
-                created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?).unwrap().with_timezone(&Utc),
-            })
-        })?;
-        for row in rows {
-            result.push(row?);
-        }
-    } else {
-        let mut stmt = conn.prepare(
-            "SELECT id, patient_id, prescription_id, start_date, end_date, times_per_day, medication_times, notes, created_at FROM medication_schedules ORDER BY created_at DESC"
-        )?;
-        let rows = stmt.query_map([], |row| {
-            let times_json: String = row.get(6)?;
-            let medication_times: Vec<String> = serde_json::from_str(&times_json).unwrap_or_default();
-            Ok(MedicationSchedule {
-                id: row.get(0)?,
-                patient_id: row.get(1)?,
-                prescription_id: row.get(2)?,
-                start_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(3)?).unwrap().with_timezone(&Utc),
-                end_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?).unwrap().with_timezone(&Utc),
-                times_per_day: row.get(5)?,
-                medication_times,
-                notes: row.get(7)?,
-                created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?).unwrap().with_timezone(&Utc),
-            })
-        })?;
-        for row in rows {
-            result.push(row?);
-        }
-    }
-    Ok(result)
+    Ok(json
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default())
 }
 
-pub fn get_medication_schedule_cmd(id: &str) -> AppResult<Option<MedicationSchedule>> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
-    let result = conn.query_row(
-        "SELECT id, patient_id, prescription_id, start_date, end_date, times_per_day, medication_times, notes, created_at FROM medication_schedules WHERE id = ?1",
-        params![id],
-        |row| {
-            let times_json: String = row.get(6)?;
-            let medication_times: Vec<String> = serde_json::from_str(&times_json).unwrap_or_default();
-            Ok(MedicationSchedule {
-                id: row.get(0)?,
-                patient_id: row.get(1)?,
-                prescription_id: row.get(2)?,
-                start_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(3)?).unwrap().with_timezone(&Utc),
-                end_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?).unwrap().with_timezone(&Utc),
-                times_per_day: row.get(5)?,
-                medication_times,
-                notes: row.get(7)?,
-                created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?).unwrap().with_timezone(&Utc),
-            })
-        },
-    );
-    match result {
-        Ok(s) => Ok(Some(s)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(e.into()),
+/// 원내 서버 HTTPS 설정 저장 (서버 재시작 후 적용)
+pub fn set_server_tls_settings(settings: &ServerTlsSettings) -> AppResult<()> {
+    let non_empty = |path: &Option<String>| path.as_deref().map(str::trim).filter(|p| !p.is_empty()).map(str::to_string);
+    let settings = ServerTlsSettings {
+        enabled: settings.enabled,
+        cert_path: non_empty(&settings.cert_path),
+        key_path: non_empty(&settings.key_path),
+    };
+    if settings.cert_path.is_some() != settings.key_path.is_some() {
+        return Err(AppError::Validation("HTTPS 인증서와 개인키 경로를 모두 지정하세요".to_string()));
+    }
+    for path in [&settings.cert_path, &settings.key_path].into_iter().flatten() {
+        if !std::path::Path::new(path).is_file() {
+            return Err(AppError::Validation(format!("파일을 찾을 수 없습니다: {}", path)));
+        }
     }
-}
 
-pub fn create_medication_schedule_cmd(schedule: &MedicationSchedule) -> AppResult<()> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    let times_json = serde_json::to_string(&schedule.medication_times)?;
     conn.execute(
-        "INSERT INTO medication_schedules (id, patient_id, prescription_id, start_date, end_date, times_per_day, medication_times, notes, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-        params![
-            schedule.id, schedule.patient_id, schedule.prescription_id,
-            schedule.start_date.to_rfc3339(), schedule.end_date.to_rfc3339(),
-            schedule.times_per_day, times_json, schedule.notes,
-            schedule.created_at.to_rfc3339()
-        ],
+        "UPDATE clinic_settings SET server_tls_settings = ?, updated_at = ?",
+        params![serde_json::to_string(&settings)?, Utc::now().to_rfc3339()],
     )?;
+
+    log::info!("HTTP 서버 HTTPS 설정: enabled={}, 인증서 지정={}", settings.enabled, settings.cert_path.is_some());
     Ok(())
 }
 
-pub fn update_medication_schedule_cmd(schedule: &MedicationSchedule) -> AppResult<()> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
-    let times_json = serde_json::to_string(&schedule.medication_times)?;
-    conn.execute(
-        "UPDATE medication_schedules SET patient_id = ?1, prescription_id = ?2, start_date = ?3, end_date = ?4, times_per_day = ?5, medication_times = ?6, notes = ?7 WHERE id = ?8",
-        params![
-            schedule.patient_id, schedule.prescription_id,
-            schedule.start_date.to_rfc3339(), schedule.end_date.to_rfc3339(),
-            schedule.times_per_day, times_json, schedule.notes,
-            schedule.id
-        ],
-    )?;
-    Ok(())
+// ============ 이메일 발송 설정 ============
+
+/// 이메일 주소 형식 확인 (헤더 삽입 방지를 위해 공백/제어 문자/꺾쇠 불허)
+pub fn is_valid_email_address(address: &str) -> bool {
+    let valid_chars = !address.chars().any(|c| c.is_whitespace() || c.is_control() || matches!(c, '<' | '>' | ',' | ';'));
+    match address.split_once('@') {
+        Some((local, domain)) => valid_chars && !local.is_empty() && domain.contains('.') && !domain.contains('@'),
+        None => false,
+    }
 }
 
-pub fn delete_medication_schedule_cmd(id: &str) -> AppResult<()> {
+/// 이메일 발송 설정 조회 (미설정 시 None)
+pub fn get_smtp_settings() -> AppResult<Option<SmtpSettings>> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    conn.execute("DELETE FROM medication_logs WHERE schedule_id = ?1", params![id])?;
-    conn.execute("DELETE FROM medication_schedules WHERE id = ?1", params![id])?;
-    Ok(())
-}
 
-// ============ 복약 기록 (커맨드용) ============
+    let json: Option<String> = conn
+        .query_row("SELECT smtp_settings FROM clinic_settings LIMIT 1", [], |row| row.get(0))
+        .ok()
+        .flatten();
+    Ok(json.and_then(|j| serde_json::from_str(&j).ok()))
+}
+
+/// 이메일 발송 설정 저장 (None이면 이메일 발송 끔, 비밀번호를 비워 두면 기존 값 유지)
+pub fn save_smtp_settings(settings: Option<&SmtpSettings>) -> AppResult<()> {
+    let mut settings = settings.cloned();
+    if let Some(s) = settings.as_mut() {
+        s.host = s.host.trim().to_string();
+        s.from_address = s.from_address.trim().to_string();
+        s.summary_recipient = s.summary_recipient.as_deref().map(str::trim).filter(|r| !r.is_empty()).map(String::from);
+        if s.host.is_empty() || s.port == 0 {
+            return Err(AppError::Validation("SMTP 서버 주소와 포트를 입력해주세요".to_string()));
+        }
+        if !is_valid_email_address(&s.from_address) {
+            return Err(AppError::Validation(format!("보내는 사람 주소가 올바르지 않습니다: {}", s.from_address)));
+        }
+        if let Some(recipient) = s.summary_recipient.as_deref().filter(|r| !is_valid_email_address(r)) {
+            return Err(AppError::Validation(format!("일일 요약 받는 주소가 올바르지 않습니다: {}", recipient)));
+        }
+        if s.password.is_empty() {
+            s.password = get_smtp_settings()?.map(|existing| existing.password).unwrap_or_default();
+        }
+    }
 
-pub fn list_medication_logs_cmd(schedule_id: &str) -> AppResult<Vec<MedicationLog>> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    let mut stmt = conn.prepare(
-        "SELECT id, schedule_id, taken_at, status, notes FROM medication_logs WHERE schedule_id = ?1 ORDER BY taken_at DESC"
+    let json = settings.as_ref().map(serde_json::to_string).transpose()?;
+    let updated = conn.execute(
+        "UPDATE clinic_settings SET smtp_settings = ?, updated_at = ?",
+        params![json, Utc::now().to_rfc3339()],
     )?;
-    let rows = stmt.query_map(params![schedule_id], |row| {
-        let status_str: String = row.get(3)?;
-        let status = match status_str.as_str() {
-            "taken" => MedicationStatus::Taken,
-            "missed" => MedicationStatus::Missed,
-            "skipped" => MedicationStatus::Skipped,
-            _ => MedicationStatus::Taken,
-        };
-        Ok(MedicationLog {
-            id: row.get(0)?,
-            schedule_id: row.get(1)?,
-            taken_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(2)?).unwrap().with_timezone(&Utc),
-            status,
-            notes: row.get(4)?,
-        })
-    })?;
-    let mut result = Vec::new();
-    for row in rows {
-        result.push(row?);
+    if updated == 0 {
+        return Err(AppError::Custom("한의원 설정을 먼저 저장해주세요".to_string()));
     }
-    Ok(result)
+
+    log::info!("이메일 발송 설정 저장: {}", settings.as_ref().map_or("사용 안 함", |s| s.host.as_str()));
+    Ok(())
 }
 
-pub fn create_medication_log_cmd(log: &MedicationLog) -> AppResult<()> {
+/// 일일 요약 발송 차례인지 확인하고 오늘 발송한 것으로 기록 (전역 알림 설정 기준, 하루 한 번만 true)
+pub fn claim_daily_summary() -> AppResult<bool> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    let status_str = match log.status {
-        MedicationStatus::Taken => "taken",
-        MedicationStatus::Missed => "missed",
-        MedicationStatus::Skipped => "skipped",
+
+    let row: Option<(String, bool, String, Option<String>)> = conn
+        .query_row(
+            "SELECT id, daily_summary_enabled, daily_summary_time, daily_summary_last_sent_on
+             FROM notification_settings WHERE schedule_id IS NULL ORDER BY updated_at DESC LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .ok();
+    let Some((id, enabled, time, last_sent_on)) = row else {
+        return Ok(false);
     };
+
+    let now = chrono::Local::now();
+    let today = now.format("%Y-%m-%d").to_string();
+    let due = chrono::NaiveTime::parse_from_str(&time, "%H:%M").is_ok_and(|t| now.time() >= t);
+    if !enabled || !due || last_sent_on.as_deref() == Some(today.as_str()) {
+        return Ok(false);
+    }
+
     conn.execute(
-        "INSERT INTO medication_logs (id, schedule_id, taken_at, status, notes) VALUES (?1, ?2, ?3, ?4, ?5)",
-        params![log.id, log.schedule_id, log.taken_at.to_rfc3339(), status_str, log.notes],
+        "UPDATE notification_settings SET daily_summary_last_sent_on = ?1 WHERE id = ?2",
+        params![today, id],
     )?;
-    Ok(())
+    Ok(true)
 }
 
-pub fn update_medication_log_cmd(id: &str, status: &str, notes: Option<&str>) -> AppResult<()> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
-    conn.execute(
-        "UPDATE medication_logs SET status = ?1, notes = ?2 WHERE id = ?3",
-        params![status, notes, id],
-    )?;
-    Ok(())
+/// 일일 요약 집계
+#[derive(Debug, Clone)]
+pub struct DailySummaryCounts {
+    /// 오늘 제출된 설문 응답
+    pub responses_today: i64,
+    /// 환자와 연결되지 않은 응답
+    pub unlinked_responses: i64,
+    /// 아직 응답하지 않은 설문 세션
+    pub pending_sessions: i64,
+    /// 읽지 않은 알림
+    pub unread_notifications: i64,
 }
 
-pub fn delete_medication_log_cmd(id: &str) -> AppResult<()> {
+/// 일일 요약 집계 (오늘 = 로컬 자정 이후)
+pub fn get_daily_summary_counts() -> AppResult<DailySummaryCounts> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    conn.execute("DELETE FROM medication_logs WHERE id = ?1", params![id])?;
-    Ok(())
+
+    let since = local_day_start_utc(chrono::Local::now().date_naive());
+    let count = |sql: &str, params: &[&dyn rusqlite::ToSql]| -> AppResult<i64> {
+        Ok(conn.query_row(sql, params, |row| row.get(0))?)
+    };
+
+    Ok(DailySummaryCounts {
+        responses_today: count("SELECT COUNT(*) FROM survey_responses WHERE submitted_at >= ?1", &[&since])?,
+        unlinked_responses: count("SELECT COUNT(*) FROM survey_responses WHERE patient_id IS NULL", &[])?,
+        pending_sessions: count(
+            "SELECT COUNT(*) FROM survey_sessions WHERE status = 'pending' AND expires_at > ?1",
+            &[&Utc::now().to_rfc3339()],
+        )?,
+        unread_notifications: count(
+            "SELECT COUNT(*) FROM notifications WHERE is_read = 0 AND is_dismissed = 0",
+            &[],
+        )?,
+    })
 }
 
-// ============ 사용량 카운트 (플랜 제한용) ============
+// ============ 이메일 대기열 ============
 
-pub fn get_usage_counts() -> AppResult<(i32, i32, i32)> {
+/// 이메일 최대 재시도 횟수 (초과 항목은 더 이상 꺼내지 않음)
+pub const EMAIL_MAX_RETRIES: i32 = 5;
+
+/// 이메일 대기 항목
+#[derive(Debug, Clone)]
+pub struct QueuedEmailDb {
+    pub id: String,
+    pub recipient: String,
+    pub subject: String,
+    pub body: String,
+    pub retry_count: i32,
+}
+
+/// 발송 실패 이메일을 대기열에 추가 (`next_retry_at`부터 재시도)
+pub fn enqueue_email(recipient: &str, subject: &str, body: &str, next_retry_at: chrono::DateTime<Utc>) -> AppResult<()> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-
-    let patient_count: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM patients",
-        [],
-        |row| row.get(0),
+    conn.execute(
+        "INSERT INTO email_queue (id, recipient, subject, body, created_at, retry_count, next_retry_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, 1, ?6)",
+        params![
+            uuid::Uuid::new_v4().to_string(),
+            recipient,
+            subject,
+            body,
+            Utc::now().to_rfc3339(),
+            next_retry_at.to_rfc3339()
+        ],
     )?;
+    Ok(())
+}
 
-    let now = Utc::now();
-    let first_day_of_month = format!("{}-{:02}-01", now.format("%Y"), now.format("%m"));
+/// 재시도 시각이 된 이메일을 오래된 순으로 꺼내 발송 중으로 표시
+pub fn dequeue_emails(limit: u32) -> AppResult<Vec<QueuedEmailDb>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let tx = conn.unchecked_transaction()?;
 
-    let prescription_count: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM prescriptions WHERE created_at >= ?1",
-        params![first_day_of_month],
-        |row| row.get(0),
-    )?;
+    let items = {
+        let mut stmt = tx.prepare(
+            "SELECT id, recipient, subject, body, retry_count FROM email_queue
+             WHERE in_flight = 0 AND retry_count < ?1 AND next_retry_at <= ?2
+             ORDER BY created_at ASC LIMIT ?3",
+        )?;
+        let rows = stmt.query_map(params![EMAIL_MAX_RETRIES, Utc::now().to_rfc3339(), limit], |row| {
+            Ok(QueuedEmailDb {
+                id: row.get(0)?,
+                recipient: row.get(1)?,
+                subject: row.get(2)?,
+                body: row.get(3)?,
+                retry_count: row.get(4)?,
+            })
+        })?;
+        rows.collect::<Result<Vec<_>, _>>()?
+    };
 
-    let chart_count: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM initial_charts WHERE created_at >= ?1",
-        params![first_day_of_month],
-        |row| row.get(0),
-    )?;
+    for item in &items {
+        tx.execute("UPDATE email_queue SET in_flight = 1 WHERE id = ?1", [&item.id])?;
+    }
+    tx.commit()?;
 
-    Ok((patient_count, prescription_count, chart_count))
+    Ok(items)
 }
 
-// ============ 휴지통 관리 ============
-
-/// 환자 소프트 삭제 (연관 데이터 cascade)
-pub fn soft_delete_patient(id: &str) -> AppResult<()> {
+/// 발송 완료된 이메일 제거
+pub fn remove_email(id: &str) -> AppResult<()> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    let now = Utc::now().to_rfc3339();
-
-    conn.execute(
-        "UPDATE patients SET deleted_at = ?1, updated_at = ?1 WHERE id = ?2",
-        params![now, id],
-    )?;
-    // cascade: prescriptions
-    conn.execute(
-        "UPDATE prescriptions SET deleted_at = ?1, updated_at = ?1 WHERE patient_id = ?2 AND deleted_at IS NULL",
-        params![now, id],
-    )?;
-    // cascade: initial_charts
-    conn.execute(
-        "UPDATE initial_charts SET deleted_at = ?1, updated_at = ?1 WHERE patient_id = ?2 AND deleted_at IS NULL",
-        params![now, id],
-    )?;
-    // cascade: progress_notes
-    conn.execute(
-        "UPDATE progress_notes SET deleted_at = ?1, updated_at = ?1 WHERE patient_id = ?2 AND deleted_at IS NULL",
-        params![now, id],
-    )?;
-
+    conn.execute("DELETE FROM email_queue WHERE id = ?1", [id])?;
     Ok(())
 }
 
-/// 초진차트 소프트 삭제
-pub fn soft_delete_initial_chart(id: &str) -> AppResult<()> {
+/// 발송 실패 이메일을 대기열로 되돌리고 재시도 횟수 증가
+pub fn mark_email_failed(id: &str, next_retry_at: chrono::DateTime<Utc>, error: &str) -> AppResult<()> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
     conn.execute(
-        "UPDATE initial_charts SET deleted_at = ?1, updated_at = ?1 WHERE id = ?2",
-        params![Utc::now().to_rfc3339(), id],
+        "UPDATE email_queue SET in_flight = 0, retry_count = retry_count + 1, next_retry_at = ?1, last_error = ?2 WHERE id = ?3",
+        params![next_retry_at.to_rfc3339(), error, id],
     )?;
     Ok(())
 }
 
-/// 경과기록 소프트 삭제
-pub fn soft_delete_progress_note(id: &str) -> AppResult<()> {
+/// 비정상 종료로 발송 중 상태에 남은 이메일을 대기 상태로 복구
+pub fn reset_inflight_emails() -> AppResult<usize> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    conn.execute(
-        "UPDATE progress_notes SET deleted_at = ?1, updated_at = ?1 WHERE id = ?2",
-        params![Utc::now().to_rfc3339(), id],
-    )?;
-    Ok(())
+    let count = conn.execute("UPDATE email_queue SET in_flight = 0 WHERE in_flight = 1", [])?;
+    Ok(count)
 }
 
-/// 휴지통에서 복원
-pub fn restore_from_trash(table: &str, id: &str) -> AppResult<()> {
+// ============ 설문 응답 목록 조회 (직원용) ============
+
+/// 설문 응답 목록 조회
+pub fn list_survey_responses(limit: Option<i32>) -> AppResult<Vec<SurveyResponseWithTemplate>> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    let now = Utc::now().to_rfc3339();
+    let limit_val = limit.unwrap_or(100);
 
-    match table {
-        "patients" => {
-            conn.execute(
-                "UPDATE patients SET deleted_at = NULL, updated_at = ?1 WHERE id = ?2",
-                params![now, id],
-            )?;
-            // cascade restore: related items
-            conn.execute(
-                "UPDATE prescriptions SET deleted_at = NULL, updated_at = ?1 WHERE patient_id = ?2",
-                params![now, id],
-            )?;
-            conn.execute(
-                "UPDATE initial_charts SET deleted_at = NULL, updated_at = ?1 WHERE patient_id = ?2",
-                params![now, id],
-            )?;
-            conn.execute(
-                "UPDATE progress_notes SET deleted_at = NULL, updated_at = ?1 WHERE patient_id = ?2",
-                params![now, id],
-            )?;
-        }
-        "prescriptions" => {
-            conn.execute(
-                "UPDATE prescriptions SET deleted_at = NULL, updated_at = ?1 WHERE id = ?2",
-                params![now, id],
-            )?;
-        }
-        "initial_charts" => {
-            conn.execute(
-                "UPDATE initial_charts SET deleted_at = NULL, updated_at = ?1 WHERE id = ?2",
-                params![now, id],
-            )?;
-        }
-        "progress_notes" => {
-            conn.execute(
-                "UPDATE progress_notes SET deleted_at = NULL, updated_at = ?1 WHERE id = ?2",
-                params![now, id],
-            )?;
-        }
-        _ => return Err(AppError::Custom(format!("Unknown table: {}", table))),
+    let mut stmt = conn.prepare(
+        r#"SELECT r.id, r.session_id, r.patient_id, r.template_id, r.respondent_name,
+                  r.answers, r.submitted_at, t.name as template_name, p.name as patient_name,
+                  p.chart_number, r.respondent_phone, r.respondent_birth_date, r.anonymous
+           FROM survey_responses r
+           LEFT JOIN survey_templates t ON r.template_id = t.id
+           LEFT JOIN patients p ON r.patient_id = p.id
+           ORDER BY r.submitted_at DESC
+           LIMIT ?"#,
+    )?;
+
+    let rows = stmt.query_map([limit_val], map_survey_response_row)?;
+
+    let mut responses = Vec::new();
+    for row in rows {
+        responses.push(row?);
     }
-    Ok(())
+    Ok(responses)
 }
 
-/// 영구 삭제
-pub fn permanent_delete(table: &str, id: &str) -> AppResult<()> {
+/// 설문 응답 목록 페이지 조회 (최신순)
+pub fn list_survey_responses_page(limit: i64, offset: i64) -> AppResult<PaginatedResponse<SurveyResponseWithTemplate>> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
 
-    match table {
-        "patients" => {
-            conn.execute("DELETE FROM prescriptions WHERE patient_id = ?1", [id])?;
-            conn.execute("DELETE FROM initial_charts WHERE patient_id = ?1", [id])?;
-            conn.execute("DELETE FROM progress_notes WHERE patient_id = ?1", [id])?;
-            conn.execute("DELETE FROM patients WHERE id = ?1", [id])?;
-        }
-        "prescriptions" => {
-            conn.execute("DELETE FROM prescriptions WHERE id = ?1", [id])?;
-        }
-        "initial_charts" => {
-            conn.execute("DELETE FROM initial_charts WHERE id = ?1", [id])?;
-        }
-        "progress_notes" => {
-            conn.execute("DELETE FROM progress_notes WHERE id = ?1", [id])?;
-        }
-        _ => return Err(AppError::Custom(format!("Unknown table: {}", table))),
-    }
-    Ok(())
+    let total: i64 = conn.query_row("SELECT COUNT(*) FROM survey_responses", [], |row| row.get(0))?;
+
+    let mut stmt = conn.prepare(
+        r#"SELECT r.id, r.session_id, r.patient_id, r.template_id, r.respondent_name,
+                  r.answers, r.submitted_at, t.name as template_name, p.name as patient_name,
+                  p.chart_number, r.respondent_phone, r.respondent_birth_date, r.anonymous
+           FROM survey_responses r
+           LEFT JOIN survey_templates t ON r.template_id = t.id
+           LEFT JOIN patients p ON r.patient_id = p.id
+           ORDER BY r.submitted_at DESC
+           LIMIT ?1 OFFSET ?2"#,
+    )?;
+    let responses = stmt
+        .query_map(params![limit, offset], map_survey_response_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(PaginatedResponse::new(responses, total, limit, offset))
 }
 
-/// 휴지통 비우기
-pub fn empty_trash() -> AppResult<TrashEmptyResult> {
+/// 설문 응답 단건 조회 (템플릿/환자 이름 포함)
+pub fn get_survey_response_detail(id: &str) -> AppResult<Option<SurveyResponseWithTemplate>> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
 
-    let p: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM patients WHERE deleted_at IS NOT NULL", [], |r| r.get(0),
-    )?;
-    let rx: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM prescriptions WHERE deleted_at IS NOT NULL", [], |r| r.get(0),
-    )?;
-    let ic: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM initial_charts WHERE deleted_at IS NOT NULL", [], |r| r.get(0),
-    )?;
-    let pn: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM progress_notes WHERE deleted_at IS NOT NULL", [], |r| r.get(0),
-    )?;
+    let result = conn.query_row(
+        r#"SELECT r.id, r.session_id, r.patient_id, r.template_id, r.respondent_name,
+                  r.answers, r.submitted_at, t.name as template_name, p.name as patient_name,
+                  p.chart_number, r.respondent_phone, r.respondent_birth_date, r.anonymous
+           FROM survey_responses r
+           LEFT JOIN survey_templates t ON r.template_id = t.id
+           LEFT JOIN patients p ON r.patient_id = p.id
+           WHERE r.id = ?1"#,
+        [id],
+        map_survey_response_row,
+    );
 
-    conn.execute("DELETE FROM patients WHERE deleted_at IS NOT NULL", [])?;
-    conn.execute("DELETE FROM prescriptions WHERE deleted_at IS NOT NULL", [])?;
-    conn.execute("DELETE FROM initial_charts WHERE deleted_at IS NOT NULL", [])?;
-    conn.execute("DELETE FROM progress_notes WHERE deleted_at IS NOT NULL", [])?;
+    match result {
+        Ok(response) => Ok(Some(response)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
 
-    Ok(TrashEmptyResult {
-        deleted_patients: p,
-        deleted_prescriptions: rx,
-        deleted_initial_charts: ic,
-        deleted_progress_notes: pn,
-        total: p + rx + ic + pn,
+fn map_survey_response_row(row: &rusqlite::Row) -> rusqlite::Result<SurveyResponseWithTemplate> {
+    let answers_json: String = row.get(5)?;
+    let answers: Vec<SurveyAnswer> = serde_json::from_str(&answers_json).unwrap_or_default();
+    Ok(SurveyResponseWithTemplate {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        patient_id: row.get(2)?,
+        template_id: row.get(3)?,
+        respondent_name: row.get(4)?,
+        answers,
+        submitted_at: row.get(6)?,
+        template_name: row.get(7)?,
+        patient_name: row.get(8)?,
+        chart_number: row.get(9)?,
+        respondent_phone: row.get(10)?,
+        respondent_birth_date: row.get(11)?,
+        anonymous: row.get::<_, Option<bool>>(12)?.unwrap_or(false),
     })
 }
 
-/// 휴지통 항목 조회
-pub fn get_trash_items() -> AppResult<Vec<TrashItem>> {
+/// 설문 응답 (템플릿 이름 포함)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SurveyResponseWithTemplate {
+    pub id: String,
+    pub session_id: Option<String>,
+    pub patient_id: Option<String>,
+    pub template_id: String,
+    pub respondent_name: Option<String>,
+    pub answers: Vec<SurveyAnswer>,
+    pub submitted_at: String,
+    pub template_name: Option<String>,
+    pub patient_name: Option<String>,
+    pub chart_number: Option<String>,
+    #[serde(default)]
+    pub respondent_phone: Option<String>,
+    #[serde(default)]
+    pub respondent_birth_date: Option<String>,
+    #[serde(default)]
+    pub anonymous: bool,
+}
+
+/// 모든 설문 템플릿 목록 조회 (비활성 포함, 환자용 목록은 호출하는 쪽에서 거름)
+pub fn list_survey_templates() -> AppResult<Vec<SurveyTemplateDb>> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    let mut items = Vec::new();
-
-    // patients
     let mut stmt = conn.prepare(
-        "SELECT id, name, deleted_at FROM patients WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC",
+        "SELECT id, name, description, questions, display_mode, is_active, require_contact, anonymous, redirect_url, category, randomize_questions, randomize_options, pin_required_questions FROM survey_templates ORDER BY name",
     )?;
-    let rows = stmt.query_map([], |row| {
-        Ok(TrashItem {
-            id: row.get(0)?,
-            item_type: "patient".to_string(),
-            name: row.get(1)?,
-            deleted_at: row.get(2)?,
-            extra_info: None,
-        })
-    })?;
-    for r in rows { items.push(r?); }
 
-    // prescriptions
-    let mut stmt = conn.prepare(
-        "SELECT id, COALESCE(prescription_name, formula, '처방'), deleted_at, patient_name FROM prescriptions WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC",
-    )?;
     let rows = stmt.query_map([], |row| {
-        Ok(TrashItem {
+        let questions_json: String = row.get(3)?;
+        let questions = parse_template_questions(&questions_json);
+        let is_active: i32 = row.get(5)?;
+        Ok(SurveyTemplateDb {
             id: row.get(0)?,
-            item_type: "prescription".to_string(),
             name: row.get(1)?,
-            deleted_at: row.get(2)?,
-            extra_info: row.get(3)?,
+            description: row.get(2)?,
+            questions,
+            display_mode: row.get(4)?,
+            is_active: is_active != 0,
+            require_contact: row.get::<_, Option<bool>>(6)?.unwrap_or(false),
+            anonymous: row.get::<_, Option<bool>>(7)?.unwrap_or(false),
+            redirect_url: row.get(8)?,
+            category: row.get(9)?,
+            randomize_questions: row.get::<_, Option<bool>>(10)?.unwrap_or(false),
+            randomize_options: row.get::<_, Option<bool>>(11)?.unwrap_or(false),
+            pin_required_questions: row.get::<_, Option<bool>>(12)?.unwrap_or(false),
         })
     })?;
-    for r in rows { items.push(r?); }
 
-    // initial_charts
-    let mut stmt = conn.prepare(
-        "SELECT ic.id, COALESCE(p.name, '환자'), ic.deleted_at, ic.chart_date FROM initial_charts ic LEFT JOIN patients p ON ic.patient_id = p.id WHERE ic.deleted_at IS NOT NULL ORDER BY ic.deleted_at DESC",
-    )?;
-    let rows = stmt.query_map([], |row| {
-        Ok(TrashItem {
-            id: row.get(0)?,
-            item_type: "initial_chart".to_string(),
-            name: row.get::<_, String>(1)? + " 초진차트",
-            deleted_at: row.get(2)?,
-            extra_info: row.get(3)?,
-        })
-    })?;
-    for r in rows { items.push(r?); }
+    let mut templates = Vec::new();
+    for row in rows {
+        templates.push(row?);
+    }
+    Ok(templates)
+}
 
-    // progress_notes
+/// 활성 설문 템플릿의 분류 목록 (가나다순, 분류 없는 템플릿 제외)
+pub fn list_survey_template_categories() -> AppResult<Vec<String>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
     let mut stmt = conn.prepare(
-        "SELECT pn.id, COALESCE(p.name, '환자'), pn.deleted_at, pn.note_date FROM progress_notes pn LEFT JOIN patients p ON pn.patient_id = p.id WHERE pn.deleted_at IS NOT NULL ORDER BY pn.deleted_at DESC",
+        "SELECT DISTINCT category FROM survey_templates
+         WHERE is_active = 1 AND category IS NOT NULL AND category != '' ORDER BY category",
     )?;
-    let rows = stmt.query_map([], |row| {
-        Ok(TrashItem {
-            id: row.get(0)?,
-            item_type: "progress_note".to_string(),
-            name: row.get::<_, String>(1)? + " 경과기록",
-            deleted_at: row.get(2)?,
-            extra_info: row.get(3)?,
-        })
-    })?;
-    for r in rows { items.push(r?); }
-
-    // sort by deleted_at DESC
-    items.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
-
-    Ok(items)
+    let categories = stmt.query_map([], |row| row.get(0))?.collect::<Result<Vec<String>, _>>()?;
+    Ok(categories)
 }
 
-/// 휴지통 항목 수
-pub fn get_trash_count() -> AppResult<TrashCount> {
+/// 설문 템플릿 삭제
+///
+/// 기본 제공 템플릿은 `force`로 확인해야 삭제됩니다 (숨기기만 하려면 비활성화).
+pub fn delete_survey_template(id: &str, force: bool) -> AppResult<()> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
+    let is_builtin = conn
+        .query_row("SELECT is_builtin FROM survey_templates WHERE id = ?1", [id], |row| row.get::<_, bool>(0))
+        .unwrap_or(false);
+    if is_builtin && !force {
+        return Err(AppError::Custom(
+            "기본 제공 템플릿입니다. 숨기려면 비활성화하고, 삭제하려면 확인 후 다시 요청해주세요".to_string(),
+        ));
+    }
+    conn.execute("DELETE FROM survey_templates WHERE id = ?1", [id])?;
+    log::info!("설문 템플릿 삭제됨: {}", id);
+    Ok(())
+}
 
-    let p: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM patients WHERE deleted_at IS NOT NULL", [], |r| r.get(0),
-    )?;
-    let rx: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM prescriptions WHERE deleted_at IS NOT NULL", [], |r| r.get(0),
-    )?;
-    let ic: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM initial_charts WHERE deleted_at IS NOT NULL", [], |r| r.get(0),
-    )?;
-    let pn: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM progress_notes WHERE deleted_at IS NOT NULL", [], |r| r.get(0),
-    )?;
+/// 설문 응답 삭제
+pub fn delete_survey_response(id: &str) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let existing: Option<(String, Option<String>, String)> = conn
+        .query_row(
+            "SELECT template_id, patient_id, submitted_at FROM survey_responses WHERE id = ?1",
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .ok();
 
-    Ok(TrashCount {
-        patients: p,
-        prescriptions: rx,
-        initial_charts: ic,
-        progress_notes: pn,
-        total: p + rx + ic + pn,
-    })
+    conn.execute("DELETE FROM survey_responses WHERE id = ?1", [id])?;
+    conn.execute("DELETE FROM survey_attachments WHERE response_id = ?1", [id])?;
+    if let Some((template_id, patient_id, submitted_at)) = existing {
+        apply_survey_stats_delta(&template_id, patient_id.is_some(), &submitted_at, -1);
+    }
+    log::info!("설문 응답 삭제됨: {}", id);
+    Ok(())
 }
 
-// ============ 사용량 통계 ============
-
-/// 사용량 통계 (deleted_at IS NULL 기준)
-pub fn get_usage_stats() -> AppResult<UsageStats> {
+/// 설문 응답에 환자 연결
+pub fn link_survey_response_to_patient(response_id: &str, patient_id: &str) -> AppResult<()> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
+    ensure_response_linkable(&conn, response_id)?;
 
-    let patients: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM patients WHERE deleted_at IS NULL", [], |r| r.get(0),
-    )?;
-    let prescriptions: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM prescriptions WHERE deleted_at IS NULL", [], |r| r.get(0),
-    )?;
-    let initial_charts: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM initial_charts WHERE deleted_at IS NULL", [], |r| r.get(0),
+    // 환자 이름 조회
+    let patient_name: Option<String> = conn.query_row(
+        "SELECT name FROM patients WHERE id = ?1",
+        [patient_id],
+        |row| row.get(0),
+    ).ok();
+
+    let was_unlinked = conn
+        .query_row(
+            "SELECT patient_id IS NULL FROM survey_responses WHERE id = ?1",
+            [response_id],
+            |row| row.get::<_, bool>(0),
+        )
+        .unwrap_or(false);
+
+    conn.execute(
+        "UPDATE survey_responses SET patient_id = ?1 WHERE id = ?2",
+        params![patient_id, response_id],
     )?;
-    let progress_notes: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM progress_notes WHERE deleted_at IS NULL", [], |r| r.get(0),
+
+    // 키오스크에서 받은 내원 경로는 환자에 값이 없을 때만 반영
+    conn.execute(
+        r#"UPDATE patients SET referral_source = (
+               SELECT ss.referral_source FROM survey_responses sr
+               JOIN survey_sessions ss ON ss.id = sr.session_id
+               WHERE sr.id = ?2)
+           WHERE id = ?1 AND (referral_source IS NULL OR TRIM(referral_source) = '')
+             AND EXISTS (
+               SELECT 1 FROM survey_responses sr
+               JOIN survey_sessions ss ON ss.id = sr.session_id
+               WHERE sr.id = ?2 AND TRIM(COALESCE(ss.referral_source, '')) != '')"#,
+        params![patient_id, response_id],
     )?;
 
-    Ok(UsageStats { patients, prescriptions, initial_charts, progress_notes })
-}
+    if was_unlinked {
+        if let Ok(mut cache) = survey_stats_cache().lock() {
+            if let Some(stats) = cache.as_mut() {
+                stats.unlinked_responses -= 1;
+            }
+        }
+    }
 
-// ============ 처방정의 초기화 ============
+    log::info!("설문 응답 환자 연결: {} -> {} ({})", response_id, patient_id, patient_name.unwrap_or_default());
+    Ok(())
+}
 
-/// 처방 정의 초기화 (전체 삭제 후 시드 재삽입)
-pub fn reset_prescription_definitions() -> AppResult<i32> {
+/// 설문 응답의 응답자 이름(및 생년월일 문항)으로 연결할 환자 후보 조회 (일치도 순)
+pub fn suggest_patient_for_response(response_id: &str) -> AppResult<Vec<Patient>> {
     ensure_db_initialized()?;
-    let conn = get_conn()?;
 
-    conn.execute("DELETE FROM prescription_notes", [])?;
-    conn.execute("DELETE FROM prescription_case_studies", [])?;
-    conn.execute("DELETE FROM prescription_definitions", [])?;
-    conn.execute("DELETE FROM prescription_categories", [])?;
+    let (respondent_name, template_id, answers_json): (Option<String>, String, String) = {
+        let conn = get_conn()?;
+        ensure_response_linkable(&conn, response_id)?;
+        match conn.query_row(
+            "SELECT respondent_name, template_id, answers FROM survey_responses WHERE id = ?1",
+            [response_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ) {
+            Ok(r) => r,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        }
+    };
 
-    seed_prescription_definitions(&conn)?;
+    let name = match respondent_name.as_deref().map(str::trim) {
+        Some(n) if !n.is_empty() => n.to_string(),
+        _ => return Ok(Vec::new()),
+    };
 
-    let count: i32 = conn.query_row(
-        "SELECT COUNT(*) FROM prescription_definitions", [], |r| r.get(0),
+    // 생년월일 문항 답변 (숫자만 비교)
+    let digits = |s: &str| s.chars().filter(|c| c.is_ascii_digit()).collect::<String>();
+    let answers: Vec<SurveyAnswer> = serde_json::from_str(&answers_json).unwrap_or_default();
+    let birth_date_answer = get_survey_template(&template_id)?
+        .and_then(|t| {
+            t.questions
+                .into_iter()
+                .find(|q| q.question_text.contains("생년월일") || q.question_text.contains("생일"))
+        })
+        .and_then(|q| answers.into_iter().find(|a| a.question_id == q.id))
+        .and_then(|a| a.answer.as_str().map(digits))
+        .filter(|d| !d.is_empty());
+
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, name, chart_number, birth_date, gender, phone, address, notes, created_at, updated_at, referral_source,
+                (SELECT group_concat(tag, ',') FROM tags WHERE entity_type = 'patient' AND entity_id = patients.id)
+         FROM patients WHERE name LIKE ?1 AND deleted_at IS NULL",
     )?;
-    Ok(count)
+    let rows = stmt.query_map([format!("%{}%", name)], map_patient_row)?;
+
+    // 점수: 이름 완전 일치 2 / 부분 일치 1, 생년월일 일치 +3
+    let mut scored = Vec::new();
+    for row in rows {
+        let patient = row?;
+        let mut score = if patient.name == name { 2 } else { 1 };
+        if let (Some(answer), Some(birth)) = (&birth_date_answer, &patient.birth_date) {
+            if *answer == digits(birth) {
+                score += 3;
+            }
+        }
+        scored.push((score, patient));
+    }
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.name.cmp(&b.1.name)));
+
+    Ok(scored.into_iter().map(|(_, p)| p).collect())
 }
 
-// ============ 전체 사용자 데이터 초기화 ============
+/// 기본 설문 템플릿 복원
+pub fn restore_default_templates() -> AppResult<()> {
+    ensure_db_initialized()?;
+    ensure_default_templates()?;
+    let conn = get_conn()?;
+    seed_builtin_templates(&conn, &Utc::now().to_rfc3339())?;
+    log::info!("기본 설문 템플릿이 복원되었습니다.");
+    Ok(())
+}
 
-/// 전체 사용자 데이터 삭제 (처방정의/카테고리/약재는 유지)
-pub fn reset_all_user_data() -> AppResult<()> {
+// ============ 설문 응답 관리 (Tauri 명령어용) ============
+
+/// 설문 응답 제출 (프론트엔드에서 직접 호출)
+/// 세션은 sql.js에만 존재하므로 FK 체크를 일시 비활성화
+pub fn submit_survey_response(
+    session_id: Option<&str>,
+    template_id: &str,
+    patient_id: Option<&str>,
+    respondent_name: Option<&str>,
+    answers: &[SurveyAnswer],
+) -> AppResult<()> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
+    let id = uuid::Uuid::new_v4().to_string();
+    let answers_json = serde_json::to_string(answers)?;
+    let now = Utc::now().to_rfc3339();
 
-    conn.execute("DELETE FROM progress_notes", [])?;
-    conn.execute("DELETE FROM initial_charts", [])?;
-    conn.execute("DELETE FROM prescriptions", [])?;
-    conn.execute("DELETE FROM chart_records", [])?;
-    conn.execute("DELETE FROM medication_logs", [])?;
-    conn.execute("DELETE FROM medication_schedules", [])?;
-    conn.execute("DELETE FROM medication_management", [])?;
-    conn.execute("DELETE FROM survey_responses", [])?;
-    conn.execute("DELETE FROM survey_sessions", [])?;
-    conn.execute("DELETE FROM patients", [])?;
+    // 세션은 sql.js에만 있고 clinic.db에는 없으므로 FK 체크 일시 비활성화
+    conn.execute_batch("PRAGMA foreign_keys = OFF")?;
+
+    let result = conn.execute(
+        r#"INSERT INTO survey_responses (id, session_id, template_id, patient_id, respondent_name, answers, submitted_at)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"#,
+        params![id, session_id, template_id, patient_id, respondent_name, answers_json, now],
+    );
 
+    conn.execute_batch("PRAGMA foreign_keys = ON")?;
+    result?;
+    apply_survey_stats_delta(template_id, patient_id.is_some(), &now, 1);
+
+    log::info!("설문 응답 제출됨: {} (template: {})", id, template_id);
     Ok(())
 }
 
-// ============ 선택적 데이터 내보내기 ============
-
-/// 선택된 테이블만 JSON 내보내기
-pub fn export_selected_data(tables: Vec<String>) -> AppResult<String> {
+/// 동기화된 설문 응답 저장 (Supabase에서 수신, session_id로 중복 체크)
+/// 이미 존재하면 false 반환, 새로 저장하면 true 반환
+/// 세션은 sql.js에만 존재하므로 FK 체크를 일시 비활성화
+pub fn save_survey_response_from_sync(
+    session_id: &str,
+    template_id: &str,
+    patient_id: Option<&str>,
+    respondent_name: Option<&str>,
+    answers: &[SurveyAnswer],
+    submitted_at: &str,
+) -> AppResult<bool> {
     ensure_db_initialized()?;
     let conn = get_conn()?;
-    let mut export = serde_json::Map::new();
 
-    for table in &tables {
-        match table.as_str() {
-            "patients" => {
-                let patients = list_patients(None)?;
-                export.insert("patients".to_string(), serde_json::to_value(&patients)?);
-            }
-            "prescriptions" => {
-                let items = list_all_prescriptions()?;
-                export.insert("prescriptions".to_string(), serde_json::to_value(&items)?);
-            }
-            "initial_charts" => {
-                let mut stmt = conn.prepare(
-                    "SELECT id, patient_id, doctor_name, chart_date, chief_complaint, present_illness, past_medical_history, notes, prescription_issued, prescription_issued_at, deleted_at, created_at, updated_at FROM initial_charts WHERE deleted_at IS NULL",
-                )?;
-                let rows = stmt.query_map([], |row| {
-                    Ok(serde_json::json!({
-                        "id": row.get::<_, String>(0)?,
-                        "patient_id": row.get::<_, String>(1)?,
-                        "doctor_name": row.get::<_, Option<String>>(2)?,
-                        "chart_date": row.get::<_, String>(3)?,
-                        "chief_complaint": row.get::<_, Option<String>>(4)?,
-                        "present_illness": row.get::<_, Option<String>>(5)?,
-                        "past_medical_history": row.get::<_, Option<String>>(6)?,
-                        "notes": row.get::<_, Option<String>>(7)?,
-                        "prescription_issued": row.get::<_, i32>(8)? != 0,
-                        "prescription_issued_at": row.get::<_, Option<String>>(9)?,
-                        "created_at": row.get::<_, String>(11)?,
-                        "updated_at": row.get::<_, String>(12)?,
-                    }))
-                })?;
-                let items: Vec<serde_json::Value> = rows.filter_map(|r| r.ok()).collect();
-                export.insert("initial_charts".to_string(), serde_json::Value::Array(items));
-            }
-            "progress_notes" => {
-                let mut stmt = conn.prepare(
-                    "SELECT id, patient_id, doctor_name, note_date, subjective, objective, assessment, plan, follow_up_plan, notes, prescription_issued, prescription_issued_at, deleted_at, created_at, updated_at FROM progress_notes WHERE deleted_at IS NULL",
-                )?;
-                let rows = stmt.query_map([], |row| {
-                    Ok(serde_json::json!({
-                        "id": row.get::<_, String>(0)?,
-                        "patient_id": row.get::<_, String>(1)?,
-                        "doctor_name": row.get::<_, Option<String>>(2)?,
-                        "note_date": row.get::<_, String>(3)?,
-                        "subjective": row.get::<_, Option<String>>(4)?,
-                        "objective": row.get::<_, Option<String>>(5)?,
-                        "assessment": row.get::<_, Option<String>>(6)?,
-                        "plan": row.get::<_, Option<String>>(7)?,
-                        "follow_up_plan": row.get::<_, Option<String>>(8)?,
-                        "notes": row.get::<_, Option<String>>(9)?,
-                        "prescription_issued": row.get::<_, i32>(10)? != 0,
-                        "prescription_issued_at": row.get::<_, Option<String>>(11)?,
-                        "created_at": row.get::<_, String>(13)?,
-                        "updated_at": row.get::<_, String>(14)?,
-                    }))
-                })?;
-                let items: Vec<serde_json::Value> = rows.filter_map(|r| r.ok()).collect();
-                export.insert("progress_notes".to_string(), serde_json::Value::Array(items));
-            }
-            "clinic_settings" => {
-                let settings = get_clinic_settings()?;
-                export.insert("clinic_settings".to_string(), serde_json::to_value(&settings)?);
-            }
-            "survey_templates" => {
-                let items = list_survey_templates()?;
-                export.insert("survey_templates".to_string(), serde_json::to_value(&items)?);
-            }
-            "survey_responses" => {
-                let items = list_survey_responses(None)?;
-                export.insert("survey_responses".to_string(), serde_json::to_value(&items)?);
-            }
-            _ => {
-                log::warn!("Unknown table for export: {}", table);
-            }
+    // session_id로 중복 체크 (내용이 다르면 충돌로 기록하고 로컬 유지)
+    let answers_json = serde_json::to_string(answers)?;
+    let existing = conn
+        .query_row(
+            "SELECT id, session_id, template_id, patient_id, respondent_name, answers, submitted_at
+             FROM survey_responses WHERE session_id = ?1",
+            [session_id],
+            map_survey_response_db_row,
+        )
+        .ok();
+
+    if let Some(local) = existing {
+        let remote = SurveyResponseDb {
+            id: local.id.clone(),
+            session_id: Some(session_id.to_string()),
+            template_id: template_id.to_string(),
+            patient_id: patient_id.map(str::to_string),
+            respondent_name: respondent_name.map(str::to_string),
+            answers: answers_json,
+            submitted_at: submitted_at.to_string(),
+        };
+        if survey_responses_conflict(&local, &remote) {
+            record_sync_conflict(&conn, SYNC_ITEM_SURVEY_RESPONSE, &local.id, &serde_json::to_value(&local)?, &serde_json::to_value(&remote)?)?;
+            log::warn!("설문 응답 동기화 충돌 기록 (session: {})", session_id);
+        } else {
+            log::info!("설문 응답 이미 존재 (session: {})", session_id);
         }
+        return Ok(false);
     }
 
-    export.insert("exported_at".to_string(), serde_json::Value::String(Utc::now().to_rfc3339()));
-    Ok(serde_json::to_string_pretty(&serde_json::Value::Object(export))?)
-}
+    let id = uuid::Uuid::new_v4().to_string();
 
-// ============ DB 바이너리 백업/복원 ============
+    // 세션은 sql.js에만 있고 clinic.db에는 없으므로 FK 체크 일시 비활성화
+    conn.execute_batch("PRAGMA foreign_keys = OFF")?;
 
-/// DB 파일을 바이너리로 읽기
-pub fn export_db_binary() -> AppResult<Vec<u8>> {
-    let db_path = get_db_path()?;
-    let data = std::fs::read(&db_path)?;
-    Ok(data)
-}
+    let result = conn.execute(
+        r#"INSERT INTO survey_responses (id, session_id, template_id, patient_id, respondent_name, answers, submitted_at)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"#,
+        params![id, session_id, template_id, patient_id, respondent_name, answers_json, submitted_at],
+    );
 
-/// DB 파일을 바이너리로 덮어쓰기
-pub fn import_db_binary(data: Vec<u8>) -> AppResult<()> {
-    let db_path = get_db_path()?;
-    std::fs::write(&db_path, &data)?;
-    log::info!("Database binary imported to {:?} ({} bytes)", db_path, data.len());
-    Ok(())
+    conn.execute_batch("PRAGMA foreign_keys = ON")?;
+    result?;
+    apply_survey_stats_delta(template_id, patient_id.is_some(), submitted_at, 1);
+
+    log::info!("동기화 설문 응답 저장됨: {} (session: {})", id, session_id);
+    Ok(true)
 }
 
-// ============ 약재 기본 데이터 시드 ============
+// ============ 동기화 충돌 ============
 
-fn seed_herbs(conn: &Connection) -> AppResult<()> {
-    let herbs = vec![
-        ("감초", 4.0, "g"), ("갈근", 8.0, "g"), ("계지", 6.0, "g"), ("작약", 6.0, "g"),
-        ("대추", 6.0, "g"), ("생강", 6.0, "g"), ("마황", 6.0, "g"), ("행인", 12.0, "g"),
-        ("석고", 32.0, "g"), ("자감초", 4.0, "g"), ("반하", 16.0, "g"), ("황련", 2.0, "g"),
-        ("황금", 6.0, "g"), ("인삼", 6.0, "g"), ("건강", 6.0, "g"), ("시호", 16.0, "g"),
-        ("대황", 8.0, "g"), ("망초", 8.0, "g"), ("후박", 8.0, "g"), ("지실", 10.0, "g"),
-        ("복령", 8.0, "g"), ("백출", 6.0, "g"), ("택사", 12.0, "g"), ("부자", 2.0, "g"),
-        ("세신", 4.0, "g"), ("오미자", 6.0, "g"), ("당귀", 6.0, "g"), ("천궁", 6.0, "g"),
-        ("숙지황", 8.0, "g"), ("산수유", 8.0, "g"), ("목단피", 6.0, "g"), ("산약", 8.0, "g"),
-        ("도인", 6.0, "g"), ("지모", 12.0, "g"), ("치자", 14.0, "g"), ("길경", 4.0, "g"),
-        ("방풍", 5.0, "g"), ("강활", 5.0, "g"), ("독활", 5.0, "g"), ("황기", 6.0, "g"),
-        ("귤피", 5.0, "g"), ("향부자", 5.0, "g"), ("의이인", 12.0, "g"), ("아교주", 4.0, "g"),
-        ("용골", 6.0, "g"), ("모려", 6.0, "g"), ("맥문동", 10.0, "g"), ("원지", 5.0, "g"),
-        ("건지황", 8.0, "g"),
-    ];
+/// 동기화 항목 종류: 설문 응답
+pub const SYNC_ITEM_SURVEY_RESPONSE: &str = "survey_response";
 
-    let now = Utc::now().to_rfc3339();
-    let mut stmt = conn.prepare(
-        "INSERT INTO herbs (name, default_dosage, unit, created_at) VALUES (?1, ?2, ?3, ?4)"
-    )?;
+/// 해결된 충돌 기록 보관 기간 (일)
+pub const RESOLVED_SYNC_CONFLICT_RETENTION_DAYS: i64 = 30;
 
-    for (name, dosage, unit) in &herbs {
-        stmt.execute(params![name, dosage, unit, now])?;
-    }
+fn map_survey_response_db_row(row: &rusqlite::Row) -> rusqlite::Result<SurveyResponseDb> {
+    Ok(SurveyResponseDb {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        template_id: row.get(2)?,
+        patient_id: row.get(3)?,
+        respondent_name: row.get(4)?,
+        answers: row.get(5)?,
+        submitted_at: row.get(6)?,
+    })
+}
 
+/// 같은 세션의 로컬/원격 응답 내용이 다른지 (답변은 JSON 값으로 비교, 원격에 환자 연결이 없으면 로컬 연결 유지로 봄)
+fn survey_responses_conflict(local: &SurveyResponseDb, remote: &SurveyResponseDb) -> bool {
+    let answers = |r: &SurveyResponseDb| serde_json::from_str::<serde_json::Value>(&r.answers).ok();
+    answers(local) != answers(remote)
+        || local.template_id != remote.template_id
+        || local.respondent_name != remote.respondent_name
+        || remote.patient_id.as_ref().is_some_and(|p| local.patient_id.as_ref() != Some(p))
+}
+
+/// 충돌 기록 (같은 항목의 미해결 충돌이 있으면 원격 버전만 갱신)
+fn record_sync_conflict(
+    conn: &Connection,
+    item_type: &str,
+    item_id: &str,
+    local: &serde_json::Value,
+    remote: &serde_json::Value,
+) -> AppResult<()> {
+    let now = Utc::now().to_rfc3339();
+    let updated = conn.execute(
+        "UPDATE sync_conflicts SET local_data = ?1, remote_data = ?2, detected_at = ?3
+         WHERE item_type = ?4 AND item_id = ?5 AND resolved_at IS NULL",
+        params![local.to_string(), remote.to_string(), now, item_type, item_id],
+    )?;
+    if updated == 0 {
+        conn.execute(
+            "INSERT INTO sync_conflicts (id, item_type, item_id, local_data, remote_data, detected_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![uuid::Uuid::new_v4().to_string(), item_type, item_id, local.to_string(), remote.to_string(), now],
+        )?;
+    }
     Ok(())
 }
 
-// ============ 처방 정의 기본 데이터 시드 ============
+fn map_sync_conflict_row(row: &rusqlite::Row) -> rusqlite::Result<SyncConflict> {
+    let json = |value: String| serde_json::from_str(&value).unwrap_or(serde_json::Value::Null);
+    let time = |value: String| value.parse().unwrap_or_else(|_| Utc::now());
+    Ok(SyncConflict {
+        id: row.get(0)?,
+        item_type: row.get(1)?,
+        item_id: row.get(2)?,
+        local_data: json(row.get(3)?),
+        remote_data: json(row.get(4)?),
+        detected_at: time(row.get(5)?),
+        resolved_at: row.get::<_, Option<String>>(6)?.map(time),
+        resolution: row
+            .get::<_, Option<String>>(7)?
+            .and_then(|r| serde_json::from_value(serde_json::Value::String(r)).ok()),
+    })
+}
 
-fn seed_prescription_definitions(conn: &Connection) -> AppResult<()> {
-    let definitions = vec![
-        ("갈근가반하탕", "", "마황제", "상한금궤", "마황:6/계지:4/자감초:4/갈근:8/작약:4/대추:6/생강:6/반하:16"),
-        ("갈근탕", "", "마황제", "상한금궤", "마황:6/계지:4/자감초:4/갈근:8/작약:4/대추:6/생강:6"),
-        ("갈근탕가천궁신이", "갈근가천신", "", "후세방", "마황:6/계지:4/자감초:4/갈근:8/작약:4/대추:6/생강:6/천궁:4/신이:4"),
-        ("갈근황금황련탕", "갈금련", "금련제", "상한금궤", "갈근:16/황련:6/황금:6/자감초:6"),
-        ("감맥대조탕", "", "감초제", "상한금궤", "생감초:6/대추:5/부소맥:28"),
-        ("감수반하탕", "", "함흉제", "상한금궤", "감수:0.6/반하:2/작약:2/자감초:1.4"),
-        ("감초건강탕", "", "건강제", "상한금궤", "자감초:8/건강:6"),
-        ("감초부자탕", "", "계지제", "상한금궤", "계지:8/자감초:4/부자:2/백출:4"),
-        ("감초사심탕", "감사", "금련제", "상한금궤", "황련:2/황금:6/인삼:6/반하:16/자감초:8/대추:6/건강:6"),
-        ("감초탕", "", "감초제", "상한금궤", "생감초:4"),
-        ("강삼조이", "", "기능성약물", "후세방", "생강:6/대추:6"),
-        ("건강부자탕", "", "부자제", "상한금궤", "건강:2/부자:2"),
-        ("건강황금황련인삼탕", "강금련인", "금련제", "상한금궤", "건강:6/황금:6/황련:6/인삼:6"),
-        ("계강조초황신부탕", "", "계지제", "상한금궤", "계지:6/작약:6/자감초:4/대추:6/마황:4/세신:4/부자:1"),
-        ("계마각반탕", "", "마황제", "상한금궤", "마황:2/계지:3/자감초:2/행인:7/작약:2/대추:2/생강:2"),
-        ("계작지모탕", "", "계지제", "상한금궤", "계지:8/작약:6/자감초:4/생강:10/지모:8/방풍:8/마황:4/백출:10/부자:2"),
-        ("계지가갈근탕", "", "계지제", "상한금궤", "계지:6/작약:6/자감초:4/대추:6/생강:6/갈근:8"),
-        ("계지가계탕", "", "계지제", "상한금궤", "계지:10/작약:6/자감초:4/대추:6/생강:6"),
-        ("계지가대황탕", "", "계지제", "상한금궤", "계지:6/작약:12/자감초:4/대추:6/생강:6/대황:4"),
-        ("계지가부자탕", "", "계지제", "상한금궤", "계지:6/작약:6/자감초:6/대추:6/생강:6/부자:1"),
-        ("계지가용골모려탕", "계용모", "계지제", "상한금궤", "계지:6/작약:6/자감초:4/대추:6/생강:6/용골:6/모려:6"),
-        ("계지가작약생강인삼신가탕", "신가탕", "계지제", "상한금궤", "계지:6/작약:8/자감초:4/대추:6/생강:8/인삼:6"),
-        ("계지가작약탕", "", "계지제", "상한금궤", "계지:6/작약:12/자감초:4/대추:6/생강:6"),
-        ("계지가황기탕", "", "계지제", "상한금궤", "계지:6/작약:6/자감초:4/대추:6/생강:6/황기:4"),
-        ("계지가후박행자탕", "", "계지제", "상한금궤", "계지:6/작약:6/자감초:4/대추:6/생강:6/후박:4/행인:15"),
-        ("계지거계가복령백출탕", "거계가영출탕", "계지제", "상한금궤", "작약:6/자감초:4/대추:6/생강:6/백출:6/복령:6"),
-        ("계지거작약가부자탕", "", "계지제", "상한금궤", "계지:6/자감초:4/생강:6/대추:6/부자:1"),
-        ("계지거작약탕", "", "계지제", "상한금궤", "계지:6/자감초:4/대추:6/생강:6"),
-        ("계지복령환", "계령", "도인제", "상한금궤", "계지:6/복령:8/도인:6/목단피:6/작약:6"),
-        ("계지부자탕", "", "계지제", "상한금궤", "계지:8/자감초:4/대추:6/생강:6/부자:3"),
-        ("계지생강지실탕", "계생지", "계지제", "상한금궤", "계지:6/생강:6/지실:10"),
-        ("계지인삼탕", "", "건강제", "상한금궤", "인삼:6/백출:6/자감초:8/건강:6/계지:8"),
-        ("계지탕", "", "계지제", "상한금궤", "계지:6/작약:6/자감초:4/대추:6/생강:6"),
-        ("과루계지탕", "", "계지제", "상한금궤", "괄루근:4/계지:6/작약:6/자감초:4/생강:6/대추:6"),
-        ("과루해백반하탕", "", "해백제", "상한금궤", "해백:6/과루인:16/반하:16"),
-        ("과루해백백주탕", "", "해백제", "상한금궤", "해백:16/과루인:16"),
-        ("곽향정기산", "곽정", "", "후세방", "곽향:7.5/자소엽:5/백지:2.5/대복피:2.5/복령:2.5/후박:2.5/백출:2.5/귤피:2.5/반하:2.5/길경:2.5/자감초:2.5/생강:3/대추:3"),
-        ("교애사물탕", "", "", "후세방", "당귀:6/작약:8/천궁:4/아교주:4/자감초:4/애엽:6/숙지황:8"),
-        ("구미강활탕", "", "", "후세방", "강활:7.5/방풍:7.5/천궁:6/백지:6/백출:6/황금:6/건지황:6/세신:2.5/자감초:2.5"),
-        ("궁귀교애탕", "", "당귀제", "상한금궤", "당귀:6/작약:8/천궁:4/아교주:4/자감초:4/애엽:6/건지황:8"),
-        ("귀비온담탕", "", "", "후세방", "귀비탕+온담탕"),
-        ("귀비탕", "", "", "후세방", "당귀:5/용안육:5/산조인:5/원지:5/인삼:5/황기:5/백출:5/복령:5/목향:2.5/자감초:1.5/생강:3/대추:3"),
-        ("귀비탕2", "", "", "후세방", "당귀:5/용안육:5/길초근:2/원지:5/인삼:5/황기:5/백출:5/복령:5/목향:2.5/자감초:1.5/생강:3/대추:3"),
-        ("귀출파징탕", "", "", "후세방", "향부자:7.5/삼릉:5/봉출:5/작약:5/당귀미:5/청피:5/오약:3.5/홍화:2.5/소목:2.5/육계:2.5"),
-        ("귤피대황박초탕", "", "귤피제", "상한금궤", "귤피:3/대황:6/망초:6"),
-        ("귤피죽여탕", "귤죽", "귤피제", "상한금궤", "귤피:16/죽여:4/대추:15/생강:16/자감초:10/인삼:2"),
-        ("귤피지실생강탕", "귤지생", "귤피제", "상한금궤", "귤피:32/지실:6/생강:16"),
-        ("귤피탕", "", "귤피제", "상한금궤", "귤피:8/생강:16"),
-        ("금은화연교", "", "기능성약물", "후세방", "금은화:16/연교:8"),
-        ("길경탕", "", "감초제", "상한금궤", "생감초:4/길경:2"),
-        ("녹용쌍금탕", "", "", "후세방", "숙지황:5/황기:5/당귀:5/천궁:5/육계:3.5/작약:12.5/백출:10/후박:5/귤피:5/곽향:5/반하:5/자감초:5/생강:3/대추:3/뉴분골:2"),
-        ("녹용쌍패탕", "", "", "후세방", "숙지황:5/황기:5/당귀:5/천궁:5/육계:3.5/작약:12.5/인삼:5/시호:5/전호:5/독활:5/강활:5/지각:5/길경:5/복령:5/자감초:5/생강:3/대추:3/박하:3/뉴분골:2"),
-        ("녹용쌍화탕", "", "", "후세방", "숙지황:5/황기:5/당귀:5/천궁:5/계지:3.5/자감초:3.5/작약:12.5/생강:3/대추:3/뉴분골:2.5"),
-        ("당귀건중탕", "", "계지제", "상한금궤", "계지:6/작약:12/자감초:4/대추:6/생강:6/당귀:8"),
-        ("당귀사역가오수유생강탕", "당사오", "계지제", "상한금궤", "계지:6/작약:6/자감초:4/대추:12/생강:16/당귀:6/목통:4/세신:6/오수유:4"),
-        ("당귀사역탕", "", "계지제", "상한금궤", "계지:6/작약:6/자감초:4/대추:12/당귀:6/목통:4/세신:6"),
-        ("당귀수산", "", "", "후세방", "당귀미:7.5/적작약:5/오약:5/향부자:5/소목:5/홍화:4/도인:3.5/육계:3/자감초:2.5"),
-        ("당귀작약산", "당작", "당귀제", "상한금궤", "당귀:6/작약:12/천궁:12/복령:8/백출:8/택사:12"),
-        ("대건중탕", "", "건강제", "상한금궤", "인삼:4/건강:8/교이:20/촉초:2"),
-        ("대승기탕", "", "대황제", "상한금궤", "대황:8/망초:7/후박:16/지실:10"),
-        ("대시함", "", "", "고시방", "대시호탕+소함흉탕"),
-        ("대시함마", "", "", "고시방", "대시호탕+소함흉탕+마행의감"),
-        ("대시함박", "", "", "고시방", "대시호탕+소함흉탕+반하후박탕"),
-        ("대시호가망초탕", "", "시호제", "상한금궤", "시호:16/반하:16/황금:6/지실:8/작약:6/대황:4/생강:10/대추:6/망초:4"),
-        ("대시호탕", "", "시호제", "상한금궤", "시호:16/반하:16/황금:6/지실:8/작약:6/대황:4/생강:10/대추:6"),
-        ("대영전", "", "", "후세방", "숙지황:15/당귀:10/구기자:10/두충:10/우슬:7.5/육계:5/자감초:5"),
-        ("대청룡탕", "", "마황제", "상한금궤", "마황:12/계지:4/자감초:4/행인:12/석고:24/대추:5/생강:6"),
-        ("대탕포방기", "대포", "", "고시방", "대함흉탕+목방기탕+방기황기탕+방기복령탕+방기지황탕"),
-        ("대함흉탕", "", "함흉제", "상한금궤", "대황:12/망초:24/감수:2"),
-        ("대함흉환", "", "함흉제", "상한금궤", "대황:16/정력자:16/망초:12/행인:10/감수:2"),
-        ("대함흉환급탕", "대함환급탕", "", "고시방", "대함흉탕+대함흉환"),
-        ("대황감수탕", "", "대황제", "상한금궤", "대황:8/감수:4/아교주:4"),
-        ("대황망초탕", "", "대황제", "상한금궤", "대황:8/황백:8/망초:8/치자:3"),
-        ("대황목단피탕", "", "도인제", "상한금궤", "도인:8/망초:8/대황:8/목단피:6/동과자:12"),
-        ("대황부자탕", "", "대황제", "상한금궤", "대황:6/부자:3/세신:4"),
-        ("대황황련사심탕", "", "금련제", "상한금궤", "황련:2/황금:2/대황:4"),
-        ("도핵승기탕", "", "도인제", "상한금궤", "도인:6/계지:4/망초:4/대황:8/자감초:4"),
-        ("도화탕", "", "기타고방", "상한금궤", "적석지:32/건강:2/갱미:30"),
-        ("독활기생탕", "", "", "후세방", "독활:7/당귀:7/작약:7/상기생:7/숙지황:5/천궁:5/인삼:5/복령:5/우슬:5/두충:5/진교:5/세신:5/방풍:5/육계:5/자감초:3/생강:3"),
-        ("독활지황탕", "", "", "사상방", "숙지황:16/산수유:8/복령:6/택사:6/목단피:4/방풍:4/독활:4"),
-        ("두충우슬", "", "기능성약물", "후세방", "두충:6/우슬:6"),
-        ("마자인환", "", "대황제", "상한금궤", "마자인:32/작약:16/지실:16/대황:32/후박:20/행인:20"),
-        ("마행감석탕", "", "마황제", "상한금궤", "마황:8/행인:15/자감초:4/석고:16"),
-        ("마행의감2", "", "마황제", "상한금궤", "마황:8/행인:4/의이인:24/자감초:4"),
-        ("마행의감3", "", "마황제", "상한금궤", "마황:8/행인:4/의이인:36/자감초:4"),
-        ("마행의감탕", "", "마황제", "상한금궤", "마황:8/행인:4/의이인:12/자감초:4"),
-        ("마황가출탕", "", "마황제", "상한금궤", "마황:6/계지:4/자감초:2/행인:21/백출:8"),
-        ("마황부자감초탕", "", "마황제", "상한금궤", "마황:4/자감초:4/부자:1"),
-        ("마황부자세신탕", "마부신/마신부", "마황제", "상한금궤", "마황:4/부자:1/세신:4"),
-        ("마황연교적소두탕", "마연적", "마황제", "상한금궤", "마황:4/연교:4/행인:4/적소두:28/대추:6/상백피:20/생강:4/자감초:4"),
-        ("마황탕", "", "마황제", "상한금궤", "마황:6/계지:4/자감초:2/행인:21"),
-        ("맥문동탕", "", "반하제", "상한금궤", "반하:32/인삼:6/대추:6/자감초:4/갱미:9/맥문동:15"),
-        ("맥문후박탕", "", "", "고시방", "맥문동탕+반하후박탕"),
-        ("목방기탕", "", "방기제", "상한금궤", "방기:6/계지:4/석고:24/인삼:8"),
-        ("목방기탕거석고가복령망초탕", "거석복망/복망탕/복망", "방기제", "상한금궤", "방기:4/계지:4/인삼:8/망초:8/복령:8"),
-        ("반하백출천마탕", "반백천", "", "후세방", "반하:7/귤피:7/맥아:7/백출:5/신곡:5/인삼:2.5/황기:2.5/천마:2.5/복령:2.5/택사:2.5/건강:1.5/황백:1/생강:3"),
-        ("반하백출천마탕2", "반백천2", "", "후세방", "반하:7/귤피:7/맥아:7/백출:5/신곡:5/인삼:2.5/황기:2.5/천마:2.5/복령:2.5/택사:2.5/건강:1.5/황금:2/생강:3"),
-        ("반하사심탕", "반사", "금련제", "상한금궤", "황련:2/황금:6/인삼:6/반하:16/자감초:6/대추:6/건강:6"),
-        ("반하후박탕", "", "반하제", "상한금궤", "반하:32/생강:10/복령:8/후박:6/자소엽:4"),
-        ("방기복령탕", "방복", "방기제", "상한금궤", "방기:6/계지:6/황기:6/자감초:4/복령:12"),
-        ("방기지황탕", "방지", "방기제", "상한금궤", "방기:6/계지:6/자감초:2/건지황:8/방풍:6"),
-        ("방기황기탕", "방황", "방기제", "상한금궤", "방기:8/황기:9/백출:6/자감초:4"),
-        ("배농산", "", "감초제", "상한금궤", "길경:2/작약:6/지실:10"),
-        ("배농산급탕", "", "감초제", "상한금궤", "생감초:4/길경:6/대추:5/생강:2/작약:6/지실:10"),
-        ("배농탕", "", "감초제", "상한금궤", "생감초:4/길경:6/대추:5/생강:2"),
-        ("백대갈", "", "", "고시방", "백호탕+대시호탕+갈금련"),
-        ("백엽탕", "", "기타고방", "상한금궤", "측백엽:9/건강:9/애엽:9"),
-        ("백인2", "", "석고제", "상한금궤", "석고:64/지모:24/갱미:18/자감초:4/인삼:6"),
-        ("백인3", "", "석고제", "상한금궤", "석고:96/지모:36/갱미:18/자감초:4/인삼:6"),
-        ("백자팔", "", "", "고시방", "백인+자감+팔미"),
-        ("백출부자탕", "", "계지제", "상한금궤", "자감초:4/대추:6/생강:6/부자:3/백출:8"),
-        ("백호2", "", "석고제", "상한금궤", "석고:64/지모:24/갱미:18/자감초:4"),
-        ("백호3", "", "석고제", "상한금궤", "석고:96/지모:36/갱미:18/자감초:4"),
-        ("백호가계지탕", "", "석고제", "상한금궤", "석고:32/지모:12/갱미:6/자감초:4/계지:6"),
-        ("백호가인삼탕", "백인", "석고제", "상한금궤", "석고:32/지모:12/갱미:18/자감초:4/인삼:6"),
-        ("백호탕", "", "석고제", "상한금궤", "석고:32/지모:12/갱미:18/자감초:4"),
-        ("보중익기탕", "", "", "후세방", "황기:7.5/인삼:5/백출:5/자감초:5/당귀:2.5/귤피:2.5/승마:1.5/시호:1.5"),
-        ("보중치습탕", "", "", "후세방", "인삼:5/백출:5/창출:3.5/귤피:3.5/복령:3.5/맥문동:3.5/목통:3.5/당귀:3.5/황금:2.5/후박:1.5/승마:1.5"),
-        ("보화탕", "", "", "후세방", "귤피:7.5/나복자:5/맥아:5/산사:5/향부자:5/후박:5/자감초:2.5/연교:2.5/"),
-        ("복령사역탕", "", "부자제", "상한금궤", "부자:2/자감초:4/건강:3/인삼:2/복령:8"),
-        ("복령음", "", "귤피제", "상한금궤", "인삼:6/백출:6/복령:6/지실:4/귤피:5/생강:8"),
-        ("복령택사탕", "", "복령제", "상한금궤", "복령:16/계지:4/자감초:2/백출:6/택사:8/생강:8"),
-        ("복령행인감초탕", "", "복령제", "상한금궤", "복령:6/행인:4/자감초:2"),
-        ("부자갱미탕", "", "부자제", "상한금궤", "부자:1/자감초:2/반하:16/대추:5/갱미:15"),
-        ("부자사심탕", "", "금련제", "상한금궤", "대황:4/황련:2/황금:2/부자:2"),
-        ("부자탕", "", "부자제", "상한금궤", "부자:2/인삼:4/백출:8/복령:6/작약:6"),
-        ("불수산", "", "", "후세방", "당귀:30/천궁:20"),
-        ("불환금정기산", "", "", "후세방", "백출:10/후박:5/귤피:5/곽향:5/반하:5/자감초:5/생강:3/대추:3"),
-        ("사간마황탕", "", "마황제", "상한금궤", "사간:6/마황:8/생강:8/세신:6/자완:6/관동화:6/오미자:6/대추:3/반하:16"),
-        ("사군자탕", "", "", "후세방", "인삼:6/백출:6/복령:6/자감초:6"),
-        ("사물탕", "", "", "후세방", "숙지황:6/당귀:6/천궁:6/작약:6"),
-        ("사역가인삼탕", "", "부자제", "상한금궤", "부자:2/자감초:4/건강:3/인삼:2"),
-        ("사역산", "", "시호제", "상한금궤", "시호:16/지실:16/작약:16/자감초:16"),
-        ("사역탕", "", "부자제", "상한금궤", "부자:2/자감초:4/건강:3"),
-        ("산조인탕", "", "당귀제", "상한금궤", "산조인:9/자감초:2/지모:4/복령:4/천궁:4"),
-        ("삼단탕", "", "", "현대처방", "단삼:6/적작약:6/홍화:6"),
-        ("삼릉봉출", "", "기능성약물", "후세방", "삼릉:6/봉출:6"),
-        ("삼물황금탕", "", "금련제", "상한금궤", "황금:2/고삼:4/건지황:8"),
-        ("삼방기탕", "", "", "고시방", "방기황기탕+방기복령탕+방기지황탕"),
-        ("삼출건비탕", "", "", "후세방", "인삼:5/백출:5/복령:5/후박:5/귤피:5/산사:5/지실:4/작약:4/사인:2.5/신곡:2.5/맥아:2.5/자감초:2.5/생강:3/대추:3"),
-        ("삼황사심탕", "", "금련제", "상한금궤", "황련:6/황금:6/대황:6"),
-        ("생간건비탕1", "", "", "현대처방", "인진호:12/갈근:8/울금:6/단삼:6/하수오:6/구기자:10/산사:6/백출:6/귤피:6/후박:6/자감초:3/곽향:3/신곡:3/맥아:3"),
-        ("생강감초탕", "", "건강제", "상한금궤", "생강:10/인삼:6/자감초:8/대추:6"),
-        ("생강사심탕", "생사", "금련제", "상한금궤", "황련:2/황금:6/인삼:6/반하:16/자감초:6/대추:6/건강:2/생강:8"),
-        ("생맥산", "", "", "후세방", "맥문동:10/인삼:5/오미자:5"),
-        ("선복대자석탕", "", "금련제", "상한금궤", "선복화:6/인삼:4/생강:10/대자석:2/자감초:6/반하:10/대추:6"),
-        ("소건중탕", "", "계지제", "상한금궤", "계지:6/작약:12/자감초:4/대추:6/생강:6/교이:40"),
-        ("소건중탕2", "소건중2", "계지제", "상한금궤", "계지:6/작약:12/자감초:4/대추:6/생강:6/교이:20"),
-        ("소목홍화", "", "기능성약물", "후세방", "소목:6/홍화:6"),
-        ("소반하가복령탕", "", "반하제", "상한금궤", "반하:32/생강:16/복령:6"),
-        ("소반하탕", "", "반하제", "상한금궤", "반하:32/생강:16"),
-        ("소승기탕", "", "대황제", "상한금궤", "대황:8/후박:4/지실:6"),
-        ("소시호가망초탕", "", "시호제", "상한금궤", "시호:16/반하:16/황금:6/인삼:6/자감초:6/생강:6/대추:6/망초:4"),
-        ("소시호탕", "", "시호제", "상한금궤", "시호:16/반하:16/황금:6/인삼:6/자감초:6/생강:6/대추:6"),
-        ("소청룡가석고탕", "소청룡석고", "마황제", "상한금궤", "마황:6/계지:6/자감초:6/작약:6/오미자:6/반하:16/건강:6/세신:6/석고:4"),
-        ("소청룡탕", "", "마황제", "상한금궤", "마황:6/계지:6/자감초:6/작약:6/오미자:6/반하:16/건강:6/세신:6"),
-        ("소함흉탕", "", "함흉제", "상한금궤", "황련:3/반하:16/과루인:16"),
-        ("소함흉탕2", "소함흉2", "금련제", "상한금궤", "황련:3/반하:16/과루실:16"),
-        ("승마갈근탕", "", "", "후세방", "갈근:10/승마:5/작약:5/자감초:5/생강:5"),
-        ("시령탕", "", "", "고시방", "소시호탕+오령산"),
-        ("시박탕", "", "", "고시방", "소시호탕+반하후박탕"),
-        ("시평탕", "", "", "고시방", "소시호탕+평위산"),
-        ("시함마", "", "", "고시방", "소시호탕+소함흉탕+마행의감탕"),
-        ("시함마농", "", "", "고시방", "시함마+배농산급탕"),
-        ("시함박", "", "", "고시방", "소시호탕+소함흉탕+반하후박탕"),
-        ("시함박농", "", "", "고시방", "시함박+배농산급탕"),
-        ("시함은화탕", "", "", "고시방", "시함마농+시함박농+금은화연교"),
-        ("시함중", "", "", "고시방", "시함탕+이중탕"),
-        ("시함탕", "", "", "후세방", "소시호탕+소함흉탕"),
-        ("시호가용골모려탕", "시용모/시모", "시호제", "상한금궤", "시호:8/반하:8/황금:3/인삼:3/생강:3/대추:3/대황:4/복령:3/계지:3/용골:3/모려:3"),
-        ("시호거반하가과루탕", "", "금련제", "상한금궤", "시호:16/인삼:6/황금:6/자감초:6/괄루근:8/생강:4/대추:6"),
-        ("시호계지건강탕", "시계건", "시호제", "상한금궤", "시호:16/계지:6/건강:4/괄루근:8/황금:6/모려:4/자감초:4"),
-        ("시호계지탕", "시계", "시호제", "상한금궤", "시호:8/반하:8/황금:3/인삼:3/자감초:2/생강:3/대추:3/작약:3/계지:3"),
-        ("십미패독산", "", "", "후세방", "시호:5/화피:5/길경:5/천궁:5/복령:5/독활:3.5/방풍:3.5/형개:2.5/생감초:2.5/생강:3.5"),
-        ("십전대보탕", "", "", "후세방", "인삼:6/백출:6/복령:6/자감초:6/숙지황:6/작약:6/천궁:6/당귀:6/황기:5/육계:5/생강:5/대추:5"),
-        ("쌍갈탕", "", "", "후세방", "원방쌍화탕+갈근탕"),
-        ("쌍금탕", "", "", "후세방", "숙지황:5/황기:5/당귀:5/천궁:5/육계:3.5/작약:12.5/백출:10/후박:5/귤피:5/곽향:5/반하:5/자감초:5/생강:3/대추:3"),
-        ("쌍패탕", "", "", "후세방", "원방쌍화탕+인삼패독산"),
-        ("억간산", "", "", "후세방", "백출:10/복령:10/당귀:7.5/천궁:7.5/조구등:7.5/시호:5/자감초:4"),
-        ("연령고본단", "", "", "후세방", "구기자:10/두충:10/맥문동:10/목향:10/백자인:10/복령:10/복분자:7.5/산수유:10/산약:10/건지황:10/석창포:5/숙지황:10/오미자:10/우슬:10/원지:5/육종용:20/인삼:10/지골피:7.5/차전자:7.5/천문동:10/천초:5/택사:5/토사자:20/파극천:10"),
-        ("영감강미신탕", "", "복령제", "상한금궤", "복령:8/자감초:6/건강:6/오미자:6/세신:6"),
-        ("영감강미신하인탕", "", "복령제", "상한금궤", "복령:8/자감초:6/건강:6/오미자:6/세신:6/반하:16/행인:6"),
-        ("영감강미신하인황탕", "", "복령제", "상한금궤", "복령:8/자감초:6/건강:6/오미자:6/세신:6/반하:16/행인:6/대황:6"),
-        ("영감강미신하탕", "", "복령제", "상한금궤", "복령:8/자감초:6/건강:6/오미자:6/세신:6/반하:16"),
-        ("영강출감탕", "", "복령제", "상한금궤", "복령:8/자감초:4/건강:8/백출:4"),
-        ("영계감조탕", "", "복령제", "상한금궤", "복령:16/계지:8/자감초:4/대추:8"),
-        ("영계미감탕", "", "복령제", "상한금궤", "복령:8/계지:8/오미자:6/자감초:6"),
-        ("영계출감탕", "", "복령제", "상한금궤", "복령:8/계지:6/자감초:4/백출:4"),
-        ("오령산", "", "복령제", "상한금궤", "복령:6/계지:4/백출:6/택사:10/저령:6"),
-        ("오매환", "", "금련제", "상한금궤", "오매:6/건강:6/당귀:6/세신:4/계지:4/인삼:4/황련:4/황백:3/촉초:2/부자:2"),
-        ("오수유탕", "", "당귀제", "상한금궤", "오수유:5/인삼:6/대추:6/생강:12"),
-        ("오자탕", "", "", "후세방", "사상자:5/육종용:5/복분자:5/구기자:5/토사자:5"),
-        ("온경탕", "", "당귀제", "상한금궤", "당귀:4/작약:4/천궁:4/아교주:4/자감초:4/인삼:4/계지:4/생강:4/목단피:4/반하:16/맥문동:5/오수유:6"),
-        ("온담탕", "", "", "후세방", "반하:10/귤피:10/복령:10/지실:10/죽여:5/자감초:2.5/생강:3/대추:3"),
-        ("원방쌍화탕", "", "", "후세방", "숙지황:5/황기:5/당귀:5/천궁:5/육계:3.5/자감초:3.5/작약:12.5/생강:3/대추:3"),
-        ("월비가반하탕", "", "마황제", "상한금궤", "마황:12/석고:16/생강:6/대추:8/자감초:4/반하:16"),
-        ("월비가출탕", "", "마황제", "상한금궤", "마황:12/자감초:4/석고:16/대추:8/생강:6/백출:8"),
-        ("월비탕", "", "마황제", "상한금궤", "마황:12/자감초:4/석고:16/대추:8/생강:6"),
-        ("육군자탕", "", "", "후세방", "반하:7.5/백출:7.5/귤피:5/복령:5/인삼:5/자감초:2.5"),
-        ("육미지황탕", "육미", "복령제", "상한금궤", "건지황:16/산약:8/산수유:8/복령:6/택사:6/목단피:6"),
-        ("육미지황탕2", "육미2", "복령제", "상한금궤", "숙지황:16/산약:8/산수유:8/복령:6/택사:6/목단피:6"),
-        ("의이부자패장산", "", "부자제", "상한금궤", "부자:2/의이인:16/패장:8"),
-        ("이진탕", "", "", "후세방", "반하:10/귤피:5/복령:5/자감초:2.5"),
-        ("인삼탕", "이중탕", "건강제", "상한금궤", "인삼:6/백출:6/자감초:6/건강:6"),
-        ("인삼패독산", "", "", "후세방", "인삼:5/시호:5/전호:5/독활:5/강활:5/지각:5/길경:5/천궁:5/복령:5/자감초:5/생강:3/박하:3"),
-        ("인숙산", "", "", "후세방", "백자인:8/숙지황:8/인삼:6/지각:6/오미자:6/계지:6/산수유:6/감국:6/복령:6/구기자:6"),
-        ("인진오령산", "", "복령제", "상한금궤", "인진호:12/택사:8/복령:8/백출:6/계지:6/저령:6"),
-        ("인진호탕", "", "치자제", "상한금궤", "치자:14/대황:4/인진호:12"),
-        ("일반쌍화탕", "", "", "후세방", "숙지황:5/황기:5/당귀:5/천궁:5/계지:3.5/자감초:3.5/작약:12.5/생강:3/대추:3"),
-        ("자감초탕", "자감", "계지제", "상한금궤", "계지:6/자감초:8/대추:10/생강:6/인삼:4/맥문동:10/건지황:8/마자인:8/아교주:4"),
-        ("자음강화탕", "", "", "후세방", "작약:6.5/당귀:6/숙지황:5/천문동:5/맥문동:5/백출:5/건지황:4/귤피:3.5/지모:2.5/황백:2.5/자감초:2.5/생강:3/대추:3"),
-        ("작약감초부자탕", "", "계지제", "상한금궤", "작약:6/자감초:6/부자:1"),
-        ("작약감초탕", "작감탕", "계지제", "상한금궤", "작약:8/자감초:8"),
-        ("저령차전자탕", "", "", "사상방", "택사:10/복령:10/저령:7.5/차전자:7.5/지모:5/석고:5/강활:5/독활:5/형개:5/방풍:5"),
-        ("저령탕", "", "복령제", "상한금궤", "복령:6/택사:6/저령:6/아교주:6"),
-        ("적소두당귀산", "", "당귀제", "상한금궤", "적소두:42/당귀:3"),
-        ("조경종옥탕", "", "", "후세방", "숙지황:7.5/향부자:7.5/당귀:5/오수유:5/천궁:5/작약:4/복령:4/귤피:4/현호색:4/목단피:4/건강:4/육계:2.5/애엽:2.5/생강:3"),
-        ("조위승기탕", "", "대황제", "상한금궤", "대황:8/망초:12/자감초:4"),
-        ("죽엽석고탕", "죽석", "석고제", "상한금궤", "석고:32/갱미:15/자감초:4/죽엽:4/인삼:4/맥문동:20/반하:16"),
-        ("중시호가망초탕", "", "", "시호제", "소시호가망초탕+대시호가망초탕"),
-        ("중시호탕", "", "시호제", "상한금궤", "시호:16/반하:16/황금:6/인삼:6/자감초:6/지실:8/작약:6/대황:4/생강:10/대추:6"),
-        ("지백지황환", "", "", "후세방", "건지황:16/산약:8/산수유:8/복령:6/택사:6/목단피:6/지모:4/황백:4"),
-        ("지실치자탕", "", "치자제", "상한금궤", "지실:6/치자:14/두시:3"),
-        ("지실해백계지탕", "", "해백제", "상한금궤", "해백:16/과루인:16/계지:2/후박:8/지실:8"),
-        ("지출탕", "", "기타고방", "상한금궤", "지실:10/백출:8"),
-        ("진무탕", "", "부자제", "상한금궤", "부자:1/백출:4/복령:6/작약:6/생강:6"),
-        ("천마구등음", "", "", "후세방", "조구등:12/천마:9/황금:9/복령:9/익모초:9/야교등:9/석결명:18/상기생:9/치자:9/두충:9/우슬:12"),
-        ("천왕보심단", "", "", "후세방", "길경:2.5/단삼:2.5/당귀:5/맥문동:5/백자인:5/복령:2.5/산조인:5/건지황:5/오미자:5/원지:2.5/인삼:2.5/천문동:5/현삼:2.5"),
-        ("체감탕", "체감탕", "다이어트", "", "의이인:300/숙지황:160/용안육:120/당귀:120/황기:120/괄루근:80/산약:80/상백피:80/귤피:80/천궁:60"),
-        ("총명탕", "", "", "후세방", "원지:10/석창포:10/복령:10"),
-        ("축천환", "", "", "후세방", "오약:5/익지인:5"),
-        ("치자감초시탕", "", "치자제", "상한금궤", "치자:14/자감초:4/두시:20"),
-        ("치자건강탕", "", "치자제", "상한금궤", "치자:14/건강:2"),
-        ("치자대황탕", "", "치자제", "상한금궤", "치자:14/대황:2/지실:10/두시:20"),
-        ("치자벽피탕", "", "치자제", "상한금궤", "치자:14/황백:4/자감초:2"),
-        ("치자생강시탕", "", "치자제", "상한금궤", "치자:14/생강:10/두시:20"),
-        ("치자시탕", "치시", "치자제", "상한금궤", "치자:14/두시:20"),
-        ("치자시탕2", "치자시2", "치자제", "상한금궤", "치자:6/두시:20"),
-        ("치자후박탕", "", "치자제", "상한금궤", "치자:14/후박:8/지실:8"),
-        ("택사탕", "", "복령제", "상한금궤", "백출:4/택사:10"),
-        ("통맥사역탕", "", "부자제", "상한금궤", "부자:3/자감초:4/건강:6"),
-        ("팔미환", "팔미", "복령제", "상한금궤", "건지황:16/산약:8/산수유:8/복령:6/택사:6/목단피:6/계지:2/부자:2"),
-        ("팔미환2", "팔미2", "복령제", "상한금궤", "숙지황:16/산약:8/산수유:8/복령:6/택사:6/목단피:6/육계:2/부자:2"),
-        ("평위산", "", "", "후세방", "백출:10/귤피:7/후박:5/자감초:3/생강:3/대추:3"),
-        ("포방기탕", "", "", "고시방", "목방기탕+방기황기탕+방기복령탕+방기지황탕"),
-        ("향사갈금련", "", "", "고시방", "향사평위산+갈금련"),
-        ("향사육군자탕", "", "", "후세방", "향부자:5/백출:5/복령:5/반하:5/귤피:5/백두구:5/후박:5/사인:2.5/인삼:2.5/목향:2.5/익지인:2.5/자감초:2.5/생강:3/대추:3"),
-        ("향사평위산", "", "", "후세방", "백출:10/귤피:5/향부자:5/지실:4/곽향:4/후박:3.5/사인:3.5/목향:2.5/자감초:2.5/생강:3"),
-        ("현부이경탕", "", "", "후세방", "육계:3.5/귤피:5/당귀:5/도인:5/목향:3.5/작약:5/봉출:5/생강:6/오약:7.5/지각:5/백출:7.5/천궁:5/향부자:15/현호색:5/홍화:3.5"),
-        ("형방사백산", "", "", "사상방", "건지황:10/복령:10/택사:10/석고:5/지모:5/강활:5/독활:5/형개:5/방풍:5"),
-        ("형방지황탕", "", "", "사상방", "숙지황:10/산수유:10/복령:10/택사:10/차전자:5/강활:5/독활:5/형개:5/방풍:5"),
-        ("형방패독산", "", "", "후세방", "인삼:5/시호:5/전호:5/독활:5/강활:5/지각:5/길경:5/천궁:5/복령:5/자감초:5/형개:5/방풍:5"),
-        ("환탕포방기", "환포", "", "고시방", "대함흉탕+대함흉환+목방기탕+방기황기탕+방기복령탕+방기지황탕"),
-        ("황금탕", "", "금련제", "상한금궤", "황금:6/작약:4/자감초:4/대추:6"),
-        ("황기건중탕", "", "계지제", "상한금궤", "계지:6/작약:12/자감초:6/대추:6/생강:6/교이:40/황기:3"),
-        ("황기건중탕2", "황기건중2", "계지제", "상한금궤", "계지:6/작약:12/자감초:6/대추:6/생강:6/교이:20/황기:6"),
-        ("황기계지오물탕", "황계오물탕/황계오물", "계지제", "상한금궤", "황기:6/계지:6/작약:6/생강:12/대추:6"),
-        ("황기작약계지고주탕", "기작계주", "계지제", "상한금궤", "황기:10/작약:6/계지:6"),
-        ("황련아교탕", "황아", "금련제", "상한금궤", "황련:8/황금:4/작약:4/아교주:3"),
-        ("황련탕", "", "금련제", "상한금궤", "황련:6/건강:6/인삼:4/자감초:6/대추:6/반하:16/계지:6"),
-        ("황련해독탕", "", "", "후세방", "황련:6/황금:6/황백:6/치자:6"),
-        ("후박마황탕", "", "마황제", "상한금궤", "후박:10/마황:8/석고:20/행인:8/반하:16/건강:4/세신:4/부소맥:28/오미자:6"),
-        ("후박삼물탕", "", "대황제", "상한금궤", "후박:16/대황:8/지실:10"),
-        ("후박칠물탕", "", "대황제", "상한금궤", "대황:6/후박:16/지실:10/자감초:6/대추:5/계지:4/생강:10"),
-        ("후생반감인탕", "", "반하제", "상한금궤", "후박:16/생강:16/반하:16/자감초:4/인삼:2"),
-    ];
+const SYNC_CONFLICT_COLUMNS: &str = "id, item_type, item_id, local_data, remote_data, detected_at, resolved_at, resolution";
+
+/// 동기화 충돌 목록 (최근 발견순, `include_resolved`가 아니면 미해결만)
+pub fn list_sync_conflicts(include_resolved: bool) -> AppResult<Vec<SyncConflict>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM sync_conflicts WHERE ?1 OR resolved_at IS NULL ORDER BY detected_at DESC",
+        SYNC_CONFLICT_COLUMNS
+    ))?;
+    let conflicts = stmt.query_map([include_resolved], map_sync_conflict_row)?;
+    Ok(conflicts.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// 미해결 동기화 충돌 수
+pub fn count_sync_conflicts() -> AppResult<i64> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    Ok(conn.query_row("SELECT COUNT(*) FROM sync_conflicts WHERE resolved_at IS NULL", [], |row| row.get(0))?)
+}
+
+/// 동기화 충돌 해결 (원격 선택 시 로컬 기록을 원격 버전으로 덮어씀, 없는 충돌이면 None)
+///
+/// 이미 해결된 충돌은 `AppError::Conflict`. 해결 후 보관 기간이 지난 해결 기록은 정리합니다.
+pub fn resolve_sync_conflict(id: &str, choice: SyncConflictChoice) -> AppResult<Option<SyncConflict>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let Some(conflict) = conn
+        .query_row(&format!("SELECT {} FROM sync_conflicts WHERE id = ?1", SYNC_CONFLICT_COLUMNS), [id], map_sync_conflict_row)
+        .ok()
+    else {
+        return Ok(None);
+    };
+    if conflict.resolved_at.is_some() {
+        return Err(AppError::Conflict("이미 해결된 충돌입니다".to_string()));
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    if choice == SyncConflictChoice::Remote {
+        match conflict.item_type.as_str() {
+            SYNC_ITEM_SURVEY_RESPONSE => {
+                let remote: SurveyResponseDb = serde_json::from_value(conflict.remote_data.clone())?;
+                let updated = tx.execute(
+                    "UPDATE survey_responses SET template_id = ?1, respondent_name = ?2, answers = ?3, submitted_at = ?4,
+                            patient_id = COALESCE(?5, patient_id)
+                     WHERE id = ?6",
+                    params![
+                        remote.template_id,
+                        remote.respondent_name,
+                        remote.answers,
+                        remote.submitted_at,
+                        remote.patient_id,
+                        conflict.item_id
+                    ],
+                )?;
+                if updated == 0 {
+                    return Err(AppError::Validation("충돌한 설문 응답이 삭제되어 원격 버전을 적용할 수 없습니다".to_string()));
+                }
+            }
+            other => return Err(AppError::Validation(format!("알 수 없는 동기화 항목 종류: {}", other))),
+        }
+    }
+    let now = Utc::now();
+    let resolution = match choice {
+        SyncConflictChoice::Local => "local",
+        SyncConflictChoice::Remote => "remote",
+    };
+    tx.execute(
+        "UPDATE sync_conflicts SET resolved_at = ?1, resolution = ?2 WHERE id = ?3",
+        params![now.to_rfc3339(), resolution, id],
+    )?;
+    let cutoff = now - chrono::Duration::days(RESOLVED_SYNC_CONFLICT_RETENTION_DAYS);
+    tx.execute("DELETE FROM sync_conflicts WHERE resolved_at < ?1", [cutoff.to_rfc3339()])?;
+    tx.commit()?;
+
+    if choice == SyncConflictChoice::Remote {
+        invalidate_survey_stats();
+    }
+    log::info!("동기화 충돌 해결: {} ({})", id, resolution);
+    Ok(Some(SyncConflict { resolved_at: Some(now), resolution: Some(choice), ..conflict }))
+}
+
+// ============ 동기화 대기열 ============
+
+/// 최대 재시도 횟수 (초과 항목은 더 이상 꺼내지 않음)
+pub const SYNC_MAX_RETRIES: i32 = 5;
+
+/// 동기화 대기 항목 (DB용)
+#[derive(Debug, Clone)]
+pub struct SyncQueueItemDb {
+    pub id: String,
+    pub item_type: String,
+    pub data: String,
+    pub created_at: String,
+    pub retry_count: i32,
+    pub next_retry_at: String,
+}
 
+/// 동기화 대기열에 추가 (이미 있으면 무시, 즉시 재시도 대상)
+pub fn enqueue_sync_item(id: &str, item_type: &str, data: &str) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
     let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT OR IGNORE INTO sync_queue (id, item_type, data, created_at, next_retry_at) VALUES (?1, ?2, ?3, ?4, ?4)",
+        params![id, item_type, data, now],
+    )?;
+    Ok(())
+}
+
+/// 재시도 시각이 된 항목을 오래된 순으로 꺼내 전송 중으로 표시
+pub fn dequeue_sync_items(limit: u32) -> AppResult<Vec<SyncQueueItemDb>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let tx = conn.unchecked_transaction()?;
+
+    let items = {
+        let mut stmt = tx.prepare(
+            "SELECT id, item_type, data, created_at, retry_count, COALESCE(next_retry_at, created_at) FROM sync_queue
+             WHERE in_flight = 0 AND retry_count < ?1 AND COALESCE(next_retry_at, created_at) <= ?2
+             ORDER BY created_at ASC LIMIT ?3",
+        )?;
+        let rows = stmt.query_map(params![SYNC_MAX_RETRIES, Utc::now().to_rfc3339(), limit], |row| {
+            Ok(SyncQueueItemDb {
+                id: row.get(0)?,
+                item_type: row.get(1)?,
+                data: row.get(2)?,
+                created_at: row.get(3)?,
+                retry_count: row.get(4)?,
+                next_retry_at: row.get(5)?,
+            })
+        })?;
+        rows.collect::<Result<Vec<_>, _>>()?
+    };
+
+    for item in &items {
+        tx.execute("UPDATE sync_queue SET in_flight = 1 WHERE id = ?1", [&item.id])?;
+    }
+    tx.commit()?;
+
+    Ok(items)
+}
+
+/// 전송 완료된 항목 제거
+pub fn remove_sync_item(id: &str) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute("DELETE FROM sync_queue WHERE id = ?1", [id])?;
+    Ok(())
+}
+
+/// 전송 실패 항목을 대기열로 되돌리고 재시도 횟수 증가
+pub fn mark_sync_item_failed(id: &str, next_retry_at: chrono::DateTime<Utc>) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute(
+        "UPDATE sync_queue SET in_flight = 0, retry_count = retry_count + 1, next_retry_at = ?1 WHERE id = ?2",
+        params![next_retry_at.to_rfc3339(), id],
+    )?;
+    Ok(())
+}
+
+/// 비정상 종료로 전송 중 상태에 남은 항목을 대기 상태로 복구
+pub fn reset_inflight_sync_items() -> AppResult<usize> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let count = conn.execute("UPDATE sync_queue SET in_flight = 0 WHERE in_flight = 1", [])?;
+    Ok(count)
+}
+
+/// 동기화 대기 항목 수 (재시도 한도 초과 항목 제외)
+pub fn count_sync_items() -> AppResult<i64> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let count = conn.query_row(
+        "SELECT COUNT(*) FROM sync_queue WHERE retry_count < ?1",
+        [SYNC_MAX_RETRIES],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
+
+// ============ 내부 직원 계정 관리 ============
+
+/// 직원 비밀번호 최소 길이
+const STAFF_PASSWORD_MIN_LEN: usize = 8;
+
+/// 직원 계정 생성 (비밀번호는 argon2로 해시, 권한은 역할 기본값)
+pub fn create_staff_account(username: &str, display_name: &str, password: &str, role: StaffRole) -> AppResult<StaffAccount> {
+    let username = username.trim();
+    let display_name = display_name.trim();
+    if username.is_empty() || display_name.is_empty() {
+        return Err(AppError::Validation("아이디와 표시 이름을 입력해주세요".to_string()));
+    }
+    let account = StaffAccount::new(
+        username.to_string(),
+        display_name.to_string(),
+        hash_staff_password(password)?,
+        role,
+    );
+
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    insert_staff_account(&conn, &account)?;
+
+    log::info!("직원 계정 생성됨: {} ({})", account.username, account.role.as_str());
+    Ok(account)
+}
+
+/// 직원 계정 추가 (아이디 중복이면 `AppError::Conflict`)
+fn insert_staff_account(conn: &Connection, account: &StaffAccount) -> AppResult<()> {
+    let permissions_json = serde_json::to_string(&account.permissions)?;
+
+    conn.execute(
+        r#"INSERT INTO staff_accounts (id, username, display_name, password_hash, role, permissions, is_active, last_login_at, created_at, updated_at)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"#,
+        params![
+            account.id,
+            account.username,
+            account.display_name,
+            account.password_hash,
+            account.role.as_str(),
+            permissions_json,
+            account.is_active,
+            account.last_login_at.map(|d| d.to_rfc3339()),
+            account.created_at.to_rfc3339(),
+            account.updated_at.to_rfc3339(),
+        ],
+    )
+    .map_err(map_staff_username_conflict)?;
+    Ok(())
+}
+
+/// 아이디 중복(UNIQUE 제약) 위반을 Conflict로 변환
+fn map_staff_username_conflict(e: rusqlite::Error) -> AppError {
+    match &e {
+        rusqlite::Error::SqliteFailure(err, Some(msg))
+            if err.code == rusqlite::ErrorCode::ConstraintViolation && msg.contains("staff_accounts.username") =>
+        {
+            AppError::Conflict("이미 사용 중인 아이디입니다".to_string())
+        }
+        _ => e.into(),
+    }
+}
+
+/// 활성 관리자가 이 계정 하나뿐인지 (마지막 관리자는 비활성화/강등/삭제 불가)
+fn is_last_active_admin(conn: &Connection, id: &str) -> AppResult<bool> {
+    let (is_active_admin, other_admins): (bool, i64) = conn.query_row(
+        "SELECT
+            EXISTS(SELECT 1 FROM staff_accounts WHERE id = ?1 AND role = 'admin' AND is_active = 1),
+            (SELECT COUNT(*) FROM staff_accounts WHERE id <> ?1 AND role = 'admin' AND is_active = 1)",
+        [id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    Ok(is_active_admin && other_admins == 0)
+}
+
+/// 저장된 권한 JSON 읽기 (읽을 수 없으면 역할 기본 권한)
+fn parse_staff_permissions(json: &str, role: &StaffRole) -> StaffPermissions {
+    serde_json::from_str(json).unwrap_or_else(|_| StaffPermissions::for_role(role))
+}
+
+/// 권한 JSON을 읽을 수 없는 계정에 역할 기본 권한 저장 (환자 삭제 권한이 빠진 예전 JSON은 그 항목만 채움)
+fn migrate_staff_permissions(conn: &Connection) -> AppResult<()> {
+    let accounts: Vec<(String, String, String)> = conn
+        .prepare("SELECT id, role, permissions FROM staff_accounts")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<Result<_, _>>()?;
+    for (id, role, json) in accounts {
+        let defaults = StaffPermissions::for_role(&StaffRole::from_str(&role));
+        let permissions = match serde_json::from_str::<serde_json::Value>(&json) {
+            Ok(serde_json::Value::Object(mut map)) if serde_json::from_str::<StaffPermissions>(&json).is_ok() => {
+                if map.contains_key("patients_delete") {
+                    continue;
+                }
+                // 삭제 권한이 생기기 전 계정은 역할 기본값으로 채움 (관리자/직원은 삭제 가능)
+                map.insert("patients_delete".to_string(), defaults.patients_delete.into());
+                serde_json::Value::Object(map).to_string()
+            }
+            _ => serde_json::to_string(&defaults)?,
+        };
+        conn.execute("UPDATE staff_accounts SET permissions = ?1 WHERE id = ?2", params![permissions, id])?;
+        log::info!("[DB] 직원 계정 권한을 역할 기본값으로 설정: {}", id);
+    }
+    Ok(())
+}
+
+const LAST_ADMIN_MESSAGE: &str = "마지막 관리자 계정은 비활성화하거나 역할을 바꿀 수 없습니다";
+
+/// 직원 계정 조회 (ID로)
+pub fn get_staff_account(id: &str) -> AppResult<Option<StaffAccount>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
     let mut stmt = conn.prepare(
-        "INSERT INTO prescription_definitions (name, alias, category, source, composition, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"
+        r#"SELECT id, username, display_name, password_hash, role, permissions, is_active, last_login_at, created_at, updated_at, must_change_password
+           FROM staff_accounts WHERE id = ?1"#,
+    )?;
+
+    let result = stmt.query_row([id], |row| {
+        let permissions_str: String = row.get(5)?;
+        let role_str: String = row.get(4)?;
+        let permissions = parse_staff_permissions(&permissions_str, &StaffRole::from_str(&role_str));
+
+        Ok(StaffAccount {
+            id: row.get(0)?,
+            username: row.get(1)?,
+            display_name: row.get(2)?,
+            password_hash: row.get(3)?,
+            role: StaffRole::from_str(&role_str),
+            permissions,
+            is_active: row.get(6)?,
+            last_login_at: row.get::<_, Option<String>>(7)?
+                .and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
+                .map(|d| d.with_timezone(&Utc)),
+            created_at: row.get::<_, String>(8)?
+                .parse::<chrono::DateTime<Utc>>()
+                .unwrap_or_else(|_| Utc::now()),
+            updated_at: row.get::<_, String>(9)?
+                .parse::<chrono::DateTime<Utc>>()
+                .unwrap_or_else(|_| Utc::now()),
+            must_change_password: row.get(10)?,
+        })
+    });
+
+    match result {
+        Ok(account) => Ok(Some(account)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 직원 계정 조회 (username으로)
+pub fn get_staff_account_by_username(username: &str) -> AppResult<Option<StaffAccount>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let mut stmt = conn.prepare(
+        r#"SELECT id, username, display_name, password_hash, role, permissions, is_active, last_login_at, created_at, updated_at, must_change_password
+           FROM staff_accounts WHERE username = ?1"#,
+    )?;
+
+    let result = stmt.query_row([username], |row| {
+        let permissions_str: String = row.get(5)?;
+        let role_str: String = row.get(4)?;
+        let permissions = parse_staff_permissions(&permissions_str, &StaffRole::from_str(&role_str));
+
+        Ok(StaffAccount {
+            id: row.get(0)?,
+            username: row.get(1)?,
+            display_name: row.get(2)?,
+            password_hash: row.get(3)?,
+            role: StaffRole::from_str(&role_str),
+            permissions,
+            is_active: row.get(6)?,
+            last_login_at: row.get::<_, Option<String>>(7)?
+                .and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
+                .map(|d| d.with_timezone(&Utc)),
+            created_at: row.get::<_, String>(8)?
+                .parse::<chrono::DateTime<Utc>>()
+                .unwrap_or_else(|_| Utc::now()),
+            updated_at: row.get::<_, String>(9)?
+                .parse::<chrono::DateTime<Utc>>()
+                .unwrap_or_else(|_| Utc::now()),
+            must_change_password: row.get(10)?,
+        })
+    });
+
+    match result {
+        Ok(account) => Ok(Some(account)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 직원 계정 목록 조회
+pub fn list_staff_accounts() -> AppResult<Vec<StaffAccountInfo>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let mut stmt = conn.prepare(
+        r#"SELECT id, username, display_name, password_hash, role, permissions, is_active, last_login_at, created_at, updated_at, must_change_password
+           FROM staff_accounts ORDER BY created_at DESC"#,
     )?;
 
-    for (name, alias, category, source, composition) in &definitions {
-        let alias_val = if alias.is_empty() { None } else { Some(*alias) };
-        let category_val = if category.is_empty() { None } else { Some(*category) };
-        stmt.execute(params![name, alias_val, category_val, source, composition, now, now])?;
+    let rows = stmt.query_map([], |row| {
+        let permissions_str: String = row.get(5)?;
+        let role_str: String = row.get(4)?;
+        let permissions = parse_staff_permissions(&permissions_str, &StaffRole::from_str(&role_str));
+
+        Ok(StaffAccountInfo {
+            id: row.get(0)?,
+            username: row.get(1)?,
+            display_name: row.get(2)?,
+            role: StaffRole::from_str(&role_str),
+            permissions,
+            is_active: row.get(6)?,
+            last_login_at: row.get::<_, Option<String>>(7)?
+                .and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
+                .map(|d| d.with_timezone(&Utc)),
+            created_at: row.get::<_, String>(8)?
+                .parse::<chrono::DateTime<Utc>>()
+                .unwrap_or_else(|_| Utc::now()),
+            updated_at: row.get::<_, String>(9)?
+                .parse::<chrono::DateTime<Utc>>()
+                .unwrap_or_else(|_| Utc::now()),
+            must_change_password: row.get(10)?,
+        })
+    })?;
+
+    let mut accounts = Vec::new();
+    for row in rows {
+        accounts.push(row?);
+    }
+    Ok(accounts)
+}
+
+/// 직원 계정 수정 (마지막 활성 관리자를 강등/비활성화하면 `AppError::Conflict`)
+pub fn update_staff_account(account: &StaffAccount) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let tx = conn.unchecked_transaction()?;
+
+    let stays_admin = account.role == StaffRole::Admin && account.is_active;
+    if !stays_admin && is_last_active_admin(&tx, &account.id)? {
+        return Err(AppError::Conflict(LAST_ADMIN_MESSAGE.to_string()));
+    }
+
+    let permissions_json = serde_json::to_string(&account.permissions)?;
+
+    tx.execute(
+        r#"UPDATE staff_accounts
+           SET username = ?2, display_name = ?3, password_hash = ?4, role = ?5,
+               permissions = ?6, is_active = ?7, updated_at = ?8
+           WHERE id = ?1"#,
+        params![
+            account.id,
+            account.username,
+            account.display_name,
+            account.password_hash,
+            account.role.as_str(),
+            permissions_json,
+            account.is_active,
+            Utc::now().to_rfc3339(),
+        ],
+    )
+    .map_err(map_staff_username_conflict)?;
+    tx.commit()?;
+
+    log::info!("직원 계정 수정됨: {}", account.username);
+    Ok(())
+}
+
+/// 직원 계정 활성/비활성 (반환: 변경된 계정, 없으면 None)
+pub fn set_staff_account_active(id: &str, is_active: bool) -> AppResult<Option<StaffAccount>> {
+    {
+        ensure_db_initialized()?;
+        let conn = get_conn()?;
+        let tx = conn.unchecked_transaction()?;
+        if !is_active && is_last_active_admin(&tx, id)? {
+            return Err(AppError::Conflict(LAST_ADMIN_MESSAGE.to_string()));
+        }
+        let updated = tx.execute(
+            "UPDATE staff_accounts SET is_active = ?2, updated_at = ?3 WHERE id = ?1",
+            params![id, is_active, Utc::now().to_rfc3339()],
+        )?;
+        if updated == 0 {
+            return Ok(None);
+        }
+        tx.commit()?;
+    }
+
+    log::info!("직원 계정 {}: {}", if is_active { "활성화" } else { "비활성화" }, id);
+    get_staff_account(id)
+}
+
+/// 직원 계정 삭제 (마지막 활성 관리자는 삭제 불가)
+pub fn delete_staff_account(id: &str) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    if is_last_active_admin(&conn, id)? {
+        return Err(AppError::Conflict("마지막 관리자 계정은 삭제할 수 없습니다".to_string()));
+    }
+    conn.execute("DELETE FROM staff_accounts WHERE id = ?1", [id])?;
+    log::info!("직원 계정 삭제됨: {}", id);
+    Ok(())
+}
+
+/// 직원 표시 이름 변경 (본인 프로필 수정용)
+pub fn update_staff_account_display_name(id: &str, name: &str) -> AppResult<()> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::Custom("표시 이름이 비어 있습니다".to_string()));
+    }
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let updated = conn.execute(
+        "UPDATE staff_accounts SET display_name = ?2, updated_at = ?3 WHERE id = ?1",
+        params![id, name, Utc::now().to_rfc3339()],
+    )?;
+    if updated == 0 {
+        return Err(AppError::Custom("계정을 찾을 수 없습니다".to_string()));
+    }
+    Ok(())
+}
+
+/// 직원 로그인 시간 업데이트
+pub fn update_staff_last_login(id: &str) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute(
+        "UPDATE staff_accounts SET last_login_at = ?2, updated_at = ?2 WHERE id = ?1",
+        params![id, Utc::now().to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+/// 직원 비밀번호 검증
+pub fn verify_staff_account_password(username: &str, password: &str) -> AppResult<Option<StaffAccount>> {
+    let account = get_staff_account_by_username(username)?;
+
+    match account {
+        Some(acc) if acc.is_active => {
+            if !verify_staff_password_hash(password, &acc.password_hash) {
+                return Ok(None);
+            }
+            // 이전 bcrypt 해시는 로그인 성공 시 argon2로 교체
+            if !acc.password_hash.starts_with("$argon2") {
+                if let Err(e) = upgrade_staff_password_hash(&acc.id, password) {
+                    log::warn!("직원 비밀번호 해시 갱신 실패: {}", e);
+                }
+            }
+            // 로그인 시간 업데이트
+            let _ = update_staff_last_login(&acc.id);
+            Ok(Some(acc))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// 저장된 해시로 비밀번호 확인 (argon2, 이전 계정은 bcrypt)
+fn verify_staff_password_hash(password: &str, hash: &str) -> bool {
+    use argon2::password_hash::{PasswordHash, PasswordVerifier};
+
+    if hash.starts_with("$argon2") {
+        PasswordHash::new(hash)
+            .is_ok_and(|parsed| argon2::Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+    } else {
+        bcrypt::verify(password, hash).unwrap_or(false)
+    }
+}
+
+fn upgrade_staff_password_hash(id: &str, password: &str) -> AppResult<()> {
+    let hash = hash_staff_password(password)?;
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute(
+        "UPDATE staff_accounts SET password_hash = ?2 WHERE id = ?1",
+        params![id, hash],
+    )?;
+    Ok(())
+}
+
+/// 본인 비밀번호 변경 (현재 비밀번호가 틀리면 false, 변경 필요 표시는 해제)
+pub fn change_staff_password(id: &str, current_password: &str, new_password: &str) -> AppResult<bool> {
+    let Some(account) = get_staff_account(id)?.filter(|a| a.is_active) else {
+        return Ok(false);
+    };
+    if !verify_staff_password_hash(current_password, &account.password_hash) {
+        return Ok(false);
+    }
+    if new_password == current_password {
+        return Err(AppError::Validation("새 비밀번호가 현재 비밀번호와 같습니다".to_string()));
+    }
+    let hash = hash_staff_password(new_password)?;
+
+    let conn = get_conn()?;
+    conn.execute(
+        "UPDATE staff_accounts SET password_hash = ?2, must_change_password = 0, updated_at = ?3 WHERE id = ?1",
+        params![id, hash, Utc::now().to_rfc3339()],
+    )?;
+    log::info!("직원 비밀번호 변경됨: {}", account.username);
+    Ok(true)
+}
+
+/// 임시 비밀번호 길이
+const TEMPORARY_PASSWORD_LEN: usize = 12;
+
+/// 관리자 비밀번호 재설정 (반환: 임시 비밀번호, 계정이 없으면 None)
+///
+/// 임시 비밀번호는 저장하지 않고 한 번만 돌려주며, 다음 로그인 후 변경하기 전까지 다른 API를 쓸 수 없습니다.
+pub fn reset_staff_password(id: &str) -> AppResult<Option<String>> {
+    use rand::Rng;
+
+    // 헷갈리는 문자(0/O, 1/l/I) 제외
+    const CHARSET: &[u8] = b"abcdefghijkmnpqrstuvwxyzABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+    let mut rng = rand::thread_rng();
+    let temporary: String = (0..TEMPORARY_PASSWORD_LEN)
+        .map(|_| CHARSET[rng.gen_range(0..CHARSET.len())] as char)
+        .collect();
+    let hash = hash_staff_password(&temporary)?;
+
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let updated = conn.execute(
+        "UPDATE staff_accounts SET password_hash = ?2, must_change_password = 1, updated_at = ?3 WHERE id = ?1",
+        params![id, hash, Utc::now().to_rfc3339()],
+    )?;
+    if updated == 0 {
+        return Ok(None);
+    }
+    log::info!("직원 비밀번호 재설정됨: {}", id);
+    Ok(Some(temporary))
+}
+
+/// 비밀번호 해시 생성 (argon2id)
+pub fn hash_staff_password(password: &str) -> AppResult<String> {
+    use argon2::password_hash::{PasswordHasher, SaltString};
+    use rand::RngCore;
+
+    if password.chars().count() < STAFF_PASSWORD_MIN_LEN {
+        return Err(AppError::Validation(format!("비밀번호는 {}자 이상이어야 합니다", STAFF_PASSWORD_MIN_LEN)));
+    }
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let salt = SaltString::encode_b64(&salt)
+        .map_err(|e| AppError::Custom(format!("Password hashing failed: {}", e)))?;
+    argon2::Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| AppError::Custom(format!("Password hashing failed: {}", e)))
+}
+
+// ============ 직원 웹 세션 ============
+
+/// 저장된 직원 웹 세션
+#[derive(Debug, Clone)]
+pub struct WebSession {
+    pub clinic_name: String,
+    pub account_id: Option<String>,
+    pub must_change_password: bool,
+    pub created_at: chrono::DateTime<Utc>,
+    pub last_activity: chrono::DateTime<Utc>,
+}
+
+/// 세션 토큰 해시 (DB가 유출되어도 토큰을 그대로 쓸 수 없도록 원문은 저장하지 않음)
+fn hash_session_token(token: &str) -> String {
+    use sha2::{Digest, Sha256};
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// 직원 웹 세션 저장
+pub fn save_web_session(token: &str, session: &WebSession) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute(
+        "INSERT OR REPLACE INTO web_sessions (token_hash, clinic_name, account_id, must_change_password, created_at, last_activity)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            hash_session_token(token),
+            session.clinic_name,
+            session.account_id,
+            session.must_change_password,
+            session.created_at.to_rfc3339(),
+            session.last_activity.to_rfc3339(),
+        ],
+    )?;
+    Ok(())
+}
+
+/// 직원 웹 세션 조회 (만료 여부는 호출하는 쪽에서 판단)
+pub fn get_web_session(token: &str) -> AppResult<Option<WebSession>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let parse_time = |value: String| {
+        chrono::DateTime::parse_from_rfc3339(&value)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e)))
+    };
+    let session = conn
+        .query_row(
+            "SELECT clinic_name, account_id, must_change_password, created_at, last_activity FROM web_sessions WHERE token_hash = ?1",
+            [hash_session_token(token)],
+            |row| {
+                Ok(WebSession {
+                    clinic_name: row.get(0)?,
+                    account_id: row.get(1)?,
+                    must_change_password: row.get(2)?,
+                    created_at: parse_time(row.get(3)?)?,
+                    last_activity: parse_time(row.get(4)?)?,
+                })
+            },
+        )
+        .ok();
+    Ok(session)
+}
+
+/// 직원 웹 세션 마지막 요청 시각 갱신
+pub fn touch_web_session(token: &str, last_activity: chrono::DateTime<Utc>) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute(
+        "UPDATE web_sessions SET last_activity = ?2 WHERE token_hash = ?1",
+        params![hash_session_token(token), last_activity.to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+/// 직원 웹 세션 토큰 교체 (재발급, 로그인 시각은 그대로)
+pub fn rename_web_session(old_token: &str, new_token: &str) -> AppResult<bool> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let updated = conn.execute(
+        "UPDATE web_sessions SET token_hash = ?2 WHERE token_hash = ?1",
+        params![hash_session_token(old_token), hash_session_token(new_token)],
+    )?;
+    Ok(updated == 1)
+}
+
+/// 직원 웹 세션 삭제 (로그아웃, 만료)
+pub fn delete_web_session(token: &str) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute("DELETE FROM web_sessions WHERE token_hash = ?1", [hash_session_token(token)])?;
+    Ok(())
+}
+
+/// 계정의 직원 웹 세션 삭제 (`keep_token` 세션은 남기고 비밀번호 변경 필요 표시만 해제)
+pub fn delete_account_web_sessions(account_id: &str, keep_token: Option<&str>) -> AppResult<usize> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let keep_hash = keep_token.map(hash_session_token);
+    let deleted = conn.execute(
+        "DELETE FROM web_sessions WHERE account_id = ?1 AND token_hash IS NOT ?2",
+        params![account_id, keep_hash],
+    )?;
+    if let Some(hash) = &keep_hash {
+        conn.execute("UPDATE web_sessions SET must_change_password = 0 WHERE token_hash = ?1", [hash])?;
+    }
+    Ok(deleted)
+}
+
+/// 만료된 직원 웹 세션 삭제 (마지막 요청이 `idle_before` 이전이거나 로그인이 `created_before` 이전)
+pub fn delete_expired_web_sessions(idle_before: chrono::DateTime<Utc>, created_before: chrono::DateTime<Utc>) -> AppResult<usize> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let deleted = conn.execute(
+        "DELETE FROM web_sessions WHERE last_activity < ?1 OR created_at < ?2",
+        params![idle_before.to_rfc3339(), created_before.to_rfc3339()],
+    )?;
+    Ok(deleted)
+}
+
+// ============ 초진차트 관리 ============
+
+use crate::models::{InitialChart, ProgressNote};
+
+/// 초진차트 생성
+pub fn create_initial_chart(chart: &InitialChart) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    conn.execute(
+        r#"INSERT INTO initial_charts (id, patient_id, doctor_name, chart_date, chief_complaint, present_illness, past_medical_history, notes, prescription_issued, prescription_issued_at, deleted_at, created_at, updated_at)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)"#,
+        params![
+            chart.id,
+            chart.patient_id,
+            chart.doctor_name,
+            chart.chart_date,
+            chart.chief_complaint,
+            chart.present_illness,
+            chart.past_medical_history,
+            chart.notes,
+            if chart.prescription_issued { 1 } else { 0 },
+            chart.prescription_issued_at,
+            chart.deleted_at,
+            chart.created_at.to_rfc3339(),
+            chart.updated_at.to_rfc3339(),
+        ],
+    )?;
+    Ok(())
+}
+
+/// 초진차트 조회
+pub fn get_initial_chart(id: &str) -> AppResult<Option<InitialChart>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let mut stmt = conn.prepare(
+        r#"SELECT id, patient_id, doctor_name, chart_date, chief_complaint, present_illness, past_medical_history, notes, prescription_issued, prescription_issued_at, deleted_at, created_at, updated_at
+           FROM initial_charts WHERE id = ?1 AND deleted_at IS NULL"#,
+    )?;
+
+    let result = stmt.query_row([id], |row| {
+        Ok(InitialChart {
+            id: row.get(0)?,
+            patient_id: row.get(1)?,
+            doctor_name: row.get(2)?,
+            chart_date: row.get(3)?,
+            chief_complaint: row.get(4)?,
+            present_illness: row.get(5)?,
+            past_medical_history: row.get(6)?,
+            notes: row.get(7)?,
+            prescription_issued: row.get::<_, i32>(8)? != 0,
+            prescription_issued_at: row.get(9)?,
+            deleted_at: row.get(10)?,
+            created_at: row.get::<_, String>(11)?
+                .parse::<chrono::DateTime<Utc>>()
+                .unwrap_or_else(|_| Utc::now()),
+            updated_at: row.get::<_, String>(12)?
+                .parse::<chrono::DateTime<Utc>>()
+                .unwrap_or_else(|_| Utc::now()),
+        })
+    });
+
+    match result {
+        Ok(chart) => Ok(Some(chart)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 환자별 초진차트 목록 조회
+pub fn get_initial_charts_by_patient(patient_id: &str) -> AppResult<Vec<InitialChart>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let mut stmt = conn.prepare(
+        r#"SELECT id, patient_id, doctor_name, chart_date, chief_complaint, present_illness, past_medical_history, notes, prescription_issued, prescription_issued_at, deleted_at, created_at, updated_at
+           FROM initial_charts WHERE patient_id = ?1 AND deleted_at IS NULL ORDER BY chart_date DESC"#,
+    )?;
+
+    let rows = stmt.query_map([patient_id], |row| {
+        Ok(InitialChart {
+            id: row.get(0)?,
+            patient_id: row.get(1)?,
+            doctor_name: row.get(2)?,
+            chart_date: row.get(3)?,
+            chief_complaint: row.get(4)?,
+            present_illness: row.get(5)?,
+            past_medical_history: row.get(6)?,
+            notes: row.get(7)?,
+            prescription_issued: row.get::<_, i32>(8)? != 0,
+            prescription_issued_at: row.get(9)?,
+            deleted_at: row.get(10)?,
+            created_at: row.get::<_, String>(11)?
+                .parse::<chrono::DateTime<Utc>>()
+                .unwrap_or_else(|_| Utc::now()),
+            updated_at: row.get::<_, String>(12)?
+                .parse::<chrono::DateTime<Utc>>()
+                .unwrap_or_else(|_| Utc::now()),
+        })
+    })?;
+
+    let mut charts = Vec::new();
+    for row in rows {
+        charts.push(row?);
+    }
+    Ok(charts)
+}
+
+/// 모든 초진차트 목록 조회 (환자 이름 포함)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct InitialChartWithPatient {
+    #[serde(flatten)]
+    pub chart: InitialChart,
+    pub patient_name: String,
+}
+
+pub fn list_initial_charts() -> AppResult<Vec<InitialChartWithPatient>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let mut stmt = conn.prepare(
+        r#"SELECT ic.id, ic.patient_id, ic.doctor_name, ic.chart_date, ic.chief_complaint, ic.present_illness, ic.past_medical_history, ic.notes, ic.prescription_issued, ic.prescription_issued_at, ic.deleted_at, ic.created_at, ic.updated_at, p.name as patient_name
+           FROM initial_charts ic
+           LEFT JOIN patients p ON ic.patient_id = p.id
+           WHERE ic.deleted_at IS NULL
+           ORDER BY ic.chart_date DESC"#,
+    )?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok(InitialChartWithPatient {
+            chart: InitialChart {
+                id: row.get(0)?,
+                patient_id: row.get(1)?,
+                doctor_name: row.get(2)?,
+                chart_date: row.get(3)?,
+                chief_complaint: row.get(4)?,
+                present_illness: row.get(5)?,
+                past_medical_history: row.get(6)?,
+                notes: row.get(7)?,
+                prescription_issued: row.get::<_, i32>(8)? != 0,
+                prescription_issued_at: row.get(9)?,
+                deleted_at: row.get(10)?,
+                created_at: row.get::<_, String>(11)?
+                    .parse::<chrono::DateTime<Utc>>()
+                    .unwrap_or_else(|_| Utc::now()),
+                updated_at: row.get::<_, String>(12)?
+                    .parse::<chrono::DateTime<Utc>>()
+                    .unwrap_or_else(|_| Utc::now()),
+            },
+            patient_name: row.get(13)?,
+        })
+    })?;
+
+    let mut charts = Vec::new();
+    for row in rows {
+        charts.push(row?);
+    }
+    Ok(charts)
+}
+
+/// 초진차트 수정
+pub fn update_initial_chart(chart: &InitialChart) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    conn.execute(
+        r#"UPDATE initial_charts SET
+           doctor_name = ?2, chart_date = ?3, chief_complaint = ?4, present_illness = ?5,
+           past_medical_history = ?6, notes = ?7, prescription_issued = ?8, prescription_issued_at = ?9,
+           updated_at = ?10
+           WHERE id = ?1"#,
+        params![
+            chart.id,
+            chart.doctor_name,
+            chart.chart_date,
+            chart.chief_complaint,
+            chart.present_illness,
+            chart.past_medical_history,
+            chart.notes,
+            if chart.prescription_issued { 1 } else { 0 },
+            chart.prescription_issued_at,
+            Utc::now().to_rfc3339(),
+        ],
+    )?;
+    Ok(())
+}
+
+/// 초진차트 소프트 삭제
+pub fn delete_initial_chart(id: &str) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute(
+        "UPDATE initial_charts SET deleted_at = ?2 WHERE id = ?1",
+        params![id, Utc::now().to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+// ============ 경과기록 관리 ============
+
+/// 경과기록 생성
+pub fn create_progress_note(note: &ProgressNote) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    conn.execute(
+        r#"INSERT INTO progress_notes (id, patient_id, doctor_name, note_date, subjective, objective, assessment, plan, follow_up_plan, notes, prescription_issued, prescription_issued_at, deleted_at, created_at, updated_at)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)"#,
+        params![
+            note.id,
+            note.patient_id,
+            note.doctor_name,
+            note.note_date,
+            note.subjective,
+            note.objective,
+            note.assessment,
+            note.plan,
+            note.follow_up_plan,
+            note.notes,
+            if note.prescription_issued { 1 } else { 0 },
+            note.prescription_issued_at,
+            note.deleted_at,
+            note.created_at.to_rfc3339(),
+            note.updated_at.to_rfc3339(),
+        ],
+    )?;
+    Ok(())
+}
+
+/// 경과기록 조회
+pub fn get_progress_note(id: &str) -> AppResult<Option<ProgressNote>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let mut stmt = conn.prepare(
+        r#"SELECT id, patient_id, doctor_name, note_date, subjective, objective, assessment, plan, follow_up_plan, notes, prescription_issued, prescription_issued_at, deleted_at, created_at, updated_at
+           FROM progress_notes WHERE id = ?1 AND deleted_at IS NULL"#,
+    )?;
+
+    let result = stmt.query_row([id], |row| {
+        Ok(ProgressNote {
+            id: row.get(0)?,
+            patient_id: row.get(1)?,
+            doctor_name: row.get(2)?,
+            note_date: row.get(3)?,
+            subjective: row.get(4)?,
+            objective: row.get(5)?,
+            assessment: row.get(6)?,
+            plan: row.get(7)?,
+            follow_up_plan: row.get(8)?,
+            notes: row.get(9)?,
+            prescription_issued: row.get::<_, i32>(10)? != 0,
+            prescription_issued_at: row.get(11)?,
+            deleted_at: row.get(12)?,
+            created_at: row.get::<_, String>(13)?
+                .parse::<chrono::DateTime<Utc>>()
+                .unwrap_or_else(|_| Utc::now()),
+            updated_at: row.get::<_, String>(14)?
+                .parse::<chrono::DateTime<Utc>>()
+                .unwrap_or_else(|_| Utc::now()),
+        })
+    });
+
+    match result {
+        Ok(note) => Ok(Some(note)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 환자별 경과기록 목록 조회
+pub fn get_progress_notes_by_patient(patient_id: &str) -> AppResult<Vec<ProgressNote>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let mut stmt = conn.prepare(
+        r#"SELECT id, patient_id, doctor_name, note_date, subjective, objective, assessment, plan, follow_up_plan, notes, prescription_issued, prescription_issued_at, deleted_at, created_at, updated_at
+           FROM progress_notes WHERE patient_id = ?1 AND deleted_at IS NULL ORDER BY note_date DESC"#,
+    )?;
+
+    let rows = stmt.query_map([patient_id], |row| {
+        Ok(ProgressNote {
+            id: row.get(0)?,
+            patient_id: row.get(1)?,
+            doctor_name: row.get(2)?,
+            note_date: row.get(3)?,
+            subjective: row.get(4)?,
+            objective: row.get(5)?,
+            assessment: row.get(6)?,
+            plan: row.get(7)?,
+            follow_up_plan: row.get(8)?,
+            notes: row.get(9)?,
+            prescription_issued: row.get::<_, i32>(10)? != 0,
+            prescription_issued_at: row.get(11)?,
+            deleted_at: row.get(12)?,
+            created_at: row.get::<_, String>(13)?
+                .parse::<chrono::DateTime<Utc>>()
+                .unwrap_or_else(|_| Utc::now()),
+            updated_at: row.get::<_, String>(14)?
+                .parse::<chrono::DateTime<Utc>>()
+                .unwrap_or_else(|_| Utc::now()),
+        })
+    })?;
+
+    let mut notes = Vec::new();
+    for row in rows {
+        notes.push(row?);
+    }
+    Ok(notes)
+}
+
+/// 경과기록 수정
+pub fn update_progress_note(note: &ProgressNote) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    conn.execute(
+        r#"UPDATE progress_notes SET
+           doctor_name = ?2, note_date = ?3, subjective = ?4, objective = ?5,
+           assessment = ?6, plan = ?7, follow_up_plan = ?8, notes = ?9,
+           prescription_issued = ?10, prescription_issued_at = ?11, updated_at = ?12
+           WHERE id = ?1"#,
+        params![
+            note.id,
+            note.doctor_name,
+            note.note_date,
+            note.subjective,
+            note.objective,
+            note.assessment,
+            note.plan,
+            note.follow_up_plan,
+            note.notes,
+            if note.prescription_issued { 1 } else { 0 },
+            note.prescription_issued_at,
+            Utc::now().to_rfc3339(),
+        ],
+    )?;
+    Ok(())
+}
+
+/// 경과기록 소프트 삭제
+pub fn delete_progress_note(id: &str) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute(
+        "UPDATE progress_notes SET deleted_at = ?2 WHERE id = ?1",
+        params![id, Utc::now().to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+// ============ 복약 일정 관리 ============
+
+use crate::models::{MedicationSchedule, MedicationLog, MedicationStatus, MedicationStats};
+
+/// 복약 일정 목록 조회
+pub fn list_medication_schedules() -> AppResult<Vec<MedicationSchedule>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let mut stmt = conn.prepare(
+        r#"SELECT id, patient_id, prescription_id, start_date, end_date, times_per_day, medication_times, notes, created_at,
+                  (SELECT COUNT(*) FROM medication_logs ml WHERE ml.schedule_id = medication_schedules.id AND ml.status = 'taken')
+           FROM medication_schedules ORDER BY created_at DESC"#,
+    )?;
+
+    let rows = stmt.query_map([], |row| {
+        let medication_times_json: String = row.get(6)?;
+        let medication_times: Vec<String> = serde_json::from_str(&medication_times_json).unwrap_or_default();
+        Ok(MedicationSchedule {
+            id: row.get(0)?,
+            patient_id: row.get(1)?,
+            prescription_id: row.get(2)?,
+            start_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(3)?)
+                .unwrap()
+                .with_timezone(&Utc),
+            end_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?)
+                .unwrap()
+                .with_timezone(&Utc),
+            times_per_day: row.get(5)?,
+            medication_times,
+            notes: row.get(7)?,
+            created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?)
+                .unwrap()
+                .with_timezone(&Utc),
+            is_completed: false,
+        }
+        .with_completion(row.get(9)?))
+    })?;
+
+    let mut schedules = Vec::new();
+    for row in rows {
+        schedules.push(row?);
+    }
+    Ok(schedules)
+}
+
+/// 복약 일정 조회 (ID로)
+pub fn get_medication_schedule(id: &str) -> AppResult<Option<MedicationSchedule>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let mut stmt = conn.prepare(
+        r#"SELECT id, patient_id, prescription_id, start_date, end_date, times_per_day, medication_times, notes, created_at,
+                  (SELECT COUNT(*) FROM medication_logs ml WHERE ml.schedule_id = medication_schedules.id AND ml.status = 'taken')
+           FROM medication_schedules WHERE id = ?1"#,
+    )?;
+
+    let result = stmt.query_row([id], |row| {
+        let medication_times_json: String = row.get(6)?;
+        let medication_times: Vec<String> = serde_json::from_str(&medication_times_json).unwrap_or_default();
+        Ok(MedicationSchedule {
+            id: row.get(0)?,
+            patient_id: row.get(1)?,
+            prescription_id: row.get(2)?,
+            start_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(3)?)
+                .unwrap()
+                .with_timezone(&Utc),
+            end_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?)
+                .unwrap()
+                .with_timezone(&Utc),
+            times_per_day: row.get(5)?,
+            medication_times,
+            notes: row.get(7)?,
+            created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?)
+                .unwrap()
+                .with_timezone(&Utc),
+            is_completed: false,
+        }
+        .with_completion(row.get(9)?))
+    });
+
+    match result {
+        Ok(schedule) => Ok(Some(schedule)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 환자별 복약 일정 조회
+pub fn get_medication_schedules_by_patient(patient_id: &str) -> AppResult<Vec<MedicationSchedule>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let mut stmt = conn.prepare(
+        r#"SELECT id, patient_id, prescription_id, start_date, end_date, times_per_day, medication_times, notes, created_at,
+                  (SELECT COUNT(*) FROM medication_logs ml WHERE ml.schedule_id = medication_schedules.id AND ml.status = 'taken')
+           FROM medication_schedules WHERE patient_id = ?1 ORDER BY created_at DESC"#,
+    )?;
+
+    let rows = stmt.query_map([patient_id], |row| {
+        let medication_times_json: String = row.get(6)?;
+        let medication_times: Vec<String> = serde_json::from_str(&medication_times_json).unwrap_or_default();
+        Ok(MedicationSchedule {
+            id: row.get(0)?,
+            patient_id: row.get(1)?,
+            prescription_id: row.get(2)?,
+            start_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(3)?)
+                .unwrap()
+                .with_timezone(&Utc),
+            end_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?)
+                .unwrap()
+                .with_timezone(&Utc),
+            times_per_day: row.get(5)?,
+            medication_times,
+            notes: row.get(7)?,
+            created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?)
+                .unwrap()
+                .with_timezone(&Utc),
+            is_completed: false,
+        }
+        .with_completion(row.get(9)?))
+    })?;
+
+    let mut schedules = Vec::new();
+    for row in rows {
+        schedules.push(row?);
+    }
+    Ok(schedules)
+}
+
+/// 환자별 복용 완료 일정 조회 (종료일 경과 + 예정 횟수만큼 복용)
+pub fn get_completed_schedules(patient_id: &str) -> AppResult<Vec<MedicationSchedule>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let mut stmt = conn.prepare(
+        r#"SELECT id, patient_id, prescription_id, start_date, end_date, times_per_day, medication_times, notes, created_at,
+                  (SELECT COUNT(*) FROM medication_logs ml WHERE ml.schedule_id = medication_schedules.id AND ml.status = 'taken')
+           FROM medication_schedules WHERE patient_id = ?1 AND end_date < ?2 ORDER BY end_date DESC"#,
+    )?;
+
+    let now = Utc::now().to_rfc3339();
+    let rows = stmt.query_map(params![patient_id, now], |row| {
+        let medication_times_json: String = row.get(6)?;
+        let medication_times: Vec<String> = serde_json::from_str(&medication_times_json).unwrap_or_default();
+        Ok(MedicationSchedule {
+            id: row.get(0)?,
+            patient_id: row.get(1)?,
+            prescription_id: row.get(2)?,
+            start_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(3)?)
+                .unwrap()
+                .with_timezone(&Utc),
+            end_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?)
+                .unwrap()
+                .with_timezone(&Utc),
+            times_per_day: row.get(5)?,
+            medication_times,
+            notes: row.get(7)?,
+            created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?)
+                .unwrap()
+                .with_timezone(&Utc),
+            is_completed: false,
+        }
+        .with_completion(row.get(9)?))
+    })?;
+
+    let mut schedules = Vec::new();
+    for row in rows {
+        let schedule = row?;
+        if schedule.is_completed {
+            schedules.push(schedule);
+        }
+    }
+    Ok(schedules)
+}
+
+/// 복용 시간 검증 및 정규화 ("8:5" → "08:05", 정렬, 중복 제거)
+fn normalize_medication_times(times: &[String]) -> AppResult<Vec<String>> {
+    let mut normalized = Vec::with_capacity(times.len());
+    for time in times {
+        let parsed = chrono::NaiveTime::parse_from_str(time.trim(), "%H:%M")
+            .map_err(|_| AppError::Validation(format!("복용 시간 형식이 올바르지 않습니다 (HH:MM): '{}'", time)))?;
+        normalized.push(parsed.format("%H:%M").to_string());
+    }
+    normalized.sort();
+    normalized.dedup();
+
+    if normalized.is_empty() {
+        return Err(AppError::Validation("복용 시간을 하나 이상 입력해주세요".to_string()));
+    }
+    Ok(normalized)
+}
+
+/// 저장 전 복약 일정 정규화 (하루 복용 횟수는 복용 시간 개수로 맞춤)
+fn normalize_medication_schedule(schedule: &MedicationSchedule) -> AppResult<MedicationSchedule> {
+    let medication_times = normalize_medication_times(&schedule.medication_times)?;
+    let mut normalized = schedule.clone();
+    normalized.times_per_day = medication_times.len() as i32;
+    normalized.medication_times = medication_times;
+    Ok(normalized)
+}
+
+/// 복약 일정 생성
+pub fn create_medication_schedule(schedule: &MedicationSchedule) -> AppResult<()> {
+    let schedule = &normalize_medication_schedule(schedule)?;
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let medication_times_json = serde_json::to_string(&schedule.medication_times)?;
+
+    conn.execute(
+        r#"INSERT INTO medication_schedules (id, patient_id, prescription_id, start_date, end_date, times_per_day, medication_times, notes, created_at)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)"#,
+        params![
+            schedule.id,
+            schedule.patient_id,
+            schedule.prescription_id,
+            schedule.start_date.to_rfc3339(),
+            schedule.end_date.to_rfc3339(),
+            schedule.times_per_day,
+            medication_times_json,
+            schedule.notes,
+            schedule.created_at.to_rfc3339(),
+        ],
+    )?;
+
+    log::info!("복약 일정 생성됨: {}", schedule.id);
+    Ok(())
+}
+
+/// 복약 일정 수정
+pub fn update_medication_schedule(id: &str, schedule: &MedicationSchedule) -> AppResult<()> {
+    let schedule = &normalize_medication_schedule(schedule)?;
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let medication_times_json = serde_json::to_string(&schedule.medication_times)?;
+
+    conn.execute(
+        r#"UPDATE medication_schedules SET
+           patient_id = ?2, prescription_id = ?3, start_date = ?4, end_date = ?5,
+           times_per_day = ?6, medication_times = ?7, notes = ?8
+           WHERE id = ?1"#,
+        params![
+            id,
+            schedule.patient_id,
+            schedule.prescription_id,
+            schedule.start_date.to_rfc3339(),
+            schedule.end_date.to_rfc3339(),
+            schedule.times_per_day,
+            medication_times_json,
+            schedule.notes,
+        ],
+    )?;
+
+    log::info!("복약 일정 수정됨: {}", id);
+    Ok(())
+}
+
+/// 복약 일정 삭제
+pub fn delete_medication_schedule(id: &str) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    // 연관된 복약 기록도 삭제
+    conn.execute("DELETE FROM medication_logs WHERE schedule_id = ?1", [id])?;
+    conn.execute("DELETE FROM medication_schedules WHERE id = ?1", [id])?;
+
+    log::info!("복약 일정 삭제됨: {}", id);
+    Ok(())
+}
+
+// ============ 복약 기록 관리 ============
+
+/// 복약 기록 목록 조회
+pub fn list_medication_logs() -> AppResult<Vec<MedicationLog>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let mut stmt = conn.prepare(
+        r#"SELECT id, schedule_id, taken_at, status, notes
+           FROM medication_logs ORDER BY taken_at DESC"#,
+    )?;
+
+    let rows = stmt.query_map([], |row| {
+        let status_str: String = row.get(3)?;
+        let status = match status_str.as_str() {
+            "taken" => MedicationStatus::Taken,
+            "missed" => MedicationStatus::Missed,
+            "skipped" => MedicationStatus::Skipped,
+            _ => MedicationStatus::Taken,
+        };
+        Ok(MedicationLog {
+            id: row.get(0)?,
+            schedule_id: row.get(1)?,
+            taken_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(2)?)
+                .unwrap()
+                .with_timezone(&Utc),
+            status,
+            notes: row.get(4)?,
+        })
+    })?;
+
+    let mut logs = Vec::new();
+    for row in rows {
+        logs.push(row?);
+    }
+    Ok(logs)
+}
+
+/// 복약 기록 조회 (ID로)
+pub fn get_medication_log(id: &str) -> AppResult<Option<MedicationLog>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let mut stmt = conn.prepare(
+        r#"SELECT id, schedule_id, taken_at, status, notes
+           FROM medication_logs WHERE id = ?1"#,
+    )?;
+
+    let result = stmt.query_row([id], |row| {
+        let status_str: String = row.get(3)?;
+        let status = match status_str.as_str() {
+            "taken" => MedicationStatus::Taken,
+            "missed" => MedicationStatus::Missed,
+            "skipped" => MedicationStatus::Skipped,
+            _ => MedicationStatus::Taken,
+        };
+        Ok(MedicationLog {
+            id: row.get(0)?,
+            schedule_id: row.get(1)?,
+            taken_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(2)?)
+                .unwrap()
+                .with_timezone(&Utc),
+            status,
+            notes: row.get(4)?,
+        })
+    });
+
+    match result {
+        Ok(log) => Ok(Some(log)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 일정별 복약 기록 조회
+pub fn get_medication_logs_by_schedule(schedule_id: &str) -> AppResult<Vec<MedicationLog>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let mut stmt = conn.prepare(
+        r#"SELECT id, schedule_id, taken_at, status, notes
+           FROM medication_logs WHERE schedule_id = ?1 ORDER BY taken_at DESC"#,
+    )?;
+
+    let rows = stmt.query_map([schedule_id], |row| {
+        let status_str: String = row.get(3)?;
+        let status = match status_str.as_str() {
+            "taken" => MedicationStatus::Taken,
+            "missed" => MedicationStatus::Missed,
+            "skipped" => MedicationStatus::Skipped,
+            _ => MedicationStatus::Taken,
+        };
+        Ok(MedicationLog {
+            id: row.get(0)?,
+            schedule_id: row.get(1)?,
+            taken_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(2)?)
+                .unwrap()
+                .with_timezone(&Utc),
+            status,
+            notes: row.get(4)?,
+        })
+    })?;
+
+    let mut logs = Vec::new();
+    for row in rows {
+        logs.push(row?);
+    }
+    Ok(logs)
+}
+
+/// 복약 기록 생성
+pub fn create_medication_log(log: &MedicationLog) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let status_str = match log.status {
+        MedicationStatus::Taken => "taken",
+        MedicationStatus::Missed => "missed",
+        MedicationStatus::Skipped => "skipped",
+    };
+
+    conn.execute(
+        r#"INSERT INTO medication_logs (id, schedule_id, taken_at, status, notes)
+           VALUES (?1, ?2, ?3, ?4, ?5)"#,
+        params![
+            log.id,
+            log.schedule_id,
+            log.taken_at.to_rfc3339(),
+            status_str,
+            log.notes,
+        ],
+    )?;
+
+    log::info!("복약 기록 생성됨: {}", log.id);
+    Ok(())
+}
+
+/// 복약 기록 수정
+pub fn update_medication_log(id: &str, log: &MedicationLog) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let status_str = match log.status {
+        MedicationStatus::Taken => "taken",
+        MedicationStatus::Missed => "missed",
+        MedicationStatus::Skipped => "skipped",
+    };
+
+    conn.execute(
+        r#"UPDATE medication_logs SET
+           schedule_id = ?2, taken_at = ?3, status = ?4, notes = ?5
+           WHERE id = ?1"#,
+        params![
+            id,
+            log.schedule_id,
+            log.taken_at.to_rfc3339(),
+            status_str,
+            log.notes,
+        ],
+    )?;
+
+    log::info!("복약 기록 수정됨: {}", id);
+    Ok(())
+}
+
+/// 환자별 복약 통계 조회
+pub fn get_medication_stats_by_patient(patient_id: &str) -> AppResult<MedicationStats> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    // 전체 일정 수
+    let total_schedules: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM medication_schedules WHERE patient_id = ?1",
+        [patient_id],
+        |row| row.get(0),
+    )?;
+
+    // 활성 일정 수 (종료일이 현재 시간 이후)
+    let now = Utc::now().to_rfc3339();
+    let active_schedules: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM medication_schedules WHERE patient_id = ?1 AND end_date > ?2",
+        params![patient_id, now],
+        |row| row.get(0),
+    )?;
+
+    // 복약 기록 통계
+    let total_logs: i32 = conn.query_row(
+        r#"SELECT COUNT(*) FROM medication_logs ml
+           JOIN medication_schedules ms ON ml.schedule_id = ms.id
+           WHERE ms.patient_id = ?1"#,
+        [patient_id],
+        |row| row.get(0),
+    )?;
+
+    let taken_count: i32 = conn.query_row(
+        r#"SELECT COUNT(*) FROM medication_logs ml
+           JOIN medication_schedules ms ON ml.schedule_id = ms.id
+           WHERE ms.patient_id = ?1 AND ml.status = 'taken'"#,
+        [patient_id],
+        |row| row.get(0),
+    )?;
+
+    let missed_count: i32 = conn.query_row(
+        r#"SELECT COUNT(*) FROM medication_logs ml
+           JOIN medication_schedules ms ON ml.schedule_id = ms.id
+           WHERE ms.patient_id = ?1 AND ml.status = 'missed'"#,
+        [patient_id],
+        |row| row.get(0),
+    )?;
+
+    let skipped_count: i32 = conn.query_row(
+        r#"SELECT COUNT(*) FROM medication_logs ml
+           JOIN medication_schedules ms ON ml.schedule_id = ms.id
+           WHERE ms.patient_id = ?1 AND ml.status = 'skipped'"#,
+        [patient_id],
+        |row| row.get(0),
+    )?;
+
+    // 복약 순응률 계산 (복용한 횟수 / 전체 기록 수 * 100)
+    let compliance_rate = if total_logs > 0 {
+        (taken_count as f64 / total_logs as f64) * 100.0
+    } else {
+        0.0
+    };
+
+    Ok(MedicationStats {
+        patient_id: patient_id.to_string(),
+        total_schedules,
+        active_schedules,
+        total_logs,
+        taken_count,
+        missed_count,
+        skipped_count,
+        compliance_rate,
+    })
+}
+
+/// 복약 시간대 (시작 시각, 이름) - 11시 전은 아침, 16시 전은 점심, 이후는 저녁
+const MEDICATION_TIME_SLOTS: [(u32, &str); 3] = [(0, "아침"), (11, "점심"), (16, "저녁")];
+
+/// 복용 시각(하루 중 분)이 속한 시간대 인덱스
+fn medication_time_slot(minute_of_day: u32) -> usize {
+    MEDICATION_TIME_SLOTS
+        .iter()
+        .rposition(|(start_hour, _)| minute_of_day >= start_hour * 60)
+        .unwrap_or(0)
+}
+
+/// 시간대별 복약 순응도 (아침/점심/저녁 순, 기록이 없는 시간대는 0.0)
+///
+/// 각 기록은 일정의 복용 시간 중 기록 시각과 가장 가까운 시간에 매칭해 시간대를 정하며,
+/// 순응도는 전체 통계와 같이 `복용 기록 수 / 전체 기록 수 * 100`.
+pub fn get_adherence_by_time_of_day(patient_id: &str) -> AppResult<Vec<(String, f64)>> {
+    use chrono::Timelike;
+
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let mut stmt = conn.prepare(
+        r#"SELECT ml.taken_at, ml.status, ms.medication_times
+           FROM medication_logs ml
+           JOIN medication_schedules ms ON ml.schedule_id = ms.id
+           WHERE ms.patient_id = ?1"#,
+    )?;
+    let logs = stmt
+        .query_map([patient_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // 시간대별 (복용, 전체)
+    let mut counts = [(0u32, 0u32); MEDICATION_TIME_SLOTS.len()];
+    for (taken_at, status, times_json) in logs {
+        let Ok(taken_at) = chrono::DateTime::parse_from_rfc3339(&taken_at) else {
+            continue;
+        };
+        let local = taken_at.with_timezone(&chrono::Local);
+        let logged_minute = local.hour() * 60 + local.minute();
+
+        // 하루를 넘는 차이는 자정 기준으로 가까운 쪽 (23:50 기록은 00:10 복용 시간에 매칭)
+        let distance = |minute: u32| {
+            let diff = minute.abs_diff(logged_minute);
+            diff.min(24 * 60 - diff)
+        };
+        let scheduled_minute = serde_json::from_str::<Vec<String>>(&times_json)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|t| chrono::NaiveTime::parse_from_str(t.trim(), "%H:%M").ok())
+            .map(|t| t.hour() * 60 + t.minute())
+            .min_by_key(|&minute| distance(minute))
+            .unwrap_or(logged_minute);
+
+        let slot = &mut counts[medication_time_slot(scheduled_minute)];
+        slot.1 += 1;
+        if status == "taken" {
+            slot.0 += 1;
+        }
+    }
+
+    Ok(MEDICATION_TIME_SLOTS
+        .iter()
+        .zip(counts)
+        .map(|((_, name), (taken, total))| {
+            let rate = if total > 0 { taken as f64 / total as f64 * 100.0 } else { 0.0 };
+            (name.to_string(), rate)
+        })
+        .collect())
+}
+
+// ============ 처방 카테고리 ============
+// (알림 관련 함수 제거됨)
+
+// ============ 처방 카테고리 ============
+
+pub fn list_prescription_categories() -> AppResult<Vec<PrescriptionCategory>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, name, color, sort_order, created_at FROM prescription_categories ORDER BY sort_order, name"
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(PrescriptionCategory {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            color: row.get(2)?,
+            sort_order: row.get(3)?,
+            created_at: row.get(4)?,
+        })
+    })?;
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row?);
+    }
+    Ok(result)
+}
+
+pub fn create_prescription_category(cat: &PrescriptionCategory) -> AppResult<i64> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute(
+        "INSERT INTO prescription_categories (name, color, sort_order, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![cat.name, cat.color, cat.sort_order, cat.created_at],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn update_prescription_category(cat: &PrescriptionCategory) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute(
+        "UPDATE prescription_categories SET name = ?1, color = ?2, sort_order = ?3 WHERE id = ?4",
+        params![cat.name, cat.color, cat.sort_order, cat.id],
+    )?;
+    Ok(())
+}
+
+pub fn delete_prescription_category(id: i64) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute("DELETE FROM prescription_categories WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+// ============ 약재 ============
+
+pub fn list_herbs() -> AppResult<Vec<Herb>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, name, default_dosage, unit, description, created_at FROM herbs ORDER BY name"
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(Herb {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            default_dosage: row.get(2)?,
+            unit: row.get(3)?,
+            description: row.get(4)?,
+            created_at: row.get(5)?,
+        })
+    })?;
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row?);
+    }
+    Ok(result)
+}
+
+pub fn create_herb(herb: &Herb) -> AppResult<i64> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute(
+        "INSERT INTO herbs (name, default_dosage, unit, description, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![herb.name, herb.default_dosage, herb.unit, herb.description, herb.created_at],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn update_herb(herb: &Herb) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute(
+        "UPDATE herbs SET name = ?1, default_dosage = ?2, unit = ?3, description = ?4 WHERE id = ?5",
+        params![herb.name, herb.default_dosage, herb.unit, herb.description, herb.id],
+    )?;
+    Ok(())
+}
+
+pub fn delete_herb(id: i64) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute("DELETE FROM herbs WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+// ============ 처방 정의 ============
+
+pub fn list_prescription_definitions() -> AppResult<Vec<PrescriptionDefinition>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, name, alias, category, source, composition, description, created_at, updated_at FROM prescription_definitions ORDER BY name"
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(PrescriptionDefinition {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            alias: row.get(2)?,
+            category: row.get(3)?,
+            source: row.get(4)?,
+            composition: row.get(5)?,
+            description: row.get(6)?,
+            created_at: row.get(7)?,
+            updated_at: row.get(8)?,
+        })
+    })?;
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row?);
+    }
+    Ok(result)
+}
+
+pub fn get_prescription_definition(id: i64) -> AppResult<Option<PrescriptionDefinition>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let result = conn.query_row(
+        "SELECT id, name, alias, category, source, composition, description, created_at, updated_at FROM prescription_definitions WHERE id = ?1",
+        params![id],
+        |row| {
+            Ok(PrescriptionDefinition {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                alias: row.get(2)?,
+                category: row.get(3)?,
+                source: row.get(4)?,
+                composition: row.get(5)?,
+                description: row.get(6)?,
+                created_at: row.get(7)?,
+                updated_at: row.get(8)?,
+            })
+        },
+    );
+    match result {
+        Ok(def) => Ok(Some(def)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn create_prescription_definition(def: &PrescriptionDefinition) -> AppResult<i64> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO prescription_definitions (name, alias, category, source, composition, description, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![def.name, def.alias, def.category, def.source, def.composition, def.description, now, now],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn update_prescription_definition(def: &PrescriptionDefinition) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "UPDATE prescription_definitions SET name = ?1, alias = ?2, category = ?3, source = ?4, composition = ?5, description = ?6, updated_at = ?7 WHERE id = ?8",
+        params![def.name, def.alias, def.category, def.source, def.composition, def.description, now, def.id],
+    )?;
+    Ok(())
+}
+
+pub fn delete_prescription_definition(id: i64) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute("DELETE FROM prescription_definitions WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+// ============ 처방 노트 ============
+
+pub fn list_prescription_notes(prescription_definition_id: i64) -> AppResult<Vec<PrescriptionNote>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, prescription_definition_id, content, created_at, updated_at FROM prescription_notes WHERE prescription_definition_id = ?1 ORDER BY created_at DESC"
+    )?;
+    let rows = stmt.query_map(params![prescription_definition_id], |row| {
+        Ok(PrescriptionNote {
+            id: row.get(0)?,
+            prescription_definition_id: row.get(1)?,
+            content: row.get(2)?,
+            created_at: row.get(3)?,
+            updated_at: row.get(4)?,
+        })
+    })?;
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row?);
+    }
+    Ok(result)
+}
+
+pub fn create_prescription_note(note: &PrescriptionNote) -> AppResult<i64> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO prescription_notes (prescription_definition_id, content, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
+        params![note.prescription_definition_id, note.content, now, now],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn update_prescription_note(note: &PrescriptionNote) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "UPDATE prescription_notes SET content = ?1, updated_at = ?2 WHERE id = ?3",
+        params![note.content, now, note.id],
+    )?;
+    Ok(())
+}
+
+pub fn delete_prescription_note(id: i64) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute("DELETE FROM prescription_notes WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+// ============ 처방 치험례 ============
+
+pub fn list_prescription_case_studies(prescription_definition_id: i64) -> AppResult<Vec<PrescriptionCaseStudy>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, prescription_definition_id, title, content, created_at, updated_at FROM prescription_case_studies WHERE prescription_definition_id = ?1 ORDER BY created_at DESC"
+    )?;
+    let rows = stmt.query_map(params![prescription_definition_id], |row| {
+        Ok(PrescriptionCaseStudy {
+            id: row.get(0)?,
+            prescription_definition_id: row.get(1)?,
+            title: row.get(2)?,
+            content: row.get(3)?,
+            created_at: row.get(4)?,
+            updated_at: row.get(5)?,
+        })
+    })?;
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row?);
+    }
+    Ok(result)
+}
+
+pub fn create_prescription_case_study(cs: &PrescriptionCaseStudy) -> AppResult<i64> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO prescription_case_studies (prescription_definition_id, title, content, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![cs.prescription_definition_id, cs.title, cs.content, now, now],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn update_prescription_case_study(cs: &PrescriptionCaseStudy) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "UPDATE prescription_case_studies SET title = ?1, content = ?2, updated_at = ?3 WHERE id = ?4",
+        params![cs.title, cs.content, now, cs.id],
+    )?;
+    Ok(())
+}
+
+pub fn delete_prescription_case_study(id: i64) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute("DELETE FROM prescription_case_studies WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+// ============ 복약 관리 (해피콜) ============
+
+pub fn list_medication_management() -> AppResult<Vec<MedicationManagement>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, prescription_id, patient_id, patient_name, prescription_name, prescription_date, days, delivery_days, start_date, end_date, happy_call_date, status, notes, postpone_count, postponed_to, contacted_at, created_at, updated_at FROM medication_management ORDER BY happy_call_date ASC"
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(MedicationManagement {
+            id: row.get(0)?,
+            prescription_id: row.get(1)?,
+            patient_id: row.get(2)?,
+            patient_name: row.get(3)?,
+            prescription_name: row.get(4)?,
+            prescription_date: row.get(5)?,
+            days: row.get(6)?,
+            delivery_days: row.get(7)?,
+            start_date: row.get(8)?,
+            end_date: row.get(9)?,
+            happy_call_date: row.get(10)?,
+            status: row.get(11)?,
+            notes: row.get(12)?,
+            postpone_count: row.get(13)?,
+            postponed_to: row.get(14)?,
+            contacted_at: row.get(15)?,
+            created_at: row.get(16)?,
+            updated_at: row.get(17)?,
+        })
+    })?;
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row?);
+    }
+    Ok(result)
+}
+
+pub fn create_medication_management(mm: &MedicationManagement) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let now = Utc::now().to_rfc3339();
+    // FK 체크 일시 비활성화 (마이그레이션 잔류 이슈 회피)
+    conn.execute_batch("PRAGMA foreign_keys = OFF")?;
+    let result = conn.execute(
+        "INSERT INTO medication_management (id, prescription_id, patient_id, patient_name, prescription_name, prescription_date, days, delivery_days, start_date, end_date, happy_call_date, status, notes, postpone_count, postponed_to, contacted_at, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+        params![
+            mm.id, mm.prescription_id, mm.patient_id, mm.patient_name, mm.prescription_name,
+            mm.prescription_date, mm.days, mm.delivery_days, mm.start_date, mm.end_date,
+            mm.happy_call_date, mm.status, mm.notes, mm.postpone_count, mm.postponed_to,
+            mm.contacted_at, now, now
+        ],
+    );
+    conn.execute_batch("PRAGMA foreign_keys = ON")?;
+    result?;
+    Ok(())
+}
+
+pub fn update_medication_management(mm: &MedicationManagement) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "UPDATE medication_management SET status = ?1, notes = ?2, postpone_count = ?3, postponed_to = ?4, happy_call_date = ?5, contacted_at = ?6, updated_at = ?7 WHERE id = ?8",
+        params![mm.status, mm.notes, mm.postpone_count, mm.postponed_to, mm.happy_call_date, mm.contacted_at, now, mm.id],
+    )?;
+    Ok(())
+}
+
+pub fn delete_medication_management(id: &str) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute("DELETE FROM medication_management WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+// ============ 복약 스케줄 (커맨드용) ============
+
+pub fn list_medication_schedules_cmd(patient_id: Option<&str>) -> AppResult<Vec<MedicationSchedule>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let mut result = Vec::new();
+
+    if let Some(pid) = patient_id {
+        let mut stmt = conn.prepare(
+            "SELECT id, patient_id, prescription_id, start_date, end_date, times_per_day, medication_times, notes, created_at, (SELECT COUNT(*) FROM medication_logs ml WHERE ml.schedule_id = medication_schedules.id AND ml.status = 'taken') FROM medication_schedules WHERE patient_id = ?1 ORDER BY created_at DESC"
+        )?;
+        let rows = stmt.query_map(params![pid], |row| {
+            let times_json: String = row.get(6)?;
+            let medication_times: Vec<String> = serde_json::from_str(&times_json).unwrap_or_default();
+            Ok(MedicationSchedule {
+                id: row.get(0)?,
+                patient_id: row.get(1)?,
+                prescription_id: row.get(2)?,
+                start_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(3)?).unwrap().with_timezone(&Utc),
+                end_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?).unwrap().with_timezone(&Utc),
+                times_per_day: row.get(5)?,
+                medication_times,
+                notes: row.get(7)?,
+                created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?).unwrap().with_timezone(&Utc),
+                is_completed: false,
+            }
+            .with_completion(row.get(9)?))
+        })?;
+        for row in rows {
+            result.push(row?);
+        }
+    } else {
+        let mut stmt = conn.prepare(
+            "SELECT id, patient_id, prescription_id, start_date, end_date, times_per_day, medication_times, notes, created_at, (SELECT COUNT(*) FROM medication_logs ml WHERE ml.schedule_id = medication_schedules.id AND ml.status = 'taken') FROM medication_schedules ORDER BY created_at DESC"
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let times_json: String = row.get(6)?;
+            let medication_times: Vec<String> = serde_json::from_str(&times_json).unwrap_or_default();
+            Ok(MedicationSchedule {
+                id: row.get(0)?,
+                patient_id: row.get(1)?,
+                prescription_id: row.get(2)?,
+                start_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(3)?).unwrap().with_timezone(&Utc),
+                end_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?).unwrap().with_timezone(&Utc),
+                times_per_day: row.get(5)?,
+                medication_times,
+                notes: row.get(7)?,
+                created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?).unwrap().with_timezone(&Utc),
+                is_completed: false,
+            }
+            .with_completion(row.get(9)?))
+        })?;
+        for row in rows {
+            result.push(row?);
+        }
+    }
+    Ok(result)
+}
+
+pub fn get_medication_schedule_cmd(id: &str) -> AppResult<Option<MedicationSchedule>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let result = conn.query_row(
+        "SELECT id, patient_id, prescription_id, start_date, end_date, times_per_day, medication_times, notes, created_at, (SELECT COUNT(*) FROM medication_logs ml WHERE ml.schedule_id = medication_schedules.id AND ml.status = 'taken') FROM medication_schedules WHERE id = ?1",
+        params![id],
+        |row| {
+            let times_json: String = row.get(6)?;
+            let medication_times: Vec<String> = serde_json::from_str(&times_json).unwrap_or_default();
+            Ok(MedicationSchedule {
+                id: row.get(0)?,
+                patient_id: row.get(1)?,
+                prescription_id: row.get(2)?,
+                start_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(3)?).unwrap().with_timezone(&Utc),
+                end_date: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?).unwrap().with_timezone(&Utc),
+                times_per_day: row.get(5)?,
+                medication_times,
+                notes: row.get(7)?,
+                created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?).unwrap().with_timezone(&Utc),
+                is_completed: false,
+            }
+            .with_completion(row.get(9)?))
+        },
+    );
+    match result {
+        Ok(s) => Ok(Some(s)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn create_medication_schedule_cmd(schedule: &MedicationSchedule) -> AppResult<()> {
+    let schedule = &normalize_medication_schedule(schedule)?;
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let times_json = serde_json::to_string(&schedule.medication_times)?;
+    conn.execute(
+        "INSERT INTO medication_schedules (id, patient_id, prescription_id, start_date, end_date, times_per_day, medication_times, notes, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            schedule.id, schedule.patient_id, schedule.prescription_id,
+            schedule.start_date.to_rfc3339(), schedule.end_date.to_rfc3339(),
+            schedule.times_per_day, times_json, schedule.notes,
+            schedule.created_at.to_rfc3339()
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn update_medication_schedule_cmd(schedule: &MedicationSchedule) -> AppResult<()> {
+    let schedule = &normalize_medication_schedule(schedule)?;
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let times_json = serde_json::to_string(&schedule.medication_times)?;
+    conn.execute(
+        "UPDATE medication_schedules SET patient_id = ?1, prescription_id = ?2, start_date = ?3, end_date = ?4, times_per_day = ?5, medication_times = ?6, notes = ?7 WHERE id = ?8",
+        params![
+            schedule.patient_id, schedule.prescription_id,
+            schedule.start_date.to_rfc3339(), schedule.end_date.to_rfc3339(),
+            schedule.times_per_day, times_json, schedule.notes,
+            schedule.id
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn delete_medication_schedule_cmd(id: &str) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute("DELETE FROM medication_logs WHERE schedule_id = ?1", params![id])?;
+    conn.execute("DELETE FROM medication_schedules WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+// ============ 복약 기록 (커맨드용) ============
+
+pub fn list_medication_logs_cmd(schedule_id: &str) -> AppResult<Vec<MedicationLog>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, schedule_id, taken_at, status, notes FROM medication_logs WHERE schedule_id = ?1 ORDER BY taken_at DESC"
+    )?;
+    let rows = stmt.query_map(params![schedule_id], |row| {
+        let status_str: String = row.get(3)?;
+        let status = match status_str.as_str() {
+            "taken" => MedicationStatus::Taken,
+            "missed" => MedicationStatus::Missed,
+            "skipped" => MedicationStatus::Skipped,
+            _ => MedicationStatus::Taken,
+        };
+        Ok(MedicationLog {
+            id: row.get(0)?,
+            schedule_id: row.get(1)?,
+            taken_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(2)?).unwrap().with_timezone(&Utc),
+            status,
+            notes: row.get(4)?,
+        })
+    })?;
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row?);
+    }
+    Ok(result)
+}
+
+pub fn create_medication_log_cmd(log: &MedicationLog) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let status_str = match log.status {
+        MedicationStatus::Taken => "taken",
+        MedicationStatus::Missed => "missed",
+        MedicationStatus::Skipped => "skipped",
+    };
+    conn.execute(
+        "INSERT INTO medication_logs (id, schedule_id, taken_at, status, notes) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![log.id, log.schedule_id, log.taken_at.to_rfc3339(), status_str, log.notes],
+    )?;
+    Ok(())
+}
+
+pub fn update_medication_log_cmd(id: &str, status: &str, notes: Option<&str>) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute(
+        "UPDATE medication_logs SET status = ?1, notes = ?2 WHERE id = ?3",
+        params![status, notes, id],
+    )?;
+    Ok(())
+}
+
+pub fn delete_medication_log_cmd(id: &str) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute("DELETE FROM medication_logs WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+// ============ 사용량 카운트 (플랜 제한용) ============
+
+pub fn get_usage_counts() -> AppResult<(i32, i32, i32)> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let patient_count: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM patients",
+        [],
+        |row| row.get(0),
+    )?;
+
+    let now = Utc::now();
+    let first_day_of_month = format!("{}-{:02}-01", now.format("%Y"), now.format("%m"));
+
+    let prescription_count: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM prescriptions WHERE created_at >= ?1",
+        params![first_day_of_month],
+        |row| row.get(0),
+    )?;
+
+    let chart_count: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM initial_charts WHERE created_at >= ?1",
+        params![first_day_of_month],
+        |row| row.get(0),
+    )?;
+
+    Ok((patient_count, prescription_count, chart_count))
+}
+
+// ============ 휴지통 관리 ============
+
+/// 환자 소프트 삭제 (연관 데이터 cascade)
+pub fn soft_delete_patient(id: &str) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let now = Utc::now().to_rfc3339();
+
+    conn.execute(
+        "UPDATE patients SET deleted_at = ?1, updated_at = ?1 WHERE id = ?2",
+        params![now, id],
+    )?;
+    // cascade: prescriptions
+    conn.execute(
+        "UPDATE prescriptions SET deleted_at = ?1, updated_at = ?1 WHERE patient_id = ?2 AND deleted_at IS NULL",
+        params![now, id],
+    )?;
+    // cascade: initial_charts
+    conn.execute(
+        "UPDATE initial_charts SET deleted_at = ?1, updated_at = ?1 WHERE patient_id = ?2 AND deleted_at IS NULL",
+        params![now, id],
+    )?;
+    // cascade: progress_notes
+    conn.execute(
+        "UPDATE progress_notes SET deleted_at = ?1, updated_at = ?1 WHERE patient_id = ?2 AND deleted_at IS NULL",
+        params![now, id],
+    )?;
+
+    Ok(())
+}
+
+/// 초진차트 소프트 삭제
+pub fn soft_delete_initial_chart(id: &str) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute(
+        "UPDATE initial_charts SET deleted_at = ?1, updated_at = ?1 WHERE id = ?2",
+        params![Utc::now().to_rfc3339(), id],
+    )?;
+    Ok(())
+}
+
+/// 경과기록 소프트 삭제
+pub fn soft_delete_progress_note(id: &str) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute(
+        "UPDATE progress_notes SET deleted_at = ?1, updated_at = ?1 WHERE id = ?2",
+        params![Utc::now().to_rfc3339(), id],
+    )?;
+    Ok(())
+}
+
+/// 휴지통에서 복원
+pub fn restore_from_trash(table: &str, id: &str) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let now = Utc::now().to_rfc3339();
+
+    match table {
+        "patients" => {
+            conn.execute(
+                "UPDATE patients SET deleted_at = NULL, updated_at = ?1 WHERE id = ?2",
+                params![now, id],
+            )
+            .map_err(map_patient_conflict)?;
+            // cascade restore: related items
+            conn.execute(
+                "UPDATE prescriptions SET deleted_at = NULL, updated_at = ?1 WHERE patient_id = ?2",
+                params![now, id],
+            )?;
+            conn.execute(
+                "UPDATE initial_charts SET deleted_at = NULL, updated_at = ?1 WHERE patient_id = ?2",
+                params![now, id],
+            )?;
+            conn.execute(
+                "UPDATE progress_notes SET deleted_at = NULL, updated_at = ?1 WHERE patient_id = ?2",
+                params![now, id],
+            )?;
+        }
+        "prescriptions" => {
+            conn.execute(
+                "UPDATE prescriptions SET deleted_at = NULL, updated_at = ?1 WHERE id = ?2",
+                params![now, id],
+            )?;
+        }
+        "initial_charts" => {
+            conn.execute(
+                "UPDATE initial_charts SET deleted_at = NULL, updated_at = ?1 WHERE id = ?2",
+                params![now, id],
+            )?;
+        }
+        "progress_notes" => {
+            conn.execute(
+                "UPDATE progress_notes SET deleted_at = NULL, updated_at = ?1 WHERE id = ?2",
+                params![now, id],
+            )?;
+        }
+        _ => return Err(AppError::Custom(format!("Unknown table: {}", table))),
+    }
+    Ok(())
+}
+
+/// 영구 삭제
+pub fn permanent_delete(table: &str, id: &str) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    match table {
+        "patients" => {
+            conn.execute("DELETE FROM prescriptions WHERE patient_id = ?1", [id])?;
+            conn.execute("DELETE FROM initial_charts WHERE patient_id = ?1", [id])?;
+            conn.execute("DELETE FROM progress_notes WHERE patient_id = ?1", [id])?;
+            conn.execute("DELETE FROM patients WHERE id = ?1", [id])?;
+        }
+        "prescriptions" => {
+            conn.execute("DELETE FROM prescriptions WHERE id = ?1", [id])?;
+        }
+        "initial_charts" => {
+            conn.execute("DELETE FROM initial_charts WHERE id = ?1", [id])?;
+        }
+        "progress_notes" => {
+            conn.execute("DELETE FROM progress_notes WHERE id = ?1", [id])?;
+        }
+        _ => return Err(AppError::Custom(format!("Unknown table: {}", table))),
+    }
+    purge_orphan_tags(&conn)?;
+    Ok(())
+}
+
+/// 휴지통 비우기
+pub fn empty_trash() -> AppResult<TrashEmptyResult> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let p: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM patients WHERE deleted_at IS NOT NULL", [], |r| r.get(0),
+    )?;
+    let rx: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM prescriptions WHERE deleted_at IS NOT NULL", [], |r| r.get(0),
+    )?;
+    let ic: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM initial_charts WHERE deleted_at IS NOT NULL", [], |r| r.get(0),
+    )?;
+    let pn: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM progress_notes WHERE deleted_at IS NOT NULL", [], |r| r.get(0),
+    )?;
+
+    conn.execute("DELETE FROM patients WHERE deleted_at IS NOT NULL", [])?;
+    conn.execute("DELETE FROM prescriptions WHERE deleted_at IS NOT NULL", [])?;
+    conn.execute("DELETE FROM initial_charts WHERE deleted_at IS NOT NULL", [])?;
+    conn.execute("DELETE FROM progress_notes WHERE deleted_at IS NOT NULL", [])?;
+    purge_orphan_tags(&conn)?;
+
+    Ok(TrashEmptyResult {
+        deleted_patients: p,
+        deleted_prescriptions: rx,
+        deleted_initial_charts: ic,
+        deleted_progress_notes: pn,
+        total: p + rx + ic + pn,
+    })
+}
+
+/// 휴지통 항목 조회
+pub fn get_trash_items() -> AppResult<Vec<TrashItem>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let mut items = Vec::new();
+
+    // patients
+    let mut stmt = conn.prepare(
+        "SELECT id, name, deleted_at FROM patients WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(TrashItem {
+            id: row.get(0)?,
+            item_type: "patient".to_string(),
+            name: row.get(1)?,
+            deleted_at: row.get(2)?,
+            extra_info: None,
+        })
+    })?;
+    for r in rows { items.push(r?); }
+
+    // prescriptions
+    let mut stmt = conn.prepare(
+        "SELECT id, COALESCE(prescription_name, formula, '처방'), deleted_at, patient_name FROM prescriptions WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(TrashItem {
+            id: row.get(0)?,
+            item_type: "prescription".to_string(),
+            name: row.get(1)?,
+            deleted_at: row.get(2)?,
+            extra_info: row.get(3)?,
+        })
+    })?;
+    for r in rows { items.push(r?); }
+
+    // initial_charts
+    let mut stmt = conn.prepare(
+        "SELECT ic.id, COALESCE(p.name, '환자'), ic.deleted_at, ic.chart_date FROM initial_charts ic LEFT JOIN patients p ON ic.patient_id = p.id WHERE ic.deleted_at IS NOT NULL ORDER BY ic.deleted_at DESC",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(TrashItem {
+            id: row.get(0)?,
+            item_type: "initial_chart".to_string(),
+            name: row.get::<_, String>(1)? + " 초진차트",
+            deleted_at: row.get(2)?,
+            extra_info: row.get(3)?,
+        })
+    })?;
+    for r in rows { items.push(r?); }
+
+    // progress_notes
+    let mut stmt = conn.prepare(
+        "SELECT pn.id, COALESCE(p.name, '환자'), pn.deleted_at, pn.note_date FROM progress_notes pn LEFT JOIN patients p ON pn.patient_id = p.id WHERE pn.deleted_at IS NOT NULL ORDER BY pn.deleted_at DESC",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(TrashItem {
+            id: row.get(0)?,
+            item_type: "progress_note".to_string(),
+            name: row.get::<_, String>(1)? + " 경과기록",
+            deleted_at: row.get(2)?,
+            extra_info: row.get(3)?,
+        })
+    })?;
+    for r in rows { items.push(r?); }
+
+    // sort by deleted_at DESC
+    items.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+
+    Ok(items)
+}
+
+/// 휴지통 항목 수
+pub fn get_trash_count() -> AppResult<TrashCount> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let p: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM patients WHERE deleted_at IS NOT NULL", [], |r| r.get(0),
+    )?;
+    let rx: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM prescriptions WHERE deleted_at IS NOT NULL", [], |r| r.get(0),
+    )?;
+    let ic: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM initial_charts WHERE deleted_at IS NOT NULL", [], |r| r.get(0),
+    )?;
+    let pn: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM progress_notes WHERE deleted_at IS NOT NULL", [], |r| r.get(0),
+    )?;
+
+    Ok(TrashCount {
+        patients: p,
+        prescriptions: rx,
+        initial_charts: ic,
+        progress_notes: pn,
+        total: p + rx + ic + pn,
+    })
+}
+
+// ============ 태그 ============
+
+/// 태그 대상 엔티티 종류 → (테이블, 삭제되지 않은 행 조건)
+const TAG_ENTITY_TABLES: &[(&str, &str, &str)] = &[
+    ("patient", "patients", "deleted_at IS NULL"),
+    ("prescription", "prescriptions", "deleted_at IS NULL"),
+    ("chart_record", "chart_records", "is_deleted = 0"),
+    ("initial_chart", "initial_charts", "deleted_at IS NULL"),
+    ("progress_note", "progress_notes", "deleted_at IS NULL"),
+];
+
+const MAX_TAG_LENGTH: usize = 50;
+
+fn tag_entity_table(entity_type: &str) -> AppResult<&'static str> {
+    TAG_ENTITY_TABLES
+        .iter()
+        .find(|(t, _, _)| *t == entity_type)
+        .map(|(_, table, _)| *table)
+        .ok_or_else(|| AppError::Custom(format!("태그를 달 수 없는 항목입니다: {}", entity_type)))
+}
+
+/// 태그 정규화 (앞의 # 제거, 공백 정리) - 쉼표는 검색 구분자라 허용하지 않음
+fn normalize_tag(tag: &str) -> AppResult<String> {
+    let tag = tag.trim().trim_start_matches('#').split_whitespace().collect::<Vec<_>>().join(" ");
+    if tag.is_empty() {
+        return Err(AppError::Custom("태그가 비어 있습니다".to_string()));
+    }
+    if tag.chars().count() > MAX_TAG_LENGTH || tag.contains(',') {
+        return Err(AppError::Custom(format!("사용할 수 없는 태그입니다: {}", tag)));
+    }
+    Ok(tag)
+}
+
+/// 삭제되지 않은 엔티티의 태그만 남기는 조건 (tags 테이블 기준)
+fn live_tag_condition() -> String {
+    let clauses: Vec<String> = TAG_ENTITY_TABLES
+        .iter()
+        .map(|(entity_type, table, alive)| {
+            format!(
+                "(tags.entity_type = '{}' AND tags.entity_id IN (SELECT id FROM {} WHERE {}))",
+                entity_type, table, alive
+            )
+        })
+        .collect();
+    format!("({})", clauses.join(" OR "))
+}
+
+fn query_entity_tags(conn: &Connection, entity_type: &str, entity_id: &str) -> AppResult<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT tag FROM tags WHERE entity_type = ?1 AND entity_id = ?2 ORDER BY tag",
+    )?;
+    let tags = stmt
+        .query_map(params![entity_type, entity_id], |row| row.get(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+    Ok(tags)
+}
+
+fn delete_entity_tags(conn: &Connection, entity_type: &str, entity_id: &str) -> AppResult<()> {
+    conn.execute(
+        "DELETE FROM tags WHERE entity_type = ?1 AND entity_id = ?2",
+        params![entity_type, entity_id],
+    )?;
+    Ok(())
+}
+
+/// 태그 정규화 + 대소문자만 다른 중복 제거 (개수 비교용)
+fn dedup_tags(tags: &[String]) -> AppResult<Vec<String>> {
+    let mut tags = tags.iter().map(|t| normalize_tag(t)).collect::<AppResult<Vec<_>>>()?;
+    let mut seen = std::collections::HashSet::new();
+    tags.retain(|t| seen.insert(t.to_lowercase()));
+    Ok(tags)
+}
+
+/// 엔티티 태그를 주어진 목록으로 교체
+fn replace_entity_tags(conn: &Connection, entity_type: &str, entity_id: &str, tags: &[String]) -> AppResult<()> {
+    let tags = dedup_tags(tags)?;
+    delete_entity_tags(conn, entity_type, entity_id)?;
+    let now = Utc::now().to_rfc3339();
+    for tag in &tags {
+        conn.execute(
+            "INSERT OR IGNORE INTO tags (entity_type, entity_id, tag, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![entity_type, entity_id, tag, now],
+        )?;
+    }
+    Ok(())
+}
+
+/// `group_concat(tag, ',')` 결과 분리 (태그에는 쉼표가 없음)
+fn split_tag_list(joined: Option<String>) -> Vec<String> {
+    let mut tags: Vec<String> = joined
+        .unwrap_or_default()
+        .split(',')
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .collect();
+    tags.sort();
+    tags
+}
+
+/// 영구 삭제된 엔티티의 태그 정리
+fn purge_orphan_tags(conn: &Connection) -> AppResult<()> {
+    for (entity_type, table, _) in TAG_ENTITY_TABLES {
+        conn.execute(
+            &format!(
+                "DELETE FROM tags WHERE entity_type = ?1 AND entity_id NOT IN (SELECT id FROM {})",
+                table
+            ),
+            [entity_type],
+        )?;
+    }
+    Ok(())
+}
+
+/// 엔티티의 태그 목록 (대상이 없으면 None)
+pub fn get_entity_tags(entity_type: &str, entity_id: &str) -> AppResult<Option<Vec<String>>> {
+    ensure_db_initialized()?;
+    let table = tag_entity_table(entity_type)?;
+    let conn = get_conn()?;
+
+    let exists: bool = conn.query_row(
+        &format!("SELECT EXISTS(SELECT 1 FROM {} WHERE id = ?1)", table),
+        [entity_id],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        return Ok(None);
+    }
+    Ok(Some(query_entity_tags(&conn, entity_type, entity_id)?))
+}
+
+/// 태그 추가 (이미 있는 태그는 무시) - 추가 후 전체 태그 반환
+pub fn add_entity_tags(entity_type: &str, entity_id: &str, tags: &[String]) -> AppResult<Option<Vec<String>>> {
+    ensure_db_initialized()?;
+    let table = tag_entity_table(entity_type)?;
+    let tags = tags.iter().map(|t| normalize_tag(t)).collect::<AppResult<Vec<_>>>()?;
+    let conn = get_conn()?;
+
+    let exists: bool = conn.query_row(
+        &format!("SELECT EXISTS(SELECT 1 FROM {} WHERE id = ?1)", table),
+        [entity_id],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        return Ok(None);
+    }
+
+    let now = Utc::now().to_rfc3339();
+    for tag in &tags {
+        conn.execute(
+            "INSERT OR IGNORE INTO tags (entity_type, entity_id, tag, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![entity_type, entity_id, tag, now],
+        )?;
+    }
+    Ok(Some(query_entity_tags(&conn, entity_type, entity_id)?))
+}
+
+/// 태그 제거 - 제거 여부 반환
+pub fn remove_entity_tag(entity_type: &str, entity_id: &str, tag: &str) -> AppResult<bool> {
+    ensure_db_initialized()?;
+    tag_entity_table(entity_type)?;
+    let tag = normalize_tag(tag)?;
+    let conn = get_conn()?;
+    let removed = conn.execute(
+        "DELETE FROM tags WHERE entity_type = ?1 AND entity_id = ?2 AND tag = ?3",
+        params![entity_type, entity_id, tag],
+    )?;
+    Ok(removed > 0)
+}
+
+/// 태그 교차 검색
+/// `match_all`이면 모든 태그가 달린 항목만, 아니면 하나라도 달린 항목
+/// `entity_types`가 비어 있으면 모든 종류에서 검색 (휴지통 항목 제외)
+pub fn search_tagged_entities(
+    tags: &[String],
+    entity_types: &[String],
+    match_all: bool,
+) -> AppResult<Vec<TaggedEntity>> {
+    ensure_db_initialized()?;
+    let tags = dedup_tags(tags)?;
+    if tags.is_empty() {
+        return Ok(Vec::new());
+    }
+    for entity_type in entity_types {
+        tag_entity_table(entity_type)?;
+    }
+    let conn = get_conn()?;
+
+    let mut values: Vec<&dyn rusqlite::ToSql> = Vec::new();
+    let tag_placeholders = tags
+        .iter()
+        .map(|t| {
+            values.push(t);
+            format!("?{}", values.len())
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut sql = format!(
+        "SELECT entity_type, entity_id FROM tags WHERE tag IN ({}) AND {}",
+        tag_placeholders,
+        live_tag_condition()
+    );
+    if !entity_types.is_empty() {
+        let type_placeholders = entity_types
+            .iter()
+            .map(|t| {
+                values.push(t);
+                format!("?{}", values.len())
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        sql.push_str(&format!(" AND entity_type IN ({})", type_placeholders));
+    }
+    sql.push_str(" GROUP BY entity_type, entity_id");
+    if match_all {
+        sql.push_str(&format!(" HAVING COUNT(DISTINCT tag) = {}", tags.len()));
+    }
+    sql.push_str(" ORDER BY MAX(created_at) DESC");
+
+    let mut stmt = conn.prepare(&sql)?;
+    let keys = stmt
+        .query_map(values.as_slice(), |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    keys.into_iter()
+        .map(|(entity_type, entity_id)| {
+            let tags = query_entity_tags(&conn, &entity_type, &entity_id)?;
+            Ok(TaggedEntity { entity_type, entity_id, tags })
+        })
+        .collect()
+}
+
+/// 태그 자동완성 (접두어 일치, 사용 횟수 순)
+pub fn suggest_tags(prefix: &str, limit: i64) -> AppResult<Vec<String>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let pattern = format!(
+        "{}%",
+        prefix.trim().trim_start_matches('#').replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+    );
+    let mut stmt = conn.prepare(
+        r"SELECT tag FROM tags WHERE tag LIKE ?1 ESCAPE '\'
+          GROUP BY tag ORDER BY COUNT(*) DESC, tag LIMIT ?2",
+    )?;
+    let tags = stmt
+        .query_map(params![pattern, limit], |row| row.get(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+    Ok(tags)
+}
+
+/// 태그별 사용 통계 (엔티티 종류별 건수 포함, 휴지통 항목 제외)
+pub fn get_tag_stats() -> AppResult<Vec<TagStat>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT tag, entity_type, COUNT(*) FROM tags WHERE {} GROUP BY tag, entity_type",
+        live_tag_condition()
+    ))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut stats: Vec<TagStat> = Vec::new();
+    for (tag, entity_type, count) in rows {
+        let stat = match stats.iter_mut().find(|s| s.tag.eq_ignore_ascii_case(&tag)) {
+            Some(stat) => stat,
+            None => {
+                stats.push(TagStat { tag, total: 0, by_type: Default::default() });
+                stats.last_mut().expect("just pushed")
+            }
+        };
+        stat.total += count;
+        *stat.by_type.entry(entity_type).or_insert(0) += count;
+    }
+    stats.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.tag.cmp(&b.tag)));
+    Ok(stats)
+}
+
+// ============ 환자 이벤트 타임라인 ============
+
+/// 타임라인 이벤트 소스: (이벤트 종류, 원본 테이블, 조회 SQL)
+/// SQL은 `?1`(환자 ID)로 걸러 (id, occurred_at, title, detail)을 반환 - 새 소스는 여기에 한 줄 추가
+const PATIENT_EVENT_SOURCES: &[(&str, &str, &str)] = &[
+    (
+        "visit",
+        "chart_records",
+        "SELECT id, visit_date, '진료', chief_complaint FROM chart_records WHERE patient_id = ?1 AND is_deleted = 0",
+    ),
+    (
+        "visit",
+        "initial_charts",
+        "SELECT id, chart_date, '초진', chief_complaint FROM initial_charts WHERE patient_id = ?1 AND deleted_at IS NULL",
+    ),
+    (
+        "visit",
+        "progress_notes",
+        "SELECT id, note_date, '경과기록', assessment FROM progress_notes WHERE patient_id = ?1 AND deleted_at IS NULL",
+    ),
+    (
+        "prescription",
+        "prescriptions",
+        "SELECT id, COALESCE(issued_at, created_at), COALESCE(NULLIF(prescription_name, ''), '처방'), status
+         FROM prescriptions WHERE patient_id = ?1 AND deleted_at IS NULL",
+    ),
+    (
+        "survey",
+        "survey_responses",
+        "SELECT r.id, r.submitted_at, COALESCE(t.name, '설문'), NULL
+         FROM survey_responses r LEFT JOIN survey_templates t ON t.id = r.template_id WHERE r.patient_id = ?1",
+    ),
+    (
+        "contact",
+        "medication_management",
+        "SELECT id, contacted_at, '해피콜', notes FROM medication_management WHERE patient_id = ?1 AND contacted_at IS NOT NULL",
+    ),
+    (
+        "document",
+        "document_signatures",
+        "SELECT s.id, s.signed_at, CASE s.document_type WHEN 'prescription' THEN '처방전 서명' ELSE '차트 서명' END, s.document_id
+         FROM document_signatures s
+         WHERE (s.document_type = 'prescription' AND s.document_id IN (SELECT id FROM prescriptions WHERE patient_id = ?1))
+            OR (s.document_type = 'chart_record' AND s.document_id IN (SELECT id FROM chart_records WHERE patient_id = ?1))",
+    ),
+];
+
+/// 환자 이벤트 통합 조회 (최신순)
+/// `event_types`가 비어 있으면 전체, 같은 시각은 종류/원본/ID 순으로 고정해 페이지 간 중복·누락 없음
+pub fn get_patient_events(
+    patient_id: &str,
+    event_types: &[String],
+    limit: i64,
+    offset: i64,
+) -> AppResult<Vec<PatientEvent>> {
+    ensure_db_initialized()?;
+    if let Some(unknown) = event_types
+        .iter()
+        .find(|t| !PATIENT_EVENT_SOURCES.iter().any(|(event_type, _, _)| event_type == t))
+    {
+        return Err(AppError::Custom(format!("알 수 없는 이벤트 종류입니다: {}", unknown)));
+    }
+
+    let union = PATIENT_EVENT_SOURCES
+        .iter()
+        .filter(|(event_type, _, _)| event_types.is_empty() || event_types.iter().any(|t| t == event_type))
+        .map(|(event_type, source, sql)| {
+            format!(
+                "SELECT * FROM (WITH e(source_id, occurred_at, title, detail) AS ({})
+                 SELECT '{}' AS event_type, '{}' AS source, source_id, occurred_at, title, detail FROM e)",
+                sql, event_type, source
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" UNION ALL ");
+
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT event_type, source, source_id, occurred_at, title, detail FROM ({})
+         WHERE occurred_at IS NOT NULL
+         ORDER BY occurred_at DESC, event_type, source, source_id LIMIT ?2 OFFSET ?3",
+        union
+    ))?;
+    let events = stmt
+        .query_map(params![patient_id, limit, offset], |row| {
+            Ok(PatientEvent {
+                event_type: row.get(0)?,
+                source: row.get(1)?,
+                source_id: row.get(2)?,
+                occurred_at: row.get(3)?,
+                title: row.get(4)?,
+                detail: row.get(5)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(events)
+}
+
+// ============ 환자 설문 추이 ============
+
+/// 환자가 같은 템플릿에 반복 응답한 척도 문항 값의 시계열 (템플릿이 없으면 None)
+///
+/// 문항 수정 전에 제출된 응답도 문항 ID 기준으로 맞추며, 현재 템플릿에 없는 문항 값은 버린다.
+pub fn get_patient_survey_series(patient_id: &str, template_id: &str) -> AppResult<Option<PatientSurveySeries>> {
+    let Some(template) = get_survey_template(template_id)? else {
+        return Ok(None);
+    };
+    let questions: Vec<SeriesQuestion> = template
+        .questions
+        .iter()
+        .filter(|q| matches!(q.question_type, QuestionType::Scale))
+        .map(|q| SeriesQuestion {
+            question_id: q.id.clone(),
+            question_text: q.question_text.clone(),
+            min: q.scale_config.as_ref().map(|c| c.min),
+            max: q.scale_config.as_ref().map(|c| c.max),
+        })
+        .collect();
+
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, submitted_at, answers FROM survey_responses
+         WHERE patient_id = ?1 AND template_id = ?2
+         ORDER BY submitted_at ASC",
+    )?;
+    let points = stmt
+        .query_map(params![patient_id, template_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .map(|(response_id, submitted_at, answers_json)| {
+            let answers: Vec<SurveyAnswer> = serde_json::from_str(&answers_json).unwrap_or_default();
+            let values = answers
+                .iter()
+                .filter(|a| questions.iter().any(|q| q.question_id == a.question_id))
+                .filter_map(|a| {
+                    let value = match &a.answer {
+                        serde_json::Value::Number(n) => n.as_f64(),
+                        serde_json::Value::String(s) => s.trim().parse::<f64>().ok(),
+                        _ => None,
+                    }?;
+                    Some((a.question_id.clone(), value))
+                })
+                .collect();
+            SurveySeriesPoint { response_id, submitted_at, values }
+        })
+        .collect();
+
+    Ok(Some(PatientSurveySeries {
+        patient_id: patient_id.to_string(),
+        template_id: template.id,
+        template_name: template.name,
+        questions,
+        points,
+    }))
+}
+
+// ============ 치료 결과 상관 ============
+
+/// 상관계수 계산에 필요한 최소 표본 수
+const MIN_CORRELATION_SAMPLES: usize = 3;
+
+/// 처방별 전후 설문 점수 변화와 복약 순응도, 둘 사이의 상관계수
+///
+/// 처방 직전의 마지막 응답을 기준으로, 다음 처방 전까지 같은 템플릿으로 제출된 첫 응답과 비교한다.
+pub fn get_treatment_outcome_correlation(patient_id: &str) -> AppResult<TreatmentOutcomeCorrelation> {
+    ensure_db_initialized()?;
+
+    // (id, 이름, 처방 시각, 복약 기록 수, 복용 수)
+    let (prescriptions, responses) = {
+        let conn = get_conn()?;
+        let mut stmt = conn.prepare(
+            r#"SELECT p.id, p.prescription_name, COALESCE(p.issued_at, p.created_at),
+                      (SELECT COUNT(*) FROM medication_logs ml JOIN medication_schedules ms ON ml.schedule_id = ms.id
+                       WHERE ms.prescription_id = p.id),
+                      (SELECT COUNT(*) FROM medication_logs ml JOIN medication_schedules ms ON ml.schedule_id = ms.id
+                       WHERE ms.prescription_id = p.id AND ml.status = 'taken')
+               FROM prescriptions p
+               WHERE p.patient_id = ?1 AND p.deleted_at IS NULL
+               ORDER BY 3 ASC"#,
+        )?;
+        let prescriptions = stmt
+            .query_map([patient_id], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, i64>(4)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, template_id, submitted_at, answers FROM survey_responses
+             WHERE patient_id = ?1 ORDER BY submitted_at ASC",
+        )?;
+        let responses = stmt
+            .query_map([patient_id], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        (prescriptions, responses)
+    };
+
+    // 응답별 점수 (척도 문항이 없거나 답하지 않은 응답은 제외)
+    let mut templates: std::collections::HashMap<String, Vec<SurveyQuestion>> = std::collections::HashMap::new();
+    let mut scored = Vec::new();
+    for (id, template_id, submitted_at, answers_json) in responses {
+        if !templates.contains_key(&template_id) {
+            let questions = get_survey_template(&template_id)?.map(|t| t.questions).unwrap_or_default();
+            templates.insert(template_id.clone(), questions);
+        }
+        let answers: Vec<SurveyAnswer> = serde_json::from_str(&answers_json).unwrap_or_default();
+        if let Some(score) = scale_score(&templates[&template_id], &answers) {
+            scored.push((id, template_id, submitted_at, score));
+        }
+    }
+
+    let items: Vec<TreatmentOutcome> = prescriptions
+        .iter()
+        .enumerate()
+        .map(|(i, (id, name, prescribed_at, total_logs, taken))| {
+            let next_at = prescriptions.get(i + 1).map(|p| p.2.as_str());
+            let before = scored.iter().rev().find(|r| r.2.as_str() < prescribed_at.as_str());
+            let after = before.and_then(|b| {
+                scored.iter().find(|r| {
+                    r.1 == b.1 && r.2.as_str() >= prescribed_at.as_str() && next_at.map_or(true, |n| r.2.as_str() < n)
+                })
+            });
+            TreatmentOutcome {
+                prescription_id: id.clone(),
+                prescription_name: name.clone(),
+                prescribed_at: prescribed_at.clone(),
+                template_id: before.map(|b| b.1.clone()),
+                before_response_id: before.map(|b| b.0.clone()),
+                after_response_id: after.map(|a| a.0.clone()),
+                score_before: before.map(|b| b.3),
+                score_after: after.map(|a| a.3),
+                score_change: before.zip(after).map(|(b, a)| a.3 - b.3),
+                adherence_rate: (*total_logs > 0).then(|| *taken as f64 / *total_logs as f64 * 100.0),
+                medication_logs: *total_logs,
+            }
+        })
+        .collect();
+
+    let pairs: Vec<(f64, f64)> = items
+        .iter()
+        .filter_map(|item| item.adherence_rate.zip(item.score_change))
+        .collect();
+
+    Ok(TreatmentOutcomeCorrelation {
+        patient_id: patient_id.to_string(),
+        adherence_score_correlation: pearson_correlation(&pairs),
+        sample_size: pairs.len() as i64,
+        items,
+    })
+}
+
+/// 척도 문항 답변 평균 (각 문항의 척도 범위 대비 0~100)
+fn scale_score(questions: &[SurveyQuestion], answers: &[SurveyAnswer]) -> Option<f64> {
+    let values: Vec<f64> = questions
+        .iter()
+        .filter(|q| matches!(q.question_type, QuestionType::Scale))
+        .filter_map(|q| {
+            let config = q.scale_config.as_ref().filter(|c| c.max > c.min)?;
+            let value = match &answers.iter().find(|a| a.question_id == q.id)?.answer {
+                serde_json::Value::Number(n) => n.as_f64(),
+                serde_json::Value::String(s) => s.trim().parse::<f64>().ok(),
+                _ => None,
+            }?;
+            Some((value - config.min as f64) / (config.max - config.min) as f64 * 100.0)
+        })
+        .collect();
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+/// 피어슨 상관계수 (표본 부족 또는 한쪽 분산이 0이면 None)
+fn pearson_correlation(pairs: &[(f64, f64)]) -> Option<f64> {
+    if pairs.len() < MIN_CORRELATION_SAMPLES {
+        return None;
+    }
+    let n = pairs.len() as f64;
+    let mean_x = pairs.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = pairs.iter().map(|p| p.1).sum::<f64>() / n;
+    let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+    for (x, y) in pairs {
+        cov += (x - mean_x) * (y - mean_y);
+        var_x += (x - mean_x).powi(2);
+        var_y += (y - mean_y).powi(2);
+    }
+    if var_x == 0.0 || var_y == 0.0 {
+        return None;
+    }
+    Some(cov / (var_x.sqrt() * var_y.sqrt()))
+}
+
+// ============ 스케줄러 락 ============
+
+/// 이 프로세스의 락 소유자 ID (실행마다 새로 생성)
+fn scheduler_instance_id() -> &'static str {
+    static INSTANCE_ID: OnceCell<String> = OnceCell::new();
+    INSTANCE_ID.get_or_init(|| uuid::Uuid::new_v4().to_string())
+}
+
+/// 백그라운드 작업 락 획득/갱신 (이미 가진 락이면 heartbeat만 갱신)
+/// 다른 프로세스의 락이라도 `stale_after_secs` 동안 갱신이 없으면 비정상 종료로 보고 넘겨받음
+pub fn try_acquire_scheduler_lock(name: &str, stale_after_secs: i64) -> AppResult<bool> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let now = Utc::now();
+    let stale_before = (now - chrono::Duration::seconds(stale_after_secs)).to_rfc3339();
+
+    let changed = conn.execute(
+        r#"INSERT INTO scheduler_locks (name, owner, heartbeat_at) VALUES (?1, ?2, ?3)
+           ON CONFLICT(name) DO UPDATE SET owner = excluded.owner, heartbeat_at = excluded.heartbeat_at
+           WHERE scheduler_locks.owner = excluded.owner OR scheduler_locks.heartbeat_at < ?4"#,
+        params![name, scheduler_instance_id(), now.to_rfc3339(), stale_before],
+    )?;
+    Ok(changed > 0)
+}
+
+/// 백그라운드 작업 락 해제 (이 프로세스가 가진 경우만)
+pub fn release_scheduler_lock(name: &str) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute(
+        "DELETE FROM scheduler_locks WHERE name = ?1 AND owner = ?2",
+        params![name, scheduler_instance_id()],
+    )?;
+    Ok(())
+}
+
+/// 테스트용: 다른 인스턴스가 잡은 락 (heartbeat는 `age_secs`초 전)
+#[cfg(test)]
+pub(crate) fn insert_test_scheduler_lock(name: &str, owner: &str, age_secs: i64) {
+    let heartbeat = (Utc::now() - chrono::Duration::seconds(age_secs)).to_rfc3339();
+    get_conn()
+        .unwrap()
+        .execute(
+            "INSERT OR REPLACE INTO scheduler_locks (name, owner, heartbeat_at) VALUES (?1, ?2, ?3)",
+            params![name, owner, heartbeat],
+        )
+        .unwrap();
+}
+
+// ============ 사용량 통계 ============
+
+/// 사용량 통계 (deleted_at IS NULL 기준)
+pub fn get_usage_stats() -> AppResult<UsageStats> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let patients: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM patients WHERE deleted_at IS NULL", [], |r| r.get(0),
+    )?;
+    let prescriptions: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM prescriptions WHERE deleted_at IS NULL", [], |r| r.get(0),
+    )?;
+    let initial_charts: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM initial_charts WHERE deleted_at IS NULL", [], |r| r.get(0),
+    )?;
+    let progress_notes: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM progress_notes WHERE deleted_at IS NULL", [], |r| r.get(0),
+    )?;
+
+    Ok(UsageStats { patients, prescriptions, initial_charts, progress_notes })
+}
+
+// ============ 대시보드 통계 ============
+
+/// 로컬 날짜가 시작되는 시각 (UTC RFC3339 - UTC로 저장된 시각과 비교용)
+fn local_day_start_utc(date: chrono::NaiveDate) -> String {
+    use chrono::TimeZone;
+
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    chrono::Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc.from_utc_datetime(&midnight))
+        .to_rfc3339()
+}
+
+/// 'YYYY-MM-DD' 기간 파라미터 (없으면 기본값)
+fn parse_local_date(value: Option<&str>, default: chrono::NaiveDate) -> AppResult<chrono::NaiveDate> {
+    match value {
+        Some(v) => chrono::NaiveDate::parse_from_str(v, "%Y-%m-%d")
+            .map_err(|_| AppError::Validation("기간 형식이 올바르지 않습니다 (YYYY-MM-DD)".to_string())),
+        None => Ok(default),
+    }
+}
+
+/// 대시보드 요약 통계 (from/to: 'YYYY-MM-DD', 미지정 시 내원/설문/복약은 오늘, 신규 환자는 이번 주 기준)
+///
+/// 날짜는 로컬 기준이고, UTC로 저장된 시각은 로컬 날짜 경계를 UTC로 바꿔 비교합니다.
+/// 데스크톱 홈 화면과 웹 대시보드(`/api/stats/dashboard`)가 함께 사용합니다.
+pub fn get_dashboard_stats(from: Option<&str>, to: Option<&str>) -> AppResult<DashboardStats> {
+    use chrono::Datelike;
+
+    let today = chrono::Local::now().date_naive();
+    let week_start = today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64);
+    let to = parse_local_date(to, today)?;
+    let visit_from = parse_local_date(from, today)?;
+    let patient_from = parse_local_date(from, week_start)?;
+
+    // [시작일 0시, 종료일 다음 날 0시) - 로컬 날짜 범위를 UTC 시각으로
+    let range_end = local_day_start_utc(to + chrono::Duration::days(1));
+    let visit_start = local_day_start_utc(visit_from);
+    let patient_start = local_day_start_utc(patient_from);
+
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    // 초진/경과 차트 날짜는 로컬 날짜('YYYY-MM-DD')로 저장됨
+    let visit_count: i32 = conn.query_row(
+        r#"SELECT
+            (SELECT COUNT(*) FROM initial_charts WHERE deleted_at IS NULL AND substr(chart_date, 1, 10) BETWEEN ?1 AND ?2)
+          + (SELECT COUNT(*) FROM progress_notes WHERE deleted_at IS NULL AND substr(note_date, 1, 10) BETWEEN ?1 AND ?2)
+          + (SELECT COUNT(*) FROM chart_records WHERE is_deleted = 0
+                AND julianday(visit_date) >= julianday(?3) AND julianday(visit_date) < julianday(?4))"#,
+        params![visit_from.to_string(), to.to_string(), visit_start, range_end],
+        |row| row.get(0),
+    )?;
+
+    let new_patients: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM patients WHERE deleted_at IS NULL AND julianday(created_at) >= julianday(?1) AND julianday(created_at) < julianday(?2)",
+        params![patient_start, range_end],
+        |row| row.get(0),
+    )?;
+
+    let pending_survey_sessions: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM survey_sessions WHERE status = 'pending' AND expires_at > ?1",
+        params![Utc::now().to_rfc3339()],
+        |row| row.get(0),
+    )?;
+
+    let (total_logs, taken_logs): (i32, i32) = conn.query_row(
+        r#"SELECT COUNT(*), COALESCE(SUM(CASE WHEN status = 'taken' THEN 1 ELSE 0 END), 0)
+           FROM medication_logs WHERE julianday(taken_at) >= julianday(?1) AND julianday(taken_at) < julianday(?2)"#,
+        params![visit_start, range_end],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    let medication_adherence_rate = if total_logs > 0 {
+        (taken_logs as f64 / total_logs as f64) * 100.0
+    } else {
+        0.0
+    };
+
+    let unlinked_survey_responses = cached_survey_response_stats(&conn)?.unlinked_responses as i32;
+
+    let survey_responses: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM survey_responses WHERE julianday(submitted_at) >= julianday(?1) AND julianday(submitted_at) < julianday(?2)",
+        params![visit_start, range_end],
+        |row| row.get(0),
+    )?;
+
+    // 일정과 기간이 겹치는 날수 × 하루 복용 횟수 (종료 시각은 포함하지 않음, MedicationSchedule::expected_doses와 같은 기준)
+    let medications_due: i32 = conn.query_row(
+        r#"SELECT COALESCE(SUM(times_per_day * MAX(0,
+                MIN(julianday(end_date), julianday(?2)) - MAX(julianday(start_date), julianday(?1))
+           )), 0)
+           FROM medication_schedules
+           WHERE julianday(start_date) < julianday(?2) AND julianday(end_date) > julianday(?1)"#,
+        params![visit_start, range_end],
+        |row| row.get::<_, f64>(0),
+    )?
+    .round() as i32;
+
+    let pending_sync_items: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sync_queue WHERE retry_count < ?1",
+        [SYNC_MAX_RETRIES],
+        |row| row.get(0),
+    )?;
+
+    Ok(DashboardStats {
+        from: visit_from.to_string(),
+        to: to.to_string(),
+        visit_count,
+        new_patients,
+        pending_survey_sessions,
+        medication_adherence_rate,
+        unlinked_survey_responses,
+        survey_responses,
+        medications_due,
+        medications_taken: taken_logs,
+        pending_sync_items,
+    })
+}
+
+// ============ 환자 요약 PDF ============
+
+/// 환자 요약 PDF에 넣을 최근 진료기록 수
+pub const PATIENT_PDF_CHART_LIMIT: usize = 10;
+
+/// 환자 요약을 인쇄용 PDF로 생성 (의뢰서 첨부용, 환자가 없으면 None)
+///
+/// 한의원 이름 머리글, 인적 사항, 중요 정보, 최근 진료기록 10건, 진행 중인 복약 일정을 담습니다.
+pub fn generate_patient_pdf(patient_id: &str) -> AppResult<Option<Vec<u8>>> {
+    let Some(detail) = get_patient_detail(patient_id)? else {
+        return Ok(None);
+    };
+    let clinic_name = get_clinic_settings()?
+        .map(|s| s.clinic_name)
+        .unwrap_or_else(|| "한의원".to_string());
+    let mut records = get_chart_records_by_patient(patient_id)?;
+    records.truncate(PATIENT_PDF_CHART_LIMIT);
+    let now = Utc::now();
+    let mut schedules = Vec::new();
+    for schedule in get_medication_schedules_by_patient(patient_id)? {
+        if schedule.is_completed || schedule.end_date < now {
+            continue;
+        }
+        let prescription_name = get_prescription(&schedule.prescription_id)?.and_then(|p| p.prescription_name);
+        schedules.push((schedule, prescription_name));
+    }
+
+    let mut doc = crate::pdf::PdfDocument::new(crate::pdf::load_cjk_font()?);
+    let patient = &detail.patient;
+    let local_date = |dt: &chrono::DateTime<Utc>| dt.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string();
+
+    doc.muted_text(&clinic_name, 10.0, 0.0);
+    doc.text("환자 요약", 18.0, 0.0);
+    doc.muted_text(&format!("출력일: {}", local_date(&now)), 9.5, 0.0);
+    doc.space(4.0);
+    doc.text(&patient.name, 14.0, 0.0);
+    let gender = patient.gender.as_deref().map(|g| if g == "M" { "남" } else { "여" });
+    for (label, value) in [
+        ("차트번호", patient.chart_number.as_deref()),
+        ("생년월일", patient.birth_date.as_deref()),
+        ("성별", gender),
+        ("연락처", patient.phone.as_deref()),
+        ("주소", patient.address.as_deref()),
+        ("특이사항", patient.notes.as_deref()),
+    ] {
+        if let Some(value) = value.filter(|v| !v.trim().is_empty()) {
+            doc.text(&format!("{}: {}", label, value), 10.5, 0.0);
+        }
+    }
+
+    if !detail.alerts.is_empty() {
+        doc.space(6.0);
+        doc.text("중요 정보", 13.0, 0.0);
+        for alert in &detail.alerts {
+            let label = match alert.alert_type {
+                PatientAlertType::Allergy => "알레르기",
+                PatientAlertType::CautionMedication => "주의 약물",
+                PatientAlertType::ChronicDisease => "만성질환",
+                PatientAlertType::Other => "주의",
+            };
+            doc.text(&format!("[{}] {}", label, alert.content), 10.5, 12.0);
+        }
+    }
+    doc.rule();
+
+    doc.text(&format!("최근 진료기록 ({}건)", records.len()), 13.0, 0.0);
+    if records.is_empty() {
+        doc.muted_text("(진료기록 없음)", 10.5, 12.0);
+    }
+    for record in &records {
+        doc.space(4.0);
+        doc.text(&local_date(&record.visit_date), 11.0, 0.0);
+        for (label, value) in [
+            ("주소증", &record.chief_complaint),
+            ("증상", &record.symptoms),
+            ("진단", &record.diagnosis),
+            ("치료", &record.treatment),
+            ("메모", &record.notes),
+        ] {
+            if let Some(value) = value.as_deref().filter(|v| !v.trim().is_empty()) {
+                doc.text(&format!("{}: {}", label, value), 10.5, 12.0);
+            }
+        }
+    }
+    doc.rule();
+
+    doc.text("복약 중인 처방", 13.0, 0.0);
+    if schedules.is_empty() {
+        doc.muted_text("(진행 중인 복약 일정 없음)", 10.5, 12.0);
+    }
+    for (schedule, prescription_name) in &schedules {
+        doc.space(4.0);
+        doc.text(prescription_name.as_deref().unwrap_or("처방"), 11.0, 0.0);
+        doc.text(
+            &format!(
+                "{} ~ {}, 하루 {}회 ({})",
+                local_date(&schedule.start_date),
+                local_date(&schedule.end_date),
+                schedule.times_per_day,
+                schedule.medication_times.join(", ")
+            ),
+            10.5,
+            12.0,
+        );
+        if let Some(notes) = schedule.notes.as_deref().filter(|n| !n.trim().is_empty()) {
+            doc.text(notes, 10.5, 12.0);
+        }
+    }
+
+    doc.finish().map(Some)
+}
+
+// ============ 설문 응답 PDF ============
+
+/// 설문 응답 1건을 인쇄용 PDF로 생성 (한의원 이름 머리글, 문항별 답변, 척도 문항은 점수 표시)
+pub fn export_survey_response_pdf(response_id: &str) -> AppResult<Option<Vec<u8>>> {
+    let Some(response) = get_survey_response_detail(response_id)? else {
+        return Ok(None);
+    };
+    // 템플릿이 삭제된 경우 질문 ID 기준으로 표시
+    let questions = get_survey_template(&response.template_id)?
+        .map(|template| template.questions)
+        .unwrap_or_default();
+    let clinic_name = get_clinic_settings()?
+        .map(|s| s.clinic_name)
+        .unwrap_or_else(|| "한의원".to_string());
+
+    let mut doc = crate::pdf::PdfDocument::new(crate::pdf::load_cjk_font()?);
+    write_survey_response_pdf(&mut doc, &clinic_name, &response, &questions);
+    doc.finish().map(Some)
+}
+
+/// 응답자 표시 이름 (익명 설문이거나 이름이 없으면 "익명")
+fn survey_respondent_label(response: &SurveyResponseWithTemplate) -> &str {
+    if response.anonymous {
+        return "익명";
+    }
+    response
+        .patient_name
+        .as_deref()
+        .or(response.respondent_name.as_deref())
+        .unwrap_or("익명")
+}
+
+/// 제출일시 표시 (현지 시각)
+fn survey_submitted_label(response: &SurveyResponseWithTemplate) -> String {
+    chrono::DateTime::parse_from_rfc3339(&response.submitted_at)
+        .map(|dt| dt.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|_| response.submitted_at.clone())
+}
+
+/// 설문 응답 1건을 현재 페이지부터 기록 (머리글, 응답자 정보, 문항별 답변)
+fn write_survey_response_pdf(
+    doc: &mut crate::pdf::PdfDocument,
+    clinic_name: &str,
+    response: &SurveyResponseWithTemplate,
+    questions: &[SurveyQuestion],
+) {
+    doc.muted_text(clinic_name, 10.0, 0.0);
+    doc.text(response.template_name.as_deref().unwrap_or("설문"), 18.0, 0.0);
+    doc.space(4.0);
+
+    let respondent = match (&response.chart_number, response.anonymous) {
+        (Some(chart_number), false) => format!("{} (차트번호 {})", survey_respondent_label(response), chart_number),
+        _ => survey_respondent_label(response).to_string(),
+    };
+    doc.text(&format!("응답자: {}", respondent), 10.5, 0.0);
+    doc.text(&format!("제출일시: {}", survey_submitted_label(response)), 10.5, 0.0);
+    for (label, value) in [("연락처", &response.respondent_phone), ("생년월일", &response.respondent_birth_date)] {
+        if let Some(value) = value {
+            doc.text(&format!("{}: {}", label, value), 10.5, 0.0);
+        }
+    }
+    doc.rule();
+
+    let answer_of = |question_id: &str| response.answers.iter().find(|a| a.question_id == question_id).map(|a| &a.answer);
+    let mut number = 0;
+    if questions.is_empty() {
+        for answer in &response.answers {
+            number += 1;
+            write_pdf_item(doc, number, &answer.question_id, None, Some(&answer.answer));
+        }
+    }
+    for question in questions {
+        if let Some(title) = question.question_text.strip_prefix('>') {
+            doc.space(6.0);
+            doc.text(title.trim(), 13.0, 0.0);
+            let answer = answer_of(&question.id).map(pdf_answer_text).unwrap_or_default();
+            if !answer.is_empty() {
+                doc.text(&answer, 10.5, 12.0);
+            }
+            continue;
+        }
+        number += 1;
+        write_pdf_item(doc, number, &question.question_text, Some(question), answer_of(&question.id));
+    }
+}
+
+fn write_pdf_item(
+    doc: &mut crate::pdf::PdfDocument,
+    number: usize,
+    label: &str,
+    question: Option<&SurveyQuestion>,
+    answer: Option<&serde_json::Value>,
+) {
+    doc.space(4.0);
+    doc.text(&format!("Q{}. {}", number, label), 11.0, 0.0);
+    let value = answer.map(pdf_answer_text).unwrap_or_default();
+    if value.is_empty() {
+        doc.muted_text("(답변 없음)", 10.5, 12.0);
+        return;
+    }
+    doc.text(&value, 10.5, 12.0);
+
+    // 척도 문항은 범위 대비 점수와 양 끝 라벨 표시
+    if let Some(scale) = question.filter(|q| matches!(q.question_type, QuestionType::Scale)).and_then(|q| q.scale_config.as_ref()) {
+        let labels: Vec<String> = [
+            scale.min_label.as_deref().map(|l| format!("{} = {}", scale.min, l)),
+            scale.max_label.as_deref().map(|l| format!("{} = {}", scale.max, l)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        let mut score = format!("점수 {} / {}", value, scale.max);
+        if !labels.is_empty() {
+            score.push_str(&format!(" ({})", labels.join(" · ")));
+        }
+        doc.muted_text(&score, 9.5, 12.0);
+    }
+}
+
+fn pdf_answer_text(value: &serde_json::Value) -> String {
+    let text = |value: &serde_json::Value| match value {
+        serde_json::Value::String(s) => s.trim().to_string(),
+        serde_json::Value::Bool(b) => if *b { "예" } else { "아니오" }.to_string(),
+        serde_json::Value::Null => String::new(),
+        other => match attachment_answer_name(other) {
+            Some(name) => format!("[첨부] {}", name),
+            None => other.to_string(),
+        },
+    };
+    match value {
+        serde_json::Value::Array(items) => items.iter().map(text).filter(|s| !s.is_empty()).collect::<Vec<_>>().join(", "),
+        value => text(value),
+    }
+}
+
+// ============ 설문 응답 일괄 내보내기 ============
+
+/// 일괄 내보내기 요청 확인 (날짜 형식, 템플릿 존재 여부)
+pub fn validate_survey_export_request(request: &SurveyExportRequest) -> AppResult<()> {
+    for date in [&request.from, &request.to].into_iter().flatten() {
+        if chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+            return Err(AppError::Validation(format!("날짜는 YYYY-MM-DD 형식이어야 합니다: {}", date)));
+        }
+    }
+    if let Some(template_id) = &request.template_id {
+        if get_survey_template(template_id)?.is_none() {
+            return Err(AppError::Validation("템플릿을 찾을 수 없습니다".to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// 설문 응답 일괄 내보내기 (반환: 파일 이름, 내용)
+///
+/// 응답을 한 건씩 읽으며 `progress(처리 건수, 전체 건수)`를 호출하고, 에러를 돌려주면 그 자리에서 중단합니다.
+/// PDF는 응답마다 새 페이지, 엑셀/CSV는 응답당 한 줄이며 템플릿을 지정하면 문항별 열로 나눕니다.
+pub fn export_survey_responses(
+    request: &SurveyExportRequest,
+    progress: &mut dyn FnMut(usize, usize) -> AppResult<()>,
+) -> AppResult<(String, Vec<u8>)> {
+    validate_survey_export_request(request)?;
+    let ids: Vec<String> = {
+        ensure_db_initialized()?;
+        let conn = get_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT id FROM survey_responses
+             WHERE (?1 IS NULL OR template_id = ?1) AND substr(submitted_at, 1, 10) BETWEEN ?2 AND ?3
+             ORDER BY submitted_at",
+        )?;
+        let rows = stmt.query_map(
+            params![
+                request.template_id,
+                request.from.as_deref().unwrap_or("0000-01-01"),
+                request.to.as_deref().unwrap_or("9999-12-31")
+            ],
+            |row| row.get(0),
+        )?;
+        rows.collect::<Result<_, _>>()?
+    };
+    let total = ids.len();
+    progress(0, total)?;
+
+    // 템플릿이 삭제된 응답은 질문 ID 기준으로 표시
+    let mut templates: std::collections::HashMap<String, Vec<SurveyQuestion>> = std::collections::HashMap::new();
+    let mut questions_of = |template_id: &str| -> AppResult<Vec<SurveyQuestion>> {
+        if !templates.contains_key(template_id) {
+            let questions = get_survey_template(template_id)?.map(|t| t.questions).unwrap_or_default();
+            templates.insert(template_id.to_string(), questions);
+        }
+        Ok(templates[template_id].clone())
+    };
+    let column_questions: Vec<SurveyQuestion> = match &request.template_id {
+        Some(template_id) => questions_of(template_id)?
+            .into_iter()
+            .filter(|q| !q.question_text.starts_with('>'))
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let clinic_name = get_clinic_settings()?
+        .map(|s| s.clinic_name)
+        .unwrap_or_else(|| "한의원".to_string());
+    let mut doc = match request.format {
+        SurveyExportFormat::Pdf => Some(crate::pdf::PdfDocument::new(crate::pdf::load_cjk_font()?)),
+        _ => None,
+    };
+    let mut rows = vec![survey_export_header(&column_questions)];
+
+    for (i, id) in ids.iter().enumerate() {
+        if let Some(response) = get_survey_response_detail(id)? {
+            let questions = questions_of(&response.template_id)?;
+            match doc.as_mut() {
+                Some(doc) => {
+                    if i > 0 {
+                        doc.new_page();
+                    }
+                    write_survey_response_pdf(doc, &clinic_name, &response, &questions);
+                }
+                None => rows.push(survey_export_row(&response, &column_questions, &questions)),
+            }
+        }
+        progress(i + 1, total)?;
+    }
+
+    let file_name = format!("survey_responses_{}.{}", chrono::Local::now().format("%Y%m%d_%H%M"), request.format.extension());
+    let bytes = match (request.format, doc) {
+        (SurveyExportFormat::Pdf, Some(doc)) => doc.finish()?,
+        (SurveyExportFormat::Xlsx, _) => crate::xlsx::write_sheet("설문 응답", &rows)?,
+        _ => {
+            let mut out = "\u{feff}".as_bytes().to_vec();
+            for row in &rows {
+                write_csv_record(&mut out, row.iter().map(String::as_str));
+            }
+            out
+        }
+    };
+    Ok((file_name, bytes))
+}
+
+/// 엑셀/CSV 머리글 (문항 열이 없으면 답변을 한 칸에 모음)
+fn survey_export_header(questions: &[SurveyQuestion]) -> Vec<String> {
+    let mut header: Vec<String> = ["제출일시", "설문", "응답자", "차트번호", "연락처", "생년월일"]
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    if questions.is_empty() {
+        header.push("답변".to_string());
+    } else {
+        header.extend(questions.iter().map(|q| q.question_text.clone()));
+    }
+    header
+}
+
+/// 엑셀/CSV 한 줄 (익명 응답은 응답자 정보를 비움, `template_questions`는 답변 칸의 질문 표시용)
+fn survey_export_row(
+    response: &SurveyResponseWithTemplate,
+    questions: &[SurveyQuestion],
+    template_questions: &[SurveyQuestion],
+) -> Vec<String> {
+    let personal = |value: &Option<String>| if response.anonymous { String::new() } else { value.clone().unwrap_or_default() };
+    let mut row = vec![
+        survey_submitted_label(response),
+        response.template_name.clone().unwrap_or_default(),
+        survey_respondent_label(response).to_string(),
+        personal(&response.chart_number),
+        personal(&response.respondent_phone),
+        personal(&response.respondent_birth_date),
+    ];
+    let answer_of = |question_id: &str| {
+        response
+            .answers
+            .iter()
+            .find(|a| a.question_id == question_id)
+            .map(|a| pdf_answer_text(&a.answer))
+            .unwrap_or_default()
+    };
+    if questions.is_empty() {
+        let answers: Vec<String> = response
+            .answers
+            .iter()
+            .map(|a| {
+                let label = template_questions
+                    .iter()
+                    .find(|q| q.id == a.question_id)
+                    .map_or(a.question_id.as_str(), |q| q.question_text.trim_start_matches('>').trim());
+                format!("{}: {}", label, pdf_answer_text(&a.answer))
+            })
+            .collect();
+        row.push(answers.join("\n"));
+    } else {
+        row.extend(questions.iter().map(|q| answer_of(&q.id)));
+    }
+    row
+}
+
+// ============ 설문 템플릿 통계 ============
+
+/// 템플릿별 문항 응답 분포 (`from`/`to`는 제출일 기준 YYYY-MM-DD, 템플릿이 없으면 None)
+pub fn get_template_response_stats(
+    template_id: &str,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> AppResult<Option<TemplateResponseStats>> {
+    let Some(template) = get_survey_template(template_id)? else {
+        return Ok(None);
+    };
+
+    let responses: Vec<Vec<SurveyAnswer>> = {
+        let conn = get_conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT answers FROM survey_responses
+             WHERE template_id = ?1 AND substr(submitted_at, 1, 10) BETWEEN ?2 AND ?3",
+        )?;
+        let rows = stmt
+            .query_map(
+                params![template_id, from.unwrap_or("0000-01-01"), to.unwrap_or("9999-12-31")],
+                |row| row.get::<_, String>(0),
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows.iter().map(|json| serde_json::from_str(json).unwrap_or_default()).collect()
+    };
+
+    let questions = template
+        .questions
+        .iter()
+        .map(|q| {
+            let answers: Vec<&serde_json::Value> = responses
+                .iter()
+                .filter_map(|answers| answers.iter().find(|a| a.question_id == q.id))
+                .map(|a| &a.answer)
+                .filter(|v| !is_empty_answer(v))
+                .collect();
+            question_stats(q, &answers)
+        })
+        .collect();
+
+    Ok(Some(TemplateResponseStats {
+        template_id: template.id,
+        template_name: template.name,
+        response_count: responses.len() as i64,
+        questions,
+    }))
+}
+
+fn is_empty_answer(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Null => true,
+        serde_json::Value::String(s) => s.trim().is_empty(),
+        serde_json::Value::Array(items) => items.is_empty(),
+        _ => false,
+    }
+}
+
+/// 선택형 답변 표시값 (예/아니오는 bool로 저장될 수 있음)
+fn answer_label(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Bool(true) => Some("예".to_string()),
+        serde_json::Value::Bool(false) => Some("아니오".to_string()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+fn question_stats(question: &SurveyQuestion, answers: &[&serde_json::Value]) -> QuestionStats {
+    let mut stats = QuestionStats {
+        question_id: question.id.clone(),
+        question_text: question.question_text.clone(),
+        question_type: question.question_type.clone(),
+        answer_count: answers.len() as i64,
+        mean: None,
+        median: None,
+        option_counts: Vec::new(),
+    };
+
+    match question.question_type {
+        QuestionType::Scale => {
+            let mut values: Vec<f64> = answers
+                .iter()
+                .filter_map(|v| v.as_f64().or_else(|| v.as_str().and_then(|s| s.trim().parse().ok())))
+                .collect();
+            if !values.is_empty() {
+                values.sort_by(|a, b| a.total_cmp(b));
+                let n = values.len();
+                stats.mean = Some(values.iter().sum::<f64>() / n as f64);
+                stats.median = Some(if n % 2 == 0 {
+                    (values[n / 2 - 1] + values[n / 2]) / 2.0
+                } else {
+                    values[n / 2]
+                });
+            }
+        }
+        QuestionType::SingleChoice | QuestionType::MultipleChoice | QuestionType::YesNo => {
+            // 선택지 순서 유지, 선택지에 없는 값(이전 버전 문항 등)은 뒤에 추가
+            let mut counts: Vec<OptionCount> = question
+                .options
+                .iter()
+                .flatten()
+                .map(|option| OptionCount { option: option.clone(), count: 0 })
+                .collect();
+            let labels = answers.iter().flat_map(|v| match v {
+                serde_json::Value::Array(items) => items.iter().filter_map(answer_label).collect::<Vec<_>>(),
+                other => answer_label(other).into_iter().collect(),
+            });
+            for label in labels {
+                match counts.iter_mut().find(|c| c.option == label) {
+                    Some(c) => c.count += 1,
+                    None => counts.push(OptionCount { option: label, count: 1 }),
+                }
+            }
+            stats.option_counts = counts;
+        }
+        QuestionType::Text | QuestionType::FileUpload => {}
+    }
+    stats
+}
+
+// ============ 내원 경로 ============
+
+/// 경로 목록 미설정 시 기본값
+const DEFAULT_REFERRAL_SOURCES: [&str; 7] = [
+    "지인 소개", "인터넷 검색", "블로그/카페", "SNS", "간판/지나가다", "기존 환자", "기타",
+];
+
+/// 내원 경로 값 정리 (앞뒤 공백 제거, 빈 값은 미입력)
+fn normalize_referral_source(source: Option<&str>) -> Option<String> {
+    source.map(str::trim).filter(|s| !s.is_empty()).map(str::to_string)
+}
+
+fn query_referral_sources(conn: &Connection) -> Vec<String> {
+    let sources: Option<String> = conn
+        .query_row("SELECT referral_sources FROM clinic_settings LIMIT 1", [], |row| row.get(0))
+        .ok()
+        .flatten();
+
+    sources
+        .and_then(|json| serde_json::from_str::<Vec<String>>(&json).ok())
+        .filter(|list| !list.is_empty())
+        .unwrap_or_else(|| DEFAULT_REFERRAL_SOURCES.iter().map(|s| s.to_string()).collect())
+}
+
+/// 내원 경로 선택지 목록 조회 (키오스크/접수 화면용)
+pub fn get_referral_sources() -> AppResult<Vec<String>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    Ok(query_referral_sources(&conn))
+}
+
+/// 내원 경로 선택지 목록 설정 (빈 목록이면 기본값으로 되돌림)
+pub fn set_referral_sources(sources: &[String]) -> AppResult<Vec<String>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let mut cleaned: Vec<String> = Vec::new();
+    for source in sources.iter().filter_map(|s| normalize_referral_source(Some(s))) {
+        if !cleaned.contains(&source) {
+            cleaned.push(source);
+        }
+    }
+
+    let value = if cleaned.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(&cleaned)?)
+    };
+    conn.execute(
+        "UPDATE clinic_settings SET referral_sources = ?, updated_at = ?",
+        params![value, Utc::now().to_rfc3339()],
+    )?;
+    Ok(query_referral_sources(&conn))
+}
+
+/// 기간 내 신규 환자의 내원 경로별 인원 (from/to: 'YYYY-MM-DD', 미지정 시 전체 기간)
+/// 설정된 경로는 0명이어도 포함, 미입력은 source = None으로 맨 뒤에 표시
+pub fn get_referral_breakdown(from: Option<&str>, to: Option<&str>) -> AppResult<Vec<ReferralCount>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    let mut stmt = conn.prepare(
+        r#"SELECT NULLIF(TRIM(referral_source), ''), COUNT(*)
+           FROM patients
+           WHERE deleted_at IS NULL AND substr(created_at, 1, 10) BETWEEN ?1 AND ?2
+           GROUP BY 1"#,
+    )?;
+    let rows = stmt
+        .query_map(
+            params![from.unwrap_or("0000-01-01"), to.unwrap_or("9999-12-31")],
+            |row| Ok(ReferralCount { source: row.get(0)?, count: row.get(1)? }),
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut counts: Vec<ReferralCount> = query_referral_sources(&conn)
+        .into_iter()
+        .map(|source| ReferralCount { source: Some(source), count: 0 })
+        .collect();
+    for row in rows {
+        match counts.iter_mut().find(|c| c.source == row.source) {
+            Some(existing) => existing.count = row.count,
+            None => counts.push(row),
+        }
+    }
+
+    // 인원 많은 순, 미입력은 항상 마지막
+    counts.sort_by(|a, b| {
+        a.source.is_none()
+            .cmp(&b.source.is_none())
+            .then_with(|| b.count.cmp(&a.count))
+    });
+    Ok(counts)
+}
+
+/// 키오스크에서 선택한 내원 경로를 세션에 기록 (환자 미지정 세션은 응답-환자 연결 시 반영)
+pub fn set_survey_session_referral_source(session_id: &str, source: Option<&str>) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let source = normalize_referral_source(source);
+    conn.execute(
+        "UPDATE survey_sessions SET referral_source = ?1 WHERE id = ?2",
+        params![source, session_id],
+    )?;
+
+    // 이미 환자가 지정된 세션이면 바로 반영 (기존 값은 유지)
+    if source.is_some() {
+        conn.execute(
+            r#"UPDATE patients SET referral_source = ?1
+               WHERE id = (SELECT patient_id FROM survey_sessions WHERE id = ?2)
+                 AND (referral_source IS NULL OR TRIM(referral_source) = '')"#,
+            params![source, session_id],
+        )?;
+    }
+    Ok(())
+}
+
+// ============ 매출 리포트 ============
+
+/// 처방 발행 시각의 로컬 날짜 ('YYYY-MM-DD', 날짜만 입력된 값은 그대로)
+fn local_date_of(value: &str) -> String {
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(value) {
+        return dt.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string();
+    }
+    // SQLite datetime() 형식('YYYY-MM-DD HH:MM:SS')은 UTC
+    if let Ok(naive) = chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S") {
+        return naive.and_utc().with_timezone(&chrono::Local).format("%Y-%m-%d").to_string();
+    }
+    value.chars().take(10).collect()
+}
+
+/// 기간별 매출 리포트 (from/to: 'YYYY-MM-DD' 로컬 날짜, 양 끝 포함)
+/// 발행일(issued_at)이 없으면 작성일 기준, price가 없는 처방은 제외
+/// 발행 시각은 UTC로 저장되므로 일/월 구분은 로컬 날짜로 변환해 집계
+pub fn get_revenue_report(from: &str, to: &str) -> AppResult<RevenueReport> {
+    let today = chrono::Local::now().date_naive();
+    let from_date = parse_local_date(Some(from), today)?;
+    let to_date = parse_local_date(Some(to), today)?;
+    let range_start = local_day_start_utc(from_date);
+    let range_end = local_day_start_utc(to_date + chrono::Duration::days(1));
+
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    // 날짜만 입력된 발행일('YYYY-MM-DD')은 로컬 날짜로 비교, 시각은 로컬 날짜 경계의 UTC 시각과 비교
+    let mut stmt = conn.prepare(
+        r#"SELECT COALESCE(issued_at, created_at) AS issued, price, insurance_type, COALESCE(prescription_name, formula, '처방')
+           FROM prescriptions
+           WHERE deleted_at IS NULL AND price IS NOT NULL
+             AND CASE WHEN length(COALESCE(issued_at, created_at)) = 10
+                      THEN COALESCE(issued_at, created_at) BETWEEN ?1 AND ?2
+                      ELSE julianday(COALESCE(issued_at, created_at)) >= julianday(?3)
+                           AND julianday(COALESCE(issued_at, created_at)) < julianday(?4)
+                 END"#,
+    )?;
+    let rows = stmt.query_map(params![from_date.to_string(), to_date.to_string(), range_start, range_end], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, Option<String>>(2)?,
+            row.get::<_, String>(3)?,
+        ))
+    })?;
+
+    let mut daily: std::collections::BTreeMap<String, RevenueBucket> = std::collections::BTreeMap::new();
+    let mut monthly: std::collections::BTreeMap<String, RevenueBucket> = std::collections::BTreeMap::new();
+    let mut by_name: std::collections::HashMap<String, TopPrescriptionRevenue> = std::collections::HashMap::new();
+    for row in rows {
+        let (issued, price, insurance_type, name) = row?;
+        let date = local_date_of(&issued);
+        let insured = if insurance_type.as_deref() == Some("insured") { price } else { 0 };
+        for (buckets, period) in [(&mut daily, date.clone()), (&mut monthly, date.chars().take(7).collect())] {
+            let bucket = buckets.entry(period.clone()).or_insert_with(|| RevenueBucket {
+                period,
+                count: 0,
+                total: 0,
+                insured: 0,
+                uninsured: 0,
+            });
+            bucket.count += 1;
+            bucket.total += price;
+            bucket.insured += insured;
+            bucket.uninsured += price - insured;
+        }
+        let top = by_name.entry(name.clone()).or_insert_with(|| TopPrescriptionRevenue {
+            prescription_name: name,
+            count: 0,
+            total: 0,
+        });
+        top.count += 1;
+        top.total += price;
+    }
+
+    let mut top_prescriptions: Vec<TopPrescriptionRevenue> = by_name.into_values().collect();
+    top_prescriptions.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.prescription_name.cmp(&b.prescription_name)));
+    top_prescriptions.truncate(10);
+
+    let monthly: Vec<RevenueBucket> = monthly.into_values().collect();
+    let prescription_count = monthly.iter().map(|b| b.count).sum();
+    let total = monthly.iter().map(|b| b.total).sum();
+    let insured = monthly.iter().map(|b| b.insured).sum();
+
+    Ok(RevenueReport {
+        from: from_date.to_string(),
+        to: to_date.to_string(),
+        prescription_count,
+        total,
+        insured,
+        uninsured: total - insured,
+        total_display: format_won(total),
+        daily: daily.into_values().collect(),
+        monthly,
+        top_prescriptions,
+    })
+}
+
+/// 원화 표시 (예: 1250000 → "1,250,000원")
+fn format_won(amount: i64) -> String {
+    let digits = amount.unsigned_abs().to_string();
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    if amount < 0 {
+        out.insert(0, '-');
+    }
+    format!("{}원", out)
+}
+
+// ============ 처방정의 초기화 ============
+
+/// 처방 정의 초기화 (전체 삭제 후 시드 재삽입)
+pub fn reset_prescription_definitions() -> AppResult<i32> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    conn.execute("DELETE FROM prescription_notes", [])?;
+    conn.execute("DELETE FROM prescription_case_studies", [])?;
+    conn.execute("DELETE FROM prescription_definitions", [])?;
+    conn.execute("DELETE FROM prescription_categories", [])?;
+
+    seed_prescription_definitions(&conn)?;
+
+    let count: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM prescription_definitions", [], |r| r.get(0),
+    )?;
+    Ok(count)
+}
+
+// ============ 전체 사용자 데이터 초기화 ============
+
+/// 전체 사용자 데이터 삭제 (처방정의/카테고리/약재는 유지)
+pub fn reset_all_user_data() -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    delete_all_user_data(&conn)?;
+    invalidate_survey_stats();
+
+    Ok(())
+}
+
+/// 환자와 환자에 딸린 기록 전체 삭제 (초기화, 백업 교체 복원용)
+fn delete_all_user_data(conn: &Connection) -> AppResult<()> {
+    conn.execute("DELETE FROM progress_notes", [])?;
+    conn.execute("DELETE FROM initial_charts", [])?;
+    conn.execute("DELETE FROM prescriptions", [])?;
+    conn.execute("DELETE FROM chart_records", [])?;
+    conn.execute("DELETE FROM medication_logs", [])?;
+    conn.execute("DELETE FROM medication_schedules", [])?;
+    conn.execute("DELETE FROM medication_management", [])?;
+    conn.execute("DELETE FROM survey_responses", [])?;
+    conn.execute("DELETE FROM survey_attachments", [])?;
+    conn.execute("DELETE FROM survey_sessions", [])?;
+    conn.execute("DELETE FROM patient_alerts", [])?;
+    conn.execute("DELETE FROM patients", [])?;
+    conn.execute("DELETE FROM tags", [])?;
+    Ok(())
+}
+
+// ============ 선택적 데이터 내보내기 ============
+
+/// 선택된 테이블만 JSON 내보내기
+pub fn export_selected_data(tables: Vec<String>) -> AppResult<String> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let mut export = serde_json::Map::new();
+
+    for table in &tables {
+        match table.as_str() {
+            "patients" => {
+                let patients = list_patients(None, &[])?;
+                export.insert("patients".to_string(), serde_json::to_value(&patients)?);
+            }
+            "prescriptions" => {
+                let items = list_all_prescriptions()?;
+                export.insert("prescriptions".to_string(), serde_json::to_value(&items)?);
+            }
+            "initial_charts" => {
+                let mut stmt = conn.prepare(
+                    "SELECT id, patient_id, doctor_name, chart_date, chief_complaint, present_illness, past_medical_history, notes, prescription_issued, prescription_issued_at, deleted_at, created_at, updated_at FROM initial_charts WHERE deleted_at IS NULL",
+                )?;
+                let rows = stmt.query_map([], |row| {
+                    Ok(serde_json::json!({
+                        "id": row.get::<_, String>(0)?,
+                        "patient_id": row.get::<_, String>(1)?,
+                        "doctor_name": row.get::<_, Option<String>>(2)?,
+                        "chart_date": row.get::<_, String>(3)?,
+                        "chief_complaint": row.get::<_, Option<String>>(4)?,
+                        "present_illness": row.get::<_, Option<String>>(5)?,
+                        "past_medical_history": row.get::<_, Option<String>>(6)?,
+                        "notes": row.get::<_, Option<String>>(7)?,
+                        "prescription_issued": row.get::<_, i32>(8)? != 0,
+                        "prescription_issued_at": row.get::<_, Option<String>>(9)?,
+                        "created_at": row.get::<_, String>(11)?,
+                        "updated_at": row.get::<_, String>(12)?,
+                    }))
+                })?;
+                let items: Vec<serde_json::Value> = rows.filter_map(|r| r.ok()).collect();
+                export.insert("initial_charts".to_string(), serde_json::Value::Array(items));
+            }
+            "progress_notes" => {
+                let mut stmt = conn.prepare(
+                    "SELECT id, patient_id, doctor_name, note_date, subjective, objective, assessment, plan, follow_up_plan, notes, prescription_issued, prescription_issued_at, deleted_at, created_at, updated_at FROM progress_notes WHERE deleted_at IS NULL",
+                )?;
+                let rows = stmt.query_map([], |row| {
+                    Ok(serde_json::json!({
+                        "id": row.get::<_, String>(0)?,
+                        "patient_id": row.get::<_, String>(1)?,
+                        "doctor_name": row.get::<_, Option<String>>(2)?,
+                        "note_date": row.get::<_, String>(3)?,
+                        "subjective": row.get::<_, Option<String>>(4)?,
+                        "objective": row.get::<_, Option<String>>(5)?,
+                        "assessment": row.get::<_, Option<String>>(6)?,
+                        "plan": row.get::<_, Option<String>>(7)?,
+                        "follow_up_plan": row.get::<_, Option<String>>(8)?,
+                        "notes": row.get::<_, Option<String>>(9)?,
+                        "prescription_issued": row.get::<_, i32>(10)? != 0,
+                        "prescription_issued_at": row.get::<_, Option<String>>(11)?,
+                        "created_at": row.get::<_, String>(13)?,
+                        "updated_at": row.get::<_, String>(14)?,
+                    }))
+                })?;
+                let items: Vec<serde_json::Value> = rows.filter_map(|r| r.ok()).collect();
+                export.insert("progress_notes".to_string(), serde_json::Value::Array(items));
+            }
+            "clinic_settings" => {
+                let settings = get_clinic_settings()?;
+                export.insert("clinic_settings".to_string(), serde_json::to_value(&settings)?);
+            }
+            "survey_templates" => {
+                let items = list_survey_templates()?;
+                export.insert("survey_templates".to_string(), serde_json::to_value(&items)?);
+            }
+            "survey_responses" => {
+                let items = list_survey_responses(None)?;
+                export.insert("survey_responses".to_string(), serde_json::to_value(&items)?);
+            }
+            _ => {
+                log::warn!("Unknown table for export: {}", table);
+            }
+        }
+    }
+
+    export.insert("exported_at".to_string(), serde_json::Value::String(Utc::now().to_rfc3339()));
+    Ok(serde_json::to_string_pretty(&serde_json::Value::Object(export))?)
+}
+
+// ============ DB 바이너리 백업/복원 ============
+
+/// DB 파일을 바이너리로 읽기
+pub fn export_db_binary() -> AppResult<Vec<u8>> {
+    let db_path = get_db_path()?;
+    let data = std::fs::read(&db_path)?;
+    Ok(data)
+}
+
+/// DB 파일을 바이너리로 덮어쓰기
+pub fn import_db_binary(data: Vec<u8>) -> AppResult<()> {
+    let db_path = get_db_path()?;
+    std::fs::write(&db_path, &data)?;
+    invalidate_survey_stats();
+    log::info!("Database binary imported to {:?} ({} bytes)", db_path, data.len());
+    Ok(())
+}
+
+// ============ 약재 기본 데이터 시드 ============
+
+fn seed_herbs(conn: &Connection) -> AppResult<()> {
+    let herbs = vec![
+        ("감초", 4.0, "g"), ("갈근", 8.0, "g"), ("계지", 6.0, "g"), ("작약", 6.0, "g"),
+        ("대추", 6.0, "g"), ("생강", 6.0, "g"), ("마황", 6.0, "g"), ("행인", 12.0, "g"),
+        ("석고", 32.0, "g"), ("자감초", 4.0, "g"), ("반하", 16.0, "g"), ("황련", 2.0, "g"),
+        ("황금", 6.0, "g"), ("인삼", 6.0, "g"), ("건강", 6.0, "g"), ("시호", 16.0, "g"),
+        ("대황", 8.0, "g"), ("망초", 8.0, "g"), ("후박", 8.0, "g"), ("지실", 10.0, "g"),
+        ("복령", 8.0, "g"), ("백출", 6.0, "g"), ("택사", 12.0, "g"), ("부자", 2.0, "g"),
+        ("세신", 4.0, "g"), ("오미자", 6.0, "g"), ("당귀", 6.0, "g"), ("천궁", 6.0, "g"),
+        ("숙지황", 8.0, "g"), ("산수유", 8.0, "g"), ("목단피", 6.0, "g"), ("산약", 8.0, "g"),
+        ("도인", 6.0, "g"), ("지모", 12.0, "g"), ("치자", 14.0, "g"), ("길경", 4.0, "g"),
+        ("방풍", 5.0, "g"), ("강활", 5.0, "g"), ("독활", 5.0, "g"), ("황기", 6.0, "g"),
+        ("귤피", 5.0, "g"), ("향부자", 5.0, "g"), ("의이인", 12.0, "g"), ("아교주", 4.0, "g"),
+        ("용골", 6.0, "g"), ("모려", 6.0, "g"), ("맥문동", 10.0, "g"), ("원지", 5.0, "g"),
+        ("건지황", 8.0, "g"),
+    ];
+
+    let now = Utc::now().to_rfc3339();
+    let mut stmt = conn.prepare(
+        "INSERT INTO herbs (name, default_dosage, unit, created_at) VALUES (?1, ?2, ?3, ?4)"
+    )?;
+
+    for (name, dosage, unit) in &herbs {
+        stmt.execute(params![name, dosage, unit, now])?;
+    }
+
+    Ok(())
+}
+
+// ============ 처방 정의 기본 데이터 시드 ============
+
+fn seed_prescription_definitions(conn: &Connection) -> AppResult<()> {
+    let definitions = vec![
+        ("갈근가반하탕", "", "마황제", "상한금궤", "마황:6/계지:4/자감초:4/갈근:8/작약:4/대추:6/생강:6/반하:16"),
+        ("갈근탕", "", "마황제", "상한금궤", "마황:6/계지:4/자감초:4/갈근:8/작약:4/대추:6/생강:6"),
+        ("갈근탕가천궁신이", "갈근가천신", "", "후세방", "마황:6/계지:4/자감초:4/갈근:8/작약:4/대추:6/생강:6/천궁:4/신이:4"),
+        ("갈근황금황련탕", "갈금련", "금련제", "상한금궤", "갈근:16/황련:6/황금:6/자감초:6"),
+        ("감맥대조탕", "", "감초제", "상한금궤", "생감초:6/대추:5/부소맥:28"),
+        ("감수반하탕", "", "함흉제", "상한금궤", "감수:0.6/반하:2/작약:2/자감초:1.4"),
+        ("감초건강탕", "", "건강제", "상한금궤", "자감초:8/건강:6"),
+        ("감초부자탕", "", "계지제", "상한금궤", "계지:8/자감초:4/부자:2/백출:4"),
+        ("감초사심탕", "감사", "금련제", "상한금궤", "황련:2/황금:6/인삼:6/반하:16/자감초:8/대추:6/건강:6"),
+        ("감초탕", "", "감초제", "상한금궤", "생감초:4"),
+        ("강삼조이", "", "기능성약물", "후세방", "생강:6/대추:6"),
+        ("건강부자탕", "", "부자제", "상한금궤", "건강:2/부자:2"),
+        ("건강황금황련인삼탕", "강금련인", "금련제", "상한금궤", "건강:6/황금:6/황련:6/인삼:6"),
+        ("계강조초황신부탕", "", "계지제", "상한금궤", "계지:6/작약:6/자감초:4/대추:6/마황:4/세신:4/부자:1"),
+        ("계마각반탕", "", "마황제", "상한금궤", "마황:2/계지:3/자감초:2/행인:7/작약:2/대추:2/생강:2"),
+        ("계작지모탕", "", "계지제", "상한금궤", "계지:8/작약:6/자감초:4/생강:10/지모:8/방풍:8/마황:4/백출:10/부자:2"),
+        ("계지가갈근탕", "", "계지제", "상한금궤", "계지:6/작약:6/자감초:4/대추:6/생강:6/갈근:8"),
+        ("계지가계탕", "", "계지제", "상한금궤", "계지:10/작약:6/자감초:4/대추:6/생강:6"),
+        ("계지가대황탕", "", "계지제", "상한금궤", "계지:6/작약:12/자감초:4/대추:6/생강:6/대황:4"),
+        ("계지가부자탕", "", "계지제", "상한금궤", "계지:6/작약:6/자감초:6/대추:6/생강:6/부자:1"),
+        ("계지가용골모려탕", "계용모", "계지제", "상한금궤", "계지:6/작약:6/자감초:4/대추:6/생강:6/용골:6/모려:6"),
+        ("계지가작약생강인삼신가탕", "신가탕", "계지제", "상한금궤", "계지:6/작약:8/자감초:4/대추:6/생강:8/인삼:6"),
+        ("계지가작약탕", "", "계지제", "상한금궤", "계지:6/작약:12/자감초:4/대추:6/생강:6"),
+        ("계지가황기탕", "", "계지제", "상한금궤", "계지:6/작약:6/자감초:4/대추:6/생강:6/황기:4"),
+        ("계지가후박행자탕", "", "계지제", "상한금궤", "계지:6/작약:6/자감초:4/대추:6/생강:6/후박:4/행인:15"),
+        ("계지거계가복령백출탕", "거계가영출탕", "계지제", "상한금궤", "작약:6/자감초:4/대추:6/생강:6/백출:6/복령:6"),
+        ("계지거작약가부자탕", "", "계지제", "상한금궤", "계지:6/자감초:4/생강:6/대추:6/부자:1"),
+        ("계지거작약탕", "", "계지제", "상한금궤", "계지:6/자감초:4/대추:6/생강:6"),
+        ("계지복령환", "계령", "도인제", "상한금궤", "계지:6/복령:8/도인:6/목단피:6/작약:6"),
+        ("계지부자탕", "", "계지제", "상한금궤", "계지:8/자감초:4/대추:6/생강:6/부자:3"),
+        ("계지생강지실탕", "계생지", "계지제", "상한금궤", "계지:6/생강:6/지실:10"),
+        ("계지인삼탕", "", "건강제", "상한금궤", "인삼:6/백출:6/자감초:8/건강:6/계지:8"),
+        ("계지탕", "", "계지제", "상한금궤", "계지:6/작약:6/자감초:4/대추:6/생강:6"),
+        ("과루계지탕", "", "계지제", "상한금궤", "괄루근:4/계지:6/작약:6/자감초:4/생강:6/대추:6"),
+        ("과루해백반하탕", "", "해백제", "상한금궤", "해백:6/과루인:16/반하:16"),
+        ("과루해백백주탕", "", "해백제", "상한금궤", "해백:16/과루인:16"),
+        ("곽향정기산", "곽정", "", "후세방", "곽향:7.5/자소엽:5/백지:2.5/대복피:2.5/복령:2.5/후박:2.5/백출:2.5/귤피:2.5/반하:2.5/길경:2.5/자감초:2.5/생강:3/대추:3"),
+        ("교애사물탕", "", "", "후세방", "당귀:6/작약:8/천궁:4/아교주:4/자감초:4/애엽:6/숙지황:8"),
+        ("구미강활탕", "", "", "후세방", "강활:7.5/방풍:7.5/천궁:6/백지:6/백출:6/황금:6/건지황:6/세신:2.5/자감초:2.5"),
+        ("궁귀교애탕", "", "당귀제", "상한금궤", "당귀:6/작약:8/천궁:4/아교주:4/자감초:4/애엽:6/건지황:8"),
+        ("귀비온담탕", "", "", "후세방", "귀비탕+온담탕"),
+        ("귀비탕", "", "", "후세방", "당귀:5/용안육:5/산조인:5/원지:5/인삼:5/황기:5/백출:5/복령:5/목향:2.5/자감초:1.5/생강:3/대추:3"),
+        ("귀비탕2", "", "", "후세방", "당귀:5/용안육:5/길초근:2/원지:5/인삼:5/황기:5/백출:5/복령:5/목향:2.5/자감초:1.5/생강:3/대추:3"),
+        ("귀출파징탕", "", "", "후세방", "향부자:7.5/삼릉:5/봉출:5/작약:5/당귀미:5/청피:5/오약:3.5/홍화:2.5/소목:2.5/육계:2.5"),
+        ("귤피대황박초탕", "", "귤피제", "상한금궤", "귤피:3/대황:6/망초:6"),
+        ("귤피죽여탕", "귤죽", "귤피제", "상한금궤", "귤피:16/죽여:4/대추:15/생강:16/자감초:10/인삼:2"),
+        ("귤피지실생강탕", "귤지생", "귤피제", "상한금궤", "귤피:32/지실:6/생강:16"),
+        ("귤피탕", "", "귤피제", "상한금궤", "귤피:8/생강:16"),
+        ("금은화연교", "", "기능성약물", "후세방", "금은화:16/연교:8"),
+        ("길경탕", "", "감초제", "상한금궤", "생감초:4/길경:2"),
+        ("녹용쌍금탕", "", "", "후세방", "숙지황:5/황기:5/당귀:5/천궁:5/육계:3.5/작약:12.5/백출:10/후박:5/귤피:5/곽향:5/반하:5/자감초:5/생강:3/대추:3/뉴분골:2"),
+        ("녹용쌍패탕", "", "", "후세방", "숙지황:5/황기:5/당귀:5/천궁:5/육계:3.5/작약:12.5/인삼:5/시호:5/전호:5/독활:5/강활:5/지각:5/길경:5/복령:5/자감초:5/생강:3/대추:3/박하:3/뉴분골:2"),
+        ("녹용쌍화탕", "", "", "후세방", "숙지황:5/황기:5/당귀:5/천궁:5/계지:3.5/자감초:3.5/작약:12.5/생강:3/대추:3/뉴분골:2.5"),
+        ("당귀건중탕", "", "계지제", "상한금궤", "계지:6/작약:12/자감초:4/대추:6/생강:6/당귀:8"),
+        ("당귀사역가오수유생강탕", "당사오", "계지제", "상한금궤", "계지:6/작약:6/자감초:4/대추:12/생강:16/당귀:6/목통:4/세신:6/오수유:4"),
+        ("당귀사역탕", "", "계지제", "상한금궤", "계지:6/작약:6/자감초:4/대추:12/당귀:6/목통:4/세신:6"),
+        ("당귀수산", "", "", "후세방", "당귀미:7.5/적작약:5/오약:5/향부자:5/소목:5/홍화:4/도인:3.5/육계:3/자감초:2.5"),
+        ("당귀작약산", "당작", "당귀제", "상한금궤", "당귀:6/작약:12/천궁:12/복령:8/백출:8/택사:12"),
+        ("대건중탕", "", "건강제", "상한금궤", "인삼:4/건강:8/교이:20/촉초:2"),
+        ("대승기탕", "", "대황제", "상한금궤", "대황:8/망초:7/후박:16/지실:10"),
+        ("대시함", "", "", "고시방", "대시호탕+소함흉탕"),
+        ("대시함마", "", "", "고시방", "대시호탕+소함흉탕+마행의감"),
+        ("대시함박", "", "", "고시방", "대시호탕+소함흉탕+반하후박탕"),
+        ("대시호가망초탕", "", "시호제", "상한금궤", "시호:16/반하:16/황금:6/지실:8/작약:6/대황:4/생강:10/대추:6/망초:4"),
+        ("대시호탕", "", "시호제", "상한금궤", "시호:16/반하:16/황금:6/지실:8/작약:6/대황:4/생강:10/대추:6"),
+        ("대영전", "", "", "후세방", "숙지황:15/당귀:10/구기자:10/두충:10/우슬:7.5/육계:5/자감초:5"),
+        ("대청룡탕", "", "마황제", "상한금궤", "마황:12/계지:4/자감초:4/행인:12/석고:24/대추:5/생강:6"),
+        ("대탕포방기", "대포", "", "고시방", "대함흉탕+목방기탕+방기황기탕+방기복령탕+방기지황탕"),
+        ("대함흉탕", "", "함흉제", "상한금궤", "대황:12/망초:24/감수:2"),
+        ("대함흉환", "", "함흉제", "상한금궤", "대황:16/정력자:16/망초:12/행인:10/감수:2"),
+        ("대함흉환급탕", "대함환급탕", "", "고시방", "대함흉탕+대함흉환"),
+        ("대황감수탕", "", "대황제", "상한금궤", "대황:8/감수:4/아교주:4"),
+        ("대황망초탕", "", "대황제", "상한금궤", "대황:8/황백:8/망초:8/치자:3"),
+        ("대황목단피탕", "", "도인제", "상한금궤", "도인:8/망초:8/대황:8/목단피:6/동과자:12"),
+        ("대황부자탕", "", "대황제", "상한금궤", "대황:6/부자:3/세신:4"),
+        ("대황황련사심탕", "", "금련제", "상한금궤", "황련:2/황금:2/대황:4"),
+        ("도핵승기탕", "", "도인제", "상한금궤", "도인:6/계지:4/망초:4/대황:8/자감초:4"),
+        ("도화탕", "", "기타고방", "상한금궤", "적석지:32/건강:2/갱미:30"),
+        ("독활기생탕", "", "", "후세방", "독활:7/당귀:7/작약:7/상기생:7/숙지황:5/천궁:5/인삼:5/복령:5/우슬:5/두충:5/진교:5/세신:5/방풍:5/육계:5/자감초:3/생강:3"),
+        ("독활지황탕", "", "", "사상방", "숙지황:16/산수유:8/복령:6/택사:6/목단피:4/방풍:4/독활:4"),
+        ("두충우슬", "", "기능성약물", "후세방", "두충:6/우슬:6"),
+        ("마자인환", "", "대황제", "상한금궤", "마자인:32/작약:16/지실:16/대황:32/후박:20/행인:20"),
+        ("마행감석탕", "", "마황제", "상한금궤", "마황:8/행인:15/자감초:4/석고:16"),
+        ("마행의감2", "", "마황제", "상한금궤", "마황:8/행인:4/의이인:24/자감초:4"),
+        ("마행의감3", "", "마황제", "상한금궤", "마황:8/행인:4/의이인:36/자감초:4"),
+        ("마행의감탕", "", "마황제", "상한금궤", "마황:8/행인:4/의이인:12/자감초:4"),
+        ("마황가출탕", "", "마황제", "상한금궤", "마황:6/계지:4/자감초:2/행인:21/백출:8"),
+        ("마황부자감초탕", "", "마황제", "상한금궤", "마황:4/자감초:4/부자:1"),
+        ("마황부자세신탕", "마부신/마신부", "마황제", "상한금궤", "마황:4/부자:1/세신:4"),
+        ("마황연교적소두탕", "마연적", "마황제", "상한금궤", "마황:4/연교:4/행인:4/적소두:28/대추:6/상백피:20/생강:4/자감초:4"),
+        ("마황탕", "", "마황제", "상한금궤", "마황:6/계지:4/자감초:2/행인:21"),
+        ("맥문동탕", "", "반하제", "상한금궤", "반하:32/인삼:6/대추:6/자감초:4/갱미:9/맥문동:15"),
+        ("맥문후박탕", "", "", "고시방", "맥문동탕+반하후박탕"),
+        ("목방기탕", "", "방기제", "상한금궤", "방기:6/계지:4/석고:24/인삼:8"),
+        ("목방기탕거석고가복령망초탕", "거석복망/복망탕/복망", "방기제", "상한금궤", "방기:4/계지:4/인삼:8/망초:8/복령:8"),
+        ("반하백출천마탕", "반백천", "", "후세방", "반하:7/귤피:7/맥아:7/백출:5/신곡:5/인삼:2.5/황기:2.5/천마:2.5/복령:2.5/택사:2.5/건강:1.5/황백:1/생강:3"),
+        ("반하백출천마탕2", "반백천2", "", "후세방", "반하:7/귤피:7/맥아:7/백출:5/신곡:5/인삼:2.5/황기:2.5/천마:2.5/복령:2.5/택사:2.5/건강:1.5/황금:2/생강:3"),
+        ("반하사심탕", "반사", "금련제", "상한금궤", "황련:2/황금:6/인삼:6/반하:16/자감초:6/대추:6/건강:6"),
+        ("반하후박탕", "", "반하제", "상한금궤", "반하:32/생강:10/복령:8/후박:6/자소엽:4"),
+        ("방기복령탕", "방복", "방기제", "상한금궤", "방기:6/계지:6/황기:6/자감초:4/복령:12"),
+        ("방기지황탕", "방지", "방기제", "상한금궤", "방기:6/계지:6/자감초:2/건지황:8/방풍:6"),
+        ("방기황기탕", "방황", "방기제", "상한금궤", "방기:8/황기:9/백출:6/자감초:4"),
+        ("배농산", "", "감초제", "상한금궤", "길경:2/작약:6/지실:10"),
+        ("배농산급탕", "", "감초제", "상한금궤", "생감초:4/길경:6/대추:5/생강:2/작약:6/지실:10"),
+        ("배농탕", "", "감초제", "상한금궤", "생감초:4/길경:6/대추:5/생강:2"),
+        ("백대갈", "", "", "고시방", "백호탕+대시호탕+갈금련"),
+        ("백엽탕", "", "기타고방", "상한금궤", "측백엽:9/건강:9/애엽:9"),
+        ("백인2", "", "석고제", "상한금궤", "석고:64/지모:24/갱미:18/자감초:4/인삼:6"),
+        ("백인3", "", "석고제", "상한금궤", "석고:96/지모:36/갱미:18/자감초:4/인삼:6"),
+        ("백자팔", "", "", "고시방", "백인+자감+팔미"),
+        ("백출부자탕", "", "계지제", "상한금궤", "자감초:4/대추:6/생강:6/부자:3/백출:8"),
+        ("백호2", "", "석고제", "상한금궤", "석고:64/지모:24/갱미:18/자감초:4"),
+        ("백호3", "", "석고제", "상한금궤", "석고:96/지모:36/갱미:18/자감초:4"),
+        ("백호가계지탕", "", "석고제", "상한금궤", "석고:32/지모:12/갱미:6/자감초:4/계지:6"),
+        ("백호가인삼탕", "백인", "석고제", "상한금궤", "석고:32/지모:12/갱미:18/자감초:4/인삼:6"),
+        ("백호탕", "", "석고제", "상한금궤", "석고:32/지모:12/갱미:18/자감초:4"),
+        ("보중익기탕", "", "", "후세방", "황기:7.5/인삼:5/백출:5/자감초:5/당귀:2.5/귤피:2.5/승마:1.5/시호:1.5"),
+        ("보중치습탕", "", "", "후세방", "인삼:5/백출:5/창출:3.5/귤피:3.5/복령:3.5/맥문동:3.5/목통:3.5/당귀:3.5/황금:2.5/후박:1.5/승마:1.5"),
+        ("보화탕", "", "", "후세방", "귤피:7.5/나복자:5/맥아:5/산사:5/향부자:5/후박:5/자감초:2.5/연교:2.5/"),
+        ("복령사역탕", "", "부자제", "상한금궤", "부자:2/자감초:4/건강:3/인삼:2/복령:8"),
+        ("복령음", "", "귤피제", "상한금궤", "인삼:6/백출:6/복령:6/지실:4/귤피:5/생강:8"),
+        ("복령택사탕", "", "복령제", "상한금궤", "복령:16/계지:4/자감초:2/백출:6/택사:8/생강:8"),
+        ("복령행인감초탕", "", "복령제", "상한금궤", "복령:6/행인:4/자감초:2"),
+        ("부자갱미탕", "", "부자제", "상한금궤", "부자:1/자감초:2/반하:16/대추:5/갱미:15"),
+        ("부자사심탕", "", "금련제", "상한금궤", "대황:4/황련:2/황금:2/부자:2"),
+        ("부자탕", "", "부자제", "상한금궤", "부자:2/인삼:4/백출:8/복령:6/작약:6"),
+        ("불수산", "", "", "후세방", "당귀:30/천궁:20"),
+        ("불환금정기산", "", "", "후세방", "백출:10/후박:5/귤피:5/곽향:5/반하:5/자감초:5/생강:3/대추:3"),
+        ("사간마황탕", "", "마황제", "상한금궤", "사간:6/마황:8/생강:8/세신:6/자완:6/관동화:6/오미자:6/대추:3/반하:16"),
+        ("사군자탕", "", "", "후세방", "인삼:6/백출:6/복령:6/자감초:6"),
+        ("사물탕", "", "", "후세방", "숙지황:6/당귀:6/천궁:6/작약:6"),
+        ("사역가인삼탕", "", "부자제", "상한금궤", "부자:2/자감초:4/건강:3/인삼:2"),
+        ("사역산", "", "시호제", "상한금궤", "시호:16/지실:16/작약:16/자감초:16"),
+        ("사역탕", "", "부자제", "상한금궤", "부자:2/자감초:4/건강:3"),
+        ("산조인탕", "", "당귀제", "상한금궤", "산조인:9/자감초:2/지모:4/복령:4/천궁:4"),
+        ("삼단탕", "", "", "현대처방", "단삼:6/적작약:6/홍화:6"),
+        ("삼릉봉출", "", "기능성약물", "후세방", "삼릉:6/봉출:6"),
+        ("삼물황금탕", "", "금련제", "상한금궤", "황금:2/고삼:4/건지황:8"),
+        ("삼방기탕", "", "", "고시방", "방기황기탕+방기복령탕+방기지황탕"),
+        ("삼출건비탕", "", "", "후세방", "인삼:5/백출:5/복령:5/후박:5/귤피:5/산사:5/지실:4/작약:4/사인:2.5/신곡:2.5/맥아:2.5/자감초:2.5/생강:3/대추:3"),
+        ("삼황사심탕", "", "금련제", "상한금궤", "황련:6/황금:6/대황:6"),
+        ("생간건비탕1", "", "", "현대처방", "인진호:12/갈근:8/울금:6/단삼:6/하수오:6/구기자:10/산사:6/백출:6/귤피:6/후박:6/자감초:3/곽향:3/신곡:3/맥아:3"),
+        ("생강감초탕", "", "건강제", "상한금궤", "생강:10/인삼:6/자감초:8/대추:6"),
+        ("생강사심탕", "생사", "금련제", "상한금궤", "황련:2/황금:6/인삼:6/반하:16/자감초:6/대추:6/건강:2/생강:8"),
+        ("생맥산", "", "", "후세방", "맥문동:10/인삼:5/오미자:5"),
+        ("선복대자석탕", "", "금련제", "상한금궤", "선복화:6/인삼:4/생강:10/대자석:2/자감초:6/반하:10/대추:6"),
+        ("소건중탕", "", "계지제", "상한금궤", "계지:6/작약:12/자감초:4/대추:6/생강:6/교이:40"),
+        ("소건중탕2", "소건중2", "계지제", "상한금궤", "계지:6/작약:12/자감초:4/대추:6/생강:6/교이:20"),
+        ("소목홍화", "", "기능성약물", "후세방", "소목:6/홍화:6"),
+        ("소반하가복령탕", "", "반하제", "상한금궤", "반하:32/생강:16/복령:6"),
+        ("소반하탕", "", "반하제", "상한금궤", "반하:32/생강:16"),
+        ("소승기탕", "", "대황제", "상한금궤", "대황:8/후박:4/지실:6"),
+        ("소시호가망초탕", "", "시호제", "상한금궤", "시호:16/반하:16/황금:6/인삼:6/자감초:6/생강:6/대추:6/망초:4"),
+        ("소시호탕", "", "시호제", "상한금궤", "시호:16/반하:16/황금:6/인삼:6/자감초:6/생강:6/대추:6"),
+        ("소청룡가석고탕", "소청룡석고", "마황제", "상한금궤", "마황:6/계지:6/자감초:6/작약:6/오미자:6/반하:16/건강:6/세신:6/석고:4"),
+        ("소청룡탕", "", "마황제", "상한금궤", "마황:6/계지:6/자감초:6/작약:6/오미자:6/반하:16/건강:6/세신:6"),
+        ("소함흉탕", "", "함흉제", "상한금궤", "황련:3/반하:16/과루인:16"),
+        ("소함흉탕2", "소함흉2", "금련제", "상한금궤", "황련:3/반하:16/과루실:16"),
+        ("승마갈근탕", "", "", "후세방", "갈근:10/승마:5/작약:5/자감초:5/생강:5"),
+        ("시령탕", "", "", "고시방", "소시호탕+오령산"),
+        ("시박탕", "", "", "고시방", "소시호탕+반하후박탕"),
+        ("시평탕", "", "", "고시방", "소시호탕+평위산"),
+        ("시함마", "", "", "고시방", "소시호탕+소함흉탕+마행의감탕"),
+        ("시함마농", "", "", "고시방", "시함마+배농산급탕"),
+        ("시함박", "", "", "고시방", "소시호탕+소함흉탕+반하후박탕"),
+        ("시함박농", "", "", "고시방", "시함박+배농산급탕"),
+        ("시함은화탕", "", "", "고시방", "시함마농+시함박농+금은화연교"),
+        ("시함중", "", "", "고시방", "시함탕+이중탕"),
+        ("시함탕", "", "", "후세방", "소시호탕+소함흉탕"),
+        ("시호가용골모려탕", "시용모/시모", "시호제", "상한금궤", "시호:8/반하:8/황금:3/인삼:3/생강:3/대추:3/대황:4/복령:3/계지:3/용골:3/모려:3"),
+        ("시호거반하가과루탕", "", "금련제", "상한금궤", "시호:16/인삼:6/황금:6/자감초:6/괄루근:8/생강:4/대추:6"),
+        ("시호계지건강탕", "시계건", "시호제", "상한금궤", "시호:16/계지:6/건강:4/괄루근:8/황금:6/모려:4/자감초:4"),
+        ("시호계지탕", "시계", "시호제", "상한금궤", "시호:8/반하:8/황금:3/인삼:3/자감초:2/생강:3/대추:3/작약:3/계지:3"),
+        ("십미패독산", "", "", "후세방", "시호:5/화피:5/길경:5/천궁:5/복령:5/독활:3.5/방풍:3.5/형개:2.5/생감초:2.5/생강:3.5"),
+        ("십전대보탕", "", "", "후세방", "인삼:6/백출:6/복령:6/자감초:6/숙지황:6/작약:6/천궁:6/당귀:6/황기:5/육계:5/생강:5/대추:5"),
+        ("쌍갈탕", "", "", "후세방", "원방쌍화탕+갈근탕"),
+        ("쌍금탕", "", "", "후세방", "숙지황:5/황기:5/당귀:5/천궁:5/육계:3.5/작약:12.5/백출:10/후박:5/귤피:5/곽향:5/반하:5/자감초:5/생강:3/대추:3"),
+        ("쌍패탕", "", "", "후세방", "원방쌍화탕+인삼패독산"),
+        ("억간산", "", "", "후세방", "백출:10/복령:10/당귀:7.5/천궁:7.5/조구등:7.5/시호:5/자감초:4"),
+        ("연령고본단", "", "", "후세방", "구기자:10/두충:10/맥문동:10/목향:10/백자인:10/복령:10/복분자:7.5/산수유:10/산약:10/건지황:10/석창포:5/숙지황:10/오미자:10/우슬:10/원지:5/육종용:20/인삼:10/지골피:7.5/차전자:7.5/천문동:10/천초:5/택사:5/토사자:20/파극천:10"),
+        ("영감강미신탕", "", "복령제", "상한금궤", "복령:8/자감초:6/건강:6/오미자:6/세신:6"),
+        ("영감강미신하인탕", "", "복령제", "상한금궤", "복령:8/자감초:6/건강:6/오미자:6/세신:6/반하:16/행인:6"),
+        ("영감강미신하인황탕", "", "복령제", "상한금궤", "복령:8/자감초:6/건강:6/오미자:6/세신:6/반하:16/행인:6/대황:6"),
+        ("영감강미신하탕", "", "복령제", "상한금궤", "복령:8/자감초:6/건강:6/오미자:6/세신:6/반하:16"),
+        ("영강출감탕", "", "복령제", "상한금궤", "복령:8/자감초:4/건강:8/백출:4"),
+        ("영계감조탕", "", "복령제", "상한금궤", "복령:16/계지:8/자감초:4/대추:8"),
+        ("영계미감탕", "", "복령제", "상한금궤", "복령:8/계지:8/오미자:6/자감초:6"),
+        ("영계출감탕", "", "복령제", "상한금궤", "복령:8/계지:6/자감초:4/백출:4"),
+        ("오령산", "", "복령제", "상한금궤", "복령:6/계지:4/백출:6/택사:10/저령:6"),
+        ("오매환", "", "금련제", "상한금궤", "오매:6/건강:6/당귀:6/세신:4/계지:4/인삼:4/황련:4/황백:3/촉초:2/부자:2"),
+        ("오수유탕", "", "당귀제", "상한금궤", "오수유:5/인삼:6/대추:6/생강:12"),
+        ("오자탕", "", "", "후세방", "사상자:5/육종용:5/복분자:5/구기자:5/토사자:5"),
+        ("온경탕", "", "당귀제", "상한금궤", "당귀:4/작약:4/천궁:4/아교주:4/자감초:4/인삼:4/계지:4/생강:4/목단피:4/반하:16/맥문동:5/오수유:6"),
+        ("온담탕", "", "", "후세방", "반하:10/귤피:10/복령:10/지실:10/죽여:5/자감초:2.5/생강:3/대추:3"),
+        ("원방쌍화탕", "", "", "후세방", "숙지황:5/황기:5/당귀:5/천궁:5/육계:3.5/자감초:3.5/작약:12.5/생강:3/대추:3"),
+        ("월비가반하탕", "", "마황제", "상한금궤", "마황:12/석고:16/생강:6/대추:8/자감초:4/반하:16"),
+        ("월비가출탕", "", "마황제", "상한금궤", "마황:12/자감초:4/석고:16/대추:8/생강:6/백출:8"),
+        ("월비탕", "", "마황제", "상한금궤", "마황:12/자감초:4/석고:16/대추:8/생강:6"),
+        ("육군자탕", "", "", "후세방", "반하:7.5/백출:7.5/귤피:5/복령:5/인삼:5/자감초:2.5"),
+        ("육미지황탕", "육미", "복령제", "상한금궤", "건지황:16/산약:8/산수유:8/복령:6/택사:6/목단피:6"),
+        ("육미지황탕2", "육미2", "복령제", "상한금궤", "숙지황:16/산약:8/산수유:8/복령:6/택사:6/목단피:6"),
+        ("의이부자패장산", "", "부자제", "상한금궤", "부자:2/의이인:16/패장:8"),
+        ("이진탕", "", "", "후세방", "반하:10/귤피:5/복령:5/자감초:2.5"),
+        ("인삼탕", "이중탕", "건강제", "상한금궤", "인삼:6/백출:6/자감초:6/건강:6"),
+        ("인삼패독산", "", "", "후세방", "인삼:5/시호:5/전호:5/독활:5/강활:5/지각:5/길경:5/천궁:5/복령:5/자감초:5/생강:3/박하:3"),
+        ("인숙산", "", "", "후세방", "백자인:8/숙지황:8/인삼:6/지각:6/오미자:6/계지:6/산수유:6/감국:6/복령:6/구기자:6"),
+        ("인진오령산", "", "복령제", "상한금궤", "인진호:12/택사:8/복령:8/백출:6/계지:6/저령:6"),
+        ("인진호탕", "", "치자제", "상한금궤", "치자:14/대황:4/인진호:12"),
+        ("일반쌍화탕", "", "", "후세방", "숙지황:5/황기:5/당귀:5/천궁:5/계지:3.5/자감초:3.5/작약:12.5/생강:3/대추:3"),
+        ("자감초탕", "자감", "계지제", "상한금궤", "계지:6/자감초:8/대추:10/생강:6/인삼:4/맥문동:10/건지황:8/마자인:8/아교주:4"),
+        ("자음강화탕", "", "", "후세방", "작약:6.5/당귀:6/숙지황:5/천문동:5/맥문동:5/백출:5/건지황:4/귤피:3.5/지모:2.5/황백:2.5/자감초:2.5/생강:3/대추:3"),
+        ("작약감초부자탕", "", "계지제", "상한금궤", "작약:6/자감초:6/부자:1"),
+        ("작약감초탕", "작감탕", "계지제", "상한금궤", "작약:8/자감초:8"),
+        ("저령차전자탕", "", "", "사상방", "택사:10/복령:10/저령:7.5/차전자:7.5/지모:5/석고:5/강활:5/독활:5/형개:5/방풍:5"),
+        ("저령탕", "", "복령제", "상한금궤", "복령:6/택사:6/저령:6/아교주:6"),
+        ("적소두당귀산", "", "당귀제", "상한금궤", "적소두:42/당귀:3"),
+        ("조경종옥탕", "", "", "후세방", "숙지황:7.5/향부자:7.5/당귀:5/오수유:5/천궁:5/작약:4/복령:4/귤피:4/현호색:4/목단피:4/건강:4/육계:2.5/애엽:2.5/생강:3"),
+        ("조위승기탕", "", "대황제", "상한금궤", "대황:8/망초:12/자감초:4"),
+        ("죽엽석고탕", "죽석", "석고제", "상한금궤", "석고:32/갱미:15/자감초:4/죽엽:4/인삼:4/맥문동:20/반하:16"),
+        ("중시호가망초탕", "", "", "시호제", "소시호가망초탕+대시호가망초탕"),
+        ("중시호탕", "", "시호제", "상한금궤", "시호:16/반하:16/황금:6/인삼:6/자감초:6/지실:8/작약:6/대황:4/생강:10/대추:6"),
+        ("지백지황환", "", "", "후세방", "건지황:16/산약:8/산수유:8/복령:6/택사:6/목단피:6/지모:4/황백:4"),
+        ("지실치자탕", "", "치자제", "상한금궤", "지실:6/치자:14/두시:3"),
+        ("지실해백계지탕", "", "해백제", "상한금궤", "해백:16/과루인:16/계지:2/후박:8/지실:8"),
+        ("지출탕", "", "기타고방", "상한금궤", "지실:10/백출:8"),
+        ("진무탕", "", "부자제", "상한금궤", "부자:1/백출:4/복령:6/작약:6/생강:6"),
+        ("천마구등음", "", "", "후세방", "조구등:12/천마:9/황금:9/복령:9/익모초:9/야교등:9/석결명:18/상기생:9/치자:9/두충:9/우슬:12"),
+        ("천왕보심단", "", "", "후세방", "길경:2.5/단삼:2.5/당귀:5/맥문동:5/백자인:5/복령:2.5/산조인:5/건지황:5/오미자:5/원지:2.5/인삼:2.5/천문동:5/현삼:2.5"),
+        ("체감탕", "체감탕", "다이어트", "", "의이인:300/숙지황:160/용안육:120/당귀:120/황기:120/괄루근:80/산약:80/상백피:80/귤피:80/천궁:60"),
+        ("총명탕", "", "", "후세방", "원지:10/석창포:10/복령:10"),
+        ("축천환", "", "", "후세방", "오약:5/익지인:5"),
+        ("치자감초시탕", "", "치자제", "상한금궤", "치자:14/자감초:4/두시:20"),
+        ("치자건강탕", "", "치자제", "상한금궤", "치자:14/건강:2"),
+        ("치자대황탕", "", "치자제", "상한금궤", "치자:14/대황:2/지실:10/두시:20"),
+        ("치자벽피탕", "", "치자제", "상한금궤", "치자:14/황백:4/자감초:2"),
+        ("치자생강시탕", "", "치자제", "상한금궤", "치자:14/생강:10/두시:20"),
+        ("치자시탕", "치시", "치자제", "상한금궤", "치자:14/두시:20"),
+        ("치자시탕2", "치자시2", "치자제", "상한금궤", "치자:6/두시:20"),
+        ("치자후박탕", "", "치자제", "상한금궤", "치자:14/후박:8/지실:8"),
+        ("택사탕", "", "복령제", "상한금궤", "백출:4/택사:10"),
+        ("통맥사역탕", "", "부자제", "상한금궤", "부자:3/자감초:4/건강:6"),
+        ("팔미환", "팔미", "복령제", "상한금궤", "건지황:16/산약:8/산수유:8/복령:6/택사:6/목단피:6/계지:2/부자:2"),
+        ("팔미환2", "팔미2", "복령제", "상한금궤", "숙지황:16/산약:8/산수유:8/복령:6/택사:6/목단피:6/육계:2/부자:2"),
+        ("평위산", "", "", "후세방", "백출:10/귤피:7/후박:5/자감초:3/생강:3/대추:3"),
+        ("포방기탕", "", "", "고시방", "목방기탕+방기황기탕+방기복령탕+방기지황탕"),
+        ("향사갈금련", "", "", "고시방", "향사평위산+갈금련"),
+        ("향사육군자탕", "", "", "후세방", "향부자:5/백출:5/복령:5/반하:5/귤피:5/백두구:5/후박:5/사인:2.5/인삼:2.5/목향:2.5/익지인:2.5/자감초:2.5/생강:3/대추:3"),
+        ("향사평위산", "", "", "후세방", "백출:10/귤피:5/향부자:5/지실:4/곽향:4/후박:3.5/사인:3.5/목향:2.5/자감초:2.5/생강:3"),
+        ("현부이경탕", "", "", "후세방", "육계:3.5/귤피:5/당귀:5/도인:5/목향:3.5/작약:5/봉출:5/생강:6/오약:7.5/지각:5/백출:7.5/천궁:5/향부자:15/현호색:5/홍화:3.5"),
+        ("형방사백산", "", "", "사상방", "건지황:10/복령:10/택사:10/석고:5/지모:5/강활:5/독활:5/형개:5/방풍:5"),
+        ("형방지황탕", "", "", "사상방", "숙지황:10/산수유:10/복령:10/택사:10/차전자:5/강활:5/독활:5/형개:5/방풍:5"),
+        ("형방패독산", "", "", "후세방", "인삼:5/시호:5/전호:5/독활:5/강활:5/지각:5/길경:5/천궁:5/복령:5/자감초:5/형개:5/방풍:5"),
+        ("환탕포방기", "환포", "", "고시방", "대함흉탕+대함흉환+목방기탕+방기황기탕+방기복령탕+방기지황탕"),
+        ("황금탕", "", "금련제", "상한금궤", "황금:6/작약:4/자감초:4/대추:6"),
+        ("황기건중탕", "", "계지제", "상한금궤", "계지:6/작약:12/자감초:6/대추:6/생강:6/교이:40/황기:3"),
+        ("황기건중탕2", "황기건중2", "계지제", "상한금궤", "계지:6/작약:12/자감초:6/대추:6/생강:6/교이:20/황기:6"),
+        ("황기계지오물탕", "황계오물탕/황계오물", "계지제", "상한금궤", "황기:6/계지:6/작약:6/생강:12/대추:6"),
+        ("황기작약계지고주탕", "기작계주", "계지제", "상한금궤", "황기:10/작약:6/계지:6"),
+        ("황련아교탕", "황아", "금련제", "상한금궤", "황련:8/황금:4/작약:4/아교주:3"),
+        ("황련탕", "", "금련제", "상한금궤", "황련:6/건강:6/인삼:4/자감초:6/대추:6/반하:16/계지:6"),
+        ("황련해독탕", "", "", "후세방", "황련:6/황금:6/황백:6/치자:6"),
+        ("후박마황탕", "", "마황제", "상한금궤", "후박:10/마황:8/석고:20/행인:8/반하:16/건강:4/세신:4/부소맥:28/오미자:6"),
+        ("후박삼물탕", "", "대황제", "상한금궤", "후박:16/대황:8/지실:10"),
+        ("후박칠물탕", "", "대황제", "상한금궤", "대황:6/후박:16/지실:10/자감초:6/대추:5/계지:4/생강:10"),
+        ("후생반감인탕", "", "반하제", "상한금궤", "후박:16/생강:16/반하:16/자감초:4/인삼:2"),
+    ];
+
+    let now = Utc::now().to_rfc3339();
+    let mut stmt = conn.prepare(
+        "INSERT INTO prescription_definitions (name, alias, category, source, composition, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"
+    )?;
+
+    for (name, alias, category, source, composition) in &definitions {
+        let alias_val = if alias.is_empty() { None } else { Some(*alias) };
+        let category_val = if category.is_empty() { None } else { Some(*category) };
+        stmt.execute(params![name, alias_val, category_val, source, composition, now, now])?;
+    }
+
+    Ok(())
+}
+
+// ============ 약재 재고관리 ============
+
+pub fn list_herb_inventory() -> AppResult<Vec<HerbInventory>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, herb_id, name, unit, current_stock, min_stock, cost_per_unit, supplier, memo, created_at, updated_at FROM herb_inventory ORDER BY name"
+    )?;
+    let items = stmt.query_map([], |row| {
+        Ok(HerbInventory {
+            id: row.get(0)?,
+            herb_id: row.get(1)?,
+            name: row.get(2)?,
+            unit: row.get(3)?,
+            current_stock: row.get(4)?,
+            min_stock: row.get(5)?,
+            cost_per_unit: row.get(6)?,
+            supplier: row.get(7)?,
+            memo: row.get(8)?,
+            created_at: row.get(9)?,
+            updated_at: row.get(10)?,
+        })
+    })?.collect::<Result<Vec<_>, _>>()?;
+    Ok(items)
+}
+
+pub fn create_herb_inventory(item: &HerbInventory) -> AppResult<i64> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute(
+        "INSERT INTO herb_inventory (herb_id, name, unit, current_stock, min_stock, cost_per_unit, supplier, memo, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![item.herb_id, item.name, item.unit, item.current_stock, item.min_stock, item.cost_per_unit, item.supplier, item.memo, item.created_at, item.updated_at],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn update_herb_inventory(item: &HerbInventory) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute(
+        "UPDATE herb_inventory SET herb_id=?1, name=?2, unit=?3, current_stock=?4, min_stock=?5, cost_per_unit=?6, supplier=?7, memo=?8, updated_at=?9 WHERE id=?10",
+        params![item.herb_id, item.name, item.unit, item.current_stock, item.min_stock, item.cost_per_unit, item.supplier, item.memo, item.updated_at, item.id],
+    )?;
+    Ok(())
+}
+
+pub fn delete_herb_inventory(id: i64) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    conn.execute("DELETE FROM herb_inventory WHERE id=?1", params![id])?;
+    Ok(())
+}
+
+pub fn bulk_import_herb_inventory() -> AppResult<i32> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let now = chrono::Utc::now().to_rfc3339();
+
+    // herbs 테이블에서 herb_inventory에 없는 약재만 가져오기
+    let mut stmt = conn.prepare(
+        "SELECT id, name FROM herbs WHERE id NOT IN (SELECT herb_id FROM herb_inventory WHERE herb_id IS NOT NULL)"
+    )?;
+    let herbs: Vec<(i64, String)> = stmt.query_map([], |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })?.collect::<Result<Vec<_>, _>>()?;
+
+    let count = herbs.len() as i32;
+    for (herb_id, name) in herbs {
+        conn.execute(
+            "INSERT INTO herb_inventory (herb_id, name, unit, current_stock, min_stock, cost_per_unit, created_at, updated_at) VALUES (?1, ?2, 'g', 0, 0, 0, ?3, ?4)",
+            params![herb_id, name, now, now],
+        )?;
+    }
+    Ok(count)
+}
+
+// 입출고 이력
+
+pub fn list_herb_stock_logs(herb_inventory_id: Option<i64>, limit: Option<i32>) -> AppResult<Vec<HerbStockLog>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let lim = limit.unwrap_or(200);
+
+    if let Some(inv_id) = herb_inventory_id {
+        let mut stmt = conn.prepare(
+            "SELECT id, herb_inventory_id, log_type, amount, prescription_id, patient_name, herb_name, note, created_at FROM herb_stock_log WHERE herb_inventory_id=?1 ORDER BY created_at DESC LIMIT ?2"
+        )?;
+        let items = stmt.query_map(params![inv_id, lim], |row| {
+            Ok(HerbStockLog {
+                id: row.get(0)?,
+                herb_inventory_id: row.get(1)?,
+                log_type: row.get(2)?,
+                amount: row.get(3)?,
+                prescription_id: row.get(4)?,
+                patient_name: row.get(5)?,
+                herb_name: row.get(6)?,
+                note: row.get(7)?,
+                created_at: row.get(8)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+        Ok(items)
+    } else {
+        let mut stmt = conn.prepare(
+            "SELECT l.id, l.herb_inventory_id, l.log_type, l.amount, l.prescription_id, l.patient_name, l.herb_name, l.note, l.created_at FROM herb_stock_log l ORDER BY l.created_at DESC LIMIT ?1"
+        )?;
+        let items = stmt.query_map(params![lim], |row| {
+            Ok(HerbStockLog {
+                id: row.get(0)?,
+                herb_inventory_id: row.get(1)?,
+                log_type: row.get(2)?,
+                amount: row.get(3)?,
+                prescription_id: row.get(4)?,
+                patient_name: row.get(5)?,
+                herb_name: row.get(6)?,
+                note: row.get(7)?,
+                created_at: row.get(8)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+        Ok(items)
+    }
+}
+
+pub fn add_stock_log(log: &HerbStockLog) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+
+    conn.execute(
+        "INSERT INTO herb_stock_log (herb_inventory_id, log_type, amount, prescription_id, patient_name, herb_name, note, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![log.herb_inventory_id, log.log_type, log.amount, log.prescription_id, log.patient_name, log.herb_name, log.note, log.created_at],
+    )?;
+
+    // 재고 업데이트
+    let delta = match log.log_type.as_str() {
+        "in" => log.amount,
+        "out" => -log.amount,
+        "adjust" => {
+            // adjust는 절대값 세팅
+            conn.execute(
+                "UPDATE herb_inventory SET current_stock=?1, updated_at=?2 WHERE id=?3",
+                params![log.amount, log.created_at, log.herb_inventory_id],
+            )?;
+            return Ok(());
+        }
+        _ => 0.0,
+    };
+
+    conn.execute(
+        "UPDATE herb_inventory SET current_stock = current_stock + ?1, updated_at=?2 WHERE id=?3",
+        params![delta, log.created_at, log.herb_inventory_id],
+    )?;
+    Ok(())
+}
+
+/// 처방 기반 자동 차감
+pub fn deduct_stock_by_prescription(prescription_id: &str, patient_name: &str, final_herbs_json: &str) -> AppResult<Vec<String>> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let now = chrono::Utc::now().to_rfc3339();
+
+    #[derive(serde::Deserialize)]
+    struct FinalHerb {
+        name: String,
+        amount: f64,
+    }
+
+    let herbs: Vec<FinalHerb> = serde_json::from_str(final_herbs_json)
+        .map_err(|e| AppError::Custom(format!("JSON 파싱 오류: {}", e)))?;
+
+    let mut warnings = Vec::new();
+
+    for herb in &herbs {
+        // 약재명으로 재고 찾기
+        let inv: Option<(i64, f64)> = conn.query_row(
+            "SELECT id, current_stock FROM herb_inventory WHERE name=?1",
+            params![herb.name],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).ok();
+
+        if let Some((inv_id, stock)) = inv {
+            // 출고 기록
+            conn.execute(
+                "INSERT INTO herb_stock_log (herb_inventory_id, log_type, amount, prescription_id, patient_name, herb_name, note, created_at) VALUES (?1, 'out', ?2, ?3, ?4, ?5, '처방 자동차감', ?6)",
+                params![inv_id, herb.amount, prescription_id, patient_name, herb.name, now],
+            )?;
+            // 재고 차감
+            conn.execute(
+                "UPDATE herb_inventory SET current_stock = current_stock - ?1, updated_at=?2 WHERE id=?3",
+                params![herb.amount, now, inv_id],
+            )?;
+
+            if stock - herb.amount < 0.0 {
+                warnings.push(format!("{}: 재고 부족 (현재 {}g)", herb.name, stock));
+            }
+        }
+    }
+
+    Ok(warnings)
+}
+
+/// 처방 삭제 시 재고 복원
+pub fn restore_stock_by_prescription(prescription_id: &str) -> AppResult<()> {
+    ensure_db_initialized()?;
+    let conn = get_conn()?;
+    let now = chrono::Utc::now().to_rfc3339();
+
+    // 해당 처방의 출고 이력 조회
+    let mut stmt = conn.prepare(
+        "SELECT herb_inventory_id, amount FROM herb_stock_log WHERE prescription_id=?1 AND log_type='out'"
+    )?;
+    let logs: Vec<(i64, f64)> = stmt.query_map(params![prescription_id], |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })?.collect::<Result<Vec<_>, _>>()?;
+
+    for (inv_id, amount) in &logs {
+        conn.execute(
+            "UPDATE herb_inventory SET current_stock = current_stock + ?1, updated_at=?2 WHERE id=?3",
+            params![amount, now, inv_id],
+        )?;
+    }
+
+    // 출고 이력 삭제
+    conn.execute(
+        "DELETE FROM herb_stock_log WHERE prescription_id=?1 AND log_type='out'",
+        params![prescription_id],
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 테스트용 직원 계정 행 (권한 JSON을 그대로 저장)
+    fn insert_raw_staff_account(conn: &Connection, role: &str, permissions: &str) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO staff_accounts (id, username, display_name, password_hash, role, permissions, is_active, created_at, updated_at)
+             VALUES (?1, ?1, '테스트', '', ?2, ?3, 1, ?4, ?4)",
+            params![id, role, permissions, now],
+        )
+        .unwrap();
+        id
+    }
+
+    fn stored_permissions(conn: &Connection, id: &str) -> serde_json::Value {
+        let json: String = conn
+            .query_row("SELECT permissions FROM staff_accounts WHERE id = ?1", [id], |row| row.get(0))
+            .unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn json_import_counts_only_written_patients() {
+        init_test_database();
+        let patient = Patient::new("복원 테스트".to_string());
+        let json = serde_json::json!({
+            "patients_data": [{"patient": patient, "prescriptions": [], "chart_records": []}],
+        })
+        .to_string();
+
+        let first = import_from_json_export(&json, ConflictStrategy::Skip).unwrap();
+        assert_eq!(first.patients_imported, 1);
+
+        let skipped = import_from_json_export(&json, ConflictStrategy::Skip).unwrap();
+        assert_eq!(skipped.patients_imported, 0);
+        assert_eq!(skipped.conflicts.len(), 1);
+
+        let overwritten = import_from_json_export(&json, ConflictStrategy::Overwrite).unwrap();
+        assert_eq!(overwritten.patients_imported, 1);
+    }
+
+    fn insert_due_follow_up(source_session_id: &str) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        let past = (Utc::now() - chrono::Duration::minutes(1)).to_rfc3339();
+        get_conn()
+            .unwrap()
+            .execute(
+                "INSERT INTO survey_follow_ups (id, rule_id, source_session_id, follow_up_template_id, chain_depth, due_at, created_at)
+                 VALUES (?1, ?1, ?2, 'default_female_health', 1, ?3, ?3)",
+                params![id, source_session_id, past],
+            )
+            .unwrap();
+        id
+    }
+
+    fn follow_up_session_id(id: &str) -> Option<Option<String>> {
+        get_conn()
+            .unwrap()
+            .query_row("SELECT session_id FROM survey_follow_ups WHERE id = ?1", [id], |row| row.get(0))
+            .ok()
+    }
+
+    #[test]
+    fn due_follow_ups_are_created_or_dropped_without_staying_claimed() {
+        init_test_database();
+        let source = create_survey_session(None, "default_female_health", Some("후속 테스트"), None, None, None, None, None, None, false, None)
+            .unwrap();
+        let ready = insert_due_follow_up(&source.id);
+        let orphaned = insert_due_follow_up(&uuid::Uuid::new_v4().to_string());
+
+        process_due_survey_follow_ups().unwrap();
+
+        let session_id = follow_up_session_id(&ready).flatten().expect("후속 세션이 연결되어야 함");
+        assert!(get_survey_session(&session_id).unwrap().is_some());
+        assert_eq!(follow_up_session_id(&orphaned), None);
+    }
+
+    /// 테스트용 처방 (환자에 연결, 금액/상태는 호출한 쪽에서 조정)
+    fn sample_prescription(patient_id: &str) -> Prescription {
+        let now = Utc::now().to_rfc3339();
+        serde_json::from_value(serde_json::json!({
+            "id": uuid::Uuid::new_v4().to_string(),
+            "patient_id": patient_id,
+            "patient_name": "테스트",
+            "prescription_name": "보중익기탕",
+            "chart_number": null,
+            "patient_age": null,
+            "patient_gender": null,
+            "source_type": null,
+            "source_id": null,
+            "formula": "보중익기탕",
+            "merged_herbs": "[]",
+            "final_herbs": "[]",
+            "total_doses": 20.0,
+            "days": 10,
+            "doses_per_day": 2,
+            "total_packs": 20,
+            "pack_volume": null,
+            "water_amount": null,
+            "herb_adjustment": null,
+            "total_dosage": 30.0,
+            "final_total_amount": 600.0,
+            "notes": null,
+            "price": 100000,
+            "insurance_type": "uninsured",
+            "status": "issued",
+            "issued_at": now,
+            "created_by": null,
+            "deleted_at": null,
+            "created_at": now,
+            "updated_at": now,
+        }))
+        .unwrap()
+    }
+
+    /// 테스트용 환자 + 처방 (반환: 환자 ID, 처방)
+    fn create_patient_with_prescription() -> (String, Prescription) {
+        init_test_database();
+        let patient = Patient::new("처방 테스트".to_string());
+        create_patient(&patient, true).unwrap();
+        let prescription = sample_prescription(&patient.id);
+        create_prescription(&prescription).unwrap();
+        (patient.id, prescription)
+    }
+
+    fn count_rows(sql: &str, id: &str) -> i64 {
+        get_conn().unwrap().query_row(sql, [id], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn prescription_delete_cascades_medication_records() {
+        let (patient_id, prescription) = create_patient_with_prescription();
+        let schedule_id = uuid::Uuid::new_v4().to_string();
+        let now = Utc::now().to_rfc3339();
+        get_conn()
+            .unwrap()
+            .execute(
+                "INSERT INTO medication_schedules (id, patient_id, prescription_id, start_date, end_date, times_per_day, medication_times, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?4, 2, '[]', ?4)",
+                params![schedule_id, patient_id, prescription.id, now],
+            )
+            .unwrap();
+
+        assert!(delete_prescription(&prescription.id, false).is_err());
+        assert_eq!(count_rows("SELECT COUNT(*) FROM prescriptions WHERE id = ?1", &prescription.id), 1);
+
+        delete_prescription(&prescription.id, true).unwrap();
+        assert_eq!(count_rows("SELECT COUNT(*) FROM prescriptions WHERE id = ?1", &prescription.id), 0);
+        assert_eq!(count_rows("SELECT COUNT(*) FROM medication_schedules WHERE id = ?1", &schedule_id), 0);
+    }
+
+    #[test]
+    fn staff_permission_migration_backfills_patient_delete() {
+        init_test_database();
+        let conn = get_conn().unwrap();
+        let legacy = |permissions: StaffPermissions| {
+            let mut value = serde_json::to_value(permissions).unwrap();
+            value.as_object_mut().unwrap().remove("patients_delete");
+            value.to_string()
+        };
+        let admin = insert_raw_staff_account(&conn, "admin", &legacy(StaffPermissions::admin()));
+        let staff = insert_raw_staff_account(&conn, "staff", &legacy(StaffPermissions::staff()));
+        let receptionist = insert_raw_staff_account(&conn, "receptionist", &legacy(StaffPermissions::receptionist()));
+        let restricted = StaffPermissions { patients_delete: false, ..StaffPermissions::staff() };
+        let explicit = insert_raw_staff_account(&conn, "staff", &serde_json::to_string(&restricted).unwrap());
+        let broken = insert_raw_staff_account(&conn, "viewer", "not json");
+
+        migrate_staff_permissions(&conn).unwrap();
+
+        assert_eq!(stored_permissions(&conn, &admin)["patients_delete"], true);
+        assert_eq!(stored_permissions(&conn, &staff)["patients_delete"], true);
+        assert_eq!(stored_permissions(&conn, &receptionist)["patients_delete"], false);
+        assert_eq!(stored_permissions(&conn, &explicit)["patients_delete"], false);
+        assert_eq!(stored_permissions(&conn, &broken)["patients_read"], true);
+        assert_eq!(stored_permissions(&conn, &broken)["patients_write"], false);
+    }
+
+    #[test]
+    fn revenue_report_counts_priced_prescriptions_within_period() {
+        let (patient_id, _) = create_patient_with_prescription();
+        let issue = |date: &str, name: &str, price: Option<i64>, insurance: &str| {
+            let mut p = sample_prescription(&patient_id);
+            let day = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+            let noon = day.and_hms_opt(12, 0, 0).unwrap().and_local_timezone(chrono::Local).unwrap();
+            p.issued_at = Some(noon.with_timezone(&Utc).to_rfc3339());
+            p.prescription_name = Some(name.to_string());
+            p.price = price;
+            p.insurance_type = Some(insurance.to_string());
+            create_prescription(&p).unwrap();
+        };
+        issue("1990-01-31", "경계 이전", Some(999), "uninsured");
+        issue("1990-02-01", "보중익기탕", Some(100_000), "insured");
+        issue("1990-02-15", "보중익기탕", Some(150_000), "uninsured");
+        issue("1990-02-15", "가격 없음", None, "uninsured");
+        issue("1990-03-31", "육미지황탕", Some(1_000_000), "uninsured");
+        issue("1990-04-01", "경계 이후", Some(999), "uninsured");
+
+        let report = get_revenue_report("1990-02-01", "1990-03-31").unwrap();
+        assert_eq!(report.prescription_count, 3);
+        assert_eq!(report.total, 1_250_000);
+        assert_eq!(report.insured, 100_000);
+        assert_eq!(report.uninsured, 1_150_000);
+        assert_eq!(report.total_display, "1,250,000원");
+        let daily: Vec<(&str, i64)> = report.daily.iter().map(|b| (b.period.as_str(), b.total)).collect();
+        assert_eq!(daily, [("1990-02-01", 100_000), ("1990-02-15", 150_000), ("1990-03-31", 1_000_000)]);
+        let monthly: Vec<(&str, i32)> = report.monthly.iter().map(|b| (b.period.as_str(), b.count)).collect();
+        assert_eq!(monthly, [("1990-02", 2), ("1990-03", 1)]);
+        assert_eq!(report.top_prescriptions[0].prescription_name, "육미지황탕");
+        assert_eq!(report.top_prescriptions[1].count, 2);
+    }
+
+    #[test]
+    fn revenue_report_buckets_by_local_date_across_midnight() {
+        use chrono::TimeZone;
+        let (patient_id, _) = create_patient_with_prescription();
+        // 로컬 1991-05-31 23:30 / 1991-06-01 00:30 — KST에서는 UTC 날짜가 하루 앞선다
+        let issue = |month: u32, day: u32, hour: u32, price: i64| {
+            let mut p = sample_prescription(&patient_id);
+            let local = chrono::Local.with_ymd_and_hms(1991, month, day, hour, 30, 0).unwrap();
+            p.issued_at = Some(local.with_timezone(&Utc).to_rfc3339());
+            p.prescription_name = Some("자정 경계".to_string());
+            p.price = Some(price);
+            p.insurance_type = Some("uninsured".to_string());
+            create_prescription(&p).unwrap();
+        };
+        issue(5, 31, 0, 1_000);
+        issue(5, 31, 23, 20_000);
+        issue(6, 1, 0, 300_000);
+        issue(6, 1, 23, 4_000_000);
+
+        let may = get_revenue_report("1991-05-31", "1991-05-31").unwrap();
+        assert_eq!(may.total, 21_000);
+        let daily: Vec<(&str, i64)> = may.daily.iter().map(|b| (b.period.as_str(), b.total)).collect();
+        assert_eq!(daily, [("1991-05-31", 21_000)]);
+
+        let report = get_revenue_report("1991-05-01", "1991-06-30").unwrap();
+        let daily: Vec<(&str, i64)> = report.daily.iter().map(|b| (b.period.as_str(), b.total)).collect();
+        assert_eq!(daily, [("1991-05-31", 21_000), ("1991-06-01", 4_300_000)]);
+        let monthly: Vec<(&str, i64)> = report.monthly.iter().map(|b| (b.period.as_str(), b.total)).collect();
+        assert_eq!(monthly, [("1991-05", 21_000), ("1991-06", 4_300_000)]);
+
+        let june_first = get_revenue_report("1991-06-01", "1991-06-01").unwrap();
+        assert_eq!(june_first.prescription_count, 2);
+        assert_eq!(june_first.total, 4_300_000);
+    }
+
+    #[test]
+    fn won_format_groups_thousands() {
+        assert_eq!(format_won(0), "0원");
+        assert_eq!(format_won(999), "999원");
+        assert_eq!(format_won(1_000), "1,000원");
+        assert_eq!(format_won(1_250_000), "1,250,000원");
+        assert_eq!(format_won(-12_345), "-12,345원");
+    }
+
+    #[test]
+    fn document_signature_detects_changes_after_signing() {
+        let (_, prescription) = create_patient_with_prescription();
+
+        let unsigned = verify_document_integrity(&prescription.id).unwrap();
+        assert!(!unsigned.valid);
+        assert!(unsigned.signature.is_none());
+
+        let signature = sign_document("prescription", &prescription.id, Some("원장"), false).unwrap();
+        assert_eq!(signature.content_hash.len(), 64);
+        assert_eq!(signature.previous_hash, None);
+        let report = verify_document_integrity(&prescription.id).unwrap();
+        assert!(report.valid);
+        assert_eq!(report.current_hash.as_deref(), Some(signature.content_hash.as_str()));
+
+        // 확정 후 내용 변경
+        get_conn()
+            .unwrap()
+            .execute("UPDATE prescriptions SET notes = '변경' WHERE id = ?1", [&prescription.id])
+            .unwrap();
+        let tampered = verify_document_integrity(&prescription.id).unwrap();
+        assert!(!tampered.valid);
+        assert_ne!(tampered.current_hash, Some(signature.content_hash.clone()));
+
+        // 문서 삭제
+        get_conn().unwrap().execute("DELETE FROM prescriptions WHERE id = ?1", [&prescription.id]).unwrap();
+        let deleted = verify_document_integrity(&prescription.id).unwrap();
+        assert!(!deleted.valid);
+        assert_eq!(deleted.current_hash, None);
+    }
+
+    #[test]
+    fn chained_signature_includes_previous_hash() {
+        let (_, first) = create_patient_with_prescription();
+        let (_, second) = create_patient_with_prescription();
+        sign_document("prescription", &first.id, None, false).unwrap();
+
+        let chained = sign_document("prescription", &second.id, None, true).unwrap();
+        let previous = chained.previous_hash.clone().expect("직전 서명 해시");
+        assert!(verify_document_integrity(&second.id).unwrap().valid);
+
+        // 같은 내용이라도 체인 해시는 직전 해시에 따라 달라짐
+        let conn = get_conn().unwrap();
+        let unchained = document_content_hash(&conn, "prescription", &second.id, None, DOCUMENT_HASH_VERSION).unwrap().unwrap();
+        assert_ne!(chained.content_hash, unchained);
+        assert_eq!(
+            document_content_hash(&conn, "prescription", &second.id, Some(&previous), DOCUMENT_HASH_VERSION).unwrap(),
+            Some(chained.content_hash)
+        );
+    }
+
+    #[test]
+    fn signature_covers_content_fields_only() {
+        let (_, prescription) = create_patient_with_prescription();
+        let set_herbs = |herbs: serde_json::Value| {
+            get_conn()
+                .unwrap()
+                .execute("UPDATE prescriptions SET final_herbs = ?1 WHERE id = ?2", params![herbs.to_string(), prescription.id])
+                .unwrap();
+        };
+        set_herbs(serde_json::json!([{"herb_name": "황기", "amount": 4.0, "unit": "g"}]));
+        let signature = sign_document("prescription", &prescription.id, None, false).unwrap();
+        assert_eq!(signature.hash_version, DOCUMENT_HASH_VERSION);
+
+        // 추적용 필드 추가/상태 변경은 내용 변경이 아님
+        set_herbs(serde_json::json!([{"herb_name": "황기", "amount": 4.0, "unit": "g", "batch_number": "L-01", "supplier": "공급처"}]));
+        get_conn()
+            .unwrap()
+            .execute("UPDATE prescriptions SET status = 'completed', updated_at = ?1 WHERE id = ?2", params![Utc::now().to_rfc3339(), prescription.id])
+            .unwrap();
+        assert!(verify_document_integrity(&prescription.id).unwrap().valid);
+
+        // 용량 변경은 변조
+        set_herbs(serde_json::json!([{"herb_name": "황기", "amount": 8.0, "unit": "g"}]));
+        assert!(!verify_document_integrity(&prescription.id).unwrap().valid);
+    }
+
+    #[test]
+    fn legacy_signature_is_verified_with_its_own_format() {
+        let (_, prescription) = create_patient_with_prescription();
+        {
+            let conn = get_conn().unwrap();
+            let legacy_hash = document_content_hash(&conn, "prescription", &prescription.id, None, 0).unwrap().unwrap();
+            conn.execute(
+                "INSERT INTO document_signatures (id, document_type, document_id, content_hash, signed_at)
+                 VALUES (?1, 'prescription', ?2, ?3, ?4)",
+                params![uuid::Uuid::new_v4().to_string(), prescription.id, legacy_hash, Utc::now().to_rfc3339()],
+            )
+            .unwrap();
+        }
+        let report = verify_document_integrity(&prescription.id).unwrap();
+        assert_eq!(report.signature.unwrap().hash_version, 0);
+        assert!(report.valid);
+    }
+
+    #[test]
+    fn signing_rejects_unknown_or_missing_documents() {
+        init_test_database();
+        assert!(matches!(sign_document("invoice", "x", None, false), Err(AppError::Validation(_))));
+        assert!(matches!(sign_document("prescription", "missing-document", None, false), Err(AppError::NotFound(_))));
+    }
+
+    #[test]
+    fn encrypted_backup_restores_only_with_passphrase() {
+        init_test_database();
+        let patient = Patient::new("암호화 백업".to_string());
+        let prescription = sample_prescription(&patient.id);
+        let json = serde_json::json!({
+            "patients_data": [{"patient": patient, "prescriptions": [prescription], "chart_records": []}],
+        })
+        .to_string();
+        let encrypted = crate::encryption::encrypt_backup(json.as_bytes(), "backup-pass").unwrap();
+
+        assert!(import_encrypted(&encrypted, "other-pass").is_err());
+        assert!(import_encrypted(json.as_bytes(), "backup-pass").is_err());
+        assert!(get_patient(&patient.id).unwrap().is_none());
+
+        assert_eq!(import_encrypted(&encrypted, "backup-pass").unwrap(), 2);
+        assert!(get_patient(&patient.id).unwrap().is_some());
+        assert!(get_prescription(&prescription.id).unwrap().is_some());
+        // 이미 있는 기록은 다시 복원하지 않음
+        assert_eq!(import_encrypted(&encrypted, "backup-pass").unwrap(), 0);
+    }
+
+    #[test]
+    fn encrypted_export_decrypts_to_plain_export() {
+        let (patient_id, _) = create_patient_with_prescription();
+        let encrypted = export_all_data_encrypted("backup-pass").unwrap();
+        let plain = crate::encryption::decrypt_backup(&encrypted, "backup-pass").unwrap();
+        let export: serde_json::Value = serde_json::from_slice(&plain).unwrap();
+        let patients = export["patients_data"].as_array().unwrap();
+        assert!(patients.iter().any(|p| p["patient"]["id"] == patient_id.as_str()));
+        assert!(serde_json::from_str::<serde_json::Value>(&export_all_data().unwrap()).is_ok());
+    }
+
+    fn pending_session(expires_at: Option<chrono::DateTime<Utc>>) -> SurveySessionDb {
+        init_test_database();
+        create_survey_session(None, "default_female_health", Some("대기 세션"), None, None, None, None, None, None, false, expires_at)
+            .unwrap()
+    }
+
+    fn session_row(id: &str) -> (String, String) {
+        get_conn()
+            .unwrap()
+            .query_row("SELECT status, expires_at FROM survey_sessions WHERE id = ?1", [id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+    }
+
+    #[test]
+    fn cancel_expires_only_pending_sessions() {
+        let session = pending_session(None);
+        let pending = list_survey_sessions(None, Some("pending"), None).unwrap();
+        assert!(pending.iter().any(|s| s.id == session.id));
+
+        cancel_survey_session(&session.id).unwrap();
+        assert_eq!(session_row(&session.id).0, "expired");
+        assert!(!list_survey_sessions(None, Some("pending"), None).unwrap().iter().any(|s| s.id == session.id));
+        assert!(list_survey_sessions(None, Some("expired"), None).unwrap().iter().any(|s| s.id == session.id));
+
+        assert!(matches!(cancel_survey_session(&session.id), Err(AppError::Custom(_))));
+        assert!(matches!(cancel_survey_session("missing-session"), Err(AppError::Custom(_))));
+    }
+
+    #[test]
+    fn extend_pushes_expiry_from_later_of_now_and_current() {
+        let future = Utc::now() + chrono::Duration::hours(10);
+        let session = pending_session(Some(future));
+        let extended = chrono::DateTime::parse_from_rfc3339(&extend_survey_session(&session.id, 24).unwrap()).unwrap();
+        assert_eq!(extended.timestamp(), (future + chrono::Duration::hours(24)).timestamp());
+        assert_eq!(session_row(&session.id).1, extended.to_rfc3339());
+
+        // 이미 지난 세션은 지금부터 연장
+        let lapsed = pending_session(Some(Utc::now() - chrono::Duration::hours(5)));
+        let before = Utc::now();
+        let extended = chrono::DateTime::parse_from_rfc3339(&extend_survey_session(&lapsed.id, 2).unwrap()).unwrap();
+        assert!(extended >= before + chrono::Duration::hours(2));
+        assert!(extended <= Utc::now() + chrono::Duration::hours(2));
+
+        cancel_survey_session(&session.id).unwrap();
+        assert!(matches!(extend_survey_session(&session.id, 1), Err(AppError::Custom(_))));
+    }
+
+    /// 캐시 값과 전체 재집계 결과 비교 (연결을 잡은 채로 비교해 다른 테스트의 쓰기와 섞이지 않도록)
+    fn assert_stats_cache_consistent() {
+        let conn = get_conn().unwrap();
+        let cached = serde_json::to_value(cached_survey_response_stats(&conn).unwrap()).unwrap();
+        let rebuilt = serde_json::to_value(build_survey_response_stats(&conn).unwrap()).unwrap();
+        assert_eq!(cached, rebuilt);
+    }
+
+    #[test]
+    fn survey_stats_cache_updates_incrementally() {
+        init_test_database();
+        let template_id = format!("stats-{}", uuid::Uuid::new_v4());
+        assert!(!get_survey_response_stats().unwrap().by_template.contains_key(&template_id));
+
+        submit_survey_response(None, &template_id, None, Some("집계"), &[]).unwrap();
+        submit_survey_response(None, &template_id, None, Some("집계"), &[]).unwrap();
+        assert_eq!(get_survey_response_stats().unwrap().by_template.get(&template_id), Some(&2));
+        assert_stats_cache_consistent();
+
+        let ids: Vec<String> = {
+            let conn = get_conn().unwrap();
+            let mut stmt = conn.prepare("SELECT id FROM survey_responses WHERE template_id = ?1").unwrap();
+            let rows = stmt.query_map([&template_id], |row| row.get(0)).unwrap();
+            rows.collect::<Result<_, _>>().unwrap()
+        };
+
+        // 환자 연결 시 미연결 수만 감소
+        let patient = Patient::new("집계 환자".to_string());
+        create_patient(&patient, true).unwrap();
+        link_survey_response_to_patient(&ids[0], &patient.id).unwrap();
+        assert_stats_cache_consistent();
+
+        for id in &ids {
+            delete_survey_response(id).unwrap();
+        }
+        assert!(!get_survey_response_stats().unwrap().by_template.contains_key(&template_id));
+        assert_stats_cache_consistent();
+
+        // 없는 응답 삭제는 캐시에 영향 없음
+        delete_survey_response("missing-response").unwrap();
+        assert_stats_cache_consistent();
+    }
+
+    #[test]
+    fn survey_stats_cache_rebuilds_after_invalidation() {
+        init_test_database();
+        {
+            let conn = get_conn().unwrap();
+            invalidate_survey_stats();
+            assert!(survey_stats_cache().lock().unwrap().is_none());
+            cached_survey_response_stats(&conn).unwrap();
+            assert!(survey_stats_cache().lock().unwrap().is_some());
+        }
+        assert_stats_cache_consistent();
+    }
+
+    #[test]
+    fn prescription_pages_report_total_and_has_more() {
+        let (patient_id, _) = create_patient_with_prescription();
+        for _ in 0..2 {
+            create_prescription(&sample_prescription(&patient_id)).unwrap();
+        }
+
+        let first = list_prescriptions_page(Some(&patient_id), 2, 0).unwrap();
+        assert_eq!((first.items.len(), first.total, first.has_more), (2, 3, true));
+        let second = list_prescriptions_page(Some(&patient_id), 2, 2).unwrap();
+        assert_eq!((second.items.len(), second.total, second.has_more), (1, 3, false));
+        let past_end = list_prescriptions_page(Some(&patient_id), 2, 10).unwrap();
+        assert_eq!((past_end.items.len(), past_end.total, past_end.has_more), (0, 3, false));
+
+        // 페이지 사이에 중복/누락 없음
+        let mut ids: Vec<String> = first.items.iter().chain(&second.items).map(|p| p.id.clone()).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 3);
+
+        let none = list_prescriptions_page(Some("missing-patient"), 2, 0).unwrap();
+        assert_eq!((none.items.len(), none.total, none.has_more), (0, 0, false));
+    }
+
+    fn follow_up_rule(source_template_id: &str, trigger_type: &str, delay_hours: i64) -> SurveyFollowUpRule {
+        SurveyFollowUpRule {
+            id: uuid::Uuid::new_v4().to_string(),
+            source_template_id: source_template_id.to_string(),
+            follow_up_template_id: "default_female_health".to_string(),
+            trigger_type: trigger_type.to_string(),
+            question_id: None,
+            min_score: None,
+            max_score: None,
+            delay_hours,
+            is_active: true,
+            created_at: Utc::now().to_rfc3339(),
+        }
+    }
+
+    fn score_answer(value: serde_json::Value) -> Vec<SurveyAnswer> {
+        vec![SurveyAnswer { question_id: "satisfaction".to_string(), answer: value }]
+    }
+
+    #[test]
+    fn follow_up_score_trigger_checks_inclusive_range() {
+        let completed = follow_up_rule("src", "completed", 0);
+        assert!(follow_up_rule_matches(&completed, &[]));
+
+        let mut low_score = follow_up_rule("src", "score", 0);
+        low_score.question_id = Some("satisfaction".to_string());
+        low_score.min_score = Some(1.0);
+        low_score.max_score = Some(3.0);
+        assert!(follow_up_rule_matches(&low_score, &score_answer(serde_json::json!(3))));
+        assert!(follow_up_rule_matches(&low_score, &score_answer(serde_json::json!(" 1 "))));
+        assert!(!follow_up_rule_matches(&low_score, &score_answer(serde_json::json!(4))));
+        assert!(!follow_up_rule_matches(&low_score, &score_answer(serde_json::json!("보통"))));
+        assert!(!follow_up_rule_matches(&low_score, &[]));
+    }
+
+    #[test]
+    fn follow_up_rule_validation() {
+        let same = SurveyFollowUpRule { follow_up_template_id: "src".to_string(), ..follow_up_rule("src", "completed", 0) };
+        let no_question = follow_up_rule("src", "score", 0);
+        let no_range = SurveyFollowUpRule { question_id: Some("q".to_string()), ..follow_up_rule("src", "score", 0) };
+        let unknown = follow_up_rule("src", "opened", 0);
+        let negative = follow_up_rule("src", "completed", -1);
+        for rule in [same, no_question, no_range, unknown, negative] {
+            assert!(matches!(save_follow_up_rule(&rule), Err(AppError::Custom(_))), "{:?}", rule);
+        }
+    }
+
+    #[test]
+    fn follow_ups_are_scheduled_with_delay_and_created_when_due() {
+        init_test_database();
+        let source_template = format!("fu-src-{}", uuid::Uuid::new_v4());
+        let immediate = follow_up_rule(&source_template, "completed", 0);
+        let delayed = SurveyFollowUpRule {
+            question_id: Some("satisfaction".to_string()),
+            max_score: Some(2.0),
+            ..follow_up_rule(&source_template, "score", 48)
+        };
+        let inactive = SurveyFollowUpRule { is_active: false, ..follow_up_rule(&source_template, "completed", 0) };
+        for rule in [&immediate, &delayed, &inactive] {
+            save_follow_up_rule(rule).unwrap();
+        }
+
+        let source = create_survey_session(None, "default_female_health", Some("후속 예약"), None, None, None, None, None, None, false, None)
+            .unwrap();
+        let answers = score_answer(serde_json::json!(1));
+        assert_eq!(schedule_survey_follow_ups(&source.id, &source_template, &answers).unwrap(), 2);
+        // 같은 세션이 다시 완료 처리되어도 중복 예약하지 않음
+        assert_eq!(schedule_survey_follow_ups(&source.id, &source_template, &answers).unwrap(), 0);
+
+        let scheduled = |rule_id: &str| -> (String, i32, String) {
+            get_conn()
+                .unwrap()
+                .query_row(
+                    "SELECT id, chain_depth, due_at FROM survey_follow_ups WHERE rule_id = ?1 AND source_session_id = ?2",
+                    params![rule_id, source.id],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                )
+                .unwrap()
+        };
+        let (immediate_id, depth, _) = scheduled(&immediate.id);
+        assert_eq!(depth, 1);
+        let (delayed_id, _, due_at) = scheduled(&delayed.id);
+        let due_at = chrono::DateTime::parse_from_rfc3339(&due_at).unwrap();
+        assert!(due_at > Utc::now() + chrono::Duration::hours(47));
+
+        process_due_survey_follow_ups().unwrap();
+        let session_id = follow_up_session_id(&immediate_id).flatten().expect("후속 세션 생성");
+        let follow_up = get_survey_session(&session_id).unwrap().unwrap();
+        assert_eq!(follow_up.template_id, "default_female_health");
+        assert_eq!(follow_up_session_id(&delayed_id), Some(None));
+    }
+
+    #[test]
+    fn follow_up_chain_stops_at_max_depth() {
+        init_test_database();
+        let source_template = format!("fu-src-{}", uuid::Uuid::new_v4());
+        save_follow_up_rule(&follow_up_rule(&source_template, "completed", 0)).unwrap();
+
+        let chained_session = |depth: i32| {
+            let session_id = uuid::Uuid::new_v4().to_string();
+            get_conn()
+                .unwrap()
+                .execute(
+                    "INSERT INTO survey_follow_ups (id, rule_id, source_session_id, follow_up_template_id, chain_depth, due_at, session_id, created_at)
+                     VALUES (?1, ?1, 'earlier', 'default_female_health', ?2, ?3, ?4, ?3)",
+                    params![uuid::Uuid::new_v4().to_string(), depth, Utc::now().to_rfc3339(), session_id],
+                )
+                .unwrap();
+            session_id
+        };
+
+        let below_limit = chained_session(MAX_FOLLOW_UP_DEPTH - 1);
+        assert_eq!(schedule_survey_follow_ups(&below_limit, &source_template, &[]).unwrap(), 1);
+        let depth: i32 = get_conn()
+            .unwrap()
+            .query_row("SELECT chain_depth FROM survey_follow_ups WHERE source_session_id = ?1", [&below_limit], |row| row.get(0))
+            .unwrap();
+        assert_eq!(depth, MAX_FOLLOW_UP_DEPTH);
+
+        let at_limit = chained_session(MAX_FOLLOW_UP_DEPTH);
+        assert_eq!(schedule_survey_follow_ups(&at_limit, &source_template, &[]).unwrap(), 0);
+    }
+
+    #[test]
+    fn tags_are_normalized() {
+        assert_eq!(normalize_tag("  #당뇨   환자 ").unwrap(), "당뇨 환자");
+        assert!(normalize_tag(" # ").is_err());
+        assert!(normalize_tag("a,b").is_err());
+        assert!(normalize_tag(&"가".repeat(MAX_TAG_LENGTH + 1)).is_err());
+        assert_eq!(dedup_tags(&["Cohort".to_string(), "#cohort".to_string()]).unwrap(), ["Cohort"]);
+    }
+
+    #[test]
+    fn tags_search_across_entity_types() {
+        let (patient_id, prescription) = create_patient_with_prescription();
+        let prefix = format!("tg{}", &uuid::Uuid::new_v4().simple().to_string()[..8]);
+        let common = format!("{}-common", prefix);
+        let rare = format!("{}-rare", prefix);
+        add_entity_tags("patient", &patient_id, &[common.clone(), rare.clone()]).unwrap();
+        add_entity_tags("prescription", &prescription.id, &[format!("#{}", common)]).unwrap();
+        assert_eq!(add_entity_tags("patient", "missing-patient", std::slice::from_ref(&common)).unwrap(), None);
+        assert!(add_entity_tags("invoice", &patient_id, std::slice::from_ref(&common)).is_err());
+
+        let ids = |found: Vec<TaggedEntity>| -> Vec<(String, String)> {
+            let mut ids: Vec<_> = found.into_iter().map(|e| (e.entity_type, e.entity_id)).collect();
+            ids.sort();
+            ids
+        };
+        let both = [common.clone(), rare.clone()];
+        assert_eq!(
+            ids(search_tagged_entities(&both, &[], false).unwrap()),
+            [("patient".to_string(), patient_id.clone()), ("prescription".to_string(), prescription.id.clone())]
+        );
+        assert_eq!(ids(search_tagged_entities(&both, &[], true).unwrap()), [("patient".to_string(), patient_id.clone())]);
+        assert_eq!(
+            ids(search_tagged_entities(&both, &["prescription".to_string()], false).unwrap()),
+            [("prescription".to_string(), prescription.id.clone())]
+        );
+
+        // 자동완성은 사용 횟수 순
+        assert_eq!(suggest_tags(&format!("#{}", prefix), 10).unwrap(), [common.clone(), rare.clone()]);
+        assert_eq!(suggest_tags(&format!("{}-r", prefix), 10).unwrap(), std::slice::from_ref(&rare));
+        assert!(suggest_tags(&format!("{}%", prefix), 10).unwrap().is_empty());
+
+        let stats = get_tag_stats().unwrap();
+        let stat = stats.iter().find(|s| s.tag == common).unwrap();
+        assert_eq!(stat.total, 2);
+        assert_eq!(stat.by_type.get("patient"), Some(&1));
+        assert_eq!(stat.by_type.get("prescription"), Some(&1));
+
+        assert!(remove_entity_tag("patient", &patient_id, &rare).unwrap());
+        assert!(!remove_entity_tag("patient", &patient_id, &rare).unwrap());
+        assert_eq!(get_entity_tags("patient", &patient_id).unwrap(), Some(vec![common]));
+    }
+
+    #[test]
+    fn deleted_entities_drop_out_of_tag_results() {
+        let (_, prescription) = create_patient_with_prescription();
+        let tag = format!("삭제-{}", uuid::Uuid::new_v4().simple());
+        add_entity_tags("prescription", &prescription.id, std::slice::from_ref(&tag)).unwrap();
+        let other = Patient::new("태그 삭제".to_string());
+        create_patient(&other, true).unwrap();
+        add_entity_tags("patient", &other.id, std::slice::from_ref(&tag)).unwrap();
+
+        // 휴지통 항목은 검색/통계에서 제외
+        soft_delete_prescription(&prescription.id).unwrap();
+        let found = search_tagged_entities(std::slice::from_ref(&tag), &[], false).unwrap();
+        assert_eq!(found.iter().map(|e| e.entity_id.as_str()).collect::<Vec<_>>(), [other.id.as_str()]);
+        assert_eq!(get_tag_stats().unwrap().iter().find(|s| s.tag == tag).unwrap().total, 1);
+
+        // 영구 삭제 시 태그 행도 정리
+        delete_prescription(&prescription.id, false).unwrap();
+        delete_patient(&other.id).unwrap();
+        let remaining: i64 = get_conn()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM tags WHERE tag = ?1", [&tag], |row| row.get(0))
+            .unwrap();
+        assert_eq!(remaining, 0);
+        assert!(get_tag_stats().unwrap().iter().all(|s| s.tag != tag));
+    }
+
+    /// 지정한 등록일의 환자 생성 (기간 집계용)
+    fn create_patient_registered_on(date: &str, referral_source: Option<&str>) -> Patient {
+        init_test_database();
+        let mut patient = Patient::new("경로 테스트".to_string());
+        patient.created_at = chrono::DateTime::parse_from_rfc3339(&format!("{}T09:00:00Z", date)).unwrap().with_timezone(&Utc);
+        patient.referral_source = referral_source.map(str::to_string);
+        create_patient(&patient, true).unwrap();
+        patient
+    }
+
+    #[test]
+    fn referral_breakdown_counts_new_patients_in_period() {
+        create_patient_registered_on("1985-04-30", Some("SNS"));
+        create_patient_registered_on("1985-05-01", Some("SNS"));
+        create_patient_registered_on("1985-05-31", Some(" SNS "));
+        create_patient_registered_on("1985-05-15", Some("라디오"));
+        create_patient_registered_on("1985-05-15", Some("  "));
+        create_patient_registered_on("1985-05-16", None);
+        let deleted = create_patient_registered_on("1985-05-20", Some("라디오"));
+        soft_delete_patient(&deleted.id).unwrap();
+
+        let counts = get_referral_breakdown(Some("1985-05-01"), Some("1985-05-31")).unwrap();
+        let count_of = |source: Option<&str>| counts.iter().find(|c| c.source.as_deref() == source).map(|c| c.count);
+        assert_eq!(count_of(Some("SNS")), Some(2));
+        assert_eq!(count_of(Some("라디오")), Some(1));
+        // 설정된 경로는 0명이어도 표시, 공백/미입력은 하나로 묶어 맨 뒤
+        assert_eq!(count_of(Some("지인 소개")), Some(0));
+        assert_eq!(count_of(None), Some(2));
+        assert_eq!(counts.last().unwrap().source, None);
+        assert_eq!(counts[0].source.as_deref(), Some("SNS"));
+    }
+
+    #[test]
+    fn referral_source_blank_is_unset() {
+        assert_eq!(normalize_referral_source(Some("  지인 소개 ")), Some("지인 소개".to_string()));
+        assert_eq!(normalize_referral_source(Some("   ")), None);
+        assert_eq!(normalize_referral_source(None), None);
+    }
+
+    fn scheduler_lock_owner(name: &str) -> Option<String> {
+        get_conn()
+            .unwrap()
+            .query_row("SELECT owner FROM scheduler_locks WHERE name = ?1", [name], |row| row.get(0))
+            .ok()
+    }
+
+    #[test]
+    fn scheduler_lock_is_held_by_one_instance() {
+        init_test_database();
+        let name = format!("lock-{}", uuid::Uuid::new_v4());
+        assert!(try_acquire_scheduler_lock(&name, 60).unwrap());
+        // 이미 가진 락은 heartbeat 갱신
+        assert!(try_acquire_scheduler_lock(&name, 60).unwrap());
+        assert_eq!(scheduler_lock_owner(&name).as_deref(), Some(scheduler_instance_id()));
+
+        release_scheduler_lock(&name).unwrap();
+        assert_eq!(scheduler_lock_owner(&name), None);
+
+        // 살아 있는 다른 인스턴스의 락은 넘겨받지 않고, 해제도 하지 않음
+        insert_test_scheduler_lock(&name, "other-process", 10);
+        assert!(!try_acquire_scheduler_lock(&name, 60).unwrap());
+        release_scheduler_lock(&name).unwrap();
+        assert_eq!(scheduler_lock_owner(&name).as_deref(), Some("other-process"));
+    }
+
+    #[test]
+    fn stale_scheduler_lock_is_taken_over() {
+        init_test_database();
+        let name = format!("lock-{}", uuid::Uuid::new_v4());
+        insert_test_scheduler_lock(&name, "other-process", 600);
+        assert!(try_acquire_scheduler_lock(&name, 60).unwrap());
+        assert_eq!(scheduler_lock_owner(&name).as_deref(), Some(scheduler_instance_id()));
+        release_scheduler_lock(&name).unwrap();
+    }
+
+    #[test]
+    fn respondent_contact_requires_name_and_phone_or_birth_date() {
+        let contact = |name: Option<&str>, phone: Option<&str>, birth_date: Option<&str>| {
+            RespondentContact {
+                respondent_name: name.map(str::to_string),
+                phone: phone.map(str::to_string),
+                birth_date: birth_date.map(str::to_string),
+            }
+            .normalized()
+        };
+
+        assert!(validate_respondent_contact(&contact(Some("홍길동"), Some("010-1234-5678"), None), None).is_ok());
+        assert!(validate_respondent_contact(&contact(Some("홍길동"), None, Some("1980-01-31")), None).is_ok());
+        // 세션에 이름이 있으면 입력하지 않아도 됨
+        assert!(validate_respondent_contact(&contact(None, None, Some("1980-01-31")), Some("홍길동")).is_ok());
+
+        assert!(validate_respondent_contact(&contact(None, Some("01012345678"), None), None).is_err());
+        assert!(validate_respondent_contact(&contact(Some("  "), Some("01012345678"), None), Some(" ")).is_err());
+        assert!(validate_respondent_contact(&contact(Some("홍길동"), None, None), None).is_err());
+        assert!(validate_respondent_contact(&contact(Some("홍길동"), Some(" "), Some("")), None).is_err());
+        assert!(validate_respondent_contact(&contact(Some("홍길동"), Some("1234"), None), None).is_err());
+        assert!(validate_respondent_contact(&contact(Some("홍길동"), None, Some("1980/01/31")), None).is_err());
+    }
+
+    #[test]
+    fn patient_events_merge_sources_in_stable_order() {
+        let (patient_id, latest) = create_patient_with_prescription();
+        let mut same_time = Vec::new();
+        for _ in 0..3 {
+            let mut p = sample_prescription(&patient_id);
+            p.issued_at = Some("2001-01-01T10:00:00+00:00".to_string());
+            create_prescription(&p).unwrap();
+            same_time.push(p.id);
+        }
+        same_time.sort();
+        let mut removed = sample_prescription(&patient_id);
+        removed.issued_at = Some("2001-01-02T10:00:00+00:00".to_string());
+        create_prescription(&removed).unwrap();
+        soft_delete_prescription(&removed.id).unwrap();
+        submit_survey_response(None, "default_female_health", Some(&patient_id), Some("타임라인"), &[]).unwrap();
+        sign_document("prescription", &latest.id, None, false).unwrap();
+
+        let all = get_patient_events(&patient_id, &[], 100, 0).unwrap();
+        let types: Vec<&str> = all.iter().map(|e| e.event_type.as_str()).collect();
+        assert_eq!(types.len(), 6);
+        assert!(all.windows(2).all(|w| w[0].occurred_at >= w[1].occurred_at));
+        assert!(!all.iter().any(|e| e.source_id == removed.id));
+        // 같은 시각은 ID 순으로 고정
+        let tail: Vec<String> = all[3..].iter().map(|e| e.source_id.clone()).collect();
+        assert_eq!(tail, same_time);
+
+        let only = |types: &[&str]| {
+            let types: Vec<String> = types.iter().map(|t| t.to_string()).collect();
+            get_patient_events(&patient_id, &types, 100, 0).unwrap()
+        };
+        assert_eq!(only(&["prescription"]).len(), 4);
+        let mixed = only(&["survey", "document"]);
+        assert_eq!(mixed.len(), 2);
+        assert!(mixed.iter().any(|e| e.event_type == "document" && e.detail.as_deref() == Some(latest.id.as_str())));
+        assert!(matches!(get_patient_events(&patient_id, &["billing".to_string()], 10, 0), Err(AppError::Custom(_))));
+
+        // 페이지를 이어 붙이면 전체 목록과 같음
+        let mut paged = Vec::new();
+        for offset in (0..6).step_by(2) {
+            paged.extend(get_patient_events(&patient_id, &[], 2, offset).unwrap().into_iter().map(|e| e.source_id));
+        }
+        assert_eq!(paged, all.iter().map(|e| e.source_id.clone()).collect::<Vec<_>>());
+        assert!(get_patient_events(&patient_id, &[], 2, 6).unwrap().is_empty());
+    }
+
+    fn v1_template_json() -> serde_json::Value {
+        serde_json::json!({
+            "format": SURVEY_TEMPLATE_EXPORT_FORMAT,
+            "version": 1,
+            "name": "이전 형식 설문",
+            "questions": [
+                {"question_type": "single_choice", "question_text": "통증 부위", "options": ["목", "허리"]},
+                {"question_type": "text", "question_text": "기타"},
+            ],
+        })
+    }
+
+    #[test]
+    fn template_import_upgrades_v1_and_fills_defaults() {
+        init_test_database();
+        let parsed = parse_survey_template_import(v1_template_json()).unwrap();
+        assert_eq!(parsed.schema_version, SURVEY_TEMPLATE_EXPORT_VERSION);
+        assert!(!parsed.require_contact && !parsed.anonymous);
+        assert_eq!(parsed.description, None);
+        assert!(parsed.questions.iter().all(|q| !q.required && q.scale_config.is_none()));
+
+        // 버전 필드가 없으면 1로 간주
+        let mut unversioned = v1_template_json();
+        unversioned.as_object_mut().unwrap().remove("version");
+        assert!(parse_survey_template_import(unversioned).is_ok());
+
+        let imported = import_survey_template(parsed).unwrap();
+        assert!(imported.questions.iter().all(|q| !q.id.is_empty()));
+        assert_eq!(imported.questions.iter().map(|q| q.order).collect::<Vec<_>>(), [Some(1), Some(2)]);
+        assert!(get_survey_template(&imported.id).unwrap().is_some());
+    }
+
+    #[test]
+    fn template_import_rejects_unknown_versions_and_types() {
+        let with = |key: &str, value: serde_json::Value| {
+            let mut json = v1_template_json();
+            json[key] = value;
+            parse_survey_template_import(json)
+        };
+        assert!(with("version", serde_json::json!(SURVEY_TEMPLATE_EXPORT_VERSION + 1)).is_err());
+        assert!(with("version", serde_json::json!(0)).is_err());
+        assert!(with("version", serde_json::json!("2")).is_err());
+        assert!(with("format", serde_json::json!("other.app")).is_err());
+        assert!(parse_survey_template_import(serde_json::json!([])).is_err());
+
+        let mut json = v1_template_json();
+        json["questions"][1]["question_type"] = serde_json::json!("matrix");
+        match parse_survey_template_import(json) {
+            Err(AppError::Custom(msg)) => assert!(msg.contains("2번") && msg.contains("matrix"), "{}", msg),
+            other => panic!("미지원 문항 유형이 통과됨: {:?}", other.map(|t| t.name)),
+        }
+
+        // 변환 후에도 내용 검증은 그대로
+        let mut no_options = parse_survey_template_import(v1_template_json()).unwrap();
+        no_options.questions[0].options = Some(vec![]);
+        assert!(import_survey_template(no_options).is_err());
+    }
+
+    #[test]
+    fn template_export_round_trips() {
+        init_test_database();
+        let original = import_survey_template(parse_survey_template_import(v1_template_json()).unwrap()).unwrap();
+        let exported = export_survey_template(&original.id).unwrap().unwrap();
+        assert_eq!(exported.schema_version, SURVEY_TEMPLATE_EXPORT_VERSION);
+
+        let json = serde_json::to_value(&exported).unwrap();
+        let copy = import_survey_template(parse_survey_template_import(json).unwrap()).unwrap();
+        assert_ne!(copy.id, original.id);
+        assert_eq!(copy.name, original.name);
+        let summary = |t: &SurveyTemplateDb| -> Vec<(String, Option<Vec<String>>)> {
+            t.questions.iter().map(|q| (q.question_text.clone(), q.options.clone())).collect()
+        };
+        assert_eq!(summary(&copy), summary(&original));
+    }
+
+    /// 0~10 척도 문항 하나짜리 템플릿 (반환: 템플릿 ID)
+    fn save_scale_template() -> String {
+        init_test_database();
+        let template = SurveyTemplateDb {
+            id: format!("scale-{}", uuid::Uuid::new_v4()),
+            name: "통증 척도".to_string(),
+            description: None,
+            questions: vec![SurveyQuestion {
+                id: "pain".to_string(),
+                question_type: QuestionType::Scale,
+                question_text: "통증 정도".to_string(),
+                required: true,
+                options: None,
+                scale_config: Some(ScaleConfig { min: 0, max: 10, min_label: None, max_label: None, step: None }),
+                order: Some(1),
+                shuffle_options: false,
+                fixed_position: false,
+                translations: None,
+            }],
+            display_mode: None,
+            is_active: true,
+            require_contact: false,
+            anonymous: false,
+            redirect_url: None,
+            category: None,
+            randomize_questions: false,
+            randomize_options: false,
+            pin_required_questions: false,
+        };
+        save_survey_template(&template).unwrap();
+        template.id
+    }
+
+    #[test]
+    fn correlation_needs_enough_varied_samples() {
+        let close = |a: Option<f64>, b: f64| a.is_some_and(|a| (a - b).abs() < 1e-9);
+        assert!(close(pearson_correlation(&[(1.0, 2.0), (2.0, 4.0), (3.0, 6.0)]), 1.0));
+        assert!(close(pearson_correlation(&[(1.0, 3.0), (2.0, 2.0), (3.0, 1.0)]), -1.0));
+        assert_eq!(pearson_correlation(&[(1.0, 2.0), (2.0, 4.0)]), None);
+        assert_eq!(pearson_correlation(&[(1.0, 5.0), (2.0, 5.0), (3.0, 5.0)]), None);
+    }
+
+    #[test]
+    fn treatment_outcomes_pair_scores_around_each_prescription() {
+        let template_id = save_scale_template();
+        let patient = Patient::new("상관 분석".to_string());
+        create_patient(&patient, true).unwrap();
+
+        let prescribe = |date: &str, logs: usize, taken: usize| {
+            let mut p = sample_prescription(&patient.id);
+            p.issued_at = Some(format!("{}T10:00:00+00:00", date));
+            create_prescription(&p).unwrap();
+            let conn = get_conn().unwrap();
+            let schedule_id = uuid::Uuid::new_v4().to_string();
+            conn.execute(
+                "INSERT INTO medication_schedules (id, patient_id, prescription_id, start_date, end_date, times_per_day, medication_times, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?4, 2, '[]', ?4)",
+                params![schedule_id, patient.id, p.id, date],
+            )
+            .unwrap();
+            for i in 0..logs {
+                conn.execute(
+                    "INSERT INTO medication_logs (id, schedule_id, taken_at, status) VALUES (?1, ?2, ?3, ?4)",
+                    params![uuid::Uuid::new_v4().to_string(), schedule_id, date, if i < taken { "taken" } else { "missed" }],
+                )
+                .unwrap();
+            }
+            p.id
+        };
+        let respond = |date: &str, answers: serde_json::Value| {
+            let answers: Vec<SurveyAnswer> = serde_json::from_value(answers).unwrap();
+            let session_id = uuid::Uuid::new_v4().to_string();
+            save_survey_response_from_sync(&session_id, &template_id, Some(&patient.id), None, &answers, &format!("{}T09:00:00+00:00", date))
+                .unwrap();
+        };
+
+        let first = prescribe("2002-01-10", 4, 2);
+        let second = prescribe("2002-02-10", 4, 4);
+        prescribe("2002-03-10", 4, 1);
+        let without_logs = prescribe("2002-04-10", 0, 0);
+        respond("2002-01-05", serde_json::json!([{"question_id": "pain", "answer": 8}]));
+        respond("2002-01-20", serde_json::json!([{"question_id": "pain", "answer": "6"}]));
+        respond("2002-01-25", serde_json::json!([])); // 척도 답이 없으면 제외
+        respond("2002-02-20", serde_json::json!([{"question_id": "pain", "answer": 3}]));
+        respond("2002-03-20", serde_json::json!([{"question_id": "pain", "answer": 2}]));
+
+        let result = get_treatment_outcome_correlation(&patient.id).unwrap();
+        assert_eq!(result.items.len(), 4);
+        let item = |id: &str| result.items.iter().find(|i| i.prescription_id == id).unwrap();
+        assert_eq!(item(&first).score_before, Some(80.0));
+        assert_eq!(item(&first).score_after, Some(60.0));
+        assert_eq!(item(&first).adherence_rate, Some(50.0));
+        // 직전 처방 이후 응답이 다음 처방의 '전' 점수
+        assert_eq!(item(&second).score_before, Some(60.0));
+        assert_eq!(item(&second).score_change, Some(-30.0));
+
+        // 복약 기록/이후 응답이 없는 처방은 부분 결과
+        let partial = item(&without_logs);
+        assert_eq!(partial.score_before, Some(20.0));
+        assert_eq!((partial.score_after, partial.score_change, partial.adherence_rate), (None, None, None));
+
+        assert_eq!(result.sample_size, 3);
+        assert!(result.adherence_score_correlation.is_some_and(|r| r < -0.9));
+
+        let empty = get_treatment_outcome_correlation("missing-patient").unwrap();
+        assert!(empty.items.is_empty());
+        assert_eq!(empty.adherence_score_correlation, None);
+    }
+
+    const PNG_BYTES: &[u8] = b"\x89PNG\r\n\x1a\nrest-of-image";
+
+    #[test]
+    fn survey_attachment_checks_size_and_mime() {
+        let session = pending_session(None);
+        let save = |mime: &str, data: &[u8]| save_survey_attachment(&session.id, "photo", "검사지.png", mime, data);
+
+        assert!(matches!(save("text/plain", b"hello"), Err(AppError::Validation(_))));
+        assert!(matches!(save("image/png", b""), Err(AppError::Validation(_))));
+        // 선언한 형식과 내용이 다르면 거부 (확장자만 바꾼 파일)
+        assert!(matches!(save("image/jpeg", PNG_BYTES), Err(AppError::Validation(_))));
+        let mut too_big = PNG_BYTES.to_vec();
+        too_big.resize(SURVEY_ATTACHMENT_MAX_BYTES + 1, 0);
+        assert!(matches!(save("image/png", &too_big), Err(AppError::Validation(_))));
+
+        let saved = save_survey_attachment(&session.id, "photo", "C:\\scan\\검사지.png", "IMAGE/PNG; name=x", PNG_BYTES).unwrap();
+        assert_eq!((saved.file_name.as_str(), saved.mime_type.as_str()), ("검사지.png", "image/png"));
+        assert_eq!(saved.size, PNG_BYTES.len() as i64);
+        let (stored, data) = get_survey_attachment(&saved.id).unwrap().unwrap();
+        assert_eq!((stored.session_id, stored.response_id), (session.id.clone(), None));
+        assert_eq!(data, PNG_BYTES);
+        assert!(get_survey_attachment("missing-attachment").unwrap().is_none());
+    }
+
+    #[test]
+    fn orphan_attachments_are_purged_once_session_ends() {
+        let open = pending_session(None);
+        let expired = pending_session(Some(Utc::now() - chrono::Duration::minutes(1)));
+        let cancelled = pending_session(None);
+        let save = |session_id: &str| save_survey_attachment(session_id, "photo", "a.png", "image/png", PNG_BYTES).unwrap().id;
+        let (kept, expired_file, cancelled_file) = (save(&open.id), save(&expired.id), save(&cancelled.id));
+        cancel_survey_session(&cancelled.id).unwrap();
+
+        assert!(purge_orphan_survey_attachments().unwrap() >= 2);
+        assert!(get_survey_attachment(&kept).unwrap().is_some());
+        assert!(get_survey_attachment(&expired_file).unwrap().is_none());
+        assert!(get_survey_attachment(&cancelled_file).unwrap().is_none());
+    }
+
+    fn log_at_local(schedule_id: &str, hour: u32, minute: u32, status: MedicationStatus) {
+        use chrono::TimeZone;
+        let taken_at = chrono::Local.with_ymd_and_hms(2001, 3, 5, hour, minute, 0).single().unwrap();
+        create_medication_log(&MedicationLog {
+            id: uuid::Uuid::new_v4().to_string(),
+            schedule_id: schedule_id.to_string(),
+            taken_at: taken_at.with_timezone(&Utc),
+            status,
+            notes: None,
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn medication_time_slots_split_at_11_and_16() {
+        let slot = |hour: u32, minute: u32| MEDICATION_TIME_SLOTS[medication_time_slot(hour * 60 + minute)].1;
+        assert_eq!([slot(0, 0), slot(10, 59), slot(11, 0), slot(15, 59), slot(16, 0), slot(23, 59)], ["아침", "아침", "점심", "점심", "저녁", "저녁"]);
+    }
+
+    #[test]
+    fn adherence_by_time_of_day_matches_nearest_schedule_time() {
+        let (patient_id, prescription) = create_patient_with_prescription();
+        let now = Utc::now();
+        let schedule = MedicationSchedule {
+            id: uuid::Uuid::new_v4().to_string(),
+            patient_id: patient_id.clone(),
+            prescription_id: prescription.id.clone(),
+            start_date: now,
+            end_date: now + chrono::Duration::days(10),
+            times_per_day: 0,
+            medication_times: vec!["08:00".to_string(), "12:30".to_string(), "19:00".to_string()],
+            notes: None,
+            created_at: now,
+            is_completed: false,
+        };
+        create_medication_schedule(&schedule).unwrap();
+
+        log_at_local(&schedule.id, 7, 40, MedicationStatus::Taken);
+        log_at_local(&schedule.id, 8, 20, MedicationStatus::Missed);
+        log_at_local(&schedule.id, 12, 0, MedicationStatus::Taken);
+        // 10:50 기록은 08:00보다 12:30에 가까우므로 점심 복용으로 집계
+        log_at_local(&schedule.id, 10, 50, MedicationStatus::Skipped);
+
+        let slots = get_adherence_by_time_of_day(&patient_id).unwrap();
+        assert_eq!(
+            slots,
+            vec![("아침".to_string(), 50.0), ("점심".to_string(), 50.0), ("저녁".to_string(), 0.0)]
+        );
+
+        let empty = get_adherence_by_time_of_day("no-such-patient").unwrap();
+        assert_eq!(empty.iter().map(|(name, rate)| (name.as_str(), *rate)).collect::<Vec<_>>(), [("아침", 0.0), ("점심", 0.0), ("저녁", 0.0)]);
     }
 
-    Ok(())
-}
+    fn identity_patient(name: &str, birth_date: Option<&str>, phone: Option<&str>) -> Patient {
+        Patient {
+            birth_date: birth_date.map(str::to_string),
+            phone: phone.map(str::to_string),
+            ..Patient::new(name.to_string())
+        }
+    }
 
-// ============ 약재 재고관리 ============
+    #[test]
+    fn duplicate_patients_conflict_on_name_birth_date_and_phone() {
+        init_test_database();
+        let name = format!("중복-{}", uuid::Uuid::new_v4());
+        let create = |birth_date: Option<&str>, phone: Option<&str>| create_patient(&identity_patient(&name, birth_date, phone), false);
+
+        create(Some("1980-01-31"), Some("010-1111-2222")).unwrap();
+        assert!(matches!(create(Some("1980-01-31"), Some("010-1111-2222")), Err(AppError::Conflict(_))));
+        // 생년월일이나 연락처 중 하나라도 다르면 다른 환자
+        create(Some("1980-01-31"), None).unwrap();
+        create(None, Some("010-1111-2222")).unwrap();
+        // NULL과 빈 문자열은 같은 값으로 비교
+        assert!(matches!(create(Some(""), Some("010-1111-2222")), Err(AppError::Conflict(_))));
+        assert!(matches!(create(Some("1980-01-31"), Some("")), Err(AppError::Conflict(_))));
+        // 이름만 있는 환자는 같은 사람으로 볼 수 없으므로 제약하지 않음
+        create(None, None).unwrap();
+        create(Some(""), None).unwrap();
+
+        // 의도적인 동명이인
+        create_patient(&identity_patient(&name, Some("1980-01-31"), Some("010-1111-2222")), true).unwrap();
+    }
 
-pub fn list_herb_inventory() -> AppResult<Vec<HerbInventory>> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
-    let mut stmt = conn.prepare(
-        "SELECT id, herb_id, name, unit, current_stock, min_stock, cost_per_unit, supplier, memo, created_at, updated_at FROM herb_inventory ORDER BY name"
-    )?;
-    let items = stmt.query_map([], |row| {
-        Ok(HerbInventory {
-            id: row.get(0)?,
-            herb_id: row.get(1)?,
-            name: row.get(2)?,
-            unit: row.get(3)?,
-            current_stock: row.get(4)?,
-            min_stock: row.get(5)?,
-            cost_per_unit: row.get(6)?,
-            supplier: row.get(7)?,
-            memo: row.get(8)?,
-            created_at: row.get(9)?,
-            updated_at: row.get(10)?,
-        })
-    })?.collect::<Result<Vec<_>, _>>()?;
-    Ok(items)
-}
+    #[test]
+    fn duplicate_check_ignores_trash_but_blocks_restore_and_update() {
+        init_test_database();
+        let name = format!("휴지통-{}", uuid::Uuid::new_v4());
+        let first = identity_patient(&name, Some("1975-05-05"), None);
+        create_patient(&first, false).unwrap();
+        soft_delete_patient(&first.id).unwrap();
+
+        let second = identity_patient(&name, Some("1975-05-05"), None);
+        create_patient(&second, false).unwrap();
+        assert!(matches!(restore_from_trash("patients", &first.id), Err(AppError::Conflict(_))));
+
+        let mut other = identity_patient(&name, Some("1975-06-06"), None);
+        create_patient(&other, false).unwrap();
+        other.birth_date = Some("1975-05-05".to_string());
+        assert!(matches!(update_patient(&other), Err(AppError::Conflict(_))));
+    }
 
-pub fn create_herb_inventory(item: &HerbInventory) -> AppResult<i64> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
-    conn.execute(
-        "INSERT INTO herb_inventory (herb_id, name, unit, current_stock, min_stock, cost_per_unit, supplier, memo, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
-        params![item.herb_id, item.name, item.unit, item.current_stock, item.min_stock, item.cost_per_unit, item.supplier, item.memo, item.created_at, item.updated_at],
-    )?;
-    Ok(conn.last_insert_rowid())
-}
+    #[test]
+    fn redirect_url_allows_only_plain_http_addresses() {
+        assert_eq!(validate_redirect_url(None).unwrap(), None);
+        assert_eq!(validate_redirect_url(Some("   ")).unwrap(), None);
+        for url in ["https://clinic.example.com", "HTTP://clinic.example.com:8080/예약?from=survey#top"] {
+            assert_eq!(validate_redirect_url(Some(url)).unwrap().as_deref(), Some(url));
+        }
+        assert_eq!(validate_redirect_url(Some(" https://a.kr/ ")).unwrap().as_deref(), Some("https://a.kr/"));
+
+        let rejected = [
+            "javascript:alert(1)",
+            "data:text/html,hi",
+            "ftp://clinic.example.com",
+            "//evil.example.com",
+            "/relative/path",
+            "https://",
+            "https://:443/path",
+            "https://user@evil.example.com",
+            "https://clinic.example.com@evil.example.com/",
+            "https://clinic.example.com\\@evil.example.com",
+            "https://clinic.example.com/a b",
+        ];
+        for url in rejected {
+            assert!(matches!(validate_redirect_url(Some(url)), Err(AppError::Validation(_))), "{}", url);
+        }
+        let too_long = format!("https://a.kr/{}", "a".repeat(REDIRECT_URL_MAX_LEN));
+        assert!(validate_redirect_url(Some(&too_long)).is_err());
+    }
 
-pub fn update_herb_inventory(item: &HerbInventory) -> AppResult<()> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
-    conn.execute(
-        "UPDATE herb_inventory SET herb_id=?1, name=?2, unit=?3, current_stock=?4, min_stock=?5, cost_per_unit=?6, supplier=?7, memo=?8, updated_at=?9 WHERE id=?10",
-        params![item.herb_id, item.name, item.unit, item.current_stock, item.min_stock, item.cost_per_unit, item.supplier, item.memo, item.updated_at, item.id],
-    )?;
-    Ok(())
-}
+    fn scale_question(id: &str, scale_config: Option<ScaleConfig>) -> SurveyQuestion {
+        SurveyQuestion {
+            id: id.to_string(),
+            question_type: QuestionType::Scale,
+            question_text: "척도".to_string(),
+            required: false,
+            options: None,
+            scale_config,
+            order: None,
+            shuffle_options: false,
+            fixed_position: false,
+            translations: None,
+        }
+    }
 
-pub fn delete_herb_inventory(id: i64) -> AppResult<()> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
-    conn.execute("DELETE FROM herb_inventory WHERE id=?1", params![id])?;
-    Ok(())
-}
+    #[test]
+    fn scale_configs_need_min_below_max_and_positive_step() {
+        let config = |min: i32, max: i32, step: Option<i32>| Some(ScaleConfig { min, max, min_label: None, max_label: None, step });
+        assert!(validate_scale_configs(&[scale_question("a", config(0, 100, Some(10))), scale_question("b", config(1, 5, None))]).is_ok());
+        for bad in [config(5, 5, None), config(10, 0, None), config(0, 10, Some(0)), config(0, 10, Some(-2)), None] {
+            assert!(matches!(validate_scale_configs(&[scale_question("a", bad)]), Err(AppError::Validation(_))));
+        }
+        // 척도형이 아닌 문항은 설정이 없어도 됨
+        let text = SurveyQuestion { question_type: QuestionType::Text, ..scale_question("t", None) };
+        assert!(validate_scale_configs(&[text]).is_ok());
+    }
 
-pub fn bulk_import_herb_inventory() -> AppResult<i32> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
-    let now = chrono::Utc::now().to_rfc3339();
+    #[test]
+    fn scale_answers_must_land_on_a_step() {
+        let questions = [scale_question("pain", Some(ScaleConfig { min: 0, max: 100, min_label: None, max_label: None, step: Some(10) }))];
+        let check = |answer: serde_json::Value| {
+            validate_scale_answers(&questions, &[SurveyAnswer { question_id: "pain".to_string(), answer }])
+        };
+        for ok in [serde_json::json!(0), serde_json::json!(40), serde_json::json!("100"), serde_json::json!(null), serde_json::json!(" ")] {
+            assert!(check(ok.clone()).is_ok(), "{}", ok);
+        }
+        for bad in [serde_json::json!(45), serde_json::json!(110), serde_json::json!(-10), serde_json::json!(2.5), serde_json::json!("열"), serde_json::json!([10])] {
+            assert!(matches!(check(bad.clone()), Err(AppError::Validation(_))), "{}", bad);
+        }
+        // 다른 문항의 답변은 검사하지 않음
+        let other = SurveyAnswer { question_id: "memo".to_string(), answer: serde_json::json!(45) };
+        assert!(validate_scale_answers(&questions, &[other]).is_ok());
+    }
 
-    // herbs 테이블에서 herb_inventory에 없는 약재만 가져오기
-    let mut stmt = conn.prepare(
-        "SELECT id, name FROM herbs WHERE id NOT IN (SELECT herb_id FROM herb_inventory WHERE herb_id IS NOT NULL)"
-    )?;
-    let herbs: Vec<(i64, String)> = stmt.query_map([], |row| {
-        Ok((row.get(0)?, row.get(1)?))
-    })?.collect::<Result<Vec<_>, _>>()?;
+    #[test]
+    fn password_reset_issues_temporary_password_and_flags_account() {
+        init_test_database();
+        let username = format!("pw-{}", uuid::Uuid::new_v4());
+        let account = create_staff_account(&username, "비밀번호", "password123", StaffRole::Staff).unwrap();
+        assert_eq!(reset_staff_password("missing-account").unwrap(), None);
+
+        let temporary = reset_staff_password(&account.id).unwrap().unwrap();
+        assert_eq!(temporary.len(), TEMPORARY_PASSWORD_LEN);
+        assert!(!temporary.contains(['0', 'O', '1', 'l', 'I']));
+        assert!(verify_staff_account_password(&username, "password123").unwrap().is_none());
+        assert!(verify_staff_account_password(&username, &temporary).unwrap().unwrap().must_change_password);
+
+        assert!(!change_staff_password(&account.id, "password123", "new-password-1").unwrap());
+        assert!(matches!(change_staff_password(&account.id, &temporary, "short"), Err(AppError::Validation(_))));
+        assert!(change_staff_password(&account.id, &temporary, "new-password-1").unwrap());
+        let changed = verify_staff_account_password(&username, "new-password-1").unwrap().unwrap();
+        assert!(!changed.must_change_password);
+    }
 
-    let count = herbs.len() as i32;
-    for (herb_id, name) in herbs {
-        conn.execute(
-            "INSERT INTO herb_inventory (herb_id, name, unit, current_stock, min_stock, cost_per_unit, created_at, updated_at) VALUES (?1, ?2, 'g', 0, 0, 0, ?3, ?4)",
-            params![herb_id, name, now, now],
-        )?;
+    fn text_macro(abbreviation: &str, expansion: &str, owner_id: Option<&str>) -> TextMacro {
+        TextMacro::new(abbreviation.to_string(), expansion.to_string(), owner_id.map(str::to_string))
     }
-    Ok(count)
-}
 
-// 입출고 이력
+    #[test]
+    fn expand_macros_prefers_longest_match_at_word_boundaries() {
+        let macros = [
+            text_macro("ㅎㅇ", "환자", None),
+            text_macro("ㅎㅇㅈ", "환자 주소증", None),
+            text_macro("", "무시", None),
+        ];
+        assert_eq!(expand_macros("ㅎㅇ 복통, ㅎㅇㅈ 확인", &macros), "환자 복통, 환자 주소증 확인");
+        assert_eq!(expand_macros("(ㅎㅇ)\nㅎㅇ.", &macros), "(환자)\n환자.");
+        // 다른 글자에 붙어 있으면 약어가 아님
+        assert_eq!(expand_macros("가ㅎㅇ ㅎㅇ가 ㅎㅇㅈㅈ", &macros), "가ㅎㅇ ㅎㅇ가 ㅎㅇㅈㅈ");
+        // 바뀐 내용은 다시 확장하지 않음
+        assert_eq!(expand_macros("ㄱ", &[text_macro("ㄱ", "ㄱ ㄱ", None)]), "ㄱ ㄱ");
+        assert_eq!(expand_macros("그대로", &[]), "그대로");
+    }
 
-pub fn list_herb_stock_logs(herb_inventory_id: Option<i64>, limit: Option<i32>) -> AppResult<Vec<HerbStockLog>> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
-    let lim = limit.unwrap_or(200);
+    #[test]
+    fn expand_macros_prefers_staff_macro_over_global() {
+        let global = text_macro("bp", "혈압", None);
+        let mine = text_macro("bp", "혈압(mmHg)", Some("staff-1"));
+        assert_eq!(expand_macros("bp 120", &[global.clone(), mine.clone()]), "혈압(mmHg) 120");
+        assert_eq!(expand_macros("bp 120", &[mine, global]), "혈압(mmHg) 120");
+    }
 
-    if let Some(inv_id) = herb_inventory_id {
-        let mut stmt = conn.prepare(
-            "SELECT id, herb_inventory_id, log_type, amount, prescription_id, patient_name, herb_name, note, created_at FROM herb_stock_log WHERE herb_inventory_id=?1 ORDER BY created_at DESC LIMIT ?2"
-        )?;
-        let items = stmt.query_map(params![inv_id, lim], |row| {
-            Ok(HerbStockLog {
-                id: row.get(0)?,
-                herb_inventory_id: row.get(1)?,
-                log_type: row.get(2)?,
-                amount: row.get(3)?,
-                prescription_id: row.get(4)?,
-                patient_name: row.get(5)?,
-                herb_name: row.get(6)?,
-                note: row.get(7)?,
-                created_at: row.get(8)?,
-            })
-        })?.collect::<Result<Vec<_>, _>>()?;
-        Ok(items)
-    } else {
-        let mut stmt = conn.prepare(
-            "SELECT l.id, l.herb_inventory_id, l.log_type, l.amount, l.prescription_id, l.patient_name, l.herb_name, l.note, l.created_at FROM herb_stock_log l ORDER BY l.created_at DESC LIMIT ?1"
-        )?;
-        let items = stmt.query_map(params![lim], |row| {
-            Ok(HerbStockLog {
-                id: row.get(0)?,
-                herb_inventory_id: row.get(1)?,
-                log_type: row.get(2)?,
-                amount: row.get(3)?,
-                prescription_id: row.get(4)?,
-                patient_name: row.get(5)?,
-                herb_name: row.get(6)?,
-                note: row.get(7)?,
-                created_at: row.get(8)?,
-            })
-        })?.collect::<Result<Vec<_>, _>>()?;
-        Ok(items)
+    #[test]
+    fn text_macros_crud_and_chart_record_expansion() {
+        init_test_database();
+        let owner = format!("macro-owner-{}", uuid::Uuid::new_v4());
+        let abbreviation = format!("m{}", &uuid::Uuid::new_v4().simple().to_string()[..8]);
+
+        let global = save_text_macro(&text_macro(&format!(" {} ", abbreviation), "전역", None)).unwrap();
+        assert_eq!(global.abbreviation, abbreviation);
+        assert!(matches!(save_text_macro(&text_macro(&abbreviation, "중복", None)), Err(AppError::Conflict(_))));
+        assert!(matches!(save_text_macro(&text_macro("a b", "공백", None)), Err(AppError::Validation(_))));
+        assert!(matches!(save_text_macro(&text_macro(&abbreviation, "", None)), Err(AppError::Validation(_))));
+        // 범위가 다르면 같은 약어를 따로 등록
+        let mine = save_text_macro(&text_macro(&abbreviation, "직원", Some(&owner))).unwrap();
+        save_text_macro(&TextMacro { expansion: "직원 약어".to_string(), ..mine.clone() }).unwrap();
+        assert_eq!(get_text_macro(&mine.id).unwrap().unwrap().expansion, "직원 약어");
+
+        let visible = list_text_macros(Some(&owner)).unwrap();
+        assert!(visible.iter().any(|m| m.id == global.id) && visible.iter().any(|m| m.id == mine.id));
+        assert!(!list_text_macros(Some("other-staff")).unwrap().iter().any(|m| m.id == mine.id));
+
+        let patient = Patient::new("약어 테스트".to_string());
+        create_patient(&patient, true).unwrap();
+        let now = Utc::now();
+        let record = |symptoms: &str| ChartRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            patient_id: patient.id.clone(),
+            visit_date: now,
+            chief_complaint: None,
+            symptoms: Some(symptoms.to_string()),
+            diagnosis: None,
+            treatment: None,
+            prescription_id: None,
+            notes: None,
+            created_at: now,
+            updated_at: now,
+        };
+        let text = format!("{} 호소", abbreviation);
+        let saved_symptoms = |macros: MacroExpansion| {
+            let r = record(&text);
+            create_chart_record(&r, macros).unwrap();
+            get_chart_record(&r.id).unwrap().unwrap().symptoms.unwrap()
+        };
+        assert_eq!(saved_symptoms(MacroExpansion::Global), "전역 호소");
+        assert_eq!(saved_symptoms(MacroExpansion::Staff(&owner)), "직원 약어 호소");
+        assert_eq!(saved_symptoms(MacroExpansion::Off), text);
+
+        let existing = record("처음");
+        create_chart_record(&existing, MacroExpansion::Off).unwrap();
+        let updated = update_chart_record(&ChartRecord { symptoms: Some(text.clone()), ..existing }, None, MacroExpansion::Staff(&owner)).unwrap();
+        assert_eq!(updated.symptoms.as_deref(), Some("직원 약어 호소"));
+
+        delete_text_macro(&mine.id).unwrap();
+        assert!(get_text_macro(&mine.id).unwrap().is_none());
+        assert_eq!(saved_symptoms(MacroExpansion::Staff(&owner)), "전역 호소");
+        delete_text_macro(&global.id).unwrap();
     }
-}
 
-pub fn add_stock_log(log: &HerbStockLog) -> AppResult<()> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
+    #[test]
+    fn expiring_sessions_exclude_finished_answered_and_reminded() {
+        init_test_database();
+        let patient = Patient { phone: Some("010-2222-3333".to_string()), ..Patient::new("리마인더 환자".to_string()) };
+        create_patient(&patient, true).unwrap();
+        let in_hours = |h: i64| Some(Utc::now() + chrono::Duration::hours(h));
+        let session = |patient_id: Option<&str>, name: Option<&str>, expires_at| {
+            create_survey_session(patient_id, "default_female_health", name, None, None, None, None, None, None, false, expires_at)
+                .unwrap()
+                .id
+        };
 
-    conn.execute(
-        "INSERT INTO herb_stock_log (herb_inventory_id, log_type, amount, prescription_id, patient_name, herb_name, note, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-        params![log.herb_inventory_id, log.log_type, log.amount, log.prescription_id, log.patient_name, log.herb_name, log.note, log.created_at],
-    )?;
+        let with_phone = session(Some(&patient.id), None, in_hours(2));
+        let without_phone = session(None, Some("연락처 없음"), Some(Utc::now() + chrono::Duration::minutes(150)));
+        let later = session(Some(&patient.id), None, in_hours(10));
+        let expired = session(Some(&patient.id), None, Some(Utc::now() - chrono::Duration::minutes(1)));
+        let completed = session(Some(&patient.id), None, in_hours(2));
+        complete_survey_session(&completed).unwrap();
+        let answered = session(Some(&patient.id), None, in_hours(2));
+        save_survey_response_from_sync(&answered, "default_female_health", Some(&patient.id), None, &[], &Utc::now().to_rfc3339())
+            .unwrap();
+        let ours = [&with_phone, &without_phone, &later, &expired, &completed, &answered];
+
+        let expiring = || -> Vec<ExpiringSurveySession> {
+            get_pending_sessions_expiring(3).unwrap().into_iter().filter(|s| ours.contains(&&s.session_id)).collect()
+        };
+        let found = expiring();
+        assert_eq!(found.iter().map(|s| s.session_id.as_str()).collect::<Vec<_>>(), [with_phone.as_str(), without_phone.as_str()]);
+        assert_eq!((found[0].name.as_deref(), found[0].phone.as_deref()), (Some("리마인더 환자"), Some("010-2222-3333")));
+        assert_eq!((found[1].name.as_deref(), found[1].phone.as_deref()), (Some("연락처 없음"), None));
+
+        // 한 번 처리한 세션은 다시 나오지 않고, 실패로 되돌리면 다음 주기에 다시 대상
+        assert!(claim_survey_reminder(&with_phone).unwrap());
+        assert!(!claim_survey_reminder(&with_phone).unwrap());
+        assert_eq!(expiring().len(), 1);
+        release_survey_reminder(&with_phone).unwrap();
+        assert_eq!(expiring().len(), 2);
+    }
 
-    // 재고 업데이트
-    let delta = match log.log_type.as_str() {
-        "in" => log.amount,
-        "out" => -log.amount,
-        "adjust" => {
-            // adjust는 절대값 세팅
-            conn.execute(
-                "UPDATE herb_inventory SET current_stock=?1, updated_at=?2 WHERE id=?3",
-                params![log.amount, log.created_at, log.herb_inventory_id],
-            )?;
-            return Ok(());
+    #[test]
+    fn survey_reminder_hours_must_be_in_range() {
+        init_test_database();
+        for hours in [0, -1, 169] {
+            assert!(matches!(set_survey_reminder_hours(Some(hours)), Err(AppError::Validation(_))));
         }
-        _ => 0.0,
-    };
+    }
 
-    conn.execute(
-        "UPDATE herb_inventory SET current_stock = current_stock + ?1, updated_at=?2 WHERE id=?3",
-        params![delta, log.created_at, log.herb_inventory_id],
-    )?;
-    Ok(())
-}
+    #[test]
+    fn patient_alerts_sort_by_severity_then_type_then_age() {
+        init_test_database();
+        let patient = Patient::new("중요 정보".to_string());
+        create_patient(&patient, true).unwrap();
+        assert!(get_patient_detail(&patient.id).unwrap().unwrap().alerts.is_empty());
+        assert!(get_patient_detail("missing-patient").unwrap().is_none());
+
+        let base = Utc::now() - chrono::Duration::hours(1);
+        let add = |minutes: i64, alert_type, severity, content: &str| {
+            let alert = PatientAlert {
+                created_at: base + chrono::Duration::minutes(minutes),
+                ..PatientAlert::new(patient.id.clone(), alert_type, severity, content.to_string())
+            };
+            save_patient_alert(&alert).unwrap().unwrap()
+        };
+        add(0, PatientAlertType::Allergy, PatientAlertSeverity::Low, "꽃가루");
+        add(1, PatientAlertType::ChronicDisease, PatientAlertSeverity::High, "당뇨");
+        add(2, PatientAlertType::Other, PatientAlertSeverity::Medium, "임신 가능성");
+        add(4, PatientAlertType::Allergy, PatientAlertSeverity::High, "땅콩");
+        add(3, PatientAlertType::Allergy, PatientAlertSeverity::High, "페니실린");
+        let warfarin = add(5, PatientAlertType::CautionMedication, PatientAlertSeverity::High, "  와파린 복용  ");
+        assert_eq!(warfarin.content, "와파린 복용");
+
+        let contents = |alerts: Vec<PatientAlert>| alerts.into_iter().map(|a| a.content).collect::<Vec<_>>();
+        assert_eq!(
+            contents(get_patient_detail(&patient.id).unwrap().unwrap().alerts),
+            ["페니실린", "땅콩", "와파린 복용", "당뇨", "임신 가능성", "꽃가루"]
+        );
 
-/// 처방 기반 자동 차감
-pub fn deduct_stock_by_prescription(prescription_id: &str, patient_name: &str, final_herbs_json: &str) -> AppResult<Vec<String>> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
-    let now = chrono::Utc::now().to_rfc3339();
+        save_patient_alert(&PatientAlert { severity: PatientAlertSeverity::Low, ..warfarin.clone() }).unwrap();
+        assert_eq!(contents(list_patient_alerts(&patient.id).unwrap()).last().map(String::as_str), Some("와파린 복용"));
+        assert!(delete_patient_alert(&warfarin.id).unwrap());
+        assert!(!delete_patient_alert(&warfarin.id).unwrap());
+        assert_eq!(list_patient_alerts(&patient.id).unwrap().len(), 5);
 
-    #[derive(serde::Deserialize)]
-    struct FinalHerb {
-        name: String,
-        amount: f64,
+        let blank = PatientAlert::new(patient.id.clone(), PatientAlertType::Other, PatientAlertSeverity::Low, " ".to_string());
+        assert!(matches!(save_patient_alert(&blank), Err(AppError::Validation(_))));
+        let orphan = PatientAlert::new("missing-patient".to_string(), PatientAlertType::Other, PatientAlertSeverity::Low, "x".to_string());
+        assert!(save_patient_alert(&orphan).unwrap().is_none());
     }
 
-    let herbs: Vec<FinalHerb> = serde_json::from_str(final_herbs_json)
-        .map_err(|e| AppError::Custom(format!("JSON 파싱 오류: {}", e)))?;
+    fn pull_response(session_id: &str, respondent_name: &str, answer: serde_json::Value) -> bool {
+        let answers = [SurveyAnswer { question_id: "q1".to_string(), answer }];
+        save_survey_response_from_sync(session_id, "default_female_health", None, Some(respondent_name), &answers, "2001-06-01T09:00:00+00:00")
+            .unwrap()
+    }
 
-    let mut warnings = Vec::new();
+    fn conflicts_for(item_id: &str, include_resolved: bool) -> Vec<SyncConflict> {
+        list_sync_conflicts(include_resolved).unwrap().into_iter().filter(|c| c.item_id == item_id).collect()
+    }
 
-    for herb in &herbs {
-        // 약재명으로 재고 찾기
-        let inv: Option<(i64, f64)> = conn.query_row(
-            "SELECT id, current_stock FROM herb_inventory WHERE name=?1",
-            params![herb.name],
-            |row| Ok((row.get(0)?, row.get(1)?)),
-        ).ok();
+    fn stored_response(session_id: &str) -> (String, String, Option<String>) {
+        get_conn()
+            .unwrap()
+            .query_row("SELECT id, answers, respondent_name FROM survey_responses WHERE session_id = ?1", [session_id], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .unwrap()
+    }
 
-        if let Some((inv_id, stock)) = inv {
-            // 출고 기록
-            conn.execute(
-                "INSERT INTO herb_stock_log (herb_inventory_id, log_type, amount, prescription_id, patient_name, herb_name, note, created_at) VALUES (?1, 'out', ?2, ?3, ?4, ?5, '처방 자동차감', ?6)",
-                params![inv_id, herb.amount, prescription_id, patient_name, herb.name, now],
-            )?;
-            // 재고 차감
-            conn.execute(
-                "UPDATE herb_inventory SET current_stock = current_stock - ?1, updated_at=?2 WHERE id=?3",
-                params![herb.amount, now, inv_id],
-            )?;
+    #[test]
+    fn differing_pull_records_one_unresolved_conflict() {
+        init_test_database();
+        let session_id = format!("conflict-{}", uuid::Uuid::new_v4());
+        assert!(pull_response(&session_id, "홍길동", serde_json::json!("목")));
+        let (response_id, local_answers, _) = stored_response(&session_id);
+
+        // 같은 내용이면 충돌 아님
+        assert!(!pull_response(&session_id, "홍길동", serde_json::json!("목")));
+        assert!(conflicts_for(&response_id, true).is_empty());
+
+        assert!(!pull_response(&session_id, "홍길동", serde_json::json!("허리")));
+        assert!(!pull_response(&session_id, "홍길동", serde_json::json!("어깨")));
+        let conflicts = conflicts_for(&response_id, false);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].item_type, SYNC_ITEM_SURVEY_RESPONSE);
+        assert_eq!(conflicts[0].local_data["answers"], local_answers);
+        assert!(conflicts[0].remote_data["answers"].as_str().unwrap().contains("어깨"));
+        // 로컬 기록은 그대로
+        assert_eq!(stored_response(&session_id).1, local_answers);
+        assert!(count_sync_conflicts().unwrap() >= 1);
+    }
 
-            if stock - herb.amount < 0.0 {
-                warnings.push(format!("{}: 재고 부족 (현재 {}g)", herb.name, stock));
-            }
+    #[test]
+    fn resolving_conflicts_applies_choice_and_prunes_old_records() {
+        init_test_database();
+        let kept = format!("conflict-{}", uuid::Uuid::new_v4());
+        let replaced = format!("conflict-{}", uuid::Uuid::new_v4());
+        for session_id in [&kept, &replaced] {
+            pull_response(session_id, "로컬", serde_json::json!(1));
+            pull_response(session_id, "원격", serde_json::json!(2));
         }
+        let (kept_id, kept_answers, _) = stored_response(&kept);
+        let (replaced_id, _, _) = stored_response(&replaced);
+        let kept_conflict = conflicts_for(&kept_id, false).remove(0);
+        let replaced_conflict = conflicts_for(&replaced_id, false).remove(0);
+
+        let old_resolved = uuid::Uuid::new_v4().to_string();
+        get_conn()
+            .unwrap()
+            .execute(
+                "INSERT INTO sync_conflicts (id, item_type, item_id, local_data, remote_data, detected_at, resolved_at, resolution)
+                 VALUES (?1, 'survey_response', 'old-item', '{}', '{}', ?2, ?2, 'local')",
+                params![old_resolved, (Utc::now() - chrono::Duration::days(RESOLVED_SYNC_CONFLICT_RETENTION_DAYS + 1)).to_rfc3339()],
+            )
+            .unwrap();
+
+        let resolved = resolve_sync_conflict(&kept_conflict.id, SyncConflictChoice::Local).unwrap().unwrap();
+        assert_eq!(resolved.resolution, Some(SyncConflictChoice::Local));
+        assert_eq!(stored_response(&kept), (kept_id.clone(), kept_answers, Some("로컬".to_string())));
+        assert!(conflicts_for(&kept_id, false).is_empty());
+        assert_eq!(conflicts_for(&kept_id, true).len(), 1);
+        assert!(matches!(resolve_sync_conflict(&kept_conflict.id, SyncConflictChoice::Remote), Err(AppError::Conflict(_))));
+        assert!(!list_sync_conflicts(true).unwrap().iter().any(|c| c.id == old_resolved));
+
+        resolve_sync_conflict(&replaced_conflict.id, SyncConflictChoice::Remote).unwrap().unwrap();
+        let (id, answers, respondent) = stored_response(&replaced);
+        assert_eq!((id, respondent.as_deref()), (replaced_id, Some("원격")));
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&answers).unwrap()[0]["answer"], 2);
+
+        assert!(resolve_sync_conflict("missing-conflict", SyncConflictChoice::Local).unwrap().is_none());
+        assert_stats_cache_consistent();
     }
 
-    Ok(warnings)
-}
+    #[test]
+    fn email_address_rejects_header_injection() {
+        assert!(is_valid_email_address("patient@example.com"));
+        assert!(is_valid_email_address("hong.gil-dong+clinic@mail.co.kr"));
+        for bad in [
+            "",
+            "patient",
+            "@example.com",
+            "patient@localhost",
+            "a@b@example.com",
+            "patient@example.com\r\nBcc: x@example.com",
+            "Hong <patient@example.com>",
+            "a@example.com, b@example.com",
+            "pa tient@example.com",
+        ] {
+            assert!(!is_valid_email_address(bad), "{:?}", bad);
+        }
+    }
 
-/// 처방 삭제 시 재고 복원
-pub fn restore_stock_by_prescription(prescription_id: &str) -> AppResult<()> {
-    ensure_db_initialized()?;
-    let conn = get_conn()?;
-    let now = chrono::Utc::now().to_rfc3339();
+    #[test]
+    fn mobile_phone_normalizes_domestic_and_international_formats() {
+        assert_eq!(normalize_mobile_phone("010-1234-5678").as_deref(), Some("01012345678"));
+        assert_eq!(normalize_mobile_phone(" 010 1234 5678 ").as_deref(), Some("01012345678"));
+        assert_eq!(normalize_mobile_phone("+82 10-1234-5678").as_deref(), Some("01012345678"));
+        assert_eq!(normalize_mobile_phone("011-123-4567").as_deref(), Some("0111234567"));
+        // 유선 번호, 자릿수 오류, +가 없는 82로 시작하는 번호는 거부
+        assert_eq!(normalize_mobile_phone("02-123-4567"), None);
+        assert_eq!(normalize_mobile_phone("010-1234-56789"), None);
+        assert_eq!(normalize_mobile_phone("8210-1234-5678"), None);
+        assert_eq!(normalize_mobile_phone(""), None);
+    }
 
-    // 해당 처방의 출고 이력 조회
-    let mut stmt = conn.prepare(
-        "SELECT herb_inventory_id, amount FROM herb_stock_log WHERE prescription_id=?1 AND log_type='out'"
-    )?;
-    let logs: Vec<(i64, f64)> = stmt.query_map(params![prescription_id], |row| {
-        Ok((row.get(0)?, row.get(1)?))
-    })?.collect::<Result<Vec<_>, _>>()?;
+    #[test]
+    fn birth_date_check_locks_session_after_three_failures() {
+        init_test_database();
+        let patient = identity_patient("생년월일 확인", Some("1985-03-07"), None);
+        create_patient(&patient, true).unwrap();
+        let pid = Some(patient.id.as_str());
+
+        // 생년월일이 없는 환자나 익명 세션에는 사용할 수 없음
+        let no_birth = identity_patient("생년월일 없음", None, None);
+        create_patient(&no_birth, true).unwrap();
+        assert!(create_survey_session(Some(&no_birth.id), "default_female_health", None, None, None, None, None, None, None, true, None).is_err());
+
+        let verified = create_survey_session(pid, "default_female_health", None, None, None, None, None, None, None, true, None).unwrap();
+        assert!(verified.verify_birth_date);
+        // 구분자 차이는 무시
+        assert_eq!(verify_survey_birth_date(&verified.id, "1985.03.07").unwrap(), BirthDateCheck::Verified);
+        assert_eq!(verify_survey_birth_date(&verified.id, "19850307").unwrap(), BirthDateCheck::Verified);
+
+        let session = create_survey_session(pid, "default_female_health", None, None, None, None, None, None, None, true, None).unwrap();
+        assert_eq!(verify_survey_birth_date(&session.id, "1985-03-08").unwrap(), BirthDateCheck::Mismatch { remaining: 2 });
+        assert_eq!(verify_survey_birth_date(&session.id, "").unwrap(), BirthDateCheck::Mismatch { remaining: 1 });
+        assert_eq!(verify_survey_birth_date(&session.id, "1985-03-09").unwrap(), BirthDateCheck::Locked);
+        // 잠긴 뒤에는 맞는 생년월일도 거부
+        assert_eq!(verify_survey_birth_date(&session.id, "1985-03-07").unwrap(), BirthDateCheck::Locked);
+        assert_eq!(get_survey_session(&session.id).unwrap().unwrap().status, SessionStatus::Locked);
+        assert_eq!(
+            count_rows("SELECT COUNT(*) FROM notifications WHERE notification_type = 'survey_locked' AND patient_id = ?1", &patient.id),
+            1
+        );
+    }
 
-    for (inv_id, amount) in &logs {
-        conn.execute(
-            "UPDATE herb_inventory SET current_stock = current_stock + ?1, updated_at=?2 WHERE id=?3",
-            params![amount, now, inv_id],
-        )?;
+    #[test]
+    fn web_sessions_are_stored_by_token_hash() {
+        init_test_database();
+        let account_id = format!("account-{}", uuid::Uuid::new_v4());
+        let now = Utc::now();
+        let session = WebSession {
+            clinic_name: "고시방한의원".to_string(),
+            account_id: Some(account_id.clone()),
+            must_change_password: true,
+            created_at: now,
+            last_activity: now,
+        };
+        let token = uuid::Uuid::new_v4().to_string();
+        let other = uuid::Uuid::new_v4().to_string();
+        save_web_session(&token, &session).unwrap();
+        save_web_session(&other, &session).unwrap();
+
+        // 토큰 원문은 DB에 남지 않음
+        assert_eq!(count_rows("SELECT COUNT(*) FROM web_sessions WHERE token_hash = ?1", &token), 0);
+        assert_eq!(count_rows("SELECT COUNT(*) FROM web_sessions WHERE account_id = ?1", &account_id), 2);
+
+        let later = now + chrono::Duration::minutes(5);
+        touch_web_session(&token, later).unwrap();
+        let loaded = get_web_session(&token).unwrap().unwrap();
+        assert_eq!(loaded.account_id.as_deref(), Some(account_id.as_str()));
+        assert!(loaded.must_change_password);
+        assert_eq!(loaded.created_at.timestamp(), now.timestamp());
+        assert_eq!(loaded.last_activity.timestamp(), later.timestamp());
+
+        // 재발급 후 이전 토큰은 무효
+        let renewed = uuid::Uuid::new_v4().to_string();
+        assert!(rename_web_session(&token, &renewed).unwrap());
+        assert!(get_web_session(&token).unwrap().is_none());
+        assert!(!rename_web_session(&token, &renewed).unwrap());
+
+        // 비밀번호 변경: 현재 세션만 남기고 변경 필요 표시 해제
+        assert_eq!(delete_account_web_sessions(&account_id, Some(&renewed)).unwrap(), 1);
+        assert!(get_web_session(&other).unwrap().is_none());
+        assert!(!get_web_session(&renewed).unwrap().unwrap().must_change_password);
+
+        delete_web_session(&renewed).unwrap();
+        assert!(get_web_session(&renewed).unwrap().is_none());
     }
 
-    // 출고 이력 삭제
-    conn.execute(
-        "DELETE FROM herb_stock_log WHERE prescription_id=?1 AND log_type='out'",
-        params![prescription_id],
-    )?;
+    #[test]
+    fn expired_web_sessions_are_deleted_by_idle_or_absolute_age() {
+        init_test_database();
+        let old = chrono::DateTime::parse_from_rfc3339("2001-01-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        let cutoff = old + chrono::Duration::hours(8);
+        let session = |created_at, last_activity| WebSession {
+            clinic_name: "고시방한의원".to_string(),
+            account_id: None,
+            must_change_password: false,
+            created_at,
+            last_activity,
+        };
+        let idle = uuid::Uuid::new_v4().to_string();
+        let aged = uuid::Uuid::new_v4().to_string();
+        let active = uuid::Uuid::new_v4().to_string();
+        save_web_session(&idle, &session(cutoff, old)).unwrap();
+        save_web_session(&aged, &session(old, cutoff + chrono::Duration::hours(1))).unwrap();
+        save_web_session(&active, &session(cutoff, cutoff + chrono::Duration::hours(1))).unwrap();
+
+        assert_eq!(delete_expired_web_sessions(cutoff, cutoff).unwrap(), 2);
+        assert!(get_web_session(&idle).unwrap().is_none());
+        assert!(get_web_session(&aged).unwrap().is_none());
+        assert!(get_web_session(&active).unwrap().is_some());
+        delete_web_session(&active).unwrap();
+    }
 
-    Ok(())
+    #[test]
+    fn scheduler_interval_is_validated_and_saved() {
+        init_test_database();
+        assert!(matches!(set_scheduler_interval_secs(9), Err(AppError::Validation(_))));
+        assert!(matches!(set_scheduler_interval_secs(24 * 60 * 60 + 1), Err(AppError::Validation(_))));
+
+        set_scheduler_interval_secs(120).unwrap();
+        assert_eq!(get_scheduler_interval_secs().unwrap(), 120);
+        set_scheduler_interval_secs(DEFAULT_SCHEDULER_INTERVAL_SECS).unwrap();
+        assert_eq!(get_scheduler_interval_secs().unwrap(), DEFAULT_SCHEDULER_INTERVAL_SECS);
+    }
+
+    #[tokio::test]
+    async fn streamed_export_ends_with_patient_count() {
+        init_test_database();
+        create_patient(&Patient::new("스트리밍 내보내기".to_string()), true).unwrap();
+
+        let mut output = Vec::new();
+        stream_all_data(&mut output).await.unwrap();
+        let lines: Vec<serde_json::Value> = output
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+
+        assert!(lines[0].get("clinic_settings").is_some());
+        let trailer = lines.last().unwrap();
+        assert_eq!(trailer["export_complete"], serde_json::json!(true));
+        assert_eq!(trailer["patient_count"], serde_json::json!(lines.len() - 2));
+        assert!(lines[1..lines.len() - 1].iter().all(|line| line.get("patient").is_some()));
+    }
+
+    #[test]
+    fn dashboard_counts_by_local_day() {
+        use chrono::TimeZone;
+        init_test_database();
+        let local = |day: u32, hour: u32, minute: u32| {
+            chrono::Local.with_ymd_and_hms(1998, 7, day, hour, minute, 0).single().unwrap().with_timezone(&Utc)
+        };
+
+        // 로컬 날짜 경계 직전/직후 (KST라면 00:30은 UTC로 전날)
+        for at in [local(14, 23, 59), local(15, 0, 30), local(15, 23, 30), local(16, 0, 0)] {
+            let patient = Patient { created_at: at, updated_at: at, ..Patient::new("대시보드 경계".to_string()) };
+            create_patient(&patient, true).unwrap();
+            let record = ChartRecord {
+                id: uuid::Uuid::new_v4().to_string(),
+                patient_id: patient.id.clone(),
+                visit_date: at,
+                chief_complaint: None,
+                symptoms: None,
+                diagnosis: None,
+                treatment: None,
+                prescription_id: None,
+                notes: None,
+                created_at: at,
+                updated_at: at,
+            };
+            create_chart_record(&record, MacroExpansion::Off).unwrap();
+        }
+
+        let stats = get_dashboard_stats(Some("1998-07-15"), Some("1998-07-15")).unwrap();
+        assert_eq!((stats.from.as_str(), stats.to.as_str()), ("1998-07-15", "1998-07-15"));
+        assert_eq!(stats.new_patients, 2);
+        assert_eq!(stats.visit_count, 2);
+        assert_eq!(get_dashboard_stats(Some("1998-07-14"), Some("1998-07-16")).unwrap().new_patients, 4);
+
+        assert!(matches!(get_dashboard_stats(Some("1998/07/15"), None), Err(AppError::Validation(_))));
+    }
 }
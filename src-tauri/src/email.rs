@@ -0,0 +1,262 @@
+//! 이메일 발송 모듈 (SMTP)
+//!
+//! 원격 환자에게 온라인 설문 링크를, 원장님께 일일 요약을 보냅니다.
+//! 발송에 실패한 메일은 DB 대기열에 넣고 정리 작업 주기마다 다시 보냅니다 (동기화 모듈과 같은 방식).
+
+use std::time::Duration;
+
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::extension::ClientId;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use crate::db;
+use crate::error::{AppError, AppResult};
+use crate::models::{SmtpSecurity, SmtpSettings};
+
+/// 연결 및 SMTP 명령별 응답 대기 시간
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 메일 한 통 발송 전체 제한 시간
+const SEND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 한 번에 재발송할 최대 메일 수
+const RETRY_BATCH_SIZE: u32 = 20;
+
+/// EHLO에 보내는 호스트 이름
+const EHLO_NAME: &str = "gosibang.local";
+
+/// 보낼 메일
+struct EmailMessage<'a> {
+    to: &'a str,
+    subject: &'a str,
+    body: &'a str,
+}
+
+/// 온라인 설문 링크 발송 (실패 시 대기열에 넣고 에러 반환)
+pub async fn send_survey_link(to: &str, url: &str) -> AppResult<()> {
+    let clinic_name = db::get_clinic_settings()
+        .ok()
+        .flatten()
+        .map(|s| s.clinic_name)
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "한의원".to_string());
+    let subject = format!("[{}] 설문 작성 안내", clinic_name);
+    let body = format!(
+        "안녕하세요, {}입니다.\n\n진료에 참고할 설문을 부탁드립니다.\n아래 링크를 눌러 작성해 주세요.\n\n{}\n\n링크는 일정 시간이 지나면 만료됩니다.",
+        clinic_name, url
+    );
+    send_or_queue(&EmailMessage { to, subject: &subject, body: &body }).await
+}
+
+/// 일일 요약 발송 (실패 시 대기열에 넣고 에러 반환)
+pub async fn send_daily_summary(to: &str, body: &str) -> AppResult<()> {
+    let subject = format!("[고시방] 일일 요약 ({})", chrono::Local::now().format("%Y-%m-%d"));
+    send_or_queue(&EmailMessage { to, subject: &subject, body }).await
+}
+
+/// 일일 요약 시각이 되었으면 요약 메일 발송 (알림 설정에서 켜 두고 받는 주소가 있을 때만)
+pub async fn dispatch_daily_summary() -> AppResult<()> {
+    let Some(recipient) = db::get_smtp_settings()?.and_then(|s| s.summary_recipient) else {
+        return Ok(());
+    };
+    if !db::claim_daily_summary()? {
+        return Ok(());
+    }
+
+    let counts = db::get_daily_summary_counts()?;
+    let body = format!(
+        "오늘 제출된 설문 응답: {}건\n환자와 연결되지 않은 응답: {}건\n응답 대기 중인 설문: {}건\n읽지 않은 알림: {}건",
+        counts.responses_today, counts.unlinked_responses, counts.pending_sessions, counts.unread_notifications
+    );
+    send_daily_summary(&recipient, &body).await
+}
+
+/// 대기열의 메일 재발송 (반환: 발송 성공 건수)
+pub async fn retry_pending_emails() -> AppResult<u32> {
+    let Some(settings) = db::get_smtp_settings()? else {
+        return Ok(0);
+    };
+
+    let mut sent_count = 0;
+    for item in db::dequeue_emails(RETRY_BATCH_SIZE)? {
+        let message = EmailMessage { to: &item.recipient, subject: &item.subject, body: &item.body };
+        match deliver(&settings, &message).await {
+            Ok(()) => {
+                db::remove_email(&item.id)?;
+                sent_count += 1;
+                log::info!("이메일 재발송 성공: {}", item.id);
+            }
+            Err(e) => {
+                let next = crate::sync::next_retry_at(item.retry_count.max(0) as u32);
+                log::warn!("이메일 재발송 실패: {} (재시도 {}회, 다음 {}): {}", item.id, item.retry_count + 1, next, e);
+                db::mark_email_failed(&item.id, next, &e.to_string())?;
+            }
+        }
+    }
+    Ok(sent_count)
+}
+
+/// 즉시 발송, 실패하면 대기열에 추가
+async fn send_or_queue(message: &EmailMessage<'_>) -> AppResult<()> {
+    if !db::is_valid_email_address(message.to) {
+        return Err(AppError::Validation(format!("이메일 주소가 올바르지 않습니다: {}", message.to)));
+    }
+    let settings = db::get_smtp_settings()?
+        .ok_or_else(|| AppError::Custom("이메일 발송 설정이 없습니다".to_string()))?;
+
+    match deliver(&settings, message).await {
+        Ok(()) => {
+            log::info!("이메일 발송 완료: {}", message.subject);
+            Ok(())
+        }
+        Err(e) => {
+            log::warn!("이메일 발송 실패, 대기열에 추가: {}", e);
+            db::enqueue_email(message.to, message.subject, message.body, crate::sync::next_retry_at(0))?;
+            Err(e)
+        }
+    }
+}
+
+/// SMTP 서버로 메일 한 통 전송
+async fn deliver(settings: &SmtpSettings, message: &EmailMessage<'_>) -> AppResult<()> {
+    let email = build_message(&settings.from_address, message)?;
+    let transport = build_transport(settings)?;
+    tokio::time::timeout(SEND_TIMEOUT, transport.send(email))
+        .await
+        .map_err(|_| AppError::Custom("SMTP 응답 시간 초과".to_string()))?
+        .map_err(|e| {
+            // 535: 인증 실패
+            if e.status().map(u16::from) == Some(535) {
+                AppError::Custom("SMTP 인증 실패 (아이디/비밀번호 확인)".to_string())
+            } else {
+                AppError::Custom(format!("SMTP 오류: {}", e))
+            }
+        })?;
+    Ok(())
+}
+
+/// 보안 설정에 맞는 SMTP 전송기 (TLS는 공인 인증서만 신뢰)
+fn build_transport(settings: &SmtpSettings) -> AppResult<AsyncSmtpTransport<Tokio1Executor>> {
+    let builder = match settings.security {
+        SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&settings.host),
+        SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.host),
+        SmtpSecurity::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host)),
+    }
+    .map_err(|e| AppError::Custom(format!("SMTP TLS 설정 실패: {}", e)))?;
+
+    let mut builder = builder
+        .port(settings.port)
+        .timeout(Some(CONNECT_TIMEOUT))
+        .hello_name(ClientId::Domain(EHLO_NAME.to_string()));
+    if !settings.username.is_empty() {
+        builder = builder.credentials(Credentials::new(settings.username.clone(), settings.password.clone()));
+    }
+    Ok(builder.build())
+}
+
+/// 메일 작성 (UTF-8 평문, 제목/본문 인코딩은 lettre가 처리)
+fn build_message(from: &str, message: &EmailMessage<'_>) -> AppResult<Message> {
+    let address = |value: &str| {
+        value
+            .parse::<Mailbox>()
+            .map_err(|e| AppError::Validation(format!("이메일 주소가 올바르지 않습니다: {} ({})", value, e)))
+    };
+    Message::builder()
+        .from(address(from)?)
+        .to(address(message.to)?)
+        .subject(message.subject)
+        .header(ContentType::TEXT_PLAIN)
+        .body(message.body.to_string())
+        .map_err(|e| AppError::Custom(format!("메일 작성 실패: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_encodes_korean_headers_and_body() {
+        let body = "안녕하세요\n설문 링크입니다: https://example.com/s/abc\n".repeat(5);
+        let message = EmailMessage { to: "patient@example.com", subject: "[고시방] 설문 안내", body: &body };
+        let raw = String::from_utf8(build_message("clinic@gosibang.kr", &message).unwrap().formatted()).unwrap();
+        let (headers, _) = raw.split_once("\r\n\r\n").unwrap();
+
+        assert!(headers.contains("From: clinic@gosibang.kr\r\n"));
+        assert!(headers.contains("To: patient@example.com\r\n"));
+        assert!(headers.contains("Subject: =?utf-8?"));
+        assert!(headers.contains("Content-Type: text/plain; charset=utf-8"));
+        assert!(headers.contains("Date: "));
+        // 8bit 원문이 헤더/본문에 그대로 실리지 않음
+        assert!(raw.is_ascii());
+    }
+
+    #[test]
+    fn invalid_addresses_are_rejected_before_sending() {
+        let message = EmailMessage { to: "not-an-address", subject: "제목", body: "본문" };
+        assert!(matches!(build_message("clinic@gosibang.kr", &message), Err(AppError::Validation(_))));
+        let message = EmailMessage { to: "patient@example.com", subject: "제목", body: "본문" };
+        assert!(matches!(build_message("", &message), Err(AppError::Validation(_))));
+    }
+
+    /// 평문 SMTP 서버 흉내 (받은 DATA 본문 반환)
+    async fn fake_smtp_server(listener: tokio::net::TcpListener) -> String {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let (socket, _) = listener.accept().await.unwrap();
+        let (read, mut write) = socket.into_split();
+        let mut lines = BufReader::new(read).lines();
+        write.write_all(b"220 fake ESMTP\r\n").await.unwrap();
+        let mut data = String::new();
+        let mut in_data = false;
+        while let Some(line) = lines.next_line().await.unwrap() {
+            if in_data {
+                if line == "." {
+                    in_data = false;
+                    write.write_all(b"250 queued\r\n").await.unwrap();
+                } else {
+                    data.push_str(&line);
+                    data.push('\n');
+                }
+                continue;
+            }
+            let reply: &[u8] = match line.split(' ').next().unwrap_or_default().to_ascii_uppercase().as_str() {
+                "EHLO" => b"250 fake\r\n",
+                "DATA" => {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                }
+                "QUIT" => {
+                    write.write_all(b"221 bye\r\n").await.unwrap();
+                    break;
+                }
+                _ => b"250 ok\r\n",
+            };
+            write.write_all(reply).await.unwrap();
+        }
+        data
+    }
+
+    #[tokio::test]
+    async fn delivers_through_smtp_server() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(fake_smtp_server(listener));
+
+        let settings = SmtpSettings {
+            host: "127.0.0.1".to_string(),
+            port,
+            security: SmtpSecurity::None,
+            username: String::new(),
+            password: String::new(),
+            from_address: "clinic@gosibang.kr".to_string(),
+            summary_recipient: None,
+        };
+        let message = EmailMessage { to: "patient@example.com", subject: "설문 안내", body: "링크: https://example.com/s/abc" };
+        deliver(&settings, &message).await.unwrap();
+
+        let data = server.await.unwrap();
+        assert!(data.contains("To: patient@example.com"));
+        assert!(data.contains("Subject: =?utf-8?"));
+    }
+}
@@ -157,3 +157,112 @@ fn deobfuscate(obfuscated: &str, salt: &str) -> String {
         .map(|(i, b)| (b ^ salt_bytes[i % salt_bytes.len()]) as char)
         .collect()
 }
+
+// ============ 백업 파일 암호화 ============
+
+/// 암호화 백업 파일 헤더
+const BACKUP_MAGIC: &[u8; 6] = b"GSBAK1";
+const BACKUP_SALT_LEN: usize = 16;
+const BACKUP_NONCE_LEN: usize = 12;
+
+/// 암호 문구에서 AES-256 키 유도 (Argon2id)
+fn derive_backup_key(passphrase: &str, salt: &[u8]) -> AppResult<ring::aead::LessSafeKey> {
+    use ring::aead::{LessSafeKey, UnboundKey, AES_256_GCM};
+
+    let mut key_bytes = [0u8; 32];
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key_bytes)
+        .map_err(|e| AppError::Custom(format!("키 유도 실패: {}", e)))?;
+
+    let unbound = UnboundKey::new(&AES_256_GCM, &key_bytes)
+        .map_err(|_| AppError::Custom("암호화 키 생성 실패".to_string()))?;
+    Ok(LessSafeKey::new(unbound))
+}
+
+/// 백업 데이터 암호화 (AES-256-GCM)
+///
+/// 형식: 헤더(6) + salt(16) + nonce(12) + 암호문 + 인증 태그(16)
+pub fn encrypt_backup(plaintext: &[u8], passphrase: &str) -> AppResult<Vec<u8>> {
+    use ring::aead::{Aad, Nonce};
+
+    if passphrase.is_empty() {
+        return Err(AppError::Custom("백업 암호를 입력해주세요".to_string()));
+    }
+
+    let mut rng = rand::thread_rng();
+    let salt: [u8; BACKUP_SALT_LEN] = rng.gen();
+    let nonce_bytes: [u8; BACKUP_NONCE_LEN] = rng.gen();
+
+    let key = derive_backup_key(passphrase, &salt)?;
+    let mut in_out = plaintext.to_vec();
+    key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce_bytes), Aad::from(BACKUP_MAGIC), &mut in_out)
+        .map_err(|_| AppError::Custom("백업 암호화 실패".to_string()))?;
+
+    let mut out = Vec::with_capacity(BACKUP_MAGIC.len() + salt.len() + nonce_bytes.len() + in_out.len());
+    out.extend_from_slice(BACKUP_MAGIC);
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&nonce_bytes);
+    out.extend_from_slice(&in_out);
+    Ok(out)
+}
+
+/// 백업 데이터 복호화 (암호 불일치/변조 시 인증 태그 검증 실패로 거부)
+pub fn decrypt_backup(data: &[u8], passphrase: &str) -> AppResult<Vec<u8>> {
+    use ring::aead::{Aad, Nonce};
+
+    let header_len = BACKUP_MAGIC.len() + BACKUP_SALT_LEN + BACKUP_NONCE_LEN;
+    if data.len() < header_len || &data[..BACKUP_MAGIC.len()] != BACKUP_MAGIC {
+        return Err(AppError::Custom("암호화 백업 파일이 아닙니다".to_string()));
+    }
+
+    let salt = &data[BACKUP_MAGIC.len()..BACKUP_MAGIC.len() + BACKUP_SALT_LEN];
+    let mut nonce_bytes = [0u8; BACKUP_NONCE_LEN];
+    nonce_bytes.copy_from_slice(&data[BACKUP_MAGIC.len() + BACKUP_SALT_LEN..header_len]);
+
+    let key = derive_backup_key(passphrase, salt)?;
+    let mut in_out = data[header_len..].to_vec();
+    let plaintext = key
+        .open_in_place(Nonce::assume_unique_for_key(nonce_bytes), Aad::from(BACKUP_MAGIC), &mut in_out)
+        .map_err(|_| AppError::Custom("백업 암호가 올바르지 않거나 파일이 손상되었습니다".to_string()))?;
+
+    Ok(plaintext.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backup_round_trip() {
+        let encrypted = encrypt_backup("환자 백업".as_bytes(), "correct horse").unwrap();
+        assert_eq!(&encrypted[..BACKUP_MAGIC.len()], BACKUP_MAGIC);
+        assert!(!encrypted.windows("환자".len()).any(|w| w == "환자".as_bytes()));
+        assert_eq!(decrypt_backup(&encrypted, "correct horse").unwrap(), "환자 백업".as_bytes());
+
+        // 같은 내용도 salt/nonce가 달라 매번 다른 암호문
+        assert_ne!(encrypt_backup("환자 백업".as_bytes(), "correct horse").unwrap(), encrypted);
+    }
+
+    #[test]
+    fn backup_rejects_wrong_passphrase_or_tampering() {
+        let encrypted = encrypt_backup(b"{\"patients_data\": []}", "correct horse").unwrap();
+        assert!(decrypt_backup(&encrypted, "wrong horse").is_err());
+
+        // 암호문 / 인증 태그 변조
+        let mut tampered = encrypted.clone();
+        let body = BACKUP_MAGIC.len() + BACKUP_SALT_LEN + BACKUP_NONCE_LEN;
+        tampered[body] ^= 0x01;
+        assert!(decrypt_backup(&tampered, "correct horse").is_err());
+        let mut tampered = encrypted.clone();
+        *tampered.last_mut().unwrap() ^= 0x01;
+        assert!(decrypt_backup(&tampered, "correct horse").is_err());
+        assert!(decrypt_backup(&encrypted[..encrypted.len() - 1], "correct horse").is_err());
+    }
+
+    #[test]
+    fn plain_or_empty_input_is_not_a_backup() {
+        assert!(encrypt_backup(b"data", "").is_err());
+        assert!(decrypt_backup(b"{\"patients_data\": []}", "correct horse").is_err());
+        assert!(decrypt_backup(BACKUP_MAGIC, "correct horse").is_err());
+    }
+}
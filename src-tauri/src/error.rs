@@ -1,5 +1,45 @@
 use thiserror::Error;
 
+/// 앱 오류
+///
+/// API 응답의 오류 코드 (`code`):
+///
+/// | 코드 | 변형 |
+/// |---|---|
+/// | `ERR_DB_001` | `Database` |
+/// | `ERR_AUTH` | `Auth` |
+/// | `ERR_NETWORK` | `Network` |
+/// | `ERR_AUTH_SUBSCRIPTION` | `SubscriptionExpired` |
+/// | `ERR_AUTH_CREDS` | `InvalidCredentials` |
+/// | `ERR_AUTH_REQUIRED` | `NotAuthenticated` |
+/// | `ERR_IO` | `Io` |
+/// | `ERR_SERIALIZATION` | `Serialization` |
+/// | `ERR_VALIDATION` | `Validation` |
+/// | `ERR_CONFLICT` | `Conflict` |
+/// | `ERR_NOT_FOUND` | `NotFound` |
+/// | `ERR_GENERAL` | `Custom` |
+///
+/// 처리기에서 `AppError` 없이 바로 거부할 때는 상태 코드로 정함 (`status_error_code`):
+///
+/// | 코드 | 상태 |
+/// |---|---|
+/// | `ERR_VALIDATION` | 400, 422 |
+/// | `ERR_AUTH_REQUIRED` | 401 |
+/// | `ERR_FORBIDDEN` | 403 |
+/// | `ERR_NOT_FOUND` | 404 |
+/// | `ERR_CONFLICT` | 409 |
+/// | `ERR_PAYLOAD_TOO_LARGE` | 413 |
+/// | `ERR_UNSUPPORTED_MEDIA_TYPE` | 415 |
+/// | `ERR_LOCKED` | 423 |
+/// | `ERR_RATE_LIMITED` | 429 |
+/// | `ERR_GENERAL` | 그 외 |
+///
+/// 상태 코드만으로 구분할 수 없는 경우:
+///
+/// | 코드 | 상태 | 의미 |
+/// |---|---|---|
+/// | `ERR_BIRTH_DATE_REQUIRED` | 403 | 설문 시작 전 생년월일 확인 필요 |
+/// | `ERR_BIRTH_DATE_MISMATCH` | 403 | 생년월일 불일치 (메시지에 남은 시도 횟수) |
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Database error: {0}")]
@@ -26,17 +66,137 @@ pub enum AppError {
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
 
+    #[error("Validation error: {0}")]
+    Validation(String),
+
+    #[error("{0}")]
+    Conflict(String),
+
+    #[error("{0}")]
+    NotFound(String),
+
     #[error("{0}")]
     Custom(String),
 }
 
+impl AppError {
+    /// 오류 종류별 고정 코드 (메시지 문구가 바뀌어도 유지)
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Database(_) => "ERR_DB_001",
+            Self::Auth(_) => "ERR_AUTH",
+            Self::Network(_) => "ERR_NETWORK",
+            Self::SubscriptionExpired => "ERR_AUTH_SUBSCRIPTION",
+            Self::InvalidCredentials => "ERR_AUTH_CREDS",
+            Self::NotAuthenticated => "ERR_AUTH_REQUIRED",
+            Self::Io(_) => "ERR_IO",
+            Self::Serialization(_) => "ERR_SERIALIZATION",
+            Self::Validation(_) => "ERR_VALIDATION",
+            Self::Conflict(_) => "ERR_CONFLICT",
+            Self::NotFound(_) => "ERR_NOT_FOUND",
+            Self::Custom(_) => "ERR_GENERAL",
+        }
+    }
+
+    /// 웹 API 응답 상태 (검증 400, 충돌 409, 없음 404, 인증 401, 구독 만료 403, 외부 서버 통신 502, 그 외 500)
+    pub fn status_code(&self) -> axum::http::StatusCode {
+        use axum::http::StatusCode;
+        match self {
+            Self::Validation(_) => StatusCode::BAD_REQUEST,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Auth(_) | Self::InvalidCredentials | Self::NotAuthenticated => StatusCode::UNAUTHORIZED,
+            Self::SubscriptionExpired => StatusCode::FORBIDDEN,
+            Self::Network(_) => StatusCode::BAD_GATEWAY,
+            Self::Database(_) | Self::Io(_) | Self::Serialization(_) | Self::Custom(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// 응답 본문 (검증/충돌/없음/일반 오류는 접두어 없이 원래 메시지)
+    pub fn to_response(&self) -> AppErrorResponse {
+        let message = match self {
+            Self::Validation(msg) | Self::Conflict(msg) | Self::NotFound(msg) | Self::Custom(msg) => msg.clone(),
+            other => other.to_string(),
+        };
+        AppErrorResponse { code: self.code(), message }
+    }
+}
+
+/// 기계가 읽을 수 있는 오류 응답 (`{"code": "...", "message": "..."}`) - 웹 API의 모든 오류 본문
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct AppErrorResponse {
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl AppErrorResponse {
+    /// 상태 코드 기준 오류 응답
+    pub fn for_status(status: axum::http::StatusCode, message: impl Into<String>) -> Self {
+        Self { code: status_error_code(status), message: message.into() }
+    }
+}
+
+/// 상태 코드별 오류 코드 (`AppError` 없이 거부하는 응답용)
+pub fn status_error_code(status: axum::http::StatusCode) -> &'static str {
+    use axum::http::StatusCode;
+    match status {
+        StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => "ERR_VALIDATION",
+        StatusCode::UNAUTHORIZED => "ERR_AUTH_REQUIRED",
+        StatusCode::FORBIDDEN => "ERR_FORBIDDEN",
+        StatusCode::NOT_FOUND => "ERR_NOT_FOUND",
+        StatusCode::CONFLICT => "ERR_CONFLICT",
+        StatusCode::PAYLOAD_TOO_LARGE => "ERR_PAYLOAD_TOO_LARGE",
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => "ERR_UNSUPPORTED_MEDIA_TYPE",
+        StatusCode::LOCKED => "ERR_LOCKED",
+        StatusCode::TOO_MANY_REQUESTS => "ERR_RATE_LIMITED",
+        _ => "ERR_GENERAL",
+    }
+}
+
 impl serde::Serialize for AppError {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::ser::Serializer,
     {
-        serializer.serialize_str(self.to_string().as_ref())
+        self.to_response().serialize(serializer)
     }
 }
 
 pub type AppResult<T> = Result<T, AppError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+
+    #[test]
+    fn status_code_follows_variant() {
+        assert_eq!(AppError::Validation("x".into()).status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(AppError::Conflict("x".into()).status_code(), StatusCode::CONFLICT);
+        assert_eq!(AppError::NotFound("x".into()).status_code(), StatusCode::NOT_FOUND);
+        assert_eq!(AppError::NotAuthenticated.status_code(), StatusCode::UNAUTHORIZED);
+        assert_eq!(AppError::SubscriptionExpired.status_code(), StatusCode::FORBIDDEN);
+        assert_eq!(AppError::Custom("x".into()).status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+        let db_error = AppError::from(rusqlite::Error::QueryReturnedNoRows);
+        assert_eq!(db_error.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn response_keeps_message_without_prefix() {
+        let response = AppError::Validation("이름을 입력해주세요".into()).to_response();
+        assert_eq!(response.code, "ERR_VALIDATION");
+        assert_eq!(response.message, "이름을 입력해주세요");
+
+        let value = serde_json::to_value(AppError::InvalidCredentials).unwrap();
+        assert_eq!(value, serde_json::json!({"code": "ERR_AUTH_CREDS", "message": "Invalid credentials"}));
+    }
+
+    #[test]
+    fn status_response_uses_status_code() {
+        let response = AppErrorResponse::for_status(StatusCode::FORBIDDEN, "권한이 없습니다");
+        assert_eq!(response.code, "ERR_FORBIDDEN");
+        assert_eq!(status_error_code(StatusCode::UNAUTHORIZED), "ERR_AUTH_REQUIRED");
+        assert_eq!(status_error_code(StatusCode::NOT_FOUND), "ERR_NOT_FOUND");
+        assert_eq!(status_error_code(StatusCode::INTERNAL_SERVER_ERROR), "ERR_GENERAL");
+    }
+}
@@ -0,0 +1,354 @@
+//! 환자용 설문 페이지 다국어 지원
+//!
+//! `/s/{token}`, `/patient` 페이지의 고정 문구(버튼, 안내, 오류 페이지)를
+//! `?lang=` 쿼리로 선택한 언어로 표시합니다. 기본값은 한국어입니다.
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// 지원 언어
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lang {
+    #[default]
+    Ko,
+    En,
+    Zh,
+    Vi,
+}
+
+impl Lang {
+    /// `lang` 쿼리 값 해석 (`en`, `zh-CN` 등) - 알 수 없으면 한국어
+    pub fn from_query(value: Option<&str>) -> Self {
+        let code = value
+            .and_then(|v| v.split(['-', '_']).next())
+            .map(|v| v.trim().to_ascii_lowercase())
+            .unwrap_or_default();
+        match code.as_str() {
+            "en" => Lang::En,
+            "zh" => Lang::Zh,
+            "vi" => Lang::Vi,
+            _ => Lang::Ko,
+        }
+    }
+
+    /// 쿼리 파라미터 맵에서 언어 추출
+    pub fn from_params(params: &HashMap<String, String>) -> Self {
+        Self::from_query(params.get("lang").map(|s| s.as_str()))
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Lang::Ko => "ko",
+            Lang::En => "en",
+            Lang::Zh => "zh",
+            Lang::Vi => "vi",
+        }
+    }
+
+    pub fn strings(self) -> &'static UiStrings {
+        match self {
+            Lang::Ko => &KO,
+            Lang::En => &EN,
+            Lang::Zh => &ZH,
+            Lang::Vi => &VI,
+        }
+    }
+}
+
+/// 환자 화면 고정 문구 (스크립트에서도 쓰도록 JSON 직렬화)
+#[derive(Debug, Serialize)]
+pub struct UiStrings {
+    // 설문 진행
+    pub survey: &'static str,
+    pub prev: &'static str,
+    pub next: &'static str,
+    pub submit: &'static str,
+    pub answer_placeholder: &'static str,
+    /// `{q}` 자리에 질문 문구
+    pub required_alert: &'static str,
+    pub preview_no_submit: &'static str,
+    pub submit_failed: &'static str,
+    pub network_error: &'static str,
+    pub submitted: &'static str,
+    pub thanks: &'static str,
+    // 오류 페이지
+    pub error: &'static str,
+    pub not_found: &'static str,
+    pub not_found_detail: &'static str,
+    pub server_error: &'static str,
+    pub retry_later: &'static str,
+    pub already_completed: &'static str,
+    pub expired: &'static str,
+    pub expired_detail: &'static str,
+    pub locked: &'static str,
+    pub locked_detail: &'static str,
+    pub template_not_found: &'static str,
+    // 본인 확인
+    pub verify_title: &'static str,
+    pub verify_prompt: &'static str,
+    pub confirm: &'static str,
+    pub verify_failed: &'static str,
+    // 키오스크
+    pub kiosk_subtitle: &'static str,
+    pub survey_type: &'static str,
+    pub select_survey: &'static str,
+    pub patient_name: &'static str,
+    pub name_placeholder: &'static str,
+    pub referral_source: &'static str,
+    pub referral_placeholder: &'static str,
+    pub enter_name: &'static str,
+    pub start_survey: &'static str,
+    pub hint_label: &'static str,
+    pub staff_hint: &'static str,
+    pub no_questions: &'static str,
+    pub session_failed: &'static str,
+    /// 환자 이름 뒤 호칭 (예: 님)
+    pub name_suffix: &'static str,
+    pub completed: &'static str,
+    pub return_tablet: &'static str,
+    /// `{n}` 자리에 남은 초
+    pub countdown: &'static str,
+    pub idle_prompt: &'static str,
+    pub idle_continue: &'static str,
+    // 연락처 (연락처 필수 템플릿)
+    pub contact_title: &'static str,
+    pub phone: &'static str,
+    pub birth_date: &'static str,
+    pub contact_required: &'static str,
+    // 파일 첨부
+    pub uploading: &'static str,
+    pub upload_failed: &'static str,
+    /// `{n}` 자리에 최대 MB
+    pub file_too_large: &'static str,
+    pub upload_in_progress: &'static str,
+    // 제출 후 이동
+    pub redirecting: &'static str,
+}
+
+static KO: UiStrings = UiStrings {
+    survey: "설문",
+    prev: "이전",
+    next: "다음",
+    submit: "제출하기",
+    answer_placeholder: "답변을 입력하세요",
+    required_alert: "\"{q}\" 질문에 답변해주세요.",
+    preview_no_submit: "미리보기에서는 응답이 제출되지 않습니다.",
+    submit_failed: "제출에 실패했습니다.",
+    network_error: "네트워크 오류가 발생했습니다.",
+    submitted: "설문이 제출되었습니다",
+    thanks: "감사합니다.",
+    error: "오류",
+    not_found: "설문을 찾을 수 없습니다",
+    not_found_detail: "잘못된 링크이거나 만료된 설문입니다.",
+    server_error: "오류가 발생했습니다",
+    retry_later: "잠시 후 다시 시도해주세요.",
+    already_completed: "이미 완료된 설문입니다",
+    expired: "만료된 설문입니다",
+    expired_detail: "새로운 설문 링크를 요청해주세요.",
+    locked: "잠긴 설문입니다",
+    locked_detail: "본인 확인에 여러 번 실패했습니다. 한의원에 문의해주세요.",
+    template_not_found: "설문 템플릿을 찾을 수 없습니다",
+    verify_title: "본인 확인",
+    verify_prompt: "설문을 시작하기 전에 생년월일을 입력해주세요.",
+    confirm: "확인",
+    verify_failed: "확인에 실패했습니다.",
+    kiosk_subtitle: "설문 시스템",
+    survey_type: "설문 종류",
+    select_survey: "설문을 선택하세요",
+    patient_name: "환자 이름",
+    name_placeholder: "이름을 입력하세요",
+    referral_source: "어떻게 알고 오셨나요? (선택)",
+    referral_placeholder: "선택 안 함",
+    enter_name: "환자 이름을 입력하세요",
+    start_survey: "설문 시작하기",
+    hint_label: "안내",
+    staff_hint: "직원이 위 정보를 입력한 후 환자에게 태블릿을 건네주세요.",
+    no_questions: "설문 질문이 없습니다",
+    session_failed: "세션 생성 실패",
+    name_suffix: "님",
+    completed: "설문이 완료되었습니다",
+    return_tablet: "태블릿을 직원에게 돌려주세요.",
+    countdown: "{n}초 후 처음으로 돌아갑니다",
+    idle_prompt: "계속하시겠습니까?",
+    idle_continue: "계속하기",
+    contact_title: "연락처 정보",
+    phone: "휴대폰 번호",
+    birth_date: "생년월일",
+    contact_required: "이름과 휴대폰 번호 또는 생년월일을 입력해주세요.",
+    uploading: "업로드 중...",
+    upload_failed: "파일 업로드에 실패했습니다.",
+    file_too_large: "파일이 너무 큽니다 (최대 {n}MB).",
+    upload_in_progress: "파일 업로드가 끝난 후 제출해주세요.",
+    redirecting: "잠시 후 한의원 페이지로 이동합니다.",
+};
+
+static EN: UiStrings = UiStrings {
+    survey: "Survey",
+    prev: "Previous",
+    next: "Next",
+    submit: "Submit",
+    answer_placeholder: "Enter your answer",
+    required_alert: "Please answer \"{q}\".",
+    preview_no_submit: "Responses are not submitted in preview.",
+    submit_failed: "Submission failed.",
+    network_error: "A network error occurred.",
+    submitted: "Your survey has been submitted",
+    thanks: "Thank you.",
+    error: "Error",
+    not_found: "Survey not found",
+    not_found_detail: "The link is invalid or the survey has expired.",
+    server_error: "Something went wrong",
+    retry_later: "Please try again later.",
+    already_completed: "This survey has already been completed",
+    expired: "This survey has expired",
+    expired_detail: "Please ask the clinic for a new survey link.",
+    locked: "This survey is locked",
+    locked_detail: "Identity verification failed too many times. Please contact the clinic.",
+    template_not_found: "Survey form not found",
+    verify_title: "Identity check",
+    verify_prompt: "Please enter your date of birth before starting the survey.",
+    confirm: "Confirm",
+    verify_failed: "Verification failed.",
+    kiosk_subtitle: "Patient survey",
+    survey_type: "Survey",
+    select_survey: "Select a survey",
+    patient_name: "Patient name",
+    name_placeholder: "Enter name",
+    referral_source: "How did you hear about us? (optional)",
+    referral_placeholder: "Prefer not to say",
+    enter_name: "Please enter the patient name",
+    start_survey: "Start survey",
+    hint_label: "Note",
+    staff_hint: "Staff: fill in the details above, then hand the tablet to the patient.",
+    no_questions: "This survey has no questions",
+    session_failed: "Could not start the survey",
+    name_suffix: "",
+    completed: "Survey complete",
+    return_tablet: "Please return the tablet to the staff.",
+    countdown: "Returning to the start in {n}s",
+    idle_prompt: "Are you still there?",
+    idle_continue: "Continue",
+    contact_title: "Contact details",
+    phone: "Mobile number",
+    birth_date: "Date of birth",
+    contact_required: "Please enter your name and either a mobile number or date of birth.",
+    uploading: "Uploading...",
+    upload_failed: "File upload failed.",
+    file_too_large: "The file is too large (max {n}MB).",
+    upload_in_progress: "Please wait for the upload to finish before submitting.",
+    redirecting: "You will be redirected to the clinic's page shortly.",
+};
+
+static ZH: UiStrings = UiStrings {
+    survey: "问卷",
+    prev: "上一题",
+    next: "下一题",
+    submit: "提交",
+    answer_placeholder: "请输入您的回答",
+    required_alert: "请回答“{q}”。",
+    preview_no_submit: "预览模式下不会提交回答。",
+    submit_failed: "提交失败。",
+    network_error: "网络错误，请重试。",
+    submitted: "问卷已提交",
+    thanks: "谢谢。",
+    error: "错误",
+    not_found: "找不到问卷",
+    not_found_detail: "链接无效或问卷已过期。",
+    server_error: "发生错误",
+    retry_later: "请稍后再试。",
+    already_completed: "该问卷已完成",
+    expired: "该问卷已过期",
+    expired_detail: "请向诊所索取新的问卷链接。",
+    locked: "该问卷已锁定",
+    locked_detail: "身份验证失败次数过多，请联系诊所。",
+    template_not_found: "找不到问卷模板",
+    verify_title: "身份验证",
+    verify_prompt: "开始问卷前，请输入您的出生日期。",
+    confirm: "确认",
+    verify_failed: "验证失败。",
+    kiosk_subtitle: "问卷系统",
+    survey_type: "问卷类型",
+    select_survey: "请选择问卷",
+    patient_name: "患者姓名",
+    name_placeholder: "请输入姓名",
+    referral_source: "您是如何了解到我们的？（可选）",
+    referral_placeholder: "不选择",
+    enter_name: "请输入患者姓名",
+    start_survey: "开始问卷",
+    hint_label: "提示",
+    staff_hint: "工作人员填写上述信息后，请将平板交给患者。",
+    no_questions: "该问卷没有题目",
+    session_failed: "无法开始问卷",
+    name_suffix: "",
+    completed: "问卷已完成",
+    return_tablet: "请将平板交还给工作人员。",
+    countdown: "{n}秒后返回首页",
+    idle_prompt: "是否继续？",
+    idle_continue: "继续",
+    contact_title: "联系方式",
+    phone: "手机号码",
+    birth_date: "出生日期",
+    contact_required: "请输入姓名以及手机号码或出生日期。",
+    uploading: "正在上传...",
+    upload_failed: "文件上传失败。",
+    file_too_large: "文件过大（最大 {n}MB）。",
+    upload_in_progress: "请在上传完成后再提交。",
+    redirecting: "即将跳转到诊所页面。",
+};
+
+static VI: UiStrings = UiStrings {
+    survey: "Khảo sát",
+    prev: "Trước",
+    next: "Tiếp",
+    submit: "Gửi",
+    answer_placeholder: "Nhập câu trả lời của bạn",
+    required_alert: "Vui lòng trả lời câu hỏi \"{q}\".",
+    preview_no_submit: "Ở chế độ xem trước, câu trả lời sẽ không được gửi.",
+    submit_failed: "Gửi không thành công.",
+    network_error: "Đã xảy ra lỗi mạng.",
+    submitted: "Khảo sát đã được gửi",
+    thanks: "Xin cảm ơn.",
+    error: "Lỗi",
+    not_found: "Không tìm thấy khảo sát",
+    not_found_detail: "Liên kết không hợp lệ hoặc khảo sát đã hết hạn.",
+    server_error: "Đã xảy ra lỗi",
+    retry_later: "Vui lòng thử lại sau.",
+    already_completed: "Khảo sát này đã được hoàn thành",
+    expired: "Khảo sát đã hết hạn",
+    expired_detail: "Vui lòng yêu cầu phòng khám gửi liên kết mới.",
+    locked: "Khảo sát đã bị khóa",
+    locked_detail: "Xác minh danh tính thất bại quá nhiều lần. Vui lòng liên hệ phòng khám.",
+    template_not_found: "Không tìm thấy mẫu khảo sát",
+    verify_title: "Xác minh danh tính",
+    verify_prompt: "Vui lòng nhập ngày sinh trước khi bắt đầu khảo sát.",
+    confirm: "Xác nhận",
+    verify_failed: "Xác minh không thành công.",
+    kiosk_subtitle: "Hệ thống khảo sát",
+    survey_type: "Loại khảo sát",
+    select_survey: "Chọn khảo sát",
+    patient_name: "Tên bệnh nhân",
+    name_placeholder: "Nhập tên",
+    referral_source: "Bạn biết đến chúng tôi qua đâu? (không bắt buộc)",
+    referral_placeholder: "Không chọn",
+    enter_name: "Vui lòng nhập tên bệnh nhân",
+    start_survey: "Bắt đầu khảo sát",
+    hint_label: "Lưu ý",
+    staff_hint: "Nhân viên nhập thông tin ở trên rồi đưa máy tính bảng cho bệnh nhân.",
+    no_questions: "Khảo sát không có câu hỏi",
+    session_failed: "Không thể bắt đầu khảo sát",
+    name_suffix: "",
+    completed: "Đã hoàn thành khảo sát",
+    return_tablet: "Vui lòng trả máy tính bảng cho nhân viên.",
+    countdown: "Quay lại màn hình đầu sau {n} giây",
+    idle_prompt: "Bạn có muốn tiếp tục không?",
+    idle_continue: "Tiếp tục",
+    contact_title: "Thông tin liên hệ",
+    phone: "Số điện thoại di động",
+    birth_date: "Ngày sinh",
+    contact_required: "Vui lòng nhập họ tên và số điện thoại hoặc ngày sinh.",
+    uploading: "Đang tải lên...",
+    upload_failed: "Tải tệp lên thất bại.",
+    file_too_large: "Tệp quá lớn (tối đa {n}MB).",
+    upload_in_progress: "Vui lòng đợi tải lên xong rồi gửi.",
+    redirecting: "Bạn sẽ được chuyển đến trang của phòng khám trong giây lát.",
+};
@@ -0,0 +1,441 @@
+//! 백그라운드 작업 큐
+//!
+//! 대량 내보내기(PDF/엑셀/CSV)처럼 오래 걸리는 작업을 화면을 막지 않고 백그라운드에서 실행합니다.
+//! 화면은 작업 ID로 진행률을 폴링하고, 끝나면 완료 파일을 받아 갑니다.
+//! 동시에 실행하는 작업 수를 제한하며, 끝난 작업과 완료 파일은 보관 기간이 지나면 정리합니다.
+//! 완료 파일에는 환자 정보가 들어 있으므로 디스크에 쓰지 않고 메모리에만 보관합니다.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tokio::sync::Semaphore;
+
+use crate::db;
+use crate::error::{AppError, AppResult};
+use crate::models::SurveyExportRequest;
+
+/// 동시에 실행할 수 있는 작업 수 (나머지는 등록 순서대로 대기)
+pub const MAX_CONCURRENT_JOBS: usize = 2;
+
+/// 끝난 작업과 완료 파일 보관 기간
+pub const JOB_RETENTION_HOURS: i64 = 24;
+
+/// 작업 종류: 설문 응답 일괄 내보내기
+pub const SURVEY_EXPORT_JOB: &str = "survey_export";
+
+/// 취소 요청으로 작업을 멈출 때의 에러 메시지
+const CANCELLED_MESSAGE: &str = "작업이 취소되었습니다";
+
+static JOBS: Lazy<Mutex<HashMap<String, JobEntry>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static SLOTS: Lazy<Arc<Semaphore>> = Lazy::new(|| Arc::new(Semaphore::new(MAX_CONCURRENT_JOBS)));
+
+/// 작업 상태
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Completed | Self::Failed | Self::Cancelled)
+    }
+
+    /// 허용되는 상태 전이 (대기 → 실행 → 완료/실패/취소, 대기 중 취소)
+    fn can_transition_to(self, next: Self) -> bool {
+        matches!(
+            (self, next),
+            (Self::Queued, Self::Running | Self::Cancelled) | (Self::Running, Self::Completed | Self::Failed | Self::Cancelled)
+        )
+    }
+}
+
+/// 작업 정보 (폴링 응답)
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    pub id: String,
+    pub kind: String,
+    pub status: JobStatus,
+    /// 처리한 항목 수 / 전체 항목 수 (전체를 세기 전에는 0)
+    pub processed: usize,
+    pub total: usize,
+    /// 실행 중 취소를 요청함 (작업이 멈추면 cancelled로 바뀜)
+    pub cancel_requested: bool,
+    pub file_name: Option<String>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// 작업 결과 파일
+#[derive(Clone)]
+pub struct JobOutput {
+    pub file_name: String,
+    pub content_type: &'static str,
+    pub bytes: Vec<u8>,
+}
+
+struct JobEntry {
+    info: JobInfo,
+    cancel: Arc<AtomicBool>,
+    /// 완료 파일 (완료된 작업만)
+    output: Option<JobOutput>,
+}
+
+/// 실행 중인 작업이 진행률을 알리고 취소 여부를 확인하는 핸들
+pub struct JobContext {
+    id: String,
+    cancel: Arc<AtomicBool>,
+}
+
+impl JobContext {
+    /// 진행률 보고 (취소가 요청되었으면 에러를 돌려주므로 `?`로 작업을 멈춤)
+    pub fn report(&self, processed: usize, total: usize) -> AppResult<()> {
+        if let Ok(mut jobs) = JOBS.lock() {
+            if let Some(entry) = jobs.get_mut(&self.id) {
+                entry.info.processed = processed;
+                entry.info.total = total;
+            }
+        }
+        if self.cancel.load(Ordering::SeqCst) {
+            return Err(AppError::Custom(CANCELLED_MESSAGE.to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// 설문 응답 일괄 내보내기 작업 등록 (요청이 잘못되었으면 등록하지 않고 에러)
+pub fn start_survey_export(request: SurveyExportRequest) -> AppResult<JobInfo> {
+    db::validate_survey_export_request(&request)?;
+    Ok(submit(SURVEY_EXPORT_JOB, move |ctx| {
+        let (file_name, bytes) = db::export_survey_responses(&request, &mut |processed, total| ctx.report(processed, total))?;
+        Ok(JobOutput { file_name, content_type: request.format.content_type(), bytes })
+    }))
+}
+
+/// 작업 등록 (빈 자리가 나면 별도 스레드에서 실행)
+pub fn submit<F>(kind: &str, work: F) -> JobInfo
+where
+    F: FnOnce(&JobContext) -> AppResult<JobOutput> + Send + 'static,
+{
+    cleanup_expired_jobs();
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let cancel = Arc::new(AtomicBool::new(false));
+    let info = JobInfo {
+        id: id.clone(),
+        kind: kind.to_string(),
+        status: JobStatus::Queued,
+        processed: 0,
+        total: 0,
+        cancel_requested: false,
+        file_name: None,
+        error: None,
+        created_at: Utc::now(),
+        started_at: None,
+        finished_at: None,
+    };
+    if let Ok(mut jobs) = JOBS.lock() {
+        jobs.insert(
+            id.clone(),
+            JobEntry { info: info.clone(), cancel: cancel.clone(), output: None },
+        );
+    }
+    log::info!("작업 등록: {} ({})", id, kind);
+
+    tauri::async_runtime::spawn(run(JobContext { id, cancel }, work));
+    info
+}
+
+async fn run<F>(ctx: JobContext, work: F)
+where
+    F: FnOnce(&JobContext) -> AppResult<JobOutput> + Send + 'static,
+{
+    let Ok(_permit) = SLOTS.clone().acquire_owned().await else {
+        return;
+    };
+    // 대기 중 취소된 작업은 실행하지 않음
+    if !transition(&ctx.id, JobStatus::Running, |_| {}) {
+        return;
+    }
+
+    let id = ctx.id.clone();
+    let cancel = ctx.cancel.clone();
+    let result = tokio::task::spawn_blocking(move || work(&ctx))
+        .await
+        .unwrap_or_else(|e| Err(AppError::Custom(format!("작업이 비정상 종료되었습니다: {}", e))));
+
+    if cancel.load(Ordering::SeqCst) {
+        transition(&id, JobStatus::Cancelled, |_| {});
+        log::info!("작업 취소됨: {}", id);
+        return;
+    }
+    match result {
+        Ok(output) => {
+            transition(&id, JobStatus::Completed, |entry| {
+                entry.info.file_name = Some(output.file_name.clone());
+                entry.output = Some(output);
+            });
+            log::info!("작업 완료: {}", id);
+        }
+        Err(e) => {
+            log::warn!("작업 실패: {}: {}", id, e);
+            transition(&id, JobStatus::Failed, |entry| entry.info.error = Some(e.to_string()));
+        }
+    }
+}
+
+/// 상태 전이 (허용되지 않는 전이면 false, 시작/종료 시각은 자동 기록)
+fn transition(id: &str, next: JobStatus, apply: impl FnOnce(&mut JobEntry)) -> bool {
+    let Ok(mut jobs) = JOBS.lock() else {
+        return false;
+    };
+    let Some(entry) = jobs.get_mut(id) else {
+        return false;
+    };
+    if !entry.info.status.can_transition_to(next) {
+        return false;
+    }
+    let now = Utc::now();
+    entry.info.status = next;
+    if next == JobStatus::Running {
+        entry.info.started_at = Some(now);
+    } else if next.is_finished() {
+        entry.info.finished_at = Some(now);
+    }
+    apply(entry);
+    true
+}
+
+/// 작업 정보 조회
+pub fn get_job(id: &str) -> Option<JobInfo> {
+    JOBS.lock().ok()?.get(id).map(|entry| entry.info.clone())
+}
+
+/// 대기 중이거나 실행 중인 작업이 있는지 (DB 최적화처럼 오래 잠그는 작업 전에 확인)
+pub fn has_active_jobs() -> bool {
+    JOBS.lock()
+        .map(|jobs| jobs.values().any(|entry| !entry.info.status.is_finished()))
+        .unwrap_or(false)
+}
+
+/// 작업 목록 (최근 등록순)
+pub fn list_jobs() -> Vec<JobInfo> {
+    let mut jobs: Vec<JobInfo> = JOBS
+        .lock()
+        .map(|jobs| jobs.values().map(|entry| entry.info.clone()).collect())
+        .unwrap_or_default();
+    jobs.sort_by_key(|job| std::cmp::Reverse(job.created_at));
+    jobs
+}
+
+/// 작업 취소 (대기 중이면 바로 취소, 실행 중이면 다음 진행률 보고 때 멈춤, 끝난 작업은 그대로)
+pub fn cancel_job(id: &str) -> Option<JobInfo> {
+    let mut jobs = JOBS.lock().ok()?;
+    let entry = jobs.get_mut(id)?;
+    match entry.info.status {
+        JobStatus::Queued => {
+            entry.cancel.store(true, Ordering::SeqCst);
+            entry.info.status = JobStatus::Cancelled;
+            entry.info.finished_at = Some(Utc::now());
+            log::info!("대기 중인 작업 취소: {}", id);
+        }
+        // 실행 중인 작업은 멈출 때까지 running으로 두어 실행 슬롯을 차지하고 있음을 표시
+        JobStatus::Running => {
+            entry.cancel.store(true, Ordering::SeqCst);
+            entry.info.cancel_requested = true;
+        }
+        _ => {}
+    }
+    Some(entry.info.clone())
+}
+
+/// 완료 파일 읽기 (완료되지 않았거나 정리된 작업이면 None)
+pub fn read_job_file(id: &str) -> AppResult<Option<JobOutput>> {
+    let jobs = JOBS.lock().map_err(|_| AppError::Custom("작업 목록을 읽을 수 없습니다".to_string()))?;
+    Ok(jobs
+        .get(id)
+        .filter(|e| e.info.status == JobStatus::Completed)
+        .and_then(|e| e.output.clone()))
+}
+
+/// 보관 기간이 지난 작업과 완료 파일 정리 (반환: 정리한 작업 수)
+pub fn cleanup_expired_jobs() -> usize {
+    let cutoff = Utc::now() - chrono::Duration::hours(JOB_RETENTION_HOURS);
+    let expired = match JOBS.lock() {
+        Ok(mut jobs) => {
+            let before = jobs.len();
+            jobs.retain(|_, e| !e.info.finished_at.is_some_and(|t| t < cutoff));
+            before - jobs.len()
+        }
+        Err(_) => return 0,
+    };
+    remove_legacy_export_files();
+    if expired > 0 {
+        log::info!("끝난 작업 {}건 정리", expired);
+    }
+    expired
+}
+
+/// 예전 버전이 디스크에 암호화 없이 남긴 완료 파일 삭제
+fn remove_legacy_export_files() {
+    let Some(dir) = dirs::data_local_dir().map(|d| d.join("gosibang").join("exports")) else {
+        return;
+    };
+    if dir.exists() {
+        match std::fs::remove_dir_all(&dir) {
+            Ok(()) => log::info!("이전 내보내기 파일 폴더 삭제: {:?}", dir),
+            Err(e) => log::warn!("이전 내보내기 파일 폴더 삭제 실패: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// 작업 큐와 실행 슬롯은 전역이라 테스트를 하나씩 실행
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    fn serial() -> std::sync::MutexGuard<'static, ()> {
+        SERIAL.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn output(name: &str) -> JobOutput {
+        JobOutput { file_name: name.to_string(), content_type: "text/csv", bytes: b"a,b\n".to_vec() }
+    }
+
+    /// 조건을 만족할 때까지 대기 (5초 안에 만족하지 않으면 실패)
+    fn wait_until(what: &str, condition: impl Fn() -> bool) {
+        for _ in 0..500 {
+            if condition() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("시간 안에 {} 상태가 되지 않음", what);
+    }
+
+    fn status(id: &str) -> JobStatus {
+        get_job(id).unwrap().status
+    }
+
+    #[test]
+    fn status_transitions() {
+        use JobStatus::*;
+        assert!(Queued.can_transition_to(Running));
+        assert!(Queued.can_transition_to(Cancelled));
+        assert!(Running.can_transition_to(Completed));
+        assert!(Running.can_transition_to(Failed));
+        assert!(Running.can_transition_to(Cancelled));
+        assert!(!Queued.can_transition_to(Completed));
+        assert!(!Running.can_transition_to(Queued));
+        for finished in [Completed, Failed, Cancelled] {
+            assert!(finished.is_finished());
+            for next in [Queued, Running, Completed, Failed, Cancelled] {
+                assert!(!finished.can_transition_to(next));
+            }
+        }
+    }
+
+    #[test]
+    fn completed_job_reports_progress_and_keeps_output_in_memory() {
+        let _guard = serial();
+        let job = submit("test", |ctx| {
+            ctx.report(1, 2)?;
+            ctx.report(2, 2)?;
+            Ok(output("result.csv"))
+        });
+        wait_until("completed", || status(&job.id) == JobStatus::Completed);
+
+        let info = get_job(&job.id).unwrap();
+        assert_eq!((info.processed, info.total), (2, 2));
+        assert_eq!(info.file_name.as_deref(), Some("result.csv"));
+        assert!(info.started_at.is_some() && info.finished_at.is_some());
+        let file = read_job_file(&job.id).unwrap().unwrap();
+        assert_eq!(file.bytes, b"a,b\n");
+    }
+
+    #[test]
+    fn failed_job_keeps_error_and_has_no_file() {
+        let _guard = serial();
+        let job = submit("test", |_| Err(AppError::Custom("실패".to_string())));
+        wait_until("failed", || status(&job.id) == JobStatus::Failed);
+        assert_eq!(get_job(&job.id).unwrap().error.as_deref(), Some("실패"));
+        assert!(read_job_file(&job.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn running_job_stops_at_next_report_after_cancel() {
+        let _guard = serial();
+        let job = submit("test", |ctx| loop {
+            ctx.report(0, 1)?;
+            std::thread::sleep(Duration::from_millis(5));
+        });
+        wait_until("running", || status(&job.id) == JobStatus::Running);
+
+        assert!(cancel_job(&job.id).unwrap().cancel_requested);
+        wait_until("cancelled", || status(&job.id) == JobStatus::Cancelled);
+        assert!(read_job_file(&job.id).unwrap().is_none());
+        // 끝난 작업은 다시 취소해도 그대로
+        assert_eq!(cancel_job(&job.id).unwrap().status, JobStatus::Cancelled);
+    }
+
+    #[test]
+    fn jobs_beyond_limit_wait_and_can_be_cancelled_while_queued() {
+        let _guard = serial();
+        let release = Arc::new(AtomicBool::new(false));
+        let blocking: Vec<JobInfo> = (0..MAX_CONCURRENT_JOBS)
+            .map(|_| {
+                let release = release.clone();
+                submit("test", move |_| {
+                    while !release.load(Ordering::SeqCst) {
+                        std::thread::sleep(Duration::from_millis(5));
+                    }
+                    Ok(output("blocking.csv"))
+                })
+            })
+            .collect();
+        wait_until("running", || blocking.iter().all(|job| status(&job.id) == JobStatus::Running));
+
+        let queued = submit("test", |_| Ok(output("queued.csv")));
+        let cancelled = submit("test", |_| Ok(output("cancelled.csv")));
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(status(&queued.id), JobStatus::Queued);
+        assert!(has_active_jobs());
+
+        assert_eq!(cancel_job(&cancelled.id).unwrap().status, JobStatus::Cancelled);
+        release.store(true, Ordering::SeqCst);
+        wait_until("completed", || status(&queued.id) == JobStatus::Completed);
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(status(&cancelled.id), JobStatus::Cancelled);
+        assert!(get_job(&cancelled.id).unwrap().started_at.is_none());
+    }
+
+    #[test]
+    fn cleanup_removes_only_expired_finished_jobs() {
+        let _guard = serial();
+        let job = submit("test", |_| Ok(output("old.csv")));
+        wait_until("completed", || status(&job.id) == JobStatus::Completed);
+        let fresh = submit("test", |_| Ok(output("fresh.csv")));
+        wait_until("completed", || status(&fresh.id) == JobStatus::Completed);
+
+        if let Some(entry) = JOBS.lock().unwrap().get_mut(&job.id) {
+            entry.info.finished_at = Some(Utc::now() - chrono::Duration::hours(JOB_RETENTION_HOURS + 1));
+        }
+        assert!(cleanup_expired_jobs() >= 1);
+        assert!(get_job(&job.id).is_none());
+        assert!(read_job_file(&job.id).unwrap().is_none());
+        assert!(get_job(&fresh.id).is_some());
+    }
+}
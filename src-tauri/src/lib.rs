@@ -1,11 +1,21 @@
 mod auth;
 mod commands;
 mod db;
+mod email;
 mod encryption;
 mod error;
+mod i18n;
+mod jobs;
+mod mdns;
+mod metrics;
 mod models;
+mod pdf;
 pub mod server;
+mod sms;
 mod sync;
+mod tls;
+mod web_api;
+mod xlsx;
 
 use commands::*;
 
@@ -59,18 +69,38 @@ pub fn run() {
             create_patient,
             get_patient,
             list_patients,
+            search_patients_by_tag,
+            list_patient_tags,
+            list_patient_alerts,
+            save_patient_alert,
+            delete_patient_alert,
+            save_patient_pdf,
             update_patient,
             delete_patient,
             // 처방 관리
             create_prescription,
             get_prescriptions_by_patient,
             list_all_prescriptions,
+            list_prescriptions_by_herb_batch,
             update_prescription,
             soft_delete_prescription,
+            delete_prescription,
             clear_all_prescriptions,
             // 차팅 관리
             create_chart_record,
             get_chart_records_by_patient,
+            get_chart_record,
+            update_chart_record,
+            delete_chart_record,
+            list_text_macros,
+            save_text_macro,
+            delete_text_macro,
+            get_prescription_history,
+            get_patient_events,
+            get_patient_survey_series,
+            get_treatment_outcome_correlation,
+            sign_document,
+            verify_document_integrity,
             // 초진차트 관리
             create_initial_chart,
             get_initial_chart,
@@ -87,6 +117,15 @@ pub fn run() {
             // 데이터 내보내기
             export_patient_data,
             export_all_data,
+            export_all_data_encrypted,
+            export_all_data_zip,
+            start_survey_export,
+            list_export_jobs,
+            get_export_job,
+            cancel_export_job,
+            get_export_job_file,
+            import_json_export,
+            import_encrypted,
             // 직원 비밀번호 관리
             set_staff_password,
             has_staff_password,
@@ -96,12 +135,30 @@ pub fn run() {
             get_server_status,
             get_server_autostart,
             set_server_autostart,
+            get_server_mdns_advertise,
+            set_server_mdns_advertise,
+            get_survey_ttl_hours,
+            set_survey_ttl_hours,
+            get_kiosk_idle_minutes,
+            set_kiosk_idle_minutes,
+            get_cors_allowed_origins,
+            set_cors_allowed_origins,
+            get_server_tls_settings,
+            set_server_tls_settings,
             // 설문 템플릿 관리
             list_survey_templates,
+            list_survey_template_categories,
             get_survey_template,
             save_survey_template,
             delete_survey_template,
+            reorder_survey_template,
             restore_default_survey_templates,
+            duplicate_survey_template,
+            export_survey_template,
+            import_survey_template,
+            list_follow_up_rules,
+            save_follow_up_rule,
+            delete_follow_up_rule,
             // 설문 세션 관리
             list_survey_sessions,
             create_survey_session,
@@ -110,19 +167,45 @@ pub fn run() {
             complete_survey_session,
             expire_survey_session,
             delete_survey_session,
+            cleanup_orphaned_survey_sessions,
             // 설문 응답 관리
             list_survey_responses,
             delete_survey_response,
             link_survey_response_to_patient,
+            suggest_patient_for_response,
+            count_unlinked_responses,
+            snooze_notification,
+            run_notification_checks,
+            get_scheduler_interval,
+            set_scheduler_interval,
             submit_survey_response,
             save_survey_response_sync,
+            list_sync_conflicts,
+            resolve_sync_conflict,
             // QR 코드 생성
             generate_survey_qr,
+            export_kiosk_poster,
+            get_smtp_settings,
+            save_smtp_settings,
+            get_sms_settings,
+            save_sms_settings,
+            send_test_sms,
+            get_notification_channel,
+            set_notification_channel,
+            get_survey_reminder_hours,
+            set_survey_reminder_hours,
+            get_retention_settings,
+            save_retention_settings,
+            purge_old_data,
+            run_db_maintenance,
+            get_db_maintenance_settings,
+            save_db_maintenance_settings,
             // 내부 직원 계정 관리
             create_staff_account,
             list_staff_accounts,
             get_staff_account,
             update_staff_account,
+            set_staff_account_active,
             delete_staff_account,
             // 처방 카테고리
             list_prescription_categories,
@@ -158,6 +241,7 @@ pub fn run() {
             // 복약 스케줄
             list_medication_schedules,
             get_medication_schedule,
+            get_completed_schedules,
             create_medication_schedule,
             update_medication_schedule,
             delete_medication_schedule,
@@ -177,8 +261,23 @@ pub fn run() {
             empty_trash,
             get_trash_items,
             get_trash_count,
+            // 태그
+            get_entity_tags,
+            add_entity_tags,
+            remove_entity_tag,
+            search_tagged_entities,
+            suggest_tags,
+            get_tag_stats,
             // 사용량 통계
             get_usage_stats,
+            get_dashboard_stats,
+            get_survey_response_stats,
+            // 매출 리포트
+            get_revenue_report,
+            // 내원 경로
+            get_referral_breakdown,
+            get_referral_sources,
+            set_referral_sources,
             // 초기화
             reset_prescription_definitions,
             reset_all_user_data,
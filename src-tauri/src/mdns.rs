@@ -0,0 +1,62 @@
+//! mDNS(Bonjour) 광고 모듈
+//!
+//! 원내 HTTP 서버를 `gosibang.local`로 광고해 태블릿에서 IP 주소를 입력하지 않고 접속할 수 있게 합니다.
+//! 주소는 데몬이 모든 네트워크 인터페이스에서 자동으로 수집하며, 인터페이스가 바뀌면 함께 갱신됩니다.
+
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+
+use crate::error::{AppError, AppResult};
+
+/// 광고 서비스 타입
+const SERVICE_TYPE: &str = "_http._tcp.local.";
+
+/// 서비스 인스턴스 이름 (`gosibang._http._tcp.local`)
+const INSTANCE_NAME: &str = "gosibang";
+
+/// 광고 호스트 이름
+const HOST_NAME: &str = "gosibang.local.";
+
+/// 광고 해제 대기 시간 (goodbye 패킷 전송)
+const UNREGISTER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// 실행 중인 mDNS 광고
+pub struct MdnsAdvertisement {
+    daemon: ServiceDaemon,
+    fullname: String,
+}
+
+impl MdnsAdvertisement {
+    /// 접속 안내용 호스트 이름 (`gosibang.local`)
+    pub fn hostname(&self) -> &'static str {
+        HOST_NAME.trim_end_matches('.')
+    }
+
+    /// 광고 해제 후 데몬 종료
+    pub fn shutdown(self) {
+        match self.daemon.unregister(&self.fullname) {
+            Ok(receiver) => {
+                let _ = receiver.recv_timeout(UNREGISTER_TIMEOUT);
+            }
+            Err(e) => log::warn!("mDNS 광고 해제 실패: {}", e),
+        }
+        if let Err(e) = self.daemon.shutdown() {
+            log::warn!("mDNS 데몬 종료 실패: {}", e);
+        }
+    }
+}
+
+/// HTTP 서버 mDNS 광고 시작
+pub fn advertise(port: u16) -> AppResult<MdnsAdvertisement> {
+    let mdns_err = |e: mdns_sd::Error| AppError::Custom(format!("mDNS 광고 실패: {}", e));
+
+    let daemon = ServiceDaemon::new().map_err(mdns_err)?;
+    let properties = [("path", "/staff")];
+    let info = ServiceInfo::new(SERVICE_TYPE, INSTANCE_NAME, HOST_NAME, "", port, &properties[..])
+        .map_err(mdns_err)?
+        .enable_addr_auto();
+    let fullname = info.get_fullname().to_string();
+    daemon.register(info).map_err(mdns_err)?;
+
+    log::info!("mDNS 광고 시작: {} (port {})", fullname, port);
+    Ok(MdnsAdvertisement { daemon, fullname })
+}
@@ -0,0 +1,113 @@
+//! 운영 지표 수집 모듈
+//!
+//! 요청 수/지연, 설문 제출, 동기화 실패, 알림 발송, DB 쿼리 지연을 프로세스 내 원자 변수로 집계하고
+//! `GET /metrics`에서 Prometheus 텍스트 형식으로 내보냅니다.
+
+use axum::{extract::Request, middleware::Next, response::Response};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// 지연 시간 히스토그램 구간 상한 (초)
+const LATENCY_BUCKETS: [f64; 10] = [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0];
+
+/// 누적 구간 히스토그램 (합계는 마이크로초 단위로 보관)
+struct Histogram {
+    buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    const fn new() -> Self {
+        Self {
+            buckets: [const { AtomicU64::new(0) }; LATENCY_BUCKETS.len()],
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    fn observe(&self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter().zip(LATENCY_BUCKETS) {
+            if secs <= bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        for (bucket, bound) in self.buckets.iter().zip(LATENCY_BUCKETS) {
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, bucket.load(Ordering::Relaxed));
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, count);
+        let _ = writeln!(out, "{}_sum {}", name, self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0);
+        let _ = writeln!(out, "{}_count {}", name, count);
+    }
+}
+
+/// 응답 상태 구간별 요청 수 (1xx ~ 5xx)
+static HTTP_REQUESTS: [AtomicU64; 5] = [const { AtomicU64::new(0) }; 5];
+static HTTP_REQUEST_DURATION: Histogram = Histogram::new();
+static DB_QUERY_DURATION: Histogram = Histogram::new();
+static SURVEY_SUBMISSIONS: AtomicU64 = AtomicU64::new(0);
+static SYNC_FAILURES: AtomicU64 = AtomicU64::new(0);
+static NOTIFICATIONS_SENT: AtomicU64 = AtomicU64::new(0);
+
+/// 모든 HTTP 요청의 수와 처리 시간을 기록하는 미들웨어
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let response = next.run(request).await;
+
+    let class = (response.status().as_u16() / 100).clamp(1, 5) as usize - 1;
+    HTTP_REQUESTS[class].fetch_add(1, Ordering::Relaxed);
+    HTTP_REQUEST_DURATION.observe(started.elapsed());
+    response
+}
+
+/// DB 연결 점유 시간 기록 (쿼리 지연 근사치)
+pub fn observe_db_query(elapsed: Duration) {
+    DB_QUERY_DURATION.observe(elapsed);
+}
+
+pub fn record_survey_submission() {
+    SURVEY_SUBMISSIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn record_sync_failure() {
+    SYNC_FAILURES.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn record_notification_sent() {
+    NOTIFICATIONS_SENT.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Prometheus 텍스트 형식 (text/plain; version=0.0.4)
+pub fn render() -> String {
+    let mut out = String::new();
+
+    let _ = writeln!(out, "# HELP gosibang_http_requests_total HTTP requests by status class.");
+    let _ = writeln!(out, "# TYPE gosibang_http_requests_total counter");
+    for (i, counter) in HTTP_REQUESTS.iter().enumerate() {
+        let _ = writeln!(out, "gosibang_http_requests_total{{status=\"{}xx\"}} {}", i + 1, counter.load(Ordering::Relaxed));
+    }
+    HTTP_REQUEST_DURATION.render(&mut out, "gosibang_http_request_duration_seconds", "HTTP request latency.");
+    DB_QUERY_DURATION.render(&mut out, "gosibang_db_query_duration_seconds", "Time the database connection was held per call.");
+
+    for (name, help, counter) in [
+        ("gosibang_survey_submissions_total", "Survey responses saved.", &SURVEY_SUBMISSIONS),
+        ("gosibang_sync_failures_total", "Survey response uploads that failed and were queued for retry.", &SYNC_FAILURES),
+        ("gosibang_notifications_sent_total", "Staff notifications created.", &NOTIFICATIONS_SENT),
+    ] {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} counter", name);
+        let _ = writeln!(out, "{} {}", name, counter.load(Ordering::Relaxed));
+    }
+
+    out
+}
@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 #[allow(unused_imports)]
 use uuid::Uuid;
 
@@ -12,6 +13,7 @@ pub struct ClinicSettings {
     pub clinic_phone: Option<String>,   // 전화번호
     pub doctor_name: Option<String>,    // 원장님 성함
     pub license_number: Option<String>, // 면허번호
+    pub clinic_logo: Option<String>,    // 로고 (base64 PNG, 설문 화면 상단 표시)
     pub created_at: DateTime<Utc>,
     #[allow(dead_code)]
     pub updated_at: DateTime<Utc>,
@@ -27,6 +29,7 @@ impl Default for ClinicSettings {
             clinic_phone: None,
             doctor_name: None,
             license_number: None,
+            clinic_logo: None,
             created_at: now,
             updated_at: now,
         }
@@ -44,12 +47,15 @@ pub struct Patient {
     pub phone: Option<String>,
     pub address: Option<String>,
     pub notes: Option<String>,           // 특이사항
+    #[serde(default)]
+    pub referral_source: Option<String>, // 내원 경로 (어떻게 알고 왔는지)
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,       // 태그 (저장 시 None이면 기존 태그 유지)
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
 impl Patient {
-    #[allow(dead_code)]
     pub fn new(name: String) -> Self {
         let now = Utc::now();
         Self {
@@ -61,12 +67,178 @@ impl Patient {
             phone: None,
             address: None,
             notes: None,
+            referral_source: None,
+            tags: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// 환자 중요 정보 종류 (배너에서 이 순서로 표시)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PatientAlertType {
+    Allergy,           // 알레르기
+    CautionMedication, // 주의 약물 (복용 중이거나 금기)
+    ChronicDisease,    // 만성질환
+    Other,             // 기타 주의사항
+}
+
+impl PatientAlertType {
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "allergy" => PatientAlertType::Allergy,
+            "caution_medication" => PatientAlertType::CautionMedication,
+            "chronic_disease" => PatientAlertType::ChronicDisease,
+            _ => PatientAlertType::Other,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PatientAlertType::Allergy => "allergy",
+            PatientAlertType::CautionMedication => "caution_medication",
+            PatientAlertType::ChronicDisease => "chronic_disease",
+            PatientAlertType::Other => "other",
+        }
+    }
+}
+
+/// 환자 중요 정보 심각도
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PatientAlertSeverity {
+    High,
+    #[default]
+    Medium,
+    Low,
+}
+
+impl PatientAlertSeverity {
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "high" => PatientAlertSeverity::High,
+            "low" => PatientAlertSeverity::Low,
+            _ => PatientAlertSeverity::Medium,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PatientAlertSeverity::High => "high",
+            PatientAlertSeverity::Medium => "medium",
+            PatientAlertSeverity::Low => "low",
+        }
+    }
+}
+
+/// 환자 중요 정보 (알레르기/주의 약물/만성질환 등, 차트 상단 배너용)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatientAlert {
+    pub id: String,
+    pub patient_id: String,
+    pub alert_type: PatientAlertType,
+    #[serde(default)]
+    pub severity: PatientAlertSeverity,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PatientAlert {
+    pub fn new(patient_id: String, alert_type: PatientAlertType, severity: PatientAlertSeverity, content: String) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4().to_string(),
+            patient_id,
+            alert_type,
+            severity,
+            content,
             created_at: now,
             updated_at: now,
         }
     }
 }
 
+/// 환자 상세 (환자 정보 + 중요 정보 목록)
+#[derive(Debug, Clone, Serialize)]
+pub struct PatientDetail {
+    #[serde(flatten)]
+    pub patient: Patient,
+    /// 심각도 → 종류 → 등록순 (없으면 빈 배열)
+    pub alerts: Vec<PatientAlert>,
+}
+
+/// 환자 목록 항목 (`stats`는 요청했을 때만 포함)
+#[derive(Debug, Clone, Serialize)]
+pub struct PatientListItem {
+    #[serde(flatten)]
+    pub patient: Patient,
+    #[serde(flatten)]
+    pub stats: Option<PatientVisitStats>,
+}
+
+/// 환자 내원 통계 (삭제되지 않은 진료기록 기준)
+#[derive(Debug, Clone, Serialize)]
+pub struct PatientVisitStats {
+    pub visit_count: i64,
+    pub last_visit_date: Option<String>,
+}
+
+/// 환자 정보 일부 수정 (생략한 필드는 그대로, null이면 값 지움)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PatientPatch {
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "nullable_field")]
+    pub chart_number: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable_field")]
+    pub birth_date: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable_field")]
+    pub gender: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable_field")]
+    pub phone: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable_field")]
+    pub address: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable_field")]
+    pub notes: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable_field")]
+    pub referral_source: Option<Option<String>>,
+    pub tags: Option<Vec<String>>,
+}
+
+impl PatientPatch {
+    /// 지정한 필드만 덮어쓴 환자 정보 (tags는 지정한 경우에만 Some)
+    pub fn apply(&self, patient: Patient) -> Patient {
+        let pick = |patch: &Option<Option<String>>, current: Option<String>| match patch {
+            Some(value) => value.clone(),
+            None => current,
+        };
+        Patient {
+            name: self.name.clone().unwrap_or(patient.name),
+            chart_number: pick(&self.chart_number, patient.chart_number),
+            birth_date: pick(&self.birth_date, patient.birth_date),
+            gender: pick(&self.gender, patient.gender),
+            phone: pick(&self.phone, patient.phone),
+            address: pick(&self.address, patient.address),
+            notes: pick(&self.notes, patient.notes),
+            referral_source: pick(&self.referral_source, patient.referral_source),
+            tags: self.tags.clone(),
+            updated_at: Utc::now(),
+            ..patient
+        }
+    }
+}
+
+/// 필드 생략(None)과 null(Some(None)) 구분
+fn nullable_field<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
 /// 한약 처방 (통합 스키마 - Charts/Prescriptions/Medications 공용)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Prescription {
@@ -92,6 +264,8 @@ pub struct Prescription {
     pub total_dosage: f64,                    // 1첩 총량
     pub final_total_amount: f64,              // 최종 총량
     pub notes: Option<String>,
+    pub price: Option<i64>,                   // 처방 금액 (원)
+    pub insurance_type: Option<String>,       // 'insured' | 'uninsured'
     pub status: String,                       // 'draft' | 'issued' | 'completed'
     pub issued_at: Option<String>,
     pub created_by: Option<String>,
@@ -100,12 +274,20 @@ pub struct Prescription {
     pub updated_at: String,
 }
 
-/// 약재 항목
+/// 약재 항목 (처방의 merged_herbs/final_herbs JSON 한 칸, 추적용 필드는 없던 기록이면 None)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HerbItem {
+    #[serde(alias = "name")]
     pub herb_name: String,   // 약재명
     pub amount: f64,         // 용량 (g)
+    #[serde(default)]
     pub unit: String,        // 단위 (g, 돈 등)
+    #[serde(default)]
+    pub batch_number: Option<String>, // 입고 로트(배치) 번호 - 회수 시 사용 처방 추적
+    #[serde(default)]
+    pub supplier: Option<String>,     // 공급처
+    #[serde(default)]
+    pub notes: Option<String>,
 }
 
 /// 차팅 기록
@@ -124,6 +306,33 @@ pub struct ChartRecord {
     pub updated_at: DateTime<Utc>,
 }
 
+/// 차팅 입력용 약어 (예: "ㅎㅇ" → "환자")
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextMacro {
+    pub id: String,
+    pub abbreviation: String,
+    pub expansion: String,
+    /// 직원 계정 ID (None이면 전역 약어)
+    #[serde(default)]
+    pub owner_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TextMacro {
+    pub fn new(abbreviation: String, expansion: String, owner_id: Option<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4().to_string(),
+            abbreviation,
+            expansion,
+            owner_id,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
 /// 초진차트
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InitialChart {
@@ -227,6 +436,26 @@ pub struct SurveyQuestion {
     pub options: Option<Vec<String>>,  // 선택형 질문의 옵션들
     pub scale_config: Option<ScaleConfig>,  // 척도형 질문 설정
     pub required: bool,
+    /// 표시 순서 (템플릿 내 중복 불가)
+    #[serde(default)]
+    pub order: Option<i32>,
+    /// 선택지 표시 순서를 화면에서 섞음 (저장되는 응답 값은 원래 선택지 그대로)
+    #[serde(default)]
+    pub shuffle_options: bool,
+    /// 템플릿의 문항 순서 섞기에서 제외하고 제자리 유지 (분기 기준 문항, 안내 문항 등)
+    #[serde(default)]
+    pub fixed_position: bool,
+    /// 언어 코드(en, zh, vi)별 번역 - 없으면 원문 표시
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translations: Option<HashMap<String, QuestionTranslation>>,
+}
+
+/// 질문 번역 (선택지는 원문 `options`와 같은 순서)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuestionTranslation {
+    pub question_text: String,
+    #[serde(default)]
+    pub options: Option<Vec<String>>,
 }
 
 /// 척도형 질문 설정
@@ -238,6 +467,26 @@ pub struct ScaleConfig {
     pub min_label: Option<String>,
     #[serde(rename = "maxLabel")]
     pub max_label: Option<String>,
+    /// 선택 간격 (없으면 1, 예: 0~100을 10 단위로)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step: Option<i32>,
+}
+
+impl ScaleConfig {
+    pub fn step(&self) -> i32 {
+        self.step.unwrap_or(1)
+    }
+
+    /// 최소값 < 최대값, 간격 > 0
+    pub fn is_valid(&self) -> bool {
+        self.min < self.max && self.step() > 0
+    }
+
+    /// 범위 안에 있고 최소값부터 간격 단위로 떨어지는 값인지
+    pub fn accepts(&self, value: i64) -> bool {
+        let (min, max, step) = (i64::from(self.min), i64::from(self.max), i64::from(self.step()));
+        (min..=max).contains(&value) && step > 0 && (value - min) % step == 0
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -248,10 +497,39 @@ pub enum QuestionType {
     MultipleChoice, // 복수 선택
     Scale,          // 척도 (1-10 등)
     YesNo,          // 예/아니오
+    FileUpload,     // 파일 첨부 (검사지 사진 등, 답변은 첨부 파일 참조)
+}
+
+/// 설문 첨부 파일 정보 (파일 내용 제외)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SurveyAttachment {
+    pub id: String,
+    pub session_id: String,
+    pub question_id: String,
+    /// 제출된 응답에 연결되면 설정 (미연결 파일은 세션 종료 후 정리)
+    pub response_id: Option<String>,
+    pub file_name: String,
+    pub mime_type: String,
+    pub size: i64,
+    pub created_at: String,
+}
+
+/// 후속 설문 규칙 (원본 설문 완료 시 후속 설문 세션 자동 생성)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SurveyFollowUpRule {
+    pub id: String,
+    pub source_template_id: String,
+    pub follow_up_template_id: String,
+    pub trigger_type: String,              // 'completed' | 'score'
+    pub question_id: Option<String>,       // score: 점수를 볼 문항
+    pub min_score: Option<f64>,            // score: 이 값 이상 (포함)
+    pub max_score: Option<f64>,            // score: 이 값 이하 (포함)
+    pub delay_hours: i64,                  // 완료 후 N시간 뒤 세션 생성
+    pub is_active: bool,
+    pub created_at: String,
 }
 
 /// 설문 세션 (온라인 설문용)
-#[allow(dead_code)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SurveySession {
     pub id: String,
@@ -270,6 +548,7 @@ pub enum SessionStatus {
     Pending,    // 대기 중
     Completed,  // 완료
     Expired,    // 만료
+    Locked,     // 본인 확인 실패로 잠김
 }
 
 impl SurveySession {
@@ -289,6 +568,116 @@ impl SurveySession {
     }
 }
 
+/// 세션별 문항/선택지 표시 순서 (세션 생성 시 시드로 한 번 섞어 저장)
+///
+/// 표시 순서만 바뀌고 응답은 문항 ID와 원래 선택지 값으로 저장되므로 채점/후속 설문 규칙에 영향 없음
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SurveyDisplayOrder {
+    pub seed: u32,
+    pub question_ids: Vec<String>,
+    /// 문항 ID별 선택지 표시 순서 (원래 `options` 인덱스)
+    #[serde(default)]
+    pub option_orders: HashMap<String, Vec<usize>>,
+}
+
+impl SurveyDisplayOrder {
+    /// 같은 시드와 문항이면 항상 같은 순서
+    ///
+    /// 고정 문항(`fixed_position`, `pin_required`일 때 필수 문항)은 제자리에 두고 나머지끼리만 섞음.
+    /// 선택지는 `randomize_options`이거나 문항의 `shuffle_options`일 때 섞음
+    pub fn generate(
+        seed: u32,
+        questions: &[SurveyQuestion],
+        randomize_questions: bool,
+        randomize_options: bool,
+        pin_required: bool,
+    ) -> Self {
+        let mut rng = SplitMix64(u64::from(seed));
+
+        let mut question_ids: Vec<String> = questions.iter().map(|q| q.id.clone()).collect();
+        if randomize_questions {
+            let slots: Vec<usize> = questions
+                .iter()
+                .enumerate()
+                .filter(|(_, q)| !(q.fixed_position || (pin_required && q.required)))
+                .map(|(i, _)| i)
+                .collect();
+            let mut movable: Vec<String> = slots.iter().map(|&i| question_ids[i].clone()).collect();
+            rng.shuffle(&mut movable);
+            for (slot, id) in slots.into_iter().zip(movable) {
+                question_ids[slot] = id;
+            }
+        }
+
+        let mut option_orders = HashMap::new();
+        for q in questions.iter().filter(|q| randomize_options || q.shuffle_options) {
+            if let Some(options) = q.options.as_ref().filter(|o| o.len() > 1) {
+                let mut order: Vec<usize> = (0..options.len()).collect();
+                rng.shuffle(&mut order);
+                option_orders.insert(q.id.clone(), order);
+            }
+        }
+
+        Self { seed, question_ids, option_orders }
+    }
+
+    /// 저장된 순서대로 문항과 선택지(번역 포함)를 재배열
+    ///
+    /// 세션 생성 후 템플릿이 바뀌었으면 새 문항은 뒤에 붙이고, 개수가 달라진 선택지 순서는 무시
+    pub fn apply(&self, questions: &mut [SurveyQuestion]) {
+        let position = |q: &SurveyQuestion| {
+            self.question_ids.iter().position(|id| *id == q.id).unwrap_or(usize::MAX)
+        };
+        questions.sort_by_key(position);
+
+        for q in questions.iter_mut() {
+            let Some(order) = self.option_orders.get(&q.id) else {
+                continue;
+            };
+            let Some(options) = q.options.as_mut() else {
+                continue;
+            };
+            if !is_permutation(order, options.len()) {
+                continue;
+            }
+            *options = order.iter().map(|&i| options[i].clone()).collect();
+            for translation in q.translations.iter_mut().flat_map(|t| t.values_mut()) {
+                if let Some(options) = translation.options.as_mut().filter(|o| o.len() == order.len()) {
+                    *options = order.iter().map(|&i| options[i].clone()).collect();
+                }
+            }
+            // 이미 섞인 순서이므로 화면에서 다시 섞지 않음
+            q.shuffle_options = false;
+        }
+    }
+}
+
+fn is_permutation(order: &[usize], len: usize) -> bool {
+    let mut seen = vec![false; len];
+    order.len() == len && order.iter().all(|&i| i < len && !std::mem::replace(&mut seen[i], true))
+}
+
+/// 시드 재현용 난수 (splitmix64 - 라이브러리 버전과 무관하게 같은 시드면 같은 결과)
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE5_E9B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Fisher-Yates
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = (self.next() % (i as u64 + 1)) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
 #[allow(dead_code)]
 fn generate_token() -> String {
     use rand::Rng;
@@ -336,6 +725,21 @@ pub struct MedicationSchedule {
     pub medication_times: Vec<String>,   // 복용 시간 (예: ["08:00", "12:00", "18:00"])
     pub notes: Option<String>,
     pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub is_completed: bool,              // 복용 완료 여부 (조회 시 계산)
+}
+
+impl MedicationSchedule {
+    /// 총 복용 예정 횟수 (하루 복용 횟수 × 복용 일수)
+    pub fn expected_doses(&self) -> i64 {
+        self.times_per_day as i64 * (self.end_date - self.start_date).num_days()
+    }
+
+    /// 복용 기록 수로 완료 여부 계산 (종료일이 지났고 예정 횟수를 모두 복용)
+    pub fn with_completion(mut self, taken_count: i64) -> Self {
+        self.is_completed = self.end_date < Utc::now() && taken_count >= self.expected_doses();
+        self
+    }
 }
 
 /// 복약 기록
@@ -414,6 +818,9 @@ impl Default for AuthState {
 pub struct StaffPermissions {
     pub patients_read: bool,
     pub patients_write: bool,
+    /// 환자 삭제 (수정 권한과 별도)
+    #[serde(default)]
+    pub patients_delete: bool,
     pub prescriptions_read: bool,
     pub prescriptions_write: bool,
     pub charts_read: bool,
@@ -428,11 +835,22 @@ pub struct StaffPermissions {
 }
 
 impl StaffPermissions {
+    /// 역할별 기본 권한
+    pub fn for_role(role: &StaffRole) -> Self {
+        match role {
+            StaffRole::Admin => Self::admin(),
+            StaffRole::Staff => Self::staff(),
+            StaffRole::Receptionist => Self::receptionist(),
+            StaffRole::Viewer => Self::viewer(),
+        }
+    }
+
     /// 관리자 권한 (모든 권한)
     pub fn admin() -> Self {
         Self {
             patients_read: true,
             patients_write: true,
+            patients_delete: true,
             prescriptions_read: true,
             prescriptions_write: true,
             charts_read: true,
@@ -450,6 +868,7 @@ impl StaffPermissions {
         Self {
             patients_read: true,
             patients_write: true,
+            patients_delete: true,
             prescriptions_read: true,
             prescriptions_write: true,
             charts_read: true,
@@ -462,11 +881,30 @@ impl StaffPermissions {
         }
     }
 
+    /// 접수 권한 (환자 등록/수정, 설문 관리 - 환자 삭제와 처방/차트 수정 불가)
+    pub fn receptionist() -> Self {
+        Self {
+            patients_read: true,
+            patients_write: true,
+            patients_delete: false,
+            prescriptions_read: true,
+            prescriptions_write: false,
+            charts_read: true,
+            charts_write: false,
+            survey_read: true,
+            survey_write: true,
+            settings_read: false,
+            medications_read: true,
+            medications_write: false,
+        }
+    }
+
     /// 열람자 권한 (읽기만)
     pub fn viewer() -> Self {
         Self {
             patients_read: true,
             patients_write: false,
+            patients_delete: false,
             prescriptions_read: true,
             prescriptions_write: false,
             charts_read: true,
@@ -484,9 +922,10 @@ impl StaffPermissions {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum StaffRole {
-    Admin,   // 관리자 (모든 권한)
-    Staff,   // 직원 (읽기/쓰기)
-    Viewer,  // 열람자 (읽기만)
+    Admin,        // 관리자 (모든 권한)
+    Staff,        // 직원 (읽기/쓰기)
+    Receptionist, // 접수 (환자 등록/수정, 삭제 불가)
+    Viewer,       // 열람자 (읽기만)
 }
 
 impl Default for StaffRole {
@@ -500,6 +939,7 @@ impl StaffRole {
         match s.to_lowercase().as_str() {
             "admin" => StaffRole::Admin,
             "staff" => StaffRole::Staff,
+            "receptionist" => StaffRole::Receptionist,
             _ => StaffRole::Viewer,
         }
     }
@@ -508,6 +948,7 @@ impl StaffRole {
         match self {
             StaffRole::Admin => "admin",
             StaffRole::Staff => "staff",
+            StaffRole::Receptionist => "receptionist",
             StaffRole::Viewer => "viewer",
         }
     }
@@ -526,15 +967,14 @@ pub struct StaffAccount {
     pub last_login_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// 관리자가 임시 비밀번호로 재설정함 (변경 전까지 비밀번호 변경 외 API 사용 불가)
+    #[serde(default)]
+    pub must_change_password: bool,
 }
 
 impl StaffAccount {
     pub fn new(username: String, display_name: String, password_hash: String, role: StaffRole) -> Self {
-        let permissions = match role {
-            StaffRole::Admin => StaffPermissions::admin(),
-            StaffRole::Staff => StaffPermissions::staff(),
-            StaffRole::Viewer => StaffPermissions::viewer(),
-        };
+        let permissions = StaffPermissions::for_role(&role);
 
         let now = Utc::now();
         Self {
@@ -548,6 +988,7 @@ impl StaffAccount {
             last_login_at: None,
             created_at: now,
             updated_at: now,
+            must_change_password: false,
         }
     }
 }
@@ -564,6 +1005,8 @@ pub struct StaffAccountInfo {
     pub last_login_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub must_change_password: bool,
 }
 
 impl From<StaffAccount> for StaffAccountInfo {
@@ -578,6 +1021,7 @@ impl From<StaffAccount> for StaffAccountInfo {
             last_login_at: account.last_login_at,
             created_at: account.created_at,
             updated_at: account.updated_at,
+            must_change_password: account.must_change_password,
         }
     }
 }
@@ -682,6 +1126,369 @@ pub struct UsageStats {
     pub progress_notes: i32,
 }
 
+/// SMTP 연결 보안 방식
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    /// 처음부터 TLS (보통 465번 포트)
+    #[default]
+    Tls,
+    /// 평문 연결 후 STARTTLS (보통 587번 포트)
+    StartTls,
+    /// 암호화 없음 (원내 메일 릴레이용)
+    None,
+}
+
+/// 이메일 발송 설정
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmtpSettings {
+    pub host: String,
+    pub port: u16,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    /// 비어 있으면 인증 없이 발송
+    #[serde(default)]
+    pub username: String,
+    /// 조회 시에는 비워서 반환, 저장 시 비워 두면 기존 비밀번호 유지
+    #[serde(default)]
+    pub password: String,
+    /// 보내는 사람 주소
+    pub from_address: String,
+    /// 일일 요약 받는 주소 (없으면 일일 요약 메일 미발송)
+    pub summary_recipient: Option<String>,
+}
+
+/// 복약 알림 발송 채널
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationChannel {
+    /// 데스크톱 알림만
+    #[default]
+    Desktop,
+    /// 환자 휴대폰 문자만
+    Sms,
+    /// 데스크톱 알림과 문자 모두
+    Both,
+}
+
+impl NotificationChannel {
+    pub fn includes_desktop(self) -> bool {
+        matches!(self, Self::Desktop | Self::Both)
+    }
+
+    pub fn includes_sms(self) -> bool {
+        matches!(self, Self::Sms | Self::Both)
+    }
+}
+
+/// 문자 발송 업체
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmsProvider {
+    /// 직접 지정한 HTTP 게이트웨이 (JSON POST)
+    HttpGateway,
+    /// 알리고 (apis.aligo.in)
+    Aligo,
+}
+
+/// 문자 발송 설정
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmsSettings {
+    pub provider: SmsProvider,
+    /// HTTP 게이트웨이 주소 (http_gateway에서만 사용)
+    #[serde(default)]
+    pub gateway_url: String,
+    /// 조회 시에는 비워서 반환, 저장 시 비워 두면 기존 키 유지
+    #[serde(default)]
+    pub api_key: String,
+    /// 업체 계정 아이디 (알리고)
+    #[serde(default)]
+    pub user_id: String,
+    /// 발신 번호 (업체에 사전 등록된 번호)
+    pub sender: String,
+}
+
+/// 오래된 기록 자동 정리 보관 기간 (일, None이면 보관 기간 없이 유지)
+///
+/// 환자/차트/처방 기록은 법정 보관 대상이라 여기서 다루지 않습니다.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RetentionSettings {
+    #[serde(default)]
+    pub survey_responses_days: Option<i64>,
+    /// 읽었거나 닫은 알림만 정리
+    #[serde(default)]
+    pub notifications_days: Option<i64>,
+    #[serde(default)]
+    pub medication_logs_days: Option<i64>,
+}
+
+impl RetentionSettings {
+    pub fn is_empty(&self) -> bool {
+        self.survey_responses_days.is_none() && self.notifications_days.is_none() && self.medication_logs_days.is_none()
+    }
+}
+
+/// 오래된 기록 정리 결과 (`dry_run`이면 지울 대상 건수만)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PurgeSummary {
+    pub dry_run: bool,
+    pub survey_responses: usize,
+    pub notifications: usize,
+    pub medication_logs: usize,
+}
+
+/// DB 최적화 주간 자동 실행 설정 (한의원 휴무 시간대로 지정)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DbMaintenanceSettings {
+    #[serde(default)]
+    pub weekly_enabled: bool,
+    /// 실행 요일 (0=월요일 ~ 6=일요일)
+    #[serde(default)]
+    pub weekday: u32,
+    /// 실행 시각 (0~23시, 이 시각 이후 첫 점검 때 실행)
+    #[serde(default)]
+    pub hour: u32,
+}
+
+/// DB 최적화 결과 (파일 크기는 바이트)
+#[derive(Debug, Clone, Serialize)]
+pub struct DbMaintenanceReport {
+    pub db_size_before: u64,
+    pub db_size_after: u64,
+    pub wal_size_before: u64,
+    pub wal_size_after: u64,
+    pub duration_ms: u64,
+}
+
+/// 동기화 충돌 해결 방법
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncConflictChoice {
+    /// 로컬 기록 유지
+    Local,
+    /// 원격(Supabase) 기록으로 덮어쓰기
+    Remote,
+}
+
+/// 동기화 충돌 (같은 항목의 로컬/원격 내용이 다를 때 양쪽 버전 보관)
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncConflict {
+    pub id: String,
+    /// 항목 종류 (현재는 survey_response)
+    pub item_type: String,
+    /// 로컬 항목 ID
+    pub item_id: String,
+    pub local_data: serde_json::Value,
+    pub remote_data: serde_json::Value,
+    pub detected_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub resolution: Option<SyncConflictChoice>,
+}
+
+/// 원내 서버 HTTPS 설정 (서버 재시작 후 적용)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ServerTlsSettings {
+    /// 인증서 경로가 없을 때 자체 서명 인증서로 HTTPS 사용
+    #[serde(default)]
+    pub enabled: bool,
+    /// 인증서 (PEM), 개인키와 함께 지정하면 enabled와 관계없이 HTTPS
+    pub cert_path: Option<String>,
+    /// 개인키 (PEM)
+    pub key_path: Option<String>,
+}
+
+/// 백업 복원 시 이미 있는 기록 처리 방식
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictStrategy {
+    /// 기존 기록 유지
+    Skip,
+    /// 백업 내용으로 덮어쓰기
+    Overwrite,
+    /// 하나라도 겹치면 전체 복원 취소
+    Error,
+    /// 기존 환자 데이터를 모두 지우고 백업 내용만 복원 (PC 이전/재해 복구용)
+    Replace,
+}
+
+/// 백업 복원 결과
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportSummary {
+    pub patients_imported: usize,
+    pub prescriptions_imported: usize,
+    pub charts_imported: usize,
+    /// 건너뛴 항목 사유 (형식이 잘못된 처방/차팅 기록 등)
+    pub errors: Vec<String>,
+    /// 이미 있던 기록과 처리 결과 (유지/덮어씀)
+    #[serde(default)]
+    pub conflicts: Vec<String>,
+}
+
+/// 설문 응답 일괄 내보내기 형식
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SurveyExportFormat {
+    Pdf,
+    #[serde(alias = "excel")]
+    Xlsx,
+    Csv,
+}
+
+impl SurveyExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Pdf => "pdf",
+            Self::Xlsx => "xlsx",
+            Self::Csv => "csv",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Pdf => "application/pdf",
+            Self::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            Self::Csv => "text/csv; charset=utf-8",
+        }
+    }
+}
+
+/// 설문 응답 일괄 내보내기 요청 (템플릿과 제출일 범위로 거름, 날짜는 YYYY-MM-DD)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SurveyExportRequest {
+    pub format: SurveyExportFormat,
+    #[serde(default)]
+    pub template_id: Option<String>,
+    #[serde(default)]
+    pub from: Option<String>,
+    #[serde(default)]
+    pub to: Option<String>,
+}
+
+/// 목록 API 공통 페이지네이션 응답
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaginatedResponse<T> {
+    pub items: Vec<T>,
+    pub total: i64,                           // 필터 적용 후 전체 건수
+    pub limit: i64,
+    pub offset: i64,
+    pub has_more: bool,                       // offset + items.len() < total
+}
+
+impl<T> PaginatedResponse<T> {
+    pub fn new(items: Vec<T>, total: i64, limit: i64, offset: i64) -> Self {
+        let has_more = offset + (items.len() as i64) < total;
+        Self { items, total, limit, offset, has_more }
+    }
+}
+
+/// 알림
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: String,
+    pub notification_type: String,
+    pub title: String,
+    pub body: String,
+    pub priority: String,
+    pub schedule_id: Option<String>,
+    pub patient_id: Option<String>,
+    pub is_read: bool,
+    pub is_dismissed: bool,
+    pub action_url: Option<String>,
+    pub created_at: String,
+    pub read_at: Option<String>,
+    /// 이 시각까지 안 읽은 알림 목록에서 숨김
+    #[serde(default)]
+    pub snoozed_until: Option<String>,
+}
+
+/// 내원 경로별 신규 환자 수 (source가 None이면 미입력)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferralCount {
+    pub source: Option<String>,
+    pub count: i64,
+}
+
+/// 대시보드 요약 통계
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DashboardStats {
+    pub from: String,                         // 집계 기간 (YYYY-MM-DD)
+    pub to: String,
+    pub visit_count: i32,                     // 기간 내 내원 (초진/경과/차팅 기록)
+    pub new_patients: i32,                    // 기간 내 신규 환자 (기간 미지정 시 이번 주)
+    pub pending_survey_sessions: i32,
+    pub medication_adherence_rate: f64,       // 기간 내 복약 순응률 (%)
+    pub unlinked_survey_responses: i32,
+    pub survey_responses: i32,                // 기간 내 받은 설문 응답
+    pub medications_due: i32,                 // 기간 내 복용 예정 횟수 (복약 일정 기준)
+    pub medications_taken: i32,               // 기간 내 복용 완료 기록
+    pub pending_sync_items: i64,              // 동기화 대기 항목 (재시도 한도 전)
+}
+
+/// 설문 응답 집계 (캐시되어 응답 제출/삭제 시 증분 갱신)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SurveyResponseStats {
+    pub total_responses: i64,
+    pub unlinked_responses: i64,                                   // 환자 미연결 응답
+    pub by_template: std::collections::BTreeMap<String, i64>,      // 템플릿 ID별 응답 수
+    pub by_date: std::collections::BTreeMap<String, i64>,          // 제출일(YYYY-MM-DD)별 응답 수
+}
+
+/// 문서 서명 (처방/차트 확정 시 내용 해시)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentSignature {
+    pub id: String,
+    pub document_type: String,                // 'prescription' | 'chart_record'
+    pub document_id: String,
+    pub content_hash: String,                 // SHA-256 (hex)
+    pub previous_hash: Option<String>,        // 서명 체인 사용 시 직전 서명 해시
+    #[serde(default)]
+    pub hash_version: i32,                    // 해시 입력 형식 버전 (0: 모델 전체 JSON, 1: 고정 내용 필드)
+    pub signed_by: Option<String>,
+    pub signed_at: String,
+}
+
+/// 문서 무결성 검증 결과
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentIntegrity {
+    pub document_id: String,
+    pub valid: bool,                          // 서명 이후 변경 없음
+    pub signature: Option<DocumentSignature>,
+    pub current_hash: Option<String>,         // 문서가 삭제된 경우 None
+}
+
+/// 기간별 매출 집계 항목 (일/월 단위)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevenueBucket {
+    pub period: String,                       // 'YYYY-MM-DD' | 'YYYY-MM'
+    pub count: i32,
+    pub total: i64,
+    pub insured: i64,
+    pub uninsured: i64,
+}
+
+/// 매출 상위 처방
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopPrescriptionRevenue {
+    pub prescription_name: String,
+    pub count: i32,
+    pub total: i64,
+}
+
+/// 매출 리포트 (금액이 입력된 처방만 집계)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevenueReport {
+    pub from: String,
+    pub to: String,
+    pub prescription_count: i32,
+    pub total: i64,
+    pub insured: i64,
+    pub uninsured: i64,
+    pub total_display: String,                // 예: "1,250,000원"
+    pub daily: Vec<RevenueBucket>,
+    pub monthly: Vec<RevenueBucket>,
+    pub top_prescriptions: Vec<TopPrescriptionRevenue>,
+}
+
 /// 복약 관리 (해피콜)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MedicationManagement {
@@ -734,3 +1541,288 @@ pub struct HerbStockLog {
     pub note: Option<String>,
     pub created_at: String,
 }
+
+/// 태그가 달린 항목 (태그 교차 검색 결과)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaggedEntity {
+    pub entity_type: String,
+    pub entity_id: String,
+    pub tags: Vec<String>,
+}
+
+/// 태그별 사용 통계
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagStat {
+    pub tag: String,
+    pub total: i64,
+    pub by_type: HashMap<String, i64>,
+}
+
+/// 환자 타임라인 이벤트 (방문/처방/설문/연락/문서 통합)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatientEvent {
+    pub event_type: String,                   // visit, prescription, survey, contact, document
+    pub source: String,                       // 원본 테이블 (chart_records 등)
+    pub source_id: String,
+    pub occurred_at: String,
+    pub title: String,
+    pub detail: Option<String>,
+}
+
+/// 환자의 같은 설문 반복 응답 추이 (치료 전후 비교용)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatientSurveySeries {
+    pub patient_id: String,
+    pub template_id: String,
+    pub template_name: String,
+    /// 현재 템플릿의 척도 문항 (삭제된 문항은 제외)
+    pub questions: Vec<SeriesQuestion>,
+    /// 제출 시각 오름차순
+    pub points: Vec<SurveySeriesPoint>,
+}
+
+/// 추이 그래프의 계열 (척도 문항)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeriesQuestion {
+    pub question_id: String,
+    pub question_text: String,
+    pub min: Option<i32>,
+    pub max: Option<i32>,
+}
+
+/// 응답 1건의 문항별 숫자 값 (`values`: question_id → 값, 답하지 않은 문항은 없음)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SurveySeriesPoint {
+    pub response_id: String,
+    pub submitted_at: String,
+    pub values: HashMap<String, f64>,
+}
+
+/// 처방 전후 설문 점수 변화와 복약 순응도 (환자 통합 뷰 인사이트)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreatmentOutcomeCorrelation {
+    pub patient_id: String,
+    /// 처방 시각 오름차순
+    pub items: Vec<TreatmentOutcome>,
+    /// 순응률과 점수 변화의 피어슨 상관계수 (두 값이 모두 있는 처방이 3건 미만이면 None)
+    pub adherence_score_correlation: Option<f64>,
+    pub sample_size: i64,
+}
+
+/// 처방 1건의 전후 비교 - 데이터가 없는 항목은 None (부분 결과)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreatmentOutcome {
+    pub prescription_id: String,
+    pub prescription_name: Option<String>,
+    pub prescribed_at: String,
+    pub template_id: Option<String>,
+    pub before_response_id: Option<String>,
+    pub after_response_id: Option<String>,
+    /// 척도 문항 평균 (척도 범위 대비 0~100)
+    pub score_before: Option<f64>,
+    pub score_after: Option<f64>,
+    pub score_change: Option<f64>,
+    /// 복약 순응률 (%) - 복약 기록이 없으면 None
+    pub adherence_rate: Option<f64>,
+    pub medication_logs: i64,
+}
+
+/// 설문 템플릿 응답 통계 (문항별 분포)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateResponseStats {
+    pub template_id: String,
+    pub template_name: String,
+    pub response_count: i64,
+    pub questions: Vec<QuestionStats>,
+}
+
+/// 문항별 응답 통계 - 척도는 평균/중앙값, 선택형은 선택지별 빈도
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuestionStats {
+    pub question_id: String,
+    pub question_text: String,
+    pub question_type: QuestionType,
+    pub answer_count: i64,
+    pub mean: Option<f64>,
+    pub median: Option<f64>,
+    pub option_counts: Vec<OptionCount>,
+}
+
+/// 선택지별 응답 수
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptionCount {
+    pub option: String,
+    pub count: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paginated_response_has_more_until_last_item() {
+        let first = PaginatedResponse::new(vec![1, 2], 5, 2, 0);
+        assert!(first.has_more);
+        let last = PaginatedResponse::new(vec![5], 5, 2, 4);
+        assert!(!last.has_more);
+        let exact = PaginatedResponse::new(vec![3, 4], 4, 2, 2);
+        assert!(!exact.has_more);
+
+        // 범위를 벗어난 페이지 / 빈 목록
+        let past_end = PaginatedResponse::<i32>::new(vec![], 5, 2, 10);
+        assert!(!past_end.has_more);
+        assert_eq!(past_end.total, 5);
+        let empty = PaginatedResponse::<i32>::new(vec![], 0, 50, 0);
+        assert!(!empty.has_more);
+
+        let value = serde_json::to_value(&first).unwrap();
+        assert_eq!(value, serde_json::json!({"items": [1, 2], "total": 5, "limit": 2, "offset": 0, "has_more": true}));
+    }
+
+    #[test]
+    fn scale_step_defaults_to_one_and_checks_boundaries() {
+        let config: ScaleConfig = serde_json::from_value(serde_json::json!({"min": 0, "max": 10})).unwrap();
+        assert_eq!(config.step(), 1);
+        assert!(config.is_valid());
+        assert!(config.accepts(0) && config.accepts(7) && config.accepts(10));
+        assert!(!config.accepts(-1) && !config.accepts(11));
+        assert!(serde_json::to_value(&config).unwrap().get("step").is_none());
+
+        let tens = ScaleConfig { min: 0, max: 100, min_label: None, max_label: None, step: Some(10) };
+        assert!(tens.accepts(0) && tens.accepts(30) && tens.accepts(100));
+        assert!(!tens.accepts(35) && !tens.accepts(110));
+        // 간격은 최소값부터 셈
+        let offset = ScaleConfig { min: 5, max: 25, step: Some(10), ..tens.clone() };
+        assert!(offset.accepts(15) && !offset.accepts(10));
+
+        assert!(!ScaleConfig { min: 10, max: 10, ..tens.clone() }.is_valid());
+        assert!(!ScaleConfig { step: Some(0), ..tens.clone() }.is_valid());
+        assert!(!ScaleConfig { step: Some(0), ..tens }.accepts(0));
+    }
+
+    #[test]
+    fn patient_alert_kinds_round_trip_and_default() {
+        for kind in [PatientAlertType::Allergy, PatientAlertType::CautionMedication, PatientAlertType::ChronicDisease, PatientAlertType::Other] {
+            assert_eq!(PatientAlertType::from_str(kind.as_str()), kind);
+            assert_eq!(serde_json::to_value(kind).unwrap(), kind.as_str());
+        }
+        for severity in [PatientAlertSeverity::High, PatientAlertSeverity::Medium, PatientAlertSeverity::Low] {
+            assert_eq!(PatientAlertSeverity::from_str(severity.as_str()), severity);
+        }
+        assert_eq!(PatientAlertType::from_str("unknown"), PatientAlertType::Other);
+        assert_eq!(PatientAlertSeverity::from_str(""), PatientAlertSeverity::Medium);
+
+        let alert: PatientAlert = serde_json::from_value(serde_json::json!({
+            "id": "a1",
+            "patient_id": "p1",
+            "alert_type": "allergy",
+            "content": "페니실린",
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap();
+        assert_eq!(alert.severity, PatientAlertSeverity::Medium);
+    }
+
+    #[test]
+    fn patient_detail_flattens_patient_with_alert_list() {
+        let detail = PatientDetail { patient: Patient::new("홍길동".to_string()), alerts: vec![] };
+        let value = serde_json::to_value(&detail).unwrap();
+        assert_eq!(value["name"], "홍길동");
+        assert_eq!(value["alerts"], serde_json::json!([]));
+    }
+
+    fn choice_question(id: &str, required: bool, fixed_position: bool, options: &[&str]) -> SurveyQuestion {
+        SurveyQuestion {
+            id: id.to_string(),
+            question_type: QuestionType::SingleChoice,
+            question_text: id.to_string(),
+            required,
+            options: Some(options.iter().map(|o| o.to_string()).collect()),
+            scale_config: None,
+            order: None,
+            shuffle_options: false,
+            fixed_position,
+            translations: None,
+        }
+    }
+
+    fn shuffle_questions() -> Vec<SurveyQuestion> {
+        (0..8)
+            .map(|i| choice_question(&format!("q{}", i), i == 3, i == 0, &["a", "b", "c", "d", "e"]))
+            .collect()
+    }
+
+    #[test]
+    fn splitmix_sequence_is_stable() {
+        // 알려진 splitmix64 출력 - 바뀌면 기존 세션의 표시 순서가 달라짐
+        let mut rng = SplitMix64(0);
+        assert_eq!(rng.next(), 0xD5A9_A293_8991_EEBA);
+        assert_eq!(rng.next(), 0x4B99_0704_4087_A973);
+    }
+
+    #[test]
+    fn display_order_is_reproducible_per_seed() {
+        let questions = shuffle_questions();
+        let first = SurveyDisplayOrder::generate(42, &questions, true, true, false);
+        assert_eq!(first, SurveyDisplayOrder::generate(42, &questions, true, true, false));
+        assert!((0..20).any(|seed| SurveyDisplayOrder::generate(seed, &questions, true, true, false).question_ids != first.question_ids));
+
+        // 저장/복원해도 같은 순서
+        let restored: SurveyDisplayOrder = serde_json::from_value(serde_json::to_value(&first).unwrap()).unwrap();
+        assert_eq!(restored, first);
+
+        let mut sorted = first.question_ids.clone();
+        sorted.sort();
+        assert_eq!(sorted, questions.iter().map(|q| q.id.clone()).collect::<Vec<_>>());
+        for order in first.option_orders.values() {
+            assert!(is_permutation(order, 5));
+        }
+    }
+
+    #[test]
+    fn fixed_and_pinned_questions_keep_their_slot() {
+        let questions = shuffle_questions();
+        for seed in 0..20 {
+            let pinned = SurveyDisplayOrder::generate(seed, &questions, true, false, true);
+            assert_eq!(pinned.question_ids[0], "q0");
+            assert_eq!(pinned.question_ids[3], "q3");
+            assert!(pinned.option_orders.is_empty());
+            assert_eq!(SurveyDisplayOrder::generate(seed, &questions, true, false, false).question_ids[0], "q0");
+        }
+
+        // 문항 순서는 그대로 두고 선택지만 (문항별 shuffle_options 포함)
+        let mut single = vec![choice_question("one", false, false, &["only"]), choice_question("own", false, false, &["x", "y"])];
+        single[1].shuffle_options = true;
+        single.push(choice_question("plain", false, false, &["a", "b"]));
+        let order = SurveyDisplayOrder::generate(7, &single, false, false, false);
+        assert_eq!(order.question_ids, ["one", "own", "plain"]);
+        assert_eq!(order.option_orders.keys().collect::<Vec<_>>(), ["own"]);
+    }
+
+    #[test]
+    fn applying_order_moves_options_with_translations() {
+        let mut english = HashMap::new();
+        english.insert(
+            "en".to_string(),
+            QuestionTranslation { question_text: "Where".to_string(), options: Some(vec!["neck".into(), "back".into(), "knee".into()]) },
+        );
+        let mut questions = vec![
+            choice_question("a", false, false, &["목", "허리", "무릎"]),
+            SurveyQuestion { translations: Some(english), shuffle_options: true, ..choice_question("b", false, false, &["목", "허리", "무릎"]) },
+            choice_question("c", false, false, &["예", "아니오"]),
+            choice_question("new", false, false, &["추가"]),
+        ];
+        let mut option_orders = HashMap::new();
+        option_orders.insert("b".to_string(), vec![2, 0, 1]);
+        option_orders.insert("c".to_string(), vec![0, 1, 2]); // 선택지 수가 달라지면 무시
+        let order = SurveyDisplayOrder { seed: 1, question_ids: vec!["c".into(), "b".into(), "a".into()], option_orders };
+        order.apply(&mut questions);
+
+        assert_eq!(questions.iter().map(|q| q.id.as_str()).collect::<Vec<_>>(), ["c", "b", "a", "new"]);
+        assert_eq!(questions[1].options.as_deref().unwrap(), ["무릎", "목", "허리"]);
+        assert_eq!(questions[1].translations.as_ref().unwrap()["en"].options.as_deref().unwrap(), ["knee", "neck", "back"]);
+        assert!(!questions[1].shuffle_options);
+        assert_eq!(questions[0].options.as_deref().unwrap(), ["예", "아니오"]);
+    }
+}
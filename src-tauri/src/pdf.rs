@@ -0,0 +1,511 @@
+//! 간단한 PDF 문서 생성 (시스템 한글 TrueType 글꼴을 내장, A4 세로 한 단 레이아웃)
+
+use crate::error::{AppError, AppResult};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// 내장할 글꼴 경로를 직접 지정하는 환경 변수 (.ttf 또는 .ttc)
+pub const PDF_FONT_ENV: &str = "GOSIBANG_PDF_FONT";
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+const LINE_SPACING: f32 = 1.45;
+
+/// 내장 시 유지할 테이블 (GSUB/GPOS 등 레이아웃 테이블은 제외해 용량 절약)
+const EMBEDDED_TABLES: [&[u8; 4]; 12] = [
+    b"OS/2", b"cmap", b"cvt ", b"fpgm", b"glyf", b"head", b"hhea", b"hmtx", b"loca", b"maxp", b"name", b"prep",
+];
+
+/// 글꼴 후보 경로 (환경 변수 → Windows → macOS → Linux 순)
+fn font_candidates() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Ok(path) = std::env::var(PDF_FONT_ENV) {
+        paths.push(PathBuf::from(path));
+    }
+    let windir = std::env::var("WINDIR").unwrap_or_else(|_| r"C:\Windows".to_string());
+    for name in ["malgun.ttf", "gulim.ttc", "batang.ttc"] {
+        paths.push(Path::new(&windir).join("Fonts").join(name));
+    }
+    for path in [
+        "/System/Library/Fonts/Supplemental/AppleGothic.ttf",
+        "/Library/Fonts/AppleGothic.ttf",
+        "/usr/share/fonts/truetype/nanum/NanumGothic.ttf",
+        "/usr/share/fonts/nanum/NanumGothic.ttf",
+    ] {
+        paths.push(PathBuf::from(path));
+    }
+    paths
+}
+
+/// 한글 글리프가 있는 첫 번째 시스템 글꼴 로드
+pub fn load_cjk_font() -> AppResult<TrueTypeFont> {
+    for path in font_candidates() {
+        let Ok(data) = std::fs::read(&path) else {
+            continue;
+        };
+        match TrueTypeFont::parse(&data) {
+            Ok(font) if font.glyph_id('가') != 0 => return Ok(font),
+            Ok(_) => log::warn!("PDF 글꼴에 한글 글리프가 없습니다: {}", path.display()),
+            Err(e) => log::warn!("PDF 글꼴을 읽을 수 없습니다 ({}): {}", path.display(), e),
+        }
+    }
+    Err(AppError::Custom(format!(
+        "PDF에 사용할 한글 글꼴을 찾을 수 없습니다 ({} 환경 변수로 .ttf 경로를 지정하세요)",
+        PDF_FONT_ENV
+    )))
+}
+
+fn be16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn be32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// 내장용 TrueType 글꼴 (TTC는 첫 번째 글꼴만 단일 sfnt로 추출)
+pub struct TrueTypeFont {
+    data: Vec<u8>,
+    tables: HashMap<[u8; 4], usize>,
+    units_per_em: u16,
+    bbox: [i16; 4],
+    ascent: i16,
+    descent: i16,
+    num_h_metrics: u16,
+    cmap: (u16, usize),
+}
+
+impl TrueTypeFont {
+    pub fn parse(raw: &[u8]) -> AppResult<Self> {
+        Self::parse_inner(raw).ok_or_else(|| AppError::Custom("TrueType 글꼴 형식이 올바르지 않습니다".to_string()))?
+    }
+
+    fn parse_inner(raw: &[u8]) -> Option<AppResult<Self>> {
+        let base = if raw.get(0..4)? == b"ttcf" { be32(raw, 12)? as usize } else { 0 };
+        match raw.get(base..base + 4)? {
+            [0, 1, 0, 0] | b"true" => {}
+            b"OTTO" => return Some(Err(AppError::Custom("CFF(OpenType) 글꼴은 지원하지 않습니다".to_string()))),
+            _ => return None,
+        }
+
+        // 필요한 테이블만 모아 단일 sfnt로 재구성
+        let num_tables = be16(raw, base + 4)? as usize;
+        let mut records = Vec::new();
+        for i in 0..num_tables {
+            let rec = base + 12 + i * 16;
+            let tag: [u8; 4] = raw.get(rec..rec + 4)?.try_into().ok()?;
+            if EMBEDDED_TABLES.contains(&&tag) {
+                let offset = be32(raw, rec + 8)? as usize;
+                let length = be32(raw, rec + 12)? as usize;
+                records.push((tag, be32(raw, rec + 4)?, raw.get(offset..offset + length)?));
+            }
+        }
+        records.sort_by_key(|(tag, _, _)| *tag);
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&[0, 1, 0, 0]);
+        let n = records.len() as u16;
+        let entry_selector = 15 - n.max(1).leading_zeros() as u16;
+        let search_range = (1u16 << entry_selector) * 16;
+        for v in [n, search_range, entry_selector, n * 16 - search_range] {
+            data.extend_from_slice(&v.to_be_bytes());
+        }
+        let mut offset = 12 + records.len() * 16;
+        let mut tables = HashMap::new();
+        for (tag, checksum, body) in &records {
+            data.extend_from_slice(tag);
+            data.extend_from_slice(&checksum.to_be_bytes());
+            data.extend_from_slice(&(offset as u32).to_be_bytes());
+            data.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            tables.insert(*tag, offset);
+            offset += (body.len() + 3) & !3;
+        }
+        for (_, _, body) in &records {
+            data.extend_from_slice(body);
+            data.resize((data.len() + 3) & !3, 0);
+        }
+
+        let head = *tables.get(b"head")?;
+        let hhea = *tables.get(b"hhea")?;
+        tables.get(b"hmtx")?;
+        let i16_at = |offset: usize| be16(&data, offset).map(|v| v as i16);
+        let cmap = Self::find_cmap(&data, *tables.get(b"cmap")?)?;
+
+        Some(Ok(Self {
+            units_per_em: be16(&data, head + 18)?.max(1),
+            bbox: [i16_at(head + 36)?, i16_at(head + 38)?, i16_at(head + 40)?, i16_at(head + 42)?],
+            ascent: i16_at(hhea + 4)?,
+            descent: i16_at(hhea + 6)?,
+            num_h_metrics: be16(&data, hhea + 34)?.max(1),
+            cmap,
+            tables,
+            data,
+        }))
+    }
+
+    /// 유니코드 cmap 하위 테이블 선택 (형식 12 우선, 없으면 형식 4) - (형식, 절대 오프셋)
+    fn find_cmap(data: &[u8], cmap: usize) -> Option<(u16, usize)> {
+        let count = be16(data, cmap + 2)? as usize;
+        let mut best: Option<(u16, usize)> = None;
+        for i in 0..count {
+            let rec = cmap + 4 + i * 8;
+            let (platform, encoding) = (be16(data, rec)?, be16(data, rec + 2)?);
+            let unicode = platform == 0 || (platform == 3 && (encoding == 1 || encoding == 10));
+            let sub = cmap + be32(data, rec + 4)? as usize;
+            let format = be16(data, sub)?;
+            if unicode && (format == 12 || (format == 4 && best.is_none())) {
+                best = Some((format, sub));
+            }
+        }
+        best
+    }
+
+    /// 문자의 글리프 번호 (없으면 0 = .notdef)
+    pub fn glyph_id(&self, ch: char) -> u16 {
+        let c = ch as u32;
+        let (format, sub) = self.cmap;
+        let d = &self.data;
+        let lookup = || -> Option<u16> {
+            if format == 12 {
+                let groups = be32(d, sub + 12)? as usize;
+                for i in 0..groups {
+                    let g = sub + 16 + i * 12;
+                    let (start, end) = (be32(d, g)?, be32(d, g + 4)?);
+                    if (start..=end).contains(&c) {
+                        return Some((be32(d, g + 8)? + (c - start)) as u16);
+                    }
+                }
+                return Some(0);
+            }
+            if c > 0xFFFF {
+                return Some(0);
+            }
+            let seg_x2 = be16(d, sub + 6)? as usize;
+            let ends = sub + 14;
+            let starts = ends + seg_x2 + 2;
+            let deltas = starts + seg_x2;
+            let range_offsets = deltas + seg_x2;
+            for i in (0..seg_x2).step_by(2) {
+                if c > be16(d, ends + i)? as u32 {
+                    continue;
+                }
+                let start = be16(d, starts + i)? as u32;
+                if c < start {
+                    return Some(0);
+                }
+                let delta = be16(d, deltas + i)?;
+                let range_offset = be16(d, range_offsets + i)? as usize;
+                if range_offset == 0 {
+                    return Some((c as u16).wrapping_add(delta));
+                }
+                let glyph = be16(d, range_offsets + i + range_offset + 2 * (c - start) as usize)?;
+                return Some(if glyph == 0 { 0 } else { glyph.wrapping_add(delta) });
+            }
+            Some(0)
+        };
+        lookup().unwrap_or(0)
+    }
+
+    /// 글리프 폭 (1000 단위)
+    fn advance(&self, glyph: u16) -> u32 {
+        let hmtx = self.tables[b"hmtx"];
+        let index = glyph.min(self.num_h_metrics - 1) as usize;
+        let advance = be16(&self.data, hmtx + index * 4).unwrap_or(0) as u32;
+        advance * 1000 / self.units_per_em as u32
+    }
+
+    fn scale(&self, v: i16) -> i32 {
+        v as i32 * 1000 / self.units_per_em as i32
+    }
+}
+
+/// A4 문서 - 위에서 아래로 흐르는 텍스트, 공간이 부족하면 자동으로 다음 페이지
+pub struct PdfDocument {
+    font: TrueTypeFont,
+    pages: Vec<String>,
+    current: String,
+    y: f32,
+    used_glyphs: BTreeMap<u16, char>,
+}
+
+impl PdfDocument {
+    pub fn new(font: TrueTypeFont) -> Self {
+        Self {
+            font,
+            pages: Vec::new(),
+            current: String::new(),
+            y: PAGE_HEIGHT - MARGIN,
+            used_glyphs: BTreeMap::new(),
+        }
+    }
+
+    /// 본문 폭
+    pub fn content_width(&self) -> f32 {
+        PAGE_WIDTH - MARGIN * 2.0
+    }
+
+    fn text_width(&self, text: &str, size: f32) -> f32 {
+        text.chars()
+            .map(|c| self.font.advance(self.font.glyph_id(c)) as f32 * size / 1000.0)
+            .sum()
+    }
+
+    pub fn new_page(&mut self) {
+        self.pages.push(std::mem::take(&mut self.current));
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    /// 남은 높이가 부족하면 다음 페이지로
+    fn ensure_space(&mut self, height: f32) {
+        if self.y - height < MARGIN {
+            self.new_page();
+        }
+    }
+
+    pub fn space(&mut self, height: f32) {
+        self.y -= height;
+    }
+
+    /// 본문 폭 가로줄
+    pub fn rule(&mut self) {
+        self.ensure_space(8.0);
+        self.y -= 4.0;
+        self.current.push_str(&format!(
+            "0.75 G 0.5 w {:.2} {:.2} m {:.2} {:.2} l S\n",
+            MARGIN,
+            self.y,
+            PAGE_WIDTH - MARGIN,
+            self.y
+        ));
+        self.y -= 4.0;
+    }
+
+    /// 검은색 텍스트 (폭에 맞춰 줄바꿈)
+    pub fn text(&mut self, text: &str, size: f32, indent: f32) {
+        self.write_lines(text, size, indent, 0.0);
+    }
+
+    /// 회색 보조 텍스트
+    pub fn muted_text(&mut self, text: &str, size: f32, indent: f32) {
+        self.write_lines(text, size, indent, 0.45);
+    }
+
+    /// 가운데 정렬 텍스트 (폭에 맞춰 줄바꿈)
+    pub fn centered_text(&mut self, text: &str, size: f32) {
+        let max_width = self.content_width();
+        let lines: Vec<String> = text.lines().flat_map(|l| self.wrap(l, size, max_width)).collect();
+        for line in lines {
+            let x = MARGIN + (max_width - self.text_width(&line, size)).max(0.0) / 2.0;
+            self.write_line(&line, size, x, 0.0);
+        }
+    }
+
+    /// 본문 가운데에 QR 코드 (한 변 `size`pt, 모듈마다 사각형으로 그려 인쇄 시에도 선명함)
+    pub fn qr_code(&mut self, text: &str, size: f32) -> AppResult<()> {
+        let code = qrcode::QrCode::new(text.as_bytes())
+            .map_err(|e| AppError::Custom(format!("QR 코드 생성 실패: {}", e)))?;
+        let width = code.width();
+        let module = size / width as f32;
+
+        self.ensure_space(size);
+        let left = MARGIN + (self.content_width() - size) / 2.0;
+        let top = self.y;
+        self.current.push_str("0 g\n");
+        for (i, color) in code.to_colors().iter().enumerate() {
+            if *color == qrcode::Color::Dark {
+                let (row, col) = (i / width, i % width);
+                self.current.push_str(&format!(
+                    "{:.2} {:.2} {:.2} {:.2} re\n",
+                    left + col as f32 * module,
+                    top - (row + 1) as f32 * module,
+                    module,
+                    module
+                ));
+            }
+        }
+        self.current.push_str("f\n");
+        self.y -= size;
+        Ok(())
+    }
+
+    fn write_lines(&mut self, text: &str, size: f32, indent: f32, gray: f32) {
+        let max_width = self.content_width() - indent;
+        let lines: Vec<String> = text.lines().flat_map(|l| self.wrap(l, size, max_width)).collect();
+        for line in lines {
+            self.write_line(&line, size, MARGIN + indent, gray);
+        }
+    }
+
+    fn write_line(&mut self, line: &str, size: f32, x: f32, gray: f32) {
+        let line_height = size * LINE_SPACING;
+        self.ensure_space(line_height);
+        let baseline = self.y - size;
+        self.y -= line_height;
+        let mut hex = String::new();
+        for c in line.chars() {
+            let glyph = self.font.glyph_id(c);
+            self.used_glyphs.entry(glyph).or_insert(c);
+            hex.push_str(&format!("{:04X}", glyph));
+        }
+        self.current.push_str(&format!(
+            "BT /F1 {:.1} Tf {} g {:.2} {:.2} Td <{}> Tj ET\n",
+            size, gray, x, baseline, hex
+        ));
+    }
+
+    /// 폭에 맞게 줄 나누기 (가능하면 공백에서 끊음)
+    fn wrap(&self, line: &str, size: f32, max_width: f32) -> Vec<String> {
+        let line = line.replace('\t', "    ");
+        let line: String = line.chars().filter(|c| !c.is_control()).collect();
+        if line.is_empty() {
+            return vec![String::new()];
+        }
+
+        let mut lines = Vec::new();
+        let mut current = String::new();
+        for c in line.chars() {
+            current.push(c);
+            if self.text_width(&current, size) <= max_width {
+                continue;
+            }
+            current.pop();
+            let break_at = current.rfind(' ').filter(|&i| i > 0);
+            match break_at {
+                Some(i) => {
+                    let rest = current[i + 1..].to_string();
+                    current.truncate(i);
+                    lines.push(std::mem::replace(&mut current, rest));
+                }
+                None => lines.push(std::mem::take(&mut current)),
+            }
+            current.push(c);
+        }
+        lines.push(current);
+        lines
+    }
+
+    /// PDF 바이트 생성
+    pub fn finish(mut self) -> AppResult<Vec<u8>> {
+        if !self.current.is_empty() || self.pages.is_empty() {
+            self.new_page();
+        }
+
+        let font = &self.font;
+        let name = "GosibangCJK";
+        let mut objects: Vec<Vec<u8>> = Vec::new();
+        let page_ids: Vec<usize> = (0..self.pages.len()).map(|i| 8 + i * 2).collect();
+
+        objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+        objects.push(
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                page_ids.iter().map(|id| format!("{} 0 R", id)).collect::<Vec<_>>().join(" "),
+                page_ids.len()
+            )
+            .into_bytes(),
+        );
+        objects.push(
+            format!(
+                "<< /Type /Font /Subtype /Type0 /BaseFont /{} /Encoding /Identity-H /DescendantFonts [4 0 R] /ToUnicode 7 0 R >>",
+                name
+            )
+            .into_bytes(),
+        );
+        let widths: String = self
+            .used_glyphs
+            .keys()
+            .map(|&g| format!("{} [{}]", g, font.advance(g)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        objects.push(
+            format!(
+                "<< /Type /Font /Subtype /CIDFontType2 /BaseFont /{} /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> /FontDescriptor 5 0 R /CIDToGIDMap /Identity /DW 1000 /W [{}] >>",
+                name, widths
+            )
+            .into_bytes(),
+        );
+        let [x_min, y_min, x_max, y_max] = font.bbox.map(|v| font.scale(v));
+        objects.push(
+            format!(
+                "<< /Type /FontDescriptor /FontName /{} /Flags 4 /FontBBox [{} {} {} {}] /ItalicAngle 0 /Ascent {} /Descent {} /CapHeight {} /StemV 80 /FontFile2 6 0 R >>",
+                name,
+                x_min,
+                y_min,
+                x_max,
+                y_max,
+                font.scale(font.ascent),
+                font.scale(font.descent),
+                font.scale(font.ascent)
+            )
+            .into_bytes(),
+        );
+        objects.push(stream_object(&font.data, &format!("/Length1 {}", font.data.len()))?);
+        objects.push(stream_object(to_unicode_cmap(&self.used_glyphs).as_bytes(), "")?);
+
+        for (i, content) in self.pages.iter().enumerate() {
+            objects.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+                    PAGE_WIDTH,
+                    PAGE_HEIGHT,
+                    page_ids[i] + 1
+                )
+                .into_bytes(),
+            );
+            objects.push(stream_object(content.as_bytes(), "")?);
+        }
+
+        let mut out = b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, body) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            out.extend_from_slice(body);
+            out.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = out.len();
+        out.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+        for offset in offsets {
+            out.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        out.extend_from_slice(
+            format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref).as_bytes(),
+        );
+        Ok(out)
+    }
+}
+
+/// Flate 압축 스트림 객체
+fn stream_object(data: &[u8], extra: &str) -> AppResult<Vec<u8>> {
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data)?;
+    let compressed = encoder.finish()?;
+    let mut object =
+        format!("<< /Length {} /Filter /FlateDecode {}>>\nstream\n", compressed.len(), extra).into_bytes();
+    object.extend_from_slice(&compressed);
+    object.extend_from_slice(b"\nendstream");
+    Ok(object)
+}
+
+/// 글리프 → 유니코드 매핑 (PDF에서 텍스트 복사/검색용)
+fn to_unicode_cmap(glyphs: &BTreeMap<u16, char>) -> String {
+    let mut cmap = String::from(
+        "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+         /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+         1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    );
+    let entries: Vec<(&u16, &char)> = glyphs.iter().collect();
+    for chunk in entries.chunks(100) {
+        cmap.push_str(&format!("{} beginbfchar\n", chunk.len()));
+        for (glyph, c) in chunk {
+            let utf16: String = c.encode_utf16(&mut [0; 2]).iter().map(|u| format!("{:04X}", u)).collect();
+            cmap.push_str(&format!("<{:04X}> <{}>\n", glyph, utf16));
+        }
+        cmap.push_str("endbfchar\n");
+    }
+    cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+    cmap
+}
@@ -3,44 +3,110 @@
 //! 환자 설문 페이지와 직원 대시보드를 인트라넷에서 제공합니다.
 
 use axum::{
-    extract::{Path, State},
-    http::{header, StatusCode},
-    response::{Html, IntoResponse, Json},
+    extract::{Path, Request, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Json, Response},
     routing::{get, post},
-    Router,
+    Extension, Router,
 };
 use rust_embed::Embed;
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::net::SocketAddr;
-use std::sync::{Arc, Mutex};
-use tower_http::cors::{Any, CorsLayer};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tower_http::limit::RequestBodyLimitLayer;
 
 use crate::auth;
 use crate::db;
-use crate::error::AppResult;
+use crate::error::{AppError, AppErrorResponse, AppResult};
+use crate::i18n::Lang;
 
 /// 내장 정적 파일 (기존 설문 시스템용)
 #[derive(Embed)]
 #[folder = "static/"]
 struct StaticAssets;
 
+/// 템플릿 미리보기 화면에서 사용하는 가상 설문 토큰 (제출 시 저장하지 않음)
+const PREVIEW_TOKEN: &str = "preview";
+
+/// 직원 세션 유휴 만료 (마지막 요청 이후, 요청마다 연장)
+const STAFF_SESSION_IDLE_HOURS: i64 = 12;
+
+/// 직원 세션 최대 유지 기간 (로그인 시각 기준, 연장/갱신해도 넘을 수 없음)
+const STAFF_SESSION_MAX_AGE_DAYS: i64 = 7;
+
+/// 직원 세션 마지막 요청 시각을 DB에 다시 기록하는 간격 (요청마다 쓰지 않도록)
+const STAFF_SESSION_ACTIVITY_SAVE_MINUTES: i64 = 5;
+
 /// 서버 상태
 #[derive(Clone)]
 pub struct AppState {
-    /// 직원 세션 (간단한 토큰 기반)
+    /// 직원 세션 캐시 (원본은 DB `web_sessions`, 앱 재시작 후에는 첫 요청 때 다시 읽음)
     pub staff_sessions: Arc<Mutex<HashMap<String, StaffSession>>>,
     /// 현재 사용자의 플랜 타입 (free, basic, premium)
     pub plan_type: Arc<Mutex<String>>,
     /// 온라인 설문 기능 활성화 여부
     pub survey_external_enabled: Arc<Mutex<bool>>,
+    /// 디버그 엔드포인트(/debug/*) 활성화 여부 (기본: 디버그 빌드에서만)
+    pub debug_endpoints: bool,
+    /// 설문 세션 과다 생성 탐지 임계치
+    pub session_abuse_threshold: SessionAbuseThreshold,
+    /// CORS 추가 허용 출처 (비어 있으면 로컬/앱 출처만, "*"이면 전체)
+    pub cors_origins: Vec<String>,
+    /// 화면 표시용 한의원 이름 캐시 (`POST /api/admin/reload-settings`로 다시 읽음)
+    pub clinic_name: Arc<RwLock<String>>,
+    /// `GET /metrics` 노출 여부 (끄면 404)
+    pub metrics_enabled: bool,
+    /// 접근 로그 레벨 (Off면 기록하지 않음)
+    pub access_log_level: log::LevelFilter,
+    /// HTTPS 사용 여부 (인증서 경로가 없으면 자체 서명 인증서 생성)
+    pub tls_enabled: bool,
+    /// HTTPS 인증서 (PEM)
+    pub tls_cert_path: Option<PathBuf>,
+    /// HTTPS 개인키 (PEM)
+    pub tls_key_path: Option<PathBuf>,
+}
+
+/// 설문 세션 과다 생성 임계치 (window_minutes 동안 max_sessions 초과 시 경고)
+#[derive(Clone, Copy, Debug)]
+pub struct SessionAbuseThreshold {
+    pub max_sessions: i32,
+    pub window_minutes: i64,
+}
+
+impl Default for SessionAbuseThreshold {
+    fn default() -> Self {
+        Self { max_sessions: 30, window_minutes: 10 }
+    }
+}
+
+/// 허용되는 플랜 타입
+pub const PLAN_TYPES: &[&str] = &["free", "basic", "premium"];
+
+/// 런타임에 바꿀 수 있는 플랜/기능 플래그
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct FeatureFlags {
+    pub plan_type: String,
+    pub survey_external_enabled: bool,
 }
 
 #[derive(Clone, Debug)]
 pub struct StaffSession {
     pub token: String,
     pub clinic_name: String,
+    /// 직원 계정으로 로그인한 경우 계정 ID (공용 직원 비밀번호 로그인은 None)
+    pub account_id: Option<String>,
     pub created_at: chrono::DateTime<chrono::Utc>,
+    /// 마지막 요청 시각 (유휴 만료 기준)
+    pub last_activity: chrono::DateTime<chrono::Utc>,
+    /// 임시 비밀번호로 로그인함 (비밀번호를 바꾸기 전까지 다른 기능 사용 불가)
+    pub must_change_password: bool,
+    /// DB에 마지막으로 기록한 요청 시각
+    activity_saved_at: chrono::DateTime<chrono::Utc>,
 }
 
 impl AppState {
@@ -49,6 +115,15 @@ impl AppState {
             staff_sessions: Arc::new(Mutex::new(HashMap::new())),
             plan_type: Arc::new(Mutex::new("free".to_string())),
             survey_external_enabled: Arc::new(Mutex::new(false)),
+            debug_endpoints: cfg!(debug_assertions),
+            session_abuse_threshold: SessionAbuseThreshold::default(),
+            cors_origins: Vec::new(),
+            clinic_name: Arc::new(RwLock::new(load_clinic_name())),
+            metrics_enabled: true,
+            access_log_level: log::LevelFilter::Info,
+            tls_enabled: false,
+            tls_cert_path: None,
+            tls_key_path: None,
         }
     }
 
@@ -57,13 +132,145 @@ impl AppState {
             staff_sessions: Arc::new(Mutex::new(HashMap::new())),
             plan_type: Arc::new(Mutex::new(plan_type)),
             survey_external_enabled: Arc::new(Mutex::new(survey_external)),
+            debug_endpoints: cfg!(debug_assertions),
+            session_abuse_threshold: SessionAbuseThreshold::default(),
+            cors_origins: Vec::new(),
+            clinic_name: Arc::new(RwLock::new(load_clinic_name())),
+            metrics_enabled: true,
+            access_log_level: log::LevelFilter::Info,
+            tls_enabled: false,
+            tls_cert_path: None,
+            tls_key_path: None,
+        }
+    }
+
+    /// 디버그 엔드포인트 명시적 활성화 (릴리스 빌드에서 진단용)
+    pub fn with_debug_endpoints(mut self, enabled: bool) -> Self {
+        self.debug_endpoints = self.debug_endpoints || enabled;
+        self
+    }
+
+    /// 설문 세션 과다 생성 임계치 설정
+    pub fn with_session_abuse_threshold(mut self, threshold: SessionAbuseThreshold) -> Self {
+        self.session_abuse_threshold = threshold;
+        self
+    }
+
+    /// 지표 엔드포인트 사용 여부 설정
+    pub fn with_metrics_enabled(mut self, enabled: bool) -> Self {
+        self.metrics_enabled = enabled;
+        self
+    }
+
+    /// 접근 로그 레벨 설정
+    pub fn with_access_log_level(mut self, level: log::LevelFilter) -> Self {
+        self.access_log_level = level;
+        self
+    }
+
+    /// CORS 허용 출처 설정
+    pub fn with_cors_origins(mut self, origins: Vec<String>) -> Self {
+        self.cors_origins = origins;
+        self
+    }
+
+    /// HTTPS 설정 (인증서/개인키가 모두 있으면 해당 파일, 없고 enabled이면 자체 서명 인증서)
+    pub fn with_tls(mut self, enabled: bool, cert_path: Option<PathBuf>, key_path: Option<PathBuf>) -> Self {
+        self.tls_enabled = enabled;
+        self.tls_cert_path = cert_path;
+        self.tls_key_path = key_path;
+        self
+    }
+
+    /// 이 설정으로 쓸 TLS 구성 (None이면 HTTP)
+    pub async fn tls_config(&self) -> AppResult<Option<axum_server::tls_rustls::RustlsConfig>> {
+        crate::tls::load_config(self.tls_enabled, self.tls_cert_path.as_deref(), self.tls_key_path.as_deref()).await
+    }
+
+    /// 캐시된 한의원 이름
+    pub fn clinic_name(&self) -> String {
+        self.clinic_name
+            .read()
+            .map(|name| name.clone())
+            .unwrap_or_else(|_| DEFAULT_CLINIC_NAME.to_string())
+    }
+
+    /// 현재 플랜/기능 플래그
+    pub fn feature_flags(&self) -> FeatureFlags {
+        // 갱신과 같은 순서로 잠가 두 값이 어긋난 상태를 읽지 않도록 함
+        let plan_type = self.plan_type.lock().ok();
+        let survey_external = self.survey_external_enabled.lock().ok();
+        FeatureFlags {
+            plan_type: plan_type.map(|p| p.clone()).unwrap_or_else(|| "free".to_string()),
+            survey_external_enabled: survey_external.map(|v| *v).unwrap_or(false),
+        }
+    }
+
+    /// 플랜/기능 플래그 갱신 (지정한 값만 변경, 알 수 없는 플랜은 거부)
+    pub fn update_feature_flags(
+        &self,
+        plan_type: Option<&str>,
+        survey_external_enabled: Option<bool>,
+    ) -> AppResult<FeatureFlags> {
+        if let Some(plan) = plan_type {
+            if !PLAN_TYPES.contains(&plan) {
+                return Err(crate::error::AppError::Validation(format!("알 수 없는 플랜입니다: {}", plan)));
+            }
+        }
+
+        let mut current_plan = self.plan_type.lock()
+            .map_err(|_| crate::error::AppError::Custom("플랜 상태 잠금 실패".to_string()))?;
+        let mut current_external = self.survey_external_enabled.lock()
+            .map_err(|_| crate::error::AppError::Custom("기능 플래그 잠금 실패".to_string()))?;
+        if let Some(plan) = plan_type {
+            *current_plan = plan.to_string();
+        }
+        if let Some(enabled) = survey_external_enabled {
+            *current_external = enabled;
+        }
+
+        Ok(FeatureFlags {
+            plan_type: current_plan.clone(),
+            survey_external_enabled: *current_external,
+        })
+    }
+
+    /// DB에서 한의원 설정을 다시 읽어 캐시 갱신 (갱신된 이름 반환)
+    pub fn reload_clinic_settings(&self) -> String {
+        let name = load_clinic_name();
+        if let Ok(mut cached) = self.clinic_name.write() {
+            *cached = name.clone();
         }
+        name
+    }
+}
+
+/// 설정이 없을 때 표시할 한의원 이름
+const DEFAULT_CLINIC_NAME: &str = "한의원";
+
+fn load_clinic_name() -> String {
+    db::get_clinic_settings()
+        .ok()
+        .flatten()
+        .map(|s| s.clinic_name)
+        .unwrap_or_else(|| DEFAULT_CLINIC_NAME.to_string())
+}
+
+/// 설문 화면 상단 로고 태그 (로고가 없으면 빈 문자열)
+fn clinic_logo_html() -> String {
+    let logo = db::get_clinic_settings().ok().flatten().and_then(|s| s.clinic_logo).unwrap_or_default();
+    // 저장 시 검증하지만 속성값에 그대로 넣으므로 base64 문자만 허용
+    if logo.is_empty() || !logo.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/' | b'=')) {
+        return String::new();
     }
+    format!(r#"<img class="clinic-logo" src="data:image/png;base64,{}" alt="">"#, logo)
 }
 
 /// 라우터 생성
 pub fn create_router(state: AppState) -> Router {
-    Router::new()
+    let router = Router::new()
+        // 메인 인덱스 (안내 페이지)
+        .route("/", get(index_handler))
         .route("/health", get(health_handler))
         // 환자 설문 페이지 (기존 기능)
         .route("/s/{token}", get(survey_page_handler))
@@ -71,46 +278,531 @@ pub fn create_router(state: AppState) -> Router {
         .route("/patient", get(patient_kiosk_page))
         .route("/api/patient/create-session", post(patient_create_session_api))
         // 설문 API
-        .route("/api/survey/{token}", get(get_survey_data).post(submit_survey))
+        .route(
+            "/api/survey/{token}",
+            get(get_survey_data).post(submit_survey).layer(RequestBodyLimitLayer::new(crate::web_api::MAX_BODY_BYTES)),
+        )
+        .route("/api/survey/{token}/abandon", post(abandon_survey_api))
+        .route(
+            "/api/survey/{token}/attachments",
+            post(upload_survey_attachment).layer(axum::extract::DefaultBodyLimit::max(db::SURVEY_ATTACHMENT_MAX_BYTES)),
+        )
+        .route("/api/survey-attachments/{id}", get(get_survey_attachment_api))
         // 직원 페이지 (간단한 설문 관리용)
         .route("/staff", get(staff_login_page))
         .route("/staff/login", post(staff_login))
         .route("/staff/dashboard", get(staff_dashboard))
+        .route("/staff/preview/{template_id}", get(staff_preview_page))
+        // 웹 클라이언트용 경로 (세션 없이 템플릿 미리보기)
+        .route("/survey-templates/{template_id}/preview", get(staff_preview_page))
+        .route("/staff/responses/{id}/print", get(staff_response_print_page))
+        .route("/staff/kiosk-poster", get(staff_kiosk_poster_page))
+        .route("/survey-responses/{id}/pdf", get(survey_response_pdf))
         .route("/api/staff/create-session", post(create_session_api))
         .route("/api/staff/create-online-session", post(create_online_session_api))
         .route("/api/responses", get(get_responses_api))
+        .route("/api/sessions", get(get_sessions_api))
+        .route("/api/sessions/{id}/cancel", post(cancel_session_api))
+        .route("/api/sessions/{id}/extend", post(extend_session_api))
+        .route("/api/sessions/{id}/regenerate-token", post(regenerate_session_token_api))
+        .route("/api/qr", get(qr_code_api))
         .route("/api/templates", get(get_templates_api))
-        // 디버그 (개발용)
-        .route("/debug/db", get(debug_db_handler))
-        .route("/debug/create-test-session", post(create_test_session_handler))
+        .route("/api/templates/categories", get(get_template_categories_api))
+        .route("/api/templates/{id}/stats", get(crate::web_api::get_template_response_stats))
         // 정적 파일 (기존 설문 시스템용)
-        .route("/static/{*path}", get(static_handler))
+        .route("/static/{*path}", get(static_handler));
+
+    // 디버그 (개발용) - 비활성화 시 라우트 자체를 등록하지 않음 (404)
+    let router = if state.debug_endpoints {
+        router
+            .route("/debug/db", get(debug_db_handler))
+            .route("/debug/create-test-session", post(create_test_session_handler))
+    } else {
+        router
+    };
+
+    let cors = cors_layer(&state.cors_origins);
+    let access_log_level = state.access_log_level;
+    router
+        .layer(cors.clone())
+        // 직원용 REST API (브라우저에서 직접 호출하므로 별도 CORS 적용)
+        .merge(crate::web_api::routes(cors))
         .with_state(state)
-        // 메인 인덱스 (안내 페이지)
-        .route("/", get(index_handler))
+        // API 오류 본문 형식 통일
+        .layer(middleware::from_fn(normalize_api_errors))
+        // 접근 로그 (요청 ID가 부여된 뒤 기록)
+        .layer(middleware::from_fn(move |request: Request, next: Next| log_requests(access_log_level, request, next)))
+        // 직원용 API를 포함한 모든 라우트에 요청 ID 부여
+        .layer(middleware::from_fn(assign_request_id))
+        // 요청 수/지연 지표
+        .layer(middleware::from_fn(crate::metrics::track_requests))
 }
 
-/// HTTP 서버 시작
-pub async fn start_server(port: u16) -> AppResult<()> {
-    let state = AppState::new();
+/// 요청 추적 ID (`X-Request-ID` 응답 헤더와 로그에 함께 기록)
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
 
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
+impl std::fmt::Display for RequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
 
-    let app = create_router(state).layer(cors);
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// 요청마다 ID를 발급해 extension으로 넘기고 응답 헤더에 추가
+async fn assign_request_id(mut request: Request, next: Next) -> Response {
+    let request_id = RequestId(uuid::Uuid::new_v4().to_string());
+    request.extensions_mut().insert(request_id.clone());
+
+    let mut response = next.run(request).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id.0) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// 로그에 값을 남기지 않는 쿼리 파라미터 (인증 토큰, 본인 확인용 생년월일)
+const REDACTED_QUERY_PARAMS: &[&str] = &["token", "dob", "password"];
+
+/// 접근 로그: 메서드, 경로, 상태, 처리 시간
+/// 헤더(Authorization 포함)는 기록하지 않고 쿼리의 민감한 값은 가림
+async fn log_requests(level: log::LevelFilter, request: Request, next: Next) -> Response {
+    let Some(level) = level.to_level() else {
+        return next.run(request).await;
+    };
+
+    let started = std::time::Instant::now();
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let query = request.uri().query().map(redact_query);
+    let request_id = request.extensions().get::<RequestId>().cloned();
+
+    let response = next.run(request).await;
+
+    log::log!(
+        level,
+        "[{}] {} {}{} {} {}ms",
+        request_id.map(|id| id.0).unwrap_or_default(),
+        method,
+        path,
+        query.map(|q| format!("?{}", q)).unwrap_or_default(),
+        response.status().as_u16(),
+        started.elapsed().as_millis()
+    );
+    response
+}
+
+fn redact_query(query: &str) -> String {
+    query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if REDACTED_QUERY_PARAMS.contains(&key) => format!("{}=***", key),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// CORS 설정
+///
+/// 키오스크/설문 페이지는 이 서버에서 직접 제공되므로(동일 출처) 기본적으로는
+/// 로컬 출처와 앱(tauri) 출처만 허용합니다. 허용 목록에 "*"가 있으면 모든 출처를
+/// 허용하되 자격 증명(쿠키 등)은 허용하지 않습니다.
+pub fn cors_layer(allowed_origins: &[String]) -> CorsLayer {
+    let methods = [Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE, Method::OPTIONS];
+    let headers = [
+        header::CONTENT_TYPE,
+        header::AUTHORIZATION,
+        header::ACCEPT,
+        header::HeaderName::from_static("idempotency-key"),
+    ];
+
+    if allowed_origins.iter().any(|o| o.trim() == "*") {
+        return CorsLayer::new()
+            .allow_origin(Any)
+            .allow_methods(methods)
+            .allow_headers(headers)
+            .allow_credentials(false);
+    }
+
+    let configured: Vec<String> = allowed_origins
+        .iter()
+        .map(|o| o.trim().trim_end_matches('/').to_string())
+        .filter(|o| !o.is_empty())
+        .collect();
+
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::predicate(move |origin: &HeaderValue, _| {
+            let origin = origin.to_str().unwrap_or_default();
+            is_local_origin(origin) || configured.iter().any(|o| o == origin)
+        }))
+        .allow_methods(methods)
+        .allow_headers(headers)
+}
+
+/// 로컬/앱 출처 여부
+fn is_local_origin(origin: &str) -> bool {
+    const APP_ORIGINS: [&str; 3] = ["tauri://localhost", "http://tauri.localhost", "https://tauri.localhost"];
+    if APP_ORIGINS.contains(&origin) {
+        return true;
+    }
+
+    let host = origin
+        .strip_prefix("http://")
+        .or_else(|| origin.strip_prefix("https://"))
+        .and_then(|rest| rest.split(':').next())
+        .unwrap_or_default();
+    host == "localhost" || host == "127.0.0.1"
+}
+
+/// 현재 서버가 HTTPS로 실행 중인지 여부 (안내 주소 생성용)
+static TLS_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// 안내 주소에 쓸 scheme ("http" 또는 "https")
+pub fn url_scheme() -> &'static str {
+    if TLS_ACTIVE.load(Ordering::SeqCst) {
+        "https"
+    } else {
+        "http"
+    }
+}
+
+/// 바인딩된 리스너로 라우터 실행 (TLS 구성이 있으면 HTTPS)
+pub async fn serve(
+    listener: tokio::net::TcpListener,
+    app: Router,
+    tls: Option<axum_server::tls_rustls::RustlsConfig>,
+) -> AppResult<()> {
+    let service = app.into_make_service_with_connect_info::<SocketAddr>();
+    let result = match tls {
+        Some(config) => {
+            TLS_ACTIVE.store(true, Ordering::SeqCst);
+            axum_server::from_tcp_rustls(listener.into_std()?, config).serve(service).await
+        }
+        None => {
+            TLS_ACTIVE.store(false, Ordering::SeqCst);
+            axum::serve(listener, service).await
+        }
+    };
+    TLS_ACTIVE.store(false, Ordering::SeqCst);
+    result.map_err(|e| crate::error::AppError::Custom(format!("Server error: {}", e)))
+}
+
+/// HTTP 서버 시작
+pub async fn start_server(port: u16) -> AppResult<()> {
+    let tls = db::get_server_tls_settings().unwrap_or_default();
+    let state = AppState::new()
+        .with_cors_origins(db::get_cors_allowed_origins().unwrap_or_default())
+        .with_tls(tls.enabled, tls.cert_path.map(PathBuf::from), tls.key_path.map(PathBuf::from));
+    let tls_config = state.tls_config().await?;
+    let scheme = if tls_config.is_some() { "https" } else { "http" };
+    let app = create_router(state);
+    let sweep = spawn_session_expiry_sweep();
 
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
-    log::info!("HTTP 서버 시작: http://0.0.0.0:{}", port);
+    log::info!("HTTP 서버 시작: {}://0.0.0.0:{}", scheme, port);
 
     let listener = tokio::net::TcpListener::bind(addr).await
         .map_err(|e| crate::error::AppError::Custom(format!("Server bind error: {}", e)))?;
 
-    axum::serve(listener, app).await
-        .map_err(|e| crate::error::AppError::Custom(format!("Server error: {}", e)))?;
+    let result = serve(listener, app, tls_config).await;
+    stop_session_expiry_sweep(sweep);
+    result
+}
+
+/// 세션 정리 작업 락 이름 (같은 DB를 쓰는 인스턴스 중 하나만 실행)
+const SESSION_SWEEP_LOCK: &str = "session_sweep";
+
+/// 실행 주기의 이 배수 동안 락 갱신이 없으면 소유 인스턴스가 비정상 종료된 것으로 봄
+const SESSION_SWEEP_LOCK_STALE_FACTOR: i64 = 3;
+
+/// 정리 작업 중복 실행 방지 (주기 실행과 수동 실행이 겹치지 않도록)
+static SESSION_SWEEP_RUNNING: Mutex<()> = Mutex::new(());
+
+/// 정리 작업 1회 실행 결과
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct SweepSummary {
+    pub expired_sessions: usize,
+    pub follow_ups_created: usize,
+    pub unlinked_notified: i64,
+    pub attachments_purged: usize,
+    pub notifications_resurfaced: usize,
+    /// 실패한 단계 (나머지 단계는 계속 실행)
+    pub errors: Vec<String>,
+}
+
+/// 만료 세션 정리, 후속 설문 생성, 미연결 응답 알림 등 정리 작업을 즉시 1회 실행
+///
+/// 다른 인스턴스가 DB 락을 가지고 있으면 `None`, 이미 실행 중이면 `AppError::Custom`.
+pub fn run_checks_once() -> AppResult<Option<SweepSummary>> {
+    let _running = match SESSION_SWEEP_RUNNING.try_lock() {
+        Ok(guard) => guard,
+        Err(std::sync::TryLockError::Poisoned(e)) => e.into_inner(),
+        Err(std::sync::TryLockError::WouldBlock) => {
+            return Err(crate::error::AppError::Custom("정리 작업이 이미 실행 중입니다".to_string()));
+        }
+    };
+
+    let stale_after_secs = SESSION_SWEEP_LOCK_STALE_FACTOR * db::get_scheduler_interval_secs()? as i64;
+    if !db::try_acquire_scheduler_lock(SESSION_SWEEP_LOCK, stale_after_secs)? {
+        return Ok(None);
+    }
+
+    let mut summary = SweepSummary::default();
+    match db::expire_overdue_survey_sessions() {
+        Ok(0) => {}
+        Ok(count) => {
+            log::info!("만료된 설문 세션 {}건 정리", count);
+            summary.expired_sessions = count;
+        }
+        Err(e) => {
+            log::warn!("설문 세션 만료 정리 실패: {}", e);
+            summary.errors.push(format!("설문 세션 만료 정리 실패: {}", e));
+        }
+    }
+    match db::process_due_survey_follow_ups() {
+        Ok(0) => {}
+        Ok(count) => {
+            log::info!("후속 설문 세션 {}건 생성", count);
+            summary.follow_ups_created = count;
+        }
+        Err(e) => {
+            log::warn!("후속 설문 처리 실패: {}", e);
+            summary.errors.push(format!("후속 설문 처리 실패: {}", e));
+        }
+    }
+    match db::notify_unlinked_responses() {
+        Ok(Some(count)) => {
+            log::info!("환자 미연결 응답 {}건 알림", count);
+            summary.unlinked_notified = count;
+        }
+        Ok(None) => {}
+        Err(e) => {
+            log::warn!("미연결 응답 알림 실패: {}", e);
+            summary.errors.push(format!("미연결 응답 알림 실패: {}", e));
+        }
+    }
+    match db::purge_orphan_survey_attachments() {
+        Ok(0) => {}
+        Ok(count) => {
+            log::info!("미제출 설문 첨부 파일 {}건 정리", count);
+            summary.attachments_purged = count;
+        }
+        Err(e) => {
+            log::warn!("설문 첨부 파일 정리 실패: {}", e);
+            summary.errors.push(format!("설문 첨부 파일 정리 실패: {}", e));
+        }
+    }
+    match db::resurface_snoozed_notifications() {
+        Ok(0) => {}
+        Ok(count) => {
+            log::info!("스누즈 종료 알림 {}건 다시 표시", count);
+            summary.notifications_resurfaced = count;
+        }
+        Err(e) => {
+            log::warn!("스누즈 알림 처리 실패: {}", e);
+            summary.errors.push(format!("스누즈 알림 처리 실패: {}", e));
+        }
+    }
+    Ok(Some(summary))
+}
+
+/// 정리 작업을 주기적으로 실행하는 백그라운드 작업 (주기는 알림 설정, 변경 시 다음 주기부터 적용)
+///
+/// 데스크톱 앱과 서버가 같은 DB로 동시에 떠 있으면 후속 설문/알림이 중복 생성되므로,
+/// 매 주기마다 DB 락을 확인해 락을 가진 인스턴스만 실행한다.
+pub fn spawn_session_expiry_sweep() -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async {
+        let mut owned = None;
+        loop {
+            match run_checks_once() {
+                Ok(result) => {
+                    let acquired = result.is_some();
+                    if owned != Some(acquired) {
+                        if acquired {
+                            log::info!("세션 정리 작업 락 획득 - 이 인스턴스에서 실행");
+                        } else {
+                            log::info!("다른 인스턴스가 세션 정리 작업을 실행 중 - 건너뜀");
+                        }
+                        owned = Some(acquired);
+                    }
+                    if acquired {
+                        run_email_jobs().await;
+                        run_reminder_jobs().await;
+                        run_survey_reminder_jobs().await;
+                        run_retention_purge();
+                        run_weekly_db_maintenance();
+                        crate::jobs::cleanup_expired_jobs();
+                    }
+                }
+                Err(e) => log::warn!("세션 정리 작업 건너뜀: {}", e),
+            }
+
+            let interval_secs = db::get_scheduler_interval_secs().unwrap_or(db::DEFAULT_SCHEDULER_INTERVAL_SECS);
+            tokio::time::sleep(std::time::Duration::from_secs(interval_secs)).await;
+        }
+    })
+}
+
+/// 이메일 재발송 및 일일 요약 (SMTP 통신이 있어 정리 작업과 별도로 비동기 실행)
+async fn run_email_jobs() {
+    match crate::email::retry_pending_emails().await {
+        Ok(0) => {}
+        Ok(count) => log::info!("대기 중이던 이메일 {}건 발송", count),
+        Err(e) => log::warn!("이메일 재발송 실패: {}", e),
+    }
+    if let Err(e) = crate::email::dispatch_daily_summary().await {
+        log::warn!("일일 요약 메일 발송 실패 (나중에 재시도됨): {}", e);
+    }
+}
+
+/// 복약 알림 (채널 설정에 따라 데스크톱 알림과 환자 문자, 문자는 방해 금지 시간에 보내지 않음)
+async fn run_reminder_jobs() {
+    let window_secs = db::get_scheduler_interval_secs().unwrap_or(db::DEFAULT_SCHEDULER_INTERVAL_SECS);
+    let reminders = match db::list_due_medication_reminders(window_secs) {
+        Ok(reminders) => reminders,
+        Err(e) => {
+            log::warn!("복약 알림 대상 조회 실패: {}", e);
+            return;
+        }
+    };
+    if reminders.is_empty() {
+        return;
+    }
+
+    let channel = db::get_notification_channel().unwrap_or_default();
+    let quiet = channel.includes_sms() && db::is_do_not_disturb_now().unwrap_or(false);
+    if quiet {
+        log::info!("방해 금지 시간 - 복약 알림 문자 건너뜀 ({}건)", reminders.len());
+    }
+    let clinic_name = load_clinic_name();
+
+    for reminder in &reminders {
+        if channel.includes_desktop() {
+            if let Err(e) = db::notify_medication_reminder(reminder) {
+                log::warn!("복약 알림 생성 실패: {}", e);
+            }
+        }
+        if !channel.includes_sms() || quiet {
+            continue;
+        }
+        let Some(phone) = reminder.phone.as_deref().filter(|p| !p.trim().is_empty()) else {
+            continue;
+        };
+        match db::claim_reminder_delivery(&reminder.schedule_id, &reminder.slot, "sms") {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(e) => {
+                log::warn!("복약 알림 문자 기록 실패: {}", e);
+                continue;
+            }
+        }
+
+        let message = crate::sms::medication_reminder_message(&clinic_name, &reminder.patient_name, &reminder.time);
+        match crate::sms::send(phone, &message).await {
+            Ok(()) => log::info!("복약 알림 문자 발송: {} {}", reminder.patient_id, reminder.slot),
+            // 번호가 잘못된 경우는 다시 보내도 실패하므로 기록 유지
+            Err(crate::error::AppError::Validation(msg)) => log::warn!("복약 알림 문자 건너뜀: {}", msg),
+            Err(e) => {
+                log::warn!("복약 알림 문자 발송 실패 (다음 주기에 재시도): {}", e);
+                if let Err(e) = db::release_reminder_delivery(&reminder.schedule_id, &reminder.slot, "sms") {
+                    log::warn!("복약 알림 문자 기록 취소 실패: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// 곧 만료되는 미완료 설문의 환자에게 리마인더 문자 (세션당 한 번, 방해 금지 시간에는 다음 주기로 미룸)
+async fn run_survey_reminder_jobs() {
+    let Ok(Some(hours)) = db::get_survey_reminder_hours() else {
+        return;
+    };
+    if !matches!(db::get_sms_settings(), Ok(Some(_))) || db::is_do_not_disturb_now().unwrap_or(false) {
+        return;
+    }
+    let sessions = match db::get_pending_sessions_expiring(hours) {
+        Ok(sessions) => sessions,
+        Err(e) => {
+            log::warn!("설문 리마인더 대상 조회 실패: {}", e);
+            return;
+        }
+    };
+    let clinic_name = load_clinic_name();
+
+    for session in &sessions {
+        match db::claim_survey_reminder(&session.session_id) {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(e) => {
+                log::warn!("설문 리마인더 기록 실패: {}", e);
+                continue;
+            }
+        }
+        // 연락처가 없으면 처리한 것으로 기록만 남김 (매 주기 다시 조회하지 않도록)
+        let Some(phone) = session.phone.as_deref().filter(|p| !p.trim().is_empty()) else {
+            log::info!("설문 리마인더 건너뜀 (연락처 없음): {}", session.session_id);
+            continue;
+        };
+
+        let expires_at = session.expires_at.with_timezone(&chrono::Local).format("%m/%d %H:%M").to_string();
+        let message = crate::sms::survey_reminder_message(&clinic_name, session.name.as_deref(), &session.template_name, &expires_at);
+        match crate::sms::send(phone, &message).await {
+            Ok(()) => log::info!("설문 리마인더 문자 발송: {}", session.session_id),
+            Err(crate::error::AppError::Validation(msg)) => log::warn!("설문 리마인더 문자 건너뜀: {}", msg),
+            Err(e) => {
+                log::warn!("설문 리마인더 문자 발송 실패 (다음 주기에 재시도): {}", e);
+                if let Err(e) = db::release_survey_reminder(&session.session_id) {
+                    log::warn!("설문 리마인더 기록 취소 실패: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// 보관 기간이 지난 설문 응답/알림/복약 기록 정리 (하루 한 번)
+fn run_retention_purge() {
+    let retention = match db::claim_daily_retention_purge() {
+        Ok(Some(retention)) => retention,
+        Ok(None) => return,
+        Err(e) => {
+            log::warn!("자동 정리 설정 확인 실패: {}", e);
+            return;
+        }
+    };
+    match db::purge_old_data(&retention, false) {
+        Ok(summary) => log::info!(
+            "오래된 기록 정리: 설문 응답 {}건, 알림 {}건, 복약 기록 {}건",
+            summary.survey_responses,
+            summary.notifications,
+            summary.medication_logs
+        ),
+        Err(e) => log::warn!("오래된 기록 정리 실패: {}", e),
+    }
+}
+
+/// 설정한 요일/시각에 DB 최적화 (주 1회)
+fn run_weekly_db_maintenance() {
+    match db::claim_weekly_db_maintenance() {
+        Ok(true) => {}
+        Ok(false) => return,
+        Err(e) => {
+            log::warn!("DB 최적화 설정 확인 실패: {}", e);
+            return;
+        }
+    }
+    if let Err(e) = db::run_maintenance() {
+        log::warn!("DB 최적화 실패: {}", e);
+    }
+}
 
-    Ok(())
+/// 세션 정리 작업 중지 및 락 반납 (다른 인스턴스가 stale 대기 없이 이어받도록)
+pub fn stop_session_expiry_sweep(sweep: tokio::task::JoinHandle<()>) {
+    sweep.abort();
+    if let Err(e) = db::release_scheduler_lock(SESSION_SWEEP_LOCK) {
+        log::warn!("세션 정리 작업 락 해제 실패: {}", e);
+    }
 }
 
 // ============ 핸들러 ============
@@ -121,82 +813,120 @@ async fn health_handler() -> &'static str {
 }
 
 /// 메인 페이지
-async fn index_handler() -> Html<String> {
-    let clinic_name = db::get_clinic_settings()
-        .ok()
-        .flatten()
-        .map(|s| s.clinic_name)
-        .unwrap_or_else(|| "한의원".to_string());
-
-    Html(format!(r#"<!DOCTYPE html>
-<html lang="ko">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>{} - 설문 시스템</title>
-    <style>
-        * {{ margin: 0; padding: 0; box-sizing: border-box; }}
-        body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif; background: #f5f5f5; min-height: 100vh; display: flex; align-items: center; justify-content: center; }}
-        .container {{ background: white; padding: 3rem; border-radius: 1rem; box-shadow: 0 4px 6px rgba(0,0,0,0.1); text-align: center; max-width: 400px; }}
-        h1 {{ color: #333; margin-bottom: 1rem; }}
-        p {{ color: #666; margin-bottom: 2rem; }}
-        a {{ display: inline-block; padding: 0.75rem 1.5rem; background: #4f46e5; color: white; text-decoration: none; border-radius: 0.5rem; }}
-        a:hover {{ background: #4338ca; }}
-    </style>
-</head>
-<body>
-    <div class="container">
-        <h1>🏥 {}</h1>
-        <p>설문 시스템에 오신 것을 환영합니다.</p>
-        <a href="/staff">직원 로그인</a>
-    </div>
-</body>
-</html>"#, clinic_name, clinic_name))
+async fn index_handler(State(state): State<AppState>) -> Html<String> {
+    let clinic_name = state.clinic_name();
+
+    Html(render_template("index.html", &[("clinic_name", &clinic_name)]))
 }
 
 /// 환자 설문 페이지
-async fn survey_page_handler(Path(token): Path<String>) -> impl IntoResponse {
+async fn survey_page_handler(
+    Path(token): Path<String>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let lang = Lang::from_params(&params);
+    let t = lang.strings();
+
     // 세션 확인
     let session = match db::get_survey_session_by_token(&token) {
         Ok(Some(s)) => s,
-        Ok(None) => return Html(error_page("설문을 찾을 수 없습니다", "잘못된 링크이거나 만료된 설문입니다.")),
-        Err(_) => return Html(error_page("오류가 발생했습니다", "잠시 후 다시 시도해주세요.")),
+        Ok(None) => return Html(error_page(lang, t.not_found, t.not_found_detail)),
+        Err(_) => return Html(error_page(lang, t.server_error, t.retry_later)),
     };
 
     // 상태 확인
     if session.status == crate::models::SessionStatus::Completed {
-        return Html(error_page("이미 완료된 설문입니다", "감사합니다."));
+        return Html(error_page(lang, t.already_completed, t.thanks));
     }
     if session.status == crate::models::SessionStatus::Expired {
-        return Html(error_page("만료된 설문입니다", "새로운 설문 링크를 요청해주세요."));
+        return Html(error_page(lang, t.expired, t.expired_detail));
+    }
+    if session.status == crate::models::SessionStatus::Locked {
+        return Html(error_page(lang, t.locked, t.locked_detail));
     }
 
-    // 템플릿 조회
-    let template = match db::get_survey_template(&session.template_id) {
+    // 본인 확인 (생년월일) - 확인 전에는 입력 단계만 표시
+    let dob = params.get("dob").map(|s| s.as_str());
+    if session.verify_birth_date {
+        match dob.map(|d| db::verify_survey_birth_date(&session.id, d)) {
+            Some(Ok(db::BirthDateCheck::Verified)) => {}
+            Some(Ok(db::BirthDateCheck::Locked)) => {
+                return Html(error_page(lang, t.locked, t.locked_detail));
+            }
+            Some(Err(_)) => return Html(error_page(lang, t.server_error, t.retry_later)),
+            _ => return Html(render_birth_date_page(&token, lang)),
+        }
+    }
+
+    // 템플릿 조회 (세션에 정해진 표시 순서가 있으면 그 순서로)
+    let mut template = match db::get_survey_template(&session.template_id) {
         Ok(Some(t)) => t,
-        _ => return Html(error_page("설문 템플릿을 찾을 수 없습니다", "")),
+        _ => return Html(error_page(lang, t.template_not_found, "")),
     };
+    if let Some(order) = &session.display_order {
+        order.apply(&mut template.questions);
+    }
 
     // 설문 페이지 렌더링
-    Html(render_survey_page(&token, &template, session.respondent_name.as_deref()))
+    let dob = if session.verify_birth_date { dob } else { None };
+    Html(render_survey_page(&token, &template, session.respondent_name.as_deref(), dob, false, lang))
+}
+
+/// 생년월일 확인 (`?dob=`) - 실패 시 응답 반환
+fn check_survey_birth_date(
+    session: &db::SurveySessionDb,
+    params: &HashMap<String, String>,
+) -> Result<(), axum::response::Response> {
+    if !session.verify_birth_date {
+        return Ok(());
+    }
+
+    let dob = params.get("dob").map(|s| s.as_str()).unwrap_or_default();
+    if dob.is_empty() {
+        return Err(coded_error_response(StatusCode::FORBIDDEN, "ERR_BIRTH_DATE_REQUIRED", "생년월일 확인이 필요합니다"));
+    }
+
+    match db::verify_survey_birth_date(&session.id, dob) {
+        Ok(db::BirthDateCheck::Verified) => Ok(()),
+        Ok(db::BirthDateCheck::Mismatch { remaining }) => Err(coded_error_response(
+            StatusCode::FORBIDDEN,
+            "ERR_BIRTH_DATE_MISMATCH",
+            format!("생년월일이 일치하지 않습니다 (남은 시도: {}회)", remaining),
+        )),
+        Ok(db::BirthDateCheck::Locked) => Err(error_response(
+            StatusCode::LOCKED,
+            "본인 확인에 여러 번 실패하여 설문이 잠겼습니다. 한의원에 문의해주세요.",
+        )),
+        Err(_) => Err(error_response(StatusCode::INTERNAL_SERVER_ERROR, "서버 오류")),
+    }
 }
 
 /// 설문 데이터 API
-async fn get_survey_data(Path(token): Path<String>) -> impl IntoResponse {
+async fn get_survey_data(
+    Path(token): Path<String>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> impl IntoResponse {
     let session = match db::get_survey_session_by_token(&token) {
         Ok(Some(s)) => s,
-        Ok(None) => return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "설문을 찾을 수 없습니다"}))).into_response(),
-        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": "서버 오류"}))).into_response(),
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "설문을 찾을 수 없습니다"),
+        Err(_) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, "서버 오류"),
     };
 
     if session.status != crate::models::SessionStatus::Pending {
-        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "유효하지 않은 설문입니다"}))).into_response();
+        return error_response(StatusCode::BAD_REQUEST, "유효하지 않은 설문입니다");
     }
 
-    let template = match db::get_survey_template(&session.template_id) {
+    if let Err(resp) = check_survey_birth_date(&session, &params) {
+        return resp;
+    }
+
+    let mut template = match db::get_survey_template(&session.template_id) {
         Ok(Some(t)) => t,
-        _ => return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "템플릿을 찾을 수 없습니다"}))).into_response(),
+        _ => return error_response(StatusCode::NOT_FOUND, "템플릿을 찾을 수 없습니다"),
     };
+    if let Some(order) = &session.display_order {
+        order.apply(&mut template.questions);
+    }
 
     Json(serde_json::json!({
         "session": session,
@@ -208,180 +938,959 @@ async fn get_survey_data(Path(token): Path<String>) -> impl IntoResponse {
 #[derive(Deserialize)]
 struct SubmitSurveyRequest {
     answers: Vec<crate::models::SurveyAnswer>,
+    /// 템플릿이 `require_contact`일 때 필수
+    #[serde(default)]
+    contact: Option<db::RespondentContact>,
 }
 
+/// `Idempotency-Key` 헤더가 같은 재시도는 최초 제출과 같은 성공 응답을 받음
 async fn submit_survey(
     Path(token): Path<String>,
+    Extension(request_id): Extension<RequestId>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+    headers: axum::http::HeaderMap,
     Json(payload): Json<SubmitSurveyRequest>,
 ) -> impl IntoResponse {
+    // 미리보기 화면에서의 제출은 저장하지 않음
+    if token == PREVIEW_TOKEN {
+        return (StatusCode::OK, Json(serde_json::json!({"success": true, "preview": true, "message": "미리보기에서는 제출되지 않습니다"}))).into_response();
+    }
+
+    let idempotency_key = headers
+        .get("idempotency-key")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty() && v.len() <= 128);
+
     // 세션 확인
     let session = match db::get_survey_session_by_token(&token) {
         Ok(Some(s)) => s,
-        Ok(None) => return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "설문을 찾을 수 없습니다"}))),
-        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": "서버 오류"}))),
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "설문을 찾을 수 없습니다"),
+        Err(_) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, "서버 오류"),
     };
 
-    if session.status != crate::models::SessionStatus::Pending {
-        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "이미 완료되었거나 만료된 설문입니다"})));
+    // 완료된 세션은 멱등 키 재시도 여부를 저장 단계에서 판별
+    if !matches!(session.status, crate::models::SessionStatus::Pending | crate::models::SessionStatus::Completed) {
+        return error_response(StatusCode::BAD_REQUEST, "이미 완료되었거나 만료된 설문입니다");
     }
 
+    if let Err(resp) = check_survey_birth_date(&session, &params) {
+        return resp;
+    }
+
+    // 연락처 필수 템플릿 검증
+    let contact = payload.contact.as_ref().map(|c| c.normalized()).unwrap_or_default();
+    let template = match db::get_survey_template(&session.template_id) {
+        Ok(t) => t,
+        Err(e) => {
+            log::error!("[{}] 설문 템플릿 조회 실패: {}", request_id, e);
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, "서버 오류");
+        }
+    };
+    let require_contact = template.as_ref().is_some_and(|t| t.require_contact && !t.anonymous);
+    if let Some(t) = template.as_ref() {
+        if let Err(e) = db::validate_scale_answers(&t.questions, &payload.answers) {
+            return app_error_response(StatusCode::UNPROCESSABLE_ENTITY, &e);
+        }
+    }
+    // 저장 이후 검증 규칙이 바뀌었을 수 있으므로 응답 직전에 다시 확인
+    let redirect_url = template
+        .and_then(|t| t.redirect_url)
+        .and_then(|url| db::validate_redirect_url(Some(&url)).ok().flatten());
+    let submitted = serde_json::json!({"success": true, "message": "설문이 제출되었습니다", "redirect_url": redirect_url});
+    if require_contact {
+        if let Err(e) = db::validate_respondent_contact(&contact, session.respondent_name.as_deref()) {
+            return app_error_response(StatusCode::BAD_REQUEST, &e);
+        }
+    }
+    let respondent_name = contact.respondent_name.as_deref().or(session.respondent_name.as_deref());
+
     // 응답 저장
     let response = match db::save_survey_response(
         &session.id,
         &session.template_id,
         session.patient_id.as_deref(),
-        session.respondent_name.as_deref(),
+        respondent_name,
+        &contact,
         &payload.answers,
+        idempotency_key,
     ) {
-        Ok(r) => r,
+        Ok(db::SurveySubmission::Saved(r)) => {
+            crate::metrics::record_survey_submission();
+            r
+        }
+        Ok(db::SurveySubmission::Duplicate) => {
+            return (StatusCode::OK, Json(submitted)).into_response();
+        }
+        // 동시에 들어온 다른 요청이 먼저 제출한 경우
+        Ok(db::SurveySubmission::AlreadySubmitted) => {
+            return error_response(StatusCode::CONFLICT, "이미 제출된 설문입니다. 감사합니다.");
+        }
         Err(e) => {
-            log::error!("설문 응답 저장 실패: {}", e);
-            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": "저장 실패"})));
+            log::error!("[{}] 설문 응답 저장 실패: {}", request_id, e);
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, "저장 실패");
         }
     };
 
+    // 후속 설문 예약 (지연 없는 규칙은 바로 생성)
+    match db::schedule_survey_follow_ups(&session.id, &session.template_id, &payload.answers) {
+        Ok(0) => {}
+        Ok(_) => {
+            if let Err(e) = db::process_due_survey_follow_ups() {
+                log::warn!("[{}] 후속 설문 생성 실패: {}", request_id, e);
+            }
+        }
+        Err(e) => log::warn!("[{}] 후속 설문 예약 실패: {}", request_id, e),
+    }
+
     // Supabase 동기화 (비동기, 실패해도 로컬 저장은 완료됨)
     tokio::spawn(async move {
         if let Err(e) = crate::sync::sync_survey_response(&response).await {
-            log::warn!("Supabase 동기화 실패 (나중에 재시도됨): {}", e);
+            log::warn!("[{}] Supabase 동기화 실패 (나중에 재시도됨): {}", request_id, e);
         }
     });
 
-    // 세션 완료 처리
-    if let Err(e) = db::complete_survey_session(&session.id) {
-        log::error!("세션 완료 처리 실패: {}", e);
+    (StatusCode::OK, Json(submitted)).into_response()
+}
+
+/// 설문 첨부 파일 업로드 (본문은 파일 내용, `Content-Type`은 파일 형식, `?question_id=&file_name=`)
+/// 제출 전에 올리고, 응답의 해당 문항 답변에 `{"attachment_id", "file_name"}`을 담아 제출
+async fn upload_survey_attachment(
+    Path(token): Path<String>,
+    Extension(request_id): Extension<RequestId>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+    headers: axum::http::HeaderMap,
+    body: axum::body::Bytes,
+) -> impl IntoResponse {
+    let session = match db::get_survey_session_by_token(&token) {
+        Ok(Some(s)) => s,
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "설문을 찾을 수 없습니다"),
+        Err(_) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, "서버 오류"),
+    };
+    let expired = chrono::DateTime::parse_from_rfc3339(&session.expires_at)
+        .map(|at| at < chrono::Utc::now())
+        .unwrap_or(false);
+    if session.status != crate::models::SessionStatus::Pending || expired {
+        return error_response(StatusCode::BAD_REQUEST, "이미 완료되었거나 만료된 설문입니다");
+    }
+
+    if let Err(resp) = check_survey_birth_date(&session, &params) {
+        return resp;
+    }
+
+    let question_id = params.get("question_id").cloned().unwrap_or_default();
+    let is_file_question = db::get_survey_template(&session.template_id)
+        .ok()
+        .flatten()
+        .is_some_and(|t| {
+            t.questions
+                .iter()
+                .any(|q| q.id == question_id && matches!(q.question_type, crate::models::QuestionType::FileUpload))
+        });
+    if !is_file_question {
+        return error_response(StatusCode::BAD_REQUEST, "파일을 첨부할 수 없는 문항입니다");
+    }
+
+    let mime_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let file_name = params.get("file_name").map(|s| s.as_str()).unwrap_or_default();
+    match db::save_survey_attachment(&session.id, &question_id, file_name, mime_type, &body) {
+        Ok(attachment) => {
+            log::info!("[{}] 설문 첨부 파일 업로드: {} ({} bytes)", request_id, attachment.id, attachment.size);
+            Json(serde_json::json!({
+                "attachment_id": attachment.id,
+                "file_name": attachment.file_name,
+                "mime_type": attachment.mime_type,
+                "size": attachment.size,
+            })).into_response()
+        }
+        Err(crate::error::AppError::Validation(msg)) => error_response(StatusCode::BAD_REQUEST, msg),
+        Err(e) => {
+            log::error!("[{}] 설문 첨부 파일 저장 실패: {}", request_id, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "저장 실패")
+        }
+    }
+}
+
+/// 설문 첨부 파일 보기 (직원용)
+async fn get_survey_attachment_api(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let token = params.get("token").cloned().unwrap_or_default();
+    if !is_valid_staff_token(&state, &token) {
+        return error_response(StatusCode::UNAUTHORIZED, "인증 필요");
+    }
+
+    match db::get_survey_attachment(&id) {
+        Ok(Some((attachment, data))) => {
+            // 비ASCII 파일 이름은 RFC 5987 형식으로 전달
+            let encoded_name: String = attachment
+                .file_name
+                .bytes()
+                .map(|b| if b.is_ascii_alphanumeric() || b".-_".contains(&b) { (b as char).to_string() } else { format!("%{:02X}", b) })
+                .collect();
+            (
+                [
+                    (header::CONTENT_TYPE, attachment.mime_type),
+                    (header::CONTENT_DISPOSITION, format!("inline; filename*=UTF-8''{}", encoded_name)),
+                    (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+                ],
+                data,
+            ).into_response()
+        }
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "첨부 파일을 찾을 수 없습니다"),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 키오스크 무입력 시간 초과로 설문 중단 (완료/만료된 세션이면 변경 없음)
+async fn abandon_survey_api(
+    Extension(request_id): Extension<RequestId>,
+    Path(token): Path<String>,
+) -> impl IntoResponse {
+    match db::abandon_survey_session(&token) {
+        Ok(Some(abandoned)) => {
+            if abandoned {
+                log::info!("[{}] 키오스크 무입력으로 설문 중단", request_id);
+            }
+            Json(serde_json::json!({"success": true, "abandoned": abandoned})).into_response()
+        }
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "설문을 찾을 수 없습니다"),
+        Err(e) => {
+            log::error!("[{}] 설문 중단 처리 실패: {}", request_id, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "서버 오류")
+        }
+    }
+}
+
+/// 직원 로그인 페이지
+async fn staff_login_page(State(state): State<AppState>) -> Html<String> {
+    let clinic_name = state.clinic_name();
+
+    Html(render_staff_login_page(&clinic_name))
+}
+
+/// 직원 로그인 처리
+#[derive(Deserialize)]
+struct StaffLoginRequest {
+    clinic_name: String,
+    /// 지정하면 공용 비밀번호 대신 해당 직원 계정 비밀번호로 확인
+    #[serde(default)]
+    username: Option<String>,
+    password: String,
+}
+
+async fn staff_login(
+    State(state): State<AppState>,
+    Json(payload): Json<StaffLoginRequest>,
+) -> impl IntoResponse {
+    // 한의원 이름 확인
+    let settings = match db::get_clinic_settings() {
+        Ok(Some(s)) => s,
+        _ => return error_response(StatusCode::UNAUTHORIZED, "설정을 찾을 수 없습니다"),
+    };
+
+    if settings.clinic_name != payload.clinic_name {
+        return error_response(StatusCode::UNAUTHORIZED, "한의원 이름이 일치하지 않습니다");
+    }
+
+    // 비밀번호 확인 (직원 계정 또는 공용 직원 비밀번호)
+    let (account_id, must_change_password) = match payload.username.as_deref().map(str::trim).filter(|u| !u.is_empty()) {
+        Some(username) => match db::verify_staff_account_password(username, &payload.password) {
+            Ok(Some(account)) => (Some(account.id), account.must_change_password),
+            Ok(None) => return error_response(StatusCode::UNAUTHORIZED, "아이디 또는 비밀번호가 일치하지 않습니다"),
+            Err(_) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, "서버 오류"),
+        },
+        None => match db::verify_staff_password(&payload.password) {
+            Ok(true) => (None, false),
+            Ok(false) => return error_response(StatusCode::UNAUTHORIZED, "비밀번호가 일치하지 않습니다"),
+            Err(_) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, "서버 오류"),
+        },
+    };
+
+    // 세션 생성
+    let token = generate_session_token();
+    let now = chrono::Utc::now();
+    let session = StaffSession {
+        token: token.clone(),
+        clinic_name: settings.clinic_name,
+        account_id,
+        created_at: now,
+        last_activity: now,
+        must_change_password,
+        activity_saved_at: now,
+    };
+
+    // 만료된 세션은 로그인할 때 함께 정리
+    if let Err(e) = db::delete_expired_web_sessions(
+        now - chrono::Duration::hours(STAFF_SESSION_IDLE_HOURS),
+        now - chrono::Duration::days(STAFF_SESSION_MAX_AGE_DAYS),
+    ) {
+        log::warn!("만료된 직원 세션 정리 실패: {}", e);
+    }
+    if let Err(e) = db::save_web_session(&token, &session.to_stored()) {
+        log::error!("직원 세션 저장 실패: {}", e);
+        return app_error_response(e.status_code(), &e);
+    }
+    if let Ok(mut sessions) = state.staff_sessions.lock() {
+        sessions.retain(|_, s| !s.is_expired(now));
+        sessions.insert(token.clone(), session);
+    }
+
+    Json(serde_json::json!({
+        "success": true,
+        "token": token,
+        "must_change_password": must_change_password,
+    })).into_response()
+}
+
+/// 직원 대시보드
+async fn staff_dashboard(
+    State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let token = params.get("token").cloned().unwrap_or_default();
+
+    // 세션 확인 (만료된 세션은 제거됨)
+    let session = active_staff_session(&state, &token).filter(|s| !s.must_change_password);
+
+    // 온라인 설문 기능 활성화 여부
+    let survey_external = state.feature_flags().survey_external_enabled;
+
+    match session {
+        Some(s) => Html(render_staff_dashboard(&s.clinic_name, &token, survey_external)),
+        None => Html(render_staff_login_page_with_error("로그인이 필요하거나 세션이 만료되었습니다.")),
+    }
+}
+
+/// 설문 템플릿 미리보기 (환자 화면과 동일, 제출 비활성화)
+///
+/// 세션을 만들지 않고 `?token=`(직원 토큰)만 확인하므로 문구나 표시 방식을 바로바로 확인할 수 있습니다.
+async fn staff_preview_page(
+    State(state): State<AppState>,
+    Path(template_id): Path<String>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let token = params.get("token").cloned().unwrap_or_default();
+    if !is_valid_staff_token(&state, &token) {
+        return Html(render_staff_login_page_with_error("로그인이 필요합니다."));
+    }
+
+    // `?lang=`으로 번역된 화면도 미리 확인
+    let lang = Lang::from_params(&params);
+    let t = lang.strings();
+    match db::get_survey_template(&template_id) {
+        Ok(Some(template)) => Html(render_survey_page(PREVIEW_TOKEN, &template, None, None, true, lang)),
+        Ok(None) => Html(error_page(lang, t.template_not_found, "")),
+        Err(_) => Html(error_page(lang, t.server_error, t.retry_later)),
+    }
+}
+
+/// 설문 응답 인쇄용 화면 (차트에 첨부할 종이 사본)
+async fn staff_response_print_page(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let token = params.get("token").cloned().unwrap_or_default();
+    if !is_valid_staff_token(&state, &token) {
+        return Html(render_staff_login_page_with_error("로그인이 필요합니다."));
+    }
+
+    let lang = Lang::Ko;
+    let t = lang.strings();
+    let response = match db::get_survey_response_detail(&id) {
+        Ok(Some(response)) => response,
+        Ok(None) => return Html(error_page(lang, "응답을 찾을 수 없습니다", "")),
+        Err(_) => return Html(error_page(lang, t.server_error, t.retry_later)),
+    };
+    // 템플릿이 삭제된 경우 질문 ID 기준으로 표시
+    let questions = db::get_survey_template(&response.template_id)
+        .ok()
+        .flatten()
+        .map(|template| template.questions)
+        .unwrap_or_default();
+    let clinic_name = state.clinic_name();
+
+    Html(render_response_print_page(&clinic_name, &response, &questions))
+}
+
+/// 키오스크 안내문 인쇄 페이지 (직원용, A4)
+async fn staff_kiosk_poster_page(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let token = params.get("token").cloned().unwrap_or_default();
+    if !is_valid_staff_token(&state, &token) {
+        return Html(render_staff_login_page_with_error("로그인이 필요합니다."));
+    }
+
+    // 직원이 접속한 포트 기준 (localhost로 접속해도 QR에는 LAN 주소를 넣음)
+    let port = headers
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.rsplit_once(':'))
+        .and_then(|(_, port)| port.parse::<u16>().ok())
+        .unwrap_or(80);
+    let url = kiosk_url(port);
+    let qr = match render_qr_png(&url) {
+        Ok(png) => {
+            use base64::Engine;
+            format!("data:image/png;base64,{}", base64::engine::general_purpose::STANDARD.encode(png))
+        }
+        Err(_) => return Html(error_page(Lang::Ko, Lang::Ko.strings().server_error, Lang::Ko.strings().retry_later)),
+    };
+    let steps: String = KIOSK_POSTER_STEPS
+        .iter()
+        .map(|step| format!("<li>{}</li>", escape_html(step)))
+        .collect();
+
+    Html(render_template(
+        "kiosk_poster.html",
+        &[
+            ("clinic_name", &state.clinic_name()),
+            ("title", KIOSK_POSTER_TITLE),
+            ("qr_data_url", &qr),
+            ("kiosk_url", &url),
+            ("steps", &steps),
+        ],
+    ))
+}
+
+/// 설문 응답 PDF (직원용, 종이 차트 보관용 인쇄)
+async fn survey_response_pdf(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let token = params.get("token").cloned().unwrap_or_default();
+    if !is_valid_staff_token(&state, &token) {
+        return error_response(StatusCode::UNAUTHORIZED, "인증 필요");
+    }
+
+    match db::export_survey_response_pdf(&id) {
+        Ok(Some(pdf)) => (
+            [
+                (header::CONTENT_TYPE, "application/pdf".to_string()),
+                (header::CONTENT_DISPOSITION, format!("inline; filename=\"survey-response-{}.pdf\"", id)),
+            ],
+            pdf,
+        )
+            .into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "응답을 찾을 수 없습니다"),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 응답 목록 API
+async fn get_responses_api(
+    State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let token = params.get("token").cloned().unwrap_or_default();
+
+    // 세션 확인
+    let valid = is_valid_staff_token(&state, &token);
+
+    if !valid {
+        return error_response(StatusCode::UNAUTHORIZED, "인증 필요");
+    }
+
+    match db::list_survey_responses(Some(100)) {
+        Ok(responses) => Json(serde_json::json!({"responses": responses})).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 설문 세션 목록 API (`?status=pending&limit=`)
+async fn get_sessions_api(
+    State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let token = params.get("token").cloned().unwrap_or_default();
+    if !is_valid_staff_token(&state, &token) {
+        return error_response(StatusCode::UNAUTHORIZED, "인증 필요");
+    }
+
+    let status = params.get("status").map(|s| s.as_str());
+    let limit = params.get("limit").and_then(|l| l.parse::<i32>().ok()).unwrap_or(100);
+
+    match db::list_survey_sessions(None, status, Some(limit)) {
+        Ok(sessions) => Json(serde_json::json!({"sessions": sessions})).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 설문 세션 취소 API
+async fn cancel_session_api(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let token = params.get("token").cloned().unwrap_or_default();
+    if !is_valid_staff_token(&state, &token) {
+        return error_response(StatusCode::UNAUTHORIZED, "인증 필요");
+    }
+
+    match db::cancel_survey_session(&id) {
+        Ok(()) => Json(serde_json::json!({"success": true})).into_response(),
+        Err(crate::error::AppError::Custom(msg)) => error_response(StatusCode::CONFLICT, msg),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 설문 세션 만료 연장 요청
+#[derive(Deserialize)]
+struct ExtendSessionRequest {
+    /// 연장 시간 (미지정 시 24시간)
+    hours: Option<u32>,
+}
+
+/// 설문 세션 만료 연장 API
+async fn extend_session_api(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+    Json(payload): Json<ExtendSessionRequest>,
+) -> impl IntoResponse {
+    let token = params.get("token").cloned().unwrap_or_default();
+    if !is_valid_staff_token(&state, &token) {
+        return error_response(StatusCode::UNAUTHORIZED, "인증 필요");
+    }
+
+    let hours = payload.hours.unwrap_or(24).clamp(1, 24 * 30);
+    match db::extend_survey_session(&id, hours as i64) {
+        Ok(expires_at) => Json(serde_json::json!({"success": true, "expires_at": expires_at})).into_response(),
+        Err(crate::error::AppError::Custom(msg)) => error_response(StatusCode::CONFLICT, msg),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 대기 중인 세션의 링크 재발급 (기존 링크 무효화, 만료 시각 재설정)
+async fn regenerate_session_token_api(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let token = params.get("token").cloned().unwrap_or_default();
+    if !is_valid_staff_token(&state, &token) {
+        return error_response(StatusCode::UNAUTHORIZED, "인증 필요");
+    }
+
+    match db::regenerate_survey_session_token(&id) {
+        Ok(session) => {
+            log::info!("설문 세션 링크 재발급: {}", session.id);
+            Json(serde_json::json!({
+                "success": true,
+                "token": session.token,
+                "url": format!("/s/{}", session.token),
+                "session_id": session.id,
+                "expires_at": session.expires_at
+            })).into_response()
+        }
+        Err(crate::error::AppError::Custom(msg)) => error_response(StatusCode::CONFLICT, msg),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// QR 코드 이미지 (`?text=`, 직원용 - 링크 재전송/안내물)
+async fn qr_code_api(
+    State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let token = params.get("token").cloned().unwrap_or_default();
+    if !is_valid_staff_token(&state, &token) {
+        return error_response(StatusCode::UNAUTHORIZED, "인증 필요");
+    }
+    let Some(text) = params.get("text").filter(|t| !t.is_empty()) else {
+        return error_response(StatusCode::BAD_REQUEST, "text가 필요합니다");
+    };
+
+    match render_qr_png(text) {
+        Ok(png) => ([(header::CONTENT_TYPE, "image/png")], png).into_response(),
+        Err(e) => app_error_response(StatusCode::BAD_REQUEST, &e),
+    }
+}
+
+/// QR 코드 PNG 생성
+pub fn render_qr_png(text: &str) -> AppResult<Vec<u8>> {
+    use image::{ImageEncoder, Luma};
+
+    let code = qrcode::QrCode::new(text.as_bytes())
+        .map_err(|e| crate::error::AppError::Custom(format!("QR 코드 생성 실패: {}", e)))?;
+    let qr_image = code.render::<Luma<u8>>().build();
+
+    let mut png_data = Vec::new();
+    image::codecs::png::PngEncoder::new(&mut png_data)
+        .write_image(
+            qr_image.as_raw(),
+            qr_image.width(),
+            qr_image.height(),
+            image::ExtendedColorType::L8,
+        )
+        .map_err(|e| crate::error::AppError::Custom(format!("QR 이미지 인코딩 실패: {}", e)))?;
+    Ok(png_data)
+}
+
+/// 키오스크 안내문 제목
+const KIOSK_POSTER_TITLE: &str = "설문에 참여해주세요";
+
+/// 키오스크 안내문 참여 방법
+const KIOSK_POSTER_STEPS: [&str; 3] = [
+    "휴대폰을 한의원 와이파이에 연결해 주세요.",
+    "카메라로 아래 QR 코드를 비춘 뒤 표시되는 주소를 눌러 주세요.",
+    "안내에 따라 설문을 작성하고 제출해 주세요.",
+];
+
+/// LAN 주소 감지 (외부로 나가는 인터페이스 기준, 패킷은 보내지 않음)
+pub fn detect_local_ip() -> Option<String> {
+    use std::net::UdpSocket;
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("8.8.8.8:80").ok()?;
+    socket.local_addr().ok().map(|addr| addr.ip().to_string())
+}
+
+/// 태블릿/휴대폰에서 접속할 키오스크 주소
+pub fn kiosk_url(port: u16) -> String {
+    let host = detect_local_ip().unwrap_or_else(|| "localhost".to_string());
+    let scheme = url_scheme();
+    if (scheme == "http" && port == 80) || (scheme == "https" && port == 443) {
+        format!("{}://{}/patient", scheme, host)
+    } else {
+        format!("{}://{}:{}/patient", scheme, host, port)
+    }
+}
+
+/// 키오스크 안내문 PDF (A4, 인쇄용)
+pub fn render_kiosk_poster_pdf(port: u16) -> AppResult<Vec<u8>> {
+    let url = kiosk_url(port);
+    let mut doc = crate::pdf::PdfDocument::new(crate::pdf::load_cjk_font()?);
+
+    doc.space(30.0);
+    doc.centered_text(&load_clinic_name(), 18.0);
+    doc.space(10.0);
+    doc.centered_text(KIOSK_POSTER_TITLE, 32.0);
+    doc.space(30.0);
+    doc.qr_code(&url, 300.0)?;
+    doc.space(10.0);
+    doc.centered_text(&url, 11.0);
+    doc.space(30.0);
+    for (i, step) in KIOSK_POSTER_STEPS.iter().enumerate() {
+        doc.text(&format!("{}. {}", i + 1, step), 14.0, 40.0);
+    }
+    doc.finish()
+}
+
+/// 정적 파일 핸들러
+async fn static_handler(Path(path): Path<String>) -> impl IntoResponse {
+    // HTML 템플릿 원본은 치환 전이므로 그대로 내보내지 않음
+    if path.starts_with("templates/") {
+        return (StatusCode::NOT_FOUND, "Not Found").into_response();
+    }
+    match StaticAssets::get(&path) {
+        Some(content) => {
+            let mime = mime_guess::from_path(&path).first_or_octet_stream();
+            (
+                [(header::CONTENT_TYPE, mime.as_ref())],
+                content.data.into_owned(),
+            ).into_response()
+        }
+        None => (StatusCode::NOT_FOUND, "Not Found").into_response(),
+    }
+}
+
+/// HTML 템플릿 렌더링 (`static/templates/{name}`)
+///
+/// 템플릿의 `{{key}}` 자리는 HTML 이스케이프한 값으로, `{{{key}}}` 자리는 값 그대로
+/// 바꾼다. 원문 삽입은 서버에서 만든 HTML 조각이나 `script_json` 결과에만 쓴다.
+/// 한 번만 훑으므로 값 안에 `{{..}}`가 있어도 다시 치환하지 않고, 목록에 없는 키는 그대로 둔다.
+fn render_template(name: &str, vars: &[(&str, &str)]) -> String {
+    let Some(file) = StaticAssets::get(&format!("templates/{}", name)) else {
+        log::error!("HTML 템플릿을 찾을 수 없습니다: {}", name);
+        return String::new();
+    };
+    let source = String::from_utf8_lossy(&file.data);
+
+    let mut html = String::with_capacity(source.len());
+    let mut rest: &str = &source;
+    while let Some(start) = rest.find("{{") {
+        html.push_str(&rest[..start]);
+        let raw = rest[start..].starts_with("{{{");
+        let (open, close) = if raw { ("{{{", "}}}") } else { ("{{", "}}") };
+        let after = &rest[start + open.len()..];
+        match after.find(close).map(|end| (&after[..end], end)) {
+            Some((key, end)) => {
+                match vars.iter().find(|(k, _)| *k == key) {
+                    Some((_, value)) if raw => html.push_str(value),
+                    Some((_, value)) => html.push_str(&escape_html(value)),
+                    None => html.push_str(&rest[start..start + open.len() + end + close.len()]),
+                }
+                rest = &after[end + close.len()..];
+            }
+            None => {
+                html.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    html.push_str(rest);
+    html
+}
+
+/// `<script>` 안에 넣을 JSON (`</script>` 등으로 스크립트를 벗어나지 못하도록 `<`, `>`, `&` 이스케이프)
+fn script_json<T: serde::Serialize + ?Sized>(value: &T) -> String {
+    serde_json::to_string(value)
+        .unwrap_or_else(|_| "null".to_string())
+        .replace('<', "\\u003c")
+        .replace('>', "\\u003e")
+        .replace('&', "\\u0026")
+        .replace('\u{2028}', "\\u2028")
+        .replace('\u{2029}', "\\u2029")
+}
+
+// ============ 헬퍼 함수 ============
+
+/// HTML 본문/속성에 넣을 문자열 이스케이프
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl StaffSession {
+    /// 유휴 시간 초과 또는 최대 유지 기간 초과
+    fn is_expired(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        now - self.last_activity > chrono::Duration::hours(STAFF_SESSION_IDLE_HOURS)
+            || now - self.created_at > chrono::Duration::days(STAFF_SESSION_MAX_AGE_DAYS)
+    }
+
+    /// 만료 시각 (유휴 만료와 최대 유지 기간 중 빠른 쪽)
+    pub fn expires_at(&self) -> chrono::DateTime<chrono::Utc> {
+        (self.last_activity + chrono::Duration::hours(STAFF_SESSION_IDLE_HOURS))
+            .min(self.created_at + chrono::Duration::days(STAFF_SESSION_MAX_AGE_DAYS))
+    }
+
+    fn from_stored(token: &str, stored: db::WebSession) -> Self {
+        Self {
+            token: token.to_string(),
+            clinic_name: stored.clinic_name,
+            account_id: stored.account_id,
+            created_at: stored.created_at,
+            last_activity: stored.last_activity,
+            must_change_password: stored.must_change_password,
+            activity_saved_at: stored.last_activity,
+        }
+    }
+
+    fn to_stored(&self) -> db::WebSession {
+        db::WebSession {
+            clinic_name: self.clinic_name.clone(),
+            account_id: self.account_id.clone(),
+            must_change_password: self.must_change_password,
+            created_at: self.created_at,
+            last_activity: self.last_activity,
+        }
+    }
+}
+
+/// 유효한 직원 세션 조회 (캐시에 없으면 DB에서 읽음, 만료된 세션은 제거, 유효하면 마지막 요청 시각 갱신)
+fn active_staff_session(state: &AppState, token: &str) -> Option<StaffSession> {
+    if token.is_empty() {
+        return None;
+    }
+    let mut sessions = state.staff_sessions.lock().ok()?;
+    if !sessions.contains_key(token) {
+        let stored = db::get_web_session(token).ok().flatten()?;
+        sessions.insert(token.to_string(), StaffSession::from_stored(token, stored));
     }
 
-    (StatusCode::OK, Json(serde_json::json!({"success": true, "message": "설문이 제출되었습니다"})))
+    let now = chrono::Utc::now();
+    if sessions.get(token)?.is_expired(now) {
+        sessions.remove(token);
+        if let Err(e) = db::delete_web_session(token) {
+            log::warn!("만료된 직원 세션 삭제 실패: {}", e);
+        }
+        return None;
+    }
+    let session = sessions.get_mut(token)?;
+    session.last_activity = now;
+    if now - session.activity_saved_at >= chrono::Duration::minutes(STAFF_SESSION_ACTIVITY_SAVE_MINUTES) {
+        match db::touch_web_session(token, now) {
+            Ok(()) => session.activity_saved_at = now,
+            Err(e) => log::warn!("직원 세션 갱신 기록 실패: {}", e),
+        }
+    }
+    Some(session.clone())
 }
 
-/// 직원 로그인 페이지
-async fn staff_login_page() -> Html<String> {
-    let clinic_name = db::get_clinic_settings()
-        .ok()
-        .flatten()
-        .map(|s| s.clinic_name)
-        .unwrap_or_else(|| "한의원".to_string());
+/// 직원 세션에 연결된 계정 ID (계정 로그인이 아니거나 세션이 없으면 None)
+pub(crate) fn staff_session_account_id(state: &AppState, token: &str) -> Option<String> {
+    active_staff_session(state, token).and_then(|s| s.account_id)
+}
 
-    Html(render_staff_login_page(&clinic_name))
+/// 직원 세션 토큰 유효성 확인 (만료되었거나 비밀번호 변경이 필요한 세션은 무효)
+pub(crate) fn is_valid_staff_token(state: &AppState, token: &str) -> bool {
+    active_staff_session(state, token).is_some_and(|s| !s.must_change_password)
 }
 
-/// 직원 로그인 처리
-#[derive(Deserialize)]
-struct StaffLoginRequest {
-    clinic_name: String,
-    password: String,
+/// 임시 비밀번호로 로그인해 비밀번호 변경이 필요한 세션인지
+pub(crate) fn staff_token_requires_password_change(state: &AppState, token: &str) -> bool {
+    active_staff_session(state, token).is_some_and(|s| s.must_change_password)
 }
 
-async fn staff_login(
-    State(state): State<AppState>,
-    Json(payload): Json<StaffLoginRequest>,
-) -> impl IntoResponse {
-    // 한의원 이름 확인
-    let settings = match db::get_clinic_settings() {
-        Ok(Some(s)) => s,
-        _ => return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({"error": "설정을 찾을 수 없습니다"}))).into_response(),
-    };
+/// 직원 세션 토큰 재발급 (기존 토큰은 즉시 무효, 최대 유지 기간은 처음 로그인 기준 그대로)
+pub(crate) fn refresh_staff_session(state: &AppState, token: &str) -> Option<StaffSession> {
+    let current = active_staff_session(state, token).filter(|s| !s.must_change_password)?;
+    let refreshed = StaffSession { token: generate_session_token(), ..current };
 
-    if settings.clinic_name != payload.clinic_name {
-        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({"error": "한의원 이름이 일치하지 않습니다"}))).into_response();
+    let mut sessions = state.staff_sessions.lock().ok()?;
+    match db::rename_web_session(token, &refreshed.token) {
+        Ok(true) => {}
+        Ok(false) => return None,
+        Err(e) => {
+            log::warn!("직원 세션 재발급 실패: {}", e);
+            return None;
+        }
     }
+    sessions.remove(token)?;
+    sessions.insert(refreshed.token.clone(), refreshed.clone());
+    Some(refreshed)
+}
 
-    // 비밀번호 확인
-    match db::verify_staff_password(&payload.password) {
-        Ok(true) => {}
-        Ok(false) => return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({"error": "비밀번호가 일치하지 않습니다"}))).into_response(),
-        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": "서버 오류"}))).into_response(),
+/// 직원 세션 종료 (로그아웃)
+pub(crate) fn end_staff_session(state: &AppState, token: &str) {
+    if let Ok(mut sessions) = state.staff_sessions.lock() {
+        sessions.remove(token);
     }
+    if let Err(e) = db::delete_web_session(token) {
+        log::warn!("직원 세션 삭제 실패: {}", e);
+    }
+}
 
-    // 세션 생성
+/// 테스트용 직원 세션 생성 (로그인 없이 캐시에만 등록, 반환: 토큰)
+#[cfg(test)]
+pub(crate) fn insert_test_staff_session(state: &AppState, account_id: Option<&str>) -> String {
     let token = generate_session_token();
+    let now = chrono::Utc::now();
     let session = StaffSession {
         token: token.clone(),
-        clinic_name: settings.clinic_name,
-        created_at: chrono::Utc::now(),
+        clinic_name: String::new(),
+        account_id: account_id.map(str::to_string),
+        created_at: now,
+        last_activity: now,
+        must_change_password: false,
+        activity_saved_at: now,
     };
-
-    if let Ok(mut sessions) = state.staff_sessions.lock() {
-        sessions.insert(token.clone(), session);
-    }
-
-    Json(serde_json::json!({
-        "success": true,
-        "token": token,
-    })).into_response()
+    state.staff_sessions.lock().unwrap().insert(token.clone(), session);
+    token
 }
 
-/// 직원 대시보드
-async fn staff_dashboard(
-    State(state): State<AppState>,
-    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
-) -> impl IntoResponse {
-    let token = params.get("token").cloned().unwrap_or_default();
-
-    // 세션 확인
-    let session = {
-        let sessions = state.staff_sessions.lock().ok();
-        sessions.and_then(|s| s.get(&token).cloned())
+/// 계정의 직원 세션 종료 (`keep_token` 세션은 유지하고 변경 필요 표시만 해제)
+pub(crate) fn revoke_staff_account_sessions(state: &AppState, account_id: &str, keep_token: Option<&str>) {
+    if let Err(e) = db::delete_account_web_sessions(account_id, keep_token) {
+        log::warn!("직원 계정 세션 삭제 실패: {}", e);
+    }
+    let Ok(mut sessions) = state.staff_sessions.lock() else {
+        return;
     };
+    sessions.retain(|token, session| {
+        session.account_id.as_deref() != Some(account_id) || Some(token.as_str()) == keep_token
+    });
+    if let Some(session) = keep_token.and_then(|t| sessions.get_mut(t)) {
+        session.must_change_password = false;
+    }
+}
 
-    // 온라인 설문 기능 활성화 여부
-    let survey_external = {
-        state.survey_external_enabled.lock().ok()
-            .map(|v| *v)
-            .unwrap_or(false)
-    };
+/// 설문 세션 생성 기록용 직원 식별자
+/// 계정 로그인 세션은 계정 ID, 공용 비밀번호 세션은 토큰 해시 (토큰 원문이나 일부는 남기지 않음)
+fn session_staff_identifier(state: &AppState, staff_token: &str) -> String {
+    use sha2::{Digest, Sha256};
+    match staff_session_account_id(state, staff_token) {
+        Some(account_id) => account_id,
+        None => format!("session:{}", &format!("{:x}", Sha256::digest(staff_token.as_bytes()))[..16]),
+    }
+}
 
-    match session {
-        Some(s) => {
-            // 24시간 유효
-            if chrono::Utc::now().signed_duration_since(s.created_at).num_hours() > 24 {
-                return Html(render_staff_login_page_with_error("세션이 만료되었습니다. 다시 로그인해주세요."));
+/// 설문 세션 생성 기록 및 과다 생성 탐지 (임계치를 처음 넘는 시점에 한 번만 알림)
+fn record_session_creation(
+    state: &AppState,
+    request_id: &RequestId,
+    session_id: &str,
+    staff_token: &str,
+    addr: SocketAddr,
+) {
+    let staff_id = session_staff_identifier(state, staff_token);
+    let ip = addr.ip().to_string();
+    let threshold = state.session_abuse_threshold;
+
+    match db::log_survey_session_creation(session_id, Some(&staff_id), Some(&ip), threshold.window_minutes) {
+        Ok((by_staff, by_ip)) => {
+            if by_staff == threshold.max_sessions + 1 {
+                let _ = db::notify_survey_session_abuse(&format!("직원 {}", staff_id), by_staff, threshold.window_minutes);
+            }
+            if by_ip == threshold.max_sessions + 1 {
+                let _ = db::notify_survey_session_abuse(&format!("IP {}", ip), by_ip, threshold.window_minutes);
             }
-            Html(render_staff_dashboard(&s.clinic_name, &token, survey_external))
         }
-        None => Html(render_staff_login_page_with_error("로그인이 필요합니다.")),
+        Err(e) => log::warn!("[{}] 설문 세션 생성 기록 실패: {}", request_id, e),
     }
 }
 
-/// 응답 목록 API
-async fn get_responses_api(
-    State(state): State<AppState>,
-    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
-) -> impl IntoResponse {
-    let token = params.get("token").cloned().unwrap_or_default();
-
-    // 세션 확인
-    let valid = {
-        let sessions = state.staff_sessions.lock().ok();
-        sessions.map(|s| s.contains_key(&token)).unwrap_or(false)
-    };
+/// `AppError` JSON 응답 (`AppErrorResponse`, `code`는 오류 종류 - `AppError::code` 참고)
+pub(crate) fn app_error_response(status: StatusCode, err: &AppError) -> Response {
+    (status, Json(err.to_response())).into_response()
+}
 
-    if !valid {
-        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({"error": "인증 필요"}))).into_response();
-    }
+/// 처리기에서 바로 거부하는 JSON 오류 응답 (`code`는 상태 코드 기준 - `status_error_code` 참고)
+pub(crate) fn error_response(status: StatusCode, message: impl Into<String>) -> Response {
+    (status, Json(AppErrorResponse::for_status(status, message))).into_response()
+}
 
-    match db::list_survey_responses(Some(100)) {
-        Ok(responses) => Json(serde_json::json!({"responses": responses})).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": e.to_string()}))).into_response(),
-    }
+/// 상태 코드만으로 구분할 수 없는 오류 응답 (예: 생년월일 확인 필요/불일치)
+pub(crate) fn coded_error_response(status: StatusCode, code: &'static str, message: impl Into<String>) -> Response {
+    (status, Json(AppErrorResponse { code, message: message.into() })).into_response()
 }
 
-/// 정적 파일 핸들러
-async fn static_handler(Path(path): Path<String>) -> impl IntoResponse {
-    match StaticAssets::get(&path) {
-        Some(content) => {
-            let mime = mime_guess::from_path(&path).first_or_octet_stream();
-            (
-                [(header::CONTENT_TYPE, mime.as_ref())],
-                content.data.into_owned(),
-            ).into_response()
-        }
-        None => (StatusCode::NOT_FOUND, "Not Found").into_response(),
+/// API 경로의 JSON이 아닌 오류 응답(본문 크기 초과, 잘못된 JSON 등 추출기 거부, 없는 경로)을
+/// `AppErrorResponse` 형식으로 바꿈 (헤더는 유지)
+async fn normalize_api_errors(request: Request, next: Next) -> Response {
+    let is_api = request.uri().path().starts_with("/api/");
+    let response = next.run(request).await;
+    let status = response.status();
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    if !is_api || is_json || !(status.is_client_error() || status.is_server_error()) {
+        return response;
     }
-}
 
-// ============ 헬퍼 함수 ============
+    let (mut parts, body) = response.into_parts();
+    let text = axum::body::to_bytes(body, 64 * 1024)
+        .await
+        .map(|b| String::from_utf8_lossy(&b).trim().to_string())
+        .unwrap_or_default();
+    let message = if text.is_empty() { status.canonical_reason().unwrap_or_default().to_string() } else { text };
+    let body = serde_json::to_vec(&AppErrorResponse::for_status(status, message)).unwrap_or_default();
+    parts.headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, axum::body::Body::from(body))
+}
 
 fn generate_session_token() -> String {
     use rand::Rng;
@@ -398,281 +1907,83 @@ fn generate_session_token() -> String {
         .collect()
 }
 
-fn error_page(title: &str, message: &str) -> String {
-    format!(r#"<!DOCTYPE html>
-<html lang="ko">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>오류</title>
-    <style>
-        * {{ margin: 0; padding: 0; box-sizing: border-box; }}
-        body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif; background: #f5f5f5; min-height: 100vh; display: flex; align-items: center; justify-content: center; }}
-        .container {{ background: white; padding: 3rem; border-radius: 1rem; box-shadow: 0 4px 6px rgba(0,0,0,0.1); text-align: center; max-width: 400px; }}
-        .icon {{ font-size: 4rem; margin-bottom: 1rem; }}
-        h1 {{ color: #333; margin-bottom: 0.5rem; font-size: 1.5rem; }}
-        p {{ color: #666; }}
-    </style>
-</head>
-<body>
-    <div class="container">
-        <div class="icon">❌</div>
-        <h1>{}</h1>
-        <p>{}</p>
-    </div>
-</body>
-</html>"#, title, message)
-}
-
-fn render_survey_page(token: &str, template: &db::SurveyTemplateDb, respondent_name: Option<&str>) -> String {
-    let questions_json = serde_json::to_string(&template.questions).unwrap_or_default();
+fn error_page(lang: Lang, title: &str, message: &str) -> String {
+    render_template(
+        "error.html",
+        &[
+            ("lang", lang.code()),
+            ("page_title", lang.strings().error),
+            ("title", title),
+            ("message", message),
+        ],
+    )
+}
+
+/// 생년월일 확인 페이지 (본인 확인 후 설문 시작)
+fn render_birth_date_page(token: &str, lang: Lang) -> String {
+    let t = lang.strings();
+    render_template(
+        "birth_date.html",
+        &[
+            ("lang", lang.code()),
+            ("title", t.verify_title),
+            ("prompt", t.verify_prompt),
+            ("confirm", t.confirm),
+            ("token", &script_json(token)),
+            ("strings", &script_json(t)),
+        ],
+    )
+}
+
+/// `preview`이면 상단에 미리보기 배너를 표시하고 제출하지 않음
+fn render_survey_page(
+    token: &str,
+    template: &db::SurveyTemplateDb,
+    respondent_name: Option<&str>,
+    dob: Option<&str>,
+    preview: bool,
+    lang: Lang,
+) -> String {
+    let t = lang.strings();
+    // 본인 확인을 거친 경우 제출 시에도 생년월일을 함께 전송
+    let submit_query = dob
+        .map(|d| format!("?dob={}", d.chars().filter(|c| c.is_ascii_digit() || *c == '-').collect::<String>()))
+        .unwrap_or_default();
+    let questions_json = script_json(&template.questions);
     let display_mode = template.display_mode.as_deref().unwrap_or("one_by_one");
-    let _name = respondent_name.unwrap_or("");
-
-    format!(r#"<!DOCTYPE html>
-<html lang="ko">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>{} - 설문</title>
-    <style>
-        * {{ margin: 0; padding: 0; box-sizing: border-box; }}
-        body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif; background: #f5f5f5; min-height: 100vh; padding: 1rem; }}
-        .container {{ max-width: 600px; margin: 0 auto; }}
-        .card {{ background: white; border-radius: 1rem; box-shadow: 0 2px 4px rgba(0,0,0,0.1); padding: 1.5rem; margin-bottom: 1rem; }}
-        h1 {{ color: #333; font-size: 1.5rem; margin-bottom: 0.5rem; }}
-        .description {{ color: #666; margin-bottom: 1rem; }}
-        .question {{ margin-bottom: 1.5rem; }}
-        .question-text {{ font-weight: 600; margin-bottom: 0.75rem; color: #333; }}
-        .required {{ color: #ef4444; }}
-        .options {{ display: grid; grid-template-columns: repeat(2, 1fr); gap: 0.5rem; }}
-        .option {{ padding: 0.75rem 1rem; border: 2px solid #e5e7eb; border-radius: 0.5rem; cursor: pointer; transition: all 0.2s; text-align: center; font-size: 0.9rem; }}
-        .option:hover {{ border-color: #4f46e5; background: #f5f3ff; }}
-        .option.selected {{ border-color: #4f46e5; background: #4f46e5; color: white; }}
-        .option-multi.selected {{ border-color: #4f46e5; background: #eef2ff; color: #4f46e5; }}
-        input[type="text"], textarea {{ width: 100%; padding: 0.75rem; border: 2px solid #e5e7eb; border-radius: 0.5rem; font-size: 1rem; }}
-        input[type="text"]:focus, textarea:focus {{ outline: none; border-color: #4f46e5; }}
-        .scale-container {{ display: flex; gap: 0.5rem; flex-wrap: wrap; }}
-        .scale-btn {{ flex: 1; min-width: 40px; padding: 0.75rem; border: 2px solid #e5e7eb; border-radius: 0.5rem; cursor: pointer; text-align: center; font-weight: 600; }}
-        .scale-btn:hover {{ border-color: #4f46e5; }}
-        .scale-btn.selected {{ border-color: #4f46e5; background: #4f46e5; color: white; }}
-        .scale-labels {{ display: flex; justify-content: space-between; margin-top: 0.5rem; font-size: 0.875rem; color: #666; }}
-        .nav-buttons {{ display: flex; gap: 1rem; margin-top: 1.5rem; }}
-        .btn {{ flex: 1; padding: 1rem; border: none; border-radius: 0.5rem; font-size: 1rem; font-weight: 600; cursor: pointer; }}
-        .btn-primary {{ background: #4f46e5; color: white; }}
-        .btn-primary:hover {{ background: #4338ca; }}
-        .btn-secondary {{ background: #e5e7eb; color: #374151; }}
-        .btn-secondary:hover {{ background: #d1d5db; }}
-        .btn:disabled {{ opacity: 0.5; cursor: not-allowed; }}
-        .progress {{ height: 4px; background: #e5e7eb; border-radius: 2px; margin-bottom: 1rem; }}
-        .progress-bar {{ height: 100%; background: #4f46e5; border-radius: 2px; transition: width 0.3s; }}
-        .success {{ text-align: center; padding: 3rem; }}
-        .success-icon {{ font-size: 4rem; margin-bottom: 1rem; }}
-        .hidden {{ display: none; }}
-    </style>
-</head>
-<body>
-    <div class="container">
-        <div class="card" id="survey-form">
-            <h1>{}</h1>
-            <p class="description">{}</p>
-            <div class="progress"><div class="progress-bar" id="progress-bar"></div></div>
-            <div id="questions-container"></div>
-            <div class="nav-buttons">
-                <button class="btn btn-secondary" id="prev-btn" onclick="prevQuestion()">이전</button>
-                <button class="btn btn-primary" id="next-btn" onclick="nextQuestion()">다음</button>
-            </div>
-        </div>
-        <div class="card success hidden" id="success-card">
-            <div class="success-icon">✅</div>
-            <h1>설문이 제출되었습니다</h1>
-            <p>감사합니다.</p>
-        </div>
-    </div>
-    <script>
-        const token = '{}';
-        const questions = {};
-        const displayMode = '{}';
-        const answers = {{}};
-        let currentIndex = 0;
-
-        function init() {{
-            renderQuestions();
-            updateNavigation();
-        }}
-
-        function renderQuestions() {{
-            const container = document.getElementById('questions-container');
-            container.innerHTML = '';
-
-            if (displayMode === 'one_by_one') {{
-                const q = questions[currentIndex];
-                container.appendChild(createQuestionElement(q, currentIndex));
-            }} else {{
-                questions.forEach((q, i) => {{
-                    container.appendChild(createQuestionElement(q, i));
-                }});
-            }}
-        }}
-
-        function createQuestionElement(q, index) {{
-            const div = document.createElement('div');
-            div.className = 'question';
-            div.innerHTML = `<div class="question-text">Q${{index + 1}}. ${{q.question_text}} ${{q.required ? '<span class="required">*</span>' : ''}}</div>`;
-
-            if (q.question_type === 'single_choice' && q.options) {{
-                const optionsDiv = document.createElement('div');
-                optionsDiv.className = 'options';
-                q.options.forEach(opt => {{
-                    const optDiv = document.createElement('div');
-                    optDiv.className = 'option' + (answers[q.id] === opt ? ' selected' : '');
-                    optDiv.textContent = opt;
-                    optDiv.onclick = () => selectOption(q.id, opt, optDiv);
-                    optionsDiv.appendChild(optDiv);
-                }});
-                div.appendChild(optionsDiv);
-            }} else if (q.question_type === 'multiple_choice' && q.options) {{
-                const optionsDiv = document.createElement('div');
-                optionsDiv.className = 'options';
-                q.options.forEach(opt => {{
-                    const optDiv = document.createElement('div');
-                    const selected = (answers[q.id] || []).includes(opt);
-                    optDiv.className = 'option option-multi' + (selected ? ' selected' : '');
-                    optDiv.textContent = opt;
-                    optDiv.onclick = () => selectMultiOption(q.id, opt, optDiv);
-                    optionsDiv.appendChild(optDiv);
-                }});
-                div.appendChild(optionsDiv);
-            }} else if (q.question_type === 'text') {{
-                const input = document.createElement('textarea');
-                input.rows = 3;
-                input.placeholder = '답변을 입력하세요';
-                input.value = answers[q.id] || '';
-                input.oninput = (e) => {{ answers[q.id] = e.target.value; }};
-                div.appendChild(input);
-            }} else if (q.question_type === 'scale' && q.scale_config) {{
-                const scaleDiv = document.createElement('div');
-                scaleDiv.className = 'scale-container';
-                for (let i = q.scale_config.min; i <= q.scale_config.max; i++) {{
-                    const btn = document.createElement('div');
-                    btn.className = 'scale-btn' + (answers[q.id] === i ? ' selected' : '');
-                    btn.textContent = i;
-                    btn.onclick = () => selectScale(q.id, i, scaleDiv);
-                    scaleDiv.appendChild(btn);
-                }}
-                div.appendChild(scaleDiv);
-                if (q.scale_config.minLabel || q.scale_config.maxLabel) {{
-                    const labels = document.createElement('div');
-                    labels.className = 'scale-labels';
-                    labels.innerHTML = `<span>${{q.scale_config.minLabel || ''}}</span><span>${{q.scale_config.maxLabel || ''}}</span>`;
-                    div.appendChild(labels);
-                }}
-            }}
-
-            return div;
-        }}
-
-        function selectOption(qId, value, element) {{
-            answers[qId] = value;
-            element.parentElement.querySelectorAll('.option').forEach(el => el.classList.remove('selected'));
-            element.classList.add('selected');
-        }}
-
-        function selectMultiOption(qId, value, element) {{
-            if (!answers[qId]) answers[qId] = [];
-            const idx = answers[qId].indexOf(value);
-            if (idx >= 0) {{
-                answers[qId].splice(idx, 1);
-                element.classList.remove('selected');
-            }} else {{
-                answers[qId].push(value);
-                element.classList.add('selected');
-            }}
-        }}
-
-        function selectScale(qId, value, container) {{
-            answers[qId] = value;
-            container.querySelectorAll('.scale-btn').forEach(el => el.classList.remove('selected'));
-            event.target.classList.add('selected');
-        }}
-
-        function updateNavigation() {{
-            const prevBtn = document.getElementById('prev-btn');
-            const nextBtn = document.getElementById('next-btn');
-            const progressBar = document.getElementById('progress-bar');
-
-            if (displayMode === 'one_by_one') {{
-                prevBtn.classList.toggle('hidden', currentIndex === 0);
-                nextBtn.textContent = currentIndex === questions.length - 1 ? '제출하기' : '다음';
-                progressBar.style.width = ((currentIndex + 1) / questions.length * 100) + '%';
-            }} else {{
-                prevBtn.classList.add('hidden');
-                nextBtn.textContent = '제출하기';
-                progressBar.style.width = '100%';
-            }}
-        }}
-
-        function prevQuestion() {{
-            if (currentIndex > 0) {{
-                currentIndex--;
-                renderQuestions();
-                updateNavigation();
-            }}
-        }}
-
-        function nextQuestion() {{
-            if (displayMode === 'one_by_one' && currentIndex < questions.length - 1) {{
-                currentIndex++;
-                renderQuestions();
-                updateNavigation();
-            }} else {{
-                submitSurvey();
-            }}
-        }}
-
-        async function submitSurvey() {{
-            // 필수 질문 확인
-            for (const q of questions) {{
-                if (q.required) {{
-                    const ans = answers[q.id];
-                    if (ans === undefined || ans === '' || (Array.isArray(ans) && ans.length === 0)) {{
-                        alert(`"${{q.question_text}}" 질문에 답변해주세요.`);
-                        return;
-                    }}
-                }}
-            }}
-
-            const answerArray = Object.entries(answers).map(([question_id, answer]) => ({{ question_id, answer }}));
-
-            try {{
-                const res = await fetch('/api/survey/' + token, {{
-                    method: 'POST',
-                    headers: {{ 'Content-Type': 'application/json' }},
-                    body: JSON.stringify({{ answers: answerArray }})
-                }});
-
-                if (res.ok) {{
-                    document.getElementById('survey-form').classList.add('hidden');
-                    document.getElementById('success-card').classList.remove('hidden');
-                }} else {{
-                    const data = await res.json();
-                    alert(data.error || '제출에 실패했습니다.');
-                }}
-            }} catch (e) {{
-                alert('네트워크 오류가 발생했습니다.');
-            }}
-        }}
-
-        init();
-    </script>
-</body>
-</html>"#,
-        template.name,
-        template.name,
-        template.description.as_deref().unwrap_or(""),
-        token,
-        questions_json,
-        display_mode
+    let preview_banner = if preview {
+        r#"<div class="preview-banner">👀 미리보기 - 환자에게 보이는 화면이며 응답은 제출되지 않습니다</div>"#
+    } else {
+        ""
+    };
+
+    render_template(
+        "survey.html",
+        &[
+            ("lang", lang.code()),
+            ("clinic_logo", &clinic_logo_html()),
+            ("survey", t.survey),
+            ("template_name", &template.name),
+            ("preview_banner", preview_banner),
+            ("description", template.description.as_deref().unwrap_or("")),
+            ("prev", t.prev),
+            ("next", t.next),
+            ("submitted", t.submitted),
+            ("thanks", t.thanks),
+            ("token", &script_json(token)),
+            ("submit_query", &script_json(&submit_query)),
+            ("is_preview", if preview { "true" } else { "false" }),
+            ("strings", &script_json(t)),
+            ("questions_json", &questions_json),
+            ("display_mode", &script_json(display_mode)),
+            ("require_contact", if template.require_contact { "true" } else { "false" }),
+            ("contact_title", t.contact_title),
+            ("patient_name", t.patient_name),
+            ("name_placeholder", t.name_placeholder),
+            ("respondent_name", respondent_name.unwrap_or("")),
+            ("phone", t.phone),
+            ("birth_date", t.birth_date),
+        ],
     )
 }
 
@@ -685,70 +1996,9 @@ fn render_staff_login_page_with_error(error: &str) -> String {
 }
 
 fn render_staff_login_page_inner(clinic_name: &str, error: Option<&str>) -> String {
-    let error_html = error.map(|e| format!(r#"<div class="error">{}</div>"#, e)).unwrap_or_default();
-
-    format!(r#"<!DOCTYPE html>
-<html lang="ko">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>직원 로그인</title>
-    <style>
-        * {{ margin: 0; padding: 0; box-sizing: border-box; }}
-        body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif; background: #f5f5f5; min-height: 100vh; display: flex; align-items: center; justify-content: center; }}
-        .container {{ background: white; padding: 2rem; border-radius: 1rem; box-shadow: 0 4px 6px rgba(0,0,0,0.1); width: 100%; max-width: 400px; }}
-        h1 {{ color: #333; margin-bottom: 1.5rem; text-align: center; }}
-        .form-group {{ margin-bottom: 1rem; }}
-        label {{ display: block; margin-bottom: 0.5rem; font-weight: 500; color: #374151; }}
-        input {{ width: 100%; padding: 0.75rem; border: 2px solid #e5e7eb; border-radius: 0.5rem; font-size: 1rem; }}
-        input:focus {{ outline: none; border-color: #4f46e5; }}
-        button {{ width: 100%; padding: 1rem; background: #4f46e5; color: white; border: none; border-radius: 0.5rem; font-size: 1rem; font-weight: 600; cursor: pointer; margin-top: 1rem; }}
-        button:hover {{ background: #4338ca; }}
-        .error {{ background: #fef2f2; color: #dc2626; padding: 0.75rem; border-radius: 0.5rem; margin-bottom: 1rem; text-align: center; }}
-    </style>
-</head>
-<body>
-    <div class="container">
-        <h1>🔐 직원 로그인</h1>
-        {}
-        <form onsubmit="login(event)">
-            <div class="form-group">
-                <label for="clinic_name">한의원 이름</label>
-                <input type="text" id="clinic_name" name="clinic_name" required placeholder="한의원 이름을 입력하세요" value="{}">
-            </div>
-            <div class="form-group">
-                <label for="password">직원 비밀번호</label>
-                <input type="password" id="password" name="password" required placeholder="비밀번호를 입력하세요">
-            </div>
-            <button type="submit">로그인</button>
-        </form>
-    </div>
-    <script>
-        async function login(e) {{
-            e.preventDefault();
-            const clinic_name = document.getElementById('clinic_name').value;
-            const password = document.getElementById('password').value;
-
-            try {{
-                const res = await fetch('/staff/login', {{
-                    method: 'POST',
-                    headers: {{ 'Content-Type': 'application/json' }},
-                    body: JSON.stringify({{ clinic_name, password }})
-                }});
-
-                const data = await res.json();
-                if (data.success) {{
-                    window.location.href = '/staff/dashboard?token=' + data.token;
-                }} else {{
-                    alert(data.error || '로그인에 실패했습니다.');
-                }}
-            }} catch (e) {{
-                alert('네트워크 오류가 발생했습니다.');
-            }}
-        }}
-    </script>
-</body>
-</html>"#, error_html, clinic_name)
+    let error_html = error.map(|e| format!(r#"<div class="error">{}</div>"#, escape_html(e))).unwrap_or_default();
+
+    render_template("staff_login.html", &[("error_html", &error_html), ("clinic_name", clinic_name)])
 }
 
 fn render_staff_dashboard(clinic_name: &str, token: &str, survey_external: bool) -> String {
@@ -759,263 +2009,150 @@ fn render_staff_dashboard(clinic_name: &str, token: &str, survey_external: bool)
         ""
     };
 
-    format!(r#"<!DOCTYPE html>
-<html lang="ko">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>{} - 설문 결과</title>
-    <style>
-        * {{ margin: 0; padding: 0; box-sizing: border-box; }}
-        body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif; background: #f5f5f5; min-height: 100vh; }}
-        .header {{ background: white; padding: 1rem 2rem; box-shadow: 0 1px 3px rgba(0,0,0,0.1); display: flex; justify-content: space-between; align-items: center; }}
-        .header h1 {{ font-size: 1.25rem; color: #333; }}
-        .header-actions {{ display: flex; gap: 1rem; align-items: center; }}
-        .btn-online {{ padding: 0.5rem 1rem; background: #7c3aed; color: white; border: none; border-radius: 0.5rem; font-weight: 600; cursor: pointer; }}
-        .btn-online:hover {{ background: #6d28d9; }}
-        .logout {{ color: #666; text-decoration: none; }}
-        .logout:hover {{ color: #333; }}
-        .container {{ max-width: 1200px; margin: 2rem auto; padding: 0 1rem; }}
-        .card {{ background: white; border-radius: 0.5rem; box-shadow: 0 1px 3px rgba(0,0,0,0.1); overflow: hidden; }}
-        .card-header {{ padding: 1rem 1.5rem; border-bottom: 1px solid #e5e7eb; font-weight: 600; }}
-        table {{ width: 100%; border-collapse: collapse; }}
-        th, td {{ padding: 1rem; text-align: left; border-bottom: 1px solid #e5e7eb; }}
-        th {{ background: #f9fafb; font-weight: 600; color: #374151; }}
-        tr:hover {{ background: #f9fafb; }}
-        .badge {{ display: inline-block; padding: 0.25rem 0.75rem; border-radius: 1rem; font-size: 0.875rem; }}
-        .badge-blue {{ background: #dbeafe; color: #1d4ed8; }}
-        .empty {{ text-align: center; padding: 3rem; color: #666; }}
-        .loading {{ text-align: center; padding: 2rem; }}
-        .modal {{ display: none; position: fixed; top: 0; left: 0; width: 100%; height: 100%; background: rgba(0,0,0,0.5); align-items: center; justify-content: center; z-index: 1000; }}
-        .modal.show {{ display: flex; }}
-        .modal-content {{ background: white; padding: 2rem; border-radius: 1rem; max-width: 500px; width: 90%; }}
-        .modal-header {{ display: flex; justify-content: space-between; align-items: center; margin-bottom: 1.5rem; }}
-        .modal-close {{ background: none; border: none; font-size: 1.5rem; cursor: pointer; color: #666; }}
-        .modal-close:hover {{ color: #333; }}
-        .form-group {{ margin-bottom: 1rem; }}
-        .form-group label {{ display: block; margin-bottom: 0.5rem; font-weight: 600; color: #374151; }}
-        .form-group select, .form-group input {{ width: 100%; padding: 0.75rem; border: 2px solid #e5e7eb; border-radius: 0.5rem; font-size: 1rem; }}
-        .btn-submit {{ width: 100%; padding: 1rem; background: #7c3aed; color: white; border: none; border-radius: 0.5rem; font-weight: 600; cursor: pointer; margin-top: 1rem; }}
-        .btn-submit:hover {{ background: #6d28d9; }}
-        .result-box {{ margin-top: 1rem; padding: 1rem; background: #f0fdf4; border: 1px solid #22c55e; border-radius: 0.5rem; }}
-        .result-url {{ word-break: break-all; font-family: monospace; padding: 0.5rem; background: white; border-radius: 0.25rem; margin-top: 0.5rem; }}
-    </style>
-</head>
-<body>
-    <div class="header">
-        <h1>📊 {} - 설문 결과</h1>
-        <div class="header-actions">
-            {}
-            <a href="/staff" class="logout">로그아웃</a>
-        </div>
-    </div>
-    <div class="container">
-        <div class="card">
-            <div class="card-header">최근 설문 응답</div>
-            <div id="responses-container">
-                <div class="loading">로딩 중...</div>
-            </div>
-        </div>
-    </div>
-    <script>
-        const token = '{}';
-
-        async function loadResponses() {{
-            try {{
-                const res = await fetch('/api/responses?token=' + token);
-                const data = await res.json();
-
-                const container = document.getElementById('responses-container');
-                if (!data.responses || data.responses.length === 0) {{
-                    container.innerHTML = '<div class="empty">설문 응답이 없습니다.</div>';
-                    return;
-                }}
-
-                let html = `<table>
-                    <thead>
-                        <tr>
-                            <th>응답자</th>
-                            <th>설문</th>
-                            <th>제출일시</th>
-                            <th>답변 수</th>
-                        </tr>
-                    </thead>
-                    <tbody>`;
-
-                data.responses.forEach(r => {{
-                    const name = r.patient_name || r.respondent_name || '익명';
-                    const template = r.template_name || '알 수 없음';
-                    const date = new Date(r.submitted_at).toLocaleString('ko-KR');
-                    const count = r.answers ? r.answers.length : 0;
-
-                    html += `<tr>
-                        <td>${{name}}</td>
-                        <td><span class="badge badge-blue">${{template}}</span></td>
-                        <td>${{date}}</td>
-                        <td>${{count}}개</td>
-                    </tr>`;
-                }});
-
-                html += '</tbody></table>';
-                container.innerHTML = html;
-            }} catch (e) {{
-                document.getElementById('responses-container').innerHTML = '<div class="empty">데이터를 불러올 수 없습니다.</div>';
-            }}
-        }}
-
-        loadResponses();
-
-        // 온라인 링크 모달 관련 함수들
-        function showOnlineLinkModal() {{
-            loadTemplatesForModal();
-            document.getElementById('online-link-modal').classList.add('show');
-        }}
-
-        function closeOnlineLinkModal() {{
-            document.getElementById('online-link-modal').classList.remove('show');
-            document.getElementById('online-result').style.display = 'none';
-        }}
-
-        async function loadTemplatesForModal() {{
-            try {{
-                const res = await fetch('/api/templates?token=' + token);
-                const data = await res.json();
-                const select = document.getElementById('modal-template');
-                select.innerHTML = '<option value="">템플릿을 선택하세요</option>';
-
-                if (data.templates && data.templates.length > 0) {{
-                    data.templates.forEach(t => {{
-                        const option = document.createElement('option');
-                        option.value = t.id;
-                        option.textContent = t.name;
-                        select.appendChild(option);
-                    }});
-                }}
-            }} catch (e) {{
-                console.error('템플릿 로드 실패:', e);
-            }}
-        }}
-
-        async function createOnlineLink() {{
-            const templateId = document.getElementById('modal-template').value;
-            const patientName = document.getElementById('modal-patient-name').value;
-            const chartNumber = document.getElementById('modal-chart-number').value;
-            const gender = document.getElementById('modal-gender').value;
-            const age = document.getElementById('modal-age').value;
-
-            if (!templateId) {{
-                alert('템플릿을 선택하세요');
-                return;
-            }}
-
-            try {{
-                const res = await fetch('/api/staff/create-online-session?token=' + token, {{
-                    method: 'POST',
-                    headers: {{ 'Content-Type': 'application/json' }},
-                    body: JSON.stringify({{
-                        template_id: templateId,
-                        respondent_name: patientName || null,
-                        patient_name: patientName || null,
-                        chart_number: chartNumber || null,
-                        patient_age: age || null,
-                        patient_gender: gender || null
-                    }})
-                }});
-
-                const data = await res.json();
-                if (data.success) {{
-                    document.getElementById('online-url-text').textContent = data.url;
-                    document.getElementById('online-result').style.display = 'block';
-                }} else {{
-                    alert(data.error || '생성 실패');
-                }}
-            }} catch (e) {{
-                alert('네트워크 오류');
-            }}
-        }}
-
-        function copyOnlineUrl() {{
-            const url = document.getElementById('online-url-text').textContent;
-            if (navigator.clipboard && window.isSecureContext) {{
-                navigator.clipboard.writeText(url).then(() => {{
-                    alert('복사되었습니다');
-                }}).catch(() => {{
-                    fallbackCopy(url);
-                }});
-            }} else {{
-                fallbackCopy(url);
-            }}
-        }}
-
-        function fallbackCopy(text) {{
-            const textarea = document.createElement('textarea');
-            textarea.value = text;
-            textarea.style.position = 'fixed';
-            textarea.style.opacity = '0';
-            document.body.appendChild(textarea);
-            textarea.select();
-            try {{
-                document.execCommand('copy');
-                alert('복사되었습니다');
-            }} catch {{
-                prompt('URL을 복사하세요:', text);
-            }}
-            document.body.removeChild(textarea);
-        }}
-    </script>
-
-    <!-- 온라인 링크 생성 모달 -->
-    <div class="modal" id="online-link-modal">
-        <div class="modal-content">
-            <div class="modal-header">
-                <h2>🌐 온라인 설문 링크 생성</h2>
-                <button class="modal-close" onclick="closeOnlineLinkModal()">&times;</button>
-            </div>
-            <div class="form-group">
-                <label for="modal-template">설문 템플릿</label>
-                <select id="modal-template">
-                    <option value="">템플릿을 선택하세요</option>
-                </select>
-            </div>
-            <div class="form-group">
-                <label for="modal-patient-name">환자 이름 (선택)</label>
-                <input type="text" id="modal-patient-name" placeholder="홍길동">
-            </div>
-            <div style="border:1px solid #e5e7eb;border-radius:0.5rem;padding:1rem;background:#f9fafb;margin-bottom:1rem;">
-                <p style="font-weight:600;color:#374151;margin-bottom:0.75rem;font-size:0.875rem;">추가 정보 (선택)</p>
-                <div style="display:grid;grid-template-columns:1fr 1fr 1fr;gap:0.5rem;">
-                    <div class="form-group" style="margin-bottom:0;">
-                        <label for="modal-chart-number" style="font-size:0.75rem;color:#6b7280;">차트번호</label>
-                        <input type="text" id="modal-chart-number" placeholder="12345" style="padding:0.5rem;">
-                    </div>
-                    <div class="form-group" style="margin-bottom:0;">
-                        <label for="modal-gender" style="font-size:0.75rem;color:#6b7280;">성별</label>
-                        <select id="modal-gender" style="padding:0.5rem;">
-                            <option value="">선택</option>
-                            <option value="male">남</option>
-                            <option value="female">여</option>
-                        </select>
-                    </div>
-                    <div class="form-group" style="margin-bottom:0;">
-                        <label for="modal-age" style="font-size:0.75rem;color:#6b7280;">나이</label>
-                        <input type="number" id="modal-age" placeholder="35" style="padding:0.5rem;">
-                    </div>
-                </div>
-            </div>
-            <button class="btn-submit" onclick="createOnlineLink()">링크 생성</button>
-            <div class="result-box" id="online-result" style="display:none;">
-                <strong>✅ 온라인 링크가 생성되었습니다</strong>
-                <div class="result-url" id="online-url-text"></div>
-                <button class="btn-submit" style="background:#22c55e;margin-top:0.5rem;" onclick="copyOnlineUrl()">URL 복사</button>
-            </div>
-        </div>
-    </div>
-</body>
-</html>"#, clinic_name, clinic_name, online_link_btn, token)
+    render_template(
+        "staff_dashboard.html",
+        &[("clinic_name", clinic_name), ("online_link_btn", online_link_btn), ("token", &script_json(token))],
+    )
+}
+
+/// 설문 응답 인쇄 화면 (`>`로 시작하는 질문은 섹션 제목, 섹션 단위로 페이지 분할 방지)
+fn render_response_print_page(
+    clinic_name: &str,
+    response: &db::SurveyResponseWithTemplate,
+    questions: &[crate::models::SurveyQuestion],
+) -> String {
+    let answer_of = |question_id: &str| {
+        response.answers.iter().find(|a| a.question_id == question_id).map(|a| &a.answer)
+    };
+
+    let mut body = String::new();
+    let mut section_open = false;
+    let mut number = 0;
+    let mut push_item = |body: &mut String, section_open: &mut bool, label: &str, answer_html: String| {
+        if !*section_open {
+            body.push_str("<section class=\"section\">\n");
+            *section_open = true;
+        }
+        number += 1;
+        body.push_str(&format!(
+            "<div class=\"item\"><div class=\"question\"><span class=\"num\">Q{}.</span> {}</div><div class=\"answer\">{}</div></div>\n",
+            number,
+            escape_html(label),
+            answer_html
+        ));
+    };
+
+    if questions.is_empty() {
+        for answer in &response.answers {
+            push_item(&mut body, &mut section_open, &answer.question_id, format_print_answer(None, Some(&answer.answer)));
+        }
+    }
+    for question in questions {
+        if let Some(title) = question.question_text.strip_prefix('>') {
+            if section_open {
+                body.push_str("</section>\n");
+            }
+            body.push_str(&format!("<section class=\"section\">\n<h2>{}</h2>\n", escape_html(title.trim())));
+            section_open = true;
+            if let Some(answer) = answer_of(&question.id) {
+                body.push_str(&format!("<div class=\"section-answer\">{}</div>\n", format_print_answer(Some(question), Some(answer))));
+            }
+            continue;
+        }
+        push_item(&mut body, &mut section_open, &question.question_text, format_print_answer(Some(question), answer_of(&question.id)));
+    }
+    if section_open {
+        body.push_str("</section>\n");
+    }
+
+    let respondent = response
+        .patient_name
+        .as_deref()
+        .or(response.respondent_name.as_deref())
+        .unwrap_or("익명");
+    let chart_number = response
+        .chart_number
+        .as_deref()
+        .map(|c| format!(" (차트번호 {})", c))
+        .unwrap_or_default();
+    let submitted_at = chrono::DateTime::parse_from_rfc3339(&response.submitted_at)
+        .map(|dt| dt.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|_| response.submitted_at.clone());
+    // 응답자가 제출 시 입력한 연락처
+    let contact_meta: String = [("연락처", &response.respondent_phone), ("생년월일", &response.respondent_birth_date)]
+        .iter()
+        .filter_map(|(label, value)| {
+            value.as_deref().map(|v| format!("\n            <div><span>{}</span>{}</div>", label, escape_html(v)))
+        })
+        .collect();
+
+    render_template(
+        "response_print.html",
+        &[
+            ("clinic_name", clinic_name),
+            ("template_name", response.template_name.as_deref().unwrap_or("설문")),
+            ("respondent", respondent),
+            ("chart_number", &chart_number),
+            ("submitted_at", &submitted_at),
+            ("contact_meta", &contact_meta),
+            ("items", &body),
+        ],
+    )
+}
+
+/// 인쇄용 답변 표시 (복수 선택은 쉼표 목록, 척도는 "7 / 10"과 양 끝 라벨)
+fn format_print_answer(question: Option<&crate::models::SurveyQuestion>, answer: Option<&serde_json::Value>) -> String {
+    use crate::models::QuestionType;
+
+    let text = |value: &serde_json::Value| match value {
+        serde_json::Value::String(s) => s.trim().to_string(),
+        serde_json::Value::Bool(b) => if *b { "예" } else { "아니오" }.to_string(),
+        serde_json::Value::Null => String::new(),
+        other => match db::attachment_answer_name(other) {
+            Some(name) => format!("[첨부] {}", name),
+            None => other.to_string(),
+        },
+    };
+    let value = match answer {
+        Some(serde_json::Value::Array(items)) => items.iter().map(text).filter(|s| !s.is_empty()).collect::<Vec<_>>().join(", "),
+        Some(value) => text(value),
+        None => String::new(),
+    };
+    if value.is_empty() {
+        return r#"<span class="empty">(답변 없음)</span>"#.to_string();
+    }
+
+    match question.map(|q| (&q.question_type, q.scale_config.as_ref())) {
+        Some((QuestionType::Scale, Some(scale))) => {
+            let labels = [
+                scale.min_label.as_deref().map(|l| format!("{} = {}", scale.min, l)),
+                scale.max_label.as_deref().map(|l| format!("{} = {}", scale.max, l)),
+            ]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" · ");
+            let labels_html = if labels.is_empty() {
+                String::new()
+            } else {
+                format!(r#"<span class="scale-labels">{}</span>"#, escape_html(&labels))
+            };
+            format!("<strong>{} / {}</strong>{}", escape_html(&value), scale.max, labels_html)
+        }
+        _ => escape_html(&value),
+    }
 }
 
 /// 디버그: 테스트 세션 생성
-async fn create_test_session_handler() -> impl IntoResponse {
+async fn create_test_session_handler(
+    State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let token = params.get("token").cloned().unwrap_or_default();
+    if !is_valid_staff_token(&state, &token) {
+        return error_response(StatusCode::UNAUTHORIZED, "인증 필요");
+    }
+
     // 테스트용 템플릿 생성 (없으면)
     let template_id = "test_template_local";
     let template = db::SurveyTemplateDb {
@@ -1030,32 +2167,47 @@ async fn create_test_session_handler() -> impl IntoResponse {
                 required: true,
                 options: Some(vec!["옵션1".to_string(), "옵션2".to_string()]),
                 scale_config: None,
+                order: Some(1),
+                shuffle_options: false,
+                fixed_position: false,
+                translations: None,
             }
         ],
         display_mode: Some("all_at_once".to_string()),
         is_active: true,
+        require_contact: false,
+        anonymous: false,
+        redirect_url: None,
+        category: None,
+        randomize_questions: false,
+        randomize_options: false,
+        pin_required_questions: false,
     };
     let _ = db::save_survey_template(&template);
 
     // 테스트 세션 생성
-    match db::create_survey_session(None, template_id, Some("테스트 응답자"), None, None, None, None, None, None) {
+    match db::create_survey_session(None, template_id, Some("테스트 응답자"), None, None, None, None, None, None, false, None) {
         Ok(session) => {
             Json(serde_json::json!({
                 "success": true,
                 "token": session.token,
                 "url": format!("/s/{}", session.token)
-            }))
-        }
-        Err(e) => {
-            Json(serde_json::json!({
-                "error": e.to_string()
-            }))
+            })).into_response()
         }
+        Err(e) => app_error_response(e.status_code(), &e),
     }
 }
 
 /// 디버그: DB 상태 확인 (개발용)
-async fn debug_db_handler() -> impl IntoResponse {
+async fn debug_db_handler(
+    State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let token = params.get("token").cloned().unwrap_or_default();
+    if !is_valid_staff_token(&state, &token) {
+        return error_response(StatusCode::UNAUTHORIZED, "인증 필요");
+    }
+
     let settings = db::get_clinic_settings();
     let has_password = db::has_staff_password();
     let all_rows = db::debug_get_all_clinic_rows();
@@ -1076,10 +2228,10 @@ async fn debug_db_handler() -> impl IntoResponse {
         "settings": settings_info,
         "password": password_info,
         "all_rows": all_rows.unwrap_or_default(),
-    }))
+    })).into_response()
 }
 
-/// 템플릿 목록 API
+/// 템플릿 목록 API (`category`를 주면 해당 분류만)
 async fn get_templates_api(
     State(state): State<AppState>,
     axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
@@ -1091,20 +2243,40 @@ async fn get_templates_api(
     let valid = if is_kiosk {
         true // 키오스크 모드는 인증 불필요
     } else {
-        let sessions = state.staff_sessions.lock().ok();
-        sessions.map(|s| s.contains_key(&token)).unwrap_or(false)
+        is_valid_staff_token(&state, &token)
     };
 
     if !valid {
-        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({"error": "인증 필요"}))).into_response();
+        return error_response(StatusCode::UNAUTHORIZED, "인증 필요");
     }
 
+    let category = params.get("category").map(|c| c.trim()).filter(|c| !c.is_empty());
     match db::list_survey_templates() {
         Ok(templates) => {
-            let active: Vec<_> = templates.into_iter().filter(|t| t.is_active).collect();
+            let active: Vec<_> = templates
+                .into_iter()
+                .filter(|t| t.is_active)
+                .filter(|t| category.is_none() || t.category.as_deref() == category)
+                .collect();
             Json(serde_json::json!({"templates": active})).into_response()
         }
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": e.to_string()}))).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 템플릿 분류 목록 API
+async fn get_template_categories_api(
+    State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let token = params.get("token").cloned().unwrap_or_default();
+    if token != "kiosk" && !is_valid_staff_token(&state, &token) {
+        return error_response(StatusCode::UNAUTHORIZED, "인증 필요");
+    }
+
+    match db::list_survey_template_categories() {
+        Ok(categories) => Json(serde_json::json!({"categories": categories})).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
     }
 }
 
@@ -1118,30 +2290,48 @@ struct CreateSessionRequest {
     chart_number: Option<String>,
     patient_age: Option<String>,
     patient_gender: Option<String>,
+    #[serde(default)]
+    verify_birth_date: bool,
+    /// 키오스크에서 선택한 내원 경로
+    referral_source: Option<String>,
+    /// 링크 만료 시각 (expires_in_hours보다 우선)
+    expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// 링크 유효 시간 (미지정 시 7일)
+    expires_in_hours: Option<u32>,
+    /// 온라인 설문 링크를 보낼 환자 이메일 (원격 환자용)
+    email: Option<String>,
 }
 
 async fn create_session_api(
     State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    axum::extract::ConnectInfo(addr): axum::extract::ConnectInfo<SocketAddr>,
     axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
     Json(payload): Json<CreateSessionRequest>,
 ) -> impl IntoResponse {
     let token = params.get("token").cloned().unwrap_or_default();
 
     // 세션 확인
-    let valid = {
-        let sessions = state.staff_sessions.lock().ok();
-        sessions.map(|s| s.contains_key(&token)).unwrap_or(false)
-    };
+    let valid = is_valid_staff_token(&state, &token);
 
     if !valid {
-        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({"error": "인증 필요"}))).into_response();
+        return error_response(StatusCode::UNAUTHORIZED, "인증 필요");
     }
 
     // 템플릿 존재 확인
     match db::get_survey_template(&payload.template_id) {
         Ok(Some(_)) => {}
-        Ok(None) => return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "템플릿을 찾을 수 없습니다"}))).into_response(),
-        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": e.to_string()}))).into_response(),
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "템플릿을 찾을 수 없습니다"),
+        Err(e) => return app_error_response(e.status_code(), &e),
+    }
+
+    // 링크 만료 시각 (기본 7일)
+    let expires_at = payload.expires_at.unwrap_or_else(|| {
+        let hours = payload.expires_in_hours.map(i64::from).unwrap_or(24 * 7);
+        chrono::Utc::now() + chrono::Duration::hours(hours)
+    });
+    if expires_at <= chrono::Utc::now() {
+        return error_response(StatusCode::BAD_REQUEST, "만료 시각은 현재 이후여야 합니다");
     }
 
     // 세션 생성
@@ -1152,18 +2342,25 @@ async fn create_session_api(
         None,
         None,
         None, None, None, None,
+        payload.verify_birth_date,
+        Some(expires_at),
     ) {
         Ok(session) => {
+            record_session_creation(&state, &request_id, &session.id, &token, addr);
             Json(serde_json::json!({
                 "success": true,
                 "token": session.token,
                 "url": format!("/s/{}", session.token),
-                "session_id": session.id
+                "session_id": session.id,
+                "expires_at": session.expires_at
             })).into_response()
         }
+        Err(crate::error::AppError::Custom(msg)) => {
+            error_response(StatusCode::BAD_REQUEST, msg)
+        }
         Err(e) => {
-            log::error!("설문 세션 생성 실패: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": "세션 생성 실패"}))).into_response()
+            log::error!("[{}] 설문 세션 생성 실패: {}", request_id, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "세션 생성 실패")
         }
     }
 }
@@ -1171,44 +2368,53 @@ async fn create_session_api(
 /// 온라인 설문 세션 생성 (Supabase 연동)
 async fn create_online_session_api(
     State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
     axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
     Json(payload): Json<CreateSessionRequest>,
 ) -> impl IntoResponse {
     let token = params.get("token").cloned().unwrap_or_default();
 
     // Staff 세션 확인
-    let valid = {
-        let sessions = state.staff_sessions.lock().ok();
-        sessions.map(|s| s.contains_key(&token)).unwrap_or(false)
-    };
+    let valid = is_valid_staff_token(&state, &token);
 
     if !valid {
-        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({"error": "인증 필요"}))).into_response();
+        return error_response(StatusCode::UNAUTHORIZED, "인증 필요");
+    }
+
+    // 이메일 발송 요청은 링크를 만들기 전에 확인
+    let email = payload.email.as_deref().map(str::trim).filter(|e| !e.is_empty()).map(String::from);
+    if let Some(address) = &email {
+        if !db::is_valid_email_address(address) {
+            return error_response(StatusCode::BAD_REQUEST, "이메일 주소가 올바르지 않습니다");
+        }
+        if !matches!(db::get_smtp_settings(), Ok(Some(_))) {
+            return error_response(StatusCode::CONFLICT, "이메일 발송 설정이 없습니다");
+        }
     }
 
     // 템플릿 조회
     let template = match db::get_survey_template(&payload.template_id) {
         Ok(Some(t)) => t,
-        Ok(None) => return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "템플릿을 찾을 수 없습니다"}))).into_response(),
-        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": e.to_string()}))).into_response(),
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "템플릿을 찾을 수 없습니다"),
+        Err(e) => return app_error_response(e.status_code(), &e),
     };
 
     // Supabase 설정 가져오기
     auth::ensure_supabase_initialized();
     let config = match auth::get_supabase_config() {
         Ok(c) => c,
-        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": format!("Supabase 미초기화: {}", e)}))).into_response(),
+        Err(e) => return app_error_response(e.status_code(), &e),
     };
     let client = match auth::get_http_client() {
         Ok(c) => c,
-        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": format!("HTTP 클라이언트 오류: {}", e)}))).into_response(),
+        Err(e) => return app_error_response(e.status_code(), &e),
     };
 
     let user_id = auth::get_user_id().unwrap_or_default();
     let access_token = auth::get_access_token().unwrap_or_default();
 
     if user_id.is_empty() || access_token.is_empty() {
-        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({"error": "로그인이 필요합니다 (Supabase 인증)"}))).into_response();
+        return error_response(StatusCode::UNAUTHORIZED, "로그인이 필요합니다 (Supabase 인증)");
     }
 
     // 1. Supabase에 템플릿 upsert
@@ -1233,8 +2439,9 @@ async fn create_online_session_api(
         .await;
 
     if let Err(e) = upsert_res {
-        log::error!("Supabase 템플릿 upsert 실패: {}", e);
-        return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": format!("템플릿 동기화 실패: {}", e)}))).into_response();
+        log::error!("[{}] Supabase 템플릿 upsert 실패: {}", request_id, e);
+        let e = AppError::from(e);
+        return app_error_response(e.status_code(), &e);
     }
 
     // 2. 16자 랜덤 토큰 생성
@@ -1269,12 +2476,13 @@ async fn create_online_session_api(
         Ok(resp) if !resp.status().is_success() => {
             let status = resp.status();
             let body = resp.text().await.unwrap_or_default();
-            log::error!("Supabase 세션 생성 실패: {} - {}", status, body);
-            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": format!("세션 생성 실패: {}", body)}))).into_response();
+            log::error!("[{}] Supabase 세션 생성 실패: {} - {}", request_id, status, body);
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, format!("세션 생성 실패: {}", body));
         }
         Err(e) => {
-            log::error!("Supabase 세션 생성 요청 실패: {}", e);
-            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": format!("세션 생성 실패: {}", e)}))).into_response();
+            log::error!("[{}] Supabase 세션 생성 요청 실패: {}", request_id, e);
+            let e = AppError::from(e);
+            return app_error_response(e.status_code(), &e);
         }
         _ => {}
     }
@@ -1290,19 +2498,32 @@ async fn create_online_session_api(
         payload.chart_number.as_deref(),
         payload.patient_age.as_deref(),
         payload.patient_gender.as_deref(),
+        false,
+        None,
     ) {
-        log::warn!("로컬 DB 세션 저장 실패 (무시): {}", e);
+        log::warn!("[{}] 로컬 DB 세션 저장 실패 (무시): {}", request_id, e);
     }
 
     // 5. Vercel URL 반환
     let survey_url = format!("https://gosibang-survey.vercel.app/s/{}", survey_token);
-    log::info!("온라인 설문 링크 생성: {}", survey_url);
+    log::info!("[{}] 온라인 설문 링크 생성: {}", request_id, survey_url);
+
+    // 이메일 발송 (비동기, 실패하면 대기열에서 재시도)
+    if let Some(address) = email.clone() {
+        let url = survey_url.clone();
+        tokio::spawn(async move {
+            if let Err(e) = crate::email::send_survey_link(&address, &url).await {
+                log::warn!("[{}] 설문 링크 메일 발송 실패 (나중에 재시도됨): {}", request_id, e);
+            }
+        });
+    }
 
     Json(serde_json::json!({
         "success": true,
         "url": survey_url,
         "token": survey_token,
-        "session_id": session_id
+        "session_id": session_id,
+        "email": email,
     })).into_response()
 }
 
@@ -1325,25 +2546,28 @@ fn generate_online_token(len: usize) -> String {
 // ============ 환자 전용 키오스크 페이지 ============
 
 /// 환자 전용 설문 키오스크 페이지
-async fn patient_kiosk_page() -> Html<String> {
-    let clinic_name = db::get_clinic_settings()
-        .ok()
-        .flatten()
-        .map(|s| s.clinic_name)
-        .unwrap_or_else(|| "한의원".to_string());
+async fn patient_kiosk_page(
+    State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+) -> Html<String> {
+    let clinic_name = state.clinic_name();
+
+    let referral_sources = db::get_referral_sources().unwrap_or_default();
+    let idle_minutes = db::get_kiosk_idle_minutes().unwrap_or(db::DEFAULT_KIOSK_IDLE_MINUTES);
 
-    Html(render_patient_kiosk_page(&clinic_name))
+    Html(render_patient_kiosk_page(&clinic_name, &referral_sources, idle_minutes, Lang::from_params(&params)))
 }
 
 /// 환자용 세션 생성 API (인증 불필요)
 async fn patient_create_session_api(
+    Extension(request_id): Extension<RequestId>,
     Json(payload): Json<CreateSessionRequest>,
 ) -> impl IntoResponse {
     // 템플릿 존재 확인
     match db::get_survey_template(&payload.template_id) {
         Ok(Some(_)) => {}
-        Ok(None) => return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "템플릿을 찾을 수 없습니다"}))).into_response(),
-        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": e.to_string()}))).into_response(),
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "템플릿을 찾을 수 없습니다"),
+        Err(e) => return app_error_response(e.status_code(), &e),
     }
 
     // 세션 생성
@@ -1354,8 +2578,15 @@ async fn patient_create_session_api(
         None,
         None,
         None, None, None, None,
+        false,
+        None,
     ) {
         Ok(session) => {
+            if payload.referral_source.is_some() {
+                if let Err(e) = db::set_survey_session_referral_source(&session.id, payload.referral_source.as_deref()) {
+                    log::warn!("[{}] 내원 경로 저장 실패: {}", request_id, e);
+                }
+            }
             Json(serde_json::json!({
                 "success": true,
                 "token": session.token,
@@ -1363,515 +2594,696 @@ async fn patient_create_session_api(
             })).into_response()
         }
         Err(e) => {
-            log::error!("설문 세션 생성 실패: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": "세션 생성 실패"}))).into_response()
+            log::error!("[{}] 설문 세션 생성 실패: {}", request_id, e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, "세션 생성 실패")
         }
     }
 }
 
 /// 환자 키오스크 페이지 렌더링
-fn render_patient_kiosk_page(clinic_name: &str) -> String {
-    format!(r#"<!DOCTYPE html>
-<html lang="ko">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0, maximum-scale=1.0, user-scalable=no">
-    <title>{} - 설문</title>
-    <style>
-        * {{ margin: 0; padding: 0; box-sizing: border-box; }}
-        body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif; background: #f5f5f5; min-height: 100vh; padding: 1rem; }}
-        .container {{ max-width: 600px; margin: 0 auto; }}
-
-        /* 대기 화면 */
-        .waiting-screen {{ display: none; }}
-        .waiting-screen .card {{ background: white; border-radius: 1rem; box-shadow: 0 2px 4px rgba(0,0,0,0.1); padding: 1.5rem; }}
-        .waiting-screen h1 {{ color: #333; font-size: 1.5rem; margin-bottom: 0.5rem; text-align: center; }}
-        .waiting-screen .subtitle {{ color: #666; font-size: 1rem; margin-bottom: 1.5rem; text-align: center; }}
-
-        .form-group {{ margin-bottom: 1.25rem; }}
-        .form-group label {{ display: block; font-weight: 600; color: #333; margin-bottom: 0.5rem; }}
-        .form-group select, .form-group input {{ width: 100%; padding: 0.75rem 1rem; border: 2px solid #e5e7eb; border-radius: 0.5rem; font-size: 1rem; }}
-        .form-group select:focus, .form-group input:focus {{ outline: none; border-color: #4f46e5; }}
-
-        .btn-start {{ width: 100%; padding: 1rem; background: #4f46e5; color: white; border: none; border-radius: 0.5rem; font-size: 1rem; font-weight: 600; cursor: pointer; }}
-        .btn-start:hover {{ background: #4338ca; }}
-        .btn-start:disabled {{ opacity: 0.5; cursor: not-allowed; }}
-
-        .staff-hint {{ margin-top: 1.25rem; padding: 1rem; background: #fef3c7; border-radius: 0.5rem; }}
-        .staff-hint p {{ color: #92400e; font-size: 0.875rem; }}
-
-        /* 설문 화면 */
-        .survey-screen {{ display: none; }}
-        .survey-screen .card {{ background: white; border-radius: 1rem; box-shadow: 0 2px 4px rgba(0,0,0,0.1); padding: 1.5rem; }}
-        .survey-header {{ margin-bottom: 1rem; }}
-        .survey-header h2 {{ color: #333; font-size: 1.5rem; margin-bottom: 0.25rem; }}
-        .survey-header .patient-name {{ color: #666; font-size: 0.9rem; }}
-        .progress {{ height: 4px; background: #e5e7eb; border-radius: 2px; margin-top: 0.75rem; }}
-        .progress-bar {{ height: 100%; background: #4f46e5; border-radius: 2px; transition: width 0.3s; }}
-
-        .questions-container {{ max-height: 60vh; overflow-y: auto; }}
-        .question {{ margin-bottom: 1.5rem; }}
-        .question-text {{ font-weight: 600; margin-bottom: 0.75rem; color: #333; }}
-        .required {{ color: #ef4444; }}
-
-        .options {{ display: grid; grid-template-columns: repeat(2, 1fr); gap: 0.5rem; }}
-        .option {{ padding: 0.75rem 1rem; border: 2px solid #e5e7eb; border-radius: 0.5rem; cursor: pointer; transition: all 0.2s; text-align: center; font-size: 0.9rem; }}
-        .option:hover {{ border-color: #4f46e5; background: #f5f3ff; }}
-        .option.selected {{ border-color: #4f46e5; background: #4f46e5; color: white; }}
-        .option-multi.selected {{ border-color: #4f46e5; background: #eef2ff; color: #4f46e5; }}
-
-        input[type="text"], textarea {{ width: 100%; padding: 0.75rem; border: 2px solid #e5e7eb; border-radius: 0.5rem; font-size: 1rem; }}
-        input[type="text"]:focus, textarea:focus {{ outline: none; border-color: #4f46e5; }}
-        textarea {{ min-height: 80px; resize: vertical; }}
-
-        .scale-container {{ display: flex; gap: 0.5rem; flex-wrap: wrap; }}
-        .scale-btn {{ flex: 1; min-width: 40px; padding: 0.75rem; border: 2px solid #e5e7eb; border-radius: 0.5rem; cursor: pointer; text-align: center; font-weight: 600; }}
-        .scale-btn:hover {{ border-color: #4f46e5; }}
-        .scale-btn.selected {{ border-color: #4f46e5; background: #4f46e5; color: white; }}
-        .scale-labels {{ display: flex; justify-content: space-between; margin-top: 0.5rem; font-size: 0.875rem; color: #666; }}
-
-        .nav-buttons {{ display: flex; gap: 1rem; margin-top: 1.5rem; }}
-        .btn {{ flex: 1; padding: 1rem; border: none; border-radius: 0.5rem; font-size: 1rem; font-weight: 600; cursor: pointer; }}
-        .btn-primary {{ background: #4f46e5; color: white; }}
-        .btn-primary:hover {{ background: #4338ca; }}
-        .btn-secondary {{ background: #e5e7eb; color: #374151; }}
-        .btn-secondary:hover {{ background: #d1d5db; }}
-        .btn:disabled {{ opacity: 0.5; cursor: not-allowed; }}
-        .hidden {{ display: none !important; }}
-
-        /* 완료 화면 */
-        .complete-screen {{ display: none; }}
-        .complete-screen .card {{ background: white; border-radius: 1rem; box-shadow: 0 2px 4px rgba(0,0,0,0.1); padding: 3rem; text-align: center; }}
-        .success-icon {{ font-size: 4rem; margin-bottom: 1rem; }}
-        .complete-screen h2 {{ color: #059669; font-size: 1.5rem; margin-bottom: 0.5rem; }}
-        .complete-screen p {{ color: #666; margin-bottom: 1rem; }}
-        .countdown {{ background: #f3f4f6; padding: 0.5rem 1rem; border-radius: 1rem; display: inline-block; color: #374151; font-size: 0.9rem; }}
-
-        /* 활성 상태 */
-        .screen.active {{ display: block; }}
-    </style>
-</head>
-<body>
-    <div class="container">
-    <!-- 대기 화면 -->
-    <div class="waiting-screen screen active" id="waiting-screen">
-        <div class="card">
-            <h1>{}</h1>
-            <p class="subtitle">설문 시스템</p>
-
-            <div class="form-group">
-                <label for="template">설문 종류</label>
-                <select id="template">
-                    <option value="">설문을 선택하세요</option>
-                </select>
-            </div>
-            <div class="form-group">
-                <label for="patient-name">환자 이름</label>
-                <input type="text" id="patient-name" placeholder="이름을 입력하세요">
-            </div>
-            <button class="btn-start" id="start-btn" onclick="startSurvey()">
-                설문 시작하기
-            </button>
-            <div class="staff-hint">
-                <p><strong>💡 안내:</strong> 직원이 위 정보를 입력한 후 환자에게 태블릿을 건네주세요.</p>
-            </div>
-        </div>
-    </div>
-
-    <!-- 설문 화면 -->
-    <div class="survey-screen screen" id="survey-screen">
-        <div class="card">
-            <div class="survey-header">
-                <h2 id="survey-title">설문</h2>
-                <p class="patient-name" id="display-patient-name"></p>
-                <div class="progress"><div class="progress-bar" id="progress-bar"></div></div>
-            </div>
-            <div id="questions-container" class="questions-container"></div>
-            <div class="nav-buttons">
-                <button class="btn btn-secondary" id="prev-btn" onclick="prevQuestion()">이전</button>
-                <button class="btn btn-primary" id="next-btn" onclick="nextQuestion()">다음</button>
-            </div>
-        </div>
-    </div>
-
-    <!-- 완료 화면 -->
-    <div class="complete-screen screen" id="complete-screen">
-        <div class="card">
-            <div class="success-icon">✅</div>
-            <h2>설문이 완료되었습니다</h2>
-            <p>감사합니다.<br>태블릿을 직원에게 돌려주세요.</p>
-            <div class="countdown" id="countdown">5초 후 처음으로 돌아갑니다</div>
-        </div>
-    </div>
-    </div>
-
-    <script>
-        let currentToken = '';
-        let questions = [];
-        let answers = {{}};
-        let currentIndex = 0;
-        let patientName = '';
-        let templateName = '';
-        let displayMode = 'one_by_one';
-
-        // 템플릿 로드
-        async function loadTemplates() {{
-            try {{
-                const res = await fetch('/api/templates?token=kiosk');
-                const data = await res.json();
-                const select = document.getElementById('template');
-
-                if (data.templates && data.templates.length > 0) {{
-                    data.templates.forEach(t => {{
-                        const option = document.createElement('option');
-                        option.value = t.id;
-                        option.textContent = t.name;
-                        option.dataset.questions = JSON.stringify(t.questions);
-                        option.dataset.name = t.name;
-                        option.dataset.displayMode = t.display_mode || 'one_by_one';
-                        select.appendChild(option);
-                    }});
-                }}
-            }} catch (e) {{
-                console.error('템플릿 로드 실패:', e);
-            }}
-        }}
-
-        // 설문 시작
-        async function startSurvey() {{
-            const templateSelect = document.getElementById('template');
-            const templateId = templateSelect.value;
-            const nameInput = document.getElementById('patient-name');
-            patientName = nameInput.value.trim();
-
-            if (!templateId) {{
-                alert('설문을 선택하세요');
-                return;
-            }}
-            if (!patientName) {{
-                alert('환자 이름을 입력하세요');
-                return;
-            }}
-
-            const selectedOption = templateSelect.options[templateSelect.selectedIndex];
-            questions = JSON.parse(selectedOption.dataset.questions || '[]');
-            templateName = selectedOption.dataset.name;
-            displayMode = selectedOption.dataset.displayMode || 'one_by_one';
-
-            if (questions.length === 0) {{
-                alert('설문 질문이 없습니다');
-                return;
-            }}
-
-            // 세션 생성
-            try {{
-                const res = await fetch('/api/patient/create-session', {{
-                    method: 'POST',
-                    headers: {{ 'Content-Type': 'application/json' }},
-                    body: JSON.stringify({{
-                        template_id: templateId,
-                        respondent_name: patientName
-                    }})
-                }});
-
-                const data = await res.json();
-                if (data.success) {{
-                    currentToken = data.token;
-                    showScreen('survey');
-                    document.getElementById('survey-title').textContent = templateName;
-                    document.getElementById('display-patient-name').textContent = patientName + '님';
-                    currentIndex = 0;
-                    answers = {{}};
-
-                    if (displayMode === 'single_page' || displayMode === 'all_at_once') {{
-                        renderAllQuestions();
-                        document.getElementById('prev-btn').classList.add('hidden');
-                        document.getElementById('next-btn').textContent = '제출하기';
-                        document.getElementById('progress-bar').style.width = '100%';
-                    }} else {{
-                        renderQuestion();
-                        updateNavigation();
-                    }}
-                }} else {{
-                    alert(data.error || '세션 생성 실패');
-                }}
-            }} catch (e) {{
-                alert('네트워크 오류가 발생했습니다');
-            }}
-        }}
-
-        // 화면 전환
-        function showScreen(screenName) {{
-            document.querySelectorAll('.screen').forEach(s => s.classList.remove('active'));
-            document.getElementById(screenName + '-screen').classList.add('active');
-        }}
-
-        // 질문 렌더링
-        function renderQuestion() {{
-            const container = document.getElementById('questions-container');
-            const q = questions[currentIndex];
-            container.innerHTML = '';
-
-            const div = document.createElement('div');
-            div.className = 'question';
-            div.innerHTML = `<div class="question-text">Q${{currentIndex + 1}}. ${{q.question_text}} ${{q.required ? '<span class="required">*</span>' : ''}}</div>`;
-
-            if (q.question_type === 'single_choice' && q.options) {{
-                const optionsDiv = document.createElement('div');
-                optionsDiv.className = 'options';
-                q.options.forEach(opt => {{
-                    const optDiv = document.createElement('div');
-                    optDiv.className = 'option' + (answers[q.id] === opt ? ' selected' : '');
-                    optDiv.textContent = opt;
-                    optDiv.onclick = () => selectOption(q.id, opt, optDiv);
-                    optionsDiv.appendChild(optDiv);
-                }});
-                div.appendChild(optionsDiv);
-            }} else if (q.question_type === 'multiple_choice' && q.options) {{
-                const optionsDiv = document.createElement('div');
-                optionsDiv.className = 'options';
-                q.options.forEach(opt => {{
-                    const optDiv = document.createElement('div');
-                    const selected = (answers[q.id] || []).includes(opt);
-                    optDiv.className = 'option option-multi' + (selected ? ' selected' : '');
-                    optDiv.textContent = opt;
-                    optDiv.onclick = () => selectMultiOption(q.id, opt, optDiv);
-                    optionsDiv.appendChild(optDiv);
-                }});
-                div.appendChild(optionsDiv);
-            }} else if (q.question_type === 'text') {{
-                const textarea = document.createElement('textarea');
-                textarea.placeholder = '답변을 입력하세요';
-                textarea.value = answers[q.id] || '';
-                textarea.oninput = (e) => {{ answers[q.id] = e.target.value; }};
-                div.appendChild(textarea);
-            }} else if (q.question_type === 'scale' && q.scale_config) {{
-                const scaleDiv = document.createElement('div');
-                scaleDiv.className = 'scale-container';
-                for (let i = q.scale_config.min; i <= q.scale_config.max; i++) {{
-                    const btn = document.createElement('div');
-                    btn.className = 'scale-btn' + (answers[q.id] === i ? ' selected' : '');
-                    btn.textContent = i;
-                    btn.onclick = () => selectScale(q.id, i, scaleDiv);
-                    scaleDiv.appendChild(btn);
-                }}
-                div.appendChild(scaleDiv);
-                if (q.scale_config.minLabel || q.scale_config.maxLabel) {{
-                    const labels = document.createElement('div');
-                    labels.className = 'scale-labels';
-                    labels.innerHTML = `<span>${{q.scale_config.minLabel || ''}}</span><span>${{q.scale_config.maxLabel || ''}}</span>`;
-                    div.appendChild(labels);
-                }}
-            }}
-
-            container.appendChild(div);
-        }}
-
-        // 모든 질문을 한 화면에 렌더링 (single_page / all_at_once 모드)
-        function renderAllQuestions() {{
-            const container = document.getElementById('questions-container');
-            container.innerHTML = '';
-
-            questions.forEach((q, idx) => {{
-                const div = document.createElement('div');
-                div.className = 'question';
-                div.innerHTML = `<div class="question-text">Q${{idx + 1}}. ${{q.question_text}} ${{q.required ? '<span class="required">*</span>' : ''}}</div>`;
-
-                if (q.question_type === 'single_choice' && q.options) {{
-                    const optionsDiv = document.createElement('div');
-                    optionsDiv.className = 'options';
-                    q.options.forEach(opt => {{
-                        const optDiv = document.createElement('div');
-                        optDiv.className = 'option' + (answers[q.id] === opt ? ' selected' : '');
-                        optDiv.textContent = opt;
-                        optDiv.onclick = () => {{
-                            answers[q.id] = opt;
-                            optDiv.parentElement.querySelectorAll('.option').forEach(el => el.classList.remove('selected'));
-                            optDiv.classList.add('selected');
-                        }};
-                        optionsDiv.appendChild(optDiv);
-                    }});
-                    div.appendChild(optionsDiv);
-                }} else if (q.question_type === 'multiple_choice' && q.options) {{
-                    const optionsDiv = document.createElement('div');
-                    optionsDiv.className = 'options';
-                    q.options.forEach(opt => {{
-                        const optDiv = document.createElement('div');
-                        const selected = (answers[q.id] || []).includes(opt);
-                        optDiv.className = 'option option-multi' + (selected ? ' selected' : '');
-                        optDiv.textContent = opt;
-                        optDiv.onclick = () => {{
-                            if (!answers[q.id]) answers[q.id] = [];
-                            const i = answers[q.id].indexOf(opt);
-                            if (i >= 0) {{
-                                answers[q.id].splice(i, 1);
-                                optDiv.classList.remove('selected');
-                            }} else {{
-                                answers[q.id].push(opt);
-                                optDiv.classList.add('selected');
-                            }}
-                        }};
-                        optionsDiv.appendChild(optDiv);
-                    }});
-                    div.appendChild(optionsDiv);
-                }} else if (q.question_type === 'text') {{
-                    const textarea = document.createElement('textarea');
-                    textarea.placeholder = '답변을 입력하세요';
-                    textarea.value = answers[q.id] || '';
-                    textarea.oninput = (e) => {{ answers[q.id] = e.target.value; }};
-                    div.appendChild(textarea);
-                }} else if (q.question_type === 'scale' && q.scale_config) {{
-                    const scaleDiv = document.createElement('div');
-                    scaleDiv.className = 'scale-container';
-                    for (let i = q.scale_config.min; i <= q.scale_config.max; i++) {{
-                        const btn = document.createElement('div');
-                        btn.className = 'scale-btn' + (answers[q.id] === i ? ' selected' : '');
-                        btn.textContent = i;
-                        btn.onclick = () => {{
-                            answers[q.id] = i;
-                            scaleDiv.querySelectorAll('.scale-btn').forEach(el => el.classList.remove('selected'));
-                            btn.classList.add('selected');
-                        }};
-                        scaleDiv.appendChild(btn);
-                    }}
-                    div.appendChild(scaleDiv);
-                    if (q.scale_config.minLabel || q.scale_config.maxLabel) {{
-                        const labels = document.createElement('div');
-                        labels.className = 'scale-labels';
-                        labels.innerHTML = `<span>${{q.scale_config.minLabel || ''}}</span><span>${{q.scale_config.maxLabel || ''}}</span>`;
-                        div.appendChild(labels);
-                    }}
-                }}
-
-                container.appendChild(div);
-            }});
-        }}
-
-        function selectOption(qId, value, element) {{
-            answers[qId] = value;
-            element.parentElement.querySelectorAll('.option').forEach(el => el.classList.remove('selected'));
-            element.classList.add('selected');
-        }}
-
-        function selectMultiOption(qId, value, element) {{
-            if (!answers[qId]) answers[qId] = [];
-            const idx = answers[qId].indexOf(value);
-            if (idx >= 0) {{
-                answers[qId].splice(idx, 1);
-                element.classList.remove('selected');
-            }} else {{
-                answers[qId].push(value);
-                element.classList.add('selected');
-            }}
-        }}
-
-        function selectScale(qId, value, container) {{
-            answers[qId] = value;
-            container.querySelectorAll('.scale-btn').forEach(el => el.classList.remove('selected'));
-            event.target.classList.add('selected');
-        }}
-
-        function updateNavigation() {{
-            const prevBtn = document.getElementById('prev-btn');
-            const nextBtn = document.getElementById('next-btn');
-            const progressBar = document.getElementById('progress-bar');
-
-            prevBtn.classList.toggle('hidden', currentIndex === 0);
-            nextBtn.textContent = currentIndex === questions.length - 1 ? '제출하기' : '다음';
-            progressBar.style.width = ((currentIndex + 1) / questions.length * 100) + '%';
-        }}
-
-        function prevQuestion() {{
-            if (currentIndex > 0) {{
-                currentIndex--;
-                renderQuestion();
-                updateNavigation();
-            }}
-        }}
-
-        function nextQuestion() {{
-            // single_page/all_at_once 모드에서는 바로 제출
-            if (displayMode === 'single_page' || displayMode === 'all_at_once') {{
-                submitSurvey();
-                return;
-            }}
-
-            if (currentIndex < questions.length - 1) {{
-                currentIndex++;
-                renderQuestion();
-                updateNavigation();
-            }} else {{
-                submitSurvey();
-            }}
-        }}
-
-        async function submitSurvey() {{
-            // 필수 질문 확인
-            for (const q of questions) {{
-                if (q.required) {{
-                    const ans = answers[q.id];
-                    if (ans === undefined || ans === '' || (Array.isArray(ans) && ans.length === 0)) {{
-                        alert(`"${{q.question_text}}" 질문에 답변해주세요.`);
-                        return;
-                    }}
-                }}
-            }}
-
-            const answerArray = Object.entries(answers).map(([question_id, answer]) => ({{ question_id, answer }}));
-
-            try {{
-                const res = await fetch('/api/survey/' + currentToken, {{
-                    method: 'POST',
-                    headers: {{ 'Content-Type': 'application/json' }},
-                    body: JSON.stringify({{ answers: answerArray }})
-                }});
-
-                if (res.ok) {{
-                    showComplete();
-                }} else {{
-                    const data = await res.json();
-                    alert(data.error || '제출에 실패했습니다.');
-                }}
-            }} catch (e) {{
-                alert('네트워크 오류가 발생했습니다.');
-            }}
-        }}
-
-        function showComplete() {{
-            showScreen('complete');
-
-            let count = 5;
-            const countdownEl = document.getElementById('countdown');
-
-            const timer = setInterval(() => {{
-                count--;
-                countdownEl.textContent = count + '초 후 처음으로 돌아갑니다';
-
-                if (count <= 0) {{
-                    clearInterval(timer);
-                    resetToWaiting();
-                }}
-            }}, 1000);
-        }}
-
-        function resetToWaiting() {{
-            // 초기화
-            document.getElementById('patient-name').value = '';
-            document.getElementById('template').selectedIndex = 0;
-            currentToken = '';
-            questions = [];
-            answers = {{}};
-            currentIndex = 0;
-            patientName = '';
-            displayMode = 'one_by_one';
-
-            showScreen('waiting');
-        }}
-
-        // 초기화
-        loadTemplates();
-    </script>
-</body>
-</html>"#, clinic_name, clinic_name)
+fn render_patient_kiosk_page(clinic_name: &str, referral_sources: &[String], idle_minutes: i64, lang: Lang) -> String {
+    let t = lang.strings();
+    let sources_json = script_json(referral_sources);
+    render_template(
+        "kiosk.html",
+        &[
+            ("lang", lang.code()),
+            ("clinic_name", clinic_name),
+            ("survey", t.survey),
+            ("kiosk_subtitle", t.kiosk_subtitle),
+            ("survey_type", t.survey_type),
+            ("select_survey", t.select_survey),
+            ("patient_name", t.patient_name),
+            ("name_placeholder", t.name_placeholder),
+            ("referral_source", t.referral_source),
+            ("referral_placeholder", t.referral_placeholder),
+            ("referral_sources", &sources_json),
+            ("phone", t.phone),
+            ("birth_date", t.birth_date),
+            ("idle_minutes", &idle_minutes.to_string()),
+            ("idle_prompt", t.idle_prompt),
+            ("idle_continue", t.idle_continue),
+            ("start_survey", t.start_survey),
+            ("hint_label", t.hint_label),
+            ("staff_hint", t.staff_hint),
+            ("prev", t.prev),
+            ("next", t.next),
+            ("completed", t.completed),
+            ("thanks", t.thanks),
+            ("return_tablet", t.return_tablet),
+            ("strings", &script_json(t)),
+        ],
+    )
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    async fn status_of(state: &AppState, method: Method, uri: &str) -> StatusCode {
+        let request = axum::http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::CONTENT_LENGTH, "0")
+            .body(Body::empty())
+            .unwrap();
+        create_router(state.clone()).oneshot(request).await.unwrap().status()
+    }
+
+    async fn submit_status(content_type: &str, body: String) -> StatusCode {
+        let request = axum::http::Request::builder()
+            .method(Method::POST)
+            .uri("/api/survey/anytoken")
+            .header(header::CONTENT_TYPE, content_type)
+            .header(header::CONTENT_LENGTH, body.len().to_string())
+            .body(Body::from(body))
+            .unwrap();
+        create_router(AppState::new()).oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn survey_submit_rejects_oversized_body() {
+        let answers = serde_json::json!({"answers": [{"question_id": "q1", "answer": "x".repeat(crate::web_api::MAX_BODY_BYTES)}]});
+        assert_eq!(submit_status("application/json", answers.to_string()).await, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn survey_submit_rejects_non_json_content_type() {
+        let body = r#"{"answers": []}"#.to_string();
+        assert_eq!(submit_status("text/plain", body.clone()).await, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(submit_status("application/x-www-form-urlencoded", body).await, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn concurrent_submits_store_one_response() {
+        db::init_test_database();
+        let session = db::create_survey_session(None, "default_female_health", Some("동시 제출"), None, None, None, None, None, None, false, None)
+            .unwrap();
+        let submit = || {
+            let request = axum::http::Request::builder()
+                .method(Method::POST)
+                .uri(format!("/api/survey/{}", session.token))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(r#"{"answers": []}"#))
+                .unwrap();
+            tokio::spawn(create_router(AppState::new()).oneshot(request))
+        };
+
+        let (first, second) = tokio::join!(submit(), submit());
+        let mut statuses = [first.unwrap().unwrap().status(), second.unwrap().unwrap().status()];
+        statuses.sort();
+        assert_eq!(statuses, [StatusCode::OK, StatusCode::CONFLICT]);
+
+        let stored = db::list_survey_responses(Some(i32::MAX))
+            .unwrap()
+            .into_iter()
+            .filter(|r| r.session_id.as_deref() == Some(session.id.as_str()))
+            .count();
+        assert_eq!(stored, 1);
+    }
+
+    #[tokio::test]
+    async fn debug_routes_are_not_registered_when_disabled() {
+        let state = AppState { debug_endpoints: false, ..AppState::new() };
+        assert_eq!(status_of(&state, Method::GET, "/debug/db").await, StatusCode::NOT_FOUND);
+        assert_eq!(status_of(&state, Method::POST, "/debug/create-test-session").await, StatusCode::NOT_FOUND);
+
+        // 켜져 있으면 라우트는 있고 인증을 요구
+        let state = AppState { debug_endpoints: true, ..AppState::new() };
+        assert_eq!(status_of(&state, Method::GET, "/debug/db").await, StatusCode::UNAUTHORIZED);
+    }
+
+    fn abuse_notifications_for(subject: &str) -> usize {
+        db::list_notifications_page(false, i64::MAX, 0)
+            .unwrap()
+            .items
+            .into_iter()
+            .filter(|n| n.notification_type == "survey_session_abuse" && n.body.starts_with(&format!("{}에서", subject)))
+            .count()
+    }
+
+    #[test]
+    fn session_creation_burst_notifies_once_per_staff_and_ip() {
+        db::init_test_database();
+        let state = AppState::new().with_session_abuse_threshold(SessionAbuseThreshold { max_sessions: 2, window_minutes: 10 });
+        let request_id = RequestId("test".to_string());
+        let bytes = *uuid::Uuid::new_v4().as_bytes();
+        let busy_ip: SocketAddr = format!("10.{}.{}.{}:5000", bytes[0], bytes[1], bytes[2]).parse().unwrap();
+        let quiet_ip: SocketAddr = format!("10.{}.{}.{}:5000", bytes[3], bytes[4], bytes[5]).parse().unwrap();
+        // 계정 로그인 세션은 계정 ID, 공용 비밀번호 세션은 토큰 해시로 구분
+        let busy_account = uuid::Uuid::new_v4().to_string();
+        let busy_staff = insert_test_staff_session(&state, Some(&busy_account));
+        let quiet_staff = insert_test_staff_session(&state, None);
+        let busy_staff_id = session_staff_identifier(&state, &busy_staff);
+        let quiet_staff_id = session_staff_identifier(&state, &quiet_staff);
+        assert_eq!(busy_staff_id, busy_account);
+        assert!(quiet_staff_id.starts_with("session:"));
+        assert!(!quiet_staff.contains(&quiet_staff_id["session:".len()..]));
+
+        // 임계치 이하 - 정상 사용
+        for _ in 0..2 {
+            record_session_creation(&state, &request_id, &uuid::Uuid::new_v4().to_string(), &busy_staff, busy_ip);
+        }
+        assert_eq!(abuse_notifications_for(&format!("직원 {}", busy_staff_id)), 0);
+        assert_eq!(abuse_notifications_for(&format!("IP {}", busy_ip.ip())), 0);
+
+        // 다른 직원/IP 기록은 서로의 카운트에 섞이지 않음
+        record_session_creation(&state, &request_id, &uuid::Uuid::new_v4().to_string(), &quiet_staff, quiet_ip);
+
+        // 임계치 초과 시점에 직원/IP 각각 한 번씩 알림, 이후 추가 생성으로 중복 알림 없음
+        for _ in 0..3 {
+            record_session_creation(&state, &request_id, &uuid::Uuid::new_v4().to_string(), &busy_staff, busy_ip);
+        }
+        assert_eq!(abuse_notifications_for(&format!("직원 {}", busy_staff_id)), 1);
+        assert_eq!(abuse_notifications_for(&format!("IP {}", busy_ip.ip())), 1);
+        assert_eq!(abuse_notifications_for(&format!("직원 {}", quiet_staff_id)), 0);
+        assert_eq!(abuse_notifications_for(&format!("IP {}", quiet_ip.ip())), 0);
+    }
+
+    #[test]
+    fn shared_ip_is_counted_across_staff() {
+        db::init_test_database();
+        let state = AppState::new().with_session_abuse_threshold(SessionAbuseThreshold { max_sessions: 2, window_minutes: 10 });
+        let request_id = RequestId("test".to_string());
+        let bytes = *uuid::Uuid::new_v4().as_bytes();
+        let ip: SocketAddr = format!("172.16.{}.{}:5000", bytes[0], bytes[1]).parse().unwrap();
+
+        // 직원별로는 한 건씩이지만 같은 IP에서 임계치를 넘음
+        let staff: Vec<String> = (0..3).map(|_| uuid::Uuid::new_v4().simple().to_string()).collect();
+        for token in &staff {
+            record_session_creation(&state, &request_id, &uuid::Uuid::new_v4().to_string(), token, ip);
+        }
+        assert_eq!(abuse_notifications_for(&format!("IP {}", ip.ip())), 1);
+        for token in &staff {
+            assert_eq!(abuse_notifications_for(&format!("직원 {}", session_staff_identifier(&state, token))), 0);
+        }
+    }
+
+    #[tokio::test]
+    async fn session_cancel_and_extend_apis_conflict_on_finished_sessions() {
+        db::init_test_database();
+        let state = AppState::new();
+        let token = insert_test_staff_session(&state, None);
+        let session = db::create_survey_session(None, "default_female_health", Some("취소 테스트"), None, None, None, None, None, None, false, None)
+            .unwrap();
+        let post = |path: String| {
+            let request = axum::http::Request::builder()
+                .method(Method::POST)
+                .uri(path)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(r#"{"hours": 48}"#))
+                .unwrap();
+            create_router(state.clone()).oneshot(request)
+        };
+
+        let extend = format!("/api/sessions/{}/extend?token={}", session.id, token);
+        let cancel = format!("/api/sessions/{}/cancel?token={}", session.id, token);
+        assert_eq!(post(format!("/api/sessions/{}/cancel", session.id)).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(post(extend.clone()).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(post(cancel.clone()).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(post(cancel).await.unwrap().status(), StatusCode::CONFLICT);
+        assert_eq!(post(extend).await.unwrap().status(), StatusCode::CONFLICT);
+        assert_eq!(status_of(&state, Method::GET, &format!("/api/sessions?status=pending&token={}", token)).await, StatusCode::OK);
+    }
+
+    #[test]
+    fn sweep_is_skipped_while_another_instance_holds_the_lock() {
+        db::init_test_database();
+        // 살아 있는 다른 인스턴스가 락을 가진 상태 - 정리/알림 작업을 하지 않고 건너뜀
+        db::insert_test_scheduler_lock(SESSION_SWEEP_LOCK, "other-process", 0);
+        assert!(run_checks_once().unwrap().is_none());
+        assert!(!db::try_acquire_scheduler_lock(SESSION_SWEEP_LOCK, 3600).unwrap());
+    }
+
+    /// 문항 하나짜리 테스트 템플릿 저장 (반환: 템플릿 ID)
+    fn save_contact_template(require_contact: bool, anonymous: bool) -> AppResult<String> {
+        db::init_test_database();
+        let template = db::SurveyTemplateDb {
+            id: format!("contact-{}", uuid::Uuid::new_v4()),
+            name: "연락처 테스트".to_string(),
+            description: None,
+            questions: vec![crate::models::SurveyQuestion {
+                id: "q1".to_string(),
+                question_type: crate::models::QuestionType::Text,
+                question_text: "불편한 곳".to_string(),
+                required: false,
+                options: None,
+                scale_config: None,
+                order: Some(1),
+                shuffle_options: false,
+                fixed_position: false,
+                translations: None,
+            }],
+            display_mode: None,
+            is_active: true,
+            require_contact,
+            anonymous,
+            redirect_url: None,
+            category: None,
+            randomize_questions: false,
+            randomize_options: false,
+            pin_required_questions: false,
+        };
+        db::save_survey_template(&template)?;
+        Ok(template.id)
+    }
+
+    async fn submit_contact(template_id: &str, session_name: Option<&str>, contact: serde_json::Value) -> StatusCode {
+        let session = db::create_survey_session(None, template_id, session_name, None, None, None, None, None, None, false, None)
+            .unwrap();
+        let body = serde_json::json!({"answers": [], "contact": contact});
+        let request = axum::http::Request::builder()
+            .method(Method::POST)
+            .uri(format!("/api/survey/{}", session.token))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        create_router(AppState::new()).oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn required_contact_is_checked_on_submit() {
+        let required = save_contact_template(true, false).unwrap();
+        let none = serde_json::Value::Null;
+        assert_eq!(submit_contact(&required, None, none.clone()).await, StatusCode::BAD_REQUEST);
+        assert_eq!(submit_contact(&required, None, serde_json::json!({"respondent_name": "홍길동"})).await, StatusCode::BAD_REQUEST);
+        assert_eq!(submit_contact(&required, None, serde_json::json!({"phone": "010-1234-5678"})).await, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            submit_contact(&required, None, serde_json::json!({"respondent_name": "홍길동", "phone": "010-1234-5678"})).await,
+            StatusCode::OK
+        );
+        // 키오스크처럼 세션에 이름이 있으면 생년월일만으로 충분
+        assert_eq!(submit_contact(&required, Some("홍길동"), serde_json::json!({"birth_date": "1980-01-31"})).await, StatusCode::OK);
+
+        // 플래그가 꺼져 있으면 기존처럼 연락처 없이 제출
+        let optional = save_contact_template(false, false).unwrap();
+        assert_eq!(submit_contact(&optional, None, none).await, StatusCode::OK);
+
+        // 익명 설문과는 함께 설정할 수 없음
+        assert!(save_contact_template(true, true).is_err());
+    }
+
+    #[test]
+    fn feature_flag_update_rejects_unknown_plan_and_keeps_state() {
+        let state = AppState::with_plan("basic".to_string(), false);
+        assert!(matches!(
+            state.update_feature_flags(Some("gold"), Some(true)),
+            Err(crate::error::AppError::Validation(_))
+        ));
+        let flags = state.feature_flags();
+        assert_eq!((flags.plan_type.as_str(), flags.survey_external_enabled), ("basic", false));
+
+        // 지정한 값만 바뀌고, 복제된 상태(핸들러마다 받는 State)에도 바로 보임
+        let handler_state = state.clone();
+        handler_state.update_feature_flags(None, Some(true)).unwrap();
+        let flags = state.feature_flags();
+        assert_eq!((flags.plan_type.as_str(), flags.survey_external_enabled), ("basic", true));
+    }
+
+    #[test]
+    fn concurrent_feature_flag_updates_stay_consistent() {
+        let state = AppState::new();
+        let writers: Vec<_> = (0..8)
+            .map(|i| {
+                let state = state.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..100 {
+                        // premium일 때만 온라인 설문을 켜는 쌍으로 갱신
+                        let premium = i % 2 == 0;
+                        let plan = if premium { "premium" } else { "free" };
+                        let flags = state.update_feature_flags(Some(plan), Some(premium)).unwrap();
+                        assert_eq!(flags.plan_type == "premium", flags.survey_external_enabled);
+                        let read = state.feature_flags();
+                        assert_eq!(read.plan_type == "premium", read.survey_external_enabled);
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn file_upload_is_stored_before_submit_and_served_to_staff() {
+        db::init_test_database();
+        let template = db::SurveyTemplateDb {
+            id: format!("upload-{}", uuid::Uuid::new_v4()),
+            name: "검사지 첨부".to_string(),
+            description: None,
+            questions: vec![crate::models::SurveyQuestion {
+                id: "photo".to_string(),
+                question_type: crate::models::QuestionType::FileUpload,
+                question_text: "검사지 사진".to_string(),
+                required: true,
+                options: None,
+                scale_config: None,
+                order: Some(1),
+                shuffle_options: false,
+                fixed_position: false,
+                translations: None,
+            }],
+            display_mode: None,
+            is_active: true,
+            require_contact: false,
+            anonymous: false,
+            redirect_url: None,
+            category: None,
+            randomize_questions: false,
+            randomize_options: false,
+            pin_required_questions: false,
+        };
+        db::save_survey_template(&template).unwrap();
+        let session = db::create_survey_session(None, &template.id, Some("첨부 환자"), None, None, None, None, None, None, false, None)
+            .unwrap();
+        let state = AppState::new();
+        let png = b"\x89PNG\r\n\x1a\nimage".to_vec();
+
+        let upload = |question_id: &str, mime: &str, data: Vec<u8>| {
+            let request = axum::http::Request::builder()
+                .method(Method::POST)
+                .uri(format!("/api/survey/{}/attachments?question_id={}&file_name=scan.png", session.token, question_id))
+                .header(header::CONTENT_TYPE, mime)
+                .body(Body::from(data))
+                .unwrap();
+            create_router(state.clone()).oneshot(request)
+        };
+        assert_eq!(upload("q-none", "image/png", png.clone()).await.unwrap().status(), StatusCode::BAD_REQUEST);
+        assert_eq!(upload("photo", "text/plain", b"text".to_vec()).await.unwrap().status(), StatusCode::BAD_REQUEST);
+        let oversized = vec![0u8; db::SURVEY_ATTACHMENT_MAX_BYTES + 1];
+        assert_eq!(upload("photo", "image/png", oversized).await.unwrap().status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let response = upload("photo", "image/png", png.clone()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let uploaded: serde_json::Value =
+            serde_json::from_slice(&axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        let attachment_id = uploaded["attachment_id"].as_str().unwrap().to_string();
+
+        let body = serde_json::json!({"answers": [{"question_id": "photo", "answer": uploaded}]});
+        let submit = axum::http::Request::builder()
+            .method(Method::POST)
+            .uri(format!("/api/survey/{}", session.token))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        assert_eq!(create_router(state.clone()).oneshot(submit).await.unwrap().status(), StatusCode::OK);
+        let (attachment, _) = db::get_survey_attachment(&attachment_id).unwrap().unwrap();
+        assert!(attachment.response_id.is_some());
+        // 제출된 설문에는 더 올릴 수 없음
+        assert_eq!(upload("photo", "image/png", png.clone()).await.unwrap().status(), StatusCode::BAD_REQUEST);
+
+        let uri = format!("/api/survey-attachments/{}", attachment_id);
+        assert_eq!(status_of(&state, Method::GET, &uri).await, StatusCode::UNAUTHORIZED);
+        let token = insert_test_staff_session(&state, None);
+        let request = axum::http::Request::builder()
+            .uri(format!("{}?token={}", uri, token))
+            .body(Body::empty())
+            .unwrap();
+        let response = create_router(state.clone()).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+        assert_eq!(axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap().to_vec(), png);
+    }
+
+    async fn submit_for_redirect(template_id: &str, query: &str) -> serde_json::Value {
+        let session = db::create_survey_session(None, template_id, Some("이동 테스트"), None, None, None, None, None, None, false, None)
+            .unwrap();
+        let request = axum::http::Request::builder()
+            .method(Method::POST)
+            .uri(format!("/api/survey/{}{}", session.token, query))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(serde_json::json!({"answers": []}).to_string()))
+            .unwrap();
+        let response = create_router(AppState::new()).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        serde_json::from_slice(&axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn submit_returns_only_the_template_redirect_url() {
+        let plain = save_contact_template(false, false).unwrap();
+        assert_eq!(submit_for_redirect(&plain, "").await["redirect_url"], serde_json::Value::Null);
+        // 요청으로 넘긴 주소는 무시 (오픈 리다이렉트 방지)
+        let forged = submit_for_redirect(&plain, "?redirect_url=https://evil.example.com").await;
+        assert_eq!(forged["redirect_url"], serde_json::Value::Null);
+
+        let mut template = db::get_survey_template(&plain).unwrap().unwrap();
+        template.redirect_url = Some("javascript:alert(1)".to_string());
+        assert!(matches!(db::save_survey_template(&template), Err(crate::error::AppError::Validation(_))));
+        template.redirect_url = Some("https://clinic.example.com/booking".to_string());
+        db::save_survey_template(&template).unwrap();
+        let submitted = submit_for_redirect(&plain, "?redirect_url=https://evil.example.com").await;
+        assert_eq!(submitted["redirect_url"], "https://clinic.example.com/booking");
+    }
+
+    #[tokio::test]
+    async fn off_step_scale_answer_is_unprocessable() {
+        let template_id = save_contact_template(false, false).unwrap();
+        let mut template = db::get_survey_template(&template_id).unwrap().unwrap();
+        template.questions[0] = crate::models::SurveyQuestion {
+            id: "pain".to_string(),
+            question_type: crate::models::QuestionType::Scale,
+            scale_config: Some(crate::models::ScaleConfig { min: 0, max: 100, min_label: None, max_label: None, step: Some(10) }),
+            ..template.questions[0].clone()
+        };
+        db::save_survey_template(&template).unwrap();
+
+        let template_id = template_id.as_str();
+        let submit = |answer: serde_json::Value| async move {
+            let session = db::create_survey_session(None, template_id, Some("척도"), None, None, None, None, None, None, false, None)
+                .unwrap();
+            let body = serde_json::json!({"answers": [{"question_id": "pain", "answer": answer}]});
+            let request = axum::http::Request::builder()
+                .method(Method::POST)
+                .uri(format!("/api/survey/{}", session.token))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            create_router(AppState::new()).oneshot(request).await.unwrap().status()
+        };
+        assert_eq!(submit(serde_json::json!(35)).await, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(submit(serde_json::json!(120)).await, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(submit(serde_json::json!(30)).await, StatusCode::OK);
+    }
+
+    /// 로그인 시각과 마지막 요청 시각을 지정한 직원 세션 (DB와 캐시에 저장, 반환: 토큰)
+    fn aged_staff_session(state: &AppState, age: chrono::Duration, idle: chrono::Duration) -> String {
+        db::init_test_database();
+        let now = chrono::Utc::now();
+        let session = StaffSession {
+            token: generate_session_token(),
+            clinic_name: String::new(),
+            account_id: None,
+            created_at: now - age,
+            last_activity: now - idle,
+            must_change_password: false,
+            activity_saved_at: now - idle,
+        };
+        db::save_web_session(&session.token, &session.to_stored()).unwrap();
+        state.staff_sessions.lock().unwrap().insert(session.token.clone(), session.clone());
+        session.token
+    }
+
+    #[test]
+    fn staff_session_expires_on_idle_or_absolute_age() {
+        let now = chrono::Utc::now();
+        let hours = chrono::Duration::hours;
+        let session = |age: i64, idle: i64| StaffSession {
+            token: String::new(),
+            clinic_name: String::new(),
+            account_id: None,
+            created_at: now - hours(age),
+            last_activity: now - hours(idle),
+            must_change_password: false,
+            activity_saved_at: now,
+        };
+        assert!(!session(30, 11).is_expired(now));
+        assert!(session(30, 13).is_expired(now));
+        assert!(!session(STAFF_SESSION_MAX_AGE_DAYS * 24 - 1, 0).is_expired(now));
+        assert!(session(STAFF_SESSION_MAX_AGE_DAYS * 24 + 1, 0).is_expired(now));
+
+        // 만료 시각은 유휴 만료와 최대 유지 기간 중 빠른 쪽
+        assert_eq!(session(1, 0).expires_at(), now + hours(STAFF_SESSION_IDLE_HOURS));
+        let near_cap = session(STAFF_SESSION_MAX_AGE_DAYS * 24 - 2, 0);
+        assert_eq!(near_cap.expires_at(), now + hours(2));
+    }
+
+    #[test]
+    fn activity_slides_idle_expiry_until_absolute_cap() {
+        let state = AppState::new();
+        let day = chrono::Duration::days(1);
+
+        // 이틀째 오후에도 계속 쓰던 세션은 유지되고 마지막 요청 시각이 갱신됨
+        let active = aged_staff_session(&state, day + chrono::Duration::hours(6), chrono::Duration::hours(11));
+        assert!(is_valid_staff_token(&state, &active));
+        let last_activity = state.staff_sessions.lock().unwrap()[&active].last_activity;
+        assert!(chrono::Utc::now() - last_activity < chrono::Duration::minutes(1));
+        // 캐시가 비어도 (서버 재시작) DB에서 이어서 확인
+        assert!(is_valid_staff_token(&AppState::new(), &active));
+
+        let idle = aged_staff_session(&state, day, chrono::Duration::hours(STAFF_SESSION_IDLE_HOURS + 1));
+        assert!(!is_valid_staff_token(&state, &idle));
+        assert!(!state.staff_sessions.lock().unwrap().contains_key(&idle));
+        assert!(db::get_web_session(&idle).unwrap().is_none());
+
+        let over_cap = chrono::Duration::days(STAFF_SESSION_MAX_AGE_DAYS) + chrono::Duration::minutes(1);
+        let capped = aged_staff_session(&state, over_cap, chrono::Duration::minutes(1));
+        assert!(!is_valid_staff_token(&state, &capped));
+    }
+
+    #[tokio::test]
+    async fn refresh_replaces_token_but_keeps_login_time() {
+        let state = AppState::new();
+        let age = chrono::Duration::days(STAFF_SESSION_MAX_AGE_DAYS) - chrono::Duration::hours(1);
+        let token = aged_staff_session(&state, age, chrono::Duration::minutes(5));
+        let created_at = state.staff_sessions.lock().unwrap()[&token].created_at;
+
+        let request = axum::http::Request::builder()
+            .method(Method::POST)
+            .uri(format!("/api/auth/refresh?token={}", token))
+            .header(header::CONTENT_LENGTH, "0")
+            .body(Body::empty())
+            .unwrap();
+        let response = create_router(state.clone()).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let refreshed: serde_json::Value =
+            serde_json::from_slice(&axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        let new_token = refreshed["token"].as_str().unwrap().to_string();
+        assert_ne!(new_token, token);
+
+        assert!(!is_valid_staff_token(&state, &token));
+        assert!(is_valid_staff_token(&state, &new_token));
+        assert!(db::get_web_session(&token).unwrap().is_none());
+        // 재발급해도 최대 유지 기간은 처음 로그인 기준
+        let session = state.staff_sessions.lock().unwrap()[&new_token].clone();
+        assert_eq!(session.created_at, created_at);
+        let expires_at: chrono::DateTime<chrono::Utc> = refreshed["expires_at"].as_str().unwrap().parse().unwrap();
+        assert_eq!(expires_at, created_at + chrono::Duration::days(STAFF_SESSION_MAX_AGE_DAYS));
+
+        assert_eq!(status_of(&state, Method::POST, &format!("/api/auth/refresh?token={}", token)).await, StatusCode::UNAUTHORIZED);
+        assert!(refresh_staff_session(&state, "").is_none());
+    }
+
+    #[tokio::test]
+    async fn reminder_sweep_texts_each_expiring_session_once() {
+        db::init_test_database();
+        // 받은 문자를 기록하는 로컬 게이트웨이
+        let received = Arc::new(Mutex::new(Vec::<serde_json::Value>::new()));
+        let sink = received.clone();
+        let gateway = Router::new().route(
+            "/send",
+            post(move |Json(body): Json<serde_json::Value>| async move {
+                sink.lock().unwrap().push(body);
+                StatusCode::OK
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let gateway_url = format!("http://{}/send", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, gateway).await });
+
+        let patient = crate::models::Patient {
+            phone: Some("010-7777-8888".to_string()),
+            ..crate::models::Patient::new("문자 환자".to_string())
+        };
+        db::create_patient(&patient, true).unwrap();
+        let expires_at = Some(chrono::Utc::now() + chrono::Duration::minutes(30));
+        let session = |patient_id: Option<&str>| {
+            db::create_survey_session(patient_id, "default_female_health", Some("응답자"), None, None, None, None, None, None, false, expires_at)
+                .unwrap()
+                .id
+        };
+        let with_phone = session(Some(&patient.id));
+        let without_phone = session(None);
+
+        // 설정이 꺼져 있으면 아무것도 하지 않음
+        run_survey_reminder_jobs().await;
+        assert!(db::claim_survey_reminder(&with_phone).unwrap());
+        db::release_survey_reminder(&with_phone).unwrap();
+
+        db::save_sms_settings(Some(&crate::models::SmsSettings {
+            provider: crate::models::SmsProvider::HttpGateway,
+            gateway_url,
+            api_key: "test-key".to_string(),
+            user_id: String::new(),
+            sender: "02-123-4567".to_string(),
+        }))
+        .unwrap();
+        db::set_survey_reminder_hours(Some(1)).unwrap();
+        run_survey_reminder_jobs().await;
+        run_survey_reminder_jobs().await;
+        db::set_survey_reminder_hours(None).unwrap();
+        db::save_sms_settings(None).unwrap();
+
+        let sent: Vec<_> = received.lock().unwrap().iter().filter(|m| m["to"] == "01077778888").cloned().collect();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0]["message"].as_str().unwrap().contains("문자 환자님"));
+        // 연락처가 없는 세션도 처리한 것으로 기록되어 다시 조회되지 않음
+        assert!(!db::claim_survey_reminder(&with_phone).unwrap());
+        assert!(!db::claim_survey_reminder(&without_phone).unwrap());
+    }
+
+    #[tokio::test]
+    async fn randomized_session_renders_stably_and_scores_by_question_id() {
+        let template_id = save_contact_template(false, false).unwrap();
+        let mut template = db::get_survey_template(&template_id).unwrap().unwrap();
+        let base = template.questions[0].clone();
+        template.questions = (0..6)
+            .map(|i| crate::models::SurveyQuestion {
+                id: format!("q{}", i),
+                question_type: crate::models::QuestionType::SingleChoice,
+                options: Some(vec!["가".to_string(), "나".to_string(), "다".to_string()]),
+                order: Some(i),
+                ..base.clone()
+            })
+            .chain(std::iter::once(crate::models::SurveyQuestion {
+                id: "pain".to_string(),
+                question_type: crate::models::QuestionType::Scale,
+                required: true,
+                scale_config: Some(crate::models::ScaleConfig { min: 0, max: 10, min_label: None, max_label: None, step: Some(2) }),
+                order: Some(6),
+                ..base.clone()
+            }))
+            .collect();
+        template.randomize_questions = true;
+        template.randomize_options = true;
+        template.pin_required_questions = true;
+        db::save_survey_template(&template).unwrap();
+
+        let session = db::create_survey_session(None, &template_id, Some("무작위"), None, None, None, None, None, None, false, None)
+            .unwrap();
+        let order = session.display_order.clone().unwrap();
+        assert_eq!(order.question_ids[6], "pain");
+        assert_eq!(db::get_survey_session_by_token(&session.token).unwrap().unwrap().display_order, Some(order.clone()));
+
+        let render = || async {
+            let request = axum::http::Request::builder().uri(format!("/api/survey/{}", session.token)).body(Body::empty()).unwrap();
+            let response = create_router(AppState::new()).oneshot(request).await.unwrap();
+            let body: serde_json::Value =
+                serde_json::from_slice(&axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+            body["template"]["questions"].clone()
+        };
+        let first = render().await;
+        assert_eq!(first, render().await);
+        let rendered_ids: Vec<&str> = first.as_array().unwrap().iter().map(|q| q["id"].as_str().unwrap()).collect();
+        assert_eq!(rendered_ids, order.question_ids);
+
+        let submit = |answer: serde_json::Value| {
+            let body = serde_json::json!({"answers": [
+                {"question_id": "q0", "answer": "다"},
+                {"question_id": "pain", "answer": answer},
+            ]});
+            let request = axum::http::Request::builder()
+                .method(Method::POST)
+                .uri(format!("/api/survey/{}", session.token))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            create_router(AppState::new()).oneshot(request)
+        };
+        assert_eq!(submit(serde_json::json!(5)).await.unwrap().status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(submit(serde_json::json!(6)).await.unwrap().status(), StatusCode::OK);
+    }
+}
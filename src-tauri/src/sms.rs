@@ -0,0 +1,106 @@
+//! 문자(SMS) 발송 모듈
+//!
+//! 복약 알림을 환자 휴대폰으로 보냅니다. 업체는 알림 설정에서 고르며,
+//! 직접 운영하는 HTTP 게이트웨이(JSON POST) 또는 알리고를 지원합니다.
+
+use std::time::Duration;
+
+use crate::db;
+use crate::error::{AppError, AppResult};
+use crate::models::{SmsProvider, SmsSettings};
+
+/// 발송 요청 제한 시간
+const SEND_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// 알리고 문자 발송 API
+const ALIGO_SEND_URL: &str = "https://apis.aligo.in/send/";
+
+/// 문자 발송 (받는 번호는 휴대폰 번호만 허용)
+pub async fn send(to_phone: &str, message: &str) -> AppResult<()> {
+    let receiver = db::normalize_mobile_phone(to_phone)
+        .ok_or_else(|| AppError::Validation(format!("휴대폰 번호가 올바르지 않습니다: {}", to_phone)))?;
+    let settings = db::get_sms_settings()?
+        .ok_or_else(|| AppError::Custom("문자 발송 설정이 없습니다".to_string()))?;
+
+    let client = reqwest::Client::builder().timeout(SEND_TIMEOUT).build()?;
+    match settings.provider {
+        SmsProvider::HttpGateway => send_via_gateway(&client, &settings, &receiver, message).await,
+        SmsProvider::Aligo => send_via_aligo(&client, &settings, &receiver, message).await,
+    }
+}
+
+/// 복약 알림 문자 내용
+pub fn medication_reminder_message(clinic_name: &str, patient_name: &str, time: &str) -> String {
+    format!("[{}] {}님, {} 한약 복용 시간입니다.", clinic_name, patient_name, time)
+}
+
+/// 미완료 설문 리마인더 문자 내용 (링크는 처음 보낸 안내를 다시 쓰도록 넣지 않음)
+pub fn survey_reminder_message(clinic_name: &str, name: Option<&str>, template_name: &str, expires_at: &str) -> String {
+    let greeting = name.map(|n| format!("{}님, ", n)).unwrap_or_default();
+    format!(
+        "[{}] {}요청드린 '{}' 설문이 아직 완료되지 않았습니다. {}까지 받으신 링크로 작성해 주세요.",
+        clinic_name, greeting, template_name, expires_at
+    )
+}
+
+/// HTTP 게이트웨이: `{"to", "from", "message"}`를 Bearer 키로 POST, 2xx면 성공
+async fn send_via_gateway(client: &reqwest::Client, settings: &SmsSettings, to: &str, message: &str) -> AppResult<()> {
+    let response = client
+        .post(&settings.gateway_url)
+        .bearer_auth(&settings.api_key)
+        .json(&serde_json::json!({ "to": to, "from": settings.sender, "message": message }))
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(AppError::Custom(format!("문자 게이트웨이 오류 ({}): {}", status, body.trim())));
+    }
+    Ok(())
+}
+
+/// 알리고: 폼 POST, 응답의 result_code가 1이면 성공 (길이에 따라 SMS/LMS 자동 선택)
+async fn send_via_aligo(client: &reqwest::Client, settings: &SmsSettings, to: &str, message: &str) -> AppResult<()> {
+    let response: serde_json::Value = client
+        .post(ALIGO_SEND_URL)
+        .form(&[
+            ("key", settings.api_key.as_str()),
+            ("user_id", settings.user_id.as_str()),
+            ("sender", settings.sender.as_str()),
+            ("receiver", to),
+            ("msg", message),
+        ])
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    // result_code는 숫자 또는 문자열로 옴
+    let code = match &response["result_code"] {
+        serde_json::Value::Number(n) => n.as_i64(),
+        serde_json::Value::String(s) => s.parse().ok(),
+        _ => None,
+    };
+    if code != Some(1) {
+        let message = response["message"].as_str().unwrap_or("알 수 없는 오류");
+        return Err(AppError::Custom(format!("알리고 발송 실패: {}", message)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn survey_reminder_message_greets_only_known_names() {
+        let named = survey_reminder_message("고시방한의원", Some("홍길동"), "초진 설문", "03/05 18:00");
+        assert_eq!(
+            named,
+            "[고시방한의원] 홍길동님, 요청드린 '초진 설문' 설문이 아직 완료되지 않았습니다. 03/05 18:00까지 받으신 링크로 작성해 주세요."
+        );
+        let anonymous = survey_reminder_message("고시방한의원", None, "초진 설문", "03/05 18:00");
+        assert!(anonymous.starts_with("[고시방한의원] 요청드린 '초진 설문'"));
+    }
+}
@@ -5,14 +5,22 @@
 use crate::auth;
 use crate::db;
 use crate::error::{AppError, AppResult};
-use once_cell::sync::OnceCell;
+use chrono::{DateTime, Utc};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
 
 /// 동기화 상태
 static SYNC_ENABLED: AtomicBool = AtomicBool::new(false);
-static PENDING_SYNC: OnceCell<Mutex<Vec<PendingSyncItem>>> = OnceCell::new();
+/// 마지막으로 Supabase 전송에 성공한 시각
+static LAST_SYNC_AT: Mutex<Option<DateTime<Utc>>> = Mutex::new(None);
+
+/// 한 번에 재시도할 최대 항목 수
+const RETRY_BATCH_SIZE: u32 = 50;
+
+/// 재시도 대기 상한 (분)
+const MAX_RETRY_DELAY_MINUTES: i64 = 120;
 
 /// 동기화 대기 항목
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -22,6 +30,7 @@ pub struct PendingSyncItem {
     pub data: serde_json::Value,
     pub created_at: String,
     pub retry_count: u32,
+    pub next_retry_at: DateTime<Utc>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -29,10 +38,56 @@ pub enum SyncItemType {
     SurveyResponse,
 }
 
+impl SyncItemType {
+    /// sync_queue.item_type 저장값
+    fn as_str(&self) -> &'static str {
+        match self {
+            SyncItemType::SurveyResponse => "survey_response",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "survey_response" => Some(SyncItemType::SurveyResponse),
+            _ => None,
+        }
+    }
+}
+
+impl PendingSyncItem {
+    fn from_db(item: db::SyncQueueItemDb) -> Option<Self> {
+        Some(Self {
+            item_type: SyncItemType::parse(&item.item_type)?,
+            data: serde_json::from_str(&item.data).ok()?,
+            id: item.id,
+            next_retry_at: DateTime::parse_from_rfc3339(&item.next_retry_at)
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now()),
+            created_at: item.created_at,
+            retry_count: item.retry_count.max(0) as u32,
+        })
+    }
+}
+
+/// 다음 재시도 시각: 2^retry_count분 + 지터(0~59분), 최대 2시간
+pub fn next_retry_at(retry_count: u32) -> DateTime<Utc> {
+    let backoff = 2_i64.saturating_pow(retry_count.min(16));
+    let jitter = rand::thread_rng().gen_range(0..60);
+    let minutes = (backoff + jitter).min(MAX_RETRY_DELAY_MINUTES);
+    Utc::now() + chrono::Duration::minutes(minutes)
+}
+
 /// 동기화 초기화
+///
+/// 대기열은 DB에 저장되므로 재시작 후에도 유지됩니다.
+/// 전송 중 상태로 남은 항목은 DB가 열릴 때 복구되며, 이미 열려 있으면 여기서 복구합니다.
 pub fn init_sync() {
-    let _ = PENDING_SYNC.set(Mutex::new(Vec::new()));
     SYNC_ENABLED.store(true, Ordering::SeqCst);
+    if db::is_database_initialized() {
+        if let Err(e) = db::reset_inflight_sync_items() {
+            log::warn!("Failed to reset in-flight sync items: {}", e);
+        }
+    }
     log::info!("Sync module initialized");
 }
 
@@ -70,6 +125,7 @@ pub async fn sync_survey_response(response: &db::SurveyResponseDb) -> AppResult<
         }
         Err(e) => {
             log::warn!("Sync failed, queuing for retry: {}", e);
+            crate::metrics::record_sync_failure();
             queue_for_sync(response)?;
             Err(e)
         }
@@ -107,6 +163,9 @@ async fn send_to_supabase(response: &db::SurveyResponseDb) -> AppResult<()> {
         .map_err(|e| AppError::Custom(format!("Network error: {}", e)))?;
 
     if res.status().is_success() {
+        if let Ok(mut last) = LAST_SYNC_AT.lock() {
+            *last = Some(Utc::now());
+        }
         Ok(())
     } else {
         let status = res.status();
@@ -120,33 +179,14 @@ async fn send_to_supabase(response: &db::SurveyResponseDb) -> AppResult<()> {
 
 /// 동기화 대기열에 추가
 fn queue_for_sync(response: &db::SurveyResponseDb) -> AppResult<()> {
-    let pending = PENDING_SYNC
-        .get()
-        .ok_or_else(|| AppError::Custom("Sync not initialized".to_string()))?;
+    let data = serde_json::to_string(response)?;
+    db::enqueue_sync_item(&response.id, SyncItemType::SurveyResponse.as_str(), &data)?;
 
-    let mut queue = pending
-        .lock()
-        .map_err(|_| AppError::Custom("Sync queue lock error".to_string()))?;
-
-    // 이미 대기 중인지 확인
-    if queue.iter().any(|item| item.id == response.id) {
-        return Ok(());
-    }
-
-    queue.push(PendingSyncItem {
-        id: response.id.clone(),
-        item_type: SyncItemType::SurveyResponse,
-        data: serde_json::to_value(response).unwrap_or_default(),
-        created_at: chrono::Utc::now().to_rfc3339(),
-        retry_count: 0,
-    });
-
-    log::info!("Queued for sync: {}, total pending: {}", response.id, queue.len());
+    log::info!("Queued for sync: {}, total pending: {}", response.id, get_pending_count());
     Ok(())
 }
 
 /// 대기 중인 항목 동기화 재시도
-#[allow(dead_code)]
 pub async fn retry_pending_sync() -> AppResult<u32> {
     if !is_sync_enabled() {
         return Ok(0);
@@ -157,63 +197,50 @@ pub async fn retry_pending_sync() -> AppResult<u32> {
         return Ok(0);
     }
 
-    let pending = PENDING_SYNC
-        .get()
-        .ok_or_else(|| AppError::Custom("Sync not initialized".to_string()))?;
-
-    let items: Vec<PendingSyncItem> = {
-        let queue = pending
-            .lock()
-            .map_err(|_| AppError::Custom("Sync queue lock error".to_string()))?;
-        queue.clone()
-    };
-
+    let items = db::dequeue_sync_items(RETRY_BATCH_SIZE)?;
     let mut synced_count = 0;
-    let mut failed_items = Vec::new();
 
-    for item in items {
-        if item.retry_count >= 5 {
-            log::warn!("Max retries exceeded for: {}", item.id);
+    for row in items {
+        let id = row.id.clone();
+        let retry_count = row.retry_count.max(0) as u32;
+        let Some(item) = PendingSyncItem::from_db(row) else {
+            // 알 수 없는 형식은 재시도해도 실패하므로 한도까지 실패 처리
+            log::warn!("Invalid sync item: {}", id);
+            db::mark_sync_item_failed(&id, next_retry_at(retry_count))?;
             continue;
-        }
+        };
 
         match item.item_type {
             SyncItemType::SurveyResponse => {
-                if let Ok(response) = serde_json::from_value::<db::SurveyResponseDb>(item.data.clone()) {
-                    match send_to_supabase(&response).await {
-                        Ok(_) => {
-                            synced_count += 1;
-                            log::info!("Retry sync successful: {}", item.id);
-                        }
-                        Err(e) => {
-                            log::warn!("Retry sync failed: {}: {}", item.id, e);
-                            let mut failed = item.clone();
-                            failed.retry_count += 1;
-                            failed_items.push(failed);
-                        }
+                let sent = match serde_json::from_value::<db::SurveyResponseDb>(item.data.clone()) {
+                    Ok(response) => send_to_supabase(&response).await,
+                    Err(e) => Err(e.into()),
+                };
+                match sent {
+                    Ok(_) => {
+                        db::remove_sync_item(&item.id)?;
+                        synced_count += 1;
+                        log::info!("Retry sync successful: {}", item.id);
+                    }
+                    Err(e) => {
+                        let next = next_retry_at(item.retry_count);
+                        log::warn!("Retry sync failed: {} (retry {}, next at {}): {}", item.id, item.retry_count + 1, next, e);
+                        db::mark_sync_item_failed(&item.id, next)?;
                     }
                 }
             }
         }
     }
 
-    // 실패한 항목 다시 저장
-    {
-        let mut queue = pending
-            .lock()
-            .map_err(|_| AppError::Custom("Sync queue lock error".to_string()))?;
-        *queue = failed_items;
-    }
-
     Ok(synced_count)
 }
 
 /// 대기 중인 동기화 항목 수
-#[allow(dead_code)]
 pub fn get_pending_count() -> usize {
-    PENDING_SYNC
-        .get()
-        .and_then(|p| p.lock().ok())
-        .map(|q| q.len())
-        .unwrap_or(0)
+    db::count_sync_items().map(|c| c as usize).unwrap_or(0)
+}
+
+/// 마지막 동기화 성공 시각
+pub fn last_sync_at() -> Option<DateTime<Utc>> {
+    LAST_SYNC_AT.lock().ok().and_then(|last| *last)
 }
@@ -0,0 +1,66 @@
+//! HTTPS(TLS) 설정 모듈
+//!
+//! 인증서/개인키 파일이 지정되면 그대로 사용하고, 지정되지 않았는데 HTTPS를 켜 두었으면
+//! 자체 서명 인증서를 만들어 데이터 폴더(`gosibang/tls`)에 보관해 재사용합니다.
+
+use std::path::{Path, PathBuf};
+
+use axum_server::tls_rustls::RustlsConfig;
+use tokio_rustls::rustls;
+
+use crate::error::{AppError, AppResult};
+
+/// 자체 서명 인증서 파일 이름
+const SELF_SIGNED_CERT_FILE: &str = "self_signed_cert.pem";
+const SELF_SIGNED_KEY_FILE: &str = "self_signed_key.pem";
+
+/// 서버 TLS 설정 읽기 (None이면 HTTP로 실행)
+///
+/// 인증서와 개인키 경로가 모두 있으면 해당 파일을, 둘 다 없고 `enabled`이면 자체 서명 인증서를 사용합니다.
+pub async fn load_config(enabled: bool, cert_path: Option<&Path>, key_path: Option<&Path>) -> AppResult<Option<RustlsConfig>> {
+    let (cert_path, key_path) = match (cert_path, key_path) {
+        (Some(cert), Some(key)) => (cert.to_path_buf(), key.to_path_buf()),
+        (None, None) if enabled => ensure_self_signed_cert()?,
+        (None, None) => return Ok(None),
+        _ => {
+            return Err(AppError::Validation("HTTPS 인증서와 개인키 경로를 모두 지정하세요".to_string()));
+        }
+    };
+
+    // rustls 기본 암호 모듈 등록 (이미 등록되어 있으면 무시)
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    let config = RustlsConfig::from_pem_file(&cert_path, &key_path)
+        .await
+        .map_err(|e| AppError::Custom(format!("HTTPS 인증서를 읽을 수 없습니다 ({}): {}", cert_path.display(), e)))?;
+    log::info!("HTTPS 인증서 사용: {}", cert_path.display());
+    Ok(Some(config))
+}
+
+/// 자체 서명 인증서 경로 (없으면 생성)
+///
+/// localhost, mDNS 이름, 현재 로컬 IP로 발급하며 IP가 바뀌면 인증서 파일을 지워 다시 만들면 됩니다.
+fn ensure_self_signed_cert() -> AppResult<(PathBuf, PathBuf)> {
+    let data_dir = dirs::data_local_dir()
+        .ok_or_else(|| AppError::Custom("Cannot find data directory".to_string()))?;
+    let tls_dir = data_dir.join("gosibang").join("tls");
+    std::fs::create_dir_all(&tls_dir)?;
+
+    let cert_path = tls_dir.join(SELF_SIGNED_CERT_FILE);
+    let key_path = tls_dir.join(SELF_SIGNED_KEY_FILE);
+    if cert_path.exists() && key_path.exists() {
+        return Ok((cert_path, key_path));
+    }
+
+    let mut names = vec!["localhost".to_string(), "gosibang.local".to_string(), "127.0.0.1".to_string()];
+    if let Some(ip) = crate::server::detect_local_ip() {
+        names.push(ip);
+    }
+    let certified = rcgen::generate_simple_self_signed(names.clone())
+        .map_err(|e| AppError::Custom(format!("자체 서명 인증서 생성 실패: {}", e)))?;
+    std::fs::write(&cert_path, certified.cert.pem())?;
+    std::fs::write(&key_path, certified.key_pair.serialize_pem())?;
+
+    log::info!("자체 서명 인증서 생성: {} ({})", cert_path.display(), names.join(", "));
+    Ok((cert_path, key_path))
+}
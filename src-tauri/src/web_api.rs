@@ -0,0 +1,2894 @@
+//! 직원용 REST API 모듈
+//!
+//! 인트라넷 웹 클라이언트에서 환자/처방 데이터를 다루는 JSON API를 제공합니다.
+//! 모든 요청은 직원 세션 토큰(`?token=`)이 필요합니다.
+
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::{header, Method, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Json, Response},
+    routing::{delete, get, post, put},
+    Extension, Router,
+};
+use std::collections::HashMap;
+use tower_http::cors::CorsLayer;
+use tower_http::limit::RequestBodyLimitLayer;
+
+use crate::auth;
+use crate::db;
+use crate::jobs;
+use crate::error::{AppError, AppResult};
+use crate::models::{ChartRecord, ClinicSettings, Patient, PatientAlert, PatientAlertSeverity, PatientAlertType, PatientPatch, Prescription, StaffAccount, StaffAccountInfo, StaffPermissions, StaffRole, SubscriptionStatus, SyncConflictChoice, SurveyExportRequest, TextMacro};
+use crate::server::{self, app_error_response, error_response, AppState, RequestId};
+use crate::sync;
+
+/// 요청 본문 최대 크기 (1MB)
+pub(crate) const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// 전체 내보내기 스트림 버퍼 크기
+const EXPORT_STREAM_BUFFER: usize = 64 * 1024;
+
+/// 목록 API 페이지 크기
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+/// 직원용 API 라우터
+pub fn routes(cors: CorsLayer) -> Router<AppState> {
+    Router::new()
+        .route("/api/prescriptions/{id}", put(update_prescription).delete(delete_prescription))
+        .route("/api/patients/{id}/prescription-history", get(get_prescription_history))
+        .route("/api/patients/{id}/events", get(get_patient_events))
+        .route("/api/patients/{id}/survey-series", get(get_patient_survey_series))
+        .route("/api/patients/{id}/treatment-outcomes", get(get_treatment_outcome_correlation))
+        .route(
+            "/api/charts/{id}",
+            get(get_chart_record).put(update_chart_record).delete(delete_chart_record),
+        )
+        .route("/api/text-macros", get(list_text_macros).post(create_text_macro))
+        .route("/api/text-macros/{id}", put(update_text_macro).delete(delete_text_macro))
+        .route("/api/documents/{document_type}/{id}/sign", post(sign_document))
+        .route("/api/documents/{id}/integrity", get(verify_document_integrity))
+        .route("/api/patients", get(list_patients).post(create_patient))
+        .route("/api/patients/tags", get(list_patient_tags))
+        .route("/api/patients/{id}/tags", get(get_patient_tags).post(add_patient_tags))
+        .route("/api/patients/{id}/tags/{tag}", delete(remove_patient_tag))
+        .route("/api/patients/{id}", get(get_patient).put(update_patient).patch(patch_patient).delete(delete_patient))
+        .route("/api/patients/{id}/export.pdf", get(export_patient_pdf))
+        .route("/api/patients/{id}/alerts", get(list_patient_alerts).post(create_patient_alert))
+        .route("/api/patient-alerts/{id}", put(update_patient_alert).delete(delete_patient_alert))
+        .route("/api/prescriptions", get(list_prescriptions))
+        .route("/api/prescriptions/by-batch/{batch_number}", get(list_prescriptions_by_batch))
+        .route("/api/notifications", get(list_notifications))
+        .route("/api/notifications/check-now", post(run_notification_checks))
+        .route("/api/notifications/{id}/snooze", post(snooze_notification))
+        .route("/api/survey-responses", get(list_survey_responses))
+        .route("/api/survey-templates/import", post(import_survey_template))
+        .route("/api/survey-templates/{id}/duplicate", post(duplicate_survey_template))
+        .route("/api/survey-templates/{id}/export", get(export_survey_template))
+        .route("/api/survey-templates/{id}/reorder", put(reorder_survey_template))
+        .route("/api/survey-templates/{id}/stats", get(get_template_response_stats))
+        .route("/api/survey-sessions/active", get(list_active_survey_sessions))
+        .route("/api/survey-sessions/{id}", delete(delete_survey_session))
+        .route("/api/survey-responses/{id}/suggestions", get(get_patient_suggestions))
+        .route("/api/tags", get(suggest_tags))
+        .route("/api/tags/stats", get(get_tag_stats))
+        .route("/api/tags/search", get(search_tagged_entities))
+        .route("/api/tags/{entity_type}/{entity_id}", get(get_entity_tags).post(add_entity_tags))
+        .route("/api/tags/{entity_type}/{entity_id}/{tag}", delete(remove_entity_tag))
+        .route("/api/sync/status", get(get_sync_status))
+        .route("/api/sync/trigger", post(trigger_sync))
+        .route("/api/sync/conflicts", get(list_sync_conflicts))
+        .route("/api/sync/conflicts/{id}/resolve", post(resolve_sync_conflict))
+        .route("/api/stats/dashboard", get(get_dashboard_stats))
+        .route("/api/stats/survey-responses", get(get_survey_response_stats))
+        .route("/api/stats/referrals", get(get_referral_breakdown))
+        .route("/api/referral-sources", get(get_referral_sources))
+        .route("/api/reports/revenue", get(get_revenue_report))
+        .route("/api/export/all", get(export_all_api))
+        .route("/api/export/all.zip", get(export_all_zip_api))
+        .route("/api/exports", get(list_export_jobs))
+        .route("/api/exports/survey-responses", post(start_survey_export))
+        .route("/api/exports/{id}", get(get_export_job))
+        .route("/api/exports/{id}/cancel", post(cancel_export_job))
+        .route("/api/exports/{id}/download", get(download_export_job))
+        .route("/api/settings", get(get_settings_api).put(save_settings_api))
+        .route("/api/admin/reload-settings", post(reload_settings))
+        .route("/api/admin/feature-flags", get(get_feature_flags).put(update_feature_flags))
+        .route("/api/admin/maintenance/cleanup-sessions", post(cleanup_orphaned_survey_sessions))
+        .route("/api/admin/maintenance/purge-old-data", post(purge_old_data))
+        .route("/api/staff-accounts/me", get(get_my_staff_account).put(update_my_staff_account))
+        .route("/api/staff-accounts", get(list_staff_accounts).post(create_staff_account))
+        .route("/api/staff-accounts/{id}", put(update_staff_account))
+        .route("/api/staff-accounts/{id}/active", put(set_staff_account_active))
+        .route("/api/staff-accounts/{id}/reset-password", post(reset_staff_password))
+        .route("/api/auth/change-password", post(change_my_password))
+        .route("/api/auth/refresh", post(refresh_session))
+        .route("/api/auth/logout", post(logout_session))
+        .route("/metrics", get(metrics))
+        .route(
+            "/api/medications/schedules/patient/{patient_id}/completed",
+            get(get_completed_schedules),
+        )
+        .route(
+            "/api/medications/stats/patient/{patient_id}/time-of-day",
+            get(get_adherence_by_time_of_day),
+        )
+        // 역직렬화 전에 본문 크기/형식 검사
+        .layer(middleware::from_fn(require_json_content_type))
+        .layer(RequestBodyLimitLayer::new(MAX_BODY_BYTES))
+        .layer(cors)
+}
+
+// ============ 헬퍼 함수 ============
+
+/// 임시 비밀번호 세션이 비밀번호 변경 외 API를 호출했을 때
+const PASSWORD_CHANGE_REQUIRED: &str = "임시 비밀번호를 변경한 뒤 이용할 수 있습니다";
+
+/// 직원 세션 확인 (실패 시 401, 비밀번호 변경이 필요한 세션은 403 응답 반환)
+fn require_staff(state: &AppState, params: &HashMap<String, String>) -> Result<(), Response> {
+    let token = params.get("token").map(|s| s.as_str()).unwrap_or_default();
+    if server::is_valid_staff_token(state, token) {
+        Ok(())
+    } else if server::staff_token_requires_password_change(state, token) {
+        Err(error_response(StatusCode::FORBIDDEN, PASSWORD_CHANGE_REQUIRED))
+    } else {
+        Err(error_response(StatusCode::UNAUTHORIZED, "인증 필요"))
+    }
+}
+
+/// 직원 계정 권한 항목 (API별로 확인하는 권한)
+#[derive(Debug, Clone, Copy)]
+enum Permission {
+    PatientsRead,
+    PatientsWrite,
+    PatientsDelete,
+    ChartsRead,
+    ChartsWrite,
+    PrescriptionsRead,
+    PrescriptionsWrite,
+    MedicationsRead,
+}
+
+impl Permission {
+    /// 태그 대상 종류별 권한 (차팅 계열은 차팅 권한)
+    fn for_entity(entity_type: &str, write: bool) -> Self {
+        match (entity_type, write) {
+            ("patient", false) => Self::PatientsRead,
+            ("patient", true) => Self::PatientsWrite,
+            ("prescription", false) => Self::PrescriptionsRead,
+            ("prescription", true) => Self::PrescriptionsWrite,
+            (_, false) => Self::ChartsRead,
+            (_, true) => Self::ChartsWrite,
+        }
+    }
+
+    fn is_granted(self, permissions: &StaffPermissions) -> bool {
+        match self {
+            Self::PatientsRead => permissions.patients_read,
+            Self::PatientsWrite => permissions.patients_write,
+            Self::PatientsDelete => permissions.patients_delete,
+            Self::ChartsRead => permissions.charts_read,
+            Self::ChartsWrite => permissions.charts_write,
+            Self::PrescriptionsRead => permissions.prescriptions_read,
+            Self::PrescriptionsWrite => permissions.prescriptions_write,
+            Self::MedicationsRead => permissions.medications_read,
+        }
+    }
+
+    fn denied_message(self) -> &'static str {
+        match self {
+            Self::PatientsRead => "환자 정보 조회 권한이 없습니다",
+            Self::PatientsWrite => "환자 정보 수정 권한이 없습니다",
+            Self::PatientsDelete => "환자 삭제 권한이 없습니다",
+            Self::ChartsRead => "차팅 조회 권한이 없습니다",
+            Self::ChartsWrite => "차팅 수정 권한이 없습니다",
+            Self::PrescriptionsRead => "처방 조회 권한이 없습니다",
+            Self::PrescriptionsWrite => "처방 수정 권한이 없습니다",
+            Self::MedicationsRead => "복약 정보 조회 권한이 없습니다",
+        }
+    }
+}
+
+/// 직원 세션 + 권한 확인 (계정 세션은 계정 권한을 확인해 없으면 403, 공용 비밀번호 세션은 제한 없음)
+fn require_permission(state: &AppState, params: &HashMap<String, String>, permission: Permission) -> Result<(), Response> {
+    require_staff(state, params)?;
+    let token = params.get("token").map(|s| s.as_str()).unwrap_or_default();
+    let Some(account_id) = server::staff_session_account_id(state, token) else {
+        return Ok(());
+    };
+    match db::get_staff_account(&account_id) {
+        Ok(Some(account)) if !account.is_active => {
+            Err(error_response(StatusCode::FORBIDDEN, "비활성화되었거나 삭제된 계정입니다"))
+        }
+        Ok(Some(account)) if permission.is_granted(&account.permissions) => Ok(()),
+        Ok(Some(_)) => Err(error_response(StatusCode::FORBIDDEN, permission.denied_message())),
+        Ok(None) => Err(error_response(StatusCode::FORBIDDEN, "비활성화되었거나 삭제된 계정입니다")),
+        Err(e) => Err(app_error_response(e.status_code(), &e)),
+    }
+}
+
+/// 직원 계정으로 로그인한 세션의 계정 조회 (공용 비밀번호 세션/비활성 계정은 403)
+fn require_staff_account(state: &AppState, params: &HashMap<String, String>) -> Result<StaffAccount, (StatusCode, String)> {
+    let token = params.get("token").map(|s| s.as_str()).unwrap_or_default();
+    if server::staff_token_requires_password_change(state, token) {
+        return Err((StatusCode::FORBIDDEN, PASSWORD_CHANGE_REQUIRED.to_string()));
+    }
+    if !server::is_valid_staff_token(state, token) {
+        return Err((StatusCode::UNAUTHORIZED, "인증 필요".to_string()));
+    }
+    let Some(account_id) = server::staff_session_account_id(state, token) else {
+        return Err((StatusCode::FORBIDDEN, "직원 계정으로 로그인해야 합니다".to_string()));
+    };
+    match db::get_staff_account(&account_id) {
+        Ok(Some(account)) if account.is_active => Ok(account),
+        Ok(_) => Err((StatusCode::FORBIDDEN, "비활성화되었거나 삭제된 계정입니다".to_string())),
+        Err(e) => Err((e.status_code(), e.to_response().message)),
+    }
+}
+
+/// 서명/변경 이력에 남길 직원 (계정 세션은 계정 ID, 공용 비밀번호 세션은 "staff")
+fn session_actor(state: &AppState, params: &HashMap<String, String>) -> String {
+    let token = params.get("token").map(|s| s.as_str()).unwrap_or_default();
+    server::staff_session_account_id(state, token).unwrap_or_else(|| "staff".to_string())
+}
+
+/// 목록 페이지 파라미터 (`?limit=&offset=`, 기본 50건, 최대 200건)
+fn pagination(params: &HashMap<String, String>) -> (i64, i64) {
+    let limit = params
+        .get("limit")
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let offset = params
+        .get("offset")
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(0)
+        .max(0);
+    (limit, offset)
+}
+
+/// 본문이 있는 POST/PUT/PATCH 요청은 application/json만 허용 (그 외 415)
+async fn require_json_content_type(request: Request, next: Next) -> Response {
+    let has_body = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .map_or(true, |v| v.trim() != "0");
+    if has_body && matches!(*request.method(), Method::POST | Method::PUT | Method::PATCH) {
+        let is_json = request
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.split(';').next().unwrap_or("").trim().eq_ignore_ascii_case("application/json"))
+            .unwrap_or(false);
+        if !is_json {
+            return error_response(StatusCode::UNSUPPORTED_MEDIA_TYPE, "Content-Type은 application/json이어야 합니다");
+        }
+    }
+    next.run(request).await
+}
+
+// ============ 목록 (PaginatedResponse) ============
+//
+// 응답 형식: { items, total, limit, offset, has_more }
+// 기존 직원 대시보드의 /api/responses 는 { responses } 형식 그대로 유지
+
+/// 환자 목록 (`?search=`, `?tags=a,b&match=all|any`, `?include_stats=true`면 내원 횟수/마지막 내원일 포함)
+///
+/// 태그는 기본적으로 모두 달린 환자만 찾으며, 이전 형식인 `?tag=`(한 개)도 받습니다.
+async fn list_patients(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsRead) {
+        return resp;
+    }
+
+    let (limit, offset) = pagination(&params);
+    let search = params.get("search").map(|s| s.as_str()).filter(|s| !s.is_empty());
+    let mut tags = list_param(&params, "tags");
+    tags.extend(params.get("tag").map(|t| t.trim().to_string()).filter(|t| !t.is_empty()));
+    let match_all = params.get("match").map_or(true, |v| v != "any");
+    let include_stats = params.get("include_stats").is_some_and(|v| v == "true");
+    match db::list_patients_page(search, &tags, match_all, include_stats, limit, offset) {
+        Ok(page) => Json(page).into_response(),
+        Err(e @ AppError::Custom(_)) => app_error_response(StatusCode::BAD_REQUEST, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 환자에게 달린 태그 목록
+async fn list_patient_tags(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsRead) {
+        return resp;
+    }
+
+    match db::list_patient_tags() {
+        Ok(tags) => Json(serde_json::json!({"tags": tags})).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 처방 목록 (`?patient_id=`)
+async fn list_prescriptions(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PrescriptionsRead) {
+        return resp;
+    }
+
+    let (limit, offset) = pagination(&params);
+    match db::list_prescriptions_page(params.get("patient_id").map(|s| s.as_str()), limit, offset) {
+        Ok(page) => Json(page).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 특정 배치 번호의 약재를 쓴 처방 목록 (약재 회수 추적, 처방마다 patient_id 포함)
+async fn list_prescriptions_by_batch(
+    State(state): State<AppState>,
+    Path(batch_number): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PrescriptionsRead) {
+        return resp;
+    }
+
+    match db::list_prescriptions_by_herb_batch(&batch_number) {
+        Ok(prescriptions) => Json(serde_json::json!({
+            "batch_number": batch_number.trim(),
+            "prescriptions": prescriptions,
+        }))
+        .into_response(),
+        Err(e @ AppError::Validation(_)) => app_error_response(StatusCode::BAD_REQUEST, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 알림 목록 (`?unread=true`)
+async fn list_notifications(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_staff(&state, &params) {
+        return resp;
+    }
+
+    let (limit, offset) = pagination(&params);
+    let unread_only = params.get("unread").is_some_and(|v| v == "true");
+    match db::list_notifications_page(unread_only, limit, offset) {
+        Ok(page) => Json(page).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct SnoozeNotificationRequest {
+    minutes: i64,
+}
+
+/// 알림 다시 알림 (`{"minutes": 10}`, 허용 시간은 알림 설정 기준)
+async fn snooze_notification(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(payload): Json<SnoozeNotificationRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_staff(&state, &params) {
+        return resp;
+    }
+
+    match db::snooze_notification(&id, payload.minutes) {
+        Ok(Some(snoozed_until)) => Json(serde_json::json!({ "success": true, "snoozed_until": snoozed_until })).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "알림을 찾을 수 없습니다"),
+        Err(e @ AppError::Validation(_)) => app_error_response(StatusCode::BAD_REQUEST, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 정리/알림 작업 즉시 실행
+async fn run_notification_checks(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_staff(&state, &params) {
+        return resp;
+    }
+
+    match server::run_checks_once() {
+        Ok(Some(summary)) => Json(summary).into_response(),
+        Ok(None) => error_response(StatusCode::CONFLICT, "다른 인스턴스에서 정리 작업을 실행 중입니다"),
+        Err(e @ AppError::Custom(_)) => app_error_response(StatusCode::CONFLICT, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 설문 응답 목록
+async fn list_survey_responses(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsRead) {
+        return resp;
+    }
+
+    let (limit, offset) = pagination(&params);
+    match db::list_survey_responses_page(limit, offset) {
+        Ok(page) => Json(page).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+// ============ 환자 ============
+
+#[derive(serde::Deserialize)]
+struct CreatePatientRequest {
+    name: String,
+    chart_number: Option<String>,
+    birth_date: Option<String>,
+    gender: Option<String>,
+    phone: Option<String>,
+    address: Option<String>,
+    notes: Option<String>,
+    referral_source: Option<String>,
+    tags: Option<Vec<String>>,
+}
+
+/// 환자 등록 (이름/생년월일/연락처가 같은 환자가 있으면 409, `?allow_duplicate=true`면 동명이인으로 등록)
+async fn create_patient(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(payload): Json<CreatePatientRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsWrite) {
+        return resp;
+    }
+
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, "환자 이름을 입력하세요");
+    }
+    let patient = Patient {
+        chart_number: payload.chart_number,
+        birth_date: payload.birth_date,
+        gender: payload.gender,
+        phone: payload.phone,
+        address: payload.address,
+        notes: payload.notes,
+        referral_source: payload.referral_source,
+        tags: payload.tags,
+        ..Patient::new(name.to_string())
+    };
+    let allow_duplicate = params.get("allow_duplicate").is_some_and(|v| v == "true");
+
+    match db::create_patient(&patient, allow_duplicate) {
+        Ok(()) => (StatusCode::CREATED, Json(serde_json::json!({"success": true, "patient": patient}))).into_response(),
+        Err(e @ AppError::Conflict(_)) => app_error_response(StatusCode::CONFLICT, &e),
+        Err(e) => {
+            log::error!("[{}] 환자 등록 실패: {}", request_id, e);
+            app_error_response(e.status_code(), &e)
+        }
+    }
+}
+
+/// 환자 상세 (중요 정보 `alerts` 포함, 차트 상단 배너용)
+async fn get_patient(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsRead) {
+        return resp;
+    }
+
+    match db::get_patient_detail(&id) {
+        Ok(Some(detail)) => Json(detail).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "환자를 찾을 수 없습니다"),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 환자 정보 수정 (전체 덮어쓰기, 본문의 id는 경로 id로 대체)
+async fn update_patient(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(mut payload): Json<Patient>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsWrite) {
+        return resp;
+    }
+
+    if !payload.id.is_empty() && payload.id != id {
+        return error_response(StatusCode::BAD_REQUEST, "경로와 본문의 환자 ID가 다릅니다");
+    }
+    match db::get_patient(&id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "환자를 찾을 수 없습니다"),
+        Err(e) => return app_error_response(e.status_code(), &e),
+    }
+
+    payload.id = id;
+    payload.updated_at = chrono::Utc::now();
+
+    match db::update_patient(&payload) {
+        Ok(()) => Json(serde_json::json!({"success": true, "patient": payload})).into_response(),
+        Err(e @ AppError::Conflict(_)) => app_error_response(StatusCode::CONFLICT, &e),
+        Err(e) => {
+            log::error!("[{}] 환자 수정 실패: {}", request_id, e);
+            app_error_response(e.status_code(), &e)
+        }
+    }
+}
+
+/// 환자 정보 일부 수정 (보낸 필드만 변경, null이면 값 지움)
+async fn patch_patient(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(payload): Json<PatientPatch>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsWrite) {
+        return resp;
+    }
+
+    match db::patch_patient(&id, &payload) {
+        Ok(Some(patient)) => Json(serde_json::json!({"success": true, "patient": patient})).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "환자를 찾을 수 없습니다"),
+        Err(e @ AppError::Validation(_)) => app_error_response(StatusCode::BAD_REQUEST, &e),
+        Err(e @ AppError::Conflict(_)) => app_error_response(StatusCode::CONFLICT, &e),
+        Err(e) => {
+            log::error!("[{}] 환자 수정 실패: {}", request_id, e);
+            app_error_response(e.status_code(), &e)
+        }
+    }
+}
+
+/// 환자 삭제 (휴지통으로 이동, 처방/차트도 함께)
+async fn delete_patient(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsDelete) {
+        return resp;
+    }
+
+    match db::get_patient(&id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "환자를 찾을 수 없습니다"),
+        Err(e) => return app_error_response(e.status_code(), &e),
+    }
+    match db::soft_delete_patient(&id) {
+        Ok(()) => {
+            log::info!("[{}] 환자 삭제: {}", request_id, id);
+            Json(serde_json::json!({"success": true})).into_response()
+        }
+        Err(e) => {
+            log::error!("[{}] 환자 삭제 실패: {}", request_id, e);
+            app_error_response(e.status_code(), &e)
+        }
+    }
+}
+
+/// 환자 요약 PDF (의뢰용 인쇄, 최근 진료기록과 진행 중인 복약 일정 포함)
+async fn export_patient_pdf(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::ChartsRead) {
+        return resp;
+    }
+
+    match db::generate_patient_pdf(&id) {
+        Ok(Some(pdf)) => (
+            [
+                (header::CONTENT_TYPE, "application/pdf".to_string()),
+                (header::CONTENT_DISPOSITION, format!("inline; filename=\"patient-{}.pdf\"", id)),
+            ],
+            pdf,
+        )
+            .into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "환자를 찾을 수 없습니다"),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+// ============ 환자 중요 정보 ============
+
+#[derive(serde::Deserialize)]
+struct PatientAlertRequest {
+    alert_type: PatientAlertType,
+    #[serde(default)]
+    severity: PatientAlertSeverity,
+    content: String,
+}
+
+/// 환자 중요 정보 목록 (심각도 → 종류 → 등록순)
+async fn list_patient_alerts(
+    State(state): State<AppState>,
+    Path(patient_id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsRead) {
+        return resp;
+    }
+
+    match db::list_patient_alerts(&patient_id) {
+        Ok(alerts) => Json(serde_json::json!({"alerts": alerts})).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 환자 중요 정보 추가
+async fn create_patient_alert(
+    State(state): State<AppState>,
+    Path(patient_id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(payload): Json<PatientAlertRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsWrite) {
+        return resp;
+    }
+
+    let alert = PatientAlert::new(patient_id, payload.alert_type, payload.severity, payload.content);
+    match db::save_patient_alert(&alert) {
+        Ok(Some(saved)) => (StatusCode::CREATED, Json(saved)).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "환자를 찾을 수 없습니다"),
+        Err(e @ AppError::Validation(_)) => app_error_response(StatusCode::BAD_REQUEST, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 환자 중요 정보 수정 (종류/심각도/내용)
+async fn update_patient_alert(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(payload): Json<PatientAlertRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsWrite) {
+        return resp;
+    }
+
+    let existing = match db::get_patient_alert(&id) {
+        Ok(Some(alert)) => alert,
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "중요 정보를 찾을 수 없습니다"),
+        Err(e) => return app_error_response(e.status_code(), &e),
+    };
+    let updated = PatientAlert {
+        alert_type: payload.alert_type,
+        severity: payload.severity,
+        content: payload.content,
+        ..existing
+    };
+    match db::save_patient_alert(&updated) {
+        Ok(Some(saved)) => Json(saved).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "환자를 찾을 수 없습니다"),
+        Err(e @ AppError::Validation(_)) => app_error_response(StatusCode::BAD_REQUEST, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 환자 중요 정보 삭제
+async fn delete_patient_alert(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsWrite) {
+        return resp;
+    }
+
+    match db::delete_patient_alert(&id) {
+        Ok(true) => Json(serde_json::json!({"success": true})).into_response(),
+        Ok(false) => error_response(StatusCode::NOT_FOUND, "중요 정보를 찾을 수 없습니다"),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+// ============ 처방 ============
+
+/// 처방 수정
+async fn update_prescription(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(mut payload): Json<Prescription>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PrescriptionsWrite) {
+        return resp;
+    }
+
+    match db::get_prescription(&id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "처방을 찾을 수 없습니다"),
+        Err(e) => return app_error_response(e.status_code(), &e),
+    }
+
+    payload.id = id;
+    payload.updated_at = chrono::Utc::now().to_rfc3339();
+
+    match db::update_prescription(&payload) {
+        Ok(()) => Json(serde_json::json!({"success": true, "prescription": payload})).into_response(),
+        Err(e) => {
+            log::error!("[{}] 처방 수정 실패: {}", request_id, e);
+            app_error_response(e.status_code(), &e)
+        }
+    }
+}
+
+/// 처방 삭제 (`?cascade=true`이면 연결된 복약 일정도 함께 삭제)
+async fn delete_prescription(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PrescriptionsWrite) {
+        return resp;
+    }
+
+    match db::get_prescription(&id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "처방을 찾을 수 없습니다"),
+        Err(e) => return app_error_response(e.status_code(), &e),
+    }
+
+    let cascade = params.get("cascade").map(|v| v == "true").unwrap_or(false);
+
+    match db::delete_prescription(&id, cascade) {
+        Ok(()) => Json(serde_json::json!({"success": true})).into_response(),
+        // 참조 중인 복약 일정이 있는 경우
+        Err(e @ AppError::Custom(_)) => app_error_response(StatusCode::CONFLICT, &e),
+        Err(e) => {
+            log::error!("[{}] 처방 삭제 실패: {}", request_id, e);
+            app_error_response(e.status_code(), &e)
+        }
+    }
+}
+
+/// 환자별 처방 이력 (처방 + 내원 기록)
+async fn get_prescription_history(
+    State(state): State<AppState>,
+    Path(patient_id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PrescriptionsRead) {
+        return resp;
+    }
+
+    match db::get_prescription_history(&patient_id) {
+        Ok(history) => Json(serde_json::json!({"history": history})).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 환자 이벤트 타임라인 (`?types=visit,survey`, `limit`, `offset`)
+async fn get_patient_events(
+    State(state): State<AppState>,
+    Path(patient_id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsRead) {
+        return resp;
+    }
+
+    let (limit, offset) = pagination(&params);
+    let event_types: Vec<String> = params
+        .get("types")
+        .map(|v| v.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect())
+        .unwrap_or_default();
+    // 한 건 더 읽어 다음 페이지 여부 판단
+    match db::get_patient_events(&patient_id, &event_types, limit + 1, offset) {
+        Ok(mut events) => {
+            let has_more = events.len() as i64 > limit;
+            events.truncate(limit as usize);
+            Json(serde_json::json!({"items": events, "limit": limit, "offset": offset, "has_more": has_more}))
+                .into_response()
+        }
+        Err(e @ AppError::Custom(_)) => app_error_response(StatusCode::BAD_REQUEST, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 환자의 설문 반복 응답 추이 (`?template_id=` 필수)
+async fn get_patient_survey_series(
+    State(state): State<AppState>,
+    Path(patient_id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsRead) {
+        return resp;
+    }
+
+    let Some(template_id) = params.get("template_id").filter(|v| !v.is_empty()) else {
+        return error_response(StatusCode::BAD_REQUEST, "template_id가 필요합니다");
+    };
+    match db::get_patient_survey_series(&patient_id, template_id) {
+        Ok(Some(series)) => Json(series).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "템플릿을 찾을 수 없습니다"),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 처방 전후 설문 점수 변화와 복약 순응도
+async fn get_treatment_outcome_correlation(
+    State(state): State<AppState>,
+    Path(patient_id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::ChartsRead) {
+        return resp;
+    }
+
+    match db::get_treatment_outcome_correlation(&patient_id) {
+        Ok(result) => Json(result).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+// ============ 차팅 ============
+
+/// 차팅 기록 조회
+async fn get_chart_record(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::ChartsRead) {
+        return resp;
+    }
+
+    match db::get_chart_record(&id) {
+        Ok(Some(record)) => Json(record).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "차팅 기록을 찾을 수 없습니다"),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 차팅 기록 수정 (이전 내용은 변경 이력에 기록)
+async fn update_chart_record(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(mut payload): Json<ChartRecord>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::ChartsWrite) {
+        return resp;
+    }
+
+    match db::get_chart_record(&id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "차팅 기록을 찾을 수 없습니다"),
+        Err(e) => return app_error_response(e.status_code(), &e),
+    }
+
+    payload.id = id;
+    payload.updated_at = chrono::Utc::now();
+
+    // 약어 확장 (`?expand_macros=false`면 입력 그대로)
+    let token = params.get("token").map(|s| s.as_str()).unwrap_or_default();
+    let account_id = server::staff_session_account_id(&state, token);
+    let macros = match (params.get("expand_macros").map(|v| v.as_str()), account_id.as_deref()) {
+        (Some("false"), _) => db::MacroExpansion::Off,
+        (_, Some(account_id)) => db::MacroExpansion::Staff(account_id),
+        (_, None) => db::MacroExpansion::Global,
+    };
+
+    let changed_by = session_actor(&state, &params);
+    match db::update_chart_record(&payload, Some(&changed_by), macros) {
+        Ok(record) => Json(serde_json::json!({"success": true, "record": record})).into_response(),
+        Err(e) => {
+            log::error!("[{}] 차팅 기록 수정 실패: {}", request_id, e);
+            app_error_response(e.status_code(), &e)
+        }
+    }
+}
+
+// ============ 약어(텍스트 매크로) ============
+
+/// 약어 목록 (전역 + 로그인한 직원 계정의 약어)
+async fn list_text_macros(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_staff(&state, &params) {
+        return resp;
+    }
+    let token = params.get("token").map(|s| s.as_str()).unwrap_or_default();
+    let account_id = server::staff_session_account_id(&state, token);
+
+    match db::list_text_macros(account_id.as_deref()) {
+        Ok(macros) => Json(serde_json::json!({ "macros": macros })).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct TextMacroRequest {
+    abbreviation: String,
+    expansion: String,
+    /// true면 전역 약어 (관리자 전용)
+    #[serde(default)]
+    global: bool,
+}
+
+/// 약어 추가 (본인 약어, `global`이면 관리자만 전역 약어로)
+async fn create_text_macro(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(payload): Json<TextMacroRequest>,
+) -> impl IntoResponse {
+    let account = match require_staff_account(&state, &params) {
+        Ok(account) => account,
+        Err((status, msg)) => return error_response(status, msg),
+    };
+    if payload.global && account.role != StaffRole::Admin {
+        return error_response(StatusCode::FORBIDDEN, "전역 약어는 관리자만 등록할 수 있습니다");
+    }
+
+    let owner_id = (!payload.global).then_some(account.id);
+    match db::save_text_macro(&TextMacro::new(payload.abbreviation, payload.expansion, owner_id)) {
+        Ok(saved) => (StatusCode::CREATED, Json(saved)).into_response(),
+        Err(e @ AppError::Validation(_)) => app_error_response(StatusCode::BAD_REQUEST, &e),
+        Err(e @ AppError::Conflict(_)) => app_error_response(StatusCode::CONFLICT, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 수정/삭제할 약어 조회 (다른 직원의 약어는 404, 전역 약어는 관리자만)
+fn find_editable_text_macro(account: &StaffAccount, id: &str) -> Result<TextMacro, (StatusCode, String)> {
+    match db::get_text_macro(id) {
+        Ok(Some(m)) if m.owner_id.as_deref() == Some(account.id.as_str()) => Ok(m),
+        Ok(Some(m)) if m.owner_id.is_none() && account.role == StaffRole::Admin => Ok(m),
+        Ok(Some(m)) if m.owner_id.is_none() => {
+            Err((StatusCode::FORBIDDEN, "전역 약어는 관리자만 수정할 수 있습니다".to_string()))
+        }
+        Ok(_) => Err((StatusCode::NOT_FOUND, "약어를 찾을 수 없습니다".to_string())),
+        Err(e) => Err((e.status_code(), e.to_response().message)),
+    }
+}
+
+/// 약어 수정 (약어/바꿀 내용만, 범위는 변경 불가)
+async fn update_text_macro(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(payload): Json<TextMacroRequest>,
+) -> impl IntoResponse {
+    let account = match require_staff_account(&state, &params) {
+        Ok(account) => account,
+        Err((status, msg)) => return error_response(status, msg),
+    };
+    let existing = match find_editable_text_macro(&account, &id) {
+        Ok(m) => m,
+        Err((status, msg)) => return error_response(status, msg),
+    };
+
+    let updated = TextMacro { abbreviation: payload.abbreviation, expansion: payload.expansion, ..existing };
+    match db::save_text_macro(&updated) {
+        Ok(saved) => Json(saved).into_response(),
+        Err(e @ AppError::Validation(_)) => app_error_response(StatusCode::BAD_REQUEST, &e),
+        Err(e @ AppError::Conflict(_)) => app_error_response(StatusCode::CONFLICT, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 약어 삭제
+async fn delete_text_macro(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let account = match require_staff_account(&state, &params) {
+        Ok(account) => account,
+        Err((status, msg)) => return error_response(status, msg),
+    };
+    if let Err((status, msg)) = find_editable_text_macro(&account, &id) {
+        return error_response(status, msg);
+    }
+
+    match db::delete_text_macro(&id) {
+        Ok(()) => Json(serde_json::json!({"success": true})).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 차팅 기록 삭제 (소프트 삭제)
+async fn delete_chart_record(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::ChartsWrite) {
+        return resp;
+    }
+
+    match db::get_chart_record(&id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "차팅 기록을 찾을 수 없습니다"),
+        Err(e) => return app_error_response(e.status_code(), &e),
+    }
+
+    let changed_by = session_actor(&state, &params);
+    match db::delete_chart_record(&id, Some(&changed_by)) {
+        Ok(()) => Json(serde_json::json!({"success": true})).into_response(),
+        Err(e) => {
+            log::error!("[{}] 차팅 기록 삭제 실패: {}", request_id, e);
+            app_error_response(e.status_code(), &e)
+        }
+    }
+}
+
+// ============ 문서 서명 ============
+
+#[derive(serde::Deserialize)]
+struct SignDocumentRequest {
+    #[serde(default)]
+    chain: bool,
+}
+
+/// 처방/차트 확정 서명
+async fn sign_document(
+    State(state): State<AppState>,
+    Path((document_type, id)): Path<(String, String)>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(payload): Json<SignDocumentRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::for_entity(&document_type, true)) {
+        return resp;
+    }
+
+    // 서명자는 요청 본문이 아니라 로그인한 세션 기준
+    let signed_by = session_actor(&state, &params);
+    match db::sign_document(&document_type, &id, Some(&signed_by), payload.chain) {
+        Ok(signature) => Json(serde_json::json!({"success": true, "signature": signature})).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 문서 무결성 검증
+async fn verify_document_integrity(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_staff(&state, &params) {
+        return resp;
+    }
+
+    match db::verify_document_integrity(&id) {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+// ============ 설문 ============
+
+/// 진행 중인 설문 세션 목록
+async fn list_active_survey_sessions(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_staff(&state, &params) {
+        return resp;
+    }
+
+    match db::list_active_survey_sessions() {
+        Ok(sessions) => Json(serde_json::json!({"count": sessions.len(), "sessions": sessions})).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 설문 세션 취소 (완료/만료된 세션은 409)
+async fn delete_survey_session(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_staff(&state, &params) {
+        return resp;
+    }
+
+    match db::get_survey_session(&id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "설문 세션을 찾을 수 없습니다"),
+        Err(e) => return app_error_response(e.status_code(), &e),
+    }
+
+    match db::delete_survey_session(&id) {
+        Ok(()) => Json(serde_json::json!({"success": true})).into_response(),
+        Err(e @ AppError::Custom(_)) => app_error_response(StatusCode::CONFLICT, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 설문 응답에 연결할 환자 후보
+async fn get_patient_suggestions(
+    State(state): State<AppState>,
+    Path(response_id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsRead) {
+        return resp;
+    }
+
+    match db::suggest_patient_for_response(&response_id) {
+        Ok(patients) => Json(serde_json::json!({"suggestions": patients})).into_response(),
+        // 익명 응답
+        Err(e @ AppError::Custom(_)) => app_error_response(StatusCode::UNPROCESSABLE_ENTITY, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 설문 템플릿 복제
+async fn duplicate_survey_template(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_staff(&state, &params) {
+        return resp;
+    }
+
+    match db::duplicate_survey_template(&id) {
+        Ok(Some(template)) => (StatusCode::CREATED, Json(template)).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "템플릿을 찾을 수 없습니다"),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 설문 템플릿 내보내기 (JSON 파일 다운로드)
+async fn export_survey_template(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_staff(&state, &params) {
+        return resp;
+    }
+
+    match db::export_survey_template(&id) {
+        Ok(Some(export)) => (
+            [(
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"survey-template-{}.json\"", id),
+            )],
+            Json(export),
+        )
+            .into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "템플릿을 찾을 수 없습니다"),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// Prometheus 형식 운영 지표 (서버 시작 시 비활성화하면 404)
+async fn metrics(State(state): State<AppState>) -> Response {
+    if !state.metrics_enabled {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        crate::metrics::render(),
+    )
+        .into_response()
+}
+
+#[derive(serde::Deserialize)]
+struct AdminCredentials {
+    username: String,
+    password: String,
+}
+
+/// 한의원 설정 캐시 다시 읽기 (직원 세션 + 관리자 계정 확인)
+async fn reload_settings(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(credentials): Json<AdminCredentials>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_staff(&state, &params) {
+        return resp;
+    }
+
+    match db::verify_staff_account_password(&credentials.username, &credentials.password) {
+        Ok(Some(account)) if account.role == StaffRole::Admin => {}
+        Ok(Some(_)) => return error_response(StatusCode::FORBIDDEN, "관리자 권한이 필요합니다"),
+        Ok(None) => return error_response(StatusCode::UNAUTHORIZED, "관리자 계정 인증에 실패했습니다"),
+        Err(e) => return app_error_response(e.status_code(), &e),
+    }
+
+    let clinic_name = state.reload_clinic_settings();
+    log::info!("[{}] 한의원 설정 캐시 갱신: {}", request_id, clinic_name);
+    Json(serde_json::json!({ "success": true, "clinic_name": clinic_name })).into_response()
+}
+
+/// 한의원 설정 조회
+async fn get_settings_api(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_staff(&state, &params) {
+        return resp;
+    }
+
+    match db::get_clinic_settings() {
+        Ok(settings) => Json(settings).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 한의원 설정 저장 (관리자 전용, 조회 응답 형식 그대로)
+async fn save_settings_api(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(settings): Json<ClinicSettings>,
+) -> impl IntoResponse {
+    let admin = match require_admin_account(&state, &params) {
+        Ok(account) => account,
+        Err((status, msg)) => return error_response(status, msg),
+    };
+
+    let logo_len = settings.clinic_logo.as_deref().map_or(0, str::len);
+    if logo_len > db::CLINIC_LOGO_MAX_BASE64_LEN {
+        return error_response(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!(
+                "로고 이미지가 너무 큽니다 ({}KB, 최대 {}KB)",
+                logo_len.div_ceil(1024),
+                db::CLINIC_LOGO_MAX_BASE64_LEN / 1024
+            ),
+        );
+    }
+
+    let settings = ClinicSettings { updated_at: chrono::Utc::now(), ..settings };
+    match db::save_clinic_settings(&settings) {
+        Ok(()) => {
+            let clinic_name = state.reload_clinic_settings();
+            log::info!("[{}] 한의원 설정 저장 ({}): {}", request_id, admin.username, clinic_name);
+            Json(settings).into_response()
+        }
+        Err(e @ AppError::Validation(_)) => app_error_response(StatusCode::BAD_REQUEST, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+// ============ 기능 플래그 ============
+
+/// 관리자 계정 세션 확인
+fn require_admin_account(state: &AppState, params: &HashMap<String, String>) -> Result<StaffAccount, (StatusCode, String)> {
+    let account = require_staff_account(state, params)?;
+    if account.role != StaffRole::Admin {
+        return Err((StatusCode::FORBIDDEN, "관리자 권한이 필요합니다".to_string()));
+    }
+    Ok(account)
+}
+
+/// 현재 플랜/기능 플래그 조회 (관리자 전용)
+async fn get_feature_flags(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err((status, msg)) = require_admin_account(&state, &params) {
+        return error_response(status, msg);
+    }
+    Json(state.feature_flags()).into_response()
+}
+
+#[derive(serde::Deserialize)]
+struct UpdateFeatureFlagsRequest {
+    plan_type: Option<String>,
+    survey_external_enabled: Option<bool>,
+    /// true면 로그인한 계정의 구독 플랜으로 plan_type을 맞춤
+    #[serde(default)]
+    sync_subscription: bool,
+}
+
+/// 플랜/기능 플래그 갱신 (관리자 전용, 서버 재시작 없이 즉시 반영)
+async fn update_feature_flags(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(payload): Json<UpdateFeatureFlagsRequest>,
+) -> impl IntoResponse {
+    let admin = match require_admin_account(&state, &params) {
+        Ok(account) => account,
+        Err((status, msg)) => return error_response(status, msg),
+    };
+
+    let mut plan_type = payload.plan_type;
+    if payload.sync_subscription {
+        let subscription = auth::get_current_auth_state().ok().and_then(|s| s.subscription);
+        match subscription {
+            Some(sub) if matches!(sub.status, SubscriptionStatus::Active | SubscriptionStatus::Trial) => {
+                plan_type = Some(sub.plan);
+            }
+            Some(_) => plan_type = Some("free".to_string()),
+            None => return error_response(StatusCode::CONFLICT, "구독 정보를 확인할 수 없습니다"),
+        }
+    }
+
+    match state.update_feature_flags(plan_type.as_deref(), payload.survey_external_enabled) {
+        Ok(flags) => {
+            log::info!(
+                "[{}] 기능 플래그 갱신 ({}): plan={}, survey_external={}",
+                request_id, admin.username, flags.plan_type, flags.survey_external_enabled
+            );
+            Json(flags).into_response()
+        }
+        Err(e @ AppError::Validation(_)) => app_error_response(StatusCode::BAD_REQUEST, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+#[derive(serde::Deserialize, Default)]
+struct CleanupSessionsRequest {
+    older_than_days: Option<i64>,
+}
+
+/// 응답 없이 끝난 오래된 설문 세션 삭제 (관리자 전용, `older_than_days` 기본 90일)
+async fn cleanup_orphaned_survey_sessions(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Query(params): Query<HashMap<String, String>>,
+    payload: Option<Json<CleanupSessionsRequest>>,
+) -> impl IntoResponse {
+    let admin = match require_admin_account(&state, &params) {
+        Ok(account) => account,
+        Err((status, msg)) => return error_response(status, msg),
+    };
+
+    let Json(payload) = payload.unwrap_or_default();
+    let older_than_days = payload.older_than_days.unwrap_or(db::ORPHANED_SESSION_RETENTION_DAYS);
+    match db::cleanup_orphaned_survey_sessions(older_than_days) {
+        Ok(deleted) => {
+            log::info!("[{}] 오래된 설문 세션 {}건 삭제 ({}, {}일 이전)", request_id, deleted, admin.username, older_than_days);
+            Json(serde_json::json!({ "deleted": deleted, "older_than_days": older_than_days })).into_response()
+        }
+        Err(e @ AppError::Validation(_)) => app_error_response(StatusCode::BAD_REQUEST, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 저장된 보관 기간으로 오래된 기록 정리 (관리자 전용, 기본은 건수만 확인, `?dry_run=false`면 삭제)
+async fn purge_old_data(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let admin = match require_admin_account(&state, &params) {
+        Ok(account) => account,
+        Err((status, msg)) => return error_response(status, msg),
+    };
+
+    let dry_run = params.get("dry_run").map_or(true, |v| v != "false");
+    let result = db::get_retention_settings().and_then(|retention| db::purge_old_data(&retention, dry_run));
+    match result {
+        Ok(summary) => {
+            if !dry_run {
+                log::info!(
+                    "[{}] 오래된 기록 정리 ({}): 설문 응답 {}건, 알림 {}건, 복약 기록 {}건",
+                    request_id,
+                    admin.username,
+                    summary.survey_responses,
+                    summary.notifications,
+                    summary.medication_logs
+                );
+            }
+            Json(summary).into_response()
+        }
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+// ============ 직원 계정 ============
+
+/// 로그인한 직원 본인 계정 (비밀번호 해시 제외)
+async fn get_my_staff_account(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    match require_staff_account(&state, &params) {
+        Ok(account) => Json(StaffAccountInfo::from(account)).into_response(),
+        Err((status, msg)) => error_response(status, msg),
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct UpdateMyStaffAccountRequest {
+    display_name: String,
+}
+
+/// 본인 표시 이름 변경 (역할은 변경 불가 - 권한 상승 방지)
+async fn update_my_staff_account(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(payload): Json<UpdateMyStaffAccountRequest>,
+) -> impl IntoResponse {
+    let account = match require_staff_account(&state, &params) {
+        Ok(account) => account,
+        Err((status, msg)) => return error_response(status, msg),
+    };
+
+    match db::update_staff_account_display_name(&account.id, &payload.display_name) {
+        Ok(()) => match db::get_staff_account(&account.id) {
+            Ok(Some(updated)) => Json(StaffAccountInfo::from(updated)).into_response(),
+            Ok(None) => error_response(StatusCode::NOT_FOUND, "계정을 찾을 수 없습니다"),
+            Err(e) => app_error_response(e.status_code(), &e),
+        },
+        Err(e @ AppError::Custom(_)) => app_error_response(StatusCode::BAD_REQUEST, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 직원 계정 목록 (관리자 전용)
+async fn list_staff_accounts(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err((status, msg)) = require_admin_account(&state, &params) {
+        return error_response(status, msg);
+    }
+
+    match db::list_staff_accounts() {
+        Ok(accounts) => Json(serde_json::json!({"accounts": accounts})).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct CreateStaffAccountRequest {
+    username: String,
+    display_name: String,
+    password: String,
+    role: String,
+}
+
+/// 직원 계정 생성 (관리자 전용, 권한은 역할 기본값)
+async fn create_staff_account(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(payload): Json<CreateStaffAccountRequest>,
+) -> impl IntoResponse {
+    let admin = match require_admin_account(&state, &params) {
+        Ok(account) => account,
+        Err((status, msg)) => return error_response(status, msg),
+    };
+
+    let role = StaffRole::from_str(&payload.role);
+    match db::create_staff_account(&payload.username, &payload.display_name, &payload.password, role) {
+        Ok(account) => {
+            log::info!("[{}] 직원 계정 생성 ({}): {}", request_id, admin.username, account.username);
+            (StatusCode::CREATED, Json(StaffAccountInfo::from(account))).into_response()
+        }
+        Err(e @ AppError::Validation(_)) => app_error_response(StatusCode::BAD_REQUEST, &e),
+        Err(e @ AppError::Conflict(_)) => app_error_response(StatusCode::CONFLICT, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct SetStaffAccountActiveRequest {
+    is_active: bool,
+}
+
+/// 직원 계정 활성/비활성 (관리자 전용, 마지막 관리자는 비활성화 불가)
+async fn set_staff_account_active(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(payload): Json<SetStaffAccountActiveRequest>,
+) -> impl IntoResponse {
+    let admin = match require_admin_account(&state, &params) {
+        Ok(account) => account,
+        Err((status, msg)) => return error_response(status, msg),
+    };
+
+    match db::set_staff_account_active(&id, payload.is_active) {
+        Ok(Some(account)) => {
+            log::info!("[{}] 직원 계정 {} ({}): {}", request_id, if account.is_active { "활성화" } else { "비활성화" }, admin.username, account.username);
+            Json(StaffAccountInfo::from(account)).into_response()
+        }
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "계정을 찾을 수 없습니다"),
+        Err(e @ AppError::Conflict(_)) => app_error_response(StatusCode::CONFLICT, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 직원 세션 토큰 재발급 (기존 토큰은 무효, 처음 로그인 후 최대 유지 기간은 그대로)
+async fn refresh_session(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_staff(&state, &params) {
+        return resp;
+    }
+    let token = params.get("token").map(|s| s.as_str()).unwrap_or_default();
+
+    match server::refresh_staff_session(&state, token) {
+        Some(session) => Json(serde_json::json!({
+            "token": session.token,
+            "expires_at": session.expires_at(),
+        }))
+        .into_response(),
+        None => error_response(StatusCode::UNAUTHORIZED, "인증 필요"),
+    }
+}
+
+/// 직원 로그아웃 (토큰 즉시 무효)
+async fn logout_session(State(state): State<AppState>, Query(params): Query<HashMap<String, String>>) -> impl IntoResponse {
+    if let Some(token) = params.get("token").filter(|t| !t.is_empty()) {
+        server::end_staff_session(&state, token);
+    }
+    Json(serde_json::json!({"success": true}))
+}
+
+#[derive(serde::Deserialize)]
+struct ChangePasswordRequest {
+    current_password: String,
+    new_password: String,
+}
+
+/// 본인 비밀번호 변경 (같은 계정의 다른 세션은 로그아웃)
+///
+/// 임시 비밀번호로 로그인한 세션이 호출할 수 있는 유일한 API입니다.
+async fn change_my_password(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(payload): Json<ChangePasswordRequest>,
+) -> impl IntoResponse {
+    let token = params.get("token").map(|s| s.as_str()).unwrap_or_default();
+    if !server::is_valid_staff_token(&state, token) && !server::staff_token_requires_password_change(&state, token) {
+        return error_response(StatusCode::UNAUTHORIZED, "인증 필요");
+    }
+    let Some(account_id) = server::staff_session_account_id(&state, token) else {
+        return error_response(StatusCode::FORBIDDEN, "직원 계정으로 로그인해야 합니다");
+    };
+
+    match db::change_staff_password(&account_id, &payload.current_password, &payload.new_password) {
+        Ok(true) => {
+            server::revoke_staff_account_sessions(&state, &account_id, Some(token));
+            log::info!("[{}] 직원 비밀번호 변경: {}", request_id, account_id);
+            Json(serde_json::json!({ "success": true })).into_response()
+        }
+        Ok(false) => error_response(StatusCode::BAD_REQUEST, "현재 비밀번호가 일치하지 않습니다"),
+        Err(e @ AppError::Validation(_)) => app_error_response(StatusCode::BAD_REQUEST, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 직원 비밀번호 재설정 (관리자 전용, 임시 비밀번호는 이 응답에서 한 번만 확인 가능)
+///
+/// 대상 계정의 기존 세션은 모두 로그아웃되며, 임시 비밀번호로 로그인하면 변경 전까지 다른 API를 쓸 수 없습니다.
+async fn reset_staff_password(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let admin = match require_admin_account(&state, &params) {
+        Ok(account) => account,
+        Err((status, msg)) => return error_response(status, msg),
+    };
+
+    match db::reset_staff_password(&id) {
+        Ok(Some(temporary_password)) => {
+            server::revoke_staff_account_sessions(&state, &id, None);
+            log::info!("[{}] 직원 비밀번호 재설정 ({}): {}", request_id, admin.username, id);
+            (
+                [(header::CACHE_CONTROL, "no-store")],
+                Json(serde_json::json!({ "temporary_password": temporary_password, "must_change_password": true })),
+            )
+                .into_response()
+        }
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "계정을 찾을 수 없습니다"),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct UpdateStaffAccountRequest {
+    display_name: Option<String>,
+    role: Option<String>,
+}
+
+/// 직원 계정 표시 이름/역할 변경 (관리자 전용, 역할 변경 시 권한은 역할 기본값으로)
+async fn update_staff_account(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(payload): Json<UpdateStaffAccountRequest>,
+) -> impl IntoResponse {
+    if let Err((status, msg)) = require_admin_account(&state, &params) {
+        return error_response(status, msg);
+    }
+
+    let mut account = match db::get_staff_account(&id) {
+        Ok(Some(account)) => account,
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "계정을 찾을 수 없습니다"),
+        Err(e) => return app_error_response(e.status_code(), &e),
+    };
+    if let Some(name) = payload.display_name {
+        if name.trim().is_empty() {
+            return error_response(StatusCode::BAD_REQUEST, "표시 이름이 비어 있습니다");
+        }
+        account.display_name = name.trim().to_string();
+    }
+    if let Some(role) = payload.role {
+        let role = StaffRole::from_str(&role);
+        if role != account.role {
+            account.permissions = StaffPermissions::for_role(&role);
+            account.role = role;
+        }
+    }
+    account.updated_at = chrono::Utc::now();
+
+    match db::update_staff_account(&account) {
+        Ok(()) => Json(StaffAccountInfo::from(account)).into_response(),
+        Err(e @ AppError::Conflict(_)) => app_error_response(StatusCode::CONFLICT, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 전체 데이터 내보내기 (관리자 전용, NDJSON 스트리밍, 첫 줄은 병원 설정)
+async fn export_all_api(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err((status, msg)) = require_admin_account(&state, &params) {
+        return error_response(status, msg);
+    }
+
+    let (writer, reader) = tokio::io::duplex(EXPORT_STREAM_BUFFER);
+    let export = tokio::spawn(async move { db::stream_all_data(writer).await });
+
+    (
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"gosibang-export-{}.ndjson\"", chrono::Local::now().format("%Y%m%d")),
+            ),
+        ],
+        export_stream_body(reader, export, request_id),
+    )
+        .into_response()
+}
+
+/// 내보내기 스트림 응답 본문 (작업이 실패하면 마지막에 오류를 넣어 응답을 끊음)
+/// 오류 없이 끝나면 클라이언트는 잘린 백업을 정상 파일로 받게 되므로 정상 종료와 구분해야 함
+fn export_stream_body(
+    reader: tokio::io::DuplexStream,
+    export: tokio::task::JoinHandle<AppResult<()>>,
+    request_id: RequestId,
+) -> axum::body::Body {
+    use futures_util::StreamExt;
+
+    let failure = futures_util::stream::once(async move {
+        let error = match export.await {
+            Ok(Ok(())) => return None,
+            Ok(Err(e)) => e.to_string(),
+            Err(e) => e.to_string(),
+        };
+        log::error!("[{}] 전체 데이터 내보내기 실패: {}", request_id, error);
+        Some(Err(std::io::Error::other(error)))
+    })
+    .filter_map(std::future::ready);
+
+    axum::body::Body::from_stream(tokio_util::io::ReaderStream::new(reader).chain(failure))
+}
+
+/// 전체 데이터 ZIP 내보내기 (관리자 전용, 테이블별 CSV)
+async fn export_all_zip_api(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err((status, msg)) = require_admin_account(&state, &params) {
+        return error_response(status, msg);
+    }
+
+    match db::export_all_data_zip() {
+        Ok(bytes) => (
+            [
+                (header::CONTENT_TYPE, "application/zip".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"clinic_export_{}.zip\"", chrono::Local::now().format("%Y%m%d")),
+                ),
+            ],
+            bytes,
+        )
+            .into_response(),
+        Err(e) => {
+            log::error!("[{}] ZIP 내보내기 실패: {}", request_id, e);
+            app_error_response(e.status_code(), &e)
+        }
+    }
+}
+
+// ============ 백그라운드 내보내기 작업 ============
+
+/// 설문 응답 일괄 내보내기 작업 등록 (`{ "format": "pdf" | "xlsx" | "csv", "template_id", "from", "to" }`, 202 + 작업 정보)
+async fn start_survey_export(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(payload): Json<SurveyExportRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsRead) {
+        return resp;
+    }
+
+    match jobs::start_survey_export(payload) {
+        Ok(job) => {
+            log::info!("[{}] 설문 응답 내보내기 작업 등록: {}", request_id, job.id);
+            (StatusCode::ACCEPTED, Json(job)).into_response()
+        }
+        Err(e @ AppError::Validation(_)) => app_error_response(StatusCode::BAD_REQUEST, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 내보내기 작업 목록 (최근 등록순)
+async fn list_export_jobs(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsRead) {
+        return resp;
+    }
+    Json(serde_json::json!({"jobs": jobs::list_jobs()})).into_response()
+}
+
+/// 내보내기 작업 상태/진행률
+async fn get_export_job(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsRead) {
+        return resp;
+    }
+    match jobs::get_job(&id) {
+        Some(job) => Json(job).into_response(),
+        None => error_response(StatusCode::NOT_FOUND, "작업을 찾을 수 없습니다"),
+    }
+}
+
+/// 내보내기 작업 취소
+async fn cancel_export_job(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsRead) {
+        return resp;
+    }
+    match jobs::cancel_job(&id) {
+        Some(job) => Json(job).into_response(),
+        None => error_response(StatusCode::NOT_FOUND, "작업을 찾을 수 없습니다"),
+    }
+}
+
+/// 완료된 내보내기 파일 내려받기 (완료 전이거나 보관 기간이 지났으면 404)
+async fn download_export_job(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsRead) {
+        return resp;
+    }
+    match jobs::read_job_file(&id) {
+        Ok(Some(file)) => (
+            [
+                (header::CONTENT_TYPE, file.content_type.to_string()),
+                (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", file.file_name)),
+            ],
+            file.bytes,
+        )
+            .into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "내려받을 파일이 없습니다"),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct ReorderQuestionsRequest {
+    question_ids: Vec<String>,
+}
+
+/// 설문 문항 순서 변경 (`{ "question_ids": [..] }`, 모든 문항 ID를 새 순서대로)
+async fn reorder_survey_template(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(payload): Json<ReorderQuestionsRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_staff(&state, &params) {
+        return resp;
+    }
+
+    match db::reorder_survey_template(&id, &payload.question_ids) {
+        Ok(Some(template)) => Json(template).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "템플릿을 찾을 수 없습니다"),
+        Err(e @ AppError::Custom(_)) => app_error_response(StatusCode::BAD_REQUEST, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 설문 템플릿 가져오기 (내보낸 JSON 그대로 전송, 이전 스키마 버전은 변환 후 저장)
+async fn import_survey_template(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(payload): Json<serde_json::Value>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_staff(&state, &params) {
+        return resp;
+    }
+
+    match db::parse_survey_template_import(payload).and_then(db::import_survey_template) {
+        Ok(template) => (StatusCode::CREATED, Json(template)).into_response(),
+        Err(e @ AppError::Custom(_)) => app_error_response(StatusCode::BAD_REQUEST, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+// ============ 태그 ============
+
+#[derive(serde::Deserialize)]
+struct AddTagsRequest {
+    tags: Vec<String>,
+}
+
+/// 쉼표 구분 목록 파라미터 (`?tags=a,b`)
+fn list_param(params: &HashMap<String, String>, key: &str) -> Vec<String> {
+    params
+        .get(key)
+        .map(|v| v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+/// 태그 자동완성 (`?prefix=&limit=`)
+async fn suggest_tags(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_staff(&state, &params) {
+        return resp;
+    }
+
+    let prefix = params.get("prefix").map(|s| s.as_str()).unwrap_or("");
+    let limit = params
+        .get("limit")
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(10)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    match db::suggest_tags(prefix, limit) {
+        Ok(tags) => Json(serde_json::json!({"tags": tags})).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 태그별 사용 통계
+async fn get_tag_stats(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsRead) {
+        return resp;
+    }
+
+    match db::get_tag_stats() {
+        Ok(stats) => Json(serde_json::json!({"stats": stats})).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 태그 교차 검색 (`?tags=a,b&types=prescription,chart_record&match=all`)
+async fn search_tagged_entities(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_staff(&state, &params) {
+        return resp;
+    }
+
+    let tags = list_param(&params, "tags");
+    if tags.is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, "tags 파라미터가 필요합니다");
+    }
+    let types = list_param(&params, "types");
+    let searched: Vec<&str> = if types.is_empty() {
+        vec!["patient", "prescription", "chart_record"]
+    } else {
+        types.iter().map(|t| t.as_str()).collect()
+    };
+    for entity_type in searched {
+        if let Err(resp) = require_permission(&state, &params, Permission::for_entity(entity_type, false)) {
+            return resp;
+        }
+    }
+    let match_all = params.get("match").map(|v| v == "all").unwrap_or(false);
+
+    match db::search_tagged_entities(&tags, &types, match_all) {
+        Ok(items) => Json(serde_json::json!({"count": items.len(), "items": items})).into_response(),
+        Err(e @ AppError::Custom(_)) => app_error_response(StatusCode::BAD_REQUEST, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 환자 태그 목록 (`/api/tags/patient/{id}`와 같음)
+async fn get_patient_tags(
+    state: State<AppState>,
+    Path(id): Path<String>,
+    params: Query<HashMap<String, String>>,
+) -> Response {
+    get_entity_tags(state, Path(("patient".to_string(), id)), params).await.into_response()
+}
+
+/// 환자 태그 추가 (`{ "tags": [..] }`, 추가 후 전체 태그 반환)
+async fn add_patient_tags(
+    state: State<AppState>,
+    Path(id): Path<String>,
+    params: Query<HashMap<String, String>>,
+    payload: Json<AddTagsRequest>,
+) -> Response {
+    add_entity_tags(state, Path(("patient".to_string(), id)), params, payload).await.into_response()
+}
+
+/// 환자 태그 제거
+async fn remove_patient_tag(
+    state: State<AppState>,
+    Path((id, tag)): Path<(String, String)>,
+    params: Query<HashMap<String, String>>,
+) -> Response {
+    remove_entity_tag(state, Path(("patient".to_string(), id, tag)), params).await.into_response()
+}
+
+/// 항목의 태그 목록
+async fn get_entity_tags(
+    State(state): State<AppState>,
+    Path((entity_type, entity_id)): Path<(String, String)>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::for_entity(&entity_type, false)) {
+        return resp;
+    }
+
+    match db::get_entity_tags(&entity_type, &entity_id) {
+        Ok(Some(tags)) => Json(serde_json::json!({"tags": tags})).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "항목을 찾을 수 없습니다"),
+        Err(e @ AppError::Custom(_)) => app_error_response(StatusCode::BAD_REQUEST, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 태그 추가 (`{ "tags": [..] }`)
+async fn add_entity_tags(
+    State(state): State<AppState>,
+    Path((entity_type, entity_id)): Path<(String, String)>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(payload): Json<AddTagsRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::for_entity(&entity_type, true)) {
+        return resp;
+    }
+
+    match db::add_entity_tags(&entity_type, &entity_id, &payload.tags) {
+        Ok(Some(tags)) => Json(serde_json::json!({"tags": tags})).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "항목을 찾을 수 없습니다"),
+        Err(e @ AppError::Custom(_)) => app_error_response(StatusCode::BAD_REQUEST, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 태그 제거
+async fn remove_entity_tag(
+    State(state): State<AppState>,
+    Path((entity_type, entity_id, tag)): Path<(String, String, String)>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::for_entity(&entity_type, true)) {
+        return resp;
+    }
+
+    match db::remove_entity_tag(&entity_type, &entity_id, &tag) {
+        Ok(true) => Json(serde_json::json!({"success": true})).into_response(),
+        Ok(false) => error_response(StatusCode::NOT_FOUND, "태그를 찾을 수 없습니다"),
+        Err(e @ AppError::Custom(_)) => app_error_response(StatusCode::BAD_REQUEST, &e),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+// ============ 복약 ============
+
+/// 환자별 복용 완료 일정
+async fn get_completed_schedules(
+    State(state): State<AppState>,
+    Path(patient_id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::MedicationsRead) {
+        return resp;
+    }
+
+    match db::get_completed_schedules(&patient_id) {
+        Ok(schedules) => Json(serde_json::json!({"schedules": schedules})).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 시간대별 복약 순응도 (`[["아침", 92.5], ["점심", 60.0], ["저녁", 0.0]]`)
+async fn get_adherence_by_time_of_day(
+    State(state): State<AppState>,
+    Path(patient_id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::MedicationsRead) {
+        return resp;
+    }
+
+    match db::get_adherence_by_time_of_day(&patient_id) {
+        Ok(slots) => Json(slots).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+// ============ 동기화 ============
+
+/// 동기화 상태 (대기 건수, 마지막 성공 시각)
+async fn get_sync_status(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_staff(&state, &params) {
+        return resp;
+    }
+
+    Json(serde_json::json!({
+        "pending_count": sync::get_pending_count(),
+        "last_sync_at": sync::last_sync_at(),
+        "sync_enabled": sync::is_sync_enabled(),
+        "conflict_count": db::count_sync_conflicts().unwrap_or(0),
+    }))
+    .into_response()
+}
+
+/// 대기 항목 동기화 재시도 (백그라운드 실행)
+async fn trigger_sync(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_staff(&state, &params) {
+        return resp;
+    }
+
+    tokio::spawn(async move {
+        match sync::retry_pending_sync().await {
+            Ok(count) => log::info!("[{}] 수동 동기화 완료: {}건", request_id, count),
+            Err(e) => log::warn!("[{}] 수동 동기화 실패: {}", request_id, e),
+        }
+    });
+    Json(serde_json::json!({"triggered": true})).into_response()
+}
+
+/// 동기화 충돌 목록 (`?include_resolved=true`면 해결된 기록 포함)
+async fn list_sync_conflicts(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_staff(&state, &params) {
+        return resp;
+    }
+
+    let include_resolved = params.get("include_resolved").is_some_and(|v| v == "true");
+    match db::list_sync_conflicts(include_resolved) {
+        Ok(conflicts) => Json(serde_json::json!({"conflicts": conflicts})).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct ResolveConflictRequest {
+    choice: SyncConflictChoice,
+}
+
+/// 동기화 충돌 해결 (`{ "choice": "local" | "remote" }`)
+async fn resolve_sync_conflict(
+    State(state): State<AppState>,
+    Extension(request_id): Extension<RequestId>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    Json(payload): Json<ResolveConflictRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_staff(&state, &params) {
+        return resp;
+    }
+
+    match db::resolve_sync_conflict(&id, payload.choice) {
+        Ok(Some(conflict)) => Json(conflict).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "충돌 기록을 찾을 수 없습니다"),
+        Err(e @ AppError::Conflict(_)) => app_error_response(StatusCode::CONFLICT, &e),
+        Err(e @ AppError::Validation(_)) => app_error_response(StatusCode::BAD_REQUEST, &e),
+        Err(e) => {
+            log::error!("[{}] 동기화 충돌 해결 실패: {}", request_id, e);
+            app_error_response(e.status_code(), &e)
+        }
+    }
+}
+
+// ============ 리포트 ============
+
+/// 대시보드 요약 통계 (`?from=YYYY-MM-DD&to=YYYY-MM-DD`, 선택)
+async fn get_dashboard_stats(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsRead) {
+        return resp;
+    }
+
+    let parse = |key: &str| match params.get(key) {
+        Some(v) => chrono::NaiveDate::parse_from_str(v, "%Y-%m-%d").map(|d| Some(d.to_string())),
+        None => Ok(None),
+    };
+    let (from, to) = match (parse("from"), parse("to")) {
+        (Ok(from), Ok(to)) => (from, to),
+        _ => return error_response(StatusCode::BAD_REQUEST, "기간 형식이 올바르지 않습니다 (YYYY-MM-DD)"),
+    };
+
+    match db::get_dashboard_stats(from.as_deref(), to.as_deref()) {
+        Ok(stats) => Json(stats).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 내원 경로별 신규 환자 수 (from/to 미지정 시 전체 기간)
+async fn get_referral_breakdown(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsRead) {
+        return resp;
+    }
+
+    let parse = |key: &str| match params.get(key) {
+        Some(v) => chrono::NaiveDate::parse_from_str(v, "%Y-%m-%d").map(|d| Some(d.to_string())),
+        None => Ok(None),
+    };
+    let (from, to) = match (parse("from"), parse("to")) {
+        (Ok(from), Ok(to)) => (from, to),
+        _ => return error_response(StatusCode::BAD_REQUEST, "기간 형식이 올바르지 않습니다 (YYYY-MM-DD)"),
+    };
+
+    match db::get_referral_breakdown(from.as_deref(), to.as_deref()) {
+        Ok(counts) => Json(counts).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 설문 템플릿 문항별 응답 통계 (`?from=&to=`)
+pub(crate) async fn get_template_response_stats(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsRead) {
+        return resp;
+    }
+
+    let parse = |key: &str| match params.get(key).filter(|v| !v.is_empty()) {
+        Some(v) => chrono::NaiveDate::parse_from_str(v, "%Y-%m-%d").map(|d| Some(d.to_string())),
+        None => Ok(None),
+    };
+    let (from, to) = match (parse("from"), parse("to")) {
+        (Ok(from), Ok(to)) => (from, to),
+        _ => return error_response(StatusCode::BAD_REQUEST, "기간 형식이 올바르지 않습니다 (YYYY-MM-DD)"),
+    };
+
+    match db::get_template_response_stats(&id, from.as_deref(), to.as_deref()) {
+        Ok(Some(stats)) => Json(stats).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, "템플릿을 찾을 수 없습니다"),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 내원 경로 선택지 목록
+async fn get_referral_sources(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_staff(&state, &params) {
+        return resp;
+    }
+
+    match db::get_referral_sources() {
+        Ok(sources) => Json(sources).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 설문 응답 집계 (캐시 우선)
+async fn get_survey_response_stats(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PatientsRead) {
+        return resp;
+    }
+
+    match db::get_survey_response_stats() {
+        Ok(stats) => Json(stats).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+/// 기간별 매출 리포트 (`?from=YYYY-MM-DD&to=YYYY-MM-DD`)
+async fn get_revenue_report(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(resp) = require_permission(&state, &params, Permission::PrescriptionsRead) {
+        return resp;
+    }
+
+    let parse = |key: &str| {
+        params
+            .get(key)
+            .and_then(|v| chrono::NaiveDate::parse_from_str(v, "%Y-%m-%d").ok())
+    };
+    let (from, to) = match (parse("from"), parse("to")) {
+        (Some(from), Some(to)) if from <= to => (from, to),
+        _ => return error_response(StatusCode::BAD_REQUEST, "from/to 기간이 올바르지 않습니다 (YYYY-MM-DD)"),
+    };
+
+    match db::get_revenue_report(&from.to_string(), &to.to_string()) {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => app_error_response(e.status_code(), &e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    /// 테스트용 직원 계정 + 세션 (반환: 세션 토큰)
+    fn staff_token(state: &AppState, role: StaffRole, permissions: Option<StaffPermissions>) -> String {
+        db::init_test_database();
+        let username = format!("test-{}", uuid::Uuid::new_v4());
+        let mut account = db::create_staff_account(&username, "테스트", "password123", role).unwrap();
+        if let Some(permissions) = permissions {
+            account.permissions = permissions;
+            db::update_staff_account(&account).unwrap();
+        }
+        server::insert_test_staff_session(state, Some(&account.id))
+    }
+
+    async fn send(state: &AppState, method: Method, uri: &str, body: Option<serde_json::Value>) -> StatusCode {
+        let request = match body {
+            Some(body) => axum::http::Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => axum::http::Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::CONTENT_LENGTH, "0")
+                .body(Body::empty()),
+        };
+        server::create_router(state.clone())
+            .oneshot(request.unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn restricted_account_is_rejected_on_protected_routes() {
+        let state = AppState::new();
+        let token = staff_token(&state, StaffRole::Viewer, Some(StaffPermissions::default()));
+
+        let routes = [
+            (Method::GET, "/api/survey-responses", None),
+            (Method::GET, "/api/patients/p1/survey-series?template_id=t1", None),
+            (Method::GET, "/api/survey-responses/r1/suggestions", None),
+            (Method::POST, "/api/exports/survey-responses", Some(serde_json::json!({"format": "csv"}))),
+            (Method::GET, "/api/exports", None),
+            (Method::GET, "/api/exports/j1", None),
+            (Method::POST, "/api/exports/j1/cancel", None),
+            (Method::GET, "/api/exports/j1/download", None),
+            (Method::GET, "/api/tags/stats", None),
+            (Method::GET, "/api/tags/search?tags=a", None),
+            (Method::GET, "/api/tags/search?tags=a&types=prescription", None),
+            (Method::GET, "/api/stats/dashboard", None),
+            (Method::GET, "/api/stats/referrals", None),
+            (Method::GET, "/api/stats/survey-responses", None),
+            (Method::GET, "/api/survey-templates/t1/stats", None),
+            (Method::GET, "/api/reports/revenue?from=2024-01-01&to=2024-01-31", None),
+            (Method::POST, "/api/documents/prescription/d1/sign", Some(serde_json::json!({}))),
+            (Method::GET, "/api/export/all", None),
+            (Method::GET, "/api/export/all.zip", None),
+        ];
+        for (method, path, body) in routes {
+            let sep = if path.contains('?') { '&' } else { '?' };
+            let uri = format!("{}{}token={}", path, sep, token);
+            assert_eq!(send(&state, method, &uri, body).await, StatusCode::FORBIDDEN, "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn tag_search_checks_each_requested_type() {
+        let state = AppState::new();
+        let permissions = StaffPermissions { prescriptions_read: false, ..StaffPermissions::viewer() };
+        let token = staff_token(&state, StaffRole::Viewer, Some(permissions));
+
+        let status = send(&state, Method::GET, &format!("/api/tags/search?tags=a&types=patient&token={}", token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let status = send(&state, Method::GET, &format!("/api/tags/search?tags=a&types=patient,prescription&token={}", token), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let status = send(&state, Method::GET, &format!("/api/tags/search?tags=a&token={}", token), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn full_export_is_admin_only() {
+        let state = AppState::new();
+        let staff = staff_token(&state, StaffRole::Staff, None);
+        let shared = server::insert_test_staff_session(&state, None);
+        for token in [staff, shared] {
+            for path in ["/api/export/all", "/api/export/all.zip"] {
+                let status = send(&state, Method::GET, &format!("{}?token={}", path, token), None).await;
+                assert_eq!(status, StatusCode::FORBIDDEN, "{}", path);
+            }
+        }
+
+        let admin = staff_token(&state, StaffRole::Admin, None);
+        let status = send(&state, Method::GET, &format!("/api/export/all.zip?token={}", admin), None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn patient_delete_requires_delete_permission() {
+        let state = AppState::new();
+        let receptionist = staff_token(&state, StaffRole::Receptionist, None);
+        let staff = staff_token(&state, StaffRole::Staff, None);
+        let patient = Patient::new("삭제 테스트".to_string());
+        db::create_patient(&patient, true).unwrap();
+
+        let uri = |token: &str| format!("/api/patients/{}?token={}", patient.id, token);
+        assert_eq!(send(&state, Method::DELETE, &uri(&receptionist), None).await, StatusCode::FORBIDDEN);
+        assert_eq!(send(&state, Method::DELETE, &uri(&staff), None).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn oversized_or_non_json_bodies_are_rejected() {
+        let state = AppState::new();
+        let token = server::insert_test_staff_session(&state, None);
+        let uri = format!("/api/patients?token={}", token);
+
+        let request = axum::http::Request::builder()
+            .method(Method::POST)
+            .uri(&uri)
+            .header(header::CONTENT_TYPE, "text/plain")
+            .body(Body::from("name=홍길동"))
+            .unwrap();
+        let response = server::create_router(state.clone()).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let oversized = serde_json::json!({"name": "x".repeat(MAX_BODY_BYTES)});
+        assert_eq!(send(&state, Method::POST, &uri, Some(oversized)).await, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn permitted_account_reaches_stats_routes() {
+        let state = AppState::new();
+        let token = staff_token(&state, StaffRole::Viewer, None);
+        for path in ["/api/stats/dashboard", "/api/stats/referrals", "/api/tags/stats", "/api/exports"] {
+            let status = send(&state, Method::GET, &format!("{}?token={}", path, token), None).await;
+            assert_eq!(status, StatusCode::OK, "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn revenue_report_validates_period() {
+        let state = AppState::new();
+        let token = staff_token(&state, StaffRole::Viewer, None);
+        let cases = [
+            ("from=1990-02-01&to=1990-03-31", StatusCode::OK),
+            ("from=1990-03-31&to=1990-02-01", StatusCode::BAD_REQUEST),
+            ("from=1990-02&to=1990-03-31", StatusCode::BAD_REQUEST),
+            ("to=1990-03-31", StatusCode::BAD_REQUEST),
+        ];
+        for (query, expected) in cases {
+            let uri = format!("/api/reports/revenue?{}&token={}", query, token);
+            assert_eq!(send(&state, Method::GET, &uri, None).await, expected, "{}", query);
+        }
+    }
+
+    #[tokio::test]
+    async fn document_integrity_api_reports_signed_state() {
+        let state = AppState::new();
+        let token = staff_token(&state, StaffRole::Admin, None);
+        let patient = Patient::new("서명 테스트".to_string());
+        db::create_patient(&patient, true).unwrap();
+        let prescription: Prescription = serde_json::from_value(serde_json::json!({
+            "id": uuid::Uuid::new_v4().to_string(),
+            "patient_id": patient.id,
+            "formula": "보중익기탕",
+            "merged_herbs": "[]",
+            "final_herbs": "[]",
+            "total_doses": 20.0,
+            "days": 10,
+            "doses_per_day": 2,
+            "total_packs": 20,
+            "total_dosage": 30.0,
+            "final_total_amount": 600.0,
+            "status": "issued",
+            "created_at": chrono::Utc::now().to_rfc3339(),
+            "updated_at": chrono::Utc::now().to_rfc3339(),
+        }))
+        .unwrap();
+        db::create_prescription(&prescription).unwrap();
+
+        // 본문의 서명자는 무시하고 로그인한 계정으로 기록
+        let sign_uri = format!("/api/documents/prescription/{}/sign?token={}", prescription.id, token);
+        let (status, body) = post_json(&state, &sign_uri, serde_json::json!({"signed_by": "원장"})).await;
+        assert_eq!(status, StatusCode::OK);
+        let account_id = server::staff_session_account_id(&state, &token).unwrap();
+        assert_eq!(body["signature"]["signed_by"], serde_json::json!(account_id));
+        let missing_uri = format!("/api/documents/prescription/missing-document/sign?token={}", token);
+        assert_eq!(send(&state, Method::POST, &missing_uri, Some(serde_json::json!({}))).await, StatusCode::NOT_FOUND);
+        let invoice_uri = format!("/api/documents/invoice/{}/sign?token={}", prescription.id, token);
+        assert_eq!(send(&state, Method::POST, &invoice_uri, Some(serde_json::json!({}))).await, StatusCode::BAD_REQUEST);
+
+        assert!(db::verify_document_integrity(&prescription.id).unwrap().valid);
+        let integrity_uri = format!("/api/documents/{}/integrity?token={}", prescription.id, token);
+        assert_eq!(send(&state, Method::GET, &integrity_uri, None).await, StatusCode::OK);
+        assert_eq!(send(&state, Method::GET, &format!("/api/documents/{}/integrity", prescription.id), None).await, StatusCode::UNAUTHORIZED);
+    }
+
+    async fn get_json(state: &AppState, uri: &str) -> serde_json::Value {
+        let request = axum::http::Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let response = server::create_router(state.clone()).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[test]
+    fn pagination_params_are_clamped() {
+        let params = |pairs: &[(&str, &str)]| pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        assert_eq!(pagination(&params(&[])), (DEFAULT_PAGE_SIZE, 0));
+        assert_eq!(pagination(&params(&[("limit", "0"), ("offset", "-5")])), (1, 0));
+        assert_eq!(pagination(&params(&[("limit", "100000"), ("offset", "20")])), (MAX_PAGE_SIZE, 20));
+        assert_eq!(pagination(&params(&[("limit", "abc")])), (DEFAULT_PAGE_SIZE, 0));
+    }
+
+    #[tokio::test]
+    async fn list_apis_share_pagination_shape() {
+        let state = AppState::new();
+        let token = staff_token(&state, StaffRole::Viewer, None);
+        for path in ["/api/patients", "/api/prescriptions", "/api/notifications", "/api/survey-responses"] {
+            let page = get_json(&state, &format!("{}?limit=1&offset=0&token={}", path, token)).await;
+            assert!(page["items"].as_array().is_some_and(|items| items.len() <= 1), "{}", path);
+            let total = page["total"].as_i64().unwrap();
+            assert_eq!(page["limit"], 1);
+            assert_eq!(page["has_more"], total > 1, "{}", path);
+        }
+
+        // 기존 대시보드용 /api/responses 는 { responses } 형식 유지
+        let legacy = get_json(&state, &format!("/api/responses?token={}", token)).await;
+        assert!(legacy["responses"].is_array());
+    }
+
+    #[tokio::test]
+    async fn referral_breakdown_api_filters_by_period() {
+        let state = AppState::new();
+        let token = staff_token(&state, StaffRole::Viewer, None);
+        for date in ["1984-07-01", "1984-07-31", "1984-08-01"] {
+            let mut patient = Patient::new("경로 API".to_string());
+            patient.created_at = chrono::DateTime::parse_from_rfc3339(&format!("{}T09:00:00Z", date)).unwrap().with_timezone(&chrono::Utc);
+            patient.referral_source = Some("현수막".to_string());
+            db::create_patient(&patient, true).unwrap();
+        }
+
+        let counts = get_json(&state, &format!("/api/stats/referrals?from=1984-07-01&to=1984-07-31&token={}", token)).await;
+        let banner = counts.as_array().unwrap().iter().find(|c| c["source"] == "현수막").unwrap();
+        assert_eq!(banner["count"], 2);
+
+        let uri = format!("/api/stats/referrals?from=1984-07&token={}", token);
+        assert_eq!(send(&state, Method::GET, &uri, None).await, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn treatment_outcomes_api_returns_patient_result() {
+        let state = AppState::new();
+        let token = staff_token(&state, StaffRole::Viewer, None);
+        let patient = Patient::new("상관 API".to_string());
+        db::create_patient(&patient, true).unwrap();
+
+        let result = get_json(&state, &format!("/api/patients/{}/treatment-outcomes?token={}", patient.id, token)).await;
+        assert_eq!(result["patient_id"], patient.id.as_str());
+        assert_eq!(result["sample_size"], 0);
+        assert!(result["adherence_score_correlation"].is_null());
+    }
+
+    #[tokio::test]
+    async fn feature_flag_api_is_admin_only_and_changes_gating() {
+        let state = AppState::new();
+        let admin = staff_token(&state, StaffRole::Admin, None);
+        let staff = staff_token(&state, StaffRole::Staff, None);
+        let uri = |token: &str| format!("/api/admin/feature-flags?token={}", token);
+        let premium = serde_json::json!({"plan_type": "premium", "survey_external_enabled": true});
+
+        assert_eq!(send(&state, Method::GET, &uri(&staff), None).await, StatusCode::FORBIDDEN);
+        assert_eq!(send(&state, Method::PUT, &uri(&staff), Some(premium.clone())).await, StatusCode::FORBIDDEN);
+        assert_eq!(
+            send(&state, Method::PUT, &uri(&admin), Some(serde_json::json!({"plan_type": "gold"}))).await,
+            StatusCode::BAD_REQUEST
+        );
+
+        let dashboard_has_online_link = || async {
+            let request = axum::http::Request::builder()
+                .uri(format!("/staff/dashboard?token={}", staff))
+                .body(Body::empty())
+                .unwrap();
+            let response = server::create_router(state.clone()).oneshot(request).await.unwrap();
+            let html = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            String::from_utf8_lossy(&html).contains("showOnlineLinkModal()\" class=\"btn-online\"")
+        };
+        assert!(!dashboard_has_online_link().await);
+
+        assert_eq!(send(&state, Method::PUT, &uri(&admin), Some(premium)).await, StatusCode::OK);
+        let flags = get_json(&state, &uri(&admin)).await;
+        assert_eq!(flags, serde_json::json!({"plan_type": "premium", "survey_external_enabled": true}));
+        assert!(dashboard_has_online_link().await);
+    }
+
+    #[tokio::test]
+    async fn adherence_by_time_of_day_api_requires_medication_read() {
+        let state = AppState::new();
+        let uri = |token: &str| format!("/api/medications/stats/patient/no-such-patient/time-of-day?token={}", token);
+        assert_eq!(send(&state, Method::GET, &uri("invalid"), None).await, StatusCode::UNAUTHORIZED);
+
+        let permissions = StaffPermissions { medications_read: false, ..StaffPermissions::viewer() };
+        let blocked = staff_token(&state, StaffRole::Viewer, Some(permissions));
+        assert_eq!(send(&state, Method::GET, &uri(&blocked), None).await, StatusCode::FORBIDDEN);
+
+        let viewer = staff_token(&state, StaffRole::Viewer, None);
+        assert_eq!(get_json(&state, &uri(&viewer)).await, serde_json::json!([["아침", 0.0], ["점심", 0.0], ["저녁", 0.0]]));
+    }
+
+    #[tokio::test]
+    async fn create_patient_api_returns_conflict_for_duplicates() {
+        let state = AppState::new();
+        let token = staff_token(&state, StaffRole::Staff, None);
+        let uri = format!("/api/patients?token={}", token);
+        let patient = serde_json::json!({
+            "name": format!("등록-{}", uuid::Uuid::new_v4()),
+            "birth_date": "1990-02-02",
+            "phone": "010-3333-4444",
+        });
+
+        assert_eq!(send(&state, Method::POST, "/api/patients", Some(patient.clone())).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(send(&state, Method::POST, &uri, Some(serde_json::json!({"name": "  "}))).await, StatusCode::BAD_REQUEST);
+        assert_eq!(send(&state, Method::POST, &uri, Some(patient.clone())).await, StatusCode::CREATED);
+        // 빠른 더블클릭으로 같은 요청이 다시 와도 한 명만 등록
+        assert_eq!(send(&state, Method::POST, &uri, Some(patient.clone())).await, StatusCode::CONFLICT);
+        let namesake = format!("{}&allow_duplicate=true", uri);
+        assert_eq!(send(&state, Method::POST, &namesake, Some(patient)).await, StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn template_import_rejects_invalid_scale_config() {
+        let state = AppState::new();
+        let token = staff_token(&state, StaffRole::Staff, None);
+        let uri = format!("/api/survey-templates/import?token={}", token);
+        let mut export = serde_json::json!({
+            "format": db::SURVEY_TEMPLATE_EXPORT_FORMAT,
+            "version": 1,
+            "name": "척도 가져오기",
+            "questions": [{
+                "question_type": "scale",
+                "question_text": "통증",
+                "scale_config": {"min": 0, "max": 100, "step": 10},
+            }],
+        });
+        assert_eq!(send(&state, Method::POST, &uri, Some(export.clone())).await, StatusCode::CREATED);
+
+        export["questions"][0]["scale_config"]["step"] = serde_json::json!(0);
+        assert_eq!(send(&state, Method::POST, &uri, Some(export.clone())).await, StatusCode::BAD_REQUEST);
+        export["questions"][0]["scale_config"] = serde_json::json!({"min": 10, "max": 10});
+        assert_eq!(send(&state, Method::POST, &uri, Some(export)).await, StatusCode::BAD_REQUEST);
+    }
+
+    async fn post_json(state: &AppState, uri: &str, body: serde_json::Value) -> (StatusCode, serde_json::Value) {
+        let request = axum::http::Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = server::create_router(state.clone()).oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null))
+    }
+
+    /// 직원 로그인 (병원 설정이 없으면 테스트용 이름으로 저장, 반환: 로그인 응답)
+    async fn staff_login(state: &AppState, username: &str, password: &str) -> serde_json::Value {
+        let clinic_name = match db::get_clinic_settings().unwrap() {
+            Some(settings) => settings.clinic_name,
+            None => {
+                let settings = crate::models::ClinicSettings { clinic_name: "테스트 한의원".to_string(), ..Default::default() };
+                db::save_clinic_settings(&settings).unwrap();
+                settings.clinic_name
+            }
+        };
+        let body = serde_json::json!({"clinic_name": clinic_name, "username": username, "password": password});
+        let (status, login) = post_json(state, "/staff/login", body).await;
+        assert_eq!(status, StatusCode::OK, "{}", login);
+        login
+    }
+
+    #[tokio::test]
+    async fn temporary_password_blocks_other_apis_until_changed() {
+        let state = AppState::new();
+        let admin = staff_token(&state, StaffRole::Admin, None);
+        let username = format!("reset-{}", uuid::Uuid::new_v4());
+        let account = db::create_staff_account(&username, "재설정", "password123", StaffRole::Staff).unwrap();
+        let old_session = server::insert_test_staff_session(&state, Some(&account.id));
+        let patients = |token: &str| format!("/api/patients?token={}", token);
+
+        let reset_uri = |token: &str| format!("/api/staff-accounts/{}/reset-password?token={}", account.id, token);
+        assert_eq!(send(&state, Method::POST, &reset_uri(&old_session), None).await, StatusCode::FORBIDDEN);
+        let (status, reset) = post_json(&state, &reset_uri(&admin), serde_json::json!({})).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(reset["must_change_password"], true);
+        let temporary = reset["temporary_password"].as_str().unwrap().to_string();
+        // 재설정하면 기존 세션은 로그아웃
+        assert_eq!(send(&state, Method::GET, &patients(&old_session), None).await, StatusCode::UNAUTHORIZED);
+
+        let login = staff_login(&state, &username, &temporary).await;
+        assert_eq!(login["must_change_password"], true);
+        let token = login["token"].as_str().unwrap().to_string();
+        let other = staff_login(&state, &username, &temporary).await["token"].as_str().unwrap().to_string();
+        assert_eq!(send(&state, Method::GET, &patients(&token), None).await, StatusCode::FORBIDDEN);
+        assert_eq!(send(&state, Method::GET, &format!("/api/staff-accounts/me?token={}", token), None).await, StatusCode::FORBIDDEN);
+
+        let change_uri = format!("/api/auth/change-password?token={}", token);
+        let change = |current: &str, new: &str| serde_json::json!({"current_password": current, "new_password": new});
+        assert_eq!(post_json(&state, &change_uri, change("wrong-password", "new-password-1")).await.0, StatusCode::BAD_REQUEST);
+        assert_eq!(post_json(&state, &change_uri, change(&temporary, &temporary)).await.0, StatusCode::BAD_REQUEST);
+        assert_eq!(post_json(&state, &change_uri, change(&temporary, "new-password-1")).await.0, StatusCode::OK);
+
+        // 변경한 세션은 계속 쓰고, 같은 계정의 다른 세션은 로그아웃
+        assert_eq!(send(&state, Method::GET, &patients(&token), None).await, StatusCode::OK);
+        assert_eq!(send(&state, Method::GET, &patients(&other), None).await, StatusCode::UNAUTHORIZED);
+        assert!(!db::get_staff_account(&account.id).unwrap().unwrap().must_change_password);
+        assert_eq!(staff_login(&state, &username, "new-password-1").await["must_change_password"], false);
+    }
+
+    #[tokio::test]
+    async fn text_macro_api_limits_global_and_foreign_macros() {
+        let state = AppState::new();
+        let admin = staff_token(&state, StaffRole::Admin, None);
+        let staff = staff_token(&state, StaffRole::Staff, None);
+        let other = staff_token(&state, StaffRole::Staff, None);
+        let abbreviation = format!("w{}", &uuid::Uuid::new_v4().simple().to_string()[..8]);
+        let body = |global: bool| serde_json::json!({"abbreviation": abbreviation, "expansion": "환자", "global": global});
+        let create_uri = |token: &str| format!("/api/text-macros?token={}", token);
+
+        assert_eq!(send(&state, Method::POST, &create_uri(&staff), Some(body(true))).await, StatusCode::FORBIDDEN);
+        let (status, mine) = post_json(&state, &create_uri(&staff), body(false)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(send(&state, Method::POST, &create_uri(&staff), Some(body(false))).await, StatusCode::CONFLICT);
+        let (status, global) = post_json(&state, &create_uri(&admin), body(true)).await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        let item_uri = |item: &serde_json::Value, token: &str| format!("/api/text-macros/{}?token={}", item["id"].as_str().unwrap(), token);
+        let edit = serde_json::json!({"abbreviation": abbreviation, "expansion": "환자분"});
+        assert_eq!(send(&state, Method::PUT, &item_uri(&mine, &other), Some(edit.clone())).await, StatusCode::NOT_FOUND);
+        assert_eq!(send(&state, Method::PUT, &item_uri(&global, &staff), Some(edit.clone())).await, StatusCode::FORBIDDEN);
+        assert_eq!(send(&state, Method::PUT, &item_uri(&mine, &staff), Some(edit)).await, StatusCode::OK);
+
+        let listed = get_json(&state, &create_uri(&other)).await;
+        let ids: Vec<&str> = listed["macros"].as_array().unwrap().iter().filter_map(|m| m["id"].as_str()).collect();
+        assert!(ids.contains(&global["id"].as_str().unwrap()) && !ids.contains(&mine["id"].as_str().unwrap()));
+
+        assert_eq!(send(&state, Method::DELETE, &item_uri(&mine, &other), None).await, StatusCode::NOT_FOUND);
+        assert_eq!(send(&state, Method::DELETE, &item_uri(&mine, &staff), None).await, StatusCode::OK);
+        assert_eq!(send(&state, Method::DELETE, &item_uri(&global, &admin), None).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn patient_detail_api_includes_sorted_alerts() {
+        let state = AppState::new();
+        let token = staff_token(&state, StaffRole::Staff, None);
+        let patient = Patient::new("배너 환자".to_string());
+        db::create_patient(&patient, true).unwrap();
+        let detail_uri = format!("/api/patients/{}?token={}", patient.id, token);
+        assert_eq!(get_json(&state, &detail_uri).await["alerts"], serde_json::json!([]));
+
+        let alerts_uri = |patient_id: &str| format!("/api/patients/{}/alerts?token={}", patient_id, token);
+        let alert = |alert_type: &str, severity: &str, content: &str| {
+            serde_json::json!({"alert_type": alert_type, "severity": severity, "content": content})
+        };
+        let create = |patient_id: &str, body: serde_json::Value| {
+            let uri = alerts_uri(patient_id);
+            let state = state.clone();
+            async move { send(&state, Method::POST, &uri, Some(body)).await }
+        };
+        assert_eq!(create(&patient.id, alert("chronic_disease", "low", "고혈압")).await, StatusCode::CREATED);
+        assert_eq!(create(&patient.id, alert("allergy", "high", "페니실린")).await, StatusCode::CREATED);
+        assert_eq!(create(&patient.id, alert("allergy", "high", " ")).await, StatusCode::BAD_REQUEST);
+        assert_eq!(create(&patient.id, alert("unknown", "high", "x")).await, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(create("missing-patient", alert("allergy", "high", "x")).await, StatusCode::NOT_FOUND);
+
+        let detail = get_json(&state, &detail_uri).await;
+        assert_eq!(detail["name"], "배너 환자");
+        let banner: Vec<_> = detail["alerts"].as_array().unwrap().iter().map(|a| (a["alert_type"].clone(), a["content"].clone())).collect();
+        assert_eq!(banner, [(serde_json::json!("allergy"), serde_json::json!("페니실린")), (serde_json::json!("chronic_disease"), serde_json::json!("고혈압"))]);
+        assert_eq!(get_json(&state, &alerts_uri(&patient.id)).await["alerts"], detail["alerts"]);
+    }
+
+    #[tokio::test]
+    async fn sync_conflict_api_lists_and_resolves() {
+        let state = AppState::new();
+        let token = staff_token(&state, StaffRole::Staff, None);
+        let session_id = format!("conflict-{}", uuid::Uuid::new_v4());
+        for name in ["로컬", "원격"] {
+            db::save_survey_response_from_sync(&session_id, "default_female_health", None, Some(name), &[], "2001-06-01T09:00:00+00:00")
+                .unwrap();
+        }
+
+        let listed = get_json(&state, &format!("/api/sync/conflicts?token={}", token)).await;
+        let conflict = listed["conflicts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|c| c["remote_data"]["session_id"] == session_id.as_str())
+            .cloned()
+            .unwrap();
+        assert_eq!(conflict["local_data"]["respondent_name"], "로컬");
+
+        let resolve_uri = |id: &str| format!("/api/sync/conflicts/{}/resolve?token={}", id, token);
+        let id = conflict["id"].as_str().unwrap();
+        let remote = serde_json::json!({"choice": "remote"});
+        assert_eq!(send(&state, Method::POST, &resolve_uri(id), Some(serde_json::json!({"choice": "both"}))).await, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(send(&state, Method::POST, &resolve_uri("missing"), Some(remote.clone())).await, StatusCode::NOT_FOUND);
+        assert_eq!(send(&state, Method::POST, &resolve_uri(id), Some(remote.clone())).await, StatusCode::OK);
+        assert_eq!(send(&state, Method::POST, &resolve_uri(id), Some(remote)).await, StatusCode::CONFLICT);
+
+        let unresolved = get_json(&state, &format!("/api/sync/conflicts?token={}", token)).await;
+        assert!(!unresolved["conflicts"].as_array().unwrap().iter().any(|c| c["id"] == id));
+        let all = get_json(&state, &format!("/api/sync/conflicts?include_resolved=true&token={}", token)).await;
+        let resolved = all["conflicts"].as_array().unwrap().iter().find(|c| c["id"] == id).cloned().unwrap();
+        assert_eq!(resolved["resolution"], "remote");
+    }
+
+    #[tokio::test]
+    async fn chart_changes_are_audited_under_session_account() {
+        let state = AppState::new();
+        let token = staff_token(&state, StaffRole::Admin, None);
+        let account_id = server::staff_session_account_id(&state, &token).unwrap();
+        let patient = Patient::new("차팅 이력".to_string());
+        db::create_patient(&patient, true).unwrap();
+        let now = chrono::Utc::now();
+        let record = ChartRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            patient_id: patient.id.clone(),
+            visit_date: now,
+            chief_complaint: Some("두통".to_string()),
+            symptoms: None,
+            diagnosis: None,
+            treatment: None,
+            prescription_id: None,
+            notes: None,
+            created_at: now,
+            updated_at: now,
+        };
+        db::create_chart_record(&record, db::MacroExpansion::Off).unwrap();
+
+        let uri = format!("/api/charts/{}?token={}&expand_macros=false", record.id, token);
+        let edited = ChartRecord { diagnosis: Some("긴장성 두통".to_string()), ..record.clone() };
+        assert_eq!(send(&state, Method::PUT, &uri, Some(serde_json::to_value(&edited).unwrap())).await, StatusCode::OK);
+        assert_eq!(send(&state, Method::DELETE, &uri, None).await, StatusCode::OK);
+
+        assert_eq!(
+            db::audit_log_actors("chart_record", &record.id),
+            [("update".to_string(), Some(account_id.clone())), ("delete".to_string(), Some(account_id))]
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_export_aborts_the_response_body() {
+        use tokio::io::AsyncWriteExt;
+
+        let stream = |fail: bool| {
+            let (mut writer, reader) = tokio::io::duplex(64);
+            let export = tokio::spawn(async move {
+                writer.write_all(b"{\"patient\":{}}\n").await?;
+                if fail {
+                    return Err(AppError::Custom("DB 오류".to_string()));
+                }
+                writer.shutdown().await?;
+                Ok(())
+            });
+            export_stream_body(reader, export, RequestId("test".to_string()))
+        };
+
+        let complete = axum::body::to_bytes(stream(false), usize::MAX).await.unwrap();
+        assert_eq!(&complete[..], b"{\"patient\":{}}\n");
+        assert!(axum::body::to_bytes(stream(true), usize::MAX).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn every_error_path_returns_code_and_message() {
+        let state = AppState::new();
+        let viewer = staff_token(&state, StaffRole::Viewer, Some(StaffPermissions::default()));
+        let admin = staff_token(&state, StaffRole::Admin, None);
+
+        let error_body = |content_type: &'static str, body: &'static str, uri: String| {
+            let state = state.clone();
+            async move {
+                let request = axum::http::Request::builder()
+                    .method(Method::POST)
+                    .uri(uri)
+                    .header(header::CONTENT_TYPE, content_type)
+                    .body(Body::from(body))
+                    .unwrap();
+                let response = server::create_router(state).oneshot(request).await.unwrap();
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&bytes).unwrap())
+            }
+        };
+        let assert_error = |(status, body): (StatusCode, serde_json::Value), expected: StatusCode, code: &str| {
+            assert_eq!(status, expected);
+            assert_eq!(body["code"], serde_json::json!(code), "{}", body);
+            assert!(body["message"].as_str().is_some_and(|m| !m.is_empty()), "{}", body);
+            assert!(body.get("error").is_none());
+        };
+
+        let (status, body) = post_json(&state, "/api/auth/refresh", serde_json::json!({})).await;
+        assert_error((status, body), StatusCode::UNAUTHORIZED, "ERR_AUTH_REQUIRED");
+        let (status, body) = post_json(&state, &format!("/api/patients?token={}", viewer), serde_json::json!({"name": "권한 없음"})).await;
+        assert_error((status, body), StatusCode::FORBIDDEN, "ERR_FORBIDDEN");
+        let (status, body) = post_json(&state, &format!("/api/documents/prescription/missing/sign?token={}", admin), serde_json::json!({})).await;
+        assert_error((status, body), StatusCode::NOT_FOUND, "ERR_NOT_FOUND");
+
+        // 핸들러 밖에서 거부되는 요청도 같은 형식
+        let sign_uri = format!("/api/documents/prescription/missing/sign?token={}", admin);
+        assert_error(error_body("application/json", "{", sign_uri.clone()).await, StatusCode::BAD_REQUEST, "ERR_VALIDATION");
+        assert_error(error_body("text/plain", "x", sign_uri).await, StatusCode::UNSUPPORTED_MEDIA_TYPE, "ERR_UNSUPPORTED_MEDIA_TYPE");
+        assert_error(error_body("application/json", "{}", "/api/no-such-route".to_string()).await, StatusCode::NOT_FOUND, "ERR_NOT_FOUND");
+    }
+}
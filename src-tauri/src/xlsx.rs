@@ -0,0 +1,121 @@
+//! 엑셀(XLSX) 생성 모듈
+//!
+//! 외부 라이브러리 없이 시트 하나짜리 통합 문서를 만듭니다. 모든 셀은 문자열(inlineStr)로 기록하므로
+//! 차트번호나 전화번호의 앞자리 0이 사라지지 않습니다.
+
+use std::io::Write;
+
+use crate::error::{AppError, AppResult};
+
+/// 셀 하나에 넣을 수 있는 최대 글자 수 (엑셀 제한)
+const MAX_CELL_CHARS: usize = 32_767;
+
+/// 시트 이름 최대 길이 (엑셀 제한)
+const MAX_SHEET_NAME_CHARS: usize = 31;
+
+const CONTENT_TYPES_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/></Types>"#;
+
+const ROOT_RELS_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+
+const WORKBOOK_RELS_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/></Relationships>"#;
+
+/// 표 한 장을 XLSX 파일로 생성 (`rows`의 첫 줄이 머리글이면 첫 줄 고정)
+pub fn write_sheet(sheet_name: &str, rows: &[Vec<String>]) -> AppResult<Vec<u8>> {
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let zip_err = |e: zip::result::ZipError| AppError::Custom(format!("엑셀 파일 생성 실패: {}", e));
+
+    let files = [
+        ("[Content_Types].xml", CONTENT_TYPES_XML.to_string()),
+        ("_rels/.rels", ROOT_RELS_XML.to_string()),
+        ("xl/workbook.xml", workbook_xml(sheet_name)),
+        ("xl/_rels/workbook.xml.rels", WORKBOOK_RELS_XML.to_string()),
+        ("xl/worksheets/sheet1.xml", sheet_xml(rows)),
+    ];
+    for (name, content) in files {
+        zip.start_file(name, options).map_err(zip_err)?;
+        zip.write_all(content.as_bytes())?;
+    }
+
+    let cursor = zip.finish().map_err(zip_err)?;
+    Ok(cursor.into_inner())
+}
+
+fn workbook_xml(sheet_name: &str) -> String {
+    // 시트 이름에 쓸 수 없는 문자는 공백으로
+    let name: String = sheet_name
+        .chars()
+        .map(|c| if matches!(c, '[' | ']' | ':' | '*' | '?' | '/' | '\\') { ' ' } else { c })
+        .take(MAX_SHEET_NAME_CHARS)
+        .collect();
+    let name = if name.trim().is_empty() { "Sheet1".to_string() } else { name };
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="{}" sheetId="1" r:id="rId1"/></sheets></workbook>"#,
+        escape_xml(&name)
+    )
+}
+
+fn sheet_xml(rows: &[Vec<String>]) -> String {
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main">"#,
+    );
+    if rows.len() > 1 {
+        xml.push_str(r#"<sheetViews><sheetView workbookViewId="0"><pane ySplit="1" topLeftCell="A2" activePane="bottomLeft" state="frozen"/></sheetView></sheetViews>"#);
+    }
+    xml.push_str("<sheetData>");
+    for (r, row) in rows.iter().enumerate() {
+        xml.push_str(&format!(r#"<row r="{}">"#, r + 1));
+        for (c, value) in row.iter().enumerate() {
+            if value.is_empty() {
+                continue;
+            }
+            let text: String = value.chars().take(MAX_CELL_CHARS).collect();
+            xml.push_str(&format!(
+                r#"<c r="{}{}" t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+                column_name(c),
+                r + 1,
+                escape_xml(&text)
+            ));
+        }
+        xml.push_str("</row>");
+    }
+    xml.push_str("</sheetData></worksheet>");
+    xml
+}
+
+/// 열 번호(0부터) → 엑셀 열 이름 (A, B, ..., Z, AA, AB, ...)
+fn column_name(mut index: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'A' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+/// XML 특수문자 변환 (XML에 쓸 수 없는 제어 문자는 제거)
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
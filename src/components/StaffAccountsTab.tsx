@@ -14,6 +14,8 @@ interface ServerStatus {
   port: number | null;
   local_ip: string | null;
   url: string | null;
+  mdns_hostname?: string | null;  // mDNS 광고 호스트 (예: gosibang.local)
+  mdns_url?: string | null;
 }
 
 export function StaffAccountsTab() {
@@ -23,6 +25,7 @@ export function StaffAccountsTab() {
   const [serverStatus, setServerStatus] = useState<ServerStatus>({ running: false, port: null, local_ip: null, url: null });
   const [serverPort, setServerPort] = useState(8787);
   const [serverAutostart, setServerAutostart] = useState(false);
+  const [mdnsAdvertise, setMdnsAdvertise] = useState(true);
   const [isStartingServer, setIsStartingServer] = useState(false);
   const [isStoppingServer, setIsStoppingServer] = useState(false);
 
@@ -55,6 +58,7 @@ export function StaffAccountsTab() {
     try {
       const autostart = await invoke<boolean>('get_server_autostart');
       setServerAutostart(autostart);
+      setMdnsAdvertise(await invoke<boolean>('get_server_mdns_advertise'));
     } catch (error) {
       console.error('자동시작 설정 로드 실패:', error);
     }
@@ -123,6 +127,18 @@ export function StaffAccountsTab() {
     }
   };
 
+  // mDNS 광고 설정 변경
+  const handleMdnsAdvertiseChange = async (enabled: boolean) => {
+    try {
+      await invoke('set_server_mdns_advertise', { enabled });
+      setMdnsAdvertise(enabled);
+      setMessage({ type: 'success', text: '다음 서버 시작부터 적용됩니다.' });
+    } catch (error) {
+      console.error('mDNS 설정 실패:', error);
+      setMessage({ type: 'error', text: `설정 실패: ${error}` });
+    }
+  };
+
   // 비밀번호 저장
   const handleSavePassword = async () => {
     if (!newPassword) {
@@ -268,6 +284,20 @@ export function StaffAccountsTab() {
           </label>
         </div>
 
+        {/* mDNS 광고 (한 줄) */}
+        <div className="flex items-center justify-between py-2 border-t border-gray-100">
+          <span className="text-sm text-gray-700">gosibang.local 주소로 접속 허용 (mDNS)</span>
+          <label className="relative inline-flex items-center cursor-pointer">
+            <input
+              type="checkbox"
+              checked={mdnsAdvertise}
+              onChange={(e) => handleMdnsAdvertiseChange(e.target.checked)}
+              className="sr-only peer"
+            />
+            <div className="w-9 h-5 bg-gray-200 peer-focus:outline-none rounded-full peer peer-checked:after:translate-x-full peer-checked:after:border-white after:content-[''] after:absolute after:top-[2px] after:start-[2px] after:bg-white after:border-gray-300 after:border after:rounded-full after:h-4 after:w-4 after:transition-all peer-checked:bg-primary-600"></div>
+          </label>
+        </div>
+
         {/* 접속 주소 (서버 실행 중일 때만) */}
         {serverStatus.running && (
           <div className="mt-4 pt-4 border-t border-gray-200">
@@ -293,6 +323,14 @@ export function StaffAccountsTab() {
                 </button>
               </div>
             </div>
+            {serverStatus.mdns_url && (
+              <p className="text-xs text-gray-500 mt-3">
+                태블릿에서 <code className="bg-gray-100 px-1 rounded">{serverStatus.mdns_url}</code> 접속
+                <button onClick={() => copyToClipboard(serverStatus.mdns_url!)} className="ml-1 p-1 hover:bg-gray-100 rounded align-middle">
+                  <Copy className="w-3 h-3 text-gray-500" />
+                </button>
+              </p>
+            )}
           </div>
         )}
       </div>
@@ -29,6 +29,12 @@ export function QuestionRenderer({ question, answer, onChange }: QuestionRendere
         />
       )}
 
+      {question.question_type === 'file_upload' && (
+        <p className="p-4 border rounded-lg text-gray-500 bg-gray-50">
+          파일 첨부 문항은 링크/태블릿 설문 화면에서 올릴 수 있습니다.
+        </p>
+      )}
+
       {question.question_type === 'single_choice' && (
         <div className="grid grid-cols-2 gap-2">
           {question.options?.map((option, index) => (
@@ -96,8 +102,8 @@ export function QuestionRenderer({ question, answer, onChange }: QuestionRendere
           </div>
           <div className="flex justify-between gap-2">
             {Array.from(
-              { length: question.scale_config.max - question.scale_config.min + 1 },
-              (_, i) => question.scale_config!.min + i
+              { length: Math.floor((question.scale_config.max - question.scale_config.min) / (question.scale_config.step || 1)) + 1 },
+              (_, i) => question.scale_config!.min + i * (question.scale_config!.step || 1)
             ).map((value) => (
               <button
                 key={value}
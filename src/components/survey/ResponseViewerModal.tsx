@@ -5,7 +5,12 @@
 
 import { useState } from 'react';
 import { X, ChevronUp, ChevronDown, Copy, Check } from 'lucide-react';
-import type { SurveyResponse, SurveyTemplate, SurveyAnswer } from '../../types';
+import type { SurveyResponse, SurveyTemplate, SurveyAnswer, SurveyAttachmentAnswer } from '../../types';
+
+// 파일 첨부 문항 답변 여부
+function isAttachmentAnswer(value: unknown): value is SurveyAttachmentAnswer {
+  return typeof value === 'object' && value !== null && 'attachment_id' in value;
+}
 
 interface ResponseViewerModalProps {
   response: SurveyResponse;
@@ -36,6 +41,9 @@ export function ResponseViewerModal({ response, template, onClose }: ResponseVie
 
   const formatAnswer = (answer: SurveyAnswer | undefined): string => {
     if (!answer) return '(답변 없음)';
+    if (isAttachmentAnswer(answer.answer)) {
+      return `[첨부] ${answer.answer.file_name}`;
+    }
     if (Array.isArray(answer.answer)) {
       return answer.answer.join(', ') || '(선택 없음)';
     }
@@ -50,6 +58,7 @@ export function ResponseViewerModal({ response, template, onClose }: ResponseVie
     const getAnswer = (qId: string): string => {
       const answer = response.answers.find(a => a.question_id === qId);
       if (!answer) return '';
+      if (isAttachmentAnswer(answer.answer)) return `[첨부] ${answer.answer.file_name}`;
       if (Array.isArray(answer.answer)) return answer.answer.join(' / ');
       return String(answer.answer || '');
     };
@@ -213,6 +222,7 @@ export function ResponseViewerModal({ response, template, onClose }: ResponseVie
                         {question.question_type === 'multiple_choice' && '복수 선택'}
                         {question.question_type === 'text' && '주관식'}
                         {question.question_type === 'scale' && '척도'}
+                        {question.question_type === 'file_upload' && '파일 첨부'}
                       </div>
                       <div className="text-gray-900">
                         {question.question_type === 'scale' && answer?.answer ? (
@@ -13,7 +13,9 @@ const WEB_API_BASE_KEY = 'gosibang_web_api_base';
 interface ApiResponse<T> {
   success: boolean;
   data?: T;
-  error?: string;
+  // 오류 응답: { code: 'ERR_...', message: '...' }
+  code?: string;
+  message?: string;
 }
 
 // ============ 세션 관리 ============
@@ -65,7 +67,7 @@ async function apiRequest<T>(
   const result: ApiResponse<T> = await response.json();
 
   if (!result.success) {
-    throw new Error(result.error || '요청 실패');
+    throw new Error(result.message || '요청 실패');
   }
 
   return result.data as T;
@@ -109,7 +111,7 @@ export async function webLogin(username: string, password: string): Promise<Logi
   const result: ApiResponse<LoginResult> = await response.json();
 
   if (!result.success || !result.data) {
-    throw new Error(result.error || '로그인 실패');
+    throw new Error(result.message || '로그인 실패');
   }
 
   // 토큰 및 세션 정보 저장
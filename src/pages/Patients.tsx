@@ -1,4 +1,4 @@
-import { useEffect, useState } from 'react';
+import { useEffect, useRef, useState } from 'react';
 import { useNavigate } from 'react-router-dom';
 import { invoke } from '@tauri-apps/api/core';
 import { Search, Plus, Edit2, Trash2, X, FileText, ClipboardList, Printer, Loader2, MessageSquare, AlertCircle, Eye, ArrowLeft, ExternalLink } from 'lucide-react';
@@ -134,7 +134,14 @@ export function Patients() {
     if (editingPatient) {
       await updatePatient(patient);
     } else {
-      await createPatient(patient);
+      try {
+        await createPatient(patient);
+      } catch (error) {
+        // 이름/생년월일/연락처가 모두 같은 환자가 이미 있으면 동명이인 등록 여부 확인
+        if (!String(error).includes('이미 등록된 환자')) throw error;
+        if (!confirm(`${patient.name} 환자가 이미 등록되어 있습니다.\n다른 사람(동명이인)으로 등록하시겠습니까?`)) return;
+        await createPatient(patient, true);
+      }
       refreshUsage(); // 사용량 갱신
     }
     setIsModalOpen(false);
@@ -391,9 +398,13 @@ function PatientModal({ patient, onSave, onClose }: PatientModalProps) {
     notes: patient?.notes || '',
   });
   const [isSubmitting, setIsSubmitting] = useState(false);
+  // 상태 반영 전에 연달아 눌린 저장(더블 클릭, Enter 반복)을 막기 위한 즉시 잠금
+  const submittingRef = useRef(false);
 
   const handleSubmit = async (e: React.FormEvent) => {
     e.preventDefault();
+    if (submittingRef.current) return;
+    submittingRef.current = true;
     setIsSubmitting(true);
 
     const now = new Date().toISOString();
@@ -410,8 +421,12 @@ function PatientModal({ patient, onSave, onClose }: PatientModalProps) {
       updated_at: now,
     };
 
-    await onSave(patientData);
-    setIsSubmitting(false);
+    try {
+      await onSave(patientData);
+    } finally {
+      submittingRef.current = false;
+      setIsSubmitting(false);
+    }
   };
 
   return (
@@ -16,7 +16,7 @@ import type { SurveyResponse, SurveyTemplate, SurveyAnswer, Patient, SurveyQuest
 const SURVEY_APP_URL = 'https://gosibang-survey.vercel.app';
 
 export function SurveyResponses() {
-  const { responses, templates, sessions, isLoading, loadResponses, loadTemplates, loadSessions, getTemplate, linkResponseToPatient, deleteResponse, createTemplate, updateTemplate, deleteTemplate, deleteSession } = useSurveyStore();
+  const { responses, templates, sessions, isLoading, loadResponses, loadTemplates, loadSessions, getTemplate, linkResponseToPatient, deleteResponse, createTemplate, updateTemplate, deleteTemplate, duplicateTemplate, deleteSession } = useSurveyStore();
   const { authState } = useAuthStore();
   const { canUseFeature } = usePlanLimits();
 
@@ -101,9 +101,9 @@ export function SurveyResponses() {
     }
   };
 
-  const handleSaveTemplate = async (data: { name: string; description?: string; display_mode: SurveyDisplayMode; questions: SurveyQuestion[] }) => {
+  const handleSaveTemplate = async (data: { name: string; description?: string; display_mode: SurveyDisplayMode; require_contact: boolean; anonymous: boolean; redirect_url?: string; category?: string; is_active?: boolean; questions: SurveyQuestion[] }) => {
     if (editingTemplate) {
-      await updateTemplate(editingTemplate.id, { ...data, is_active: editingTemplate.is_active });
+      await updateTemplate(editingTemplate.id, { ...data, is_active: data.is_active ?? editingTemplate.is_active });
     } else {
       await createTemplate(data);
     }
@@ -111,24 +111,18 @@ export function SurveyResponses() {
   };
 
   const handleDuplicateTemplate = async (template: SurveyTemplate) => {
-    const newQuestions = template.questions.map(q => ({ ...q, id: generateQuestionId() }));
-    await createTemplate({
-      name: `${template.name} (복사본)`,
-      description: template.description,
-      display_mode: template.display_mode,
-      questions: newQuestions,
-    });
+    await duplicateTemplate(template.id);
   };
 
   // 미연결 응답 수
-  const unlinkedCount = responses.filter(r => !r.patient_id).length;
+  const unlinkedCount = responses.filter(r => !r.patient_id && !r.anonymous).length;
 
   const filteredResponses = responses.filter((response) => {
     const matchesSearch = !searchTerm ||
       response.patient_name?.toLowerCase().includes(searchTerm.toLowerCase()) ||
       response.template_name?.toLowerCase().includes(searchTerm.toLowerCase());
     const matchesTemplate = !selectedTemplateId || response.template_id === selectedTemplateId;
-    const matchesUnlinked = !showUnlinkedOnly || !response.patient_id;
+    const matchesUnlinked = !showUnlinkedOnly || (!response.patient_id && !response.anonymous);
     return matchesSearch && matchesTemplate && matchesUnlinked;
   });
 
@@ -382,11 +376,13 @@ export function SurveyResponses() {
                         <td className="px-3 sm:px-4 py-3">
                           <div className="flex items-center gap-2 flex-wrap">
                             <span className="truncate max-w-[120px] sm:max-w-none">
-                              {response.patient_id
+                              {response.anonymous
+                                ? '익명'
+                                : response.patient_id
                                 ? `${response.patient_name || '-'}${response.chart_number ? ` (${response.chart_number})` : ''}`
                                 : response.respondent_name || '-'}
                             </span>
-                            {!response.patient_id && (
+                            {!response.patient_id && !response.anonymous && (
                               <span className="px-1.5 py-0.5 text-xs bg-orange-100 text-orange-700 rounded flex-shrink-0">
                                 미연결
                               </span>
@@ -411,7 +407,7 @@ export function SurveyResponses() {
                             >
                               보기
                             </button>
-                            {!response.patient_id && (
+                            {!response.patient_id && !response.anonymous && (
                               <button
                                 onClick={() => setLinkingResponse(response)}
                                 className="px-2 py-1 text-xs text-orange-600 hover:bg-orange-50 rounded transition-colors"
@@ -1076,7 +1072,7 @@ function PatientLinkModal({ response, onLink, onClose }: PatientLinkModalProps)
 
 interface TemplateEditorModalProps {
   template: SurveyTemplate | null;
-  onSave: (data: { name: string; description?: string; display_mode: SurveyDisplayMode; questions: SurveyQuestion[] }) => Promise<void>;
+  onSave: (data: { name: string; description?: string; display_mode: SurveyDisplayMode; require_contact: boolean; anonymous: boolean; redirect_url?: string; category?: string; is_active?: boolean; questions: SurveyQuestion[] }) => Promise<void>;
   onClose: () => void;
 }
 
@@ -1084,6 +1080,11 @@ function TemplateEditorModal({ template, onSave, onClose }: TemplateEditorModalP
   const [name, setName] = useState(template?.name || '');
   const [description, setDescription] = useState(template?.description || '');
   const [displayMode, setDisplayMode] = useState<SurveyDisplayMode>(template?.display_mode || 'one_by_one');
+  const [requireContact, setRequireContact] = useState(template?.require_contact ?? false);
+  const [anonymous, setAnonymous] = useState(template?.anonymous ?? false);
+  const [redirectUrl, setRedirectUrl] = useState(template?.redirect_url || '');
+  const [category, setCategory] = useState(template?.category || '');
+  const [isActive, setIsActive] = useState(template?.is_active ?? true);
   const [questions, setQuestions] = useState<SurveyQuestion[]>(
     template?.questions || []
   );
@@ -1144,10 +1145,14 @@ function TemplateEditorModal({ template, onSave, onClose }: TemplateEditorModalP
       alert('모든 질문의 내용을 입력해주세요.');
       return;
     }
+    if (redirectUrl.trim() && !/^https?:\/\/\S+$/i.test(redirectUrl.trim())) {
+      alert('제출 후 이동 주소는 http:// 또는 https://로 시작해야 합니다.');
+      return;
+    }
 
     setSaving(true);
     try {
-      await onSave({ name, description, display_mode: displayMode, questions });
+      await onSave({ name, description, display_mode: displayMode, require_contact: requireContact, anonymous, redirect_url: redirectUrl.trim() || undefined, category: category.trim() || undefined, is_active: isActive, questions });
     } finally {
       setSaving(false);
     }
@@ -1326,6 +1331,85 @@ function TemplateEditorModal({ template, onSave, onClose }: TemplateEditorModalP
               </p>
             </div>
 
+            <div>
+              <label className="flex items-center gap-2 cursor-pointer">
+                <input
+                  type="checkbox"
+                  checked={requireContact}
+                  disabled={anonymous}
+                  onChange={(e) => setRequireContact(e.target.checked)}
+                  className="text-primary-600"
+                />
+                <span className="text-sm font-medium text-gray-700">제출 시 연락처 필수</span>
+              </label>
+              <p className="text-xs text-gray-500 mt-1">
+                응답자 이름과 휴대폰 번호 또는 생년월일을 입력해야 제출할 수 있습니다.
+              </p>
+            </div>
+
+            <div>
+              <label className="flex items-center gap-2 cursor-pointer">
+                <input
+                  type="checkbox"
+                  checked={anonymous}
+                  onChange={(e) => {
+                    setAnonymous(e.target.checked);
+                    if (e.target.checked) setRequireContact(false);
+                  }}
+                  className="text-primary-600"
+                />
+                <span className="text-sm font-medium text-gray-700">익명 설문</span>
+              </label>
+              <p className="text-xs text-gray-500 mt-1">
+                응답자 이름을 받지 않으며 응답을 환자와 연결할 수 없습니다. (만족도 조사 등)
+              </p>
+            </div>
+
+            {template && (
+              <div>
+                <label className="flex items-center gap-2">
+                  <input
+                    type="checkbox"
+                    checked={isActive}
+                    onChange={(e) => setIsActive(e.target.checked)}
+                    className="text-primary-600"
+                  />
+                  <span className="text-sm font-medium text-gray-700">활성</span>
+                </label>
+                <p className="text-xs text-gray-500 mt-1">
+                  비활성 템플릿은 키오스크와 설문 링크 생성 목록에 나오지 않습니다. (복사한 템플릿은 비활성으로 만들어집니다)
+                </p>
+              </div>
+            )}
+
+            <div>
+              <label className="block text-sm font-medium text-gray-700 mb-1">분류</label>
+              <input
+                type="text"
+                value={category}
+                onChange={(e) => setCategory(e.target.value)}
+                className="input-field"
+                placeholder="예: 초진, 통증 평가, 치료 후 경과"
+              />
+              <p className="text-xs text-gray-500 mt-1">
+                같은 분류의 템플릿은 직원 화면의 템플릿 선택 목록에서 함께 묶여 표시됩니다.
+              </p>
+            </div>
+
+            <div>
+              <label className="block text-sm font-medium text-gray-700 mb-1">제출 후 이동 주소</label>
+              <input
+                type="url"
+                value={redirectUrl}
+                onChange={(e) => setRedirectUrl(e.target.value)}
+                className="input-field"
+                placeholder="https://example.com/thanks"
+              />
+              <p className="text-xs text-gray-500 mt-1">
+                온라인 설문 제출 후 이 주소로 이동합니다. 비워 두면 완료 화면을 표시합니다. (키오스크 제외)
+              </p>
+            </div>
+
             <div className="border-t pt-4">
               <div className="flex items-center justify-between mb-3">
                 <h3 className="font-medium text-gray-900">질문 목록</h3>
@@ -1413,6 +1497,7 @@ function QuestionEditor({ question, index, totalCount, onUpdate, onDelete, onMov
     { value: 'multiple_choice', label: '복수 선택' },
     { value: 'text', label: '주관식' },
     { value: 'scale', label: '척도' },
+    { value: 'file_upload', label: '파일 첨부' },
   ];
 
   const handleTypeChange = (type: QuestionType) => {
@@ -1575,7 +1660,7 @@ function QuestionEditor({ question, index, totalCount, onUpdate, onDelete, onMov
                     onChange={(e) => handleScaleConfigChange({ max: parseInt(e.target.value) || 5 })}
                     className="input-field w-20"
                     min={question.scale_config.min + 1}
-                    max={10}
+                    max={100}
                   />
                   <input
                     type="text"
@@ -1586,6 +1671,16 @@ function QuestionEditor({ question, index, totalCount, onUpdate, onDelete, onMov
                   />
                 </div>
               </div>
+              <div>
+                <label className="text-sm text-gray-600">간격</label>
+                <input
+                  type="number"
+                  value={question.scale_config.step ?? 1}
+                  onChange={(e) => handleScaleConfigChange({ step: Math.max(1, parseInt(e.target.value) || 1) })}
+                  className="input-field w-20"
+                  min={1}
+                />
+              </div>
             </div>
           )}
         </div>
@@ -30,7 +30,7 @@ export function SurveyTemplates() {
     }
   };
 
-  const handleSave = async (data: { name: string; description?: string; display_mode: SurveyDisplayMode; questions: SurveyQuestion[] }) => {
+  const handleSave = async (data: { name: string; description?: string; display_mode: SurveyDisplayMode; require_contact: boolean; anonymous: boolean; questions: SurveyQuestion[] }) => {
     if (editingTemplate) {
       await updateTemplate(editingTemplate.id, { ...data, is_active: editingTemplate.is_active });
     } else {
@@ -45,6 +45,8 @@ export function SurveyTemplates() {
       name: `${template.name} (복사본)`,
       description: template.description,
       display_mode: template.display_mode,
+      require_contact: template.require_contact,
+      anonymous: template.anonymous,
       questions: newQuestions,
     });
   };
@@ -136,7 +138,7 @@ export function SurveyTemplates() {
 
 interface TemplateEditorModalProps {
   template: SurveyTemplate | null;
-  onSave: (data: { name: string; description?: string; display_mode: SurveyDisplayMode; questions: SurveyQuestion[] }) => Promise<void>;
+  onSave: (data: { name: string; description?: string; display_mode: SurveyDisplayMode; require_contact: boolean; anonymous: boolean; questions: SurveyQuestion[] }) => Promise<void>;
   onClose: () => void;
 }
 
@@ -144,6 +146,8 @@ function TemplateEditorModal({ template, onSave, onClose }: TemplateEditorModalP
   const [name, setName] = useState(template?.name || '');
   const [description, setDescription] = useState(template?.description || '');
   const [displayMode, setDisplayMode] = useState<SurveyDisplayMode>(template?.display_mode || 'one_by_one');
+  const [requireContact, setRequireContact] = useState(template?.require_contact ?? false);
+  const [anonymous, setAnonymous] = useState(template?.anonymous ?? false);
   const [questions, setQuestions] = useState<SurveyQuestion[]>(
     template?.questions || []
   );
@@ -207,7 +211,7 @@ function TemplateEditorModal({ template, onSave, onClose }: TemplateEditorModalP
 
     setSaving(true);
     try {
-      await onSave({ name, description, display_mode: displayMode, questions });
+      await onSave({ name, description, display_mode: displayMode, require_contact: requireContact, anonymous, questions });
     } finally {
       setSaving(false);
     }
@@ -390,6 +394,40 @@ function TemplateEditorModal({ template, onSave, onClose }: TemplateEditorModalP
               </p>
             </div>
 
+            <div>
+              <label className="flex items-center gap-2 cursor-pointer">
+                <input
+                  type="checkbox"
+                  checked={requireContact}
+                  disabled={anonymous}
+                  onChange={(e) => setRequireContact(e.target.checked)}
+                  className="text-primary-600"
+                />
+                <span className="text-sm font-medium text-gray-700">제출 시 연락처 필수</span>
+              </label>
+              <p className="text-xs text-gray-500 mt-1">
+                응답자 이름과 휴대폰 번호 또는 생년월일을 입력해야 제출할 수 있습니다.
+              </p>
+            </div>
+
+            <div>
+              <label className="flex items-center gap-2 cursor-pointer">
+                <input
+                  type="checkbox"
+                  checked={anonymous}
+                  onChange={(e) => {
+                    setAnonymous(e.target.checked);
+                    if (e.target.checked) setRequireContact(false);
+                  }}
+                  className="text-primary-600"
+                />
+                <span className="text-sm font-medium text-gray-700">익명 설문</span>
+              </label>
+              <p className="text-xs text-gray-500 mt-1">
+                응답자 이름을 받지 않으며 응답을 환자와 연결할 수 없습니다. (만족도 조사 등)
+              </p>
+            </div>
+
             <div className="border-t pt-4">
               <div className="flex items-center justify-between mb-3">
                 <h3 className="font-medium text-gray-900">질문 목록</h3>
@@ -477,6 +515,7 @@ function QuestionEditor({ question, index, totalCount, onUpdate, onDelete, onMov
     { value: 'multiple_choice', label: '복수 선택' },
     { value: 'text', label: '주관식' },
     { value: 'scale', label: '척도' },
+    { value: 'file_upload', label: '파일 첨부' },
   ];
 
   const handleTypeChange = (type: QuestionType) => {
@@ -13,7 +13,7 @@ interface PatientStore {
   // Actions
   loadPatients: (search?: string) => Promise<void>;
   selectPatient: (patient: Patient | null) => void;
-  createPatient: (patient: Omit<Patient, 'id' | 'created_at' | 'updated_at'>) => Promise<void>;
+  createPatient: (patient: Omit<Patient, 'id' | 'created_at' | 'updated_at'>, allowDuplicate?: boolean) => Promise<void>;
   updatePatient: (patient: Patient) => Promise<void>;
   deletePatient: (id: string) => Promise<void>;
   loadPrescriptions: (patientId: string) => Promise<void>;
@@ -48,7 +48,7 @@ export const usePatientStore = create<PatientStore>((set, get) => ({
     }
   },
 
-  createPatient: async (patient) => {
+  createPatient: async (patient, allowDuplicate = false) => {
     set({ isLoading: true, error: null });
     try {
       const id = crypto.randomUUID();
@@ -67,7 +67,7 @@ export const usePatientStore = create<PatientStore>((set, get) => ({
         updated_at: now,
       };
 
-      await invoke('create_patient', { patient: newPatient });
+      await invoke('create_patient', { patient: newPatient, allowDuplicate });
       await get().loadPatients();
       set({ isLoading: false });
     } catch (error) {
@@ -21,6 +21,7 @@ interface TauriSurveySessionWithPatient {
   chart_number: string | null;
   patient_age: string | null;
   patient_gender: string | null;
+  template_name: string | null;
 }
 
 // Tauri에서 반환하는 세션 구조 (create/get)
@@ -47,6 +48,10 @@ interface TauriSurveyTemplate {
   questions: SurveyQuestion[];
   display_mode: string | null;
   is_active: boolean;
+  require_contact?: boolean;
+  anonymous?: boolean;
+  redirect_url?: string | null;
+  category?: string | null;
 }
 
 // Tauri에서 반환하는 응답 구조 (clinic.db)
@@ -61,6 +66,7 @@ interface TauriSurveyResponse {
   template_name: string | null;
   patient_name: string | null;
   chart_number: string | null;
+  anonymous?: boolean;
 }
 
 interface SurveyStore {
@@ -74,9 +80,10 @@ interface SurveyStore {
   // 템플릿 관련
   loadTemplates: () => Promise<void>;
   getTemplate: (id: string) => SurveyTemplate | null;
-  createTemplate: (data: { name: string; description?: string; questions: SurveyQuestion[]; display_mode?: SurveyDisplayMode }) => Promise<SurveyTemplate>;
-  updateTemplate: (id: string, data: { name: string; description?: string; questions: SurveyQuestion[]; display_mode?: SurveyDisplayMode; is_active?: boolean }) => Promise<void>;
-  deleteTemplate: (id: string) => Promise<void>;
+  createTemplate: (data: { name: string; description?: string; questions: SurveyQuestion[]; display_mode?: SurveyDisplayMode; require_contact?: boolean; anonymous?: boolean; redirect_url?: string; category?: string }) => Promise<SurveyTemplate>;
+  updateTemplate: (id: string, data: { name: string; description?: string; questions: SurveyQuestion[]; display_mode?: SurveyDisplayMode; require_contact?: boolean; anonymous?: boolean; redirect_url?: string; category?: string; is_active?: boolean }) => Promise<void>;
+  deleteTemplate: (id: string, force?: boolean) => Promise<void>;
+  duplicateTemplate: (id: string) => Promise<void>;
 
   // 세션 관련
   loadSessions: (filters?: { patient_id?: string; status?: string }) => Promise<void>;
@@ -120,6 +127,10 @@ export const useSurveyStore = create<SurveyStore>((set, get) => ({
         questions: t.questions,
         display_mode: (t.display_mode || 'one_by_one') as SurveyDisplayMode,
         is_active: t.is_active,
+        require_contact: t.require_contact ?? false,
+        anonymous: t.anonymous ?? false,
+        redirect_url: t.redirect_url || undefined,
+        category: t.category || undefined,
         created_at: new Date().toISOString(), // Rust DB에 없으면 현재 시간
         updated_at: new Date().toISOString(),
       }));
@@ -146,6 +157,10 @@ export const useSurveyStore = create<SurveyStore>((set, get) => ({
       questions: data.questions,
       display_mode: data.display_mode || 'one_by_one',
       is_active: true,
+      require_contact: data.require_contact ?? false,
+      anonymous: data.anonymous ?? false,
+      redirect_url: data.redirect_url || null,
+      category: data.category || null,
     };
 
     const newId = await invoke<string>('save_survey_template', { template: templateInput });
@@ -157,6 +172,10 @@ export const useSurveyStore = create<SurveyStore>((set, get) => ({
       questions: data.questions,
       display_mode: data.display_mode || 'one_by_one',
       is_active: true,
+      require_contact: data.require_contact ?? false,
+      anonymous: data.anonymous ?? false,
+      redirect_url: data.redirect_url,
+      category: data.category,
       created_at: new Date().toISOString(),
       updated_at: new Date().toISOString(),
     };
@@ -174,6 +193,10 @@ export const useSurveyStore = create<SurveyStore>((set, get) => ({
       questions: data.questions,
       display_mode: data.display_mode || 'one_by_one',
       is_active: data.is_active ?? true,
+      require_contact: data.require_contact ?? false,
+      anonymous: data.anonymous ?? false,
+      redirect_url: data.redirect_url || null,
+      category: data.category || null,
     };
 
     await invoke<string>('save_survey_template', { template: templateInput });
@@ -182,13 +205,19 @@ export const useSurveyStore = create<SurveyStore>((set, get) => ({
     await get().loadTemplates();
   },
 
-  deleteTemplate: async (id) => {
-    await invoke('delete_survey_template', { id });
+  deleteTemplate: async (id, force) => {
+    await invoke('delete_survey_template', { id, force });
 
     // 템플릿 목록 다시 로드
     await get().loadTemplates();
   },
 
+  duplicateTemplate: async (id) => {
+    // 복사본은 비활성 상태로 생성됨 (편집 후 활성화)
+    await invoke('duplicate_survey_template', { id });
+    await get().loadTemplates();
+  },
+
   // ===== 세션 관련 =====
 
   loadSessions: async (filters) => {
@@ -432,6 +461,7 @@ export const useSurveyStore = create<SurveyStore>((set, get) => ({
         template_name: r.template_name || '알 수 없는 템플릿',
         patient_name: r.patient_name || undefined,
         chart_number: r.chart_number || undefined,
+        anonymous: r.anonymous ?? false,
       }));
 
       // 클라이언트 사이드 필터링
@@ -485,6 +515,7 @@ export const useSurveyStore = create<SurveyStore>((set, get) => ({
         template_name: r.template_name || '알 수 없는 템플릿',
         patient_name: r.patient_name || undefined,
         chart_number: r.chart_number || undefined,
+        anonymous: r.anonymous ?? false,
       }));
 
     return responses;
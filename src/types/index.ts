@@ -6,6 +6,7 @@ export interface ClinicSettings {
   clinic_phone?: string;
   doctor_name?: string;
   license_number?: string;
+  clinic_logo?: string;  // base64 PNG (설문 화면 상단 표시, 최대 200KB)
   created_at: string;
   updated_at: string;
 }
@@ -20,6 +21,24 @@ export interface Patient {
   phone?: string;
   address?: string;
   notes?: string;
+  referral_source?: string;  // 내원 경로
+  tags?: string[];           // 태그 (저장 시 생략하면 기존 태그 유지)
+  visit_count?: number;      // 내원 횟수 (목록을 include_stats로 조회했을 때만)
+  last_visit_date?: string | null;  // 마지막 내원일 (목록을 include_stats로 조회했을 때만)
+  created_at: string;
+  updated_at: string;
+}
+
+// 환자 중요 정보 (차트 상단 배너)
+export type PatientAlertType = 'allergy' | 'caution_medication' | 'chronic_disease' | 'other';
+export type PatientAlertSeverity = 'high' | 'medium' | 'low';
+
+export interface PatientAlert {
+  id: string;
+  patient_id: string;
+  alert_type: PatientAlertType;
+  severity: PatientAlertSeverity;
+  content: string;
   created_at: string;
   updated_at: string;
 }
@@ -79,6 +98,9 @@ export interface FinalHerb {
   herb_id: number;
   name: string;
   amount: number;
+  batch_number?: string;  // 입고 로트(배치) 번호 - 회수 시 추적용
+  supplier?: string;      // 공급처
+  notes?: string;
 }
 
 // ===== 약재 재고 타입 =====
@@ -135,6 +157,8 @@ export interface Prescription {
   total_dosage: number;
   final_total_amount: number;
   notes?: string;
+  price?: number;
+  insurance_type?: 'insured' | 'uninsured';
   status: 'draft' | 'issued' | 'completed';
   issued_at?: string;
   chief_complaint?: string;
@@ -194,6 +218,16 @@ export interface ChartRecord {
   updated_at: string;
 }
 
+// 차팅 입력용 약어 (예: "ㅎㅇ" → "환자")
+export interface TextMacro {
+  id: string;
+  abbreviation: string;
+  expansion: string;
+  owner_id?: string;  // 직원 계정 ID (없으면 전역)
+  created_at: string;
+  updated_at: string;
+}
+
 // 경과 엔트리 (UI용 - haniwon 스타일)
 export interface ProgressEntry {
   id: string;
@@ -207,7 +241,14 @@ export interface ProgressEntry {
 }
 
 // 설문 질문 유형
-export type QuestionType = 'text' | 'single_choice' | 'multiple_choice' | 'scale';
+export type QuestionType = 'text' | 'single_choice' | 'multiple_choice' | 'scale' | 'file_upload';
+
+// 파일 첨부 문항 답변 (링크/태블릿 설문에서 업로드한 파일 참조)
+export interface SurveyAttachmentAnswer {
+  attachment_id: string;
+  file_name: string;
+}
+
 
 // 척도 설정
 export interface ScaleConfig {
@@ -215,6 +256,7 @@ export interface ScaleConfig {
   max: number;
   minLabel?: string;
   maxLabel?: string;
+  step?: number;  // 선택 간격 (기본 1)
 }
 
 // 설문 질문
@@ -226,6 +268,15 @@ export interface SurveyQuestion {
   scale_config?: ScaleConfig;
   required: boolean;
   order: number;
+  shuffle_options?: boolean;  // 선택지 표시 순서 섞기 (응답 값은 원래 선택지)
+  fixed_position?: boolean;   // 템플릿 문항 순서 섞기에서 제외 (제자리 유지)
+  translations?: Record<string, QuestionTranslation>;  // 언어 코드(en, zh, vi)별 번역
+}
+
+// 질문 번역 (options는 원문과 같은 순서)
+export interface QuestionTranslation {
+  question_text: string;
+  options?: string[];
 }
 
 // 설문 표시 모드
@@ -239,10 +290,24 @@ export interface SurveyTemplate {
   questions: SurveyQuestion[];
   display_mode: SurveyDisplayMode;
   is_active: boolean;
+  require_contact?: boolean;
+  anonymous?: boolean;
+  redirect_url?: string;     // 제출 완료 후 이동할 주소 (http/https)
+  category?: string;         // 분류 (초진, 통증 평가, 치료 후 경과 등)
+  randomize_questions?: boolean;     // 세션마다 문항 순서 섞기
+  randomize_options?: boolean;       // 세션마다 선택지 순서 섞기
+  pin_required_questions?: boolean;  // 문항을 섞을 때 필수 문항은 제자리 유지
   created_at: string;
   updated_at: string;
 }
 
+// 세션별 표시 순서 (세션 생성 시 시드로 정해짐)
+export interface SurveyDisplayOrder {
+  seed: number;
+  question_ids: string[];
+  option_orders: Record<string, number[]>;  // 문항 ID별 원래 선택지 인덱스 순서
+}
+
 // 설문 세션 (링크용)
 export interface SurveySession {
   id: string;
@@ -250,7 +315,7 @@ export interface SurveySession {
   patient_id?: string;
   template_id: string;
   respondent_name?: string;
-  status: 'pending' | 'completed' | 'expired';
+  status: 'pending' | 'completed' | 'expired' | 'locked';
   expires_at: string;
   completed_at?: string;
   created_by?: string;
@@ -261,6 +326,7 @@ export interface SurveySession {
   patient_age?: string;
   patient_gender?: string;
   template_name?: string;
+  display_order?: SurveyDisplayOrder;
 }
 
 // 설문 응답 답변
@@ -276,12 +342,15 @@ export interface SurveyResponse {
   patient_id?: string;
   template_id: string;
   respondent_name?: string;
+  respondent_phone?: string;
+  respondent_birth_date?: string;
   answers: SurveyAnswer[];
   submitted_at: string;
   // 조인 데이터
   patient_name?: string;
   chart_number?: string;
   template_name?: string;
+  anonymous?: boolean;       // 익명 설문 응답 (환자 연결 불가)
 }
 
 // 복약 상태
@@ -298,6 +367,7 @@ export interface MedicationSchedule {
   medication_times: string[];
   notes?: string;
   created_at: string;
+  is_completed?: boolean;
 }
 
 // 복약 기록 (MedicationLog)
@@ -567,6 +637,7 @@ export interface Question {
 export interface StaffPermissions {
   patients_read: boolean;     // 환자 조회
   patients_write: boolean;    // 환자 수정
+  patients_delete: boolean;   // 환자 삭제
   prescriptions_read: boolean;   // 처방 조회
   prescriptions_write: boolean;  // 처방 수정
   charts_read: boolean;       // 차트 조회
@@ -579,7 +650,7 @@ export interface StaffPermissions {
 }
 
 // 기본 권한 프리셋
-export type StaffRole = 'admin' | 'staff' | 'viewer';
+export type StaffRole = 'admin' | 'staff' | 'receptionist' | 'viewer';
 
 // 내부 직원 계정
 export interface StaffAccount {
@@ -592,12 +663,13 @@ export interface StaffAccount {
   last_login_at?: string;
   created_at: string;
   updated_at: string;
+  must_change_password?: boolean;  // 임시 비밀번호로 재설정됨
 }
 
 // ===== 알림 타입 =====
 
 // 알림 유형
-export type NotificationType = 'medication_reminder' | 'missed_medication' | 'daily_summary';
+export type NotificationType = 'medication_reminder' | 'missed_medication' | 'daily_summary' | 'survey_locked' | 'survey_session_abuse' | 'unlinked_responses';
 
 // 알림 우선순위
 export type NotificationPriority = 'low' | 'normal' | 'high' | 'critical';
@@ -616,6 +688,7 @@ export interface Notification {
   action_url?: string;
   created_at: string;
   read_at?: string;
+  snoozed_until?: string;      // 이 시각까지 안 읽은 알림에서 숨김
 }
 
 // 알림 설정
@@ -632,6 +705,216 @@ export interface NotificationSettings {
   sound_preset: string;
   do_not_disturb_start?: string;  // HH:mm
   do_not_disturb_end?: string;    // HH:mm
+  snooze_minutes_options?: number[];  // 허용 스누즈 시간(분), 기본 [5, 10, 30, 60]
+  scheduler_interval_secs?: number;   // 정리/알림 작업 주기(초), 기본 600
+  channel?: NotificationChannel;      // 복약 알림 발송 채널, 기본 desktop
   created_at: string;
   updated_at: string;
 }
+
+// 정리/알림 작업 1회 실행 결과
+export interface SweepSummary {
+  expired_sessions: number;
+  follow_ups_created: number;
+  unlinked_notified: number;
+  attachments_purged: number;
+  notifications_resurfaced: number;
+  errors: string[];
+}
+
+// SMTP 연결 보안 방식 (tls: 465번, start_tls: 587번, none: 원내 릴레이)
+export type SmtpSecurity = 'tls' | 'start_tls' | 'none';
+
+// 이메일 발송 설정
+export interface SmtpSettings {
+  host: string;
+  port: number;
+  security: SmtpSecurity;
+  username: string;
+  password: string;              // 조회 시 빈 문자열, 저장 시 비워 두면 기존 값 유지
+  from_address: string;
+  summary_recipient?: string;    // 일일 요약 받는 주소
+}
+
+// 복약 알림 발송 채널
+export type NotificationChannel = 'desktop' | 'sms' | 'both';
+
+// 문자 발송 업체 (http_gateway: 직접 운영하는 JSON 게이트웨이, aligo: 알리고)
+export type SmsProvider = 'http_gateway' | 'aligo';
+
+// 문자 발송 설정
+export interface SmsSettings {
+  provider: SmsProvider;
+  gateway_url: string;           // http_gateway에서만 사용
+  api_key: string;               // 조회 시 빈 문자열, 저장 시 비워 두면 기존 값 유지
+  user_id: string;               // 알리고 아이디
+  sender: string;                // 사전 등록된 발신 번호
+}
+
+// 원내 서버 HTTPS 설정 (서버 재시작 후 적용)
+export interface ServerTlsSettings {
+  enabled: boolean;              // 인증서 경로가 없으면 자체 서명 인증서 사용
+  cert_path?: string;            // 인증서 (PEM)
+  key_path?: string;             // 개인키 (PEM)
+}
+
+// 백업 복원 시 이미 있는 기록 처리 방식
+export type ConflictStrategy = 'skip' | 'overwrite' | 'error' | 'replace';
+
+// 백업 복원 결과
+export interface ImportSummary {
+  patients_imported: number;
+  prescriptions_imported: number;
+  charts_imported: number;
+  errors: string[];
+  conflicts: string[];           // 이미 있던 기록과 처리 결과
+}
+
+// 목록 API 공통 페이지네이션 응답
+export interface PaginatedResponse<T> {
+  items: T[];
+  total: number;
+  limit: number;
+  offset: number;
+  has_more: boolean;
+}
+
+// 후속 설문 규칙 (원본 설문 완료 시 후속 설문 자동 생성)
+export interface SurveyFollowUpRule {
+  id: string;
+  source_template_id: string;
+  follow_up_template_id: string;
+  trigger_type: 'completed' | 'score';
+  question_id?: string;
+  min_score?: number;
+  max_score?: number;
+  delay_hours: number;
+  is_active: boolean;
+  created_at: string;
+}
+
+// 태그 대상 항목 종류
+export type TagEntityType = 'patient' | 'prescription' | 'chart_record' | 'initial_chart' | 'progress_note';
+
+// 태그 교차 검색 결과
+export interface TaggedEntity {
+  entity_type: TagEntityType;
+  entity_id: string;
+  tags: string[];
+}
+
+// 태그별 사용 통계
+export interface TagStat {
+  tag: string;
+  total: number;
+  by_type: Partial<Record<TagEntityType, number>>;
+}
+
+// 내원 경로별 신규 환자 수 (source가 없으면 미입력)
+export interface ReferralCount {
+  source?: string | null;
+  count: number;
+}
+
+// 환자 타임라인 이벤트 (방문/처방/설문/연락/문서)
+export type PatientEventType = 'visit' | 'prescription' | 'survey' | 'contact' | 'document';
+
+export interface PatientEvent {
+  event_type: PatientEventType;
+  source: string;
+  source_id: string;
+  occurred_at: string;
+  title: string;
+  detail?: string | null;
+}
+
+// 설문 템플릿 응답 통계
+export interface OptionCount {
+  option: string;
+  count: number;
+}
+
+export interface QuestionStats {
+  question_id: string;
+  question_text: string;
+  question_type: QuestionType;
+  answer_count: number;
+  mean: number | null;
+  median: number | null;
+  option_counts: OptionCount[];
+}
+
+export interface TemplateResponseStats {
+  template_id: string;
+  template_name: string;
+  response_count: number;
+  questions: QuestionStats[];
+}
+
+// 환자 설문 반복 응답 추이
+export interface SeriesQuestion {
+  question_id: string;
+  question_text: string;
+  min: number | null;
+  max: number | null;
+}
+
+export interface SurveySeriesPoint {
+  response_id: string;
+  submitted_at: string;
+  values: Record<string, number>;
+}
+
+export interface PatientSurveySeries {
+  patient_id: string;
+  template_id: string;
+  template_name: string;
+  questions: SeriesQuestion[];
+  points: SurveySeriesPoint[];
+}
+
+// 처방 전후 설문 점수 변화와 복약 순응도
+export interface TreatmentOutcome {
+  prescription_id: string;
+  prescription_name: string | null;
+  prescribed_at: string;
+  template_id: string | null;
+  before_response_id: string | null;
+  after_response_id: string | null;
+  score_before: number | null;  // 척도 범위 대비 0~100
+  score_after: number | null;
+  score_change: number | null;
+  adherence_rate: number | null;  // %
+  medication_logs: number;
+}
+
+export interface TreatmentOutcomeCorrelation {
+  patient_id: string;
+  items: TreatmentOutcome[];
+  adherence_score_correlation: number | null;
+  sample_size: number;
+}
+
+// 백그라운드 내보내기 작업
+export type ExportJobStatus = 'queued' | 'running' | 'completed' | 'failed' | 'cancelled';
+
+export interface SurveyExportRequest {
+  format: 'pdf' | 'xlsx' | 'csv';
+  template_id?: string;
+  from?: string;  // YYYY-MM-DD (제출일 기준)
+  to?: string;
+}
+
+export interface ExportJob {
+  id: string;
+  kind: string;
+  status: ExportJobStatus;
+  processed: number;
+  total: number;
+  cancel_requested: boolean;  // 실행 중 취소 요청 (멈추면 cancelled)
+  file_name: string | null;
+  error: string | null;
+  created_at: string;
+  started_at: string | null;
+  finished_at: string | null;
+}